@@ -0,0 +1,7 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Use the bundled protoc so no system protobuf install is needed
+    std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+    tonic_prost_build::compile_protos("proto/rdf_extractor.proto")?;
+    println!("cargo:rerun-if-changed=proto");
+    Ok(())
+}
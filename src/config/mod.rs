@@ -1,8 +1,10 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
-use anyhow::{Result, Context};
+use std::path::{Path, PathBuf};
+use crate::error::{Error, ErrorContext, Result};
+
+mod ontology;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Configuration {
@@ -10,6 +12,9 @@ pub struct Configuration {
     pub description: String,
     pub version: String,
     pub extraction_questions: Vec<ExtractionQuestion>,
+    /// Values for `{{name}}` placeholders in extraction questions
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub variables: HashMap<String, String>,
     pub rdf_schema: RdfSchema,
     pub output_format: OutputFormat,
     pub llm_settings: LlmSettings,
@@ -17,6 +22,40 @@ pub struct Configuration {
     pub validation_rules: Vec<String>,
     #[serde(default)]
     pub post_processing: PostProcessing,
+    /// Rewrites of the predicates the model chose to canonical ones, tried
+    /// in order; the first matching mapping applies
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub predicate_mappings: Vec<PredicateMapping>,
+    #[serde(default)]
+    pub literal_normalization: LiteralNormalization,
+    #[serde(default, skip_serializing_if = "EntityLinkingSettings::is_disabled")]
+    pub entity_linking: EntityLinkingSettings,
+    #[serde(default)]
+    pub budget: BudgetSettings,
+    #[serde(default)]
+    pub ocr: OcrSettings,
+    /// Handlers for further file extensions (e.g. "docx") or URL schemes
+    /// (e.g. "s3://"), overriding the built-in ones
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub document_handlers: HashMap<String, DocumentHandlerSettings>,
+    #[serde(default)]
+    pub server: ServerSettings,
+    #[serde(default, skip_serializing_if = "ApprovalSettings::is_disabled")]
+    pub approval: ApprovalSettings,
+    #[serde(default, skip_serializing_if = "CoreferenceSettings::is_disabled")]
+    pub coreference: CoreferenceSettings,
+    /// Prompting for documents detected to be in a language, keyed by its
+    /// ISO 639-3 code ("deu") or English name ("German")
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub language_prompts: HashMap<String, LanguagePrompt>,
+    /// Seconds one document may take, loading included, before it is
+    /// recorded as timed out and the run moves on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub document_timeout: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crawl: Option<CrawlProfile>,
+    #[serde(default, skip_serializing_if = "HtmlSettings::is_empty")]
+    pub html: HtmlSettings,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -42,6 +81,51 @@ pub struct RdfSchema {
     pub classes: HashMap<String, String>,
     #[serde(default)]
     pub custom_vocabularies: HashMap<String, String>,
+    /// Direct superclasses of each class, e.g. `Manager: [Person]`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub subclass_of: HashMap<String, Vec<String>>,
+    /// Reasoning axioms for predicates, keyed like `predicates`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub predicate_axioms: HashMap<String, PredicateAxioms>,
+    /// OWL/RDFS ontology (Turtle) whose classes, properties and axioms are
+    /// added to the ones above; relative paths are resolved against the
+    /// configuration file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ontology_file: Option<PathBuf>,
+}
+
+/// RDFS/OWL-lite declarations for a predicate; class and predicate names
+/// are resolved against the schema namespace unless they are full URIs
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct PredicateAxioms {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sub_property_of: Vec<String>,
+    /// Class of every subject of the predicate
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+    /// Class of every URI object of the predicate
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub range: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inverse_of: Option<String>,
+    #[serde(default)]
+    pub transitive: bool,
+}
+
+impl Default for RdfSchema {
+    fn default() -> Self {
+        Self {
+            namespace: "http://example.org/".to_string(),
+            prefix: "ex".to_string(),
+            base_uri: "http://example.org/resource/".to_string(),
+            predicates: HashMap::new(),
+            classes: HashMap::new(),
+            custom_vocabularies: HashMap::new(),
+            subclass_of: HashMap::new(),
+            predicate_axioms: HashMap::new(),
+            ontology_file: None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -52,6 +136,32 @@ pub enum OutputFormat {
     NTriples,
     RdfXml,
     Json,
+    /// N-Triples with the named graph as fourth term
+    NQuads,
+    /// Turtle with named graph blocks
+    TriG,
+    /// Neo4j Cypher statements (property graph)
+    Cypher,
+    /// GraphML for Gephi and other graph visualization tools
+    #[serde(rename = "graphml")]
+    GraphMl,
+}
+
+impl OutputFormat {
+    /// Media type of the serialization
+    pub fn media_type(&self) -> &'static str {
+        match self {
+            Self::Turtle => "text/turtle",
+            Self::JsonLd => "application/ld+json",
+            Self::NTriples => "application/n-triples",
+            Self::RdfXml => "application/rdf+xml",
+            Self::Json => "application/json",
+            Self::NQuads => "application/n-quads",
+            Self::TriG => "application/trig",
+            Self::Cypher => "text/plain",
+            Self::GraphMl => "application/graphml+xml",
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -66,6 +176,109 @@ pub struct LlmSettings {
     pub max_tokens: u32,
     #[serde(default = "default_timeout")]
     pub timeout: u64,
+    /// Query the server for model capabilities and tune settings on startup
+    #[serde(default = "default_true")]
+    pub auto_configure: bool,
+    /// Model context window in tokens, used when the server does not report one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_window: Option<u32>,
+    /// Average characters per token for the model's tokenizer (defaults to 4)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chars_per_token: Option<f32>,
+    /// Hard cap on document characters per prompt; overrides token budgeting
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_document_chars: Option<usize>,
+    /// How structured output is requested (detected if unset)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub structured_output: Option<StructuredOutputMode>,
+    #[serde(default)]
+    pub cache: CacheSettings,
+    /// Sampling overrides for extraction calls
+    #[serde(default, skip_serializing_if = "GenerationOptions::is_empty")]
+    pub extraction: GenerationOptions,
+    /// Sampling overrides for LLM-populated templates
+    #[serde(default, skip_serializing_if = "GenerationOptions::is_empty")]
+    pub generation: GenerationOptions,
+    /// Sampling overrides for template enhancement
+    #[serde(default, skip_serializing_if = "GenerationOptions::is_empty")]
+    pub enhancement: GenerationOptions,
+}
+
+impl LlmSettings {
+    pub fn phase_options(&self, phase: LlmPhase) -> GenerationOptions {
+        match phase {
+            LlmPhase::Extraction => self.extraction,
+            LlmPhase::Generation => self.generation,
+            LlmPhase::Enhancement => self.enhancement,
+        }
+    }
+}
+
+/// What an LLM call is for, so each kind can be sampled differently
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LlmPhase {
+    Extraction,
+    Generation,
+    Enhancement,
+}
+
+/// Sampling parameters for one call; unset values fall back to the
+/// client's settings
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
+pub struct GenerationOptions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+}
+
+impl GenerationOptions {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// These options, with unset values taken from `fallback`
+    pub fn or(self, fallback: GenerationOptions) -> Self {
+        Self {
+            temperature: self.temperature.or(fallback.temperature),
+            top_p: self.top_p.or(fallback.top_p),
+            max_tokens: self.max_tokens.or(fallback.max_tokens),
+        }
+    }
+}
+
+/// On-disk cache of LLM responses
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheSettings {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default = "default_cache_directory")]
+    pub directory: String,
+    /// Entries older than this are ignored; unset means never expire
+    #[serde(default = "default_cache_ttl", skip_serializing_if = "Option::is_none")]
+    pub ttl_seconds: Option<u64>,
+}
+
+impl Default for CacheSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            directory: default_cache_directory(),
+            ttl_seconds: default_cache_ttl(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum StructuredOutputMode {
+    /// Ask for JSON in the prompt and parse the reply
+    #[default]
+    Prompt,
+    /// Use the server's guided decoding (`response_format` with a JSON Schema)
+    GuidedJson,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -74,45 +287,520 @@ pub struct PostProcessing {
     pub deduplicate: bool,
     #[serde(default = "default_true")]
     pub normalize_uris: bool,
+    /// Drop triples whose model-reported confidence is below this value
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_confidence: Option<f32>,
+}
+
+/// Rewrite of an extracted predicate, e.g. `employedBy` to `worksFor`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PredicateMapping {
+    /// Predicate to rewrite: a local name such as `employedBy`, matched
+    /// against the last segment of the predicate IRI, or a full IRI
+    pub from: String,
+    /// Canonical predicate: a local name in the schema namespace, a
+    /// prefixed name or an IRI; with `regex`, `$1` etc. insert capture groups
+    pub to: String,
+    /// Treat `from` as a regular expression that must match the whole name
+    #[serde(default)]
+    pub regex: bool,
+    #[serde(default)]
+    pub ignore_case: bool,
+}
+
+/// Normalizers applied to literal objects as triples are stored, so the same
+/// value written differently in different documents matches
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct LiteralNormalization {
+    /// Trim and collapse runs of whitespace
+    #[serde(default)]
+    pub whitespace: bool,
+    /// Unicode NFC, so composed and decomposed accents compare equal
+    #[serde(default)]
+    pub unicode_nfc: bool,
+    /// Dates to ISO 8601 (`2024-03-05`), typed `xsd:date` or `xsd:dateTime`
+    #[serde(default)]
+    pub dates: bool,
+    /// Read `05/03/2024` as 5 March rather than May 3
+    #[serde(default)]
+    pub day_first: bool,
+    /// Phone numbers to E.164 (`+14155550100`)
+    #[serde(default)]
+    pub phone_numbers: bool,
+    /// Predicates whose values are phone numbers; `schema:telephone`,
+    /// `schema:faxNumber`, `foaf:phone` and `vcard:tel` when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub phone_predicates: Vec<String>,
+    /// Country calling code of national phone numbers, e.g. "1" or "44"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_country_code: Option<String>,
+    /// Amounts to `<amount> <ISO 4217 code>` (`1200.50 USD`)
+    #[serde(default)]
+    pub currencies: bool,
+    /// Predicates (IRIs or prefixed names) to normalize; all when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub predicates: Vec<String>,
+}
+
+impl LiteralNormalization {
+    pub fn is_enabled(&self) -> bool {
+        self.whitespace || self.unicode_nfc || self.dates || self.phone_numbers || self.currencies
+    }
+}
+
+/// Linking of newly extracted entities to entities already in the knowledge
+/// graph, by the embedding similarity of their labels, before new URIs are
+/// stored
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EntityLinkingSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Model served by the OpenAI-compatible `/v1/embeddings` endpoint
+    pub model: String,
+    /// Embeddings server; the LLM server when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    /// API key for `base_url`; the LLM API key when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    /// Cosine similarity from which a new entity takes the existing URI
+    #[serde(default = "default_link_threshold")]
+    pub threshold: f32,
+    /// Cosine similarity from which a new entity below `threshold` keeps
+    /// its URI and is stated `owl:sameAs` the existing one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub same_as_threshold: Option<f32>,
+}
+
+impl Default for EntityLinkingSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            model: String::new(),
+            base_url: None,
+            api_key: None,
+            threshold: default_link_threshold(),
+            same_as_threshold: None,
+        }
+    }
+}
+
+impl EntityLinkingSettings {
+    pub fn is_disabled(&self) -> bool {
+        !self.enabled && self.model.is_empty()
+    }
+}
+
+/// Per-run token and cost limits for LLM calls
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct BudgetSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_total_tokens: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_cost: Option<f64>,
+    /// Price per 1000 prompt tokens
+    #[serde(default)]
+    pub prompt_cost_per_1k: f64,
+    /// Price per 1000 completion tokens
+    #[serde(default)]
+    pub completion_cost_per_1k: f64,
+    #[serde(default)]
+    pub on_exceeded: BudgetAction,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetAction {
+    /// Stop processing further documents
+    #[default]
+    Stop,
+    /// Ask for confirmation before continuing past the budget
+    Confirm,
+}
+
+/// OCR for images and scanned PDFs, using the tesseract CLI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OcrSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    /// tesseract executable
+    #[serde(default = "default_ocr_command")]
+    pub command: String,
+    /// tesseract language codes, e.g. "eng+deu"
+    #[serde(default = "default_ocr_language")]
+    pub language: String,
+    /// Executable used to rasterize PDF pages before OCR
+    #[serde(default = "default_pdf_rasterizer")]
+    pub pdf_rasterizer: String,
+    #[serde(default = "default_ocr_dpi")]
+    pub dpi: u32,
+    /// PDFs whose extracted text is shorter than this are OCRed instead
+    #[serde(default = "default_ocr_min_pdf_chars")]
+    pub min_pdf_chars: usize,
+}
+
+impl Default for OcrSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            command: default_ocr_command(),
+            language: default_ocr_language(),
+            pdf_rasterizer: default_pdf_rasterizer(),
+            dpi: default_ocr_dpi(),
+            min_pdf_chars: default_ocr_min_pdf_chars(),
+        }
+    }
+}
+
+/// CSS selectors choosing the parts of web pages that are extracted
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct HtmlSelectors {
+    /// Only the text inside elements matching one of these, e.g. `article`;
+    /// the whole body when empty or when nothing matches
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    /// Elements skipped with their content, e.g. `nav` or `.cookie-banner`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+}
+
+/// Selectors for fetched web pages, with per-site overrides
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct HtmlSettings {
+    #[serde(flatten)]
+    pub selectors: HtmlSelectors,
+    /// Selectors for the pages of a host and its subdomains, replacing the
+    /// ones above
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub sites: HashMap<String, HtmlSelectors>,
+}
+
+impl HtmlSettings {
+    pub fn is_empty(&self) -> bool {
+        self.selectors.include.is_empty() && self.selectors.exclude.is_empty() && self.sites.is_empty()
+    }
+}
+
+/// Limits for large URL batches: the URLs given to `extract` and the pages
+/// found with `--crawl-depth`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrawlProfile {
+    /// Pages to crawl per seed URL, unless `--crawl-max-pages` is given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_pages: Option<usize>,
+    /// Minimum pause between two requests to the same host, in milliseconds
+    #[serde(default = "default_per_host_delay_ms")]
+    pub per_host_delay_ms: u64,
+    #[serde(default)]
+    pub retry: RetryPolicy,
+    /// Job manifest tracking the batch; when it exists, `extract` continues
+    /// it instead of starting a new job
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resume_file: Option<PathBuf>,
+}
+
+impl Default for CrawlProfile {
+    fn default() -> Self {
+        Self {
+            max_pages: None,
+            per_host_delay_ms: default_per_host_delay_ms(),
+            retry: RetryPolicy::default(),
+            resume_file: None,
+        }
+    }
+}
+
+/// Retries of page requests failing with a connection error, a timeout,
+/// HTTP 429 or a 5xx status
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    /// Requests per page, the first one included
+    #[serde(default = "default_retry_attempts")]
+    pub attempts: u32,
+    /// Wait before the first retry, doubled for each further one; a
+    /// `Retry-After` header takes precedence
+    #[serde(default = "default_retry_backoff_ms")]
+    pub backoff_ms: u64,
+    /// Longest wait between two attempts
+    #[serde(default = "default_retry_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+}
+
+impl RetryPolicy {
+    /// A single attempt per request
+    pub fn none() -> Self {
+        Self { attempts: 1, ..Self::default() }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: default_retry_attempts(),
+            backoff_ms: default_retry_backoff_ms(),
+            max_backoff_ms: default_retry_max_backoff_ms(),
+        }
+    }
+}
+
+/// Built-in handlers a `document_handlers` entry can refer to
+pub const BUILTIN_DOCUMENT_HANDLERS: &[&str] = &["pdf", "text", "email", "spreadsheet", "presentation", "url", "ocr"];
+
+/// Handler for a file extension or URL scheme: a built-in handler or an
+/// external program printing the document's text to stdout
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentHandlerSettings {
+    /// One of `BUILTIN_DOCUMENT_HANDLERS`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub builtin: Option<String>,
+    /// Program and arguments; `{source}` is replaced with the document's path
+    /// or URL, which is appended when no argument contains it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub command: Vec<String>,
+}
+
+impl DocumentHandlerSettings {
+    fn validate(&self, key: &str) -> Result<()> {
+        match (&self.builtin, self.command.is_empty()) {
+            (Some(name), true) if BUILTIN_DOCUMENT_HANDLERS.contains(&name.as_str()) => Ok(()),
+            (Some(name), true) => Err(Error::config(format!(
+                "Unknown built-in document handler '{}' for '{}'; expected one of: {}",
+                name,
+                key,
+                BUILTIN_DOCUMENT_HANDLERS.join(", ")
+            ))),
+            (None, false) => Ok(()),
+            _ => Err(Error::config(format!("Document handler '{}' needs exactly one of `builtin` or `command`", key))),
+        }
+    }
+}
+
+/// Review step for generated documents: when enabled, `generate` holds
+/// each document as pending until it is approved and published
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ApprovalSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Program and arguments run for each pending document; `{document}` is
+    /// replaced with the path of its content, which is appended when no
+    /// argument contains it. Printing `approve` or `reject` decides it.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub command: Vec<String>,
+    /// URL receiving a JSON POST for each pending document; a response of
+    /// `{"decision": "approve"}` or `{"decision": "reject"}` decides it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook: Option<String>,
+}
+
+impl ApprovalSettings {
+    pub fn is_disabled(&self) -> bool {
+        !self.enabled && self.command.is_empty() && self.webhook.is_none()
+    }
+}
+
+/// Pass run over each document before extraction that finds the entities
+/// it mentions and every way it refers to them ("IBM", "the company",
+/// "it"), so extraction names each entity with one subject URI
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CoreferenceSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Instructions replacing the built-in coreference prompt, e.g. to
+    /// describe domain-specific abbreviations
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instructions: Option<String>,
+}
+
+impl CoreferenceSettings {
+    pub fn is_disabled(&self) -> bool {
+        !self.enabled && self.instructions.is_none()
+    }
+}
+
+/// Prompting for documents in one language; unset parts use the defaults
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LanguagePrompt {
+    /// Replaces the instructions at the end of the extraction prompt
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instructions: Option<String>,
+    /// Replaces the extraction system prompt
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+    /// Replace the configured questions, e.g. with translations
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extraction_questions: Vec<ExtractionQuestion>,
 }
 
+/// Settings for `serve` mode
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerSettings {
+    /// Keys accepted as `Authorization: Bearer <key>` or `X-API-Key`; when
+    /// empty, requests are not authenticated
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub api_keys: Vec<ApiKeySettings>,
+    /// Log method, path, status, key name and latency of every request
+    #[serde(default = "default_true")]
+    pub log_requests: bool,
+    /// Separate knowledge graphs by name; a key bound to one sees only it
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub datasets: HashMap<String, DatasetSettings>,
+    /// Hosts `POST /jobs` may fetch although they resolve to a loopback,
+    /// private or link-local address
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_hosts: Vec<String>,
+}
+
+impl Default for ServerSettings {
+    fn default() -> Self {
+        Self { api_keys: Vec::new(), log_requests: true, datasets: HashMap::new(), allowed_hosts: Vec::new() }
+    }
+}
+
+/// A knowledge graph served alongside the main one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatasetSettings {
+    pub kg_path: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKeySettings {
+    /// Name shown in request logs
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    /// Environment variable holding the key, instead of `key`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_env: Option<String>,
+    #[serde(default)]
+    pub permission: Permission,
+    /// Dataset from `datasets` this key is confined to; the main knowledge
+    /// graph when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dataset: Option<String>,
+}
+
+/// What an API key may do; each level includes the ones before it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    /// SPARQL queries, graph downloads and entity pages
+    #[default]
+    Read,
+    /// Adding and replacing graphs
+    Ingest,
+    /// Deleting graphs
+    Admin,
+}
+
+fn default_ocr_command() -> String { "tesseract".to_string() }
+fn default_ocr_language() -> String { "eng".to_string() }
+fn default_pdf_rasterizer() -> String { "pdftoppm".to_string() }
+fn default_ocr_dpi() -> u32 { 300 }
+fn default_ocr_min_pdf_chars() -> usize { 50 }
+
+fn default_per_host_delay_ms() -> u64 { 1000 }
+fn default_retry_attempts() -> u32 { 3 }
+fn default_retry_backoff_ms() -> u64 { 2000 }
+fn default_retry_max_backoff_ms() -> u64 { 60_000 }
+
 fn default_temperature() -> f32 { 0.3 }
 fn default_max_tokens() -> u32 { 4096 }
 fn default_timeout() -> u64 { 120 }
 fn default_true() -> bool { true }
+fn default_cache_directory() -> String { ".llm_cache".to_string() }
+fn default_cache_ttl() -> Option<u64> { Some(7 * 24 * 60 * 60) }
+fn default_link_threshold() -> f32 { 0.92 }
 
 impl Configuration {
     /// Load configuration from a YAML or JSON file
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
         let path = path.as_ref();
         let content = fs::read_to_string(path)
-            .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+            .config_context(|| format!("Failed to read config file: {}", path.display()))?;
 
-        let config = if path.extension().and_then(|s| s.to_str()) == Some("json") {
-            serde_json::from_str(&content)?
+        let mut config: Self = if path.extension().and_then(|s| s.to_str()) == Some("json") {
+            serde_json::from_str(&content)
+                .config_context(|| format!("Failed to parse config file: {}", path.display()))?
         } else {
-            serde_yaml::from_str(&content)?
+            serde_yaml::from_str(&content)
+                .config_context(|| format!("Failed to parse config file: {}", path.display()))?
         };
 
+        if config.rdf_schema.ontology_file.is_some() {
+            config.rdf_schema.load_ontology(path.parent().unwrap_or(Path::new(".")))?;
+        }
+
         Ok(config)
     }
 
+    /// Fill `{{name}}` placeholders in question texts and descriptions from
+    /// `variables`; a placeholder without a value is an error
+    pub fn resolve_variables(&mut self) -> Result<()> {
+        let mut handlebars = handlebars::Handlebars::new();
+        handlebars.set_strict_mode(true);
+        handlebars.register_escape_fn(handlebars::no_escape);
+
+        let language_questions = self.language_prompts.values_mut().flat_map(|prompt| &mut prompt.extraction_questions);
+        for question in self.extraction_questions.iter_mut().chain(language_questions) {
+            let texts = std::iter::once(&mut question.question).chain(question.description.as_mut());
+            for text in texts.filter(|text| text.contains("{{")) {
+                *text = handlebars.render_template(text, &self.variables)
+                    .config_context(|| format!("Failed to fill variables in question '{}'", question.id))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Prompting configured for a language, looked up by its ISO 639-3 code,
+    /// then by its English name; an exact key wins over one differing in
+    /// case, and of those the first in sort order
+    pub fn language_prompt(&self, code: &str, name: &str) -> Option<&LanguagePrompt> {
+        let find = |wanted: &str| self.language_prompts.get(wanted).or_else(|| {
+            self.language_prompts.iter()
+                .filter(|(key, _)| key.eq_ignore_ascii_case(wanted))
+                .min_by_key(|(key, _)| key.as_str())
+                .map(|(_, prompt)| prompt)
+        });
+        find(code).or_else(|| find(name))
+    }
+
     /// Validate the configuration
     pub fn validate(&self) -> Result<()> {
         if self.extraction_questions.is_empty() {
-            anyhow::bail!("No extraction questions defined");
+            return Err(Error::config("No extraction questions defined"));
         }
 
         if self.rdf_schema.base_uri.is_empty() {
-            anyhow::bail!("No base URI defined for RDF schema");
+            return Err(Error::config("No base URI defined for RDF schema"));
         }
 
-        for question in &self.extraction_questions {
+        let language_questions = self.language_prompts.values().flat_map(|prompt| &prompt.extraction_questions);
+        for question in self.extraction_questions.iter().chain(language_questions) {
             if question.id.is_empty() {
-                anyhow::bail!("Question missing ID: {}", question.question);
+                return Err(Error::config(format!("Question missing ID: {}", question.question)));
             }
         }
 
+        for (key, handler) in &self.document_handlers {
+            handler.validate(key)?;
+        }
+
+        for mapping in &self.predicate_mappings {
+            if mapping.to.trim().is_empty() {
+                return Err(Error::config(format!("Predicate mapping for '{}' has no `to` predicate", mapping.from)));
+            }
+            if mapping.regex {
+                regex::Regex::new(&mapping.from)
+                    .config_context(|| format!("Invalid predicate mapping regex '{}'", mapping.from))?;
+            }
+        }
+
+        crate::handlers::HtmlFilter::new(&self.html)?;
+
+        if self.crawl.as_ref().is_some_and(|crawl| crawl.retry.attempts == 0) {
+            return Err(Error::config("crawl.retry.attempts must be at least 1"));
+        }
+
         Ok(())
     }
 
@@ -162,6 +850,9 @@ impl Configuration {
                 predicates,
                 classes,
                 custom_vocabularies: HashMap::new(),
+                subclass_of: HashMap::new(),
+                predicate_axioms: HashMap::new(),
+                ontology_file: None,
             },
             output_format: OutputFormat::Turtle,
             llm_settings: LlmSettings {
@@ -171,6 +862,15 @@ impl Configuration {
                 temperature: 0.3,
                 max_tokens: 4096,
                 timeout: 120,
+                auto_configure: true,
+                context_window: None,
+                chars_per_token: None,
+                max_document_chars: None,
+                structured_output: None,
+                cache: CacheSettings::default(),
+                extraction: GenerationOptions::default(),
+                generation: GenerationOptions::default(),
+                enhancement: GenerationOptions::default(),
             },
             validation_rules: vec![
                 "require_valid_uri".to_string(),
@@ -179,7 +879,45 @@ impl Configuration {
             post_processing: PostProcessing {
                 deduplicate: true,
                 normalize_uris: true,
+                min_confidence: None,
             },
+            predicate_mappings: Vec::new(),
+            literal_normalization: LiteralNormalization::default(),
+            budget: BudgetSettings::default(),
+            ocr: OcrSettings::default(),
+            document_handlers: HashMap::new(),
+            variables: HashMap::new(),
+            server: ServerSettings::default(),
+            entity_linking: EntityLinkingSettings::default(),
+            approval: ApprovalSettings::default(),
+            coreference: CoreferenceSettings::default(),
+            language_prompts: HashMap::new(),
+            document_timeout: None,
+            crawl: None,
+            html: HtmlSettings::default(),
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_variables() {
+        let mut config = Configuration::example();
+        config.extraction_questions[0].question = "Who manufactures {{product}}?".to_string();
+        config.extraction_questions[0].description = Some("Companies behind {{ product }} in {{region}}".to_string());
+        config.variables = HashMap::from([
+            ("product".to_string(), "R&D <Widget>".to_string()),
+            ("region".to_string(), "EU".to_string()),
+        ]);
+
+        config.resolve_variables().unwrap();
+        assert_eq!(config.extraction_questions[0].question, "Who manufactures R&D <Widget>?");
+        assert_eq!(config.extraction_questions[0].description.as_deref(), Some("Companies behind R&D <Widget> in EU"));
+
+        let mut missing = Configuration::example();
+        missing.extraction_questions[0].question = "Who sells {{product}}?".to_string();
+        assert!(missing.resolve_variables().is_err());
+    }
+}
@@ -0,0 +1,467 @@
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+use super::{OutputFormat, PredicateAxioms, RdfSchema};
+use crate::core::RdfTriple;
+use crate::error::{ErrorContext, Result};
+use crate::utils::parse_rdf;
+
+const RDF: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+const RDFS: &str = "http://www.w3.org/2000/01/rdf-schema#";
+const OWL: &str = "http://www.w3.org/2002/07/owl#";
+const XSD: &str = "http://www.w3.org/2001/XMLSchema#";
+
+const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+const RDFS_LABEL: &str = "http://www.w3.org/2000/01/rdf-schema#label";
+const RDFS_COMMENT: &str = "http://www.w3.org/2000/01/rdf-schema#comment";
+const RDFS_SUBCLASS_OF: &str = "http://www.w3.org/2000/01/rdf-schema#subClassOf";
+const RDFS_SUBPROPERTY_OF: &str = "http://www.w3.org/2000/01/rdf-schema#subPropertyOf";
+const RDFS_DOMAIN: &str = "http://www.w3.org/2000/01/rdf-schema#domain";
+const RDFS_RANGE: &str = "http://www.w3.org/2000/01/rdf-schema#range";
+const OWL_INVERSE_OF: &str = "http://www.w3.org/2002/07/owl#inverseOf";
+
+const CLASS_TYPES: [&str; 2] = [
+    "http://www.w3.org/2002/07/owl#Class",
+    "http://www.w3.org/2000/01/rdf-schema#Class",
+];
+const PROPERTY_TYPES: [&str; 4] = [
+    "http://www.w3.org/1999/02/22-rdf-syntax-ns#Property",
+    "http://www.w3.org/2002/07/owl#ObjectProperty",
+    "http://www.w3.org/2002/07/owl#DatatypeProperty",
+    "http://www.w3.org/2002/07/owl#TransitiveProperty",
+];
+
+impl RdfSchema {
+    /// Resolve a class or predicate name from the schema to a URI
+    ///
+    /// Full URIs are kept, `prefix:local` names are expanded with the
+    /// standard vocabularies, the schema prefix or `custom_vocabularies`,
+    /// and bare names are placed in the schema namespace.
+    pub fn resolve_term(&self, name: &str) -> String {
+        if name.contains("://") || name.starts_with("urn:") {
+            return name.to_string();
+        }
+
+        if let Some((prefix, local)) = name.split_once(':') {
+            if let Some(namespace) = self.namespace_for(prefix) {
+                return format!("{}{}", namespace, local);
+            }
+        }
+
+        format!("{}{}", self.namespace, name)
+    }
+
+    /// Inverse of [`resolve_term`](Self::resolve_term): the local name for
+    /// URIs in the schema namespace, `prefix:local` for known vocabularies
+    /// and the URI itself otherwise
+    pub fn term_name(&self, uri: &str) -> String {
+        if let Some(local) = uri.strip_prefix(self.namespace.as_str()).filter(|l| !l.is_empty()) {
+            return local.to_string();
+        }
+
+        let mut vocabularies: Vec<(&str, &str)> = vec![("rdf", RDF), ("rdfs", RDFS), ("owl", OWL), ("xsd", XSD)];
+        vocabularies.extend(self.custom_vocabularies.iter().map(|(p, ns)| (p.as_str(), ns.as_str())));
+        vocabularies.into_iter()
+            .find_map(|(prefix, namespace)| {
+                uri.strip_prefix(namespace)
+                    .filter(|local| !local.is_empty() && !local.contains(['/', '#']))
+                    .map(|local| format!("{}:{}", prefix, local))
+            })
+            .unwrap_or_else(|| uri.to_string())
+    }
+
+    /// Whether `class` is `ancestor` or one of its (transitive) subclasses
+    /// according to `subclass_of`; both are URIs
+    pub fn is_subclass_of(&self, class: &str, ancestor: &str) -> bool {
+        let mut pending = vec![class.to_string()];
+        let mut seen = BTreeSet::new();
+        while let Some(current) = pending.pop() {
+            if current == ancestor {
+                return true;
+            }
+            if !seen.insert(current.clone()) {
+                continue;
+            }
+            for (name, parents) in &self.subclass_of {
+                if self.resolve_term(name) == current {
+                    pending.extend(parents.iter().map(|parent| self.resolve_term(parent)));
+                }
+            }
+        }
+        false
+    }
+
+    /// Whether `triple` agrees with its predicate's domain and range
+    ///
+    /// Literal ranges (`xsd:*`, `rdfs:Literal`) require a literal object
+    /// and class ranges a resource. When `types` (URI to `rdf:type`
+    /// classes) lists the subject's or object's classes, one of them must
+    /// be the domain or range or a subclass of it; untyped resources pass.
+    pub fn fits_domain_range(&self, triple: &RdfTriple, types: &HashMap<String, Vec<String>>) -> bool {
+        let predicate = self.resolve_term(&triple.predicate);
+        let Some(axioms) = self.predicate_axioms.iter()
+            .find(|(name, _)| self.resolve_term(name) == predicate)
+            .map(|(_, axioms)| axioms)
+        else {
+            return true;
+        };
+
+        let is_instance = |resource: &str, class: &str| {
+            let class = self.resolve_term(class);
+            types.get(resource).is_none_or(|classes| classes.iter().any(|c| self.is_subclass_of(c, &class)))
+        };
+        let is_resource = |value: &str| value.contains("://") || value.starts_with("urn:") || value.starts_with("_:");
+
+        if let Some(domain) = &axioms.domain {
+            if !is_instance(&triple.subject, domain) {
+                return false;
+            }
+        }
+        if let Some(range) = &axioms.range {
+            let range_uri = self.resolve_term(range);
+            if range_uri.starts_with(XSD) || range_uri == format!("{}Literal", RDFS) {
+                return !is_resource(&triple.object);
+            }
+            if !is_resource(&triple.object) || !is_instance(&triple.object, range) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Add the classes, properties and axioms declared in `ontology_file`
+    ///
+    /// Entries already in the configuration take precedence over the
+    /// ontology's. Descriptions come from `rdfs:comment`, falling back to
+    /// `rdfs:label`. Blank-node class expressions (unions, restrictions)
+    /// are skipped.
+    pub fn load_ontology(&mut self, base_dir: &Path) -> Result<()> {
+        let Some(file) = &self.ontology_file else { return Ok(()) };
+        let path = base_dir.join(file);
+        let content = std::fs::read_to_string(&path)
+            .config_context(|| format!("Failed to read ontology file: {}", path.display()))?;
+        let triples = parse_rdf(&content, &OutputFormat::Turtle)
+            .config_context(|| format!("Failed to parse ontology file: {}", path.display()))?;
+
+        self.add_ontology(&triples);
+        Ok(())
+    }
+
+    fn add_ontology(&mut self, triples: &[RdfTriple]) {
+        let named = |value: &str| !value.starts_with("_:");
+        let mut classes: BTreeSet<&str> = BTreeSet::new();
+        let mut properties: BTreeSet<&str> = BTreeSet::new();
+        let mut axioms: HashMap<&str, PredicateAxioms> = HashMap::new();
+        let mut parents: HashMap<&str, Vec<String>> = HashMap::new();
+        let mut labels: HashMap<&str, &str> = HashMap::new();
+        let mut comments: HashMap<&str, &str> = HashMap::new();
+
+        for triple in triples.iter().filter(|t| named(&t.subject)) {
+            let (subject, object) = (triple.subject.as_str(), triple.object.as_str());
+            // Only English or untagged text is used for descriptions
+            let english = triple.metadata.get("lang").is_none_or(|lang| lang.starts_with("en"));
+            match triple.predicate.as_str() {
+                RDF_TYPE if CLASS_TYPES.contains(&object) => {
+                    classes.insert(subject);
+                }
+                RDF_TYPE if PROPERTY_TYPES.contains(&object) => {
+                    properties.insert(subject);
+                    if object.ends_with("#TransitiveProperty") {
+                        axioms.entry(subject).or_default().transitive = true;
+                    }
+                }
+                RDFS_LABEL if english => {
+                    labels.entry(subject).or_insert(object);
+                }
+                RDFS_COMMENT if english => {
+                    comments.entry(subject).or_insert(object);
+                }
+                RDFS_SUBCLASS_OF if named(object) => {
+                    classes.insert(subject);
+                    parents.entry(subject).or_default().push(self.term_name(object));
+                }
+                RDFS_SUBPROPERTY_OF if named(object) => {
+                    properties.insert(subject);
+                    axioms.entry(subject).or_default().sub_property_of.push(self.term_name(object));
+                }
+                RDFS_DOMAIN if named(object) => {
+                    properties.insert(subject);
+                    axioms.entry(subject).or_default().domain.get_or_insert_with(|| self.term_name(object));
+                }
+                RDFS_RANGE if named(object) => {
+                    properties.insert(subject);
+                    axioms.entry(subject).or_default().range.get_or_insert_with(|| self.term_name(object));
+                }
+                OWL_INVERSE_OF if named(object) => {
+                    properties.insert(subject);
+                    axioms.entry(subject).or_default().inverse_of.get_or_insert_with(|| self.term_name(object));
+                }
+                _ => {}
+            }
+        }
+
+        let description = |uri: &str| -> String {
+            comments.get(uri).or_else(|| labels.get(uri)).map(|d| d.to_string()).unwrap_or_default()
+        };
+
+        for class in classes {
+            let name = self.term_name(class);
+            if let Some(class_parents) = parents.remove(class) {
+                let known = self.subclass_of.entry(name.clone()).or_default();
+                for parent in class_parents {
+                    if !known.contains(&parent) {
+                        known.push(parent);
+                    }
+                }
+            }
+            self.classes.entry(name).or_insert_with(|| description(class));
+        }
+
+        for property in properties {
+            let name = self.term_name(property);
+            if let Some(property_axioms) = axioms.remove(property) {
+                self.predicate_axioms.entry(name.clone()).or_insert(property_axioms);
+            }
+            self.predicates.entry(name).or_insert_with(|| description(property));
+        }
+    }
+
+    pub(crate) fn namespace_for(&self, prefix: &str) -> Option<&str> {
+        match prefix {
+            "rdf" => Some(RDF),
+            "rdfs" => Some(RDFS),
+            "owl" => Some(OWL),
+            "xsd" => Some(XSD),
+            _ if prefix == self.prefix => Some(self.namespace.as_str()),
+            _ => self.custom_vocabularies.get(prefix).map(String::as_str),
+        }
+    }
+
+    /// The schema's vocabulary as an RDFS/OWL ontology in Turtle
+    ///
+    /// Classes become `owl:Class` and predicates become `owl:ObjectProperty`
+    /// or `owl:DatatypeProperty` (`rdf:Property` when no range is known),
+    /// each labelled with its name and commented with its description.
+    /// `subclass_of` and `predicate_axioms` are stated as the matching
+    /// RDFS/OWL axioms.
+    pub fn to_ontology_turtle(&self) -> String {
+        let mut prefixes: Vec<(&str, &str)> = vec![
+            (self.prefix.as_str(), self.namespace.as_str()),
+            ("rdf", RDF),
+            ("rdfs", RDFS),
+            ("owl", OWL),
+            ("xsd", XSD),
+        ];
+        let mut vocabularies: Vec<_> = self.custom_vocabularies.iter().collect();
+        vocabularies.sort();
+        prefixes.extend(vocabularies.into_iter().map(|(p, ns)| (p.as_str(), ns.as_str())));
+        prefixes.dedup_by_key(|(p, _)| *p);
+
+        let term = |name: &str| compact(&self.resolve_term(name), &prefixes);
+
+        let mut output = String::new();
+        for (prefix, namespace) in &prefixes {
+            output.push_str(&format!("@prefix {}: <{}> .\n", prefix, namespace));
+        }
+        output.push_str(&format!(
+            "\n<{}> a owl:Ontology ;\n    rdfs:label {} .\n",
+            self.namespace.trim_end_matches(['#', '/']),
+            literal(&format!("{} vocabulary", self.prefix))
+        ));
+
+        let classes: BTreeSet<&String> = self.classes.keys().chain(self.subclass_of.keys()).collect();
+        for class in classes {
+            let mut statements = vec!["a owl:Class".to_string(), format!("rdfs:label {}", literal(&label(class)))];
+            if let Some(description) = self.classes.get(class).filter(|d| !d.is_empty()) {
+                statements.push(format!("rdfs:comment {}", literal(description)));
+            }
+            let mut parents: Vec<&String> = self.subclass_of.get(class).into_iter().flatten().collect();
+            parents.sort();
+            statements.extend(parents.into_iter().map(|parent| format!("rdfs:subClassOf {}", term(parent))));
+            push_resource(&mut output, &term(class), &statements);
+        }
+
+        let predicates: BTreeSet<&String> = self.predicates.keys().chain(self.predicate_axioms.keys()).collect();
+        for predicate in predicates {
+            let axioms = self.predicate_axioms.get(predicate).cloned().unwrap_or_default();
+            let range = axioms.range.as_deref().map(|range| self.resolve_term(range));
+            let kind = match &range {
+                Some(range) if range.starts_with(XSD) || range == &format!("{}Literal", RDFS) => "owl:DatatypeProperty",
+                Some(_) => "owl:ObjectProperty",
+                None => "rdf:Property",
+            };
+
+            let mut statements = vec![format!("a {}", kind)];
+            if axioms.transitive {
+                statements.push("a owl:TransitiveProperty".to_string());
+            }
+            statements.push(format!("rdfs:label {}", literal(&label(predicate))));
+            if let Some(description) = self.predicates.get(predicate).filter(|d| !d.is_empty()) {
+                statements.push(format!("rdfs:comment {}", literal(description)));
+            }
+            if let Some(domain) = &axioms.domain {
+                statements.push(format!("rdfs:domain {}", term(domain)));
+            }
+            if let Some(range) = &range {
+                statements.push(format!("rdfs:range {}", compact(range, &prefixes)));
+            }
+            statements.extend(axioms.sub_property_of.iter().map(|parent| format!("rdfs:subPropertyOf {}", term(parent))));
+            if let Some(inverse) = &axioms.inverse_of {
+                statements.push(format!("owl:inverseOf {}", term(inverse)));
+            }
+            push_resource(&mut output, &term(predicate), &statements);
+        }
+
+        output
+    }
+}
+
+fn push_resource(output: &mut String, subject: &str, statements: &[String]) {
+    output.push_str(&format!("\n{} {} .\n", subject, statements.join(" ;\n    ")));
+}
+
+/// Prefixed name for `uri` when its local part is a valid Turtle local name
+fn compact(uri: &str, prefixes: &[(&str, &str)]) -> String {
+    prefixes.iter()
+        .filter_map(|(prefix, namespace)| uri.strip_prefix(namespace).map(|local| (prefix, local)))
+        .find(|(_, local)| {
+            !local.is_empty()
+                && local.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+                && !local.starts_with('-')
+        })
+        .map(|(prefix, local)| format!("{}:{}", prefix, local))
+        .unwrap_or_else(|| format!("<{}>", uri))
+}
+
+fn literal(value: &str) -> String {
+    let escaped = value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n").replace('\r', "\\r");
+    format!("\"{}\"@en", escaped)
+}
+
+/// Human-readable label for a camelCase or PascalCase name: `worksFor`
+/// becomes "works for", `Organization` stays "Organization"
+fn label(name: &str) -> String {
+    let local = name.rsplit([':', '/', '#']).next().unwrap_or(name);
+    let mut label = String::new();
+    let mut previous_lower = false;
+    for c in local.chars() {
+        if c == '_' || c == '-' {
+            label.push(' ');
+            previous_lower = false;
+            continue;
+        }
+        if c.is_uppercase() && previous_lower {
+            label.push(' ');
+            label.extend(c.to_lowercase());
+        } else {
+            label.push(c);
+        }
+        previous_lower = c.is_lowercase() || c.is_ascii_digit();
+    }
+    label
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PredicateAxioms;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_ontology_turtle() {
+        let schema = RdfSchema {
+            namespace: "http://ex.org/ns#".to_string(),
+            prefix: "ex".to_string(),
+            classes: HashMap::from([
+                ("Person".to_string(), "A human being".to_string()),
+                ("Manager".to_string(), "A person who \"manages\"".to_string()),
+            ]),
+            predicates: HashMap::from([
+                ("worksFor".to_string(), "Person works for organization".to_string()),
+                ("hasName".to_string(), "Entity has name".to_string()),
+            ]),
+            subclass_of: HashMap::from([("Manager".to_string(), vec!["Person".to_string()])]),
+            predicate_axioms: HashMap::from([
+                ("worksFor".to_string(), PredicateAxioms {
+                    domain: Some("Person".to_string()),
+                    range: Some("schema:Organization".to_string()),
+                    ..Default::default()
+                }),
+                ("hasName".to_string(), PredicateAxioms { range: Some("xsd:string".to_string()), ..Default::default() }),
+            ]),
+            custom_vocabularies: HashMap::from([("schema".to_string(), "https://schema.org/".to_string())]),
+            ..Default::default()
+        };
+
+        let turtle = schema.to_ontology_turtle();
+        assert!(turtle.contains("@prefix schema: <https://schema.org/> ."));
+        assert!(turtle.contains("<http://ex.org/ns> a owl:Ontology"));
+        assert!(turtle.contains("ex:Manager a owl:Class ;\n    rdfs:label \"Manager\"@en ;\n    rdfs:comment \"A person who \\\"manages\\\"\"@en ;\n    rdfs:subClassOf ex:Person ."));
+        assert!(turtle.contains("ex:worksFor a owl:ObjectProperty ;\n    rdfs:label \"works for\"@en ;"));
+        assert!(turtle.contains("rdfs:domain ex:Person ;\n    rdfs:range schema:Organization ."));
+        assert!(turtle.contains("ex:hasName a owl:DatatypeProperty ;"));
+
+        let mut parser = rio_turtle::TurtleParser::new(turtle.as_bytes(), None);
+        let mut count = 0;
+        rio_api::parser::TriplesParser::parse_all(&mut parser, &mut |_| -> std::result::Result<(), rio_turtle::TurtleError> {
+            count += 1;
+            Ok(())
+        }).unwrap();
+        assert_eq!(count, 18);
+    }
+
+    #[test]
+    fn test_load_ontology() {
+        let ontology = r#"
+            @prefix ex: <http://ex.org/ns#> .
+            @prefix owl: <http://www.w3.org/2002/07/owl#> .
+            @prefix rdfs: <http://www.w3.org/2000/01/rdf-schema#> .
+            @prefix xsd: <http://www.w3.org/2001/XMLSchema#> .
+            @prefix schema: <https://schema.org/> .
+
+            ex:Person a owl:Class ; rdfs:label "Person"@en ; rdfs:comment "Ein Mensch"@de, "A human being"@en .
+            ex:Manager a owl:Class ; rdfs:subClassOf ex:Person, [ a owl:Restriction ] .
+            ex:worksFor a owl:ObjectProperty ; rdfs:label "works for" ;
+                rdfs:domain ex:Person ; rdfs:range schema:Organization .
+            ex:hasName a owl:DatatypeProperty ; rdfs:range xsd:string .
+            ex:partOf a owl:TransitiveProperty .
+        "#;
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("ontology.ttl"), ontology).unwrap();
+
+        let mut schema = RdfSchema {
+            namespace: "http://ex.org/ns#".to_string(),
+            prefix: "ex".to_string(),
+            predicates: HashMap::from([("hasName".to_string(), "Configured description".to_string())]),
+            custom_vocabularies: HashMap::from([("schema".to_string(), "https://schema.org/".to_string())]),
+            ontology_file: Some("ontology.ttl".into()),
+            ..Default::default()
+        };
+        schema.load_ontology(dir.path()).unwrap();
+
+        assert_eq!(schema.classes["Person"], "A human being");
+        assert_eq!(schema.classes["Manager"], "");
+        assert_eq!(schema.subclass_of["Manager"], vec!["Person".to_string()]);
+        assert_eq!(schema.predicates["worksFor"], "works for");
+        assert_eq!(schema.predicates["hasName"], "Configured description");
+        assert_eq!(schema.predicate_axioms["worksFor"].domain.as_deref(), Some("Person"));
+        assert_eq!(schema.predicate_axioms["worksFor"].range.as_deref(), Some("schema:Organization"));
+        assert_eq!(schema.predicate_axioms["hasName"].range.as_deref(), Some("xsd:string"));
+        assert!(schema.predicate_axioms["partOf"].transitive);
+        assert!(schema.is_subclass_of("http://ex.org/ns#Manager", "http://ex.org/ns#Person"));
+
+        let types = HashMap::from([
+            ("http://ex.org/ann".to_string(), vec!["http://ex.org/ns#Manager".to_string()]),
+            ("http://ex.org/acme".to_string(), vec!["http://ex.org/ns#Person".to_string()]),
+        ]);
+        let triple = |s: &str, p: &str, o: &str| RdfTriple::new(s.to_string(), format!("http://ex.org/ns#{}", p), o.to_string());
+        assert!(schema.fits_domain_range(&triple("http://ex.org/ann", "hasName", "Ann"), &types));
+        assert!(!schema.fits_domain_range(&triple("http://ex.org/ann", "hasName", "http://ex.org/name"), &types));
+        assert!(schema.fits_domain_range(&triple("http://ex.org/ann", "worksFor", "http://ex.org/initech"), &types));
+        assert!(!schema.fits_domain_range(&triple("http://ex.org/ann", "worksFor", "http://ex.org/acme"), &types));
+        assert!(!schema.fits_domain_range(&triple("http://ex.org/ann", "worksFor", "Acme"), &types));
+
+        schema.ontology_file = Some("missing.ttl".into());
+        assert!(schema.load_ontology(dir.path()).is_err());
+    }
+}
@@ -0,0 +1,215 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+use crate::config::{BudgetAction, BudgetSettings};
+use crate::core::llm_client::Usage;
+use crate::core::ExtractionResult;
+
+/// Cumulative LLM usage for a run
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BudgetUsage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+    pub calls: u64,
+    pub estimated_cost: f64,
+}
+
+impl std::fmt::Display for BudgetUsage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f,
+            "{} tokens ({} prompt, {} completion) over {} calls, estimated cost {:.4}",
+            self.total_tokens,
+            self.prompt_tokens,
+            self.completion_tokens,
+            self.calls,
+            self.estimated_cost
+        )
+    }
+}
+
+impl std::ops::AddAssign<&BudgetUsage> for BudgetUsage {
+    fn add_assign(&mut self, other: &BudgetUsage) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.total_tokens += other.total_tokens;
+        self.calls += other.calls;
+        self.estimated_cost += other.estimated_cost;
+    }
+}
+
+/// LLM usage of one document in a run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentUsage {
+    pub source: String,
+    #[serde(flatten)]
+    pub usage: BudgetUsage,
+}
+
+/// Usage report for an extraction run: run totals plus a per-document
+/// breakdown, serialized as JSON for billing dashboards
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageReport {
+    pub model: String,
+    pub prompt_cost_per_1k: f64,
+    pub completion_cost_per_1k: f64,
+    pub total: BudgetUsage,
+    pub documents: Vec<DocumentUsage>,
+}
+
+impl UsageReport {
+    pub fn new(model: &str, settings: &BudgetSettings, total: BudgetUsage, results: &[ExtractionResult]) -> Self {
+        let documents = results.iter()
+            .map(|result| DocumentUsage {
+                source: result.document_source.clone(),
+                usage: result.usage.clone().unwrap_or_default(),
+            })
+            .collect();
+
+        Self {
+            model: model.to_string(),
+            prompt_cost_per_1k: settings.prompt_cost_per_1k,
+            completion_cost_per_1k: settings.completion_cost_per_1k,
+            total,
+            documents,
+        }
+    }
+}
+
+/// Tracks token usage against the configured budget
+pub struct TokenBudget {
+    settings: BudgetSettings,
+    usage: Mutex<BudgetUsage>,
+    approved: Mutex<bool>,
+}
+
+impl TokenBudget {
+    pub fn new(settings: BudgetSettings) -> Self {
+        Self {
+            settings,
+            usage: Mutex::new(BudgetUsage::default()),
+            approved: Mutex::new(false),
+        }
+    }
+
+    pub fn action(&self) -> BudgetAction {
+        self.settings.on_exceeded
+    }
+
+    pub fn settings(&self) -> &BudgetSettings {
+        &self.settings
+    }
+
+    /// Add one call to the run totals and return its priced usage
+    pub fn record(&self, usage: &Usage) -> BudgetUsage {
+        let call = BudgetUsage {
+            prompt_tokens: usage.prompt_tokens as u64,
+            completion_tokens: usage.completion_tokens as u64,
+            total_tokens: usage.total_tokens as u64,
+            calls: 1,
+            estimated_cost: usage.prompt_tokens as f64 / 1000.0 * self.settings.prompt_cost_per_1k
+                + usage.completion_tokens as f64 / 1000.0 * self.settings.completion_cost_per_1k,
+        };
+        *self.usage.lock().unwrap() += &call;
+        call
+    }
+
+    /// Track a call about to be sent with an estimated `prompt_tokens`; if
+    /// the call is dropped before [`PendingCall::finish`], e.g. by a document
+    /// timeout, its estimated prompt is recorded since the server has
+    /// already spent it
+    pub fn start_call(&self, prompt_tokens: usize) -> PendingCall<'_> {
+        PendingCall { budget: self, prompt_tokens, finished: false }
+    }
+
+    pub fn usage(&self) -> BudgetUsage {
+        self.usage.lock().unwrap().clone()
+    }
+
+    /// Returns a description of the exceeded limit, if any
+    pub fn exceeded(&self) -> Option<String> {
+        let usage = self.usage();
+
+        if let Some(max_tokens) = self.settings.max_total_tokens {
+            if usage.total_tokens >= max_tokens {
+                return Some(format!("token budget exceeded: {} of {} tokens used", usage.total_tokens, max_tokens));
+            }
+        }
+
+        if let Some(max_cost) = self.settings.max_cost {
+            if usage.estimated_cost >= max_cost {
+                return Some(format!("cost budget exceeded: {:.4} of {:.4} spent", usage.estimated_cost, max_cost));
+            }
+        }
+
+        None
+    }
+
+    /// Whether further LLM calls may be made
+    pub fn allows_more(&self) -> bool {
+        self.exceeded().is_none() || *self.approved.lock().unwrap()
+    }
+
+    /// Allow the run to continue past the budget
+    pub fn approve_overrun(&self) {
+        *self.approved.lock().unwrap() = true;
+    }
+}
+
+/// A call in flight; see [`TokenBudget::start_call`]
+pub struct PendingCall<'a> {
+    budget: &'a TokenBudget,
+    prompt_tokens: usize,
+    finished: bool,
+}
+
+impl PendingCall<'_> {
+    /// The call returned; its actual usage is recorded by the caller
+    pub fn finish(mut self) {
+        self.finished = true;
+    }
+}
+
+impl Drop for PendingCall<'_> {
+    fn drop(&mut self) {
+        if !self.finished {
+            let prompt_tokens = self.prompt_tokens as u32;
+            self.budget.record(&Usage { prompt_tokens, completion_tokens: 0, total_tokens: prompt_tokens });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(prompt: u32, completion: u32) -> Usage {
+        Usage {
+            prompt_tokens: prompt,
+            completion_tokens: completion,
+            total_tokens: prompt + completion,
+        }
+    }
+
+    #[test]
+    fn test_token_budget_exceeded() {
+        let budget = TokenBudget::new(BudgetSettings {
+            max_total_tokens: Some(1000),
+            prompt_cost_per_1k: 0.5,
+            completion_cost_per_1k: 1.5,
+            ..Default::default()
+        });
+
+        let call = budget.record(&usage(400, 200));
+        assert_eq!(call.calls, 1);
+        assert!(budget.allows_more());
+        assert!((budget.usage().estimated_cost - 0.5).abs() < 1e-9);
+
+        budget.record(&usage(300, 100));
+        assert!(!budget.allows_more());
+        assert!(budget.exceeded().unwrap().contains("token budget"));
+
+        budget.approve_overrun();
+        assert!(budget.allows_more());
+    }
+}
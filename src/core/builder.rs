@@ -0,0 +1,127 @@
+use crate::error::Result;
+use std::path::Path;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+use crate::config::Configuration;
+use crate::core::extractor::{BudgetConfirmation, RdfExtractor};
+use crate::core::llm_client::VllmClient;
+use crate::handlers::DocumentHandler;
+use crate::knowledge_graph::Correction;
+
+/// Builds an `RdfExtractor` from a configuration plus optional overrides
+pub struct RdfExtractorBuilder {
+    config: Configuration,
+    budget_confirmation: Option<BudgetConfirmation>,
+    document_handlers: Vec<(String, Box<dyn DocumentHandler>)>,
+    corrections: Vec<Correction>,
+    document_timeout: Option<Duration>,
+    cancellation: Option<CancellationToken>,
+}
+
+impl RdfExtractorBuilder {
+    pub fn new(config: Configuration) -> Self {
+        Self {
+            config,
+            budget_confirmation: None,
+            document_handlers: Vec::new(),
+            corrections: Vec::new(),
+            document_timeout: None,
+            cancellation: None,
+        }
+    }
+
+    pub fn from_config_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self::new(Configuration::from_file(path)?))
+    }
+
+    pub fn server_url(mut self, server_url: impl Into<String>) -> Self {
+        self.config.llm_settings.base_url = server_url.into();
+        self
+    }
+
+    pub fn api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.config.llm_settings.api_key = Some(api_key.into());
+        self
+    }
+
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.config.llm_settings.model = model.into();
+        self
+    }
+
+    pub fn cache(mut self, enabled: bool) -> Self {
+        self.config.llm_settings.cache.enabled = enabled;
+        self
+    }
+
+    pub fn auto_configure(mut self, enabled: bool) -> Self {
+        self.config.llm_settings.auto_configure = enabled;
+        self
+    }
+
+    /// Value for a `{{name}}` placeholder in the extraction questions,
+    /// overriding the config's `variables`
+    pub fn variable(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.config.variables.insert(name.into(), value.into());
+        self
+    }
+
+    pub fn budget_confirmation(mut self, confirm: BudgetConfirmation) -> Self {
+        self.budget_confirmation = Some(confirm);
+        self
+    }
+
+    /// Handle a file extension or URL scheme (e.g. "docx" or "s3://") with a
+    /// custom handler; takes precedence over handlers from the config
+    pub fn document_handler(mut self, ext_or_scheme: impl Into<String>, handler: Box<dyn DocumentHandler>) -> Self {
+        self.document_handlers.push((ext_or_scheme.into(), handler));
+        self
+    }
+
+    /// Time one document may take, overriding the config's `document_timeout`
+    pub fn document_timeout(mut self, timeout: Duration) -> Self {
+        self.document_timeout = Some(timeout);
+        self
+    }
+
+    /// Token that stops the extraction when cancelled, e.g. on Ctrl-C
+    pub fn cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// Reviewer corrections to include in extraction prompts as counter-examples
+    pub fn corrections(mut self, corrections: Vec<Correction>) -> Self {
+        self.corrections = corrections;
+        self
+    }
+
+    /// The configuration with all overrides applied
+    pub fn config(&self) -> &Configuration {
+        &self.config
+    }
+
+    /// Fill question variables, validate the configuration and create the extractor
+    pub fn build(mut self) -> Result<RdfExtractor> {
+        self.config.resolve_variables()?;
+        self.config.validate()?;
+
+        let llm_client = VllmClient::from_settings(&self.config.llm_settings)?;
+        let mut extractor = RdfExtractor::new(self.config, llm_client).with_corrections(self.corrections);
+        if let Some(confirm) = self.budget_confirmation {
+            extractor = extractor.with_budget_confirmation(confirm);
+        }
+        if let Some(timeout) = self.document_timeout {
+            extractor = extractor.with_document_timeout(timeout);
+        }
+        if let Some(token) = self.cancellation {
+            extractor = extractor.with_cancellation(token);
+        }
+        for (ext_or_scheme, handler) in self.document_handlers {
+            extractor.register_document_handler(&ext_or_scheme, handler);
+        }
+
+        Ok(extractor)
+    }
+}
@@ -0,0 +1,174 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+use crate::core::extractor::{ExtractionResult, RdfTriple};
+
+/// Side-by-side comparison of two extraction configurations
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtractionComparison {
+    pub label_a: String,
+    pub label_b: String,
+    pub documents: Vec<DocumentComparison>,
+    pub total_a: usize,
+    pub total_b: usize,
+    pub total_shared: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentComparison {
+    pub source: String,
+    pub shared: Vec<RdfTriple>,
+    pub only_a: Vec<RdfTriple>,
+    pub only_b: Vec<RdfTriple>,
+}
+
+impl DocumentComparison {
+    /// Jaccard agreement between the two triple sets
+    pub fn agreement(&self) -> f64 {
+        agreement(self.shared.len(), self.only_a.len(), self.only_b.len())
+    }
+}
+
+impl ExtractionComparison {
+    /// Compare results document by document, matching on `document_source`
+    pub fn new(
+        label_a: &str,
+        results_a: &[ExtractionResult],
+        label_b: &str,
+        results_b: &[ExtractionResult],
+    ) -> Self {
+        let mut by_source: BTreeMap<&str, (Vec<&RdfTriple>, Vec<&RdfTriple>)> = BTreeMap::new();
+
+        for result in results_a {
+            by_source.entry(&result.document_source).or_default().0.extend(&result.triples);
+        }
+        for result in results_b {
+            by_source.entry(&result.document_source).or_default().1.extend(&result.triples);
+        }
+
+        let documents: Vec<DocumentComparison> = by_source
+            .into_iter()
+            .map(|(source, (a, b))| compare_triples(source, &a, &b))
+            .collect();
+
+        let total_shared = documents.iter().map(|d| d.shared.len()).sum();
+        let total_a = total_shared + documents.iter().map(|d| d.only_a.len()).sum::<usize>();
+        let total_b = total_shared + documents.iter().map(|d| d.only_b.len()).sum::<usize>();
+
+        Self {
+            label_a: label_a.to_string(),
+            label_b: label_b.to_string(),
+            documents,
+            total_a,
+            total_b,
+            total_shared,
+        }
+    }
+
+    pub fn agreement(&self) -> f64 {
+        agreement(
+            self.total_shared,
+            self.total_a - self.total_shared,
+            self.total_b - self.total_shared,
+        )
+    }
+}
+
+fn triple_key(triple: &RdfTriple) -> (&str, &str, &str) {
+    (&triple.subject, &triple.predicate, &triple.object)
+}
+
+fn compare_triples(source: &str, a: &[&RdfTriple], b: &[&RdfTriple]) -> DocumentComparison {
+    let keys_a: HashSet<_> = a.iter().map(|t| triple_key(t)).collect();
+    let keys_b: HashSet<_> = b.iter().map(|t| triple_key(t)).collect();
+
+    let mut seen = HashSet::new();
+    let mut shared = Vec::new();
+    let mut only_a = Vec::new();
+    for triple in a {
+        if !seen.insert(triple_key(triple)) {
+            continue;
+        }
+        if keys_b.contains(&triple_key(triple)) {
+            shared.push((*triple).clone());
+        } else {
+            only_a.push((*triple).clone());
+        }
+    }
+
+    let mut seen = HashSet::new();
+    let only_b = b.iter()
+        .filter(|t| seen.insert(triple_key(t)) && !keys_a.contains(&triple_key(t)))
+        .map(|t| (*t).clone())
+        .collect();
+
+    DocumentComparison {
+        source: source.to_string(),
+        shared,
+        only_a,
+        only_b,
+    }
+}
+
+fn agreement(shared: usize, only_a: usize, only_b: usize) -> f64 {
+    let union = shared + only_a + only_b;
+    if union == 0 {
+        1.0
+    } else {
+        shared as f64 / union as f64
+    }
+}
+
+impl std::fmt::Display for ExtractionComparison {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Comparison: A = {}, B = {}", self.label_a, self.label_b)?;
+
+        for doc in &self.documents {
+            writeln!(f, "\n{} (agreement {:.1}%)", doc.source, doc.agreement() * 100.0)?;
+            for triple in &doc.only_a {
+                writeln!(f, "  A only: {}", triple.to_ntriple())?;
+            }
+            for triple in &doc.only_b {
+                writeln!(f, "  B only: {}", triple.to_ntriple())?;
+            }
+            writeln!(f, "  shared: {}", doc.shared.len())?;
+        }
+
+        write!(f,
+            "\nTotals: A {} triples, B {} triples, shared {}, overall agreement {:.1}%",
+            self.total_a,
+            self.total_b,
+            self.total_shared,
+            self.agreement() * 100.0
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn triple(s: &str, p: &str, o: &str) -> RdfTriple {
+        RdfTriple::new(s.to_string(), p.to_string(), o.to_string())
+    }
+
+    #[test]
+    fn test_compare_results() {
+        let result_a = ExtractionResult::new("doc.txt".to_string(), "a".to_string(), 0.0)
+            .with_triples(vec![
+                triple("http://ex.org/a", "http://ex.org/hasName", "Acme"),
+                triple("http://ex.org/a", "http://ex.org/locatedIn", "Paris"),
+            ]);
+        let result_b = ExtractionResult::new("doc.txt".to_string(), "b".to_string(), 0.0)
+            .with_triples(vec![
+                triple("http://ex.org/a", "http://ex.org/hasName", "Acme"),
+                triple("http://ex.org/a", "http://ex.org/locatedIn", "London"),
+            ]);
+
+        let comparison = ExtractionComparison::new("a", &[result_a], "b", &[result_b]);
+        assert_eq!(comparison.total_shared, 1);
+        assert_eq!(comparison.documents[0].only_a.len(), 1);
+        assert_eq!(comparison.documents[0].only_b.len(), 1);
+        assert!((comparison.agreement() - 1.0 / 3.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,128 @@
+use crate::error::{Error, ErrorContext, Result};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::config::{EntityLinkingSettings, LlmSettings};
+
+/// Inputs sent per `/v1/embeddings` request
+const EMBEDDING_BATCH_SIZE: usize = 64;
+
+#[derive(Debug, Clone, Serialize)]
+struct EmbeddingRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+/// Client of an OpenAI-compatible `/v1/embeddings` endpoint
+#[derive(Clone)]
+pub struct EmbeddingClient {
+    client: reqwest::Client,
+    base_url: String,
+    pub model: String,
+}
+
+impl EmbeddingClient {
+    pub fn new(base_url: String, api_key: Option<String>, model: String, timeout: u64) -> Result<Self> {
+        let mut headers = reqwest::header::HeaderMap::new();
+        if let Some(key) = api_key {
+            headers.insert(
+                reqwest::header::AUTHORIZATION,
+                reqwest::header::HeaderValue::from_str(&format!("Bearer {}", key))
+                    .config_context(|| "Invalid API key")?,
+            );
+        }
+
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(timeout))
+            .default_headers(headers)
+            .build()
+            .llm_context(|| "Failed to create HTTP client")?;
+
+        Ok(Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            model,
+        })
+    }
+
+    /// Client for the configured embedding model, on the LLM server unless
+    /// another one is set
+    pub fn from_settings(settings: &EntityLinkingSettings, llm: &LlmSettings) -> Result<Self> {
+        if settings.model.is_empty() {
+            return Err(Error::config("entity_linking.model is required"));
+        }
+        Self::new(
+            settings.base_url.clone().unwrap_or_else(|| llm.base_url.clone()),
+            settings.api_key.clone().or_else(|| llm.api_key.clone()),
+            settings.model.clone(),
+            llm.timeout,
+        )
+    }
+
+    /// One vector per input, in input order
+    pub async fn embed(&self, inputs: &[String]) -> Result<Vec<Vec<f32>>> {
+        let url = format!("{}/v1/embeddings", self.base_url);
+        let mut vectors = Vec::with_capacity(inputs.len());
+
+        for batch in inputs.chunks(EMBEDDING_BATCH_SIZE) {
+            let response = self.client
+                .post(&url)
+                .json(&EmbeddingRequest { model: &self.model, input: batch })
+                .send()
+                .await
+                .llm_context(|| "Failed to send embeddings request")?;
+
+            if !response.status().is_success() {
+                let status = response.status().as_u16();
+                let body = response.text().await.unwrap_or_default();
+                return Err(Error::Llm {
+                    message: format!("Embeddings API returned error: {}", body),
+                    status: Some(status),
+                    usage: None,
+                    source: None,
+                });
+            }
+
+            let mut response: EmbeddingResponse = response.json().await
+                .llm_context(|| "Failed to parse embeddings response")?;
+            if response.data.len() != batch.len() {
+                return Err(Error::llm(format!(
+                    "Embeddings API returned {} vectors for {} inputs",
+                    response.data.len(),
+                    batch.len()
+                )));
+            }
+            response.data.sort_by_key(|data| data.index);
+            vectors.extend(response.data.into_iter().map(|data| data.embedding));
+        }
+
+        Ok(vectors)
+    }
+}
+
+/// Cosine similarity of two vectors; 0 when either is zero or their
+/// lengths differ
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
@@ -1,14 +1,26 @@
-use anyhow::{Result, Context};
+use crate::error::{Error, Result};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::time::Instant;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, info, warn};
 use uuid::Uuid;
 
-use crate::config::Configuration;
-use crate::handlers::DocumentProcessor;
-use crate::core::llm_client::{VllmClient, PromptBuilder};
+use crate::config::{Configuration, ExtractionQuestion};
+use crate::handlers::{AddressGuard, DocumentHandler, DocumentProcessor, HtmlFilter, UrlHandler};
+use crate::core::llm_client::{VllmClient, PromptBuilder, BudgetedPrompt, ModelCapabilities, EntityMention, PromptGuidance};
+use crate::core::response::{field_text, triple_objects};
+use crate::core::language::detect_language;
+use crate::utils::normalize_source;
+use crate::core::tokens::TokenEstimator;
+use crate::core::predicate_mapping::PredicateMapper;
+use crate::core::budget::{TokenBudget, BudgetUsage, UsageReport};
+use crate::config::BudgetAction;
+use crate::knowledge_graph::Correction;
+
+const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RdfTriple {
@@ -19,6 +31,9 @@ pub struct RdfTriple {
     pub confidence: f32,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub source: Option<String>,
+    /// Named graph the triple belongs to; `None` is the default graph
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub graph: Option<String>,
     #[serde(default)]
     pub metadata: HashMap<String, String>,
 }
@@ -33,6 +48,7 @@ impl RdfTriple {
             object,
             confidence: 1.0,
             source: None,
+            graph: None,
             metadata: HashMap::new(),
         }
     }
@@ -42,6 +58,11 @@ impl RdfTriple {
         self
     }
 
+    pub fn with_graph(mut self, graph: String) -> Self {
+        self.graph = Some(graph);
+        self
+    }
+
     pub fn with_confidence(mut self, confidence: f32) -> Self {
         self.confidence = confidence;
         self
@@ -68,6 +89,12 @@ pub struct ExtractionResult {
     #[serde(default)]
     pub errors: Vec<String>,
     pub config_name: String,
+    /// LLM usage spent on this result, including failed calls
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<BudgetUsage>,
+    /// Triples dropped by `require_known_predicates`, kept to report schema drift
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unknown_predicates: Vec<RdfTriple>,
 }
 
 impl ExtractionResult {
@@ -85,6 +112,8 @@ impl ExtractionResult {
             metadata: HashMap::new(),
             errors: Vec::new(),
             config_name,
+            usage: None,
+            unknown_predicates: Vec::new(),
         }
     }
 
@@ -102,25 +131,210 @@ impl ExtractionResult {
         self.metadata = metadata;
         self
     }
+
+    pub fn with_usage(mut self, usage: BudgetUsage) -> Self {
+        self.usage = Some(usage);
+        self
+    }
+
+    pub fn with_unknown_predicates(mut self, triples: Vec<RdfTriple>) -> Self {
+        self.unknown_predicates = triples;
+        self
+    }
+
+    /// Report the result (and its triples' sources) under `name` instead of
+    /// the temporary path or URL the document was read from
+    pub fn with_source_name(mut self, name: &str) -> Self {
+        for triple in &mut self.triples {
+            if triple.source.as_deref() == Some(self.document_source.as_str()) {
+                triple.source = Some(name.to_string());
+            }
+        }
+        self.document_source = name.to_string();
+        self
+    }
 }
 
+/// Callback asked whether to continue once the budget is exceeded
+pub type BudgetConfirmation = Box<dyn Fn(&str) -> bool + Send + Sync>;
+
 pub struct RdfExtractor {
     config: Configuration,
     llm_client: VllmClient,
     document_processor: DocumentProcessor,
+    predicate_mapper: PredicateMapper,
+    budget: TokenBudget,
+    budget_confirmation: Option<BudgetConfirmation>,
+    context_window: Option<u32>,
+    token_estimator: TokenEstimator,
+    /// Reviewer corrections shown to the model as counter-examples
+    corrections: RwLock<Vec<Correction>>,
+    document_timeout: Option<Duration>,
+    cancellation: CancellationToken,
 }
 
 impl RdfExtractor {
-    pub fn new(config: Configuration, llm_client: VllmClient) -> Self {
+    pub fn new(config: Configuration, mut llm_client: VllmClient) -> Self {
+        let budget = TokenBudget::new(config.budget.clone());
+        let context_window = config.llm_settings.context_window;
+        let token_estimator = config.llm_settings.chars_per_token
+            .map(TokenEstimator::new)
+            .unwrap_or_default();
+        if let Some(mode) = config.llm_settings.structured_output {
+            llm_client.set_structured_output(mode);
+        }
+        let mut document_processor = DocumentProcessor::new();
+        if config.ocr.enabled {
+            document_processor = document_processor.with_ocr(config.ocr.clone());
+        }
+        let html_filter = match config.html.is_empty() {
+            true => None,
+            false => HtmlFilter::new(&config.html)
+                .inspect_err(|e| warn!("Ignoring HTML selectors from config: {}", e))
+                .ok(),
+        };
+        if config.crawl.is_some() || html_filter.is_some() {
+            for scheme in ["http://", "https://"] {
+                document_processor.register_handler(scheme, Box::new(url_handler(&config, html_filter.as_ref())));
+            }
+        }
+        if let Err(e) = document_processor.register_configured(&config.document_handlers, &config.ocr) {
+            warn!("Ignoring document handlers from config: {}", e);
+        }
+        let predicate_mapper = PredicateMapper::new(&config.predicate_mappings, &config.rdf_schema)
+            .unwrap_or_else(|e| {
+                warn!("Ignoring predicate mappings from config: {}", e);
+                PredicateMapper::default()
+            });
+
         Self {
-            config,
             llm_client,
-            document_processor: DocumentProcessor::new(),
+            document_processor,
+            predicate_mapper,
+            budget,
+            budget_confirmation: None,
+            context_window,
+            token_estimator,
+            corrections: RwLock::new(Vec::new()),
+            document_timeout: config.document_timeout.map(Duration::from_secs),
+            cancellation: CancellationToken::new(),
+            config,
         }
     }
 
+    /// Tune prompt size and structured-output mode to the detected model
+    /// capabilities. Settings given explicitly in the config take precedence.
+    pub fn apply_capabilities(&mut self, capabilities: &ModelCapabilities) {
+        if capabilities.context_window.is_some() {
+            self.context_window = capabilities.context_window;
+        }
+
+        if self.config.llm_settings.structured_output.is_none() {
+            self.llm_client.set_structured_output(capabilities.structured_output_mode());
+        }
+
+        info!(
+            "Auto-configured extractor: context_window={:?}, structured_output={:?}",
+            self.context_window,
+            capabilities.structured_output_mode()
+        );
+    }
+
+    /// Set the callback used when `budget.on_exceeded` is `confirm`
+    pub fn with_budget_confirmation(mut self, confirm: BudgetConfirmation) -> Self {
+        self.budget_confirmation = Some(confirm);
+        self
+    }
+
+    /// Include `corrections` in extraction prompts as counter-examples
+    pub fn with_corrections(self, corrections: Vec<Correction>) -> Self {
+        self.set_corrections(corrections);
+        self
+    }
+
+    /// Replace the corrections included in later extraction prompts, e.g.
+    /// after a reviewer records a new one
+    pub fn set_corrections(&self, corrections: Vec<Correction>) {
+        *self.corrections.write().unwrap() = corrections;
+    }
+
+    /// Record documents taking longer than `timeout` as timed out and move on
+    pub fn with_document_timeout(mut self, timeout: Duration) -> Self {
+        self.document_timeout = Some(timeout);
+        self
+    }
+
+    /// Stop extracting when `token` is cancelled; the document in progress
+    /// and those not started are recorded as cancelled
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = token;
+        self
+    }
+
+    /// Token that cancels this extractor's runs
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancellation.clone()
+    }
+
+    pub fn config(&self) -> &Configuration {
+        &self.config
+    }
+
+    pub fn llm_client(&self) -> &VllmClient {
+        &self.llm_client
+    }
+
+    /// Detect model capabilities and apply them, if enabled in the config;
+    /// the probe completions are charged to the token budget
+    pub async fn auto_configure(&mut self) -> Result<Option<ModelCapabilities>> {
+        if !self.config.llm_settings.auto_configure {
+            return Ok(None);
+        }
+
+        let capabilities = self.llm_client.detect_capabilities().await?;
+        for usage in &capabilities.probe_usage {
+            self.budget.record(usage);
+        }
+        self.apply_capabilities(&capabilities);
+        Ok(Some(capabilities))
+    }
+
+    /// Register a handler for a file extension or URL scheme (e.g. "docx" or
+    /// "s3://"); see [`DocumentProcessor::register_handler`]
+    pub fn register_document_handler(&mut self, ext_or_scheme: &str, handler: Box<dyn DocumentHandler>) {
+        self.document_processor.register_handler(ext_or_scheme, handler);
+    }
+
+    /// Only fetch URLs from public addresses, as when fetching on behalf of
+    /// server clients; see [`AddressGuard`]
+    pub fn guard_urls(&mut self, guard: AddressGuard) {
+        // Selector errors were reported when the extractor was created
+        let html_filter = HtmlFilter::new(&self.config.html).ok().filter(|_| !self.config.html.is_empty());
+        for scheme in ["http://", "https://"] {
+            let handler = url_handler(&self.config, html_filter.as_ref()).with_guard(guard.clone());
+            self.document_processor.register_handler(scheme, Box::new(handler));
+        }
+    }
+
+    /// File extensions the extractor can process
+    pub fn supported_extensions(&self) -> Vec<String> {
+        self.document_processor.supported_extensions()
+    }
+
+    /// Cumulative LLM usage of this extractor
+    pub fn budget_usage(&self) -> BudgetUsage {
+        self.budget.usage()
+    }
+
+    /// Run totals so far, with the per-document usage of `results`
+    pub fn usage_report(&self, results: &[ExtractionResult]) -> UsageReport {
+        UsageReport::new(&self.llm_client.model, self.budget.settings(), self.budget.usage(), results)
+    }
+
     pub async fn extract_from_document(&self, source: &str) -> Result<ExtractionResult> {
         let start_time = Instant::now();
+        // Record the same source for a file however it was named
+        let source = &normalize_source(source);
 
         info!("Starting extraction from document: {}", source);
 
@@ -140,20 +354,116 @@ impl RdfExtractor {
         };
 
         debug!("Document processed, text length: {}", processed_doc.text.len());
+        let section_title = processed_doc.metadata.get("section_title").cloned();
+        let mut result = self.extract_processed(source, &processed_doc.text, processed_doc.metadata, start_time).await?;
+
+        // Triples from a section keep its title with their provenance
+        if let Some(title) = section_title {
+            for triple in &mut result.triples {
+                triple.metadata.insert("section_title".to_string(), title.clone());
+            }
+        }
+        Ok(result)
+    }
+
+    /// Read `<source>#section=<n>` sources as sections of their document;
+    /// see [`DocumentProcessor::split_sections`]
+    pub fn split_sections(&mut self, enabled: bool) {
+        self.document_processor.split_sections(enabled);
+    }
+
+    /// `<source>#section=<n>` sources for the top-level sections of a long
+    /// document, to extract each one separately; see [`DocumentProcessor::section_sources`]
+    pub async fn section_sources(&self, source: &str) -> Result<Vec<String>> {
+        self.document_processor.section_sources(source).await
+    }
+
+    /// Extract triples from text already in hand, such as a snippet in a
+    /// prompt test case; `source` names it in the result
+    pub async fn extract_from_text(&self, source: &str, text: &str) -> Result<ExtractionResult> {
+        self.extract_processed(source, text, HashMap::new(), Instant::now()).await
+    }
+
+    async fn extract_processed(
+        &self,
+        source: &str,
+        text: &str,
+        mut metadata: HashMap<String, String>,
+        start_time: Instant,
+    ) -> Result<ExtractionResult> {
+        // Name each entity once before extraction, when configured
+        let (entities, coreference_usage) = if self.config.coreference.enabled && self.budget.allows_more() {
+            self.resolve_coreferences(source, text).await
+        } else {
+            (Vec::new(), BudgetUsage::default())
+        };
+
+        // Prompt in the document's language, when configured
+        let language = detect_language(text);
+        let language_prompt = language.as_ref().and_then(|language| self.config.language_prompt(&language.code, &language.name));
+        let questions = match language_prompt {
+            Some(prompt) if !prompt.extraction_questions.is_empty() => &prompt.extraction_questions,
+            _ => &self.config.extraction_questions,
+        };
+        let guidance = PromptGuidance {
+            entities,
+            corrections: self.corrections.read().unwrap().clone(),
+            instructions: language_prompt.and_then(|prompt| prompt.instructions.clone()),
+            system_prompt: language_prompt.and_then(|prompt| prompt.system_prompt.clone()),
+        };
 
         // Build extraction prompt
-        let prompt = PromptBuilder::build_extraction_prompt(
-            &processed_doc.text,
-            &self.config.extraction_questions,
-            &self.config.rdf_schema,
-        );
+        let prompt = match self.build_prompt(text, questions, &guidance) {
+            Ok(prompt) => prompt,
+            Err(e) => {
+                warn!("{}: {}", source, e);
+                let processing_time = start_time.elapsed().as_secs_f64();
+                return Ok(ExtractionResult::new(
+                    source.to_string(),
+                    self.config.name.clone(),
+                    processing_time,
+                ).with_error(e.to_string()).with_usage(coreference_usage));
+            }
+        };
+        if prompt.truncated {
+            warn!("Document truncated to ~{} tokens to fit the context window: {}", prompt.document_tokens, source);
+        }
+
+        if !self.budget.allows_more() {
+            let error_msg = format!("Skipped: {}", self.budget.exceeded().unwrap_or_default());
+            warn!("{}", error_msg);
+            let processing_time = start_time.elapsed().as_secs_f64();
+            return Ok(ExtractionResult::new(
+                source.to_string(),
+                self.config.name.clone(),
+                processing_time,
+            ).with_error(error_msg).with_usage(coreference_usage));
+        }
 
         // Extract with LLM
-        let llm_response = match self.llm_client
-            .generate_structured(&prompt, Some(PromptBuilder::get_system_prompt()))
-            .await {
-            Ok(response) => response,
+        let pending = self.budget.start_call(prompt.prompt_tokens);
+        let response = self.llm_client
+            .generate_structured_with_schema(
+                &prompt.prompt,
+                Some(guidance.system_prompt()),
+                "rdf_triples",
+                &PromptBuilder::triples_json_schema(),
+            )
+            .await;
+        pending.finish();
+        let llm_response = match response {
+            Ok((response, usage)) => {
+                let mut call_usage = self.budget.record(&usage);
+                call_usage += &coreference_usage;
+                (response, usage, call_usage)
+            }
             Err(e) => {
+                // Tokens spent on a response we could not use still count
+                let mut call_usage = match &e {
+                    Error::Llm { usage: Some(usage), .. } => self.budget.record(usage),
+                    _ => BudgetUsage::default(),
+                };
+                call_usage += &coreference_usage;
                 let error_msg = format!("LLM extraction failed: {}", e);
                 warn!("{}", error_msg);
                 let processing_time = start_time.elapsed().as_secs_f64();
@@ -161,25 +471,37 @@ impl RdfExtractor {
                     source.to_string(),
                     self.config.name.clone(),
                     processing_time,
-                ).with_error(error_msg));
+                ).with_error(error_msg).with_usage(call_usage));
             }
         };
 
         debug!("LLM response received: {:?}", llm_response);
 
         // Parse triples from LLM response
+        let (llm_response, usage, call_usage) = llm_response;
         let triples = self.parse_llm_response(&llm_response, source)?;
 
         // Apply post-processing
-        let processed_triples = self.post_process_triples(triples);
+        let (processed_triples, unknown_predicates) = self.post_process_triples(triples);
 
         let processing_time = start_time.elapsed().as_secs_f64();
 
         // Build metadata
-        let mut metadata = processed_doc.metadata;
         metadata.insert("extraction_config".to_string(), self.config.name.clone());
         metadata.insert("llm_model".to_string(), self.llm_client.model.clone());
-        metadata.insert("num_questions".to_string(), self.config.extraction_questions.len().to_string());
+        metadata.insert("num_questions".to_string(), questions.len().to_string());
+        if let Some(language) = &language {
+            metadata.insert("detected_language".to_string(), language.code.clone());
+        }
+        metadata.insert("estimated_prompt_tokens".to_string(), prompt.prompt_tokens.to_string());
+        metadata.insert("estimated_document_tokens".to_string(), prompt.document_tokens.to_string());
+        metadata.insert("document_truncated".to_string(), prompt.truncated.to_string());
+        metadata.insert("prompt_tokens".to_string(), usage.prompt_tokens.to_string());
+        metadata.insert("completion_tokens".to_string(), usage.completion_tokens.to_string());
+        metadata.insert("total_tokens".to_string(), usage.total_tokens.to_string());
+        if self.config.coreference.enabled {
+            metadata.insert("coreference_entities".to_string(), guidance.entities.len().to_string());
+        }
 
         info!(
             "Extraction completed: {} triples extracted in {:.2}s",
@@ -193,35 +515,198 @@ impl RdfExtractor {
             processing_time,
         )
         .with_triples(processed_triples)
-        .with_metadata(metadata))
+        .with_metadata(metadata)
+        .with_usage(call_usage)
+        .with_unknown_predicates(unknown_predicates))
     }
 
     pub async fn extract_from_multiple(&self, sources: Vec<String>) -> Result<Vec<ExtractionResult>> {
         let mut results = Vec::new();
-
-        for source in sources {
-            let result = self.extract_from_document(&source).await?;
+        self.extract_each(sources, async |result| {
             results.push(result);
+            Ok::<_, Error>(())
+        }).await?;
+        Ok(results)
+    }
+
+    /// Extract documents one at a time, handing each result to `on_result`
+    /// as soon as it is available so callers can persist progress; the next
+    /// document starts once `on_result` has finished. Documents that time
+    /// out are recorded as errors; once cancelled, the document in progress
+    /// and the rest are recorded as cancelled.
+    pub async fn extract_each<F, E>(&self, sources: Vec<String>, mut on_result: F) -> std::result::Result<(), E>
+    where
+        F: AsyncFnMut(ExtractionResult) -> std::result::Result<(), E>,
+        E: From<Error>,
+    {
+        let mut remaining = sources.into_iter();
+
+        while let Some(source) = remaining.next() {
+            let stop_reason = if self.cancellation.is_cancelled() {
+                Some("Cancelled".to_string())
+            } else if !self.budget.allows_more() && !self.confirm_overrun() {
+                Some(format!("Skipped: {}", self.budget.exceeded().unwrap_or_default()))
+            } else {
+                None
+            };
+            if let Some(reason) = stop_reason {
+                warn!("Stopping extraction: {}", reason);
+                for skipped in std::iter::once(source).chain(remaining.by_ref()) {
+                    on_result(ExtractionResult::new(
+                        skipped,
+                        self.config.name.clone(),
+                        0.0,
+                    ).with_error(reason.clone())).await?;
+                }
+                break;
+            }
+
+            let result = self.extract_within_limits(&source).await?;
+            on_result(result).await?;
         }
 
-        Ok(results)
+        Ok(())
+    }
+
+    /// Extract one document, giving up when it exceeds the document timeout
+    /// or the run is cancelled
+    pub async fn extract_within_limits(&self, source: &str) -> Result<ExtractionResult> {
+        let start_time = Instant::now();
+        let stopped = |error: String| {
+            warn!("{}: {}", error, source);
+            Ok(ExtractionResult::new(
+                source.to_string(),
+                self.config.name.clone(),
+                start_time.elapsed().as_secs_f64(),
+            ).with_error(error))
+        };
+
+        let extraction = async {
+            match self.document_timeout {
+                Some(timeout) => match tokio::time::timeout(timeout, self.extract_from_document(source)).await {
+                    Ok(result) => result,
+                    Err(_) => stopped(format!("Timed out after {:.1}s", timeout.as_secs_f64())),
+                },
+                None => self.extract_from_document(source).await,
+            }
+        };
+
+        tokio::select! {
+            result = extraction => result,
+            _ = self.cancellation.cancelled() => stopped("Cancelled".to_string()),
+        }
+    }
+
+    fn confirm_overrun(&self) -> bool {
+        if self.budget.action() != BudgetAction::Confirm {
+            return false;
+        }
+
+        let reason = self.budget.exceeded().unwrap_or_default();
+        let approved = self.budget_confirmation
+            .as_ref()
+            .is_some_and(|confirm| confirm(&reason));
+
+        if approved {
+            self.budget.approve_overrun();
+        }
+        approved
+    }
+
+    /// Ask the model which entities the document refers to in several ways.
+    /// A failed pass only loses the guidance, so extraction goes ahead without it.
+    async fn resolve_coreferences(&self, source: &str, text: &str) -> (Vec<EntityMention>, BudgetUsage) {
+        let prompt = match PromptBuilder::build_coreference_prompt_for_context(
+            text,
+            self.config.coreference.instructions.as_deref(),
+            self.context_window.unwrap_or(PromptBuilder::DEFAULT_CONTEXT_WINDOW),
+            self.llm_client.max_tokens(),
+            &self.token_estimator,
+        ) {
+            Ok(prompt) => prompt,
+            Err(e) => {
+                warn!("Coreference pass skipped, extracting without it: {}", e);
+                return (Vec::new(), BudgetUsage::default());
+            }
+        };
+
+        let pending = self.budget.start_call(prompt.prompt_tokens);
+        let result = self.llm_client
+            .generate_structured_with_schema(
+                &prompt.prompt,
+                Some(PromptBuilder::get_coreference_system_prompt()),
+                "entity_mentions",
+                &PromptBuilder::coreference_json_schema(),
+            )
+            .await;
+        pending.finish();
+
+        match result {
+            Ok((response, usage)) => {
+                let call_usage = self.budget.record(&usage);
+                let entities = PromptBuilder::parse_entity_mentions(&response);
+                debug!("Coreference pass found {} entities in {}", entities.len(), source);
+                (entities, call_usage)
+            }
+            Err(e) => {
+                warn!("Coreference pass failed, extracting without it: {}", e);
+                let call_usage = match &e {
+                    Error::Llm { usage: Some(usage), .. } => self.budget.record(usage),
+                    _ => BudgetUsage::default(),
+                };
+                (Vec::new(), call_usage)
+            }
+        }
+    }
+
+    /// Size the prompt to the context window, or to `max_document_chars` when configured
+    fn build_prompt(&self, document_text: &str, questions: &[ExtractionQuestion], guidance: &PromptGuidance) -> Result<BudgetedPrompt> {
+        let schema = &self.config.rdf_schema;
+
+        if let Some(max_chars) = self.config.llm_settings.max_document_chars {
+            let prompt = PromptBuilder::build_extraction_prompt_with_limit(document_text, questions, schema, guidance, max_chars);
+            let document_tokens = self.token_estimator.estimate(PromptBuilder::truncate_chars(document_text, max_chars));
+            return Ok(BudgetedPrompt {
+                prompt_tokens: self.token_estimator.estimate(&prompt)
+                    + self.token_estimator.estimate(guidance.system_prompt()),
+                prompt,
+                document_tokens,
+                truncated: document_text.len() > max_chars,
+            });
+        }
+
+        PromptBuilder::build_extraction_prompt_for_context(
+            document_text,
+            questions,
+            schema,
+            guidance,
+            self.context_window.unwrap_or(PromptBuilder::DEFAULT_CONTEXT_WINDOW),
+            self.llm_client.max_tokens(),
+            &self.token_estimator,
+        )
     }
 
     pub fn merge_results(&self, results: Vec<ExtractionResult>) -> Result<ExtractionResult> {
         if results.is_empty() {
-            anyhow::bail!("Cannot merge empty results");
+            return Err(Error::document("merged", "Cannot merge empty results"));
         }
 
         let mut all_triples = Vec::new();
+        let mut unknown_predicates = Vec::new();
         let mut all_errors = Vec::new();
         let mut total_time = 0.0;
         let mut sources = Vec::new();
+        let mut usage = BudgetUsage::default();
 
         for result in &results {
             all_triples.extend(result.triples.clone());
+            unknown_predicates.extend(result.unknown_predicates.clone());
             all_errors.extend(result.errors.clone());
             total_time += result.processing_time_seconds;
             sources.push(result.document_source.clone());
+            if let Some(result_usage) = &result.usage {
+                usage += result_usage;
+            }
         }
 
         // Deduplicate triples if enabled
@@ -240,52 +725,40 @@ impl RdfExtractor {
             total_time,
         )
         .with_triples(all_triples)
-        .with_metadata(metadata))
+        .with_metadata(metadata)
+        .with_usage(usage)
+        .with_unknown_predicates(unknown_predicates))
     }
 
     fn parse_llm_response(&self, response: &serde_json::Value, source: &str) -> Result<Vec<RdfTriple>> {
-        let triples_array = if response.is_array() {
-            response.as_array().unwrap()
-        } else if let Some(triples) = response.get("triples") {
-            triples.as_array().context("'triples' field is not an array")?
-        } else {
-            // Try to find any array in the response
-            return Ok(Vec::new());
-        };
+        let triple_objects = triple_objects(response);
+        if triple_objects.is_empty() {
+            debug!("No triples in the LLM response for {}: {}", source, response);
+        }
 
         let mut triples = Vec::new();
 
-        for triple_value in triples_array {
-            if let Some(triple_obj) = triple_value.as_object() {
-                let subject = triple_obj.get("subject")
-                    .and_then(|s| s.as_str())
-                    .unwrap_or("")
-                    .to_string();
-
-                let predicate = triple_obj.get("predicate")
-                    .and_then(|p| p.as_str())
-                    .unwrap_or("")
-                    .to_string();
-
-                let object = triple_obj.get("object")
-                    .and_then(|o| o.as_str())
-                    .unwrap_or("")
-                    .to_string();
-
-                if !subject.is_empty() && !predicate.is_empty() && !object.is_empty() {
-                    let mut triple = RdfTriple::new(
-                        self.normalize_uri(subject),
-                        self.normalize_predicate(predicate),
-                        object,
-                    ).with_source(source.to_string());
-
-                    // Extract confidence if present
-                    if let Some(conf) = triple_obj.get("confidence").and_then(|c| c.as_f64()) {
-                        triple = triple.with_confidence(conf as f32);
-                    }
-
-                    triples.push(triple);
+        for triple_obj in &triple_objects {
+            let subject = field_text(triple_obj, "subject").unwrap_or_default();
+            let predicate = field_text(triple_obj, "predicate").unwrap_or_default();
+            let object = field_text(triple_obj, "object").unwrap_or_default();
+
+            if !subject.is_empty() && !predicate.is_empty() && !object.is_empty() {
+                let mut triple = RdfTriple::new(
+                    self.normalize_uri(subject),
+                    self.normalize_predicate(predicate),
+                    object,
+                ).with_source(source.to_string());
+
+                // Extract confidence if present, also when given as a string
+                let confidence = triple_obj.get("confidence").and_then(|c| {
+                    c.as_f64().or_else(|| c.as_str().and_then(|s| s.trim().parse().ok()))
+                });
+                if let Some(conf) = confidence {
+                    triple = triple.with_confidence(conf as f32);
                 }
+
+                triples.push(triple);
             }
         }
 
@@ -308,20 +781,35 @@ impl RdfExtractor {
         }
     }
 
-    fn post_process_triples(&self, triples: Vec<RdfTriple>) -> Vec<RdfTriple> {
+    /// Post-processed triples, and those dropped for a predicate outside the schema
+    fn post_process_triples(&self, triples: Vec<RdfTriple>) -> (Vec<RdfTriple>, Vec<RdfTriple>) {
         let mut processed = triples;
+        let mut unknown_predicates = Vec::new();
+
+        // Rewrite predicates to canonical ones, so the checks below see them
+        if !self.predicate_mapper.is_empty() {
+            let rewritten = self.predicate_mapper.apply(&mut processed);
+            if rewritten > 0 {
+                debug!("Mapped {} predicates to canonical ones", rewritten);
+            }
+        }
 
         // Apply deduplication
         if self.config.post_processing.deduplicate {
             processed = self.deduplicate_triples(processed);
         }
 
+        // Drop low-confidence triples
+        if let Some(min_confidence) = self.config.post_processing.min_confidence {
+            processed.retain(|t| t.confidence >= min_confidence);
+        }
+
         // Apply validation rules
         if !self.config.validation_rules.is_empty() {
-            processed = self.apply_validation_rules(processed);
+            (processed, unknown_predicates) = self.apply_validation_rules(processed);
         }
 
-        processed
+        (processed, unknown_predicates)
     }
 
     fn deduplicate_triples(&self, triples: Vec<RdfTriple>) -> Vec<RdfTriple> {
@@ -342,43 +830,153 @@ impl RdfExtractor {
         unique_triples
     }
 
-    fn apply_validation_rules(&self, triples: Vec<RdfTriple>) -> Vec<RdfTriple> {
+    /// Triples passing every rule, and those `require_known_predicates` rejected
+    fn apply_validation_rules(&self, triples: Vec<RdfTriple>) -> (Vec<RdfTriple>, Vec<RdfTriple>) {
         let mut valid_triples = Vec::new();
+        let mut unknown_predicates = Vec::new();
+        let schema = &self.config.rdf_schema;
+
+        // Classes stated for each resource in this batch, for domain/range checks
+        let mut types: HashMap<String, Vec<String>> = HashMap::new();
+        if self.config.validation_rules.iter().any(|rule| rule == "require_domain_range") {
+            for triple in triples.iter().filter(|t| schema.resolve_term(&t.predicate) == RDF_TYPE) {
+                types.entry(triple.subject.clone()).or_default().push(schema.resolve_term(&triple.object));
+            }
+        }
 
         for triple in triples {
             let mut is_valid = true;
+            let mut known_predicate = true;
 
             for rule in &self.config.validation_rules {
                 match rule.as_str() {
-                    "require_valid_uri" => {
-                        if !triple.subject.starts_with("http") {
-                            is_valid = false;
-                            break;
-                        }
+                    "require_valid_uri" if !triple.subject.starts_with("http") => {
+                        is_valid = false;
+                        break;
                     }
                     "require_known_predicates" => {
                         let predicate_name = triple.predicate
                             .split('/')
-                            .last()
+                            .next_back()
                             .unwrap_or("")
                             .split('#')
-                            .last()
+                            .next_back()
                             .unwrap_or("");
 
                         if !self.config.rdf_schema.predicates.contains_key(predicate_name) {
                             is_valid = false;
+                            known_predicate = false;
                             break;
                         }
                     }
+                    "require_domain_range" if !schema.fits_domain_range(&triple, &types) => {
+                        is_valid = false;
+                        break;
+                    }
                     _ => {}
                 }
             }
 
             if is_valid {
                 valid_triples.push(triple);
+            } else if !known_predicate {
+                unknown_predicates.push(triple);
             }
         }
 
-        valid_triples
+        (valid_triples, unknown_predicates)
     }
-}
\ No newline at end of file
+}
+
+/// Handler for http(s) URLs, keeping to the config's crawl profile
+fn url_handler(config: &Configuration, html_filter: Option<&HtmlFilter>) -> UrlHandler {
+    let handler = match &config.crawl {
+        Some(profile) => UrlHandler::with_profile(profile),
+        None => UrlHandler::new(),
+    };
+    match html_filter {
+        Some(filter) => handler.with_filter(filter.clone()),
+        None => handler,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_document_timeout_and_cancellation() {
+        let mut server = mockito::Server::new_async().await;
+        let reply = r#"{"id":"1","object":"chat.completion","created":0,"model":"m",
+            "choices":[{"index":0,"message":{"role":"assistant","content":"[{\"subject\":\"acme\",\"predicate\":\"hasName\",\"object\":\"Acme\"}]"},"finish_reason":"stop"}],
+            "usage":{"prompt_tokens":1,"completion_tokens":1,"total_tokens":2}}"#;
+        server.mock("POST", "/v1/chat/completions")
+            .match_body(mockito::Matcher::Regex("Slow Corp".into()))
+            .with_chunked_body(move |writer| {
+                std::thread::sleep(Duration::from_millis(600));
+                writer.write_all(reply.as_bytes())
+            })
+            .create_async().await;
+        server.mock("POST", "/v1/chat/completions")
+            .with_body(reply)
+            .create_async().await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let document = |name: &str, text: &str| {
+            let path = dir.path().join(name);
+            std::fs::write(&path, text).unwrap();
+            path.to_string_lossy().to_string()
+        };
+        let sources = vec![document("acme.txt", "Acme is quick."), document("slow.txt", "Slow Corp is slow.")];
+
+        let mut config = Configuration::example();
+        config.llm_settings.base_url = server.url();
+        config.llm_settings.cache.enabled = false;
+        let extractor = RdfExtractor::new(config.clone(), VllmClient::from_settings(&config.llm_settings).unwrap())
+            .with_document_timeout(Duration::from_millis(200));
+
+        // The slow document times out without failing the run
+        let results = extractor.extract_from_multiple(sources.clone()).await.unwrap();
+        assert!(results[0].errors.is_empty(), "{:?}", results[0].errors);
+        assert_eq!(results[0].triples.len(), 1);
+        assert_eq!(results[1].errors, ["Timed out after 0.2s"]);
+        assert!(results[1].triples.is_empty());
+
+        // The abandoned call is charged its estimated prompt
+        let usage = extractor.budget_usage();
+        assert_eq!(usage.calls, 2);
+        assert!(usage.prompt_tokens > 1 && usage.completion_tokens == 1, "{:?}", usage);
+
+        // Once cancelled, every document left is recorded as cancelled
+        extractor.cancellation_token().cancel();
+        let results = extractor.extract_from_multiple(sources).await.unwrap();
+        assert!(results.iter().all(|r| r.errors == ["Cancelled"]));
+    }
+
+    #[tokio::test]
+    async fn test_auto_configure_charges_probes() {
+        let mut server = mockito::Server::new_async().await;
+        server.mock("GET", "/v1/models")
+            .with_body(r#"{"object":"list","data":[{"id":"Qwen/Qwen2.5-32B-Instruct","object":"model","created":0,"owned_by":"vllm","max_model_len":32768}]}"#)
+            .create_async().await;
+        server.mock("POST", "/v1/chat/completions")
+            .match_body(mockito::Matcher::Regex("tool_choice".into()))
+            .with_status(400)
+            .create_async().await;
+        server.mock("POST", "/v1/chat/completions")
+            .with_body(r#"{"id":"1","object":"chat.completion","created":0,"model":"m",
+                "choices":[{"index":0,"message":{"role":"assistant","content":"{"},"finish_reason":"length"}],
+                "usage":{"prompt_tokens":9,"completion_tokens":1,"total_tokens":10}}"#)
+            .create_async().await;
+
+        let mut config = Configuration::example();
+        config.llm_settings.base_url = server.url();
+        let mut extractor = RdfExtractor::new(config.clone(), VllmClient::from_settings(&config.llm_settings).unwrap());
+
+        let capabilities = extractor.auto_configure().await.unwrap().unwrap();
+        assert_eq!(capabilities.context_window, Some(32768));
+        assert!(capabilities.supports_guided_json && !capabilities.supports_tool_calling);
+        let usage = extractor.budget_usage();
+        assert_eq!((usage.calls, usage.total_tokens), (1, 10));
+    }
+}
@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+
+/// Characters of a document looked at to detect its language
+const DETECTION_SAMPLE_CHARS: usize = 10_000;
+
+/// Language a document was detected to be written in
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DetectedLanguage {
+    /// ISO 639-3 code, e.g. "deu"
+    pub code: String,
+    /// English name, e.g. "German"
+    pub name: String,
+    pub confidence: f64,
+}
+
+/// Detect the language of `text` from its first characters; `None` when the
+/// text is too short or mixed to tell reliably
+pub fn detect_language(text: &str) -> Option<DetectedLanguage> {
+    let sample = match text.char_indices().nth(DETECTION_SAMPLE_CHARS) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    };
+
+    let info = whatlang::detect(sample).filter(|info| info.is_reliable())?;
+    Some(DetectedLanguage {
+        code: info.lang().code().to_string(),
+        name: info.lang().eng_name().to_string(),
+        confidence: info.confidence(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_language() {
+        let german = "Die Acme GmbH hat ihren Sitz in Berlin. Geschäftsführerin des Unternehmens ist seit dem Jahr 2019 Ana Lopez, die zuvor bei einer Bank gearbeitet hat.";
+        let detected = detect_language(german).unwrap();
+        assert_eq!((detected.code.as_str(), detected.name.as_str()), ("deu", "German"));
+
+        let english = "Acme Corporation is based in Berlin. Its chief executive since 2019 is Ana Lopez, who previously worked for a bank.";
+        assert_eq!(detect_language(english).unwrap().code, "eng");
+        assert_eq!(detect_language("ok"), None);
+
+        // Prompts are configured by code or English name
+        let mut config = crate::config::Configuration::example();
+        config.language_prompts.insert("German".into(), crate::config::LanguagePrompt {
+            instructions: Some("Extrahiere die Tripel.".into()),
+            ..Default::default()
+        });
+        assert!(config.language_prompt(&detected.code, &detected.name).is_some());
+        assert!(config.language_prompt("eng", "English").is_none());
+
+        // The code is looked up before the name, and exact keys first
+        for (key, text) in [("deu", "code"), ("DEU", "upper code"), ("german", "name")] {
+            config.language_prompts.insert(key.into(), crate::config::LanguagePrompt { instructions: Some(text.into()), ..Default::default() });
+        }
+        assert_eq!(config.language_prompt("deu", "German").unwrap().instructions.as_deref(), Some("code"));
+        assert_eq!(config.language_prompt("Deu", "German").unwrap().instructions.as_deref(), Some("upper code"));
+        config.language_prompts.retain(|key, _| key == "German");
+
+        let guidance = crate::core::llm_client::PromptGuidance {
+            instructions: config.language_prompt("deu", "german").unwrap().instructions.clone(),
+            ..Default::default()
+        };
+        let prompt = crate::core::llm_client::PromptBuilder::build_extraction_prompt_with_limit(
+            german, &config.extraction_questions, &config.rdf_schema, &guidance, 1000);
+        assert!(prompt.contains("## Instructions\nExtrahiere die Tripel.\n"), "{}", prompt);
+        assert!(!prompt.contains("Only extract information") && prompt.contains("## Output Format"));
+        assert_eq!(guidance.system_prompt(), crate::core::llm_client::PromptBuilder::get_system_prompt());
+    }
+}
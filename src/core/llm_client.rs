@@ -1,8 +1,16 @@
-use anyhow::{Result, Context};
+use crate::error::{Error, ErrorContext, Result};
 use reqwest;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::PathBuf;
 use std::time::{Duration, Instant};
-use tracing::debug;
+use tracing::{debug, warn};
+
+use crate::config::{GenerationOptions, LlmPhase, LlmSettings, StructuredOutputMode};
+use crate::core::response::parse_json;
+use crate::core::tokens::TokenEstimator;
+use crate::knowledge_graph::Correction;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatMessage {
@@ -24,6 +32,8 @@ pub struct ChatCompletionRequest {
     pub presence_penalty: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -56,6 +66,8 @@ pub struct Model {
     pub object: String,
     pub created: u64,
     pub owned_by: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_model_len: Option<u32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -64,6 +76,28 @@ pub struct ModelsResponse {
     pub data: Vec<Model>,
 }
 
+/// Capabilities reported by (or probed from) the serving backend
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModelCapabilities {
+    pub model: String,
+    pub context_window: Option<u32>,
+    pub supports_guided_json: bool,
+    pub supports_tool_calling: bool,
+    /// Tokens spent by the probe completions, to be charged to a budget
+    #[serde(skip)]
+    pub probe_usage: Vec<Usage>,
+}
+
+impl ModelCapabilities {
+    pub fn structured_output_mode(&self) -> StructuredOutputMode {
+        if self.supports_guided_json {
+            StructuredOutputMode::GuidedJson
+        } else {
+            StructuredOutputMode::Prompt
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct LlmResponse {
     pub content: String,
@@ -71,6 +105,88 @@ pub struct LlmResponse {
     pub model: String,
     pub finish_reason: String,
     pub response_time: Duration,
+    /// Whether the response was served from the on-disk cache
+    pub cached: bool,
+}
+
+/// Cached completion stored on disk, keyed by a hash of the request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedCompletion {
+    created_at: u64,
+    content: String,
+    usage: Usage,
+    model: String,
+    finish_reason: String,
+}
+
+/// Content-addressed on-disk cache of chat completions
+#[derive(Debug, Clone)]
+pub struct ResponseCache {
+    directory: PathBuf,
+    ttl: Option<Duration>,
+}
+
+impl ResponseCache {
+    pub fn new<P: Into<PathBuf>>(directory: P, ttl: Option<Duration>) -> Self {
+        Self {
+            directory: directory.into(),
+            ttl,
+        }
+    }
+
+    fn key(request: &ChatCompletionRequest) -> Result<String> {
+        let serialized = serde_json::to_vec(request)
+            .llm_context(|| "Failed to serialize request for cache key")?;
+        let digest = Sha256::digest(&serialized);
+        Ok(digest.iter().map(|b| format!("{:02x}", b)).collect())
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.directory.join(&key[..2]).join(format!("{}.json", key))
+    }
+
+    async fn get(&self, key: &str) -> Option<CachedCompletion> {
+        let content = tokio::fs::read_to_string(self.entry_path(key)).await.ok()?;
+        let entry: CachedCompletion = serde_json::from_str(&content).ok()?;
+
+        if let Some(ttl) = self.ttl {
+            let age = unix_now().saturating_sub(entry.created_at);
+            if age > ttl.as_secs() {
+                debug!("Cache entry expired: {}", key);
+                return None;
+            }
+        }
+
+        Some(entry)
+    }
+
+    async fn put(&self, key: &str, entry: &CachedCompletion) -> Result<()> {
+        let path = self.entry_path(key);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await
+                .llm_context(|| format!("Failed to create cache directory: {}", parent.display()))?;
+        }
+        let content = serde_json::to_vec(entry)
+            .llm_context(|| "Failed to serialize cache entry")?;
+        tokio::fs::write(&path, content).await
+            .llm_context(|| format!("Failed to write cache entry: {}", path.display()))
+    }
+
+    /// Remove every cached entry
+    pub async fn clear(&self) -> Result<()> {
+        let context = || format!("Failed to clear cache: {}", self.directory.display());
+        if tokio::fs::try_exists(&self.directory).await.llm_context(context)? {
+            tokio::fs::remove_dir_all(&self.directory).await.llm_context(context)?;
+        }
+        Ok(())
+    }
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
 }
 
 #[derive(Clone)]
@@ -80,7 +196,9 @@ pub struct VllmClient {
     pub model: String,
     temperature: f32,
     max_tokens: u32,
-    timeout: Duration,
+    structured_output: StructuredOutputMode,
+    cache: Option<ResponseCache>,
+    phases: HashMap<LlmPhase, GenerationOptions>,
 }
 
 impl VllmClient {
@@ -101,14 +219,16 @@ impl VllmClient {
         if let Some(key) = api_key {
             headers.insert(
                 reqwest::header::AUTHORIZATION,
-                reqwest::header::HeaderValue::from_str(&format!("Bearer {}", key))?,
+                reqwest::header::HeaderValue::from_str(&format!("Bearer {}", key))
+                    .config_context(|| "Invalid API key")?,
             );
         }
 
         let client = reqwest::Client::builder()
             .timeout(Duration::from_secs(timeout))
             .default_headers(headers)
-            .build()?;
+            .build()
+            .llm_context(|| "Failed to create HTTP client")?;
 
         Ok(Self {
             client,
@@ -116,10 +236,66 @@ impl VllmClient {
             model,
             temperature,
             max_tokens,
-            timeout: Duration::from_secs(timeout),
+            structured_output: StructuredOutputMode::Prompt,
+            cache: None,
+            phases: HashMap::new(),
         })
     }
 
+    /// Build a client from configured LLM settings, including the response cache
+    pub fn from_settings(settings: &LlmSettings) -> Result<Self> {
+        let mut client = Self::new(
+            settings.base_url.clone(),
+            settings.api_key.clone(),
+            settings.model.clone(),
+            settings.temperature,
+            settings.max_tokens,
+            settings.timeout,
+        )?;
+
+        for phase in [LlmPhase::Extraction, LlmPhase::Generation, LlmPhase::Enhancement] {
+            client = client.with_phase_options(phase, settings.phase_options(phase));
+        }
+
+        if settings.cache.enabled {
+            client = client.with_cache(ResponseCache::new(
+                &settings.cache.directory,
+                settings.cache.ttl_seconds.map(Duration::from_secs),
+            ));
+        }
+
+        Ok(client)
+    }
+
+    pub fn with_cache(mut self, cache: ResponseCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    pub fn with_structured_output(mut self, mode: StructuredOutputMode) -> Self {
+        self.structured_output = mode;
+        self
+    }
+
+    pub fn set_structured_output(&mut self, mode: StructuredOutputMode) {
+        self.structured_output = mode;
+    }
+
+    /// Sampling overrides for every call made for `phase`
+    pub fn with_phase_options(mut self, phase: LlmPhase, options: GenerationOptions) -> Self {
+        self.phases.insert(phase, options);
+        self
+    }
+
+    pub fn phase_options(&self, phase: LlmPhase) -> GenerationOptions {
+        self.phases.get(&phase).copied().unwrap_or_default()
+    }
+
+    /// Completion token limit of extraction calls
+    pub fn max_tokens(&self) -> u32 {
+        self.phase_options(LlmPhase::Extraction).max_tokens.unwrap_or(self.max_tokens)
+    }
+
     pub async fn check_health(&self) -> Result<bool> {
         let url = format!("{}/health", self.base_url);
         let response = self.client
@@ -141,22 +317,120 @@ impl VllmClient {
             .get(&url)
             .send()
             .await
-            .context("Failed to fetch models")?;
+            .llm_context(|| "Failed to fetch models")?;
 
         if !response.status().is_success() {
-            anyhow::bail!("API returned error: {}", response.status());
+            return Err(Error::Llm {
+                message: "API returned error while listing models".to_string(),
+                status: Some(response.status().as_u16()),
+                usage: None,
+                source: None,
+            });
         }
 
         let models: ModelsResponse = response.json().await
-            .context("Failed to parse models response")?;
+            .llm_context(|| "Failed to parse models response")?;
 
         Ok(models.data.into_iter().map(|m| m.id).collect())
     }
 
+    /// Query the server for the model's context window and supported features
+    pub async fn detect_capabilities(&self) -> Result<ModelCapabilities> {
+        let url = format!("{}/v1/models", self.base_url);
+
+        let response = self.client
+            .get(&url)
+            .send()
+            .await
+            .llm_context(|| "Failed to fetch models")?;
+
+        if !response.status().is_success() {
+            return Err(Error::Llm {
+                message: "API returned error while listing models".to_string(),
+                status: Some(response.status().as_u16()),
+                usage: None,
+                source: None,
+            });
+        }
+
+        let models: ModelsResponse = response.json().await
+            .llm_context(|| "Failed to parse models response")?;
+
+        let context_window = models.data.iter()
+            .find(|m| m.id == self.model)
+            .and_then(|m| m.max_model_len);
+
+        let (supports_guided_json, guided_json_usage) = self.probe_feature(serde_json::json!({
+            "response_format": {
+                "type": "json_schema",
+                "json_schema": { "name": "probe", "schema": { "type": "object" } }
+            }
+        })).await;
+        let (supports_tool_calling, tool_calling_usage) = self.probe_feature(serde_json::json!({
+            "tools": [{
+                "type": "function",
+                "function": {
+                    "name": "noop",
+                    "description": "Capability probe",
+                    "parameters": { "type": "object", "properties": {} }
+                }
+            }],
+            "tool_choice": "auto"
+        })).await;
+
+        let capabilities = ModelCapabilities {
+            model: self.model.clone(),
+            context_window,
+            supports_guided_json,
+            supports_tool_calling,
+            probe_usage: guided_json_usage.into_iter().chain(tool_calling_usage).collect(),
+        };
+
+        debug!("Detected model capabilities: {:?}", capabilities);
+        Ok(capabilities)
+    }
+
+    /// Send a minimal completion with extra request fields; the feature is
+    /// considered supported if the server accepts the request. Returns the
+    /// usage the server reported for the completion, if any
+    async fn probe_feature(&self, extra: serde_json::Value) -> (bool, Option<Usage>) {
+        let mut request = serde_json::json!({
+            "model": self.model,
+            "messages": [{ "role": "user", "content": "{}" }],
+            "max_tokens": 1,
+        });
+
+        if let (Some(body), Some(fields)) = (request.as_object_mut(), extra.as_object()) {
+            for (key, value) in fields {
+                body.insert(key.clone(), value.clone());
+            }
+        }
+
+        let url = format!("{}/v1/chat/completions", self.base_url);
+        let response = match self.client.post(&url).json(&request).send().await {
+            Ok(resp) if resp.status().is_success() => resp,
+            _ => return (false, None),
+        };
+        let usage = response.json::<serde_json::Value>().await.ok()
+            .and_then(|body| serde_json::from_value(body.get("usage")?.clone()).ok());
+        (true, usage)
+    }
+
     pub async fn generate(
         &self,
         prompt: &str,
         system_prompt: Option<&str>,
+        options: GenerationOptions,
+    ) -> Result<LlmResponse> {
+        self.send_chat(prompt, system_prompt, None, options).await
+    }
+
+    async fn send_chat(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        response_format: Option<serde_json::Value>,
+        options: GenerationOptions,
     ) -> Result<LlmResponse> {
         let start_time = Instant::now();
 
@@ -177,12 +451,32 @@ impl VllmClient {
         let request = ChatCompletionRequest {
             model: self.model.clone(),
             messages,
-            temperature: self.temperature,
-            max_tokens: self.max_tokens,
-            top_p: Some(0.9),
+            temperature: options.temperature.unwrap_or(self.temperature),
+            max_tokens: options.max_tokens.unwrap_or(self.max_tokens),
+            top_p: Some(options.top_p.unwrap_or(0.9)),
             frequency_penalty: Some(0.0),
             presence_penalty: Some(0.0),
             stop: None,
+            response_format,
+        };
+
+        let cache_key = match &self.cache {
+            Some(cache) => {
+                let key = ResponseCache::key(&request)?;
+                if let Some(entry) = cache.get(&key).await {
+                    debug!("Cache hit for request {}", key);
+                    return Ok(LlmResponse {
+                        content: entry.content,
+                        usage: entry.usage,
+                        model: entry.model,
+                        finish_reason: entry.finish_reason,
+                        response_time: start_time.elapsed(),
+                        cached: true,
+                    });
+                }
+                Some(key)
+            }
+            None => None,
         };
 
         debug!("Sending request to vLLM: {:?}", request);
@@ -193,30 +487,54 @@ impl VllmClient {
             .json(&request)
             .send()
             .await
-            .context("Failed to send request to vLLM")?;
+            .llm_context(|| "Failed to send request to vLLM")?;
 
         if !response.status().is_success() {
             let status = response.status();
             let error_text = response.text().await.unwrap_or_default();
-            anyhow::bail!("vLLM API error {}: {}", status, error_text);
+            return Err(Error::Llm {
+                message: format!("vLLM API error: {}", error_text),
+                status: Some(status.as_u16()),
+                usage: None,
+                source: None,
+            });
         }
 
         let completion: ChatCompletionResponse = response.json().await
-            .context("Failed to parse completion response")?;
+            .llm_context(|| "Failed to parse completion response")?;
 
         let choice = completion.choices
             .into_iter()
             .next()
-            .ok_or_else(|| anyhow::anyhow!("No choices in response"))?;
+            .ok_or_else(|| Error::Llm {
+                message: "No choices in response".to_string(),
+                status: None,
+                usage: Some(completion.usage.clone()),
+                source: None,
+            })?;
 
         let response_time = start_time.elapsed();
 
+        if let (Some(cache), Some(key)) = (&self.cache, cache_key) {
+            let entry = CachedCompletion {
+                created_at: unix_now(),
+                content: choice.message.content.clone(),
+                usage: completion.usage.clone(),
+                model: completion.model.clone(),
+                finish_reason: choice.finish_reason.clone(),
+            };
+            if let Err(e) = cache.put(&key, &entry).await {
+                warn!("Failed to cache LLM response: {}", e);
+            }
+        }
+
         Ok(LlmResponse {
             content: choice.message.content,
             usage: completion.usage,
             model: completion.model,
             finish_reason: choice.finish_reason,
             response_time,
+            cached: false,
         })
     }
 
@@ -225,49 +543,282 @@ impl VllmClient {
         prompt: &str,
         system_prompt: Option<&str>,
     ) -> Result<serde_json::Value> {
+        let (value, _) = self.generate_structured_with_usage(prompt, system_prompt).await?;
+        Ok(value)
+    }
+
+    /// Like `generate_structured`, also returning the billed token usage of the call
+    pub async fn generate_structured_with_usage(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+    ) -> Result<(serde_json::Value, Usage)> {
+        self.send_structured(prompt, system_prompt, None).await
+    }
+
+    /// Generate JSON conforming to `schema`; in guided mode the server enforces
+    /// the schema during decoding, otherwise it is only requested in the prompt
+    pub async fn generate_structured_with_schema(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        schema_name: &str,
+        schema: &serde_json::Value,
+    ) -> Result<(serde_json::Value, Usage)> {
+        self.send_structured(prompt, system_prompt, Some((schema_name, schema))).await
+    }
+
+    fn structured_response_format(&self, schema: Option<(&str, &serde_json::Value)>) -> Option<serde_json::Value> {
+        match (self.structured_output, schema) {
+            (StructuredOutputMode::GuidedJson, Some((name, schema))) => Some(serde_json::json!({
+                "type": "json_schema",
+                "json_schema": { "name": name, "schema": schema, "strict": true }
+            })),
+            (StructuredOutputMode::GuidedJson, None) => Some(serde_json::json!({ "type": "json_object" })),
+            (StructuredOutputMode::Prompt, _) => None,
+        }
+    }
+
+    async fn send_structured(
+        &self,
+        prompt: &str,
+        system_prompt: Option<&str>,
+        schema: Option<(&str, &serde_json::Value)>,
+    ) -> Result<(serde_json::Value, Usage)> {
         // Add JSON instruction to prompt
         let json_prompt = format!(
             "{}\n\nPlease respond with valid JSON only. Do not include any markdown formatting or explanation text.",
             prompt
         );
 
-        let response = self.generate(&json_prompt, system_prompt).await?;
+        let response_format = self.structured_response_format(schema);
+        let options = self.phase_options(LlmPhase::Extraction);
+        let response = self.send_chat(&json_prompt, system_prompt, response_format, options).await?;
 
-        // Try to parse JSON from response
         let content = response.content.trim();
 
-        // Handle common cases where LLM wraps JSON in markdown
-        let json_content = if content.starts_with("```json") && content.ends_with("```") {
-            &content[7..content.len() - 3].trim()
-        } else if content.starts_with("```") && content.ends_with("```") {
-            &content[3..content.len() - 3].trim()
+        // Guided decoding returns bare JSON; otherwise recover JSON wrapped in
+        // markdown, surrounded by commentary or encoded as a string
+        let value = parse_json(content).ok_or_else(|| Error::Llm {
+            message: format!("Failed to parse JSON response: {}", content),
+            status: None,
+            usage: Some(response.usage.clone()),
+            source: None,
+        })?;
+
+        // Cached responses were already paid for
+        let usage = if response.cached {
+            Usage { prompt_tokens: 0, completion_tokens: 0, total_tokens: 0 }
         } else {
-            content
+            response.usage
         };
 
-        serde_json::from_str(json_content)
-            .with_context(|| format!("Failed to parse JSON response: {}", json_content))
+        Ok((value, usage))
+    }
+}
+
+/// An entity a document refers to in several ways, found by the coreference pass
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EntityMention {
+    /// The entity's most complete name
+    pub name: String,
+    /// Other expressions the document uses for it
+    #[serde(default)]
+    pub mentions: Vec<String>,
+}
+
+/// What the extraction prompt tells the model besides the document,
+/// questions and schema
+#[derive(Debug, Clone, Default)]
+pub struct PromptGuidance {
+    /// Entities from the coreference pass, each to get one subject URI
+    pub entities: Vec<EntityMention>,
+    /// Reviewer corrections shown as counter-examples
+    pub corrections: Vec<Correction>,
+    /// Instructions replacing the built-in ones, e.g. for the document's language
+    pub instructions: Option<String>,
+    /// System prompt replacing the built-in one
+    pub system_prompt: Option<String>,
+}
+
+impl PromptGuidance {
+    /// System prompt sent with the extraction prompt
+    pub fn system_prompt(&self) -> &str {
+        self.system_prompt.as_deref().unwrap_or(PromptBuilder::get_system_prompt())
     }
 }
 
+/// Extraction prompt sized to a context window
+#[derive(Debug, Clone)]
+pub struct BudgetedPrompt {
+    pub prompt: String,
+    /// Estimated tokens of prompt plus system prompt
+    pub prompt_tokens: usize,
+    pub document_tokens: usize,
+    pub truncated: bool,
+}
+
 pub struct PromptBuilder;
 
 impl PromptBuilder {
+    pub const DEFAULT_MAX_DOCUMENT_CHARS: usize = 8000;
+    /// Context window assumed when neither the server nor the config reports one
+    pub const DEFAULT_CONTEXT_WINDOW: u32 = 8192;
+    /// Role markers and special tokens added by the chat template
+    const CHAT_TEMPLATE_OVERHEAD_TOKENS: usize = 64;
+    /// Smallest share of the context window left for the document
+    pub const MIN_DOCUMENT_TOKENS: usize = 128;
+
     pub fn build_extraction_prompt(
         document_text: &str,
         questions: &[crate::config::ExtractionQuestion],
         schema: &crate::config::RdfSchema,
     ) -> String {
-        let mut prompt = String::new();
+        Self::build_extraction_prompt_with_limit(
+            document_text,
+            questions,
+            schema,
+            &PromptGuidance::default(),
+            Self::DEFAULT_MAX_DOCUMENT_CHARS,
+        )
+    }
 
+    pub fn build_extraction_prompt_with_limit(
+        document_text: &str,
+        questions: &[crate::config::ExtractionQuestion],
+        schema: &crate::config::RdfSchema,
+        guidance: &PromptGuidance,
+        max_document_chars: usize,
+    ) -> String {
         // Document content (truncated to prevent token overflow)
+        let truncated_text = Self::truncate_chars(document_text, max_document_chars);
+        Self::assemble_extraction_prompt(truncated_text, questions, schema, guidance)
+    }
+
+    /// Cut `text` to at most `max_bytes`, backing off to a char boundary
+    pub fn truncate_chars(text: &str, max_bytes: usize) -> &str {
+        if text.len() <= max_bytes {
+            return text;
+        }
+        let mut end = max_bytes;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        &text[..end]
+    }
+
+    /// Build the extraction prompt, truncating the document so that prompt,
+    /// system prompt and completion fit in the model's context window
+    pub fn build_extraction_prompt_for_context(
+        document_text: &str,
+        questions: &[crate::config::ExtractionQuestion],
+        schema: &crate::config::RdfSchema,
+        guidance: &PromptGuidance,
+        context_window: u32,
+        max_completion_tokens: u32,
+        estimator: &TokenEstimator,
+    ) -> Result<BudgetedPrompt> {
+        Self::fit_to_context(
+            document_text,
+            |document| Self::assemble_extraction_prompt(document, questions, schema, guidance),
+            guidance.system_prompt(),
+            context_window,
+            max_completion_tokens,
+            estimator,
+        )
+    }
+
+    /// Build the coreference prompt listing the entities of a document and
+    /// the ways it refers to them, truncating the document to fit the
+    /// model's context window
+    pub fn build_coreference_prompt_for_context(
+        document_text: &str,
+        instructions: Option<&str>,
+        context_window: u32,
+        max_completion_tokens: u32,
+        estimator: &TokenEstimator,
+    ) -> Result<BudgetedPrompt> {
+        Self::fit_to_context(
+            document_text,
+            |document| Self::assemble_coreference_prompt(document, instructions),
+            Self::get_coreference_system_prompt(),
+            context_window,
+            max_completion_tokens,
+            estimator,
+        )
+    }
+
+    /// Truncate the document so that the prompt `assemble` builds around it,
+    /// the system prompt and the completion fit in the context window. Fails
+    /// when that leaves fewer than [`Self::MIN_DOCUMENT_TOKENS`] for the document
+    fn fit_to_context(
+        document_text: &str,
+        assemble: impl Fn(&str) -> String,
+        system_prompt: &str,
+        context_window: u32,
+        max_completion_tokens: u32,
+        estimator: &TokenEstimator,
+    ) -> Result<BudgetedPrompt> {
+        let scaffold_tokens = estimator.estimate(&assemble(""))
+            + estimator.estimate(system_prompt)
+            + Self::CHAT_TEMPLATE_OVERHEAD_TOKENS;
+
+        let document_budget = (context_window as usize)
+            .saturating_sub(max_completion_tokens as usize)
+            .saturating_sub(scaffold_tokens);
+        if document_budget < Self::MIN_DOCUMENT_TOKENS {
+            return Err(Error::config(format!(
+                "A context window of {} tokens leaves {} tokens for the document after ~{} prompt and {} completion tokens; \
+                raise context_window or lower max_tokens",
+                context_window,
+                document_budget,
+                scaffold_tokens,
+                max_completion_tokens
+            )));
+        }
+
+        let document = estimator.truncate(document_text, document_budget);
+        let document_tokens = estimator.estimate(document);
+
+        Ok(BudgetedPrompt {
+            prompt: assemble(document),
+            prompt_tokens: scaffold_tokens + document_tokens,
+            document_tokens,
+            truncated: document.len() < document_text.len(),
+        })
+    }
+
+    fn assemble_coreference_prompt(document_text: &str, instructions: Option<&str>) -> String {
+        let mut prompt = String::new();
+
         prompt.push_str("## Document Content\n");
-        let truncated_text = if document_text.len() > 8000 {
-            &document_text[..8000]
-        } else {
-            document_text
-        };
-        prompt.push_str(truncated_text);
+        prompt.push_str(document_text);
+        prompt.push_str("\n\n## Instructions\n");
+        prompt.push_str(instructions.unwrap_or(
+            "List the entities (organizations, people, places, products and so on) that the document refers to in more than one way. \
+            For each, give its most complete name and every other expression the document uses for it: \
+            abbreviations, acronyms, shortened names, pronouns and descriptions such as \"the company\". \
+            Leave out entities the document always names the same way.",
+        ));
+        prompt.push_str(r#"
+
+Return a JSON object of the form:
+{"entities": [{"name": "International Business Machines", "mentions": ["IBM", "the company", "it"]}]}
+"#);
+
+        prompt
+    }
+
+    fn assemble_extraction_prompt(
+        document_text: &str,
+        questions: &[crate::config::ExtractionQuestion],
+        schema: &crate::config::RdfSchema,
+        guidance: &PromptGuidance,
+    ) -> String {
+        let mut prompt = String::new();
+
+        prompt.push_str("## Document Content\n");
+        prompt.push_str(document_text);
         prompt.push_str("\n\n");
 
         // Extraction questions
@@ -278,32 +829,85 @@ impl PromptBuilder {
                 prompt.push_str(&format!("  Constraints: {}\n", question.constraints.join(", ")));
             }
         }
-        prompt.push_str("\n");
+        prompt.push('\n');
 
         // Schema information
         prompt.push_str("## RDF Schema\n");
         prompt.push_str(&format!("Base URI: {}\n", schema.base_uri));
         prompt.push_str(&format!("Namespace: {}\n", schema.namespace));
 
+        if !schema.classes.is_empty() {
+            prompt.push_str("\nAvailable Classes (use with rdf:type):\n");
+            for (class, desc) in &schema.classes {
+                prompt.push_str(&format!("- {}: {}\n", class, desc));
+            }
+        }
+
         if !schema.predicates.is_empty() {
             prompt.push_str("\nAvailable Predicates:\n");
             for (pred, desc) in &schema.predicates {
-                prompt.push_str(&format!("- {}: {}\n", pred, desc));
+                prompt.push_str(&format!("- {}: {}", pred, desc));
+                if let Some(axioms) = schema.predicate_axioms.get(pred) {
+                    if let Some(domain) = &axioms.domain {
+                        prompt.push_str(&format!(" (subject: {})", domain));
+                    }
+                    if let Some(range) = &axioms.range {
+                        prompt.push_str(&format!(" (object: {})", range));
+                    }
+                }
+                prompt.push('\n');
             }
         }
 
-        // Instructions
+        // Entities found by the coreference pass, so each gets one subject URI
+        if !guidance.entities.is_empty() {
+            prompt.push_str("\n## Entities in the Document\n");
+            prompt.push_str("The document refers to these entities in several ways. Use one subject URI per entity, based on its name:\n");
+            for entity in &guidance.entities {
+                prompt.push_str(&format!("- {} (also: {})\n", entity.name, entity.mentions.join(", ")));
+            }
+        }
+
+        // Reviewer corrections of earlier extractions, as counter-examples
+        if !guidance.corrections.is_empty() {
+            prompt.push_str("\n## Corrections from Reviewers\n");
+            prompt.push_str("Reviewers corrected these triples from earlier extractions. Do not repeat these mistakes:\n");
+            for correction in &guidance.corrections {
+                let triple = format!("{} {} {}", correction.subject, correction.predicate, correction.object);
+                match &correction.corrected_object {
+                    Some(object) => prompt.push_str(&format!("- Wrong: {} (the object should be: {})", triple, object)),
+                    None => prompt.push_str(&format!("- Wrong: {}", triple)),
+                }
+                if let Some(note) = &correction.note {
+                    prompt.push_str(&format!(" - {}", note));
+                }
+                prompt.push('\n');
+            }
+        }
+
+        // Instructions; configured ones replace the built-in prose, but the
+        // output format the response is parsed with stays
         prompt.push_str("\n## Instructions\n");
-        prompt.push_str(r#"
+        match &guidance.instructions {
+            Some(instructions) => {
+                prompt.push_str(instructions);
+                prompt.push('\n');
+            }
+            None => prompt.push_str(r#"
 Extract the requested information from the document and return it as RDF triples.
 Each triple should have:
 - subject: The entity being described (use URIs from the base URI)
 - predicate: The relationship or property (use predicates from the schema)
 - object: The value or related entity
 
-Return the triples as a JSON array with objects containing 'subject', 'predicate', and 'object' fields.
 Only extract information that directly answers the specified questions.
 If information is not found in the document, do not create triples for it.
+"#),
+        }
+
+        prompt.push_str(r#"
+## Output Format
+Return the triples as a JSON array with objects containing 'subject', 'predicate', and 'object' fields.
 
 Example format:
 [
@@ -318,6 +922,79 @@ Example format:
         prompt
     }
 
+    /// JSON Schema of the extraction response used for guided decoding
+    pub fn triples_json_schema() -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "triples": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "subject": { "type": "string" },
+                            "predicate": { "type": "string" },
+                            "object": { "type": "string" },
+                            "confidence": { "type": "number", "minimum": 0, "maximum": 1 }
+                        },
+                        "required": ["subject", "predicate", "object"],
+                        "additionalProperties": false
+                    }
+                }
+            },
+            "required": ["triples"],
+            "additionalProperties": false
+        })
+    }
+
+    /// JSON Schema of the coreference response used for guided decoding
+    pub fn coreference_json_schema() -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "entities": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "name": { "type": "string" },
+                            "mentions": { "type": "array", "items": { "type": "string" } }
+                        },
+                        "required": ["name", "mentions"],
+                        "additionalProperties": false
+                    }
+                }
+            },
+            "required": ["entities"],
+            "additionalProperties": false
+        })
+    }
+
+    /// Entities from a coreference response, skipping malformed entries and
+    /// entities without another mention
+    pub fn parse_entity_mentions(response: &serde_json::Value) -> Vec<EntityMention> {
+        let entities = response.get("entities").unwrap_or(response);
+        let Some(entities) = entities.as_array() else {
+            return Vec::new();
+        };
+
+        entities.iter()
+            .filter_map(|entity| serde_json::from_value::<EntityMention>(entity.clone()).ok())
+            .map(|mut entity| {
+                entity.name = entity.name.trim().to_string();
+                entity.mentions.retain(|mention| {
+                    !mention.trim().is_empty() && !mention.trim().eq_ignore_ascii_case(&entity.name)
+                });
+                entity
+            })
+            .filter(|entity| !entity.name.is_empty() && !entity.mentions.is_empty())
+            .collect()
+    }
+
+    pub fn get_coreference_system_prompt() -> &'static str {
+        "You are an expert at coreference resolution. You find every expression in a document that refers to the same entity, without inventing entities the document does not mention."
+    }
+
     pub fn get_system_prompt() -> &'static str {
         r#"You are an expert knowledge extraction system specializing in converting unstructured text into structured RDF triples.
 
@@ -331,4 +1008,91 @@ Your task is to:
 
 Return your response as a JSON array of triple objects."#
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_phase_options() {
+        let mut server = mockito::Server::new_async().await;
+        let reply = r#"{"id":"1","object":"chat.completion","created":0,"model":"m",
+            "choices":[{"index":0,"message":{"role":"assistant","content":"{\"triples\":[]}"},"finish_reason":"stop"}],
+            "usage":{"prompt_tokens":1,"completion_tokens":1,"total_tokens":2}}"#;
+        let extraction = server.mock("POST", "/v1/chat/completions")
+            .match_body(mockito::Matcher::PartialJson(serde_json::json!({"temperature": 0.0, "max_tokens": 512, "top_p": 0.9})))
+            .with_body(reply)
+            .create_async().await;
+        let enhancement = server.mock("POST", "/v1/chat/completions")
+            .match_body(mockito::Matcher::PartialJson(serde_json::json!({"temperature": 0.75, "max_tokens": 64, "top_p": 0.5})))
+            .with_body(reply)
+            .create_async().await;
+
+        let client = VllmClient::new(server.url(), None, "m".into(), 0.3, 64, 5).unwrap()
+            .with_phase_options(LlmPhase::Extraction, GenerationOptions { temperature: Some(0.0), max_tokens: Some(512), ..Default::default() })
+            .with_phase_options(LlmPhase::Enhancement, GenerationOptions { temperature: Some(0.75), ..Default::default() });
+        assert_eq!(client.max_tokens(), 512);
+
+        client.generate_structured("Extract", None).await.unwrap();
+        let template = GenerationOptions { top_p: Some(0.5), ..Default::default() };
+        client.generate("Enhance", None, template.or(client.phase_options(LlmPhase::Enhancement))).await.unwrap();
+
+        extraction.assert_async().await;
+        enhancement.assert_async().await;
+    }
+
+    #[test]
+    fn test_prompt_corrections() {
+        let schema = crate::config::RdfSchema::default();
+        let estimator = TokenEstimator::default();
+        let correction = Correction {
+            id: "1".into(),
+            subject: "http://ex.org/acme".into(),
+            predicate: "http://ex.org/ceo".into(),
+            object: "Bob".into(),
+            corrected_object: Some("Ana".into()),
+            note: Some("Bob left in 2020".into()),
+            source: None,
+            recorded_at: chrono::Utc::now(),
+        };
+        let document = "word ".repeat(4000);
+        let build = |guidance: &PromptGuidance| PromptBuilder::build_extraction_prompt_for_context(&document, &[], &schema, guidance, 2048, 256, &estimator).unwrap();
+
+        let plain = build(&PromptGuidance::default());
+        let corrected = build(&PromptGuidance { corrections: vec![correction], ..Default::default() });
+        assert!(!plain.prompt.contains("## Corrections from Reviewers"));
+        assert!(corrected.prompt.contains("- Wrong: http://ex.org/acme http://ex.org/ceo Bob (the object should be: Ana) - Bob left in 2020\n"));
+        // The corrections come out of the document's share of the context window
+        assert!(corrected.document_tokens < plain.document_tokens);
+
+        // A window the completion and scaffold fill up is refused rather than sent without the document
+        let error = PromptBuilder::build_extraction_prompt_for_context(&document, &[], &schema, &PromptGuidance::default(), 2048, 2000, &estimator)
+            .unwrap_err();
+        assert!(matches!(error, Error::Config { .. }) && error.to_string().contains("raise context_window"), "{}", error);
+    }
+
+    #[test]
+    fn test_coreference_guidance() {
+        let response = serde_json::json!({"entities": [
+            {"name": " International Business Machines ", "mentions": ["IBM", "the company", "", "international business machines"]},
+            {"name": "Berlin", "mentions": []},
+            {"mentions": ["it"]},
+        ]});
+        let entities = PromptBuilder::parse_entity_mentions(&response);
+        assert_eq!(entities, [EntityMention {
+            name: "International Business Machines".into(),
+            mentions: vec!["IBM".into(), "the company".into()],
+        }]);
+
+        let schema = crate::config::RdfSchema::default();
+        let guidance = PromptGuidance { entities, ..Default::default() };
+        let prompt = PromptBuilder::build_extraction_prompt_with_limit("IBM said the company grew.", &[], &schema, &guidance, 1000);
+        assert!(prompt.contains("## Entities in the Document\n"));
+        assert!(prompt.contains("- International Business Machines (also: IBM, the company)\n"));
+
+        let coreference = PromptBuilder::build_coreference_prompt_for_context(
+            "IBM said the company grew.", Some("Also list ticker symbols."), 2048, 256, &TokenEstimator::default()).unwrap();
+        assert!(coreference.prompt.contains("Also list ticker symbols."));
+        assert!(!coreference.truncated);
+    }
+}
@@ -1,5 +1,25 @@
 pub mod llm_client;
+pub mod embeddings;
 pub mod extractor;
+pub mod budget;
+pub mod watcher;
+pub mod subscriber;
+pub mod comparison;
+pub mod builder;
+pub mod tokens;
+pub mod prompt_tests;
+pub mod language;
+pub mod response;
+pub mod predicate_mapping;
 
 pub use llm_client::VllmClient;
-pub use extractor::{RdfExtractor, ExtractionResult, RdfTriple};
\ No newline at end of file
+pub use embeddings::EmbeddingClient;
+pub use extractor::{RdfExtractor, ExtractionResult, RdfTriple};
+pub use budget::{TokenBudget, BudgetUsage, DocumentUsage, UsageReport};
+pub use watcher::DocumentWatcher;
+pub use subscriber::{DocumentSubscriber, ReceivedDocument};
+pub use comparison::ExtractionComparison;
+pub use builder::RdfExtractorBuilder;
+pub use tokens::TokenEstimator;
+pub use prompt_tests::{PromptTestCase, PromptTestOutcome, PromptTestReport};
+pub use predicate_mapping::PredicateMapper;
\ No newline at end of file
@@ -0,0 +1,132 @@
+use regex::{Regex, RegexBuilder};
+
+use crate::config::{PredicateMapping, RdfSchema};
+use crate::core::RdfTriple;
+use crate::error::{ErrorContext, Result};
+
+enum Pattern {
+    /// Whole predicate IRI
+    Iri(String),
+    /// Last segment of the predicate IRI
+    Name(String),
+    Regex(Regex),
+}
+
+struct CompiledMapping {
+    pattern: Pattern,
+    ignore_case: bool,
+    to: String,
+}
+
+/// Rewrites predicates chosen by the model to canonical ones, following the
+/// config's `predicate_mappings`
+#[derive(Default)]
+pub struct PredicateMapper {
+    mappings: Vec<CompiledMapping>,
+    /// Resolves `to` names and prefixed names to IRIs
+    schema: RdfSchema,
+}
+
+impl PredicateMapper {
+    pub fn new(mappings: &[PredicateMapping], schema: &RdfSchema) -> Result<Self> {
+        let mut compiled = Vec::new();
+        for mapping in mappings {
+            let pattern = if mapping.regex {
+                let regex = RegexBuilder::new(&format!("^(?:{})$", mapping.from))
+                    .case_insensitive(mapping.ignore_case)
+                    .build()
+                    .config_context(|| format!("Invalid predicate mapping regex '{}'", mapping.from))?;
+                Pattern::Regex(regex)
+            } else if mapping.from.contains("://") {
+                Pattern::Iri(mapping.from.clone())
+            } else {
+                Pattern::Name(mapping.from.clone())
+            };
+            compiled.push(CompiledMapping { pattern, ignore_case: mapping.ignore_case, to: mapping.to.clone() });
+        }
+        Ok(Self { mappings: compiled, schema: schema.clone() })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mappings.is_empty()
+    }
+
+    /// Canonical IRI for `predicate`, or `None` when no mapping matches
+    pub fn map(&self, predicate: &str) -> Option<String> {
+        let name = predicate.rsplit(['/', '#']).next().unwrap_or(predicate);
+        let same = |a: &str, b: &str, ignore_case: bool| if ignore_case { a.eq_ignore_ascii_case(b) } else { a == b };
+
+        self.mappings.iter().find_map(|mapping| {
+            let to = match &mapping.pattern {
+                Pattern::Iri(iri) => same(predicate, iri, mapping.ignore_case).then(|| mapping.to.clone()),
+                Pattern::Name(from) => same(name, from, mapping.ignore_case).then(|| mapping.to.clone()),
+                Pattern::Regex(regex) => regex.captures(name).map(|captures| {
+                    let mut to = String::new();
+                    captures.expand(&mapping.to, &mut to);
+                    to
+                }),
+            }?;
+            Some(self.schema.resolve_term(&to))
+        })
+    }
+
+    /// Rewrite the predicates of `triples` in place; returns how many changed
+    pub fn apply(&self, triples: &mut [RdfTriple]) -> usize {
+        let mut rewritten = 0;
+        for triple in triples {
+            if let Some(predicate) = self.map(&triple.predicate).filter(|p| *p != triple.predicate) {
+                triple.predicate = predicate;
+                rewritten += 1;
+            }
+        }
+        rewritten
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_predicate_mappings() {
+        let schema = RdfSchema {
+            namespace: "http://ex.org/ns#".into(),
+            prefix: "ex".into(),
+            custom_vocabularies: [("foaf".to_string(), "http://xmlns.com/foaf/0.1/".to_string())].into(),
+            ..Default::default()
+        };
+        let mapping = |from: &str, to: &str, regex: bool, ignore_case: bool| PredicateMapping {
+            from: from.into(),
+            to: to.into(),
+            regex,
+            ignore_case,
+        };
+        let mapper = PredicateMapper::new(&[
+            mapping("employedBy", "worksFor", false, true),
+            mapping("located_?in", "locatedIn", true, true),
+            mapping("has_(\\w+)", "has$1", true, false),
+            mapping("http://schema.org/name", "foaf:name", false, false),
+            mapping("fullName", "http://xmlns.com/foaf/0.1/name", false, false),
+        ], &schema).unwrap();
+
+        let map = |predicate: &str| mapper.map(predicate);
+        assert_eq!(map("http://ex.org/ns#EmployedBy").as_deref(), Some("http://ex.org/ns#worksFor"));
+        assert_eq!(map("http://ex.org/ns#Located_In").as_deref(), Some("http://ex.org/ns#locatedIn"));
+        assert_eq!(map("http://ex.org/ns#locatedin").as_deref(), Some("http://ex.org/ns#locatedIn"));
+        assert_eq!(map("http://ex.org/ns#has_revenue").as_deref(), Some("http://ex.org/ns#hasrevenue"));
+        assert_eq!(map("http://ex.org/ns#HAS_revenue"), None);
+        assert_eq!(map("http://schema.org/name").as_deref(), Some("http://xmlns.com/foaf/0.1/name"));
+        assert_eq!(map("http://ex.org/ns#fullName").as_deref(), Some("http://xmlns.com/foaf/0.1/name"));
+        // The regex must match the whole name
+        assert_eq!(map("http://ex.org/ns#relocated_in"), None);
+
+        let mut triples = vec![
+            RdfTriple::new("http://ex.org/bob".into(), "http://ex.org/ns#employedBy".into(), "http://ex.org/acme".into()),
+            RdfTriple::new("http://ex.org/bob".into(), "http://ex.org/ns#worksFor".into(), "http://ex.org/acme".into()),
+        ];
+        assert_eq!(mapper.apply(&mut triples), 1);
+        assert!(triples.iter().all(|t| t.predicate == "http://ex.org/ns#worksFor"));
+
+        assert!(PredicateMapper::new(&[mapping("(", "x", true, false)], &schema).is_err());
+    }
+}
@@ -0,0 +1,213 @@
+use crate::error::{Error, ErrorContext, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::RdfSchema;
+use crate::core::extractor::{RdfExtractor, RdfTriple};
+
+/// A document snippet with the triples extraction must (and must not)
+/// produce from it, kept as a YAML or JSON file in a test case directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptTestCase {
+    /// Defaults to the file name without extension
+    #[serde(default)]
+    pub name: String,
+    /// Document text to extract from
+    #[serde(default)]
+    pub text: Option<String>,
+    /// Document to extract from instead of `text`, relative to the case file
+    #[serde(default)]
+    pub document: Option<PathBuf>,
+    /// Triples that must be extracted
+    #[serde(default)]
+    pub expected: Vec<RdfTriple>,
+    /// Triples that must not be extracted
+    #[serde(default)]
+    pub forbidden: Vec<RdfTriple>,
+    /// Also fail on extracted triples that are not expected
+    #[serde(default)]
+    pub exact: bool,
+}
+
+/// How a case fared against one extraction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptTestOutcome {
+    pub name: String,
+    pub passed: bool,
+    /// Triples extracted in total
+    pub extracted: usize,
+    /// Expected triples that were not extracted
+    pub missing: Vec<RdfTriple>,
+    /// Forbidden triples that were extracted
+    pub forbidden: Vec<RdfTriple>,
+    /// Extracted triples that were not expected; failures only for exact cases
+    pub unexpected: Vec<RdfTriple>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Results of running a directory of prompt test cases
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptTestReport {
+    pub model: String,
+    pub config: String,
+    pub passed: usize,
+    pub failed: usize,
+    pub cases: Vec<PromptTestOutcome>,
+}
+
+impl PromptTestCase {
+    /// Load every `.yaml`, `.yml` and `.json` case in `dir`, ordered by file name
+    pub fn load_dir<P: AsRef<Path>>(dir: P) -> Result<Vec<Self>> {
+        let dir = dir.as_ref();
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+            .config_context(|| format!("Failed to read test case directory: {}", dir.display()))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| matches!(path.extension().and_then(|e| e.to_str()), Some("yaml" | "yml" | "json")))
+            .collect();
+        paths.sort();
+
+        paths.iter().map(|path| Self::load(path)).collect()
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .config_context(|| format!("Failed to read test case: {}", path.display()))?;
+        let mut case: Self = if path.extension().is_some_and(|e| e == "json") {
+            serde_json::from_str(&content).config_context(|| format!("Failed to parse test case: {}", path.display()))?
+        } else {
+            serde_yaml::from_str(&content).config_context(|| format!("Failed to parse test case: {}", path.display()))?
+        };
+
+        if case.name.is_empty() {
+            case.name = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+        }
+        if let Some(document) = &case.document {
+            case.document = Some(path.parent().unwrap_or(Path::new(".")).join(document));
+        }
+        if case.text.is_some() == case.document.is_some() {
+            return Err(Error::config(format!("Test case {} needs either `text` or `document`", path.display())));
+        }
+        Ok(case)
+    }
+
+    /// Extract the case's document with `extractor` and check the triples
+    pub async fn run(&self, extractor: &RdfExtractor) -> PromptTestOutcome {
+        let result = match (&self.text, &self.document) {
+            (Some(text), _) => extractor.extract_from_text(&self.name, text).await,
+            (None, Some(document)) => extractor.extract_from_document(&document.to_string_lossy()).await,
+            (None, None) => Err(Error::config(format!("Test case {} has no document", self.name))),
+        };
+
+        match result {
+            Ok(result) if result.errors.is_empty() => self.evaluate(&extractor.config().rdf_schema, &result.triples),
+            Ok(result) => self.failed(result.errors.join("; ")),
+            Err(e) => self.failed(e.to_string()),
+        }
+    }
+
+    /// Compare `extracted` with the expected and forbidden triples. Subjects
+    /// and predicates may be written as `prefix:local` or local names of the
+    /// schema; literal objects match ignoring case and surrounding whitespace.
+    pub fn evaluate(&self, schema: &RdfSchema, extracted: &[RdfTriple]) -> PromptTestOutcome {
+        let found = |triple: &RdfTriple| extracted.iter().any(|t| matches(schema, triple, t));
+
+        let missing: Vec<RdfTriple> = self.expected.iter().filter(|t| !found(t)).cloned().collect();
+        let forbidden: Vec<RdfTriple> = self.forbidden.iter().filter(|t| found(t)).cloned().collect();
+        let unexpected: Vec<RdfTriple> = extracted.iter()
+            .filter(|t| !self.expected.iter().any(|expected| matches(schema, expected, t)))
+            .cloned()
+            .collect();
+
+        PromptTestOutcome {
+            name: self.name.clone(),
+            passed: missing.is_empty() && forbidden.is_empty() && (!self.exact || unexpected.is_empty()),
+            extracted: extracted.len(),
+            missing,
+            forbidden,
+            unexpected,
+            error: None,
+        }
+    }
+
+    fn failed(&self, error: String) -> PromptTestOutcome {
+        PromptTestOutcome {
+            name: self.name.clone(),
+            passed: false,
+            extracted: 0,
+            missing: self.expected.clone(),
+            forbidden: Vec::new(),
+            unexpected: Vec::new(),
+            error: Some(error),
+        }
+    }
+}
+
+impl PromptTestReport {
+    pub fn new(model: &str, config: &str, cases: Vec<PromptTestOutcome>) -> Self {
+        let passed = cases.iter().filter(|c| c.passed).count();
+        Self {
+            model: model.to_string(),
+            config: config.to_string(),
+            passed,
+            failed: cases.len() - passed,
+            cases,
+        }
+    }
+}
+
+/// Whether `actual` is the triple `expected` describes
+fn matches(schema: &RdfSchema, expected: &RdfTriple, actual: &RdfTriple) -> bool {
+    let term = |expected: &str, actual: &str| expected == actual || schema.resolve_term(expected) == actual;
+    term(&expected.subject, &actual.subject)
+        && term(&expected.predicate, &actual.predicate)
+        && (expected.object.trim().eq_ignore_ascii_case(actual.object.trim())
+            || (expected.object.contains(':') && schema.resolve_term(&expected.object) == actual.object))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prompt_test_case() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("acme.yaml"), r#"
+text: "Acme Corporation is based in Berlin. Its CEO is Ana Lopez."
+expected:
+  - { subject: "http://example.org/resource/acme", predicate: "ex:basedIn", object: "http://example.org/resource/berlin" }
+  - { subject: "http://example.org/resource/acme", predicate: "hasCeo", object: "ana lopez" }
+forbidden:
+  - { subject: "http://example.org/resource/acme", predicate: "hasCeo", object: "Bob" }
+"#).unwrap();
+        fs::write(dir.path().join("notes.txt"), "not a case").unwrap();
+        fs::write(dir.path().join("empty.json"), r#"{"name": "no document"}"#).unwrap();
+        assert!(PromptTestCase::load_dir(dir.path()).is_err());
+        fs::remove_file(dir.path().join("empty.json")).unwrap();
+
+        let cases = PromptTestCase::load_dir(dir.path()).unwrap();
+        assert_eq!(cases.len(), 1);
+        let case = &cases[0];
+        assert_eq!(case.name, "acme");
+
+        let schema = RdfSchema::default();
+        let ex = |local: &str| format!("{}{}", schema.namespace, local);
+        let acme = "http://example.org/resource/acme".to_string();
+        let extracted = vec![
+            RdfTriple::new(acme.clone(), ex("basedIn"), "http://example.org/resource/berlin".into()),
+            RdfTriple::new(acme.clone(), ex("hasCeo"), " Ana Lopez".into()),
+            RdfTriple::new(acme.clone(), ex("hasName"), "Acme Corporation".into()),
+        ];
+        let outcome = case.evaluate(&schema, &extracted);
+        assert!(outcome.passed, "{:?}", outcome);
+        assert_eq!(outcome.unexpected.len(), 1);
+
+        // Missing, forbidden and (for exact cases) unexpected triples fail the case
+        let outcome = case.evaluate(&schema, &[extracted[0].clone(), RdfTriple::new(acme, ex("hasCeo"), "Bob".into())]);
+        assert!(!outcome.passed);
+        assert_eq!((outcome.missing.len(), outcome.forbidden.len()), (1, 1));
+        let exact = PromptTestCase { exact: true, ..case.clone() };
+        assert!(!exact.evaluate(&schema, &extracted).passed);
+    }
+}
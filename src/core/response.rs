@@ -0,0 +1,197 @@
+//! Recovery of JSON and triples from model responses that do not follow the
+//! requested format exactly.
+
+use serde_json::{Map, Value};
+
+/// Parse the JSON in a model response. Tries, in order: the whole response,
+/// the contents of a markdown code fence, and the complete JSON object or
+/// array in the text with the most triples, which skips commentary around it.
+/// A JSON string holding JSON is decoded as well.
+pub fn parse_json(content: &str) -> Option<Value> {
+    let content = content.trim();
+    let value = serde_json::from_str(content).ok()
+        .or_else(|| fenced_block(content).and_then(|block| serde_json::from_str(block).ok()))
+        .or_else(|| embedded_json(content))?;
+    Some(decode_string(value))
+}
+
+/// The objects describing triples in a parsed response: a top-level array, a
+/// `triples` field, an array under any other key at any depth, or a single
+/// triple object. Arrays and objects encoded as JSON strings are decoded.
+pub fn triple_objects(response: &Value) -> Vec<Map<String, Value>> {
+    let mut found = Vec::new();
+    collect_triples(&decode_string(response.clone()), &mut found);
+    found
+}
+
+/// Text of a triple field; numbers and booleans are written out
+pub fn field_text(triple: &Map<String, Value>, key: &str) -> Option<String> {
+    match triple.get(key)? {
+        Value::String(s) => Some(s.trim().to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn is_triple(object: &Map<String, Value>) -> bool {
+    ["subject", "predicate", "object"].iter().all(|key| object.contains_key(*key))
+}
+
+fn collect_triples(value: &Value, found: &mut Vec<Map<String, Value>>) {
+    match value {
+        Value::Object(object) if is_triple(object) => found.push(object.clone()),
+        Value::Object(object) => {
+            // Prefer the conventional key, so unrelated arrays are not searched
+            if let Some(triples) = object.get("triples") {
+                let before = found.len();
+                collect_triples(&decode_string(triples.clone()), found);
+                if found.len() > before {
+                    return;
+                }
+            }
+            for (key, nested) in object {
+                if key != "triples" {
+                    collect_triples(&decode_string(nested.clone()), found);
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                collect_triples(&decode_string(item.clone()), found);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Decode a string holding a JSON object or array, repeatedly for JSON
+/// encoded more than once
+fn decode_string(value: Value) -> Value {
+    let mut value = value;
+    while let Value::String(s) = &value {
+        let trimmed = s.trim();
+        if !trimmed.starts_with(['{', '[', '"']) {
+            break;
+        }
+        match serde_json::from_str::<Value>(trimmed) {
+            Ok(decoded) => value = decoded,
+            Err(_) => break,
+        }
+    }
+    value
+}
+
+/// Contents of the first markdown code fence, without its language tag
+fn fenced_block(content: &str) -> Option<&str> {
+    let start = content.find("```")? + 3;
+    let rest = &content[start..];
+    let body_start = rest.find('\n').map(|i| i + 1).unwrap_or(0);
+    let rest = &rest[body_start..];
+    let end = rest.find("```").unwrap_or(rest.len());
+    Some(rest[..end].trim())
+}
+
+/// The balanced JSON object or array in `content` that holds the most
+/// triples, or the largest one when none does, so a fragment mentioned in
+/// commentary (`see [1]`) does not win over the answer
+fn embedded_json(content: &str) -> Option<Value> {
+    let bytes = content.as_bytes();
+    let mut candidates: Vec<(usize, usize, Value)> = Vec::new();
+    let mut start = 0;
+    while start < bytes.len() {
+        if bytes[start] == b'{' || bytes[start] == b'[' {
+            if let Some(end) = balanced_end(&content[start..]) {
+                if let Ok(value) = serde_json::from_str(&content[start..start + end]) {
+                    candidates.push((triple_objects(&value).len(), end, value));
+                    // Fragments nested in this one are part of it
+                    start += end;
+                    continue;
+                }
+            }
+        }
+        start += 1;
+    }
+    // The first of equal candidates wins
+    candidates.into_iter()
+        .rev()
+        .max_by_key(|(triples, len, _)| (*triples, *len))
+        .map(|(_, _, value)| value)
+}
+
+/// Length of the object or array `text` starts with, skipping brackets inside strings
+fn balanced_end(text: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (i, c) in text.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' | '[' => depth += 1,
+            '}' | ']' => {
+                depth = depth.checked_sub(1)?;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_json_recovery() {
+        let expected = json!({"triples": [{"subject": "a", "predicate": "b", "object": "c [1]"}]});
+        let compact = expected.to_string();
+
+        assert_eq!(parse_json(&compact), Some(expected.clone()));
+        assert_eq!(parse_json(&format!("```json\n{}\n```", compact)), Some(expected.clone()));
+        assert_eq!(parse_json(&format!("Here are the triples:\n```\n{}\n```\nLet me know!", compact)), Some(expected.clone()));
+        assert_eq!(parse_json(&format!("Sure! {} I skipped {{uncertain}} facts.", compact)), Some(expected.clone()));
+        // String-encoded JSON, twice over
+        let encoded = Value::String(Value::String(compact.clone()).to_string()).to_string();
+        assert_eq!(parse_json(&encoded), Some(expected.clone()));
+        assert_eq!(parse_json("I could not find any facts."), None);
+        assert_eq!(parse_json("{\"triples\": [ unterminated"), None);
+
+        // The fragment holding triples wins over others in the commentary
+        let noted = format!("As noted in [1], the facts are {} and {{\"confidence\": \"high\"}}", compact);
+        assert_eq!(parse_json(&noted), Some(expected));
+        assert_eq!(parse_json("Options [1] or {\"a\": [1, 2]}"), Some(json!({"a": [1, 2]})));
+    }
+
+    #[test]
+    fn test_triple_objects() {
+        let triple = json!({"subject": "acme", "predicate": "hasCeo", "object": "Ana"});
+        let count = |response: Value| triple_objects(&response).len();
+
+        assert_eq!(count(json!([triple, triple])), 2);
+        assert_eq!(count(json!({"triples": [triple]})), 1);
+        assert_eq!(count(triple.clone()), 1);
+        assert_eq!(count(json!({"result": {"facts": [triple, {"note": "no triple"}]}})), 1);
+        assert_eq!(count(json!({"triples": triple.clone()})), 1);
+        assert_eq!(count(json!({"triples": json!([triple]).to_string()})), 1);
+        assert_eq!(count(json!({"triples": [], "other": [triple]})), 1);
+        assert_eq!(count(json!({"triples": [triple], "sources": [triple]})), 1);
+        assert_eq!(count(json!({"answer": "none"})), 0);
+
+        let numeric = json!({"subject": "acme", "predicate": "employees", "object": 1200});
+        assert_eq!(field_text(numeric.as_object().unwrap(), "object").as_deref(), Some("1200"));
+    }
+}
@@ -0,0 +1,173 @@
+use crate::error::{ErrorContext, Result};
+use async_nats::{Client, HeaderMap, Message, Subscriber};
+use std::path::{Path, PathBuf};
+use tokio_stream::StreamExt;
+use tracing::info;
+
+/// Header naming a document sent as the message payload; its extension
+/// selects the document handler
+pub const FILE_NAME_HEADER: &str = "File-Name";
+
+/// A document received from the message queue: the name it is reported
+/// under and the path or URL it is read from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReceivedDocument {
+    pub name: String,
+    pub source: String,
+    /// Reply subject of a message sent as a request, answered once the
+    /// document's triples are stored
+    pub reply: Option<String>,
+}
+
+/// Consumes documents published on a NATS subject
+///
+/// A message whose payload is a single http(s) URL is fetched from that
+/// URL; any other payload is the document itself, saved to a temporary
+/// file named after the `File-Name` header.
+pub struct DocumentSubscriber {
+    client: Client,
+    subscriber: Subscriber,
+    upload_dir: PathBuf,
+    received: usize,
+}
+
+impl DocumentSubscriber {
+    /// Subscribe to `subject`; subscribers sharing a `queue_group` split the
+    /// messages between them
+    pub async fn connect(url: &str, subject: &str, queue_group: Option<String>) -> Result<Self> {
+        let client = async_nats::connect(url).await
+            .server_context(|| format!("Failed to connect to NATS at {}", url))?;
+        let subscriber = match queue_group {
+            Some(group) => client.queue_subscribe(subject.to_string(), group).await,
+            None => client.subscribe(subject.to_string()).await,
+        }
+        .server_context(|| format!("Failed to subscribe to {}", subject))?;
+
+        info!("Consuming documents from NATS subject: {}", subject);
+
+        Ok(Self {
+            client,
+            subscriber,
+            upload_dir: std::env::temp_dir().join(format!("rdf-stream-{}", uuid::Uuid::new_v4())),
+            received: 0,
+        })
+    }
+
+    /// Wait for the next document; `None` once the subscription ends
+    pub async fn next_document(&mut self) -> Option<Result<ReceivedDocument>> {
+        let message = self.subscriber.next().await?;
+        self.received += 1;
+        Some(document_from_message(&message, &self.upload_dir.join(self.received.to_string())).await)
+    }
+
+    /// Publish serialized triples extracted from `source` to `subject`
+    pub async fn publish(&self, subject: &str, source: &str, content_type: &str, triples: usize, payload: String) -> Result<()> {
+        let mut headers = HeaderMap::new();
+        headers.insert("Source", source);
+        headers.insert("Content-Type", content_type);
+        headers.insert("Triple-Count", triples.to_string());
+
+        self.client.publish_with_headers(subject.to_string(), headers, payload.into()).await
+            .server_context(|| format!("Failed to publish triples to {}", subject))?;
+        self.client.flush().await
+            .server_context(|| format!("Failed to publish triples to {}", subject))
+    }
+
+    /// Acknowledge a document sent as a request, after its triples are
+    /// stored; senders that get no reply can send it again
+    pub async fn acknowledge(&self, document: &ReceivedDocument, triples: usize) -> Result<()> {
+        let Some(reply) = &document.reply else {
+            return Ok(());
+        };
+        self.client.publish(reply.clone(), triples.to_string().into()).await
+            .server_context(|| format!("Failed to acknowledge {}", document.name))?;
+        self.client.flush().await
+            .server_context(|| format!("Failed to acknowledge {}", document.name))
+    }
+
+    /// Remove the temporary copy of a document once it has been processed
+    pub async fn finish(&self, document: &ReceivedDocument) {
+        if let Some(dir) = Path::new(&document.source).parent().filter(|dir| dir.starts_with(&self.upload_dir)) {
+            tokio::fs::remove_dir_all(dir).await.ok();
+        }
+    }
+}
+
+async fn document_from_message(message: &Message, dir: &Path) -> Result<ReceivedDocument> {
+    let file_name = message.headers.as_ref()
+        .and_then(|headers| headers.get(FILE_NAME_HEADER))
+        .map(|value| value.as_str().to_string());
+    let reply = message.reply.as_ref().map(|subject| subject.to_string());
+
+    if file_name.is_none() {
+        if let Some(url) = payload_url(&message.payload) {
+            return Ok(ReceivedDocument { name: url.clone(), source: url, reply });
+        }
+    }
+
+    // Keep only the last path component so senders cannot pick the location
+    let name = file_name.as_deref()
+        .and_then(|name| name.rsplit(['/', '\\']).next())
+        .filter(|name| !name.is_empty() && *name != "." && *name != "..")
+        .unwrap_or("message.txt")
+        .to_string();
+    let path = dir.join(&name);
+    tokio::fs::create_dir_all(dir).await
+        .document_context(&name, || "Failed to store received document")?;
+    tokio::fs::write(&path, &message.payload).await
+        .document_context(&name, || "Failed to store received document")?;
+
+    Ok(ReceivedDocument { name, source: path.to_string_lossy().to_string(), reply })
+}
+
+/// The URL a payload consists of, if it is a single http(s) URL
+fn payload_url(payload: &[u8]) -> Option<String> {
+    let text = std::str::from_utf8(payload).ok()?.trim();
+    let is_url = (text.starts_with("http://") || text.starts_with("https://")) && !text.contains(char::is_whitespace);
+    is_url.then(|| text.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(payload: &str, file_name: Option<&str>) -> Message {
+        let headers = file_name.map(|name| {
+            let mut headers = HeaderMap::new();
+            headers.insert(FILE_NAME_HEADER, name);
+            headers
+        });
+        Message {
+            subject: "documents".into(),
+            reply: None,
+            payload: payload.to_string().into(),
+            headers,
+            status: None,
+            description: None,
+            length: payload.len(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_document_from_message() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let url = document_from_message(&message(" https://example.com/a.html\n", None), dir.path()).await.unwrap();
+        assert_eq!(url.source, "https://example.com/a.html");
+        assert_eq!(url.reply, None);
+
+        // Requests keep their reply subject for the acknowledgement
+        let mut request = message("https://example.com/b.html", None);
+        request.reply = Some("_INBOX.ack".into());
+        let requested = document_from_message(&request, dir.path()).await.unwrap();
+        assert_eq!(requested.reply.as_deref(), Some("_INBOX.ack"));
+
+        let upload = document_from_message(&message("Acme was founded in 1999.", Some("../notes.md")), dir.path()).await.unwrap();
+        assert_eq!(upload.name, "notes.md");
+        assert_eq!(upload.source, dir.path().join("notes.md").to_string_lossy());
+        assert_eq!(std::fs::read_to_string(&upload.source).unwrap(), "Acme was founded in 1999.");
+
+        let text = document_from_message(&message("see https://example.com for more", None), dir.path()).await.unwrap();
+        assert_eq!(text.name, "message.txt");
+    }
+}
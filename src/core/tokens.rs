@@ -0,0 +1,79 @@
+/// Approximate token counter for prompt budgeting
+///
+/// Mirrors how BPE tokenizers split text: punctuation and CJK characters are
+/// roughly one token each, while runs of letters and digits cost about one
+/// token per `chars_per_token` characters.
+#[derive(Debug, Clone, Copy)]
+pub struct TokenEstimator {
+    chars_per_token: f32,
+}
+
+impl Default for TokenEstimator {
+    fn default() -> Self {
+        Self { chars_per_token: Self::DEFAULT_CHARS_PER_TOKEN }
+    }
+}
+
+impl TokenEstimator {
+    pub const DEFAULT_CHARS_PER_TOKEN: f32 = 4.0;
+
+    pub fn new(chars_per_token: f32) -> Self {
+        Self { chars_per_token: chars_per_token.max(1.0) }
+    }
+
+    pub fn estimate(&self, text: &str) -> usize {
+        self.scan(text, usize::MAX).0
+    }
+
+    /// Longest prefix of `text` that fits in `max_tokens`, cut on a char boundary
+    pub fn truncate<'a>(&self, text: &'a str, max_tokens: usize) -> &'a str {
+        let (_, end) = self.scan(text, max_tokens);
+        &text[..end]
+    }
+
+    /// Count tokens up to `limit`; returns the count and the byte offset reached
+    fn scan(&self, text: &str, limit: usize) -> (usize, usize) {
+        let mut tokens = 0;
+        let mut word_chars = 0usize;
+
+        for (offset, c) in text.char_indices() {
+            let cost = if c.is_alphanumeric() && !is_cjk(c) {
+                word_chars += 1;
+                // A word costs a token for its first char and then every chars_per_token chars
+                if (word_chars - 1) as f32 % self.chars_per_token < 1.0 { 1 } else { 0 }
+            } else {
+                word_chars = 0;
+                if c.is_whitespace() { 0 } else { 1 }
+            };
+
+            if tokens + cost > limit {
+                return (tokens, offset);
+            }
+            tokens += cost;
+        }
+
+        (tokens, text.len())
+    }
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32, 0x3040..=0x30FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xAC00..=0xD7AF)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_and_truncate() {
+        let estimator = TokenEstimator::default();
+        assert_eq!(estimator.estimate(""), 0);
+        assert_eq!(estimator.estimate("Acme Corp."), 3);
+        assert_eq!(estimator.estimate("internationalization"), 5);
+
+        let text = "Acme Corp. hired Jane Doe as CTO.";
+        let truncated = estimator.truncate(text, 3);
+        assert_eq!(truncated, "Acme Corp. ");
+        assert_eq!(estimator.truncate(text, 1000), text);
+    }
+}
@@ -0,0 +1,92 @@
+use crate::error::{ErrorContext, Result};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+
+/// Watches a directory and yields documents once they stop changing
+pub struct DocumentWatcher {
+    _watcher: RecommendedWatcher,
+    events: mpsc::UnboundedReceiver<PathBuf>,
+    pending: HashMap<PathBuf, Instant>,
+    debounce: Duration,
+}
+
+impl DocumentWatcher {
+    pub fn new(dir: &Path, extensions: Vec<String>, debounce: Duration) -> Result<Self> {
+        let (sender, events) = mpsc::unbounded_channel();
+
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+            match res {
+                Ok(event) => {
+                    if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                        return;
+                    }
+                    for path in event.paths {
+                        let supported = path.extension()
+                            .and_then(|e| e.to_str())
+                            .is_some_and(|ext| extensions.iter().any(|known| known.eq_ignore_ascii_case(ext)));
+                        if supported && path.is_file() {
+                            let _ = sender.send(path);
+                        }
+                    }
+                }
+                Err(e) => warn!("Watch error: {}", e),
+            }
+        }).document_context(&dir.to_string_lossy(), || "Failed to create file watcher")?;
+
+        watcher.watch(dir, RecursiveMode::Recursive)
+            .document_context(&dir.to_string_lossy(), || "Failed to watch directory")?;
+
+        info!("Watching directory for new documents: {}", dir.display());
+
+        Ok(Self {
+            _watcher: watcher,
+            events,
+            pending: HashMap::new(),
+            debounce,
+        })
+    }
+
+    /// Wait for the next document that has been quiet for the debounce period
+    pub async fn next_document(&mut self) -> Option<PathBuf> {
+        loop {
+            if let Some(path) = self.take_settled() {
+                return Some(path);
+            }
+
+            if self.pending.is_empty() {
+                let path = self.events.recv().await?;
+                self.touch(path);
+            } else {
+                tokio::select! {
+                    event = self.events.recv() => match event {
+                        Some(path) => self.touch(path),
+                        None => {
+                            // Watcher closed: flush whatever is still pending
+                            let path = self.pending.keys().next().cloned()?;
+                            self.pending.remove(&path);
+                            return Some(path);
+                        }
+                    },
+                    _ = tokio::time::sleep(self.debounce / 4) => {}
+                }
+            }
+        }
+    }
+
+    fn touch(&mut self, path: PathBuf) {
+        debug!("Change detected: {}", path.display());
+        self.pending.insert(path, Instant::now());
+    }
+
+    fn take_settled(&mut self) -> Option<PathBuf> {
+        let settled = self.pending.iter()
+            .find(|(_, last_change)| last_change.elapsed() >= self.debounce)
+            .map(|(path, _)| path.clone())?;
+        self.pending.remove(&settled);
+        Some(settled)
+    }
+}
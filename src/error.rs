@@ -0,0 +1,201 @@
+use crate::core::llm_client::Usage;
+
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Library error, split by the part of the pipeline that failed
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("configuration error: {message}")]
+    Config {
+        message: String,
+        #[source]
+        source: Option<BoxError>,
+    },
+
+    #[error("document error ({document}): {message}")]
+    Document {
+        document: String,
+        message: String,
+        #[source]
+        source: Option<BoxError>,
+    },
+
+    #[error("LLM error{}: {message}", status.map(|s| format!(" (HTTP {})", s)).unwrap_or_default())]
+    Llm {
+        message: String,
+        /// HTTP status returned by the server, if the request got that far
+        status: Option<u16>,
+        /// Tokens consumed by the failed call, when the server reported them
+        usage: Option<Usage>,
+        #[source]
+        source: Option<BoxError>,
+    },
+
+    #[error("knowledge graph storage error: {message}")]
+    Storage {
+        message: String,
+        #[source]
+        source: Option<BoxError>,
+    },
+
+    /// A SPARQL query that cannot be parsed, is not supported, or exceeds
+    /// the query limits; the query, not the store, has to change
+    #[error("query error: {message}")]
+    Query {
+        message: String,
+        #[source]
+        source: Option<BoxError>,
+    },
+
+    #[error("template error: {message}")]
+    Template {
+        message: String,
+        #[source]
+        source: Option<BoxError>,
+    },
+
+    #[error("server error: {message}")]
+    Server {
+        message: String,
+        #[source]
+        source: Option<BoxError>,
+    },
+}
+
+impl Error {
+    pub fn config(message: impl Into<String>) -> Self {
+        Self::Config { message: message.into(), source: None }
+    }
+
+    pub fn document(document: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::Document { document: document.into(), message: message.into(), source: None }
+    }
+
+    pub fn llm(message: impl Into<String>) -> Self {
+        Self::Llm { message: message.into(), status: None, usage: None, source: None }
+    }
+
+    pub fn storage(message: impl Into<String>) -> Self {
+        Self::Storage { message: message.into(), source: None }
+    }
+
+    pub fn query(message: impl Into<String>) -> Self {
+        Self::Query { message: message.into(), source: None }
+    }
+
+    pub fn template(message: impl Into<String>) -> Self {
+        Self::Template { message: message.into(), source: None }
+    }
+
+    pub fn server(message: impl Into<String>) -> Self {
+        Self::Server { message: message.into(), source: None }
+    }
+
+    /// Whether retrying the same call could succeed: timeouts, connection
+    /// failures, rate limiting and server-side LLM errors
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Llm { status: Some(status), .. } => *status == 429 || *status >= 500,
+            Self::Llm { status: None, source: Some(source), .. } => source
+                .downcast_ref::<reqwest::Error>()
+                .is_some_and(|e| e.is_timeout() || e.is_connect()),
+            _ => false,
+        }
+    }
+}
+
+/// Attach a categorized message to a foreign error or a missing value,
+/// similar to anyhow's `with_context`
+pub trait ErrorContext<T> {
+    fn config_context<S: Into<String>, F: FnOnce() -> S>(self, message: F) -> Result<T>;
+    fn document_context<S: Into<String>, F: FnOnce() -> S>(self, document: &str, message: F) -> Result<T>;
+    fn llm_context<S: Into<String>, F: FnOnce() -> S>(self, message: F) -> Result<T>;
+    fn storage_context<S: Into<String>, F: FnOnce() -> S>(self, message: F) -> Result<T>;
+    fn query_context<S: Into<String>, F: FnOnce() -> S>(self, message: F) -> Result<T>;
+    fn template_context<S: Into<String>, F: FnOnce() -> S>(self, message: F) -> Result<T>;
+    fn server_context<S: Into<String>, F: FnOnce() -> S>(self, message: F) -> Result<T>;
+}
+
+impl<T, E> ErrorContext<T> for std::result::Result<T, E>
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    fn config_context<S: Into<String>, F: FnOnce() -> S>(self, message: F) -> Result<T> {
+        self.map_err(|e| Error::Config { message: message().into(), source: Some(Box::new(e)) })
+    }
+
+    fn document_context<S: Into<String>, F: FnOnce() -> S>(self, document: &str, message: F) -> Result<T> {
+        self.map_err(|e| Error::Document {
+            document: document.to_string(),
+            message: message().into(),
+            source: Some(Box::new(e)),
+        })
+    }
+
+    fn llm_context<S: Into<String>, F: FnOnce() -> S>(self, message: F) -> Result<T> {
+        self.map_err(|e| Error::Llm { message: message().into(), status: None, usage: None, source: Some(Box::new(e)) })
+    }
+
+    fn storage_context<S: Into<String>, F: FnOnce() -> S>(self, message: F) -> Result<T> {
+        self.map_err(|e| Error::Storage { message: message().into(), source: Some(Box::new(e)) })
+    }
+
+    fn query_context<S: Into<String>, F: FnOnce() -> S>(self, message: F) -> Result<T> {
+        self.map_err(|e| Error::Query { message: message().into(), source: Some(Box::new(e)) })
+    }
+
+    fn template_context<S: Into<String>, F: FnOnce() -> S>(self, message: F) -> Result<T> {
+        self.map_err(|e| Error::Template { message: message().into(), source: Some(Box::new(e)) })
+    }
+
+    fn server_context<S: Into<String>, F: FnOnce() -> S>(self, message: F) -> Result<T> {
+        self.map_err(|e| Error::Server { message: message().into(), source: Some(Box::new(e)) })
+    }
+}
+
+impl<T> ErrorContext<T> for Option<T> {
+    fn config_context<S: Into<String>, F: FnOnce() -> S>(self, message: F) -> Result<T> {
+        self.ok_or_else(|| Error::config(message()))
+    }
+
+    fn document_context<S: Into<String>, F: FnOnce() -> S>(self, document: &str, message: F) -> Result<T> {
+        self.ok_or_else(|| Error::document(document, message()))
+    }
+
+    fn llm_context<S: Into<String>, F: FnOnce() -> S>(self, message: F) -> Result<T> {
+        self.ok_or_else(|| Error::llm(message()))
+    }
+
+    fn storage_context<S: Into<String>, F: FnOnce() -> S>(self, message: F) -> Result<T> {
+        self.ok_or_else(|| Error::storage(message()))
+    }
+
+    fn query_context<S: Into<String>, F: FnOnce() -> S>(self, message: F) -> Result<T> {
+        self.ok_or_else(|| Error::query(message()))
+    }
+
+    fn template_context<S: Into<String>, F: FnOnce() -> S>(self, message: F) -> Result<T> {
+        self.ok_or_else(|| Error::template(message()))
+    }
+
+    fn server_context<S: Into<String>, F: FnOnce() -> S>(self, message: F) -> Result<T> {
+        self.ok_or_else(|| Error::server(message()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retryable_errors() {
+        let rate_limited = Error::Llm { message: "slow down".into(), status: Some(429), usage: None, source: None };
+        let bad_request = Error::Llm { message: "bad".into(), status: Some(400), usage: None, source: None };
+        assert!(rate_limited.is_retryable());
+        assert!(!bad_request.is_retryable());
+        assert!(!Error::config("missing field").is_retryable());
+        assert_eq!(rate_limited.to_string(), "LLM error (HTTP 429): slow down");
+    }
+}
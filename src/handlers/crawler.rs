@@ -0,0 +1,217 @@
+use crate::config::RetryPolicy;
+use crate::error::{Error, ErrorContext, Result};
+use super::fetch::Fetcher;
+use regex::Regex;
+use scraper::{Html, Selector};
+use std::collections::{HashSet, VecDeque};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+use url::Url;
+
+/// How far and where `Crawler` follows links from a seed URL
+#[derive(Debug, Clone)]
+pub struct CrawlOptions {
+    /// Link hops to follow from the seed; 0 fetches only the seed
+    pub depth: usize,
+    /// Only follow links on the seed's host
+    pub same_domain: bool,
+    /// When set, only URLs matching one of these are followed and returned
+    pub include: Vec<Regex>,
+    /// URLs matching any of these are skipped
+    pub exclude: Vec<Regex>,
+    /// Also read the seed host's /sitemap.xml; listed pages count as one hop
+    /// from the seed
+    pub sitemap: bool,
+    /// Minimum pause between two requests to the same host
+    pub delay: Duration,
+    /// Stop once this many pages are found
+    pub max_pages: usize,
+    pub retry: RetryPolicy,
+}
+
+impl Default for CrawlOptions {
+    fn default() -> Self {
+        Self {
+            depth: 1,
+            same_domain: true,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            sitemap: false,
+            delay: Duration::from_millis(500),
+            max_pages: 50,
+            retry: RetryPolicy::none(),
+        }
+    }
+}
+
+/// Expands seed URLs into the pages reachable from them, breadth first
+pub struct Crawler {
+    fetcher: Fetcher,
+    options: CrawlOptions,
+}
+
+impl Crawler {
+    pub fn new(options: CrawlOptions) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .user_agent(concat!("rdf_knowledge_extractor/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .document_context("crawler", || "Failed to create HTTP client")?;
+
+        let fetcher = Fetcher::new(client, options.delay, options.retry.clone());
+        Ok(Self { fetcher, options })
+    }
+
+    /// URLs of the pages found from `seed`, starting with the seed itself
+    pub async fn crawl(&self, seed: &str) -> Result<Vec<String>> {
+        let seed = Url::parse(seed).document_context(seed, || "Invalid seed URL")?;
+        let mut pages = vec![seed.to_string()];
+        let mut seen = HashSet::from([seed.to_string()]);
+        let mut queue = VecDeque::from([(seed.clone(), 0)]);
+        let mut requests = 0;
+
+        if self.options.sitemap {
+            for url in self.sitemap_urls(&seed, &mut requests).await {
+                if pages.len() < self.options.max_pages && self.allowed(&seed, &url) && seen.insert(url.to_string()) {
+                    pages.push(url.to_string());
+                    queue.push_back((url, 1));
+                }
+            }
+        }
+
+        while let Some((url, depth)) = queue.pop_front() {
+            if depth >= self.options.depth || pages.len() >= self.options.max_pages {
+                continue;
+            }
+
+            let html = match self.fetch(&url, &mut requests).await {
+                Ok(html) => html,
+                Err(e) => {
+                    warn!("Skipping links of {}: {}", url, e);
+                    continue;
+                }
+            };
+
+            for link in links(&html, &url) {
+                if pages.len() >= self.options.max_pages {
+                    break;
+                }
+                if self.allowed(&seed, &link) && seen.insert(link.to_string()) {
+                    debug!("Found {} at depth {}", link, depth + 1);
+                    pages.push(link.to_string());
+                    queue.push_back((link, depth + 1));
+                }
+            }
+        }
+
+        info!("Crawled {}: {} page(s) in {} request(s)", seed, pages.len(), requests);
+        Ok(pages)
+    }
+
+    fn allowed(&self, seed: &Url, url: &Url) -> bool {
+        let text = url.as_str();
+        (!self.options.same_domain || url.host_str() == seed.host_str())
+            && (self.options.include.is_empty() || self.options.include.iter().any(|re| re.is_match(text)))
+            && !self.options.exclude.iter().any(|re| re.is_match(text))
+    }
+
+    async fn fetch(&self, url: &Url, requests: &mut usize) -> Result<String> {
+        *requests += 1;
+        let response = self.fetcher.get(url.as_str()).await?;
+        if !response.status().is_success() {
+            return Err(Error::document(url.as_str(), format!("HTTP {}", response.status())));
+        }
+        response.text().await.document_context(url.as_str(), || "Failed to read response body")
+    }
+
+    /// Page URLs listed in the seed host's sitemap, following one level of
+    /// sitemap index
+    async fn sitemap_urls(&self, seed: &Url, requests: &mut usize) -> Vec<Url> {
+        let Ok(sitemap) = seed.join("/sitemap.xml") else {
+            return Vec::new();
+        };
+
+        let mut urls = Vec::new();
+        let mut sitemaps = vec![sitemap];
+        let mut nested = true;
+        while let Some(sitemap) = sitemaps.pop() {
+            let xml = match self.fetch(&sitemap, requests).await {
+                Ok(xml) => xml,
+                Err(e) => {
+                    warn!("Could not read sitemap {}: {}", sitemap, e);
+                    continue;
+                }
+            };
+            for url in sitemap_locations(&xml) {
+                if nested && url.path().ends_with(".xml") {
+                    sitemaps.push(url);
+                } else {
+                    urls.push(url);
+                }
+            }
+            nested = false;
+        }
+        urls
+    }
+}
+
+/// Absolute http(s) links of a page, without fragments
+fn links(html: &str, base: &Url) -> Vec<Url> {
+    let selector = Selector::parse("a[href]").expect("valid selector");
+    Html::parse_document(html)
+        .select(&selector)
+        .filter_map(|a| a.value().attr("href"))
+        .filter_map(|href| base.join(href).ok())
+        .filter(|url| matches!(url.scheme(), "http" | "https"))
+        .map(|mut url| {
+            url.set_fragment(None);
+            url
+        })
+        .collect()
+}
+
+fn sitemap_locations(xml: &str) -> Vec<Url> {
+    let loc = Regex::new(r"<loc>\s*([^<\s]+)\s*</loc>").expect("valid regex");
+    loc.captures_iter(xml)
+        .filter_map(|c| Url::parse(&html_escape::decode_html_entities(&c[1])).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_crawl() {
+        let mut server = mockito::Server::new_async().await;
+        let base = server.url();
+        let page = |body: String| format!("<html><body>{}</body></html>", body);
+
+        server.mock("GET", "/").with_body(page(format!(
+            r#"<a href="/about">About</a> <a href="{}/team#top">Team</a> <a href="/files/report.pdf">PDF</a>
+               <a href="https://elsewhere.example/">Other</a> <a href="mailto:info@example.com">Mail</a>"#,
+            base
+        ))).create_async().await;
+        server.mock("GET", "/about").with_body(page(r#"<a href="/history">History</a>"#.to_string())).create_async().await;
+        server.mock("GET", "/team").with_body(page(r#"<a href="/">Home</a>"#.to_string())).create_async().await;
+        server.mock("GET", "/sitemap.xml").with_body(format!(
+            "<urlset><url><loc>{0}/news</loc></url><url><loc>{0}/about</loc></url></urlset>",
+            base
+        )).create_async().await;
+
+        let crawler = Crawler::new(CrawlOptions {
+            depth: 2,
+            exclude: vec![Regex::new(r"\.pdf$").unwrap()],
+            sitemap: true,
+            delay: Duration::ZERO,
+            ..CrawlOptions::default()
+        }).unwrap();
+
+        let pages = crawler.crawl(&format!("{}/", base)).await.unwrap();
+        let paths: Vec<&str> = pages.iter().map(|p| &p[base.len()..]).collect();
+        assert_eq!(paths, ["/", "/news", "/about", "/team", "/history"]);
+
+        let shallow = Crawler::new(CrawlOptions { depth: 0, delay: Duration::ZERO, ..CrawlOptions::default() }).unwrap();
+        assert_eq!(shallow.crawl(&base).await.unwrap().len(), 1);
+    }
+}
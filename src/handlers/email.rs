@@ -0,0 +1,240 @@
+//! Email messages, from RFC 822 (`.eml`) or Outlook (`.msg`) files
+//!
+//! The text given to extraction is the message's own body: quoted replies,
+//! forwarded or replied-to messages below it, and the signature are
+//! stripped, so a thread does not extract the same statements once per
+//! reply. The headers become document metadata.
+
+use super::DocumentHandler;
+use crate::error::{Error, ErrorContext, Result};
+use async_trait::async_trait;
+use mail_parser::{Address, MessageParser};
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+
+/// Signature of an OLE compound file, the container of `.msg` files
+const CFB_MAGIC: [u8; 8] = [0xd0, 0xcf, 0x11, 0xe0, 0xa1, 0xb1, 0x1a, 0xe1];
+
+pub struct EmailHandler;
+
+#[async_trait]
+impl DocumentHandler for EmailHandler {
+    async fn extract_text(&self, source: &str) -> Result<String> {
+        let email = read_email(source).await?;
+        Ok(strip_quoted(&email.body))
+    }
+
+    async fn get_metadata(&self, source: &str) -> Result<HashMap<String, String>> {
+        let email = read_email(source).await?;
+        let mut metadata = HashMap::new();
+        metadata.insert("source".to_string(), source.to_string());
+        metadata.insert("type".to_string(), "email".to_string());
+
+        let headers = [
+            ("from", email.from),
+            ("to", email.to),
+            ("cc", email.cc),
+            ("date", email.date),
+            ("subject", email.subject),
+            ("message_id", email.message_id),
+        ];
+        for (key, value) in headers {
+            if let Some(value) = value.filter(|v| !v.is_empty()) {
+                metadata.insert(key.to_string(), value);
+            }
+        }
+
+        if let Ok(meta) = tokio::fs::metadata(source).await {
+            metadata.insert("size".to_string(), meta.len().to_string());
+        }
+
+        Ok(metadata)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Email {
+    from: Option<String>,
+    to: Option<String>,
+    cc: Option<String>,
+    date: Option<String>,
+    subject: Option<String>,
+    message_id: Option<String>,
+    body: String,
+}
+
+async fn read_email(source: &str) -> Result<Email> {
+    let bytes = tokio::fs::read(source).await
+        .document_context(source, || "Failed to read email file")?;
+
+    if bytes.starts_with(&CFB_MAGIC) {
+        parse_msg(bytes).document_context(source, || "Failed to read Outlook message")
+    } else {
+        parse_eml(&bytes).ok_or_else(|| Error::document(source, "Not an RFC 822 email message"))
+    }
+}
+
+/// Headers and body of an RFC 822 message; the body is the first text part,
+/// or the first HTML part converted to text
+fn parse_eml(bytes: &[u8]) -> Option<Email> {
+    let message = MessageParser::default().parse(bytes)?;
+    Some(Email {
+        from: message.from().map(format_addresses),
+        to: message.to().map(format_addresses),
+        cc: message.cc().map(format_addresses),
+        date: message.date().map(|date| date.to_rfc3339()),
+        subject: message.subject().map(str::to_string),
+        message_id: message.message_id().map(str::to_string),
+        body: message.body_text(0).map(|body| body.into_owned()).unwrap_or_default(),
+    })
+}
+
+/// `Name <address>` for each address, comma-separated
+fn format_addresses(address: &Address) -> String {
+    address.iter()
+        .map(|addr| match (addr.name(), addr.address()) {
+            (Some(name), Some(address)) => format!("{} <{}>", name, address),
+            (name, address) => name.or(address).unwrap_or_default().to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Headers and plain-text body of an Outlook message
+///
+/// The internet headers the message was received with, when Outlook kept
+/// them, give the addresses and date; otherwise the display names Outlook
+/// stores for the sender and recipients are used.
+fn parse_msg(bytes: Vec<u8>) -> std::io::Result<Email> {
+    let mut file = cfb::CompoundFile::open(Cursor::new(bytes))?;
+    let mut property = |id: u16| msg_property(&mut file, id);
+
+    let mut email = property(0x007d)
+        .and_then(|headers| parse_eml(format!("{}\r\n\r\n", headers.trim_end()).as_bytes()))
+        .unwrap_or_default();
+
+    email.subject = email.subject.or_else(|| property(0x0037));
+    email.from = email.from.or_else(|| {
+        match (property(0x0c1a), property(0x5d01).or_else(|| property(0x0c1f))) {
+            (Some(name), Some(address)) => Some(format!("{} <{}>", name, address)),
+            (name, address) => name.or(address),
+        }
+    });
+    email.to = email.to.or_else(|| property(0x0e04));
+    email.cc = email.cc.or_else(|| property(0x0e03));
+    email.message_id = email.message_id.or_else(|| property(0x1035));
+    email.body = property(0x1000).unwrap_or_default();
+    Ok(email)
+}
+
+/// A string property of a `.msg` file, stored as UTF-16 (`001F`) or in the
+/// message's 8-bit code page (`001E`)
+fn msg_property<F: Read + std::io::Seek>(file: &mut cfb::CompoundFile<F>, id: u16) -> Option<String> {
+    let mut read = |kind: &str| {
+        let mut stream = file.open_stream(format!("/__substg1.0_{:04X}{}", id, kind)).ok()?;
+        let mut data = Vec::new();
+        stream.read_to_end(&mut data).ok()?;
+        Some(data)
+    };
+
+    let value = if let Some(data) = read("001F") {
+        let units: Vec<u16> = data.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+        String::from_utf16_lossy(&units)
+    } else {
+        let data = read("001E")?;
+        encoding_rs::WINDOWS_1252.decode(&data).0.into_owned()
+    };
+    let value = value.trim_end_matches('\0').trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+/// The body without quoted lines (`> ...`), without everything from the
+/// first reply or forward header ("On ... wrote:", "-----Original
+/// Message-----", Outlook's "From: ... Sent: ..." block), and without the
+/// signature after a `-- ` line
+fn strip_quoted(body: &str) -> String {
+    let lines: Vec<&str> = body.lines().collect();
+    let mut kept = Vec::new();
+
+    for (i, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        let next = lines.get(i + 1).map(|l| l.trim()).unwrap_or_default();
+
+        let reply_header = trimmed.starts_with("On ") && trimmed.ends_with("wrote:")
+            || trimmed.contains("Original Message-----")
+            || trimmed.contains("Forwarded message -----")
+            || trimmed.len() >= 10 && trimmed.chars().all(|c| c == '_') && next.starts_with("From:")
+            || trimmed.starts_with("From:") && (next.starts_with("Sent:") || next.starts_with("Date:"));
+        if reply_header || trimmed == "--" {
+            break;
+        }
+        if !trimmed.starts_with('>') {
+            kept.push(line.trim_end());
+        }
+    }
+
+    let mut text = kept.join("\n");
+    text.truncate(text.trim_end().len());
+    text.trim_start_matches('\n').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[tokio::test]
+    async fn test_email_handler() {
+        let dir = tempfile::tempdir().unwrap();
+        let eml = dir.path().join("thread.eml");
+        std::fs::write(&eml, concat!(
+            "From: Ana Ruiz <ana@acme.example>\r\n",
+            "To: Bo Chen <bo@globex.example>, sales@acme.example\r\n",
+            "Subject: =?UTF-8?Q?Renewal_=E2=80=93_Globex?=\r\n",
+            "Date: Tue, 3 Mar 2026 10:15:00 +0100\r\n",
+            "Message-ID: <r1@acme.example>\r\n",
+            "Content-Type: text/plain; charset=utf-8\r\n",
+            "Content-Transfer-Encoding: quoted-printable\r\n",
+            "\r\n",
+            "Hi Bo,\r\n",
+            "\r\n",
+            "Globex signed the renewal with Acme for 2026 =E2=80=93 thanks!\r\n",
+            "> Can you confirm the renewal?\r\n",
+            "\r\n",
+            "-- \r\n",
+            "Ana Ruiz, Account Manager\r\n",
+            "\r\n",
+            "On Mon, 2 Mar 2026, Bo Chen <bo@globex.example> wrote:\r\n",
+            "> Globex is reviewing the contract.\r\n",
+        )).unwrap();
+        let eml = eml.to_str().unwrap();
+
+        let text = EmailHandler.extract_text(eml).await.unwrap();
+        assert_eq!(text, "Hi Bo,\n\nGlobex signed the renewal with Acme for 2026 – thanks!");
+        let metadata = EmailHandler.get_metadata(eml).await.unwrap();
+        assert_eq!(metadata["from"], "Ana Ruiz <ana@acme.example>");
+        assert_eq!(metadata["to"], "Bo Chen <bo@globex.example>, sales@acme.example");
+        assert_eq!(metadata["subject"], "Renewal – Globex");
+        assert_eq!(metadata["date"], "2026-03-03T10:15:00+01:00");
+        assert_eq!(metadata["message_id"], "r1@acme.example");
+
+        // An Outlook message without internet headers
+        let msg = dir.path().join("reply.msg");
+        let mut file = cfb::create(&msg).unwrap();
+        let mut property = |id: &str, value: &str| {
+            let utf16: Vec<u8> = value.encode_utf16().flat_map(u16::to_le_bytes).collect();
+            file.create_stream(format!("/__substg1.0_{}001F", id)).unwrap().write_all(&utf16).unwrap();
+        };
+        property("0037", "RE: Renewal");
+        property("0C1A", "Bo Chen");
+        property("0E04", "Ana Ruiz");
+        property("1000", "Confirmed.\r\n\r\n________________\r\nFrom: Ana Ruiz\r\nSent: Tuesday\r\nHi Bo,");
+        drop(file);
+        let msg = msg.to_str().unwrap();
+
+        assert_eq!(EmailHandler.extract_text(msg).await.unwrap(), "Confirmed.");
+        let metadata = EmailHandler.get_metadata(msg).await.unwrap();
+        assert_eq!((metadata["subject"].as_str(), metadata["from"].as_str(), metadata["to"].as_str()), ("RE: Renewal", "Bo Chen", "Ana Ruiz"));
+        assert!(!metadata.contains_key("date"));
+    }
+}
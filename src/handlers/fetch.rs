@@ -0,0 +1,287 @@
+use crate::config::RetryPolicy;
+use crate::error::{Error, ErrorContext, Result};
+use reqwest::{header, Response, StatusCode};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+use tracing::warn;
+use url::Url;
+
+/// User agent of document fetches
+pub(crate) const USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36";
+
+/// Timeout of a single document fetch
+pub(crate) const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Redirects followed by a guarded fetch
+const MAX_REDIRECTS: usize = 10;
+
+/// HTTP GETs spaced at least `delay` apart per host, retried by a
+/// `RetryPolicy`; clones share the per-host schedule
+#[derive(Clone)]
+pub struct Fetcher {
+    client: reqwest::Client,
+    delay: Duration,
+    retry: RetryPolicy,
+    next_slot: Arc<Mutex<HashMap<String, Instant>>>,
+    guard: Option<AddressGuard>,
+}
+
+impl Fetcher {
+    pub fn new(client: reqwest::Client, delay: Duration, retry: RetryPolicy) -> Self {
+        Self { client, delay, retry, next_slot: Arc::new(Mutex::new(HashMap::new())), guard: None }
+    }
+
+    /// Only fetch from public addresses; see [`AddressGuard`]
+    pub fn with_guard(mut self, guard: AddressGuard) -> Self {
+        self.guard = Some(guard);
+        self
+    }
+
+    /// Response to GET `url`, after retrying failed attempts; the last
+    /// response is returned whatever its status
+    pub async fn get(&self, url: &str) -> Result<Response> {
+        let host = Url::parse(url).ok()
+            .and_then(|u| u.host_str().map(str::to_string))
+            .unwrap_or_default();
+        let mut attempt = 1;
+        loop {
+            self.wait_for(&host).await;
+            let response = match &self.guard {
+                Some(guard) => self.send_guarded(guard, url).await?,
+                None => self.client.get(url).send().await,
+            };
+            let retry_after = match &response {
+                Ok(response) if !retryable_status(response.status()) => None,
+                Ok(response) => Some(retry_after(response)),
+                Err(e) if e.is_timeout() || e.is_connect() || e.is_request() => Some(None),
+                Err(_) => None,
+            };
+
+            match retry_after {
+                Some(retry_after) if attempt < self.retry.attempts => {
+                    // A server asking for a longer wait than the policy allows gets the maximum
+                    let max_wait = Duration::from_millis(self.retry.max_backoff_ms);
+                    let wait = retry_after.map_or_else(|| self.backoff(attempt), |wait| wait.min(max_wait));
+                    match &response {
+                        Ok(response) => warn!("{} returned HTTP {}; retrying in {:?}", url, response.status(), wait),
+                        Err(e) => warn!("Failed to fetch {}: {}; retrying in {:?}", url, e, wait),
+                    }
+                    tokio::time::sleep(wait).await;
+                    attempt += 1;
+                }
+                _ => return response.document_context(url, || "Failed to fetch URL"),
+            }
+        }
+    }
+
+    /// GET `url`, following redirects by hand so every hop is checked by
+    /// `guard`, and connecting to the address it checked rather than
+    /// resolving the host again. The outer error is a refused URL, which is
+    /// not retried.
+    async fn send_guarded(&self, guard: &AddressGuard, url: &str) -> Result<reqwest::Result<Response>> {
+        let mut url = Url::parse(url).document_context(url, || "Invalid URL")?;
+        for _ in 0..=MAX_REDIRECTS {
+            let address = guard.check(&url).await?;
+            let mut builder = reqwest::Client::builder()
+                .timeout(FETCH_TIMEOUT)
+                .user_agent(USER_AGENT)
+                .redirect(reqwest::redirect::Policy::none());
+            if let (Some(domain), Some(address)) = (url.domain(), address) {
+                builder = builder.resolve(domain, address);
+            }
+            let client = builder.build().document_context(url.as_str(), || "Failed to create HTTP client")?;
+
+            let response = match client.get(url.clone()).send().await {
+                Ok(response) => response,
+                Err(e) => return Ok(Err(e)),
+            };
+            let next = response.status().is_redirection()
+                .then(|| response.headers().get(header::LOCATION))
+                .flatten()
+                .and_then(|location| location.to_str().ok())
+                .and_then(|location| url.join(location).ok());
+            match next {
+                Some(next) => url = next,
+                None => return Ok(Ok(response)),
+            }
+        }
+        Err(Error::document(url.as_str(), format!("Stopped after {} redirects", MAX_REDIRECTS)))
+    }
+
+    /// Wait for this host's next free slot and reserve the one after it
+    async fn wait_for(&self, host: &str) {
+        let slot = {
+            let mut next_slot = self.next_slot.lock().await;
+            let now = Instant::now();
+            let slot = next_slot.get(host).copied().filter(|slot| *slot > now).unwrap_or(now);
+            next_slot.insert(host.to_string(), slot + self.delay);
+            slot
+        };
+        tokio::time::sleep_until(slot).await;
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u64.saturating_pow(attempt.saturating_sub(1));
+        Duration::from_millis(self.retry.backoff_ms.saturating_mul(factor).min(self.retry.max_backoff_ms))
+    }
+}
+
+/// Refuses URLs whose host resolves to a loopback, private, link-local or
+/// otherwise internal address, unless the host is allowed by name, so that
+/// fetches on behalf of clients cannot reach services behind the server
+#[derive(Debug, Clone, Default)]
+pub struct AddressGuard {
+    allowed_hosts: Arc<Vec<String>>,
+}
+
+impl AddressGuard {
+    pub fn new(allowed_hosts: &[String]) -> Self {
+        Self { allowed_hosts: Arc::new(allowed_hosts.iter().map(|host| host.to_ascii_lowercase()).collect()) }
+    }
+
+    /// Address to connect to for `url`; `None` for an allowed host, which
+    /// is resolved as usual
+    pub async fn check(&self, url: &Url) -> Result<Option<SocketAddr>> {
+        let host = match url.host() {
+            Some(url::Host::Domain(domain)) => domain.to_ascii_lowercase(),
+            Some(url::Host::Ipv4(ip)) => ip.to_string(),
+            Some(url::Host::Ipv6(ip)) => ip.to_string(),
+            None => return Err(Error::document(url.as_str(), "URL without a host")),
+        };
+        if self.allowed_hosts.contains(&host) {
+            return Ok(None);
+        }
+
+        let port = url.port_or_known_default().unwrap_or(80);
+        let addresses: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), port)).await
+            .document_context(url.as_str(), || format!("Cannot resolve {}", host))?
+            .collect();
+        if let Some(address) = addresses.iter().find(|address| !is_public(&address.ip())) {
+            return Err(Error::document(url.as_str(), format!("{} resolves to the internal address {}", host, address.ip())));
+        }
+        addresses.first().copied()
+            .map(Some)
+            .ok_or_else(|| Error::document(url.as_str(), format!("Cannot resolve {}", host)))
+    }
+}
+
+/// Whether `address` is reachable on the public internet
+pub fn is_public(address: &IpAddr) -> bool {
+    match address {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(mapped) => is_public_v4(&mapped),
+            None => is_public_v6(ip),
+        },
+    }
+}
+
+fn is_public_v4(ip: &Ipv4Addr) -> bool {
+    let [first, second, ..] = ip.octets();
+    !(ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || ip.is_documentation()
+        || first == 0
+        // Carrier-grade NAT, 100.64.0.0/10
+        || (first == 100 && (64..128).contains(&second)))
+}
+
+fn is_public_v6(ip: &Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    !(ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        // Unique local, fc00::/7
+        || (first & 0xfe00) == 0xfc00
+        // Link-local, fe80::/10
+        || (first & 0xffc0) == 0xfe80)
+}
+
+fn retryable_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// Wait requested by a `Retry-After: <seconds>` header
+fn retry_after(response: &Response) -> Option<Duration> {
+    response.headers().get(header::RETRY_AFTER)?
+        .to_str().ok()?
+        .trim().parse().ok()
+        .map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_retry_and_host_delay() {
+        let mut server = mockito::Server::new_async().await;
+        let flaky = server.mock("GET", "/flaky").with_status(503).expect(2).create_async().await;
+        let busy = server.mock("GET", "/busy").with_status(429).with_header("Retry-After", "3600").expect(2).create_async().await;
+        let missing = server.mock("GET", "/missing").with_status(404).expect(1).create_async().await;
+        server.mock("GET", "/page").with_body("ok").create_async().await;
+
+        let retry = RetryPolicy { attempts: 2, backoff_ms: 10, max_backoff_ms: 10 };
+        let fetcher = Fetcher::new(reqwest::Client::new(), Duration::from_millis(50), retry);
+
+        let start = Instant::now();
+        assert_eq!(fetcher.get(&format!("{}/flaky", server.url())).await.unwrap().status(), 503);
+        assert_eq!(fetcher.get(&format!("{}/missing", server.url())).await.unwrap().status(), 404);
+        let page = fetcher.get(&format!("{}/page", server.url())).await.unwrap();
+        assert_eq!(page.text().await.unwrap(), "ok");
+        // Four requests to one host, 50ms apart
+        assert!(start.elapsed() >= Duration::from_millis(150));
+        flaky.assert_async().await;
+        missing.assert_async().await;
+
+        // Retry-After is capped at the policy's longest backoff
+        let start = Instant::now();
+        assert_eq!(fetcher.get(&format!("{}/busy", server.url())).await.unwrap().status(), 429);
+        assert!(start.elapsed() < Duration::from_secs(5));
+        busy.assert_async().await;
+
+        assert_eq!(fetcher.backoff(1), Duration::from_millis(10));
+        let fetcher = Fetcher::new(reqwest::Client::new(), Duration::ZERO, RetryPolicy::default());
+        assert_eq!(fetcher.backoff(3), Duration::from_millis(8000));
+        assert_eq!(fetcher.backoff(20), Duration::from_secs(60));
+    }
+
+    #[tokio::test]
+    async fn test_guarded_redirects() {
+        let mut server = mockito::Server::new_async().await;
+        let port = server.socket_address().port();
+        server.mock("GET", "/moved").with_status(302).with_header("Location", "/page").create_async().await;
+        server.mock("GET", "/page").with_body("ok").create_async().await;
+        server.mock("GET", "/escape")
+            .with_status(302)
+            .with_header("Location", &format!("http://localhost:{}/internal", port))
+            .create_async().await;
+        let internal = server.mock("GET", "/internal").expect(0).create_async().await;
+
+        // Only the mock server's address is allowed; localhost resolves to loopback
+        let guard = AddressGuard::new(&["127.0.0.1".to_string()]);
+        let fetcher = Fetcher::new(reqwest::Client::new(), Duration::ZERO, RetryPolicy::none()).with_guard(guard);
+        let page = fetcher.get(&format!("{}/moved", server.url())).await.unwrap();
+        assert_eq!(page.text().await.unwrap(), "ok");
+
+        let error = fetcher.get(&format!("{}/escape", server.url())).await.unwrap_err().to_string();
+        assert!(error.contains("internal address"), "{}", error);
+        internal.assert_async().await;
+
+        let unguarded = Fetcher::new(reqwest::Client::new(), Duration::ZERO, RetryPolicy::none())
+            .with_guard(AddressGuard::default());
+        assert!(unguarded.get(&format!("{}/page", server.url())).await.is_err());
+        assert!(is_public(&"93.184.216.34".parse().unwrap()));
+        for internal in ["169.254.169.254", "10.0.0.1", "100.64.0.1", "::ffff:192.168.0.1", "fd00::1", "fe80::1"] {
+            assert!(!is_public(&internal.parse().unwrap()), "{}", internal);
+        }
+    }
+}
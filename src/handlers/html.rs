@@ -0,0 +1,163 @@
+use crate::config::{HtmlSelectors, HtmlSettings};
+use crate::error::{Error, Result};
+use scraper::{ElementRef, Html, Node, Selector};
+use std::collections::HashSet;
+use tracing::debug;
+use url::Url;
+
+/// Elements whose content is never page text
+const SKIPPED_ELEMENTS: &[&str] = &["script", "style", "noscript", "template"];
+
+#[derive(Clone)]
+struct SelectorSet {
+    include: Vec<Selector>,
+    exclude: Vec<Selector>,
+}
+
+impl SelectorSet {
+    fn new(selectors: &HtmlSelectors) -> Result<Self> {
+        Ok(Self {
+            include: selectors.include.iter().map(|s| parse_selector(s)).collect::<Result<_>>()?,
+            exclude: selectors.exclude.iter().map(|s| parse_selector(s)).collect::<Result<_>>()?,
+        })
+    }
+}
+
+/// Picks the text of a web page with the `html` config's CSS selectors,
+/// using a site's own selectors for its pages
+#[derive(Clone)]
+pub struct HtmlFilter {
+    default: SelectorSet,
+    /// Hosts with their selectors, longest first so the most specific wins
+    sites: Vec<(String, SelectorSet)>,
+}
+
+impl HtmlFilter {
+    pub fn new(settings: &HtmlSettings) -> Result<Self> {
+        let mut sites = settings.sites.iter()
+            .map(|(host, selectors)| Ok((host.trim_start_matches('.').to_ascii_lowercase(), SelectorSet::new(selectors)?)))
+            .collect::<Result<Vec<_>>>()?;
+        sites.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then_with(|| a.0.cmp(&b.0)));
+        Ok(Self { default: SelectorSet::new(&settings.selectors)?, sites })
+    }
+
+    /// Text of the included elements of `html`, without the excluded ones;
+    /// the whole body when nothing is included
+    pub fn text(&self, html: &str, url: &str) -> String {
+        let selectors = self.selectors_for(url);
+        let document = Html::parse_document(html);
+
+        let excluded: HashSet<_> = selectors.exclude.iter()
+            .flat_map(|selector| document.select(selector))
+            .map(|element| element.id())
+            .collect();
+
+        let mut roots: Vec<ElementRef> = selectors.include.iter()
+            .flat_map(|selector| document.select(selector))
+            .collect();
+        if roots.is_empty() {
+            if !selectors.include.is_empty() {
+                debug!("No included elements on {}; using the whole page", url);
+            }
+            roots = document.select(&Selector::parse("body").expect("valid selector")).collect();
+        }
+        if roots.is_empty() {
+            roots.push(document.root_element());
+        }
+
+        // Nested matches are covered by their outermost included ancestor
+        let root_ids: HashSet<_> = roots.iter().map(|root| root.id()).collect();
+        roots.retain(|root| !root.ancestors().any(|ancestor| root_ids.contains(&ancestor.id())));
+        roots.sort_by_key(|root| root.id());
+        roots.dedup_by_key(|root| root.id());
+
+        let mut parts = Vec::new();
+        for root in roots {
+            // An included element inside an excluded one is excluded with it
+            if excluded.contains(&root.id()) || root.ancestors().any(|ancestor| excluded.contains(&ancestor.id())) {
+                continue;
+            }
+            let mut words = Vec::new();
+            let mut stack = vec![*root];
+            while let Some(node) = stack.pop() {
+                match node.value() {
+                    Node::Text(text) => {
+                        let text = text.trim();
+                        if !text.is_empty() {
+                            words.push(text.to_string());
+                        }
+                    }
+                    Node::Element(element) if node.id() != root.id()
+                        && (excluded.contains(&node.id()) || SKIPPED_ELEMENTS.contains(&element.name())) => {}
+                    Node::Element(_) => stack.extend(node.children().rev()),
+                    _ => {}
+                }
+            }
+            if !words.is_empty() {
+                parts.push(words.join(" "));
+            }
+        }
+        parts.join("\n")
+    }
+
+    fn selectors_for(&self, url: &str) -> &SelectorSet {
+        let host = Url::parse(url).ok()
+            .and_then(|url| url.host_str().map(str::to_ascii_lowercase))
+            .unwrap_or_default();
+        self.sites.iter()
+            .find(|(site, _)| host == *site || host.ends_with(&format!(".{}", site)))
+            .map(|(_, selectors)| selectors)
+            .unwrap_or(&self.default)
+    }
+}
+
+pub fn parse_selector(selector: &str) -> Result<Selector> {
+    Selector::parse(selector).map_err(|e| Error::config(format!("Invalid CSS selector '{}': {}", selector, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_html_selectors() {
+        let page = r#"<html><body>
+            <nav>Home | About</nav>
+            <div class="cookie-banner">We use cookies</div>
+            <article><h1>Acme acquires Globex</h1><p>Acme Corp bought <b>Globex</b> on Monday.</p>
+              <aside class="related">Related: Initech</aside><script>track()</script></article>
+            <div class="content"><p>Founded in 1999.</p></div>
+            <footer>© Acme</footer>
+        </body></html>"#;
+        let selectors = |include: &[&str], exclude: &[&str]| HtmlSelectors {
+            include: include.iter().map(|s| s.to_string()).collect(),
+            exclude: exclude.iter().map(|s| s.to_string()).collect(),
+        };
+        let settings = HtmlSettings {
+            selectors: selectors(&["article", ".content", "article p"], &[".related"]),
+            sites: HashMap::from([("news.example.com".to_string(), selectors(&[], &["nav", ".cookie-banner", "footer"]))]),
+        };
+        let filter = HtmlFilter::new(&settings).unwrap();
+
+        assert_eq!(
+            filter.text(page, "https://example.org/post"),
+            "Acme acquires Globex Acme Corp bought Globex on Monday.\nFounded in 1999."
+        );
+        // Site selectors replace the defaults, for subdomains too
+        assert_eq!(
+            filter.text(page, "https://eu.news.example.com/post"),
+            "Acme acquires Globex Acme Corp bought Globex on Monday. Related: Initech Founded in 1999."
+        );
+
+        // Pages without included elements fall back to the body
+        let plain = HtmlFilter::new(&HtmlSettings { selectors: selectors(&["main"], &["nav"]), ..Default::default() }).unwrap();
+        assert_eq!(plain.text("<body><nav>Menu</nav><p>Text</p></body>", "https://example.org/"), "Text");
+
+        // Included elements inside excluded ones are dropped with them
+        let paragraphs = HtmlFilter::new(&HtmlSettings { selectors: selectors(&["p"], &["nav"]), ..Default::default() }).unwrap();
+        assert_eq!(paragraphs.text("<body><nav><p>Menu</p></nav><p>Text</p></body>", "https://example.org/"), "Text");
+
+        assert!(parse_selector("div[").is_err());
+    }
+}
@@ -1,10 +1,28 @@
-use anyhow::{Result, Context};
+use crate::config::{CrawlProfile, DocumentHandlerSettings, OcrSettings, RetryPolicy};
+use crate::error::{Error, ErrorContext, Result};
 use async_trait::async_trait;
-use std::path::Path;
-use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
 use reqwest;
+use crate::utils::paths::{is_remote, normalize_source, source_extension};
 use scraper::{Html, Selector};
 
+pub mod crawler;
+pub mod email;
+pub mod fetch;
+pub mod html;
+pub mod presentation;
+pub mod sections;
+pub mod spreadsheet;
+pub use crawler::{CrawlOptions, Crawler};
+pub use email::EmailHandler;
+pub use fetch::{AddressGuard, Fetcher};
+pub use html::HtmlFilter;
+pub use presentation::PresentationHandler;
+pub use sections::{parse_section_source, section_source, split_sections, Section};
+pub use spreadsheet::{SpreadsheetHandler, SPREADSHEET_EXTENSIONS};
+
 #[async_trait]
 pub trait DocumentHandler: Send + Sync {
     async fn extract_text(&self, source: &str) -> Result<String>;
@@ -17,11 +35,11 @@ pub struct PdfHandler;
 impl DocumentHandler for PdfHandler {
     async fn extract_text(&self, source: &str) -> Result<String> {
         let bytes = tokio::fs::read(source).await
-            .with_context(|| format!("Failed to read PDF file: {}", source))?;
+            .document_context(source, || "Failed to read PDF file")?;
 
         // Use pdf-extract for text extraction
         let text = pdf_extract::extract_text_from_mem(&bytes)
-            .with_context(|| "Failed to extract text from PDF")?;
+            .document_context(source, || "Failed to extract text from PDF")?;
 
         Ok(text)
     }
@@ -47,7 +65,7 @@ impl DocumentHandler for TextHandler {
     async fn extract_text(&self, source: &str) -> Result<String> {
         // Read file and detect encoding
         let bytes = tokio::fs::read(source).await
-            .with_context(|| format!("Failed to read text file: {}", source))?;
+            .document_context(source, || "Failed to read text file")?;
 
         // Try to detect encoding
         let encoding = if let Some((enc, _)) = encoding_rs::Encoding::for_bom(&bytes) {
@@ -77,33 +95,195 @@ impl DocumentHandler for TextHandler {
     }
 }
 
+/// Image extensions handled by `OcrHandler`
+pub const OCR_IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "tif", "tiff"];
+
+/// OCR through the tesseract CLI, for images and for PDFs without a text layer
+///
+/// PDFs are first run through pdf-extract; only when that yields fewer than
+/// `min_pdf_chars` characters are the pages rasterized and OCRed.
+pub struct OcrHandler {
+    settings: OcrSettings,
+}
+
+impl OcrHandler {
+    pub fn new(settings: OcrSettings) -> Self {
+        Self { settings }
+    }
+
+    async fn run(&self, program: &str, args: &[&std::ffi::OsStr], source: &str) -> Result<String> {
+        let output = tokio::process::Command::new(program)
+            .args(args)
+            .output()
+            .await
+            .document_context(source, || format!("Failed to run {} (is it installed?)", program))?;
+
+        if !output.status.success() {
+            return Err(Error::document(source, format!(
+                "{} exited with {}: {}",
+                program,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    async fn ocr_image(&self, image: &Path, source: &str) -> Result<String> {
+        self.run(&self.settings.command, &[
+            image.as_os_str(),
+            "stdout".as_ref(),
+            "-l".as_ref(),
+            self.settings.language.as_ref(),
+        ], source).await
+    }
+
+    async fn ocr_pdf(&self, source: &str) -> Result<String> {
+        let dir = std::env::temp_dir().join(format!("rdf-ocr-{}", uuid::Uuid::new_v4()));
+        tokio::fs::create_dir_all(&dir).await
+            .document_context(source, || "Failed to create OCR working directory")?;
+
+        let result = self.ocr_pdf_pages(source, &dir).await;
+        if let Err(e) = tokio::fs::remove_dir_all(&dir).await {
+            tracing::warn!("Failed to remove OCR working directory {}: {}", dir.display(), e);
+        }
+        result
+    }
+
+    async fn ocr_pdf_pages(&self, source: &str, dir: &Path) -> Result<String> {
+        let dpi = self.settings.dpi.to_string();
+        let prefix = dir.join("page");
+        self.run(&self.settings.pdf_rasterizer, &[
+            "-r".as_ref(),
+            dpi.as_ref(),
+            "-png".as_ref(),
+            source.as_ref(),
+            prefix.as_os_str(),
+        ], source).await?;
+
+        let mut pages: Vec<PathBuf> = std::fs::read_dir(dir)
+            .document_context(source, || "Failed to list rasterized pages")?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "png"))
+            .collect();
+        // pdftoppm zero-pads page numbers, so name order is page order
+        pages.sort();
+
+        let mut text = Vec::with_capacity(pages.len());
+        for page in &pages {
+            text.push(self.ocr_image(page, source).await?);
+        }
+        Ok(text.join("\n"))
+    }
+}
+
+#[async_trait]
+impl DocumentHandler for OcrHandler {
+    async fn extract_text(&self, source: &str) -> Result<String> {
+        if !is_pdf(source) {
+            return self.ocr_image(Path::new(source), source).await;
+        }
+
+        match PdfHandler.extract_text(source).await {
+            Ok(text) if text.trim().chars().count() >= self.settings.min_pdf_chars => return Ok(text),
+            Ok(_) => tracing::info!("Little text found in {}, falling back to OCR", source),
+            Err(e) => tracing::warn!("{}; falling back to OCR", e),
+        }
+        self.ocr_pdf(source).await
+    }
+
+    async fn get_metadata(&self, source: &str) -> Result<HashMap<String, String>> {
+        if is_pdf(source) {
+            return PdfHandler.get_metadata(source).await;
+        }
+
+        let mut metadata = HashMap::new();
+        metadata.insert("source".to_string(), source.to_string());
+        metadata.insert("type".to_string(), "image".to_string());
+
+        if let Ok(meta) = tokio::fs::metadata(source).await {
+            metadata.insert("size".to_string(), meta.len().to_string());
+        }
+
+        Ok(metadata)
+    }
+}
+
+fn is_pdf(source: &str) -> bool {
+    Path::new(source).extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("pdf"))
+}
+
 pub struct UrlHandler {
-    client: reqwest::Client,
+    fetcher: Fetcher,
+    /// Selectors picking the page text; the whole body when unset
+    filter: Option<HtmlFilter>,
+    /// Last page fetched, so metadata does not request it again
+    last_page: tokio::sync::Mutex<Option<(String, String)>>,
+}
+
+impl Default for UrlHandler {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl UrlHandler {
     pub fn new() -> Self {
+        Self::with_fetcher(Duration::ZERO, RetryPolicy::none())
+    }
+
+    /// Handler keeping to a crawl profile's per-host delay and retry policy
+    pub fn with_profile(profile: &CrawlProfile) -> Self {
+        Self::with_fetcher(Duration::from_millis(profile.per_host_delay_ms), profile.retry.clone())
+    }
+
+    fn with_fetcher(delay: Duration, retry: RetryPolicy) -> Self {
         let client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(30))
-            .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
+            .timeout(fetch::FETCH_TIMEOUT)
+            .user_agent(fetch::USER_AGENT)
             .build()
             .expect("Failed to create HTTP client");
 
-        Self { client }
+        Self { fetcher: Fetcher::new(client, delay, retry), filter: None, last_page: tokio::sync::Mutex::new(None) }
+    }
+
+    /// Only fetch pages from public addresses, checking every redirect;
+    /// see [`AddressGuard`]
+    pub fn with_guard(mut self, guard: AddressGuard) -> Self {
+        self.fetcher = self.fetcher.with_guard(guard);
+        self
+    }
+
+    /// Extract only the parts of pages chosen by the `html` config's selectors
+    pub fn with_filter(mut self, filter: HtmlFilter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    async fn fetch(&self, source: &str) -> Result<String> {
+        let mut last_page = self.last_page.lock().await;
+        if let Some((_, html)) = last_page.as_ref().filter(|(url, _)| url == source) {
+            return Ok(html.clone());
+        }
+
+        let html = self.fetcher.get(source).await?
+            .text().await
+            .document_context(source, || "Failed to read response body")?;
+        *last_page = Some((source.to_string(), html.clone()));
+        Ok(html)
     }
 }
 
 #[async_trait]
 impl DocumentHandler for UrlHandler {
     async fn extract_text(&self, source: &str) -> Result<String> {
-        let response = self.client
-            .get(source)
-            .send()
-            .await
-            .with_context(|| format!("Failed to fetch URL: {}", source))?;
-
-        let html = response.text().await
-            .with_context(|| "Failed to read response body")?;
+        let html = self.fetch(source).await?;
+        if let Some(filter) = &self.filter {
+            return Ok(filter.text(&html, source));
+        }
 
         // Parse HTML and extract text
         let document = Html::parse_document(&html);
@@ -146,12 +326,7 @@ impl DocumentHandler for UrlHandler {
         metadata.insert("type".to_string(), "url".to_string());
 
         // Try to fetch and parse metadata from HTML
-        let response = self.client
-            .get(source)
-            .send()
-            .await?;
-
-        let html = response.text().await?;
+        let html = self.fetch(source).await?;
         let document = Html::parse_document(&html);
 
         // Extract title
@@ -177,25 +352,231 @@ impl DocumentHandler for UrlHandler {
     }
 }
 
+/// Runs an external program that prints a document's text to stdout, for
+/// formats declared in the config's `document_handlers`
+pub struct CommandHandler {
+    program: String,
+    args: Vec<String>,
+}
+
+impl CommandHandler {
+    /// Handler for `command`: a program followed by its arguments
+    pub fn new(command: &[String]) -> Result<Self> {
+        let (program, args) = command.split_first()
+            .ok_or_else(|| Error::config("Document handler command is empty"))?;
+        Ok(Self { program: program.clone(), args: args.to_vec() })
+    }
+
+    fn args_for(&self, source: &str) -> Vec<String> {
+        let mut args: Vec<String> = self.args.iter().map(|arg| arg.replace("{source}", source)).collect();
+        if !self.args.iter().any(|arg| arg.contains("{source}")) {
+            args.push(source.to_string());
+        }
+        args
+    }
+}
+
+#[async_trait]
+impl DocumentHandler for CommandHandler {
+    async fn extract_text(&self, source: &str) -> Result<String> {
+        let output = tokio::process::Command::new(&self.program)
+            .args(self.args_for(source))
+            .output()
+            .await
+            .document_context(source, || format!("Failed to run {}", self.program))?;
+
+        if !output.status.success() {
+            return Err(Error::document(source, format!(
+                "{} exited with {}: {}",
+                self.program,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    async fn get_metadata(&self, source: &str) -> Result<HashMap<String, String>> {
+        let mut metadata = HashMap::new();
+        metadata.insert("source".to_string(), source.to_string());
+        metadata.insert("type".to_string(), "command".to_string());
+        metadata.insert("handler".to_string(), self.program.clone());
+
+        if let Ok(meta) = tokio::fs::metadata(source).await {
+            metadata.insert("size".to_string(), meta.len().to_string());
+        }
+
+        Ok(metadata)
+    }
+}
+
+/// Handler described by a `document_handlers` config entry
+pub fn configured_handler(settings: &DocumentHandlerSettings, ocr: &OcrSettings) -> Result<Box<dyn DocumentHandler>> {
+    match settings.builtin.as_deref() {
+        Some("pdf") => Ok(Box::new(PdfHandler)),
+        Some("text") => Ok(Box::new(TextHandler)),
+        Some("url") => Ok(Box::new(UrlHandler::new())),
+        Some("ocr") => Ok(Box::new(OcrHandler::new(ocr.clone()))),
+        Some("email") => Ok(Box::new(EmailHandler)),
+        Some("spreadsheet") => Ok(Box::new(SpreadsheetHandler)),
+        Some("presentation") => Ok(Box::new(PresentationHandler)),
+        Some(name) => Err(Error::config(format!("Unknown built-in document handler: {}", name))),
+        None => Ok(Box::new(CommandHandler::new(&settings.command)?)),
+    }
+}
+
+/// Picks a handler for each source: by URL scheme (`scheme://...`), else by
+/// file extension, falling back to plain text
 pub struct DocumentProcessor {
     handlers: HashMap<String, Box<dyn DocumentHandler>>,
+    schemes: HashMap<String, Box<dyn DocumentHandler>>,
+    /// Whether `<source>#section=<n>` sources name sections; otherwise the
+    /// marker is read as part of the source, e.g. a URL fragment
+    sections: bool,
+    /// Documents split into sections whose sections have not all been
+    /// read yet, so no document is read again for each section
+    split_documents: tokio::sync::Mutex<HashMap<String, SplitDocument>>,
+}
+
+/// A document split into its sections, with the sections still to read
+struct SplitDocument {
+    document: ProcessedDocument,
+    sections: Vec<Section>,
+    unread: HashSet<usize>,
+}
+
+impl Default for DocumentProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl DocumentProcessor {
     pub fn new() -> Self {
-        let mut handlers: HashMap<String, Box<dyn DocumentHandler>> = HashMap::new();
+        let mut processor = Self {
+            handlers: HashMap::new(),
+            schemes: HashMap::new(),
+            sections: false,
+            split_documents: tokio::sync::Mutex::new(HashMap::new()),
+        };
 
         // Register default handlers
-        handlers.insert("pdf".to_string(), Box::new(PdfHandler));
-        handlers.insert("txt".to_string(), Box::new(TextHandler));
-        handlers.insert("text".to_string(), Box::new(TextHandler));
-        handlers.insert("md".to_string(), Box::new(TextHandler));
-        handlers.insert("url".to_string(), Box::new(UrlHandler::new()));
+        processor.register_handler("pdf", Box::new(PdfHandler));
+        processor.register_handler("txt", Box::new(TextHandler));
+        processor.register_handler("text", Box::new(TextHandler));
+        processor.register_handler("md", Box::new(TextHandler));
+        processor.register_handler("eml", Box::new(EmailHandler));
+        processor.register_handler("msg", Box::new(EmailHandler));
+        for extension in SPREADSHEET_EXTENSIONS {
+            processor.register_handler(extension, Box::new(SpreadsheetHandler));
+        }
+        processor.register_handler("pptx", Box::new(PresentationHandler));
+        processor.register_handler("http://", Box::new(UrlHandler::new()));
+        processor.register_handler("https://", Box::new(UrlHandler::new()));
 
-        Self { handlers }
+        processor
     }
 
+    /// Register `handler` for a file extension ("docx" or ".docx") or a URL
+    /// scheme ("s3://"), replacing any handler already registered for it
+    pub fn register_handler(&mut self, ext_or_scheme: &str, handler: Box<dyn DocumentHandler>) {
+        match ext_or_scheme.strip_suffix("://") {
+            Some(scheme) => self.schemes.insert(scheme.to_ascii_lowercase(), handler),
+            None => self.handlers.insert(ext_or_scheme.trim_start_matches('.').to_ascii_lowercase(), handler),
+        };
+    }
+
+    /// Register the handlers declared in the config's `document_handlers`
+    pub fn register_configured(
+        &mut self,
+        handlers: &HashMap<String, DocumentHandlerSettings>,
+        ocr: &OcrSettings,
+    ) -> Result<()> {
+        for (ext_or_scheme, settings) in handlers {
+            self.register_handler(ext_or_scheme, configured_handler(settings, ocr)?);
+        }
+        Ok(())
+    }
+
+    /// Register the OCR handler for images and as the PDF handler, which
+    /// falls back to OCR when a PDF has no usable text layer
+    pub fn with_ocr(mut self, settings: OcrSettings) -> Self {
+        for extension in OCR_IMAGE_EXTENSIONS.iter().copied().chain(["pdf"]) {
+            self.handlers.insert(extension.to_string(), Box::new(OcrHandler::new(settings.clone())));
+        }
+        self
+    }
+
+    /// Read `<source>#section=<n>` sources as that section of the
+    /// document, as [`section_sources`](Self::section_sources) names them
+    pub fn split_sections(&mut self, enabled: bool) {
+        self.sections = enabled;
+    }
+
+    /// Read a document from a path, a `file://` URI or a URL; for a
+    /// `<source>#section=<n>` source when sections are split, only that
+    /// section of the document
     pub async fn process(&self, source: &str) -> Result<ProcessedDocument> {
+        let Some((document, index)) = parse_section_source(source).filter(|_| self.sections) else {
+            return self.process_document(source).await;
+        };
+
+        let document = normalize_source(document);
+        let mut split_documents = self.split_documents.lock().await;
+        if !split_documents.contains_key(&document) {
+            let split = self.split_document(&document).await?;
+            split_documents.insert(document.clone(), split);
+        }
+        let split = split_documents.get_mut(&document).expect("inserted above");
+        let section = split.sections.iter()
+            .find(|section| section.index == index)
+            .cloned()
+            .ok_or_else(|| Error::document(source, format!("{} has no section {}", split.document.source, index)))?;
+
+        let mut metadata = split.document.metadata.clone();
+        metadata.insert("document".to_string(), split.document.source.clone());
+        metadata.insert("section".to_string(), index.to_string());
+        if !section.title.is_empty() {
+            metadata.insert("section_title".to_string(), section.title);
+        }
+        let processed = ProcessedDocument {
+            source: section_source(&split.document.source, index),
+            text: section.text,
+            metadata,
+        };
+
+        // Once all its sections are read, the document is not needed any more
+        split.unread.remove(&index);
+        if split.unread.is_empty() {
+            split_documents.remove(&document);
+        }
+        Ok(processed)
+    }
+
+    /// `<source>#section=<n>` sources for the top-level sections of
+    /// `source`, or `source` itself when it has no sections to split by.
+    /// The sections are kept until each has been read with
+    /// [`process`](Self::process).
+    pub async fn section_sources(&self, source: &str) -> Result<Vec<String>> {
+        let split = self.split_document(source).await?;
+        if split.sections.len() < 2 {
+            return Ok(vec![split.document.source]);
+        }
+        let sources = split.sections.iter().map(|section| section_source(&split.document.source, section.index)).collect();
+        self.split_documents.lock().await.insert(split.document.source.clone(), split);
+        Ok(sources)
+    }
+
+    async fn split_document(&self, source: &str) -> Result<SplitDocument> {
+        let mut document = self.process_document(source).await?;
+        let sections = split_sections(&std::mem::take(&mut document.text));
+        let unread = sections.iter().map(|section| section.index).collect();
+        Ok(SplitDocument { document, sections, unread })
+    }
+
+    async fn process_document(&self, source: &str) -> Result<ProcessedDocument> {
+        let source = &normalize_source(source);
         let handler = self.get_handler(source)?;
 
         let text = handler.extract_text(source).await?;
@@ -208,22 +589,29 @@ impl DocumentProcessor {
         })
     }
 
-    fn get_handler(&self, source: &str) -> Result<&Box<dyn DocumentHandler>> {
+    /// File extensions with a registered handler
+    pub fn supported_extensions(&self) -> Vec<String> {
+        self.handlers.keys().cloned().collect()
+    }
+
+    fn get_handler(&self, source: &str) -> Result<&dyn DocumentHandler> {
         // Check if it's a URL
-        if source.starts_with("http://") || source.starts_with("https://") {
-            return self.handlers.get("url")
-                .ok_or_else(|| anyhow::anyhow!("URL handler not found"));
+        if let Some((scheme, _)) = source.split_once("://").filter(|_| is_remote(source)) {
+            if let Some(handler) = self.schemes.get(&scheme.to_ascii_lowercase()) {
+                return Ok(handler.as_ref());
+            }
+            if scheme == "http" || scheme == "https" {
+                return Err(Error::document(source, "URL handler not found"));
+            }
         }
 
-        // Get file extension
-        let path = Path::new(source);
-        let extension = path.extension()
-            .and_then(|e| e.to_str())
-            .unwrap_or("txt");
+        // Get file extension, ignoring case (REPORT.PDF)
+        let extension = source_extension(source).unwrap_or_else(|| "txt".to_string());
 
-        self.handlers.get(extension)
+        self.handlers.get(&extension)
             .or_else(|| self.handlers.get("txt"))
-            .ok_or_else(|| anyhow::anyhow!("No handler found for file type: {}", extension))
+            .map(|h| h.as_ref())
+            .ok_or_else(|| Error::document(source, format!("No handler found for file type: {}", extension)))
     }
 
     pub async fn process_multiple(&self, sources: Vec<String>) -> Vec<Result<ProcessedDocument>> {
@@ -242,4 +630,96 @@ pub struct ProcessedDocument {
     pub source: String,
     pub text: String,
     pub metadata: HashMap<String, String>,
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fixed(&'static str);
+
+    #[async_trait]
+    impl DocumentHandler for Fixed {
+        async fn extract_text(&self, _source: &str) -> Result<String> {
+            Ok(self.0.to_string())
+        }
+
+        async fn get_metadata(&self, _source: &str) -> Result<HashMap<String, String>> {
+            Ok(HashMap::new())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_register_handler() {
+        let mut processor = DocumentProcessor::new();
+        processor.register_handler(".docx", Box::new(Fixed("word")));
+        processor.register_handler("s3://", Box::new(Fixed("bucket")));
+
+        assert_eq!(processor.process("report.docx").await.unwrap().text, "word");
+        assert_eq!(processor.process("s3://docs/report.pdf").await.unwrap().text, "bucket");
+        assert!(processor.supported_extensions().contains(&"docx".to_string()));
+        let source = processor.process("C:\\Reports\\Q1.DOCX").await.unwrap().source;
+        assert_eq!(source, if cfg!(windows) { "C:/Reports/Q1.DOCX" } else { "C:\\Reports\\Q1.DOCX" });
+
+        // file:// URIs are read as the local file they name
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes 1.md");
+        std::fs::write(&path, "plain").unwrap();
+        let uri = crate::utils::paths::file_uri(&path.to_string_lossy());
+        let document = processor.process(&uri).await.unwrap();
+        let canonical = std::fs::canonicalize(&path).unwrap();
+        assert_eq!((document.text.as_str(), document.source.as_str()), ("plain", canonical.to_string_lossy().as_ref()));
+
+        let command = CommandHandler::new(&["pandoc".to_string(), "{source}".to_string(), "-t".to_string(), "plain".to_string()]).unwrap();
+        assert_eq!(command.args_for("a.docx"), ["a.docx", "-t", "plain"]);
+        assert_eq!(CommandHandler::new(&["cat".to_string()]).unwrap().args_for("a.txt"), ["a.txt"]);
+    }
+
+    /// Handler counting the documents it reads
+    struct Counting(&'static str, std::sync::Arc<std::sync::atomic::AtomicUsize>);
+
+    #[async_trait]
+    impl DocumentHandler for Counting {
+        async fn extract_text(&self, _source: &str) -> Result<String> {
+            self.1.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(self.0.to_string())
+        }
+
+        async fn get_metadata(&self, _source: &str) -> Result<HashMap<String, String>> {
+            Ok(HashMap::new())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_process_section() {
+        let body = "The manual explains how to install, configure and maintain the pump in industrial settings. ".repeat(3);
+        let text: &'static str = Box::leak(format!("# Install\n{0}\n# Maintain\n{0}", body).into_boxed_str());
+        let reads = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let mut processor = DocumentProcessor::new();
+        processor.register_handler("manual", Box::new(Counting(text, reads.clone())));
+        processor.register_handler("docs://", Box::new(Counting(text, reads.clone())));
+
+        // The marker is only read as a section when sections are split
+        let page = processor.process("docs://faq#section=2").await.unwrap();
+        assert_eq!((page.source.as_str(), page.text.as_str()), ("docs://faq#section=2", text));
+        processor.split_sections(true);
+        reads.store(0, std::sync::atomic::Ordering::SeqCst);
+
+        // Each document is read once, however many are split before extraction
+        let sources = processor.section_sources("pump.manual").await.unwrap();
+        assert_eq!(sources, ["pump.manual#section=1", "pump.manual#section=2"]);
+        let others = processor.section_sources("valve.manual").await.unwrap();
+        let section = processor.process(&sources[1]).await.unwrap();
+        assert_eq!(section.source, "pump.manual#section=2");
+        assert!(section.text.starts_with("# Maintain"));
+        assert_eq!(section.metadata["section_title"], "Maintain");
+        assert_eq!(section.metadata["document"], "pump.manual");
+        for source in others.iter().chain(&sources[..1]) {
+            processor.process(source).await.unwrap();
+        }
+        assert_eq!(reads.load(std::sync::atomic::Ordering::SeqCst), 2);
+        assert!(processor.split_documents.lock().await.is_empty());
+
+        assert!(processor.process("pump.manual#section=3").await.is_err());
+    }
+}
@@ -0,0 +1,302 @@
+//! PowerPoint presentations (`.pptx`)
+//!
+//! Slides are given to extraction in presentation order as `Slide <n>:
+//! <title>` followed by the slide's body text and its speaker notes. The
+//! metadata records each slide's title and the character offset where its
+//! text starts, so a statement can be traced back to its slide.
+
+use super::DocumentHandler;
+use crate::error::{Error, ErrorContext, Result};
+use async_trait::async_trait;
+use quick_xml::encoding::Decoder;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+
+pub struct PresentationHandler;
+
+#[async_trait]
+impl DocumentHandler for PresentationHandler {
+    async fn extract_text(&self, source: &str) -> Result<String> {
+        let slides = read_presentation(source).await?;
+        Ok(presentation_text(&slides).0)
+    }
+
+    async fn get_metadata(&self, source: &str) -> Result<HashMap<String, String>> {
+        let slides = read_presentation(source).await?;
+        let (_, offsets) = presentation_text(&slides);
+
+        let mut metadata = HashMap::new();
+        metadata.insert("source".to_string(), source.to_string());
+        metadata.insert("type".to_string(), "presentation".to_string());
+        metadata.insert("slides".to_string(), slides.len().to_string());
+        for (slide, offset) in slides.iter().zip(offsets) {
+            metadata.insert(format!("slide.{}.offset", slide.number), offset.to_string());
+            if let Some(title) = &slide.title {
+                metadata.insert(format!("slide.{}.title", slide.number), title.clone());
+            }
+        }
+
+        if let Ok(meta) = tokio::fs::metadata(source).await {
+            metadata.insert("size".to_string(), meta.len().to_string());
+        }
+
+        Ok(metadata)
+    }
+}
+
+/// Text of one slide, numbered from 1 in presentation order
+#[derive(Debug, Default, PartialEq)]
+pub struct Slide {
+    pub number: usize,
+    pub title: Option<String>,
+    /// Paragraphs of the other shapes and tables
+    pub body: Vec<String>,
+    /// Paragraphs of the speaker notes
+    pub notes: Vec<String>,
+}
+
+/// The document text and the offset (in characters) of each slide in it
+fn presentation_text(slides: &[Slide]) -> (String, Vec<usize>) {
+    let mut text = String::new();
+    let mut offsets = Vec::new();
+    for slide in slides {
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        offsets.push(text.chars().count());
+        text.push_str(&format!("Slide {}: {}\n", slide.number, slide.title.as_deref().unwrap_or_default()));
+        for paragraph in &slide.body {
+            text.push_str(&format!("{}\n", paragraph));
+        }
+        if !slide.notes.is_empty() {
+            text.push_str(&format!("Notes: {}\n", slide.notes.join("\n")));
+        }
+    }
+    (text, offsets)
+}
+
+async fn read_presentation(source: &str) -> Result<Vec<Slide>> {
+    let bytes = tokio::fs::read(source).await
+        .document_context(source, || "Failed to read presentation")?;
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes))
+        .document_context(source, || "Not a .pptx file")?;
+    let mut part = |name: &str| -> Result<Option<String>> {
+        let mut file = match archive.by_name(name) {
+            Ok(file) => file,
+            Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+            Err(e) => return Err(e).document_context(source, || format!("Failed to read {}", name)),
+        };
+        let mut content = String::new();
+        file.read_to_string(&mut content)
+            .document_context(source, || format!("Failed to read {}", name))?;
+        Ok(Some(content))
+    };
+
+    let presentation = part("ppt/presentation.xml")?
+        .ok_or_else(|| Error::document(source, "Not a PowerPoint presentation: ppt/presentation.xml is missing"))?;
+    let slide_parts = relationships(&part("ppt/_rels/presentation.xml.rels")?.unwrap_or_default(), "ppt/");
+
+    let mut slides = Vec::new();
+    for (index, id) in slide_ids(&presentation).iter().enumerate() {
+        let Some(path) = slide_parts.iter().find(|r| &r.id == id).map(|r| r.target.clone()) else { continue };
+        let Some(xml) = part(&path)? else { continue };
+
+        let mut slide = read_shapes(&xml);
+        slide.number = index + 1;
+
+        let (dir, file) = path.rsplit_once('/').unwrap_or(("", &path));
+        let slide_relationships = relationships(&part(&format!("{}/_rels/{}.rels", dir, file))?.unwrap_or_default(), &format!("{}/", dir));
+        if let Some(notes) = slide_relationships.iter().find(|r| r.kind.ends_with("/notesSlide")) {
+            if let Some(xml) = part(&notes.target)? {
+                slide.notes = read_shapes(&xml).body;
+            }
+        }
+        slides.push(slide);
+    }
+    Ok(slides)
+}
+
+struct Relationship {
+    id: String,
+    kind: String,
+    /// Path of the target part in the archive
+    target: String,
+}
+
+/// Relationships of a `.rels` part, with targets resolved against `base`
+fn relationships(xml: &str, base: &str) -> Vec<Relationship> {
+    let mut reader = Reader::from_str(xml);
+    let mut relationships = Vec::new();
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e) | Event::Empty(e)) if e.local_name().as_ref() == b"Relationship" => {
+                let (Some(id), Some(target)) = (attribute(&e, b"Id", reader.decoder()), attribute(&e, b"Target", reader.decoder())) else { continue };
+                relationships.push(Relationship {
+                    id,
+                    kind: attribute(&e, b"Type", reader.decoder()).unwrap_or_default(),
+                    target: resolve_target(base, &target),
+                });
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+    }
+    relationships
+}
+
+/// `ppt/slides/` + `../notesSlides/notesSlide1.xml` -> `ppt/notesSlides/notesSlide1.xml`
+fn resolve_target(base: &str, target: &str) -> String {
+    if let Some(absolute) = target.strip_prefix('/') {
+        return absolute.to_string();
+    }
+    let mut parts: Vec<&str> = base.split('/').filter(|p| !p.is_empty()).collect();
+    for segment in target.split('/') {
+        match segment {
+            ".." => { parts.pop(); }
+            "." | "" => {}
+            segment => parts.push(segment),
+        }
+    }
+    parts.join("/")
+}
+
+/// Relationship IDs of the slides, in presentation order
+fn slide_ids(presentation: &str) -> Vec<String> {
+    let mut reader = Reader::from_str(presentation);
+    let mut ids = Vec::new();
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e) | Event::Empty(e)) if e.local_name().as_ref() == b"sldId" => {
+                // The relationship is `r:id`; the unprefixed `id` is the slide's own ID
+                ids.extend(e.attributes()
+                    .flatten()
+                    .find(|a| a.key.local_name().as_ref() == b"id" && a.key.prefix().is_some())
+                    .and_then(|a| a.decode_and_unescape_value(reader.decoder()).ok().map(|v| v.into_owned())));
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+    }
+    ids
+}
+
+/// Attribute by local name, whatever its prefix
+fn attribute(element: &BytesStart, name: &[u8], decoder: Decoder) -> Option<String> {
+    element.attributes()
+        .flatten()
+        .filter(|a| a.key.local_name().as_ref() == name)
+        .find_map(|a| a.decode_and_unescape_value(decoder).ok().map(|v| v.into_owned()))
+}
+
+/// Title placeholder text and the other paragraphs of a slide (or notes
+/// slide); slide number, date and footer placeholders are skipped
+fn read_shapes(xml: &str) -> Slide {
+    let mut reader = Reader::from_str(xml);
+    let mut slide = Slide::default();
+    let mut title = Vec::new();
+
+    let mut in_shape = false;
+    let mut placeholder = String::new();
+    let mut in_text = false;
+    let mut paragraph = String::new();
+    let mut shape_paragraphs = Vec::new();
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) => match e.local_name().as_ref() {
+                b"sp" => {
+                    in_shape = true;
+                    placeholder.clear();
+                }
+                b"p" => paragraph.clear(),
+                b"t" => in_text = true,
+                b"ph" => placeholder = attribute(&e, b"type", reader.decoder()).unwrap_or_else(|| "body".to_string()),
+                _ => {}
+            },
+            Ok(Event::Empty(e)) => match e.local_name().as_ref() {
+                b"ph" => placeholder = attribute(&e, b"type", reader.decoder()).unwrap_or_else(|| "body".to_string()),
+                b"br" => paragraph.push(' '),
+                _ => {}
+            },
+            Ok(Event::Text(e)) if in_text => paragraph.push_str(&e.decode().unwrap_or_default()),
+            Ok(Event::GeneralRef(e)) if in_text => {
+                if let Ok(Some(c)) = e.resolve_char_ref() {
+                    paragraph.push(c);
+                } else if let Some(text) = e.decode().ok().and_then(|name| quick_xml::escape::resolve_predefined_entity(&name)) {
+                    paragraph.push_str(text);
+                }
+            }
+            Ok(Event::End(e)) => match e.local_name().as_ref() {
+                b"t" => in_text = false,
+                b"p" => {
+                    let text = paragraph.split_whitespace().collect::<Vec<_>>().join(" ");
+                    if !text.is_empty() {
+                        if in_shape { shape_paragraphs.push(text) } else { slide.body.push(text) }
+                    }
+                }
+                b"sp" => {
+                    in_shape = false;
+                    match placeholder.as_str() {
+                        "title" | "ctrTitle" => title.append(&mut shape_paragraphs),
+                        "sldNum" | "dt" | "ftr" | "hdr" | "sldImg" => shape_paragraphs.clear(),
+                        _ => slide.body.append(&mut shape_paragraphs),
+                    }
+                }
+                _ => {}
+            },
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+    }
+
+    slide.title = (!title.is_empty()).then(|| title.join(" "));
+    slide
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn shape(placeholder: Option<&str>, paragraphs: &[&str]) -> String {
+        let ph = placeholder.map(|t| format!(r#"<p:nvSpPr><p:nvPr><p:ph type="{}"/></p:nvPr></p:nvSpPr>"#, t)).unwrap_or_default();
+        let paragraphs: String = paragraphs.iter().map(|p| format!("<a:p><a:r><a:t>{}</a:t></a:r></a:p>", p)).collect();
+        format!("<p:sp>{}<p:txBody>{}</p:txBody></p:sp>", ph, paragraphs)
+    }
+
+    #[tokio::test]
+    async fn test_presentation_handler() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("deck.pptx");
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+        let mut file = |name: &str, content: String| {
+            zip.start_file(name, zip::write::SimpleFileOptions::default()).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        };
+        let ns = r#"xmlns:p="http://schemas.openxmlformats.org/presentationml/2006/main" xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships""#;
+        let rel = |id: &str, kind: &str, target: &str| format!(r#"<Relationship Id="{}" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/{}" Target="{}"/>"#, id, kind, target);
+
+        // Slide order comes from presentation.xml, not from the part names
+        file("ppt/presentation.xml", format!(r#"<p:presentation {}><p:sldIdLst><p:sldId id="257" r:id="rId3"/><p:sldId id="256" r:id="rId2"/></p:sldIdLst></p:presentation>"#, ns));
+        file("ppt/_rels/presentation.xml.rels", format!("<Relationships>{}{}</Relationships>", rel("rId2", "slide", "slides/slide1.xml"), rel("rId3", "slide", "slides/slide2.xml")));
+        file("ppt/slides/slide2.xml", format!("<p:sld {}><p:cSld><p:spTree>{}{}{}</p:spTree></p:cSld></p:sld>", ns,
+            shape(Some("ctrTitle"), &["Q3 Review"]), shape(None, &["Acme &amp; Globex signed", "Revenue  grew 12%"]), shape(Some("sldNum"), &["1"])));
+        file("ppt/slides/_rels/slide2.xml.rels", format!("<Relationships>{}</Relationships>", rel("rId1", "notesSlide", "../notesSlides/notesSlide1.xml")));
+        file("ppt/notesSlides/notesSlide1.xml", format!("<p:notes {}><p:cSld><p:spTree>{}{}</p:spTree></p:cSld></p:notes>", ns,
+            shape(Some("sldImg"), &[]), shape(Some("body"), &["Ana Ruiz leads the Globex account."])));
+        file("ppt/slides/slide1.xml", format!("<p:sld {}><p:cSld><p:spTree>{}</p:spTree></p:cSld></p:sld>", ns, shape(Some("title"), &["Next Steps"])));
+        zip.finish().unwrap();
+        let source = path.to_str().unwrap();
+
+        let text = PresentationHandler.extract_text(source).await.unwrap();
+        assert_eq!(text, "Slide 1: Q3 Review\nAcme & Globex signed\nRevenue grew 12%\nNotes: Ana Ruiz leads the Globex account.\n\nSlide 2: Next Steps\n");
+
+        let metadata = PresentationHandler.get_metadata(source).await.unwrap();
+        assert_eq!(metadata["slides"], "2");
+        assert_eq!(metadata["slide.2.title"], "Next Steps");
+        let offset: usize = metadata["slide.2.offset"].parse().unwrap();
+        assert!(text[offset..].starts_with("Slide 2:"));
+    }
+}
@@ -0,0 +1,203 @@
+//! Splitting long documents into their top-level sections, so each chapter
+//! of a book or item of a filing can be extracted as a source of its own,
+//! addressed as `<source>#section=<n>`
+
+use regex::Regex;
+
+/// Sections with less text than this after their heading, such as table of
+/// contents entries, are merged into the next section
+const MIN_SECTION_CHARS: usize = 200;
+
+const SECTION_MARKER: &str = "#section=";
+
+/// A top-level section of a document's text
+#[derive(Debug, Clone, PartialEq)]
+pub struct Section {
+    /// Position among the document's sections, from 1
+    pub index: usize,
+    pub title: String,
+    /// Text of the section, its heading included
+    pub text: String,
+}
+
+/// Kinds of heading lines, from the outermost
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum HeadingKind {
+    /// `#` to `######`
+    Markdown(usize),
+    /// "Part II", "Chapter 3: ...", "Section 4", "Article 5"
+    Keyword(u8),
+    /// "Item 1A. Risk Factors", as in SEC filings
+    Item,
+    /// "1. Introduction", "2 Background"
+    Numbered,
+}
+
+struct Heading {
+    kind: HeadingKind,
+    offset: usize,
+    title: String,
+    number: Option<u32>,
+}
+
+struct HeadingPatterns {
+    markdown: Regex,
+    keyword: Regex,
+    item: Regex,
+    numbered: Regex,
+}
+
+impl HeadingPatterns {
+    fn new() -> Self {
+        Self {
+            markdown: Regex::new(r"^(#{1,6})\s+(\S.*?)\s*#*$").expect("valid regex"),
+            keyword: Regex::new(r"(?i)^(part|chapter|section|article)\s+([0-9]+|[ivxlcdm]+|one|two|three|four|five|six|seven|eight|nine|ten)\b")
+                .expect("valid regex"),
+            item: Regex::new(r"(?i)^item\s+\d+[a-z]?\b").expect("valid regex"),
+            numbered: Regex::new(r"^(\d{1,2})\.?\s+\p{Lu}[^.]{0,80}$").expect("valid regex"),
+        }
+    }
+
+    fn heading(&self, line: &str, offset: usize) -> Option<Heading> {
+        if let Some(captures) = self.markdown.captures(line) {
+            return Some(Heading { kind: HeadingKind::Markdown(captures[1].len()), offset, title: captures[2].to_string(), number: None });
+        }
+        if let Some(captures) = self.keyword.captures(line) {
+            let rank = match captures[1].to_ascii_lowercase().as_str() {
+                "part" => 0,
+                "chapter" => 1,
+                "section" => 2,
+                _ => 3,
+            };
+            return Some(Heading { kind: HeadingKind::Keyword(rank), offset, title: line.to_string(), number: None });
+        }
+        if self.item.is_match(line) {
+            return Some(Heading { kind: HeadingKind::Item, offset, title: line.to_string(), number: None });
+        }
+        if let Some(captures) = self.numbered.captures(line) {
+            let number = captures[1].parse().ok();
+            return Some(Heading { kind: HeadingKind::Numbered, offset, title: line.to_string(), number });
+        }
+        None
+    }
+}
+
+/// The top-level sections of `text`: the outermost kind of heading that
+/// occurs at least twice; a single section holding the whole text when the
+/// document has no such headings
+pub fn split_sections(text: &str) -> Vec<Section> {
+    let headings = headings(text);
+    let Some(top) = top_level(&headings) else {
+        return vec![Section { index: 1, title: String::new(), text: text.to_string() }];
+    };
+    let top: Vec<&Heading> = headings.iter().filter(|h| h.kind == top).collect();
+
+    // Text before the first heading belongs to the first section
+    // Merge short sections into the next one, the last into the previous one
+    let mut merged: Vec<(String, usize, usize)> = Vec::new();
+    let mut pending: Option<usize> = None;
+    for (i, heading) in top.iter().enumerate() {
+        let end = top.get(i + 1).map_or(text.len(), |next| next.offset);
+        let start = pending.take().unwrap_or(if i == 0 { 0 } else { heading.offset });
+        if body_len(&text[heading.offset..end]) < MIN_SECTION_CHARS {
+            pending = Some(start);
+            continue;
+        }
+        merged.push((heading.title.clone(), start, end));
+    }
+    if let Some(start) = pending {
+        match merged.last_mut() {
+            Some(last) => last.2 = text.len(),
+            None => merged.push((top.last().map(|h| h.title.clone()).unwrap_or_default(), start, text.len())),
+        }
+    }
+
+    merged.into_iter()
+        .enumerate()
+        .map(|(i, (title, start, end))| Section { index: i + 1, title, text: text[start..end].trim().to_string() })
+        .collect()
+}
+
+/// Source naming section `index` of `source`
+pub fn section_source(source: &str, index: usize) -> String {
+    format!("{}{}{}", source, SECTION_MARKER, index)
+}
+
+/// The document and section index of a `<source>#section=<n>` source
+pub fn parse_section_source(source: &str) -> Option<(&str, usize)> {
+    let (document, index) = source.rsplit_once(SECTION_MARKER)?;
+    let index = index.parse().ok().filter(|&i| i > 0)?;
+    Some((document, index))
+}
+
+fn headings(text: &str) -> Vec<Heading> {
+    let patterns = HeadingPatterns::new();
+    let mut headings = Vec::new();
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if !trimmed.is_empty() && trimmed.chars().count() <= 120 {
+            if let Some(heading) = patterns.heading(trimmed, offset) {
+                headings.push(heading);
+            }
+        }
+        offset += line.len();
+    }
+    headings
+}
+
+/// Outermost heading kind occurring at least twice; numbered headings only
+/// count when they run 1, 2, 3, ... so numbered lists are not taken for them
+fn top_level(headings: &[Heading]) -> Option<HeadingKind> {
+    let mut kinds: Vec<HeadingKind> = headings.iter().map(|h| h.kind.clone()).collect();
+    kinds.sort();
+    kinds.dedup();
+    kinds.into_iter().find(|kind| {
+        let of_kind: Vec<&Heading> = headings.iter().filter(|h| h.kind == *kind).collect();
+        match kind {
+            HeadingKind::Numbered => of_kind.len() >= 2
+                && of_kind.iter().enumerate().all(|(i, h)| h.number == Some(i as u32 + 1)),
+            _ => of_kind.len() >= 2,
+        }
+    })
+}
+
+/// Characters of a section after its heading line
+fn body_len(section: &str) -> usize {
+    section.split_once('\n').map_or(0, |(_, body)| body.trim().chars().count())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_sections() {
+        let paragraph = "The company designs and sells widgets to industrial customers across Europe and North America. ".repeat(3);
+        let book = format!(
+            "Annual Report 2024\n\nContents\nChapter 1 Overview\nChapter 2 Risks\n\nChapter 1 Overview\n{0}\n1. Products\nWidgets.\n\
+             2. Customers\nFactories.\n\nChapter 2 Risks\n{0}\n\nChapter 3 Outlook\nShort.\n",
+            paragraph
+        );
+        let sections = split_sections(&book);
+        let titles: Vec<&str> = sections.iter().map(|s| s.title.as_str()).collect();
+        assert_eq!(titles, ["Chapter 1 Overview", "Chapter 2 Risks"]);
+        // The title page and contents join the first chapter, the short last chapter the previous one
+        assert!(sections[0].text.starts_with("Annual Report 2024"));
+        assert!(sections[0].text.contains("2. Customers"));
+        assert!(sections[1].text.ends_with("Chapter 3 Outlook\nShort."));
+        assert_eq!(sections[1].index, 2);
+
+        let markdown = format!("# Intro\n{0}\n## Detail\n{0}\n# Usage\n{0}", paragraph);
+        let titles: Vec<String> = split_sections(&markdown).into_iter().map(|s| s.title).collect();
+        assert_eq!(titles, ["Intro", "Usage"]);
+
+        // A numbered list is not a list of sections
+        let list = format!("Steps\n{0}\n3. Open The box\n{0}\n7. Close The box\n{0}", paragraph);
+        assert_eq!(split_sections(&list).len(), 1);
+
+        assert_eq!(section_source("docs/10-K.pdf", 3), "docs/10-K.pdf#section=3");
+        assert_eq!(parse_section_source("docs/10-K.pdf#section=3"), Some(("docs/10-K.pdf", 3)));
+        assert_eq!(parse_section_source("https://ex.org/page#section=x"), None);
+    }
+}
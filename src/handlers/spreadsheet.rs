@@ -0,0 +1,227 @@
+//! Spreadsheets (`.xlsx`, `.xlsm`, `.xlsb`, `.xls`, `.ods`)
+//!
+//! Each sheet is given to extraction as structured text, one line per row
+//! with the header row's column names, so the model sees which value
+//! belongs to which column. For a fixed layout, an `import` mapping with a
+//! `spreadsheet` source turns rows into triples without the model.
+
+use super::DocumentHandler;
+use crate::error::{ErrorContext, Result};
+use async_trait::async_trait;
+use calamine::{Data, DataType, Reader};
+use std::collections::HashMap;
+use std::path::Path;
+
+const XSD_INTEGER: &str = "http://www.w3.org/2001/XMLSchema#integer";
+const XSD_DOUBLE: &str = "http://www.w3.org/2001/XMLSchema#double";
+const XSD_BOOLEAN: &str = "http://www.w3.org/2001/XMLSchema#boolean";
+const XSD_DATE: &str = "http://www.w3.org/2001/XMLSchema#date";
+const XSD_DATE_TIME: &str = "http://www.w3.org/2001/XMLSchema#dateTime";
+const XSD_DURATION: &str = "http://www.w3.org/2001/XMLSchema#duration";
+
+pub const SPREADSHEET_EXTENSIONS: &[&str] = &["xlsx", "xlsm", "xlsb", "xls", "ods"];
+
+pub struct SpreadsheetHandler;
+
+#[async_trait]
+impl DocumentHandler for SpreadsheetHandler {
+    async fn extract_text(&self, source: &str) -> Result<String> {
+        let sheets = read_workbook(Path::new(source))?;
+        let text: Vec<String> = sheets.iter()
+            .filter(|sheet| !sheet.rows.is_empty())
+            .map(Sheet::to_text)
+            .collect();
+        Ok(text.join("\n"))
+    }
+
+    async fn get_metadata(&self, source: &str) -> Result<HashMap<String, String>> {
+        let sheets = read_workbook(Path::new(source))?;
+        let mut metadata = HashMap::new();
+        metadata.insert("source".to_string(), source.to_string());
+        metadata.insert("type".to_string(), "spreadsheet".to_string());
+        metadata.insert("sheets".to_string(), sheets.iter().map(|s| s.name.as_str()).collect::<Vec<_>>().join(", "));
+
+        for sheet in &sheets {
+            let (columns, rows) = sheet.columns_and_rows();
+            metadata.insert(format!("sheet.{}.rows", sheet.name), rows.len().to_string());
+            metadata.insert(format!("sheet.{}.columns", sheet.name), columns.join(", "));
+        }
+
+        if let Ok(meta) = tokio::fs::metadata(source).await {
+            metadata.insert("size".to_string(), meta.len().to_string());
+        }
+
+        Ok(metadata)
+    }
+}
+
+/// A worksheet's used range, row by row
+#[derive(Debug)]
+pub struct Sheet {
+    pub name: String,
+    pub rows: Vec<Vec<Option<SheetValue>>>,
+}
+
+/// A non-empty cell as text, with the XSD datatype of numbers, booleans,
+/// dates and durations
+#[derive(Debug, Clone, PartialEq)]
+pub struct SheetValue {
+    pub value: String,
+    pub datatype: Option<&'static str>,
+}
+
+/// Every sheet of the workbook at `path`, in workbook order
+pub fn read_workbook(path: &Path) -> Result<Vec<Sheet>> {
+    let source = path.display().to_string();
+    let mut workbook = calamine::open_workbook_auto(path)
+        .document_context(&source, || "Failed to open spreadsheet")?;
+
+    let mut sheets = Vec::new();
+    for name in workbook.sheet_names() {
+        let range = workbook.worksheet_range(&name)
+            .document_context(&source, || format!("Failed to read sheet '{}'", name))?;
+        let rows = range.rows()
+            .map(|row| row.iter().map(sheet_value).collect())
+            .collect();
+        sheets.push(Sheet { name, rows });
+    }
+    Ok(sheets)
+}
+
+fn sheet_value(cell: &Data) -> Option<SheetValue> {
+    let typed = |value: String, datatype| Some(SheetValue { value, datatype: Some(datatype) });
+    match cell {
+        Data::Empty | Data::Error(_) => None,
+        Data::String(text) if text.trim().is_empty() => None,
+        Data::String(text) => Some(SheetValue { value: text.trim().to_string(), datatype: None }),
+        Data::Int(n) => typed(n.to_string(), XSD_INTEGER),
+        Data::Float(n) if n.fract() == 0.0 && n.abs() < 1e15 => typed(format!("{}", *n as i64), XSD_INTEGER),
+        Data::Float(n) => typed(n.to_string(), XSD_DOUBLE),
+        Data::Bool(b) => typed(b.to_string(), XSD_BOOLEAN),
+        Data::DateTime(date) if date.is_duration() => typed(cell.as_string().unwrap_or_default(), XSD_DURATION),
+        Data::DateTime(_) => match cell.as_datetime() {
+            Some(datetime) if datetime.time() == chrono::NaiveTime::MIN => typed(datetime.format("%Y-%m-%d").to_string(), XSD_DATE),
+            Some(datetime) => typed(datetime.format("%Y-%m-%dT%H:%M:%S").to_string(), XSD_DATE_TIME),
+            None => None,
+        },
+        Data::DateTimeIso(value) if value.contains('T') => typed(value.clone(), XSD_DATE_TIME),
+        Data::DateTimeIso(value) => typed(value.clone(), XSD_DATE),
+        Data::DurationIso(value) => typed(value.clone(), XSD_DURATION),
+    }
+}
+
+impl Sheet {
+    /// Column names and the data rows: the first row is the header when all
+    /// its cells are text, otherwise columns are named by letter (A, B, ...)
+    pub fn columns_and_rows(&self) -> (Vec<String>, &[Vec<Option<SheetValue>>]) {
+        let Some(first) = self.rows.first() else { return (Vec::new(), &[]) };
+        let is_header = first.iter().flatten().all(|cell| cell.datatype.is_none()) && first.iter().any(Option::is_some);
+
+        if is_header {
+            let columns = first.iter()
+                .enumerate()
+                .map(|(i, cell)| cell.as_ref().map(|c| c.value.clone()).unwrap_or_else(|| column_letter(i)))
+                .collect();
+            (columns, &self.rows[1..])
+        } else {
+            let width = self.rows.iter().map(Vec::len).max().unwrap_or_default();
+            ((0..width).map(column_letter).collect(), &self.rows)
+        }
+    }
+
+    /// `Sheet: <name>` followed by `Row <n>: <column>: <value>; ...` lines
+    fn to_text(&self) -> String {
+        let (columns, rows) = self.columns_and_rows();
+        let mut text = format!("Sheet: {}\n", self.name);
+        for (i, row) in rows.iter().enumerate() {
+            let cells: Vec<String> = columns.iter()
+                .zip(row)
+                .filter_map(|(column, cell)| cell.as_ref().map(|c| format!("{}: {}", column, c.value)))
+                .collect();
+            if !cells.is_empty() {
+                text.push_str(&format!("Row {}: {}\n", i + 1, cells.join("; ")));
+            }
+        }
+        text
+    }
+}
+
+/// Spreadsheet column name of the 0-based `index`: A, ..., Z, AA, AB, ...
+pub(crate) fn column_letter(index: usize) -> String {
+    let mut name = String::new();
+    let mut n = index + 1;
+    while n > 0 {
+        let rem = (n - 1) % 26;
+        name.insert(0, (b'A' + rem as u8) as char);
+        n = (n - 1) / 26;
+    }
+    name
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::io::Write;
+
+    /// Minimal `.xlsx` with one sheet per `(name, rows)`; cells starting with
+    /// `=` are numbers, others inline strings
+    pub(crate) fn write_xlsx(path: &Path, sheets: &[(&str, &[&[&str]])]) {
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(path).unwrap());
+        let mut file = |name: &str, content: String| {
+            zip.start_file(name, zip::write::SimpleFileOptions::default()).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        };
+
+        let overrides: String = (1..=sheets.len())
+            .map(|i| format!(r#"<Override PartName="/xl/worksheets/sheet{}.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/>"#, i))
+            .collect();
+        file("[Content_Types].xml", format!(r#"<?xml version="1.0" encoding="UTF-8"?><Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/>{}</Types>"#, overrides));
+        let entries: String = sheets.iter().enumerate()
+            .map(|(i, (name, _))| format!(r#"<sheet name="{}" sheetId="{}" r:id="rId{}"/>"#, name, i + 1, i + 1))
+            .collect();
+        file("xl/workbook.xml", format!(r#"<?xml version="1.0" encoding="UTF-8"?><workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets>{}</sheets></workbook>"#, entries));
+        let relationships: String = (1..=sheets.len())
+            .map(|i| format!(r#"<Relationship Id="rId{}" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet{}.xml"/>"#, i, i))
+            .collect();
+        file("xl/_rels/workbook.xml.rels", format!(r#"<?xml version="1.0" encoding="UTF-8"?><Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">{}</Relationships>"#, relationships));
+
+        for (i, (_, rows)) in sheets.iter().enumerate() {
+            let rows: String = rows.iter().enumerate()
+                .map(|(r, cells)| {
+                    let cells: String = cells.iter().enumerate()
+                        .filter(|(_, value)| !value.is_empty())
+                        .map(|(c, value)| match value.strip_prefix('=') {
+                            Some(number) => format!(r#"<c r="{}{}"><v>{}</v></c>"#, column_letter(c), r + 1, number),
+                            None => format!(r#"<c r="{}{}" t="inlineStr"><is><t>{}</t></is></c>"#, column_letter(c), r + 1, value),
+                        })
+                        .collect();
+                    format!(r#"<row r="{}">{}</row>"#, r + 1, cells)
+                })
+                .collect();
+            file(&format!("xl/worksheets/sheet{}.xml", i + 1), format!(r#"<?xml version="1.0" encoding="UTF-8"?><worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetData>{}</sheetData></worksheet>"#, rows));
+        }
+        zip.finish().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_spreadsheet_handler() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("accounts.xlsx");
+        write_xlsx(&path, &[
+            ("Accounts", &[&["Company", "Owner", "Seats"], &["Acme", "Ana Ruiz", "=120"], &["Globex", "", "=35"]]),
+            ("Notes", &[&["=2026", "Renewals due"]]),
+        ]);
+        let source = path.to_str().unwrap();
+
+        let text = SpreadsheetHandler.extract_text(source).await.unwrap();
+        assert_eq!(text, "Sheet: Accounts\nRow 1: Company: Acme; Owner: Ana Ruiz; Seats: 120\nRow 2: Company: Globex; Seats: 35\n\nSheet: Notes\nRow 1: A: 2026; B: Renewals due\n");
+
+        let metadata = SpreadsheetHandler.get_metadata(source).await.unwrap();
+        assert_eq!(metadata["sheets"], "Accounts, Notes");
+        assert_eq!(metadata["sheet.Accounts.rows"], "2");
+        assert_eq!(metadata["sheet.Accounts.columns"], "Company, Owner, Seats");
+
+        let sheets = read_workbook(&path).unwrap();
+        assert_eq!(sheets[0].rows[1][2], Some(SheetValue { value: "120".into(), datatype: Some(XSD_INTEGER) }));
+    }
+}
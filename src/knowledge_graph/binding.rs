@@ -0,0 +1,196 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+pub const XSD: &str = "http://www.w3.org/2001/XMLSchema#";
+
+/// A value bound to a query variable
+///
+/// Triples store plain strings, so the kind of a value is recovered from its
+/// lexical form: URIs become `Iri`, `_:` labels `BlankNode`, and literals
+/// written as `"chat"@fr` or `"5"^^<...#int>` keep their language tag or
+/// datatype. Other literals get `xsd:integer`, `xsd:decimal`, `xsd:boolean`,
+/// `xsd:date` or `xsd:dateTime` when the value parses as one.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Binding {
+    Iri {
+        value: String,
+    },
+    BlankNode {
+        value: String,
+    },
+    Literal {
+        value: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        datatype: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        lang: Option<String>,
+    },
+}
+
+impl Binding {
+    pub fn iri(value: impl Into<String>) -> Self {
+        Self::Iri { value: value.into() }
+    }
+
+    /// Plain string literal, without a datatype or language tag
+    pub fn literal(value: impl Into<String>) -> Self {
+        Self::Literal { value: value.into(), datatype: None, lang: None }
+    }
+
+    pub fn typed_literal(value: impl Into<String>, datatype: impl Into<String>) -> Self {
+        Self::Literal { value: value.into(), datatype: Some(datatype.into()), lang: None }
+    }
+
+    pub fn lang_literal(value: impl Into<String>, lang: impl Into<String>) -> Self {
+        Self::Literal { value: value.into(), datatype: None, lang: Some(lang.into()) }
+    }
+
+    /// Classify a value stored in a triple
+    pub fn from_term(term: &str) -> Self {
+        if is_iri(term) {
+            return Self::iri(term);
+        }
+        if let Some(label) = term.strip_prefix("_:") {
+            if !label.is_empty() && !label.contains(char::is_whitespace) {
+                return Self::BlankNode { value: term.to_string() };
+            }
+        }
+        if let Some(literal) = parse_quoted_literal(term) {
+            return literal;
+        }
+
+        match infer_datatype(term) {
+            Some(datatype) => Self::typed_literal(term, format!("{}{}", XSD, datatype)),
+            None => Self::literal(term),
+        }
+    }
+
+    /// The IRI, blank node label or lexical form of the literal
+    pub fn value(&self) -> &str {
+        match self {
+            Self::Iri { value } | Self::BlankNode { value } | Self::Literal { value, .. } => value,
+        }
+    }
+
+    pub fn into_value(self) -> String {
+        match self {
+            Self::Iri { value } | Self::BlankNode { value } | Self::Literal { value, .. } => value,
+        }
+    }
+
+    pub fn is_iri(&self) -> bool {
+        matches!(self, Self::Iri { .. })
+    }
+
+    pub fn datatype(&self) -> Option<&str> {
+        match self {
+            Self::Literal { datatype, .. } => datatype.as_deref(),
+            _ => None,
+        }
+    }
+
+    pub fn lang(&self) -> Option<&str> {
+        match self {
+            Self::Literal { lang, .. } => lang.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// `xsd` local name of the datatype, e.g. `integer`
+    pub fn xsd_type(&self) -> Option<&str> {
+        self.datatype().and_then(|datatype| datatype.strip_prefix(XSD))
+    }
+
+    /// N-Triples form of the term, e.g. `<http://...>` or `"5"^^<...#integer>`
+    pub fn to_ntriples(&self) -> String {
+        match self {
+            Self::Iri { value } => format!("<{}>", value),
+            Self::BlankNode { value } => value.clone(),
+            Self::Literal { value, datatype, lang } => {
+                let quoted = format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""));
+                match (lang, datatype) {
+                    (Some(lang), _) => format!("{}@{}", quoted, lang),
+                    (None, Some(datatype)) => format!("{}^^<{}>", quoted, datatype),
+                    (None, None) => quoted,
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.value())
+    }
+}
+
+fn is_iri(term: &str) -> bool {
+    ["http://", "https://", "urn:", "file://", "mailto:"].iter().any(|scheme| term.starts_with(scheme))
+        && !term.contains(char::is_whitespace)
+}
+
+/// `"value"@lang` or `"value"^^<datatype>` (also `^^xsd:name`)
+fn parse_quoted_literal(term: &str) -> Option<Binding> {
+    let rest = term.strip_prefix('"')?;
+    let end = rest.rfind('"')?;
+    let (value, suffix) = (&rest[..end], &rest[end + 1..]);
+    let value = value.replace("\\\"", "\"").replace("\\\\", "\\");
+
+    if suffix.is_empty() {
+        return None;
+    }
+    if let Some(lang) = suffix.strip_prefix('@') {
+        let valid = !lang.is_empty() && lang.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+        return valid.then(|| Binding::lang_literal(value, lang));
+    }
+
+    let datatype = suffix.strip_prefix("^^")?;
+    let datatype = if let Some(iri) = datatype.strip_prefix('<').and_then(|d| d.strip_suffix('>')) {
+        iri.to_string()
+    } else if let Some(local) = datatype.strip_prefix("xsd:") {
+        format!("{}{}", XSD, local)
+    } else {
+        return None;
+    };
+    Some(Binding::typed_literal(value, datatype))
+}
+
+fn infer_datatype(value: &str) -> Option<&'static str> {
+    if value.is_empty() || value != value.trim() {
+        return None;
+    }
+    if value.parse::<i64>().is_ok() {
+        Some("integer")
+    } else if value.parse::<f64>().is_ok_and(f64::is_finite) {
+        Some("decimal")
+    } else if value == "true" || value == "false" {
+        Some("boolean")
+    } else if chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d").is_ok() {
+        Some("date")
+    } else if chrono::DateTime::parse_from_rfc3339(value).is_ok()
+        || chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S").is_ok()
+    {
+        Some("dateTime")
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_term() {
+        assert_eq!(Binding::from_term("http://ex.org/acme"), Binding::iri("http://ex.org/acme"));
+        assert_eq!(Binding::from_term("_:b0"), Binding::BlankNode { value: "_:b0".into() });
+        assert_eq!(Binding::from_term("Acme Corp"), Binding::literal("Acme Corp"));
+        assert_eq!(Binding::from_term("42").xsd_type(), Some("integer"));
+        assert_eq!(Binding::from_term("4.5").xsd_type(), Some("decimal"));
+        assert_eq!(Binding::from_term("2024-03-01").xsd_type(), Some("date"));
+        assert_eq!(Binding::from_term("\"chat\"@fr"), Binding::lang_literal("chat", "fr"));
+        assert_eq!(Binding::from_term("\"5\"^^xsd:int"), Binding::typed_literal("5", format!("{}int", XSD)));
+        assert_eq!(Binding::lang_literal("a \"b\"", "en").to_ntriples(), "\"a \\\"b\\\"\"@en");
+    }
+}
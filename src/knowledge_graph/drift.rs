@@ -0,0 +1,152 @@
+use crate::core::RdfTriple;
+use crate::error::{ErrorContext, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Example triples kept per unknown predicate
+const MAX_EXAMPLES: usize = 3;
+
+/// A predicate outside the schema that extraction produced
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnknownPredicate {
+    /// Triples dropped for this predicate
+    pub count: usize,
+    /// Documents it was extracted from
+    pub sources: Vec<String>,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    /// The first few triples dropped for it
+    pub examples: Vec<DriftExample>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DriftExample {
+    pub subject: String,
+    pub object: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+/// Predicates outside the schema that extraction produced and
+/// `require_known_predicates` dropped, accumulated across runs in
+/// `<kg_path>.drift.json`, so recurring ones can be added to the schema
+pub struct SchemaDrift {
+    path: PathBuf,
+    predicates: BTreeMap<String, UnknownPredicate>,
+}
+
+impl SchemaDrift {
+    /// Drift file used for the knowledge graph stored at `kg_path`
+    pub fn path_for(kg_path: &str) -> PathBuf {
+        PathBuf::from(format!("{}.drift.json", kg_path))
+    }
+
+    pub fn open_for(kg_path: &str) -> Result<Self> {
+        Self::open(Self::path_for(kg_path))
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let predicates = if path.exists() {
+            let content = fs::read_to_string(&path)
+                .storage_context(|| format!("Failed to read schema drift: {}", path.display()))?;
+            serde_json::from_str(&content)
+                .storage_context(|| format!("Failed to parse schema drift: {}", path.display()))?
+        } else {
+            BTreeMap::new()
+        };
+
+        Ok(Self { path, predicates })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let content = serde_json::to_string_pretty(&self.predicates)
+            .storage_context(|| "Failed to serialize schema drift")?;
+        fs::write(&self.path, content)
+            .storage_context(|| format!("Failed to write schema drift: {}", self.path.display()))
+    }
+
+    /// Count `triples` against their predicates
+    pub fn record(&mut self, triples: &[RdfTriple]) {
+        let now = Utc::now();
+        for triple in triples {
+            let entry = self.predicates.entry(triple.predicate.clone()).or_insert_with(|| UnknownPredicate {
+                count: 0,
+                sources: Vec::new(),
+                first_seen: now,
+                last_seen: now,
+                examples: Vec::new(),
+            });
+            entry.count += 1;
+            entry.last_seen = now;
+            if let Some(source) = &triple.source {
+                if !entry.sources.contains(source) {
+                    entry.sources.push(source.clone());
+                }
+            }
+            if entry.examples.len() < MAX_EXAMPLES {
+                entry.examples.push(DriftExample {
+                    subject: triple.subject.clone(),
+                    object: triple.object.clone(),
+                    source: triple.source.clone(),
+                });
+            }
+        }
+    }
+
+    /// Up to `limit` predicates, most frequently dropped first
+    pub fn top(&self, limit: usize) -> Vec<(&str, &UnknownPredicate)> {
+        let mut predicates: Vec<_> = self.predicates.iter().map(|(p, u)| (p.as_str(), u)).collect();
+        predicates.sort_by(|a, b| b.1.count.cmp(&a.1.count).then(a.0.cmp(b.0)));
+        predicates.truncate(limit);
+        predicates
+    }
+
+    pub fn len(&self) -> usize {
+        self.predicates.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.predicates.is_empty()
+    }
+
+    /// Forget the recorded predicates, e.g. once the schema has been updated
+    pub fn clear(&mut self) {
+        self.predicates.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema_drift() {
+        let dir = tempfile::tempdir().unwrap();
+        let kg_path = dir.path().join("kg.db").to_string_lossy().to_string();
+        let dropped = |p: &str, o: &str, source: &str| RdfTriple::new("http://ex.org/acme".into(), format!("http://ex.org/{}", p), o.into())
+            .with_source(source.into());
+
+        let mut drift = SchemaDrift::open_for(&kg_path).unwrap();
+        drift.record(&[dropped("ceo", "Ana", "a.pdf"), dropped("revenue", "1M", "a.pdf")]);
+        drift.save().unwrap();
+
+        let mut drift = SchemaDrift::open_for(&kg_path).unwrap();
+        drift.record(&[
+            dropped("revenue", "2M", "b.pdf"),
+            dropped("revenue", "3M", "b.pdf"),
+            dropped("revenue", "4M", "c.pdf"),
+        ]);
+        let top = drift.top(1);
+        assert_eq!(top.len(), 1);
+        let (predicate, unknown) = top[0];
+        assert_eq!((predicate, unknown.count), ("http://ex.org/revenue", 4));
+        assert_eq!(unknown.sources, ["a.pdf", "b.pdf", "c.pdf"]);
+        let objects: Vec<&str> = unknown.examples.iter().map(|e| e.object.as_str()).collect();
+        assert_eq!(objects, ["1M", "2M", "3M"]);
+        assert_eq!(drift.top(10)[1].0, "http://ex.org/ceo");
+    }
+}
@@ -0,0 +1,260 @@
+//! On-disk index of entity label embeddings, kept next to the knowledge
+//! graph so semantic features embed each stored entity once rather than at
+//! every start
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::journal::write_atomic;
+use crate::error::{Error, ErrorContext, Result};
+
+const MAGIC: &[u8; 4] = b"RKEI";
+
+/// Format version written by this release; files of a later version are
+/// refused rather than misread
+pub const FORMAT_VERSION: u16 = 1;
+
+/// Label embeddings of one model, stored in `<kg_path>.embeddings`.
+///
+/// The file holds a header (magic, format version, model, dimensions, count)
+/// followed by each label and its vector as little-endian `f32`s, about a
+/// third of the size of the same vectors in JSON. The JSON cache of earlier
+/// releases (`<kg_path>.embeddings.json`) is read when no index exists yet
+/// and removed once the index is saved.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmbeddingIndex {
+    model: String,
+    vectors: HashMap<String, Vec<f32>>,
+}
+
+/// Cache layout of earlier releases
+#[derive(Deserialize)]
+struct LegacyCache {
+    model: String,
+    vectors: HashMap<String, Vec<f32>>,
+}
+
+impl EmbeddingIndex {
+    /// Index used for the knowledge graph stored at `kg_path`
+    pub fn path_for(kg_path: &str) -> PathBuf {
+        PathBuf::from(format!("{}.embeddings", kg_path))
+    }
+
+    pub fn new(model: impl Into<String>) -> Self {
+        Self { model: model.into(), vectors: HashMap::new() }
+    }
+
+    /// Read the index at `path`; an index of another model is discarded,
+    /// and a missing one starts empty
+    pub fn open<P: AsRef<Path>>(path: P, model: &str) -> Result<Self> {
+        let path = path.as_ref();
+        let index = if path.exists() {
+            let content = fs::read(path)
+                .storage_context(|| format!("Failed to read embedding index: {}", path.display()))?;
+            Self::decode(&content).map_err(|e| Error::storage(format!(
+                "{}: {}; rebuild it with `kg rebuild-embeddings`",
+                path.display(),
+                e
+            )))?
+        } else {
+            let legacy = legacy_path(path);
+            if !legacy.exists() {
+                return Ok(Self::new(model));
+            }
+            let content = fs::read_to_string(&legacy)
+                .storage_context(|| format!("Failed to read embedding cache: {}", legacy.display()))?;
+            let cache: LegacyCache = serde_json::from_str(&content)
+                .storage_context(|| format!("Failed to parse embedding cache: {}", legacy.display()))?;
+            Self { model: cache.model, vectors: cache.vectors }
+        };
+
+        if index.model != model {
+            return Ok(Self::new(model));
+        }
+        Ok(index)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        write_atomic(path, &self.encode()?)?;
+        let legacy = legacy_path(path);
+        if legacy.exists() {
+            fs::remove_file(&legacy)
+                .storage_context(|| format!("Failed to remove embedding cache: {}", legacy.display()))?;
+        }
+        Ok(())
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Length of the stored vectors; 0 while the index is empty
+    pub fn dimensions(&self) -> usize {
+        self.vectors.values().next().map_or(0, Vec::len)
+    }
+
+    pub fn len(&self) -> usize {
+        self.vectors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vectors.is_empty()
+    }
+
+    pub fn contains(&self, label: &str) -> bool {
+        self.vectors.contains_key(label)
+    }
+
+    pub fn get(&self, label: &str) -> Option<&[f32]> {
+        self.vectors.get(label).map(Vec::as_slice)
+    }
+
+    pub fn insert(&mut self, label: String, vector: Vec<f32>) {
+        self.vectors.insert(label, vector);
+    }
+
+    fn encode(&self) -> Result<Vec<u8>> {
+        let dimensions = self.dimensions();
+        if let Some((label, vector)) = self.vectors.iter().find(|(_, v)| v.len() != dimensions) {
+            return Err(Error::storage(format!(
+                "Embedding of '{}' has {} dimensions, expected {}",
+                label,
+                vector.len(),
+                dimensions
+            )));
+        }
+
+        let mut labels: Vec<&String> = self.vectors.keys().collect();
+        labels.sort();
+
+        let mut out = Vec::with_capacity(16 + self.model.len() + labels.len() * (8 + dimensions * 4));
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        write_str(&mut out, &self.model);
+        out.extend_from_slice(&(dimensions as u32).to_le_bytes());
+        out.extend_from_slice(&(labels.len() as u32).to_le_bytes());
+        for label in labels {
+            write_str(&mut out, label);
+            for value in &self.vectors[label] {
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        Ok(out)
+    }
+
+    fn decode(content: &[u8]) -> std::result::Result<Self, String> {
+        let mut reader = Reader { content, offset: 0 };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err("not an embedding index".to_string());
+        }
+        let version = u16::from_le_bytes(reader.array()?);
+        if version > FORMAT_VERSION {
+            return Err(format!("index format {} is newer than this release supports ({})", version, FORMAT_VERSION));
+        }
+
+        let model = reader.string()?;
+        let dimensions = reader.u32()? as usize;
+        let count = reader.u32()? as usize;
+        let mut vectors = HashMap::with_capacity(count.min(content.len()));
+        for _ in 0..count {
+            let label = reader.string()?;
+            let vector = reader.take(dimensions * 4)?
+                .chunks_exact(4)
+                .map(|bytes| f32::from_le_bytes(bytes.try_into().expect("4-byte chunk")))
+                .collect();
+            vectors.insert(label, vector);
+        }
+        if reader.offset != content.len() {
+            return Err("unexpected data after the last embedding".to_string());
+        }
+        Ok(Self { model, vectors })
+    }
+}
+
+/// `<path>.json`, where earlier releases cached the embeddings
+fn legacy_path(path: &Path) -> PathBuf {
+    let mut legacy = path.as_os_str().to_owned();
+    legacy.push(".json");
+    PathBuf::from(legacy)
+}
+
+/// Length-prefixed UTF-8
+fn write_str(out: &mut Vec<u8>, value: &str) {
+    out.extend_from_slice(&(value.len() as u32).to_le_bytes());
+    out.extend_from_slice(value.as_bytes());
+}
+
+struct Reader<'a> {
+    content: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> std::result::Result<&'a [u8], String> {
+        let end = self.offset.checked_add(len)
+            .filter(|&end| end <= self.content.len())
+            .ok_or_else(|| "index is truncated".to_string())?;
+        let bytes = &self.content[self.offset..end];
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> std::result::Result<[u8; N], String> {
+        Ok(self.take(N)?.try_into().expect("N bytes"))
+    }
+
+    fn u32(&mut self) -> std::result::Result<u32, String> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn string(&mut self) -> std::result::Result<String, String> {
+        let len = self.u32()? as usize;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| "index holds a label that is not UTF-8".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedding_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let kg_path = dir.path().join("kg.db").to_string_lossy().to_string();
+        let path = EmbeddingIndex::path_for(&kg_path);
+
+        // The JSON cache of earlier releases is carried over, then removed
+        let legacy = format!("{}.embeddings.json", kg_path);
+        fs::write(&legacy, r#"{"model":"embed","vectors":{"Acme Corporation":[1.0,0.0,0.5]}}"#).unwrap();
+        let mut index = EmbeddingIndex::open(&path, "embed").unwrap();
+        assert_eq!(index.get("Acme Corporation"), Some([1.0, 0.0, 0.5].as_slice()));
+
+        index.insert("Globex".to_string(), vec![0.0, 1.0, -0.25]);
+        index.save(&path).unwrap();
+        assert!(!Path::new(&legacy).exists());
+        assert!(fs::metadata(&path).unwrap().len() < 100);
+
+        let reopened = EmbeddingIndex::open(&path, "embed").unwrap();
+        assert_eq!(reopened, index);
+        assert_eq!(reopened.dimensions(), 3);
+        assert!(EmbeddingIndex::open(&path, "other-model").unwrap().is_empty());
+
+        // Later formats and damaged files point at the rebuild command
+        let mut content = fs::read(&path).unwrap();
+        content[4] = 9;
+        fs::write(&path, &content).unwrap();
+        let error = EmbeddingIndex::open(&path, "embed").unwrap_err().to_string();
+        assert!(error.contains("newer") && error.contains("kg rebuild-embeddings"), "{}", error);
+        content[4] = 1;
+        fs::write(&path, &content[..content.len() - 2]).unwrap();
+        assert!(EmbeddingIndex::open(&path, "embed").unwrap_err().to_string().contains("truncated"));
+
+        let mut uneven = EmbeddingIndex::new("embed");
+        uneven.insert("a".to_string(), vec![1.0]);
+        uneven.insert("b".to_string(), vec![1.0, 2.0]);
+        assert!(uneven.save(&path).is_err());
+    }
+}
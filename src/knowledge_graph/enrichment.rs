@@ -0,0 +1,591 @@
+//! Enrichment from public knowledge bases: labelled entities are looked up
+//! in Wikidata or DBpedia by label, and the matches are stored as
+//! `owl:sameAs` links together with a few of their properties (type,
+//! country, industry), so templates and queries can use them offline
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{debug, warn};
+use url::Url;
+
+use super::federation::parse_results_json;
+use super::labels::entity_labels;
+use super::{Binding, KnowledgeGraph, SimpleSparqlResults};
+use crate::config::RetryPolicy;
+use crate::core::RdfTriple;
+use crate::error::{Error, ErrorContext, Result};
+use crate::handlers::Fetcher;
+
+const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+const RDFS_LABEL: &str = "http://www.w3.org/2000/01/rdf-schema#label";
+const OWL_SAME_AS: &str = "http://www.w3.org/2002/07/owl#sameAs";
+
+/// Types of vocabulary terms, whose labels name classes and properties
+/// rather than entities
+const TERM_TYPES: [&str; 6] = [
+    "http://www.w3.org/2002/07/owl#Class",
+    "http://www.w3.org/2000/01/rdf-schema#Class",
+    "http://www.w3.org/1999/02/22-rdf-syntax-ns#Property",
+    "http://www.w3.org/2002/07/owl#ObjectProperty",
+    "http://www.w3.org/2002/07/owl#DatatypeProperty",
+    "http://www.w3.org/2002/07/owl#TransitiveProperty",
+];
+
+/// Search hits compared with an entity's label per lookup
+const SEARCH_HITS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExternalSource {
+    Wikidata,
+    Dbpedia,
+}
+
+impl ExternalSource {
+    pub const ALL: [ExternalSource; 2] = [Self::Wikidata, Self::Dbpedia];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Wikidata => "wikidata",
+            Self::Dbpedia => "dbpedia",
+        }
+    }
+
+    /// Namespace of the source's entity IRIs
+    pub fn namespace(&self) -> &'static str {
+        match self {
+            Self::Wikidata => "http://www.wikidata.org/entity/",
+            Self::Dbpedia => "http://dbpedia.org/resource/",
+        }
+    }
+
+    fn search_url(&self) -> &'static str {
+        match self {
+            Self::Wikidata => "https://www.wikidata.org/w/api.php",
+            Self::Dbpedia => "https://lookup.dbpedia.org/api/search",
+        }
+    }
+
+    fn sparql_url(&self) -> &'static str {
+        match self {
+            Self::Wikidata => "https://query.wikidata.org/sparql",
+            Self::Dbpedia => "https://dbpedia.org/sparql",
+        }
+    }
+
+    /// Predicate the source states `property` with, also used for the
+    /// triples stored on the local entity
+    pub fn predicate(&self, property: ExternalProperty) -> &'static str {
+        match (self, property) {
+            (Self::Wikidata, ExternalProperty::Type) => "http://www.wikidata.org/prop/direct/P31",
+            (Self::Wikidata, ExternalProperty::Country) => "http://www.wikidata.org/prop/direct/P17",
+            (Self::Wikidata, ExternalProperty::Industry) => "http://www.wikidata.org/prop/direct/P452",
+            (Self::Dbpedia, ExternalProperty::Type) => RDF_TYPE,
+            (Self::Dbpedia, ExternalProperty::Country) => "http://dbpedia.org/ontology/country",
+            (Self::Dbpedia, ExternalProperty::Industry) => "http://dbpedia.org/ontology/industry",
+        }
+    }
+}
+
+/// Properties of a matched entity that can be copied into the knowledge graph
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExternalProperty {
+    Type,
+    Country,
+    Industry,
+}
+
+impl ExternalProperty {
+    pub const ALL: [ExternalProperty; 3] = [Self::Type, Self::Country, Self::Industry];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Type => "type",
+            Self::Country => "country",
+            Self::Industry => "industry",
+        }
+    }
+}
+
+/// A value of an external property, with its label when the source has one
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExternalValue {
+    pub uri: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+/// An entity of the external source matching a label
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExternalEntity {
+    pub uri: String,
+    pub label: String,
+    #[serde(default)]
+    pub properties: BTreeMap<ExternalProperty, Vec<ExternalValue>>,
+}
+
+/// A local entity and the external entity it was linked to
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExternalLink {
+    pub uri: String,
+    pub label: String,
+    pub target: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EnrichReport {
+    pub linked: Vec<ExternalLink>,
+    /// Labels the source has no exact match for
+    pub not_found: Vec<String>,
+    /// Entities already `owl:sameAs` an entity of the source
+    pub already_linked: usize,
+    /// Labels not in the cache, when offline
+    pub uncached: usize,
+    /// Labels whose lookup failed; they are tried again on the next run
+    pub failed: usize,
+    /// Links and properties to store
+    pub triples: Vec<RdfTriple>,
+}
+
+/// Which entities `Enricher::enrich` looks up and what it stores for them
+#[derive(Debug, Clone)]
+pub struct EnrichOptions {
+    pub properties: Vec<ExternalProperty>,
+    /// Only entities of one of these classes; all labelled entities when empty
+    pub types: Vec<String>,
+    /// Entities looked up at most, cached ones included
+    pub limit: Option<usize>,
+}
+
+impl Default for EnrichOptions {
+    fn default() -> Self {
+        Self { properties: ExternalProperty::ALL.to_vec(), types: Vec::new(), limit: None }
+    }
+}
+
+/// Lookups already made, including misses, keyed by source, language and label
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct LookupCache {
+    entries: HashMap<String, Option<ExternalEntity>>,
+}
+
+/// Looks up labelled entities in Wikidata or DBpedia; requests to the
+/// source are spaced by a delay and their results cached in
+/// `<kg_path>.enrichment.json`, so reruns and `offline` runs need no requests
+pub struct Enricher {
+    source: ExternalSource,
+    language: String,
+    fetcher: Fetcher,
+    search_url: String,
+    sparql_url: String,
+    offline: bool,
+    cache_path: Option<PathBuf>,
+    cache: LookupCache,
+}
+
+impl Enricher {
+    /// Lookup cache used for the knowledge graph stored at `kg_path`
+    pub fn cache_path_for(kg_path: &str) -> PathBuf {
+        PathBuf::from(format!("{}.enrichment.json", kg_path))
+    }
+
+    /// Enricher matching labels in `language` (e.g. "en"), waiting `delay`
+    /// between requests to the source
+    pub fn new(source: ExternalSource, language: impl Into<String>, delay: Duration) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(60))
+            // Wikidata rejects requests without one
+            .user_agent(concat!("rdf_knowledge_extractor/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .storage_context(|| "Failed to create HTTP client")?;
+
+        Ok(Self {
+            source,
+            language: language.into(),
+            fetcher: Fetcher::new(client, delay, RetryPolicy::default()),
+            search_url: source.search_url().to_string(),
+            sparql_url: source.sparql_url().to_string(),
+            offline: false,
+            cache_path: None,
+            cache: LookupCache::default(),
+        })
+    }
+
+    /// Send lookups to these URLs instead of the source's public ones,
+    /// e.g. a mirror
+    pub fn with_endpoints(mut self, search_url: impl Into<String>, sparql_url: impl Into<String>) -> Self {
+        self.search_url = search_url.into();
+        self.sparql_url = sparql_url.into();
+        self
+    }
+
+    /// Only use cached lookups
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Read and write lookups at `path`
+    pub fn with_cache<P: AsRef<Path>>(mut self, path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        if path.exists() {
+            let content = fs::read_to_string(&path)
+                .storage_context(|| format!("Failed to read enrichment cache: {}", path.display()))?;
+            self.cache = serde_json::from_str(&content)
+                .storage_context(|| format!("Failed to parse enrichment cache: {}", path.display()))?;
+        }
+        self.cache_path = Some(path);
+        Ok(self)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.cache_path else {
+            return Ok(());
+        };
+        let content = serde_json::to_string(&self.cache)
+            .storage_context(|| "Failed to serialize enrichment cache")?;
+        fs::write(path, content)
+            .storage_context(|| format!("Failed to write enrichment cache: {}", path.display()))
+    }
+
+    /// Look up the labelled entities of `knowledge_graph` that are not yet
+    /// linked to the source; only exact label matches (ignoring case) are
+    /// linked. Entities of the sources themselves, such as property values
+    /// stored by earlier runs, and the classes and properties of the schema
+    /// are not looked up. The triples to store are returned in the report.
+    pub async fn enrich(&mut self, knowledge_graph: &KnowledgeGraph, options: &EnrichOptions) -> Result<EnrichReport> {
+        let triples = knowledge_graph.triples();
+        let schema = knowledge_graph.schema();
+        let mut terms: HashSet<String> = schema.classes.keys().chain(schema.predicates.keys())
+            .map(|name| schema.resolve_term(name))
+            .collect();
+        for triple in triples.iter().filter(|t| t.predicate == RDF_TYPE) {
+            // Anything used as a type is a class
+            terms.insert(triple.object.clone());
+            if TERM_TYPES.contains(&triple.object.as_str()) {
+                terms.insert(triple.subject.clone());
+            }
+        }
+        let external = |uri: &str| ExternalSource::ALL.iter().any(|source| uri.starts_with(source.namespace()));
+
+        let linked: HashSet<&str> = triples.iter()
+            .filter(|t| t.predicate == OWL_SAME_AS && t.object.starts_with(self.source.namespace()))
+            .map(|t| t.subject.as_str())
+            .collect();
+        let typed: HashSet<&str> = triples.iter()
+            .filter(|t| t.predicate == RDF_TYPE && options.types.contains(&t.object))
+            .map(|t| t.subject.as_str())
+            .collect();
+
+        let mut report = EnrichReport::default();
+        let mut entities: Vec<(String, String)> = entity_labels(triples).into_iter()
+            .filter(|(uri, _)| options.types.is_empty() || typed.contains(uri.as_str()))
+            .filter(|(uri, _)| !external(uri) && !terms.contains(uri))
+            .collect();
+        entities.sort();
+        for (uri, label) in entities {
+            if linked.contains(uri.as_str()) {
+                report.already_linked += 1;
+                continue;
+            }
+            if options.limit.is_some_and(|limit| report.linked.len() + report.not_found.len() >= limit) {
+                break;
+            }
+
+            let key = format!("{}|{}|{}", self.source.name(), self.language, label.to_lowercase());
+            let found = match self.cache.entries.get(&key) {
+                Some(cached) => cached.clone(),
+                None if self.offline => {
+                    report.uncached += 1;
+                    continue;
+                }
+                None => match self.lookup(&label).await {
+                    Ok(found) => {
+                        self.cache.entries.insert(key, found.clone());
+                        found
+                    }
+                    Err(e) => {
+                        warn!("Could not look up \"{}\" in {}: {}", label, self.source.name(), e);
+                        report.failed += 1;
+                        continue;
+                    }
+                },
+            };
+
+            match found {
+                Some(entity) => {
+                    report.triples.extend(self.entity_triples(&uri, &entity, &options.properties));
+                    report.linked.push(ExternalLink { uri, label, target: entity.uri });
+                }
+                None => report.not_found.push(label),
+            }
+        }
+        Ok(report)
+    }
+
+    /// `owl:sameAs` link and selected properties of a matched entity, with
+    /// the labels of the property values
+    fn entity_triples(&self, uri: &str, entity: &ExternalEntity, properties: &[ExternalProperty]) -> Vec<RdfTriple> {
+        let with_source = |mut triple: RdfTriple| {
+            triple.source = Some(entity.uri.clone());
+            triple
+        };
+        let mut triples = vec![with_source(RdfTriple::new(uri.to_string(), OWL_SAME_AS.to_string(), entity.uri.clone()))];
+        for property in properties {
+            for value in entity.properties.get(property).into_iter().flatten() {
+                triples.push(with_source(RdfTriple::new(
+                    uri.to_string(),
+                    self.source.predicate(*property).to_string(),
+                    value.uri.clone(),
+                )));
+                if let Some(label) = &value.label {
+                    let mut triple = RdfTriple::new(value.uri.clone(), RDFS_LABEL.to_string(), label.clone());
+                    triple.metadata.insert("lang".to_string(), self.language.clone());
+                    triples.push(with_source(triple));
+                }
+            }
+        }
+        triples
+    }
+
+    /// The source's entity labelled exactly `label`, with its properties
+    async fn lookup(&self, label: &str) -> Result<Option<ExternalEntity>> {
+        let Some((uri, matched)) = self.search(label).await? else {
+            debug!("No {} entity labelled \"{}\"", self.source.name(), label);
+            return Ok(None);
+        };
+        let properties = self.properties(&uri).await?;
+        Ok(Some(ExternalEntity { uri, label: matched, properties }))
+    }
+
+    /// IRI and label of the first search hit whose label or alias is `label`
+    async fn search(&self, label: &str) -> Result<Option<(String, String)>> {
+        let limit = SEARCH_HITS.to_string();
+        let params: Vec<(&str, &str)> = match self.source {
+            ExternalSource::Wikidata => vec![
+                ("action", "wbsearchentities"),
+                ("search", label),
+                ("language", &self.language),
+                ("uselang", &self.language),
+                ("type", "item"),
+                ("limit", &limit),
+                ("format", "json"),
+            ],
+            ExternalSource::Dbpedia => vec![("query", label), ("maxResults", &limit), ("format", "json")],
+        };
+        let json = self.get_json(&self.search_url, &params).await?;
+
+        let same = |candidate: &str| candidate.trim().eq_ignore_ascii_case(label.trim());
+        let hit = match self.source {
+            ExternalSource::Wikidata => json.get("search").and_then(Value::as_array).into_iter().flatten()
+                .find_map(|hit| {
+                    let uri = hit.get("concepturi").and_then(Value::as_str)?;
+                    let hit_label = hit.get("label").and_then(Value::as_str).unwrap_or_default();
+                    let matched = hit.pointer("/match/text").and_then(Value::as_str).unwrap_or(hit_label);
+                    (same(hit_label) || same(matched)).then(|| (uri.to_string(), hit_label.to_string()))
+                }),
+            ExternalSource::Dbpedia => json.get("docs").and_then(Value::as_array).into_iter().flatten()
+                .find_map(|doc| {
+                    let uri = doc.pointer("/resource/0").and_then(Value::as_str)?;
+                    let hit_label = strip_highlight(doc.pointer("/label/0").and_then(Value::as_str)?);
+                    same(&hit_label).then(|| (uri.to_string(), hit_label))
+                }),
+        };
+        Ok(hit)
+    }
+
+    /// Type, country and industry of the source's entity `uri`, each with
+    /// its label when available
+    async fn properties(&self, uri: &str) -> Result<BTreeMap<ExternalProperty, Vec<ExternalValue>>> {
+        let pairs: Vec<String> = ExternalProperty::ALL.iter()
+            .map(|property| format!("(\"{}\" <{}>)", property.name(), self.source.predicate(*property)))
+            .collect();
+        // DBpedia types include many from other ontologies; keep its own
+        let type_filter = match self.source {
+            ExternalSource::Dbpedia => "FILTER(?property != \"type\" || STRSTARTS(STR(?value), \"http://dbpedia.org/ontology/\"))",
+            ExternalSource::Wikidata => "",
+        };
+        let query = format!(
+            "SELECT ?property ?value ?valueLabel WHERE {{\n  VALUES (?property ?p) {{ {} }}\n  <{}> ?p ?value .\n  {}\n  \
+             OPTIONAL {{ ?value <{}> ?valueLabel FILTER(LANG(?valueLabel) = \"{}\") }}\n}}",
+            pairs.join(" "), uri, type_filter, RDFS_LABEL, self.language
+        );
+        let json = self.get_json(&self.sparql_url, &[("query", &query), ("format", "json")]).await?;
+        let SimpleSparqlResults::Solutions { rows, .. } = parse_results_json(&json)? else {
+            return Ok(BTreeMap::new());
+        };
+
+        let mut properties: BTreeMap<ExternalProperty, Vec<ExternalValue>> = BTreeMap::new();
+        for row in rows {
+            let property = ExternalProperty::ALL.into_iter()
+                .find(|p| matches!(row.get("property"), Some(Binding::Literal { value, .. }) if value == p.name()));
+            let (Some(property), Some(Binding::Iri { value })) = (property, row.get("value")) else {
+                continue;
+            };
+            let label = match row.get("valueLabel") {
+                Some(Binding::Literal { value, .. }) => Some(value.clone()),
+                _ => None,
+            };
+            let values = properties.entry(property).or_default();
+            if !values.iter().any(|v| &v.uri == value) {
+                values.push(ExternalValue { uri: value.clone(), label });
+            }
+        }
+        Ok(properties)
+    }
+
+    async fn get_json(&self, base: &str, params: &[(&str, &str)]) -> Result<Value> {
+        let url = Url::parse_with_params(base, params)
+            .storage_context(|| format!("Invalid lookup URL: {}", base))?;
+        let response = self.fetcher.get(url.as_str()).await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(Error::storage(format!("{} returned HTTP {}", base, status)));
+        }
+        response.json().await
+            .storage_context(|| format!("{} did not return JSON", base))
+    }
+}
+
+/// DBpedia Lookup marks the matched words with `<B>...</B>`
+fn strip_highlight(label: &str) -> String {
+    label.replace("<B>", "").replace("</B>", "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RdfSchema;
+
+    #[tokio::test]
+    async fn test_wikidata_enrichment() {
+        let mut server = mockito::Server::new_async().await;
+        let search = server.mock("GET", "/w/api.php")
+            .match_query(mockito::Matcher::UrlEncoded("search".into(), "Acme Corporation".into()))
+            .with_body(r#"{"search": [
+                {"concepturi": "http://www.wikidata.org/entity/Q1", "label": "Acme Corporation Ltd"},
+                {"concepturi": "http://www.wikidata.org/entity/Q2", "label": "ACME Corp", "match": {"type": "alias", "text": "Acme Corporation"}}
+            ]}"#)
+            .expect(1)
+            .create_async().await;
+        server.mock("GET", "/w/api.php")
+            .match_query(mockito::Matcher::UrlEncoded("search".into(), "Nowhere Inc".into()))
+            .with_body(r#"{"search": []}"#)
+            .create_async().await;
+        let sparql = server.mock("GET", "/sparql")
+            .match_query(mockito::Matcher::Any)
+            .with_body(r#"{"head": {"vars": ["property", "value", "valueLabel"]}, "results": {"bindings": [
+                {"property": {"type": "literal", "value": "type"}, "value": {"type": "uri", "value": "http://www.wikidata.org/entity/Q4830453"},
+                 "valueLabel": {"type": "literal", "xml:lang": "en", "value": "business"}},
+                {"property": {"type": "literal", "value": "country"}, "value": {"type": "uri", "value": "http://www.wikidata.org/entity/Q30"},
+                 "valueLabel": {"type": "literal", "xml:lang": "en", "value": "United States"}},
+                {"property": {"type": "literal", "value": "industry"}, "value": {"type": "uri", "value": "http://www.wikidata.org/entity/Q11016"}}
+            ]}}"#)
+            .expect(1)
+            .create_async().await;
+
+        let mut kg = KnowledgeGraph::in_memory(RdfSchema::default()).unwrap();
+        kg.add_triples(&[
+            RdfTriple::new("http://ex.org/acme".into(), RDFS_LABEL.into(), "Acme Corporation".into()),
+            RdfTriple::new("http://ex.org/nowhere".into(), RDFS_LABEL.into(), "Nowhere Inc".into()),
+            RdfTriple::new("http://ex.org/globex".into(), RDFS_LABEL.into(), "Globex".into()),
+            RdfTriple::new("http://ex.org/globex".into(), OWL_SAME_AS.into(), "http://www.wikidata.org/entity/Q3".into()),
+        ]).unwrap();
+
+        let cache_path = std::env::temp_dir().join(format!("enrichment_test_{}.json", uuid::Uuid::new_v4()));
+        let enricher = |offline: bool| Enricher::new(ExternalSource::Wikidata, "en", Duration::ZERO).unwrap()
+            .with_endpoints(format!("{}/w/api.php", server.url()), format!("{}/sparql", server.url()))
+            .offline(offline)
+            .with_cache(&cache_path)
+            .unwrap();
+        let options = EnrichOptions { properties: vec![ExternalProperty::Country], ..EnrichOptions::default() };
+
+        let mut online = enricher(false);
+        let report = online.enrich(&kg, &options).await.unwrap();
+        online.save().unwrap();
+        assert_eq!(report.linked, [ExternalLink {
+            uri: "http://ex.org/acme".into(),
+            label: "Acme Corporation".into(),
+            target: "http://www.wikidata.org/entity/Q2".into(),
+        }]);
+        assert_eq!(report.not_found, ["Nowhere Inc"]);
+        assert_eq!(report.already_linked, 1);
+        let stored: Vec<(&str, &str, &str)> = report.triples.iter()
+            .map(|t| (t.subject.as_str(), t.predicate.as_str(), t.object.as_str()))
+            .collect();
+        assert_eq!(stored, [
+            ("http://ex.org/acme", OWL_SAME_AS, "http://www.wikidata.org/entity/Q2"),
+            ("http://ex.org/acme", "http://www.wikidata.org/prop/direct/P17", "http://www.wikidata.org/entity/Q30"),
+            ("http://www.wikidata.org/entity/Q30", RDFS_LABEL, "United States"),
+        ]);
+
+        // Offline runs answer from the cache, including misses and other properties
+        let options = EnrichOptions { properties: vec![ExternalProperty::Type, ExternalProperty::Industry], ..options };
+        let report = enricher(true).enrich(&kg, &options).await.unwrap();
+        assert_eq!((report.linked.len(), report.not_found.len(), report.uncached), (1, 1, 0));
+        assert_eq!(report.triples.len(), 4);
+        search.assert_async().await;
+        sparql.assert_async().await;
+
+        fs::remove_file(cache_path).ok();
+    }
+
+    #[tokio::test]
+    async fn test_dbpedia_enrichment() {
+        let mut server = mockito::Server::new_async().await;
+        let search = server.mock("GET", "/api/search")
+            .match_query(mockito::Matcher::UrlEncoded("query".into(), "Acme Corporation".into()))
+            .with_body(r#"{"docs": [
+                {"resource": ["http://dbpedia.org/resource/Acme_Holdings"], "label": ["<B>Acme</B> Holdings"]},
+                {"resource": ["http://dbpedia.org/resource/Acme_Corporation"], "label": ["<B>Acme</B> <B>Corporation</B>"]}
+            ]}"#)
+            .expect(1)
+            .create_async().await;
+        let sparql = server.mock("GET", "/sparql")
+            .match_query(mockito::Matcher::Any)
+            .with_body(r#"{"head": {"vars": ["property", "value", "valueLabel"]}, "results": {"bindings": [
+                {"property": {"type": "literal", "value": "type"}, "value": {"type": "uri", "value": "http://dbpedia.org/ontology/Company"},
+                 "valueLabel": {"type": "literal", "xml:lang": "en", "value": "company"}}
+            ]}}"#)
+            .expect(1)
+            .create_async().await;
+
+        let mut schema = RdfSchema::default();
+        schema.classes.insert("Organization".into(), "A company".into());
+        let class = schema.resolve_term("Organization");
+        let mut kg = KnowledgeGraph::in_memory(schema).unwrap();
+        kg.add_triples(&[
+            RdfTriple::new("http://ex.org/acme".into(), RDFS_LABEL.into(), "Acme Corporation".into()),
+            RdfTriple::new("http://ex.org/acme".into(), RDF_TYPE.into(), class.clone()),
+            // Schema terms and values stored by earlier runs are not entities to look up
+            RdfTriple::new(class, RDFS_LABEL.into(), "Organization".into()),
+            RdfTriple::new("http://ex.org/worksFor".into(), RDF_TYPE.into(), TERM_TYPES[3].into()),
+            RdfTriple::new("http://ex.org/worksFor".into(), RDFS_LABEL.into(), "works for".into()),
+            RdfTriple::new("http://dbpedia.org/resource/United_States".into(), RDFS_LABEL.into(), "United States".into()),
+        ]).unwrap();
+
+        let mut enricher = Enricher::new(ExternalSource::Dbpedia, "en", Duration::ZERO).unwrap()
+            .with_endpoints(format!("{}/api/search", server.url()), format!("{}/sparql", server.url()));
+        let options = EnrichOptions { properties: vec![ExternalProperty::Type], ..EnrichOptions::default() };
+        let report = enricher.enrich(&kg, &options).await.unwrap();
+        assert_eq!(report.linked, [ExternalLink {
+            uri: "http://ex.org/acme".into(),
+            label: "Acme Corporation".into(),
+            target: "http://dbpedia.org/resource/Acme_Corporation".into(),
+        }]);
+        assert!(report.not_found.is_empty() && report.failed == 0, "{:?}", report);
+        let stored: Vec<(&str, &str)> = report.triples.iter().map(|t| (t.predicate.as_str(), t.object.as_str())).collect();
+        assert_eq!(stored, [
+            (OWL_SAME_AS, "http://dbpedia.org/resource/Acme_Corporation"),
+            (RDF_TYPE, "http://dbpedia.org/ontology/Company"),
+            (RDFS_LABEL, "company"),
+        ]);
+        search.assert_async().await;
+        sparql.assert_async().await;
+    }
+}
@@ -0,0 +1,563 @@
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+use super::{Binding, KnowledgeGraph, SimpleSparqlResults};
+use crate::error::{Error, ErrorContext, Result};
+
+/// Local solutions sent to a remote endpoint per request, as a `VALUES` block
+const VALUES_BATCH: usize = 100;
+
+/// A remote SPARQL 1.1 query endpoint, such as Wikidata's
+/// `https://query.wikidata.org/sparql` or a corporate triple store
+pub struct SparqlEndpoint {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl SparqlEndpoint {
+    pub fn new(url: impl Into<String>) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(60))
+            // Public endpoints such as Wikidata reject requests without one
+            .user_agent(concat!("rdf_knowledge_extractor/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .storage_context(|| "Failed to create HTTP client")?;
+        Ok(Self { client, url: url.into() })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Run a SELECT or ASK query and read its SPARQL JSON results
+    pub async fn query(&self, query: &str) -> Result<SimpleSparqlResults> {
+        debug!("Querying {}: {}", self.url, query);
+        let body = url::form_urlencoded::Serializer::new(String::new())
+            .append_pair("query", query)
+            .finish();
+        let response = self.client.post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .header(reqwest::header::ACCEPT, "application/sparql-results+json")
+            .body(body)
+            .send().await
+            .storage_context(|| format!("Failed to reach SPARQL endpoint {}", self.url))?;
+
+        let status = response.status();
+        let text = response.text().await
+            .storage_context(|| format!("Failed to read results from {}", self.url))?;
+        if !status.is_success() {
+            let message: String = text.trim().chars().take(500).collect();
+            return Err(Error::storage(format!("SPARQL endpoint {} returned HTTP {}: {}", self.url, status, message)));
+        }
+
+        let json: Value = serde_json::from_str(&text)
+            .storage_context(|| format!("SPARQL endpoint {} did not return JSON results", self.url))?;
+        parse_results_json(&json)
+            .storage_context(|| format!("Unexpected results from SPARQL endpoint {}", self.url))
+    }
+}
+
+/// Run `query` against the knowledge graph, fetching the patterns in its
+/// top-level `SERVICE <url> { ... }` blocks from those endpoints
+///
+/// The rest of the WHERE clause runs locally first. Each SERVICE block is
+/// then sent with the local bindings of the variables it shares, as a
+/// `VALUES` block, and its solutions are joined with the local ones.
+/// `SERVICE SILENT` keeps the local solutions when the endpoint fails.
+/// Projection, `DISTINCT`, `LIMIT` and `OFFSET` apply to the joined
+/// solutions; aggregates, `GROUP BY` and `ORDER BY` cannot be combined with
+/// SERVICE. Queries without SERVICE run locally as they are.
+pub async fn execute_federated(knowledge_graph: &KnowledgeGraph, query: &str) -> Result<SimpleSparqlResults> {
+    let Some(federated) = FederatedQuery::parse(query)? else {
+        return knowledge_graph.execute_sparql(query);
+    };
+
+    let (mut variables, mut rows) = if federated.local.trim().is_empty() {
+        (Vec::new(), vec![HashMap::new()])
+    } else {
+        let local = format!("{}SELECT * WHERE {{{}}}", federated.prologue, federated.local);
+        match knowledge_graph.execute_sparql(&local)? {
+            SimpleSparqlResults::Solutions { variables, rows } => (SimpleSparqlResults::columns(&variables, &rows), rows),
+            SimpleSparqlResults::Boolean(_) => return Err(Error::storage("Expected solutions from the local part of the query")),
+        }
+    };
+
+    for service in &federated.services {
+        if rows.is_empty() {
+            break;
+        }
+        let endpoint = SparqlEndpoint::new(&service.endpoint)?;
+        match join_service(&endpoint, &federated.prologue, &service.pattern, &rows).await {
+            Ok((service_variables, joined)) => {
+                for variable in service_variables {
+                    if !variables.contains(&variable) {
+                        variables.push(variable);
+                    }
+                }
+                rows = joined;
+            }
+            Err(e) if service.silent => warn!("Ignoring failed SERVICE SILENT {}: {}", service.endpoint, e),
+            Err(e) => return Err(e),
+        }
+    }
+
+    if let Some(projection) = &federated.projection {
+        for row in &mut rows {
+            row.retain(|variable, _| projection.contains(variable));
+        }
+        variables = projection.clone();
+    }
+    if federated.distinct {
+        let mut seen = HashSet::new();
+        rows.retain(|row| {
+            let key: Vec<Option<String>> = variables.iter().map(|v| row.get(v).map(Binding::to_ntriples)).collect();
+            seen.insert(key)
+        });
+    }
+    let rows = rows.into_iter()
+        .skip(federated.offset)
+        .take(federated.limit.unwrap_or(usize::MAX))
+        .collect();
+
+    Ok(SimpleSparqlResults::Solutions { variables, rows })
+}
+
+/// Run a query on `endpoint` when one is given, otherwise as `execute_federated`
+pub async fn execute_on(knowledge_graph: &KnowledgeGraph, endpoint: Option<&str>, query: &str) -> Result<SimpleSparqlResults> {
+    match endpoint {
+        Some(url) => SparqlEndpoint::new(url)?.query(query).await,
+        None => execute_federated(knowledge_graph, query).await,
+    }
+}
+
+/// Whether the query has a SERVICE block, outside strings and comments
+pub fn uses_service(query: &str) -> bool {
+    find_keyword(query, &code_mask(query), 0, "SERVICE").is_some()
+}
+
+/// Solutions of `pattern` on the endpoint joined with `rows`, and the
+/// variables the endpoint returned
+async fn join_service(
+    endpoint: &SparqlEndpoint,
+    prologue: &str,
+    pattern: &str,
+    rows: &[HashMap<String, Binding>],
+) -> Result<(Vec<String>, Vec<HashMap<String, Binding>>)> {
+    let bound: HashSet<&String> = rows.iter().flat_map(|row| row.keys()).collect();
+    let shared: Vec<String> = pattern_variables(pattern).into_iter()
+        .filter(|variable| bound.contains(variable))
+        .collect();
+
+    // Distinct local values of the shared variables; blank nodes cannot be sent
+    let mut tuples: Vec<Vec<Option<&Binding>>> = Vec::new();
+    let mut seen = HashSet::new();
+    for row in rows {
+        let tuple: Vec<Option<&Binding>> = shared.iter()
+            .map(|variable| row.get(variable).filter(|b| !matches!(b, Binding::BlankNode { .. })))
+            .collect();
+        if seen.insert(tuple.iter().map(|b| b.map(Binding::to_ntriples)).collect::<Vec<_>>()) {
+            tuples.push(tuple);
+        }
+    }
+
+    let queries: Vec<String> = if shared.is_empty() {
+        vec![format!("{}SELECT * WHERE {{{}}}", prologue, pattern)]
+    } else {
+        let head = shared.iter().map(|v| format!("?{}", v)).collect::<Vec<_>>().join(" ");
+        tuples.chunks(VALUES_BATCH)
+            .map(|batch| {
+                let values: Vec<String> = batch.iter()
+                    .map(|tuple| {
+                        let terms: Vec<String> = tuple.iter()
+                            .map(|b| b.map(Binding::to_ntriples).unwrap_or_else(|| "UNDEF".to_string()))
+                            .collect();
+                        format!("({})", terms.join(" "))
+                    })
+                    .collect();
+                format!("{}SELECT * WHERE {{ VALUES ({}) {{ {} }} {} }}", prologue, head, values.join(" "), pattern)
+            })
+            .collect()
+    };
+
+    let mut variables = Vec::new();
+    let mut remote_rows = Vec::new();
+    for query in queries {
+        match endpoint.query(&query).await? {
+            SimpleSparqlResults::Solutions { variables: remote_variables, rows } => {
+                for variable in SimpleSparqlResults::columns(&remote_variables, &rows) {
+                    if !variables.contains(&variable) {
+                        variables.push(variable);
+                    }
+                }
+                remote_rows.extend(rows);
+            }
+            SimpleSparqlResults::Boolean(_) => {
+                return Err(Error::storage(format!("SPARQL endpoint {} answered a SELECT query with a boolean", endpoint.url())));
+            }
+        }
+    }
+    debug!("{} solutions from {}", remote_rows.len(), endpoint.url());
+
+    let mut joined = Vec::new();
+    for row in rows {
+        for remote in &remote_rows {
+            let compatible = shared.iter().all(|variable| match (row.get(variable), remote.get(variable)) {
+                (Some(local), Some(remote)) => local == remote,
+                _ => true,
+            });
+            if compatible {
+                let mut merged = row.clone();
+                for (variable, binding) in remote {
+                    merged.entry(variable.clone()).or_insert_with(|| binding.clone());
+                }
+                joined.push(merged);
+            }
+        }
+    }
+    Ok((variables, joined))
+}
+
+/// Parse SPARQL 1.1 Query Results JSON
+pub fn parse_results_json(json: &Value) -> Result<SimpleSparqlResults> {
+    if let Some(boolean) = json.get("boolean").and_then(Value::as_bool) {
+        return Ok(SimpleSparqlResults::Boolean(boolean));
+    }
+
+    let variables: Vec<String> = json.pointer("/head/vars")
+        .and_then(Value::as_array)
+        .map(|vars| vars.iter().filter_map(Value::as_str).map(str::to_string).collect())
+        .unwrap_or_default();
+    let bindings = json.pointer("/results/bindings")
+        .and_then(Value::as_array)
+        .storage_context(|| "Missing results.bindings")?;
+
+    let mut rows = Vec::new();
+    for solution in bindings {
+        let solution = solution.as_object().storage_context(|| "A solution is not an object")?;
+        let mut row = HashMap::new();
+        for (variable, term) in solution {
+            row.insert(variable.clone(), binding_from_json(term)?);
+        }
+        rows.push(row);
+    }
+    Ok(SimpleSparqlResults::Solutions { variables, rows })
+}
+
+fn binding_from_json(term: &Value) -> Result<Binding> {
+    let value = term.get("value").and_then(Value::as_str).storage_context(|| "A term has no value")?;
+    match term.get("type").and_then(Value::as_str) {
+        Some("uri") => Ok(Binding::iri(value)),
+        Some("bnode") => Ok(Binding::BlankNode { value: format!("_:{}", value) }),
+        Some("literal" | "typed-literal") => {
+            if let Some(lang) = term.get("xml:lang").and_then(Value::as_str) {
+                Ok(Binding::lang_literal(value, lang))
+            } else if let Some(datatype) = term.get("datatype").and_then(Value::as_str) {
+                Ok(Binding::typed_literal(value, datatype))
+            } else {
+                Ok(Binding::literal(value))
+            }
+        }
+        other => Err(Error::storage(format!("Unknown term type: {:?}", other))),
+    }
+}
+
+/// A SELECT query split into its local part and its SERVICE blocks
+#[derive(Debug)]
+struct FederatedQuery {
+    /// PREFIX and BASE declarations, sent with every part
+    prologue: String,
+    distinct: bool,
+    /// `None` for `SELECT *`
+    projection: Option<Vec<String>>,
+    /// The WHERE clause without its SERVICE blocks
+    local: String,
+    services: Vec<Service>,
+    limit: Option<usize>,
+    offset: usize,
+}
+
+#[derive(Debug)]
+struct Service {
+    endpoint: String,
+    silent: bool,
+    pattern: String,
+}
+
+impl FederatedQuery {
+    /// `None` when the query has no SERVICE block
+    fn parse(query: &str) -> Result<Option<Self>> {
+        if !uses_service(query) {
+            return Ok(None);
+        }
+        let code = code_mask(query);
+
+        let select = find_keyword(query, &code, 0, "SELECT")
+            .query_context(|| "Only SELECT queries can use SERVICE")?;
+        let open = (select..query.len()).find(|&i| code[i] && query.as_bytes()[i] == b'{')
+            .storage_context(|| "Missing WHERE clause")?;
+        let close = matching_brace(query, &code, open).query_context(|| "Unbalanced braces in WHERE clause")?;
+
+        let mut projection_text = query[select + "SELECT".len()..open].trim();
+        if projection_text.len() >= 5 && projection_text[projection_text.len() - 5..].eq_ignore_ascii_case("WHERE") {
+            projection_text = projection_text[..projection_text.len() - 5].trim_end();
+        }
+        let mut distinct = false;
+        let mut projection = Some(Vec::new());
+        for token in projection_text.split_whitespace() {
+            if token.eq_ignore_ascii_case("DISTINCT") || token.eq_ignore_ascii_case("REDUCED") {
+                distinct = true;
+            } else if token == "*" {
+                projection = None;
+            } else if let Some(variable) = token.strip_prefix(['?', '$']) {
+                if let Some(variables) = &mut projection {
+                    variables.push(variable.to_string());
+                }
+            } else {
+                return Err(Error::query("Aggregates and expressions in SELECT cannot be combined with SERVICE"));
+            }
+        }
+
+        let (limit, offset) = parse_modifiers(&query[close + 1..])?;
+
+        let mut local = String::new();
+        let mut services = Vec::new();
+        let mut position = open + 1;
+        while let Some(start) = find_keyword(query, &code, position, "SERVICE").filter(|&start| start < close) {
+            let depth = (open + 1..start).fold(0i32, |depth, i| match (code[i], query.as_bytes()[i]) {
+                (true, b'{') => depth + 1,
+                (true, b'}') => depth - 1,
+                _ => depth,
+            });
+            if depth != 0 {
+                return Err(Error::storage("SERVICE is only supported at the top level of the WHERE clause"));
+            }
+            local.push_str(&query[position..start]);
+
+            let mut cursor = start + "SERVICE".len();
+            let rest = query[cursor..].trim_start();
+            cursor = query.len() - rest.len();
+            let silent = rest.len() >= 6 && rest[..6].eq_ignore_ascii_case("SILENT");
+            if silent {
+                cursor += 6;
+            }
+            let rest = query[cursor..].trim_start();
+            cursor = query.len() - rest.len();
+            let endpoint = rest.strip_prefix('<')
+                .and_then(|rest| rest.split_once('>'))
+                .map(|(iri, _)| iri.to_string())
+                .query_context(|| "SERVICE needs an endpoint IRI such as <https://query.wikidata.org/sparql>")?;
+            cursor += endpoint.len() + 2;
+
+            let block = (cursor..close).find(|&i| code[i] && query.as_bytes()[i] == b'{')
+                .storage_context(|| format!("Missing pattern for SERVICE <{}>", endpoint))?;
+            let block_end = matching_brace(query, &code, block)
+                .storage_context(|| format!("Unbalanced braces in SERVICE <{}>", endpoint))?;
+            services.push(Service { endpoint, silent, pattern: query[block + 1..block_end].to_string() });
+
+            // Drop the `.` that may separate the block from the next pattern
+            position = block_end + 1;
+            let rest = query[position..close].trim_start();
+            if rest.starts_with('.') {
+                position = close - rest.len() + 1;
+            }
+        }
+        local.push_str(&query[position..close]);
+
+        Ok(Some(Self {
+            prologue: query[..select].to_string(),
+            distinct,
+            projection,
+            local,
+            services,
+            limit,
+            offset,
+        }))
+    }
+}
+
+fn parse_modifiers(text: &str) -> Result<(Option<usize>, usize)> {
+    let mut limit = None;
+    let mut offset = 0;
+    let mut tokens = text.split_whitespace();
+    while let Some(token) = tokens.next() {
+        let value = |tokens: &mut std::str::SplitWhitespace| tokens.next()
+            .and_then(|n| n.parse::<usize>().ok())
+            .storage_context(|| format!("{} needs a number", token.to_uppercase()));
+        if token.eq_ignore_ascii_case("LIMIT") {
+            limit = Some(value(&mut tokens)?);
+        } else if token.eq_ignore_ascii_case("OFFSET") {
+            offset = value(&mut tokens)?;
+        } else {
+            return Err(Error::storage("Only LIMIT and OFFSET can follow a WHERE clause with SERVICE"));
+        }
+    }
+    Ok((limit, offset))
+}
+
+/// Variables named in a pattern, in order of first appearance
+fn pattern_variables(pattern: &str) -> Vec<String> {
+    let code = code_mask(pattern);
+    let bytes = pattern.as_bytes();
+    let mut variables = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if code[i] && (bytes[i] == b'?' || bytes[i] == b'$') {
+            let end = (i + 1..bytes.len())
+                .find(|&j| !(bytes[j].is_ascii_alphanumeric() || bytes[j] == b'_'))
+                .unwrap_or(bytes.len());
+            let name = pattern[i + 1..end].to_string();
+            if !name.is_empty() && !variables.contains(&name) {
+                variables.push(name);
+            }
+            i = end;
+        } else {
+            i += 1;
+        }
+    }
+    variables
+}
+
+/// Whether each byte of the query is outside string literals, IRIs and comments
+fn code_mask(query: &str) -> Vec<bool> {
+    let bytes = query.as_bytes();
+    let mut code = vec![true; bytes.len()];
+    let mut i = 0;
+    while i < bytes.len() {
+        let end = match bytes[i] {
+            quote @ (b'"' | b'\'') => {
+                let mut j = i + 1;
+                while j < bytes.len() && bytes[j] != quote {
+                    j += if bytes[j] == b'\\' { 2 } else { 1 };
+                }
+                j.min(bytes.len() - 1)
+            }
+            // `<` opens an IRI unless whitespace comes first, as in `?a < 5`
+            b'<' => match (i + 1..bytes.len()).find(|&j| bytes[j] == b'>' || bytes[j].is_ascii_whitespace()) {
+                Some(j) if bytes[j] == b'>' => j,
+                _ => {
+                    i += 1;
+                    continue;
+                }
+            },
+            b'#' => (i..bytes.len()).find(|&j| bytes[j] == b'\n').unwrap_or(bytes.len() - 1),
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+        code[i..=end].iter_mut().for_each(|c| *c = false);
+        i = end + 1;
+    }
+    code
+}
+
+/// Start of the first `keyword` at or after `from`, as a whole word outside
+/// strings, IRIs and comments
+fn find_keyword(query: &str, code: &[bool], from: usize, keyword: &str) -> Option<usize> {
+    let bytes = query.as_bytes();
+    let is_word = |b: u8| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'?' | b'$' | b':');
+    (from..=query.len().saturating_sub(keyword.len())).find(|&i| {
+        code[i]
+            && bytes[i..i + keyword.len()].eq_ignore_ascii_case(keyword.as_bytes())
+            && (i == 0 || !is_word(bytes[i - 1]))
+            && bytes.get(i + keyword.len()).is_none_or(|&b| !is_word(b))
+    })
+}
+
+fn matching_brace(query: &str, code: &[bool], open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, byte) in query.bytes().enumerate().skip(open) {
+        match (code[i], byte) {
+            (true, b'{') => depth += 1,
+            (true, b'}') => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RdfSchema;
+    use crate::core::RdfTriple;
+
+    #[test]
+    fn test_federated_query_parsing() {
+        let query = "PREFIX wdt: <http://www.wikidata.org/prop/direct/>\n\
+            SELECT DISTINCT ?name ?population WHERE {\n\
+              ?city hasName ?name ; sameAs ?item .\n\
+              SERVICE SILENT <https://query.wikidata.org/sparql> { ?item wdt:P1082 ?population . FILTER(?population > \"{\") } .\n\
+              # SERVICE <http://ignored> { }\n\
+              FILTER(?name != \"SERVICE\")\n\
+            } LIMIT 5 OFFSET 1";
+        let federated = FederatedQuery::parse(query).unwrap().unwrap();
+        assert!(federated.prologue.starts_with("PREFIX wdt:"));
+        assert!(federated.distinct);
+        assert_eq!(federated.projection, Some(vec!["name".to_string(), "population".to_string()]));
+        assert_eq!((federated.limit, federated.offset), (Some(5), 1));
+        assert_eq!(federated.services.len(), 1);
+        assert!(federated.services[0].silent);
+        assert_eq!(federated.services[0].endpoint, "https://query.wikidata.org/sparql");
+        assert_eq!(pattern_variables(&federated.services[0].pattern), ["item", "population"]);
+        assert!(!federated.local.contains("wdt:") && federated.local.contains("FILTER(?name"));
+        assert!(!federated.local.trim_start().starts_with('.'));
+
+        assert!(FederatedQuery::parse("SELECT ?s WHERE { ?s ?p \"SERVICE\" }").unwrap().is_none());
+        assert!(FederatedQuery::parse("SELECT (COUNT(?s) AS ?n) WHERE { SERVICE <http://x> { ?s ?p ?o } }").is_err());
+        assert!(FederatedQuery::parse("SELECT * WHERE { OPTIONAL { SERVICE <http://x> { ?s ?p ?o } } }").is_err());
+        assert!(FederatedQuery::parse("SELECT * WHERE { SERVICE <http://x> { ?s ?p ?o } } ORDER BY ?s").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_service_join() {
+        let mut server = mockito::Server::new_async().await;
+        let results = serde_json::json!({
+            "head": { "vars": ["item", "population"] },
+            "results": { "bindings": [
+                { "item": { "type": "uri", "value": "http://www.wikidata.org/entity/Q90" },
+                  "population": { "type": "literal", "value": "2145906", "datatype": "http://www.w3.org/2001/XMLSchema#decimal" } },
+                { "item": { "type": "uri", "value": "http://www.wikidata.org/entity/Q64" },
+                  "population": { "type": "literal", "value": "3755251", "datatype": "http://www.w3.org/2001/XMLSchema#decimal" } },
+            ] }
+        });
+        let endpoint = server.mock("POST", "/sparql")
+            .match_header("accept", "application/sparql-results+json")
+            .match_body(mockito::Matcher::Regex("VALUES".to_string()))
+            .with_header("content-type", "application/sparql-results+json")
+            .with_body(results.to_string())
+            .create_async().await;
+
+        let mut kg = KnowledgeGraph::in_memory(RdfSchema::default()).unwrap();
+        kg.add_triples(&[
+            RdfTriple::new("http://ex.org/paris".into(), "http://ex.org/hasName".into(), "Paris".into()),
+            RdfTriple::new("http://ex.org/paris".into(), "http://ex.org/sameAs".into(), "http://www.wikidata.org/entity/Q90".into()),
+            RdfTriple::new("http://ex.org/lyon".into(), "http://ex.org/hasName".into(), "Lyon".into()),
+            RdfTriple::new("http://ex.org/lyon".into(), "http://ex.org/sameAs".into(), "http://www.wikidata.org/entity/Q456".into()),
+        ]).unwrap();
+
+        let query = format!(
+            "PREFIX ex: <http://ex.org/>\nSELECT ?name ?population WHERE {{ ?city ex:hasName ?name . ?city ex:sameAs ?item . SERVICE <{}/sparql> {{ ?item <http://www.wikidata.org/prop/direct/P1082> ?population }} }}",
+            server.url()
+        );
+        let SimpleSparqlResults::Solutions { variables, rows } = execute_federated(&kg, &query).await.unwrap() else { panic!() };
+        endpoint.assert_async().await;
+        assert_eq!(variables, ["name", "population"]);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["name"].value(), "Paris");
+        assert_eq!(rows[0]["population"].xsd_type(), Some("decimal"));
+        assert!(!rows[0].contains_key("item"));
+
+        // A failing SILENT service keeps the local solutions
+        let query = "SELECT * WHERE { ?city hasName ?name SERVICE SILENT <http://127.0.0.1:9/sparql> { ?city ?p ?o } }";
+        let SimpleSparqlResults::Solutions { rows, .. } = execute_federated(&kg, query).await.unwrap() else { panic!() };
+        assert_eq!(rows.len(), 2);
+    }
+}
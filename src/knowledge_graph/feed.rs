@@ -0,0 +1,291 @@
+//! Machine-readable JSON summaries of entities (properties, relations and
+//! sources), one file per entity, for other applications' detail pages
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::labels::entity_labels;
+use super::KnowledgeGraph;
+use crate::core::RdfTriple;
+use crate::error::{ErrorContext, Result};
+use crate::utils::integrity::sha256_hex;
+use crate::utils::serialization::compact_iri;
+
+const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PropertyValue {
+    pub value: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lang: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub datatype: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    /// The entity is the subject
+    Outgoing,
+    /// The entity is the object
+    Incoming,
+}
+
+/// A link between the entity and another resource
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Relation {
+    pub predicate: String,
+    pub direction: Direction,
+    pub uri: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+/// What the knowledge graph states about one entity; predicates and types
+/// are compacted with the graph's prefixes where possible
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EntitySummary {
+    pub uri: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    pub types: Vec<String>,
+    /// Literal values by predicate
+    pub properties: BTreeMap<String, Vec<PropertyValue>>,
+    pub relations: Vec<Relation>,
+    /// Documents the entity's triples were extracted from
+    pub sources: Vec<String>,
+}
+
+/// An entity's file in the feed, and the hash of its summary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeedEntry {
+    pub file: String,
+    pub hash: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FeedUpdate {
+    pub written: usize,
+    pub unchanged: usize,
+    pub removed: usize,
+}
+
+/// A directory with one `<name>.json` summary per entity and `index.json`
+/// mapping each entity URI to its file. Updating only rewrites the files of
+/// entities whose summary changed and deletes those of removed entities.
+pub struct EntityFeed {
+    dir: PathBuf,
+    index: BTreeMap<String, FeedEntry>,
+}
+
+impl EntityFeed {
+    pub fn open<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        let index_path = dir.join("index.json");
+        let index = if index_path.exists() {
+            let content = fs::read_to_string(&index_path)
+                .storage_context(|| format!("Failed to read entity feed index: {}", index_path.display()))?;
+            serde_json::from_str(&content)
+                .storage_context(|| format!("Failed to parse entity feed index: {}", index_path.display()))?
+        } else {
+            BTreeMap::new()
+        };
+        Ok(Self { dir, index })
+    }
+
+    pub fn entries(&self) -> &BTreeMap<String, FeedEntry> {
+        &self.index
+    }
+
+    /// Bring the feed up to date with `knowledge_graph`, limited to entities
+    /// of `types` (full IRIs) when any are given
+    pub fn update(&mut self, knowledge_graph: &KnowledgeGraph, types: &[String]) -> Result<FeedUpdate> {
+        fs::create_dir_all(&self.dir)
+            .storage_context(|| format!("Failed to create entity feed directory: {}", self.dir.display()))?;
+
+        let summaries = summarize(knowledge_graph, types);
+        let mut update = FeedUpdate::default();
+
+        let stale: Vec<String> = self.index.keys().filter(|uri| !summaries.contains_key(*uri)).cloned().collect();
+        for uri in stale {
+            if let Some(entry) = self.index.remove(&uri) {
+                let path = self.dir.join(&entry.file);
+                if path.exists() {
+                    fs::remove_file(&path)
+                        .storage_context(|| format!("Failed to remove entity summary: {}", path.display()))?;
+                }
+                update.removed += 1;
+            }
+        }
+
+        for (uri, summary) in summaries {
+            let content = serde_json::to_string_pretty(&summary)
+                .storage_context(|| format!("Failed to serialize entity summary: {}", uri))?;
+            let hash = sha256_hex(content.as_bytes());
+            let file = self.index.get(&uri).map_or_else(|| file_name(&uri), |entry| entry.file.clone());
+            let path = self.dir.join(&file);
+            if self.index.get(&uri).is_some_and(|entry| entry.hash == hash) && path.exists() {
+                update.unchanged += 1;
+                continue;
+            }
+
+            fs::write(&path, content)
+                .storage_context(|| format!("Failed to write entity summary: {}", path.display()))?;
+            self.index.insert(uri, FeedEntry { file, hash, updated_at: Utc::now() });
+            update.written += 1;
+        }
+
+        let index_path = self.dir.join("index.json");
+        let content = serde_json::to_string_pretty(&self.index)
+            .storage_context(|| "Failed to serialize entity feed index")?;
+        fs::write(&index_path, content)
+            .storage_context(|| format!("Failed to write entity feed index: {}", index_path.display()))?;
+
+        Ok(update)
+    }
+}
+
+/// Summaries of the IRI subjects of `knowledge_graph`, by URI
+pub fn summarize(knowledge_graph: &KnowledgeGraph, types: &[String]) -> BTreeMap<String, EntitySummary> {
+    let mut prefixes: Vec<(String, String)> = knowledge_graph.prefixes().into_iter().collect();
+    prefixes.sort();
+    let compact = |iri: &str| compact_iri(iri, &prefixes).unwrap_or_else(|| iri.to_string());
+
+    let triples = knowledge_graph.triples();
+    let labels = entity_labels(triples);
+    let mut outgoing: HashMap<&str, Vec<&RdfTriple>> = HashMap::new();
+    let mut incoming: HashMap<&str, Vec<&RdfTriple>> = HashMap::new();
+    for triple in triples {
+        outgoing.entry(triple.subject.as_str()).or_default().push(triple);
+        if is_iri(&triple.object) && triple.predicate != RDF_TYPE {
+            incoming.entry(triple.object.as_str()).or_default().push(triple);
+        }
+    }
+
+    let mut summaries = BTreeMap::new();
+    for (uri, statements) in &outgoing {
+        if !is_iri(uri) {
+            continue;
+        }
+        let entity_types: BTreeSet<&str> = statements.iter()
+            .filter(|t| t.predicate == RDF_TYPE)
+            .map(|t| t.object.as_str())
+            .collect();
+        if !types.is_empty() && !types.iter().any(|t| entity_types.contains(t.as_str())) {
+            continue;
+        }
+
+        let mut properties: BTreeMap<String, Vec<PropertyValue>> = BTreeMap::new();
+        let mut relations = BTreeSet::new();
+        let mut sources = BTreeSet::new();
+        for triple in statements {
+            sources.extend(triple.source.clone());
+            if triple.predicate == RDF_TYPE {
+                continue;
+            }
+            if is_iri(&triple.object) {
+                relations.insert(Relation {
+                    predicate: compact(&triple.predicate),
+                    direction: Direction::Outgoing,
+                    uri: triple.object.clone(),
+                    label: labels.get(triple.object.as_str()).cloned(),
+                });
+            } else {
+                let value = PropertyValue {
+                    value: triple.object.clone(),
+                    lang: triple.metadata.get("lang").cloned(),
+                    datatype: triple.metadata.get("datatype").map(|datatype| compact(datatype)),
+                };
+                let values = properties.entry(compact(&triple.predicate)).or_default();
+                if !values.contains(&value) {
+                    values.push(value);
+                }
+            }
+        }
+        for triple in incoming.get(uri).into_iter().flatten() {
+            relations.insert(Relation {
+                predicate: compact(&triple.predicate),
+                direction: Direction::Incoming,
+                uri: triple.subject.clone(),
+                label: labels.get(triple.subject.as_str()).cloned(),
+            });
+        }
+
+        summaries.insert(uri.to_string(), EntitySummary {
+            uri: uri.to_string(),
+            label: labels.get(*uri).cloned(),
+            types: entity_types.into_iter().map(&compact).collect(),
+            properties,
+            relations: relations.into_iter().collect(),
+            sources: sources.into_iter().collect(),
+        });
+    }
+    summaries
+}
+
+/// Readable, collision-free file name for an entity, e.g. `acme-1a2b3c4d.json`
+fn file_name(uri: &str) -> String {
+    let local = uri.trim_end_matches(['/', '#']).rsplit(['/', '#']).next().unwrap_or_default();
+    let slug: String = local.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .take(60)
+        .collect();
+    format!("{}-{}.json", slug, &sha256_hex(uri.as_bytes())[..8])
+}
+
+fn is_iri(value: &str) -> bool {
+    value.starts_with("http://") || value.starts_with("https://")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RdfSchema;
+    use crate::knowledge_graph::TriplePattern;
+
+    const LABEL: &str = "http://www.w3.org/2000/01/rdf-schema#label";
+
+    #[test]
+    fn test_entity_feed() {
+        let schema = RdfSchema { prefix: "ex".into(), namespace: "http://ex.org/".into(), ..Default::default() };
+        let mut kg = KnowledgeGraph::in_memory(schema).unwrap();
+        kg.add_triples(&[
+            RdfTriple::new("http://ex.org/acme".into(), LABEL.into(), "Acme".into()).with_source("a.pdf".into()),
+            RdfTriple::new("http://ex.org/acme".into(), RDF_TYPE.into(), "http://ex.org/Company".into()),
+            RdfTriple::new("http://ex.org/acme".into(), "http://ex.org/founded".into(), "1999".into()),
+            RdfTriple::new("http://ex.org/ana".into(), "http://ex.org/worksFor".into(), "http://ex.org/acme".into())
+                .with_source("b.pdf".into()),
+        ]).unwrap();
+
+        let acme = &summarize(&kg, &[])["http://ex.org/acme"];
+        assert_eq!(acme.label.as_deref(), Some("Acme"));
+        assert_eq!(acme.types, ["ex:Company"]);
+        assert_eq!(acme.properties["ex:founded"][0].value, "1999");
+        assert_eq!(acme.relations, [Relation {
+            predicate: "ex:worksFor".into(),
+            direction: Direction::Incoming,
+            uri: "http://ex.org/ana".into(),
+            label: None,
+        }]);
+        assert_eq!(acme.sources, ["a.pdf"]);
+        assert_eq!(summarize(&kg, &["http://ex.org/Company".into()]).len(), 1);
+
+        let dir = std::env::temp_dir().join(format!("entity_feed_test_{}", uuid::Uuid::new_v4()));
+        let mut feed = EntityFeed::open(&dir).unwrap();
+        assert_eq!(feed.update(&kg, &[]).unwrap(), FeedUpdate { written: 2, unchanged: 0, removed: 0 });
+
+        // Only changed entities are rewritten; removed ones are deleted
+        kg.remove_triples(&TriplePattern { subject: Some("http://ex.org/ana".into()), ..Default::default() }).unwrap();
+        let mut feed = EntityFeed::open(&dir).unwrap();
+        let ana_file = dir.join(&feed.entries()["http://ex.org/ana"].file);
+        assert_eq!(feed.update(&kg, &[]).unwrap(), FeedUpdate { written: 1, unchanged: 0, removed: 1 });
+        assert!(!ana_file.exists());
+        assert_eq!(feed.update(&kg, &[]).unwrap(), FeedUpdate { written: 0, unchanged: 1, removed: 0 });
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
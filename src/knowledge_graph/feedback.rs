@@ -0,0 +1,199 @@
+use crate::core::RdfTriple;
+use crate::error::{ErrorContext, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use utoipa::ToSchema;
+
+use super::{KnowledgeGraph, TriplePattern};
+
+/// Corrections included in extraction prompts unless configured otherwise
+pub const DEFAULT_PROMPT_CORRECTIONS: usize = 10;
+
+/// A reviewer's correction of an extracted triple: rejected outright, or
+/// with the object it should have had
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct Correction {
+    pub id: String,
+    pub subject: String,
+    pub predicate: String,
+    pub object: String,
+    /// The right object; `None` when the triple was rejected
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub corrected_object: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    /// Document the triple was extracted from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    pub recorded_at: DateTime<Utc>,
+}
+
+impl Correction {
+    pub fn is_rejection(&self) -> bool {
+        self.corrected_object.is_none()
+    }
+
+    /// Remove the corrected triple from `knowledge_graph`, in any graph, and
+    /// add its replacement for a fixed object; returns the triples removed
+    pub fn apply(&self, knowledge_graph: &mut KnowledgeGraph) -> Result<usize> {
+        let pattern = TriplePattern {
+            subject: Some(self.subject.clone()),
+            predicate: Some(self.predicate.clone()),
+            object: Some(self.object.clone()),
+            graph: None,
+        };
+        let replacements: Vec<RdfTriple> = match &self.corrected_object {
+            Some(object) => knowledge_graph.find(&pattern).into_iter()
+                .map(|triple| RdfTriple { object: object.clone(), confidence: 1.0, ..triple.clone() })
+                .collect(),
+            None => Vec::new(),
+        };
+
+        let removed = knowledge_graph.remove_triples(&pattern)?;
+        knowledge_graph.add_triples(&replacements)?;
+        Ok(removed)
+    }
+}
+
+/// Corrections recorded by reviewers, kept in `<kg_path>.feedback.json` in
+/// the order they were made. Recent ones are shown to the model as
+/// counter-examples in later extraction prompts.
+pub struct FeedbackStore {
+    path: PathBuf,
+    corrections: Vec<Correction>,
+}
+
+impl FeedbackStore {
+    /// Feedback file used for the knowledge graph stored at `kg_path`
+    pub fn path_for(kg_path: &str) -> PathBuf {
+        PathBuf::from(format!("{}.feedback.json", kg_path))
+    }
+
+    pub fn open_for(kg_path: &str) -> Result<Self> {
+        Self::open(Self::path_for(kg_path))
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let corrections = if path.exists() {
+            let content = fs::read_to_string(&path)
+                .storage_context(|| format!("Failed to read feedback: {}", path.display()))?;
+            serde_json::from_str(&content)
+                .storage_context(|| format!("Failed to parse feedback: {}", path.display()))?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self { path, corrections })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let content = serde_json::to_string_pretty(&self.corrections)
+            .storage_context(|| "Failed to serialize feedback")?;
+        fs::write(&self.path, content)
+            .storage_context(|| format!("Failed to write feedback: {}", self.path.display()))
+    }
+
+    /// Record that `triple` is wrong
+    pub fn reject(&mut self, triple: &RdfTriple, note: Option<String>) -> &Correction {
+        self.record(triple, None, note)
+    }
+
+    /// Record that `triple` should have had `corrected_object`
+    pub fn fix(&mut self, triple: &RdfTriple, corrected_object: String, note: Option<String>) -> &Correction {
+        self.record(triple, Some(corrected_object), note)
+    }
+
+    fn record(&mut self, triple: &RdfTriple, corrected_object: Option<String>, note: Option<String>) -> &Correction {
+        let id = uuid::Uuid::new_v4().simple().to_string()[..8].to_string();
+        self.corrections.push(Correction {
+            id,
+            subject: triple.subject.clone(),
+            predicate: triple.predicate.clone(),
+            object: triple.object.clone(),
+            corrected_object,
+            note: note.filter(|note| !note.trim().is_empty()),
+            source: triple.source.clone(),
+            recorded_at: Utc::now(),
+        });
+        self.corrections.last().unwrap()
+    }
+
+    /// Corrections in the order they were recorded
+    pub fn list(&self) -> &[Correction] {
+        &self.corrections
+    }
+
+    /// Up to `limit` corrections, newest first, skipping older corrections
+    /// of a triple corrected again later
+    pub fn recent(&self, limit: usize) -> Vec<Correction> {
+        let mut recent: Vec<Correction> = Vec::new();
+        for correction in self.corrections.iter().rev() {
+            if recent.len() == limit {
+                break;
+            }
+            let seen = recent.iter().any(|c| {
+                (&c.subject, &c.predicate, &c.object) == (&correction.subject, &correction.predicate, &correction.object)
+            });
+            if !seen {
+                recent.push(correction.clone());
+            }
+        }
+        recent
+    }
+
+    pub fn remove(&mut self, id: &str) -> Option<Correction> {
+        let position = self.corrections.iter().position(|c| c.id == id)?;
+        Some(self.corrections.remove(position))
+    }
+
+    pub fn len(&self) -> usize {
+        self.corrections.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.corrections.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RdfSchema;
+
+    #[test]
+    fn test_feedback_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let kg_path = dir.path().join("kg.db").to_string_lossy().to_string();
+        let triple = |p: &str, o: &str| RdfTriple::new("http://ex.org/acme".into(), format!("http://ex.org/{}", p), o.into())
+            .with_source("a.pdf".into());
+
+        let mut feedback = FeedbackStore::open_for(&kg_path).unwrap();
+        feedback.reject(&triple("ceo", "Bob"), Some("Bob left in 2020".into()));
+        let first = feedback.fix(&triple("revenue", "1M"), "1B".into(), None).id.clone();
+        feedback.fix(&triple("revenue", "1M"), "1.2B".into(), Some(" ".into()));
+        feedback.save().unwrap();
+
+        let mut feedback = FeedbackStore::open_for(&kg_path).unwrap();
+        assert_eq!(feedback.len(), 3);
+        let recent = feedback.recent(10);
+        let objects: Vec<Option<&str>> = recent.iter().map(|c| c.corrected_object.as_deref()).collect();
+        assert_eq!(objects, [Some("1.2B"), None]);
+        assert_eq!(recent[0].note, None);
+        assert_eq!(recent[1].source.as_deref(), Some("a.pdf"));
+        assert_eq!(feedback.recent(1).len(), 1);
+        assert_eq!(feedback.remove(&first).unwrap().corrected_object.as_deref(), Some("1B"));
+        assert!(feedback.remove(&first).is_none());
+
+        // Applying a correction removes the triple, and replaces it for a fixed object
+        let mut kg = KnowledgeGraph::in_memory(RdfSchema::default()).unwrap();
+        kg.add_triples(&[triple("ceo", "Bob"), triple("revenue", "1M").with_graph("http://ex.org/g".into())]).unwrap();
+        for correction in feedback.list() {
+            assert_eq!(correction.apply(&mut kg).unwrap(), 1);
+        }
+        let remaining: Vec<(&str, Option<&str>)> = kg.triples().iter().map(|t| (t.object.as_str(), t.graph.as_deref())).collect();
+        assert_eq!(remaining, [("1.2B", Some("http://ex.org/g"))]);
+    }
+}
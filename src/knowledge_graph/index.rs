@@ -0,0 +1,147 @@
+//! Hash indexes over the triples of a knowledge graph, so duplicate checks
+//! and lookups by subject, predicate or object don't scan every triple
+
+use std::collections::HashMap;
+
+use crate::core::RdfTriple;
+
+type TermId = u32;
+type Positions = HashMap<TermId, HashMap<TermId, Vec<usize>>>;
+
+/// SPO, POS and OSP indexes from terms to positions in the triple list,
+/// plus an exact (subject, predicate, object, graph) index for duplicates.
+/// Terms are interned once, so each index only holds integer ids.
+#[derive(Debug, Clone, Default)]
+pub struct TripleIndex {
+    terms: HashMap<String, TermId>,
+    quads: HashMap<(TermId, TermId, TermId, Option<TermId>), usize>,
+    spo: Positions,
+    pos: Positions,
+    osp: Positions,
+}
+
+impl TripleIndex {
+    pub fn from_triples(triples: &[RdfTriple]) -> Self {
+        let mut index = Self::default();
+        for (position, triple) in triples.iter().enumerate() {
+            index.insert(triple, position);
+        }
+        index
+    }
+
+    /// Index `triple`, stored at `position` in the triple list
+    pub fn insert(&mut self, triple: &RdfTriple, position: usize) {
+        let s = self.intern(&triple.subject);
+        let p = self.intern(&triple.predicate);
+        let o = self.intern(&triple.object);
+        let g = triple.graph.as_deref().map(|graph| self.intern(graph));
+
+        self.quads.entry((s, p, o, g)).or_insert(position);
+        self.spo.entry(s).or_default().entry(p).or_default().push(position);
+        self.pos.entry(p).or_default().entry(o).or_default().push(position);
+        self.osp.entry(o).or_default().entry(s).or_default().push(position);
+    }
+
+    /// Position of the triple with the same subject, predicate, object and graph
+    pub fn position(&self, triple: &RdfTriple) -> Option<usize> {
+        let key = (
+            self.id(&triple.subject)?,
+            self.id(&triple.predicate)?,
+            self.id(&triple.object)?,
+            match &triple.graph {
+                Some(graph) => Some(self.id(graph)?),
+                None => None,
+            },
+        );
+        self.quads.get(&key).copied()
+    }
+
+    pub fn contains(&self, triple: &RdfTriple) -> bool {
+        self.position(triple).is_some()
+    }
+
+    /// Positions of the triples with the given subject, predicate and object,
+    /// in ascending order; `None` when no term is given, as every triple matches
+    pub fn matching(&self, subject: Option<&str>, predicate: Option<&str>, object: Option<&str>) -> Option<Vec<usize>> {
+        // A term that was never stored matches nothing
+        let lookup = |term: Option<&str>| term.map(|term| self.id(term));
+        let (s, p, o) = (lookup(subject), lookup(predicate), lookup(object));
+        if [s, p, o].contains(&Some(None)) {
+            return Some(Vec::new());
+        }
+        let (s, p, o) = (s.flatten(), p.flatten(), o.flatten());
+
+        let mut positions: Vec<usize> = match (s, p, o) {
+            (None, None, None) => return None,
+            (Some(s), Some(p), o) => {
+                let positions = nested(&self.spo, s, p);
+                match o {
+                    Some(o) => positions.iter().copied().filter(|&i| nested(&self.osp, o, s).contains(&i)).collect(),
+                    None => positions.to_vec(),
+                }
+            }
+            (Some(s), None, Some(o)) => nested(&self.osp, o, s).to_vec(),
+            (None, Some(p), Some(o)) => nested(&self.pos, p, o).to_vec(),
+            (Some(s), None, None) => flatten(self.spo.get(&s)),
+            (None, Some(p), None) => flatten(self.pos.get(&p)),
+            (None, None, Some(o)) => flatten(self.osp.get(&o)),
+        };
+        positions.sort_unstable();
+        Some(positions)
+    }
+
+    fn intern(&mut self, term: &str) -> TermId {
+        if let Some(&id) = self.terms.get(term) {
+            return id;
+        }
+        let id = self.terms.len() as TermId;
+        self.terms.insert(term.to_string(), id);
+        id
+    }
+
+    fn id(&self, term: &str) -> Option<TermId> {
+        self.terms.get(term).copied()
+    }
+}
+
+fn nested(index: &Positions, first: TermId, second: TermId) -> &[usize] {
+    index.get(&first).and_then(|inner| inner.get(&second)).map_or(&[], Vec::as_slice)
+}
+
+fn flatten(inner: Option<&HashMap<TermId, Vec<usize>>>) -> Vec<usize> {
+    inner.into_iter().flat_map(|inner| inner.values().flatten().copied()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_triple_index() {
+        let triple = |s: &str, p: &str, o: &str| RdfTriple::new(format!("http://ex.org/{}", s), format!("http://ex.org/{}", p), o.to_string());
+        let triples = vec![
+            triple("acme", "name", "Acme"),
+            triple("acme", "basedIn", "http://ex.org/berlin"),
+            triple("ana", "worksFor", "http://ex.org/acme"),
+            triple("globex", "basedIn", "http://ex.org/berlin"),
+            triple("acme", "name", "Acme").with_graph("http://ex.org/g".into()),
+        ];
+        let index = TripleIndex::from_triples(&triples);
+        let ex = |local: &str| format!("http://ex.org/{}", local);
+
+        assert_eq!(index.matching(Some(&ex("acme")), None, None), Some(vec![0, 1, 4]));
+        assert_eq!(index.matching(None, Some(&ex("basedIn")), None), Some(vec![1, 3]));
+        assert_eq!(index.matching(None, None, Some(&ex("berlin"))), Some(vec![1, 3]));
+        assert_eq!(index.matching(Some(&ex("acme")), Some(&ex("name")), Some("Acme")), Some(vec![0, 4]));
+        assert_eq!(index.matching(Some(&ex("ana")), None, Some(&ex("acme"))), Some(vec![2]));
+        assert_eq!(index.matching(None, Some(&ex("basedIn")), Some(&ex("berlin"))), Some(vec![1, 3]));
+        assert_eq!(index.matching(Some(&ex("nobody")), None, None), Some(vec![]));
+        assert_eq!(index.matching(None, None, None), None);
+
+        // Duplicates are found by graph as well as by subject, predicate and object
+        assert_eq!(index.position(&triples[4]), Some(4));
+        assert_eq!(index.position(&triple("acme", "name", "Acme")), Some(0));
+        assert!(!index.contains(&triple("acme", "name", "ACME")));
+        assert!(!index.contains(&triple("acme", "name", "Acme").with_graph("http://ex.org/other".into())));
+    }
+}
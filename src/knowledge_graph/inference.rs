@@ -0,0 +1,284 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::config::RdfSchema;
+use crate::core::RdfTriple;
+
+pub const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+const RDFS_SUBCLASS_OF: &str = "http://www.w3.org/2000/01/rdf-schema#subClassOf";
+const RDFS_SUBPROPERTY_OF: &str = "http://www.w3.org/2000/01/rdf-schema#subPropertyOf";
+const RDFS_DOMAIN: &str = "http://www.w3.org/2000/01/rdf-schema#domain";
+const RDFS_RANGE: &str = "http://www.w3.org/2000/01/rdf-schema#range";
+const OWL_INVERSE_OF: &str = "http://www.w3.org/2002/07/owl#inverseOf";
+const OWL_TRANSITIVE_PROPERTY: &str = "http://www.w3.org/2002/07/owl#TransitiveProperty";
+
+/// Forward-chaining RDFS/OWL-lite reasoner
+///
+/// Applies subclass and subproperty closure, domain and range typing,
+/// inverse properties and transitive properties until no new triples
+/// follow. Axioms come from `RdfSchema::subclass_of` and
+/// `RdfSchema::predicate_axioms`, plus any `rdfs:subClassOf`,
+/// `rdfs:subPropertyOf`, `rdfs:domain`, `rdfs:range`, `owl:inverseOf` and
+/// `owl:TransitiveProperty` triples in the graph.
+#[derive(Debug, Clone, Default)]
+pub struct Reasoner {
+    superclasses: HashMap<String, Vec<String>>,
+    superproperties: HashMap<String, Vec<String>>,
+    domains: HashMap<String, Vec<String>>,
+    ranges: HashMap<String, Vec<String>>,
+    /// Recorded in both directions
+    inverses: HashMap<String, Vec<String>>,
+    transitive: HashSet<String>,
+}
+
+impl Reasoner {
+    pub fn from_schema(schema: &RdfSchema) -> Self {
+        let mut reasoner = Self::default();
+
+        for (class, parents) in &schema.subclass_of {
+            for parent in parents {
+                add_edge(&mut reasoner.superclasses, schema.resolve_term(class), schema.resolve_term(parent));
+            }
+        }
+
+        for (predicate, axioms) in &schema.predicate_axioms {
+            let predicate = schema.resolve_term(predicate);
+            for parent in &axioms.sub_property_of {
+                add_edge(&mut reasoner.superproperties, predicate.clone(), schema.resolve_term(parent));
+            }
+            if let Some(domain) = &axioms.domain {
+                add_edge(&mut reasoner.domains, predicate.clone(), schema.resolve_term(domain));
+            }
+            if let Some(range) = &axioms.range {
+                add_edge(&mut reasoner.ranges, predicate.clone(), schema.resolve_term(range));
+            }
+            if let Some(inverse) = &axioms.inverse_of {
+                reasoner.add_inverse(predicate.clone(), schema.resolve_term(inverse));
+            }
+            if axioms.transitive {
+                reasoner.transitive.insert(predicate);
+            }
+        }
+
+        reasoner
+    }
+
+    /// Pick up axioms stated as triples in the graph
+    pub fn add_axioms_from(&mut self, triples: &[RdfTriple]) {
+        for triple in triples {
+            let (s, o) = (triple.subject.clone(), triple.object.clone());
+            match triple.predicate.as_str() {
+                RDFS_SUBCLASS_OF | "rdfs:subClassOf" => add_edge(&mut self.superclasses, s, o),
+                RDFS_SUBPROPERTY_OF | "rdfs:subPropertyOf" => add_edge(&mut self.superproperties, s, o),
+                RDFS_DOMAIN | "rdfs:domain" => add_edge(&mut self.domains, s, o),
+                RDFS_RANGE | "rdfs:range" => add_edge(&mut self.ranges, s, o),
+                OWL_INVERSE_OF | "owl:inverseOf" => self.add_inverse(s, o),
+                p if is_type(p) && (o == OWL_TRANSITIVE_PROPERTY || o == "owl:TransitiveProperty") => {
+                    self.transitive.insert(s);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.superclasses.is_empty()
+            && self.superproperties.is_empty()
+            && self.domains.is_empty()
+            && self.ranges.is_empty()
+            && self.inverses.is_empty()
+            && self.transitive.is_empty()
+    }
+
+    /// Triples entailed by `triples` that are not already among them
+    ///
+    /// Inferred triples stay in the graph of the triple they were derived
+    /// from, take the lowest confidence of their premises and record the
+    /// rule in `metadata["inferred"]`.
+    pub fn infer(&self, triples: &[RdfTriple]) -> Vec<RdfTriple> {
+        if self.is_empty() {
+            return Vec::new();
+        }
+
+        let mut known: HashSet<(String, String, String, Option<String>)> = triples.iter().map(key).collect();
+        // Transitive predicates indexed by subject and by object
+        let mut forward: HashMap<(String, String), Vec<RdfTriple>> = HashMap::new();
+        let mut backward: HashMap<(String, String), Vec<RdfTriple>> = HashMap::new();
+        for triple in triples {
+            self.index_transitive(triple, &mut forward, &mut backward);
+        }
+
+        let mut inferred = Vec::new();
+        let mut frontier: Vec<RdfTriple> = triples.to_vec();
+
+        while !frontier.is_empty() {
+            let mut next = Vec::new();
+
+            for triple in &frontier {
+                let mut conclusions = self.apply_rules(triple);
+
+                if self.transitive.contains(&triple.predicate) {
+                    let p = &triple.predicate;
+                    for after in forward.get(&(p.clone(), triple.object.clone())).into_iter().flatten() {
+                        conclusions.push(derive(triple, Some(after), &triple.subject, p, &after.object, "transitive"));
+                    }
+                    for before in backward.get(&(p.clone(), triple.subject.clone())).into_iter().flatten() {
+                        conclusions.push(derive(before, Some(triple), &before.subject, p, &triple.object, "transitive"));
+                    }
+                }
+
+                for conclusion in conclusions {
+                    if conclusion.subject == conclusion.object && self.transitive.contains(&conclusion.predicate) {
+                        continue;
+                    }
+                    if known.insert(key(&conclusion)) {
+                        self.index_transitive(&conclusion, &mut forward, &mut backward);
+                        next.push(conclusion);
+                    }
+                }
+            }
+
+            inferred.extend(next.iter().cloned());
+            frontier = next;
+        }
+
+        inferred
+    }
+
+    /// Conclusions that follow from a single triple
+    fn apply_rules(&self, triple: &RdfTriple) -> Vec<RdfTriple> {
+        let mut conclusions = Vec::new();
+        let (s, p, o) = (&triple.subject, &triple.predicate, &triple.object);
+
+        if is_type(p) {
+            for class in self.superclasses.get(o).into_iter().flatten() {
+                conclusions.push(derive(triple, None, s, RDF_TYPE, class, "subclass"));
+            }
+            return conclusions;
+        }
+
+        for parent in self.superproperties.get(p).into_iter().flatten() {
+            conclusions.push(derive(triple, None, s, parent, o, "subproperty"));
+        }
+        for class in self.domains.get(p).into_iter().flatten() {
+            conclusions.push(derive(triple, None, s, RDF_TYPE, class, "domain"));
+        }
+        if is_uri(o) {
+            for class in self.ranges.get(p).into_iter().flatten() {
+                conclusions.push(derive(triple, None, o, RDF_TYPE, class, "range"));
+            }
+            for inverse in self.inverses.get(p).into_iter().flatten() {
+                conclusions.push(derive(triple, None, o, inverse, s, "inverse"));
+            }
+        }
+
+        conclusions
+    }
+
+    fn index_transitive(
+        &self,
+        triple: &RdfTriple,
+        forward: &mut HashMap<(String, String), Vec<RdfTriple>>,
+        backward: &mut HashMap<(String, String), Vec<RdfTriple>>,
+    ) {
+        if self.transitive.contains(&triple.predicate) {
+            let p = triple.predicate.clone();
+            forward.entry((p.clone(), triple.subject.clone())).or_default().push(triple.clone());
+            backward.entry((p, triple.object.clone())).or_default().push(triple.clone());
+        }
+    }
+
+    fn add_inverse(&mut self, predicate: String, inverse: String) {
+        add_edge(&mut self.inverses, predicate.clone(), inverse.clone());
+        add_edge(&mut self.inverses, inverse, predicate);
+    }
+}
+
+fn add_edge(edges: &mut HashMap<String, Vec<String>>, from: String, to: String) {
+    let targets = edges.entry(from).or_default();
+    if !targets.contains(&to) {
+        targets.push(to);
+    }
+}
+
+fn derive(premise: &RdfTriple, other: Option<&RdfTriple>, s: &str, p: &str, o: &str, rule: &str) -> RdfTriple {
+    let mut triple = RdfTriple::new(s.to_string(), p.to_string(), o.to_string());
+    triple.confidence = other.map_or(premise.confidence, |other| premise.confidence.min(other.confidence));
+    triple.source = premise.source.clone();
+    triple.graph = premise.graph.clone();
+    triple.metadata.insert("inferred".to_string(), rule.to_string());
+    triple
+}
+
+fn key(triple: &RdfTriple) -> (String, String, String, Option<String>) {
+    (triple.subject.clone(), triple.predicate.clone(), triple.object.clone(), triple.graph.clone())
+}
+
+fn is_type(predicate: &str) -> bool {
+    predicate == RDF_TYPE || predicate == "rdf:type" || predicate == "a"
+}
+
+fn is_uri(value: &str) -> bool {
+    value.starts_with("http://") || value.starts_with("https://") || value.starts_with("urn:")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PredicateAxioms;
+
+    const NS: &str = "http://ex.org/ns#";
+
+    fn triple(s: &str, p: &str, o: &str) -> RdfTriple {
+        RdfTriple::new(s.to_string(), p.to_string(), o.to_string())
+    }
+
+    #[test]
+    fn test_inference_rules() {
+        let schema = RdfSchema {
+            namespace: NS.to_string(),
+            subclass_of: HashMap::from([
+                ("Manager".to_string(), vec!["Employee".to_string()]),
+                ("Employee".to_string(), vec!["Person".to_string()]),
+            ]),
+            predicate_axioms: HashMap::from([
+                ("manages".to_string(), PredicateAxioms {
+                    sub_property_of: vec!["worksWith".to_string()],
+                    domain: Some("Manager".to_string()),
+                    range: Some("Employee".to_string()),
+                    inverse_of: Some("reportsTo".to_string()),
+                    ..Default::default()
+                }),
+                ("partOf".to_string(), PredicateAxioms { transitive: true, ..Default::default() }),
+            ]),
+            ..RdfSchema::default()
+        };
+
+        let ns = |name: &str| format!("{}{}", NS, name);
+        let mut manages = triple("http://ex.org/ann", &ns("manages"), "http://ex.org/bob");
+        manages.confidence = 0.8;
+        let triples = vec![
+            manages,
+            triple("http://ex.org/sales", &ns("partOf"), "http://ex.org/emea"),
+            triple("http://ex.org/emea", &ns("partOf"), "http://ex.org/acme"),
+            triple(&ns("Intern"), RDFS_SUBCLASS_OF, &ns("Employee")),
+            triple("http://ex.org/cy", RDF_TYPE, &ns("Intern")),
+        ];
+
+        let mut reasoner = Reasoner::from_schema(&schema);
+        reasoner.add_axioms_from(&triples);
+        let inferred = reasoner.infer(&triples);
+        let has = |s: &str, p: &str, o: &str| inferred.iter().any(|t| t.subject == s && t.predicate == p && t.object == o);
+
+        assert!(has("http://ex.org/ann", &ns("worksWith"), "http://ex.org/bob"));
+        assert!(has("http://ex.org/ann", RDF_TYPE, &ns("Person")));
+        assert!(has("http://ex.org/bob", RDF_TYPE, &ns("Person")));
+        assert!(has("http://ex.org/bob", &ns("reportsTo"), "http://ex.org/ann"));
+        assert!(has("http://ex.org/sales", &ns("partOf"), "http://ex.org/acme"));
+        assert!(has("http://ex.org/cy", RDF_TYPE, &ns("Person")));
+
+        let reports_to = inferred.iter().find(|t| t.predicate == ns("reportsTo")).unwrap();
+        assert_eq!(reports_to.confidence, 0.8);
+        assert_eq!(reports_to.metadata["inferred"], "inverse");
+        assert!(reasoner.infer(&[triples, inferred].concat()).is_empty());
+    }
+}
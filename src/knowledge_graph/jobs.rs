@@ -0,0 +1,250 @@
+use crate::core::ExtractionResult;
+use crate::utils::normalize_source;
+use crate::error::{Error, ErrorContext, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentStatus {
+    Pending,
+    Completed,
+    Failed,
+}
+
+/// Progress of one document in an extraction job
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct JobDocument {
+    pub source: String,
+    pub status: DocumentStatus,
+    #[serde(default)]
+    pub attempts: u32,
+    #[serde(default)]
+    pub triples: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// Per-document status of an extraction run, persisted in
+/// `<kg_path>.jobs/<job_id>.json` so an interrupted run can be resumed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobManifest {
+    pub job_id: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Named graph used when triples are stored per run
+    pub run_graph: String,
+    /// `--graph` target the job was started with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub graph: Option<String>,
+    /// Inputs the job was started with, before crawling or splitting them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inputs: Vec<String>,
+    /// The documents are `<source>#section=<n>` sections of the inputs
+    #[serde(default)]
+    pub split_sections: bool,
+    pub documents: Vec<JobDocument>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl JobManifest {
+    /// Directory holding the job manifests of the knowledge graph at `kg_path`
+    pub fn dir_for(kg_path: &str) -> PathBuf {
+        PathBuf::from(format!("{}.jobs", kg_path))
+    }
+
+    /// New job over `sources`, all pending
+    pub fn create(kg_path: &str, sources: &[String], graph: Option<String>) -> Self {
+        let now = Utc::now();
+        let job_id = format!("{}-{}", now.format("%Y%m%d-%H%M%S"), &uuid::Uuid::new_v4().simple().to_string()[..8]);
+        let documents = sources.iter()
+            .map(|source| JobDocument {
+                source: normalize_source(source),
+                status: DocumentStatus::Pending,
+                attempts: 0,
+                triples: 0,
+                error: None,
+                finished_at: None,
+            })
+            .collect();
+
+        Self {
+            path: Self::dir_for(kg_path).join(format!("{}.json", job_id)),
+            job_id,
+            created_at: now,
+            updated_at: now,
+            run_graph: format!("urn:uuid:{}", uuid::Uuid::new_v4()),
+            graph,
+            inputs: Vec::new(),
+            split_sections: false,
+            documents,
+        }
+    }
+
+    pub fn open(kg_path: &str, job_id: &str) -> Result<Self> {
+        let path = Self::dir_for(kg_path).join(format!("{}.json", job_id));
+        if !path.exists() {
+            return Err(Error::storage(format!("No extraction job '{}' in {}", job_id, Self::dir_for(kg_path).display())));
+        }
+        Self::open_path(path)
+    }
+
+    /// Manifest stored at `path`, e.g. a crawl profile's `resume_file`
+    pub fn open_path(path: PathBuf) -> Result<Self> {
+        let content = fs::read_to_string(&path)
+            .storage_context(|| format!("Failed to read job manifest: {}", path.display()))?;
+        let mut manifest: Self = serde_json::from_str(&content)
+            .storage_context(|| format!("Failed to parse job manifest: {}", path.display()))?;
+        manifest.path = path;
+        Ok(manifest)
+    }
+
+    /// Store the manifest at `path` instead of the knowledge graph's job directory
+    pub fn with_path(mut self, path: PathBuf) -> Self {
+        self.path = path;
+        self
+    }
+
+    /// Record the inputs the job was started with, when its documents were
+    /// derived from them
+    pub fn with_inputs(mut self, inputs: &[String]) -> Self {
+        self.inputs = inputs.iter().map(|input| normalize_source(input)).collect();
+        self
+    }
+
+    /// Whether the job was started with `inputs`, in any order; true when
+    /// the manifest predates recording them
+    pub fn started_with(&self, inputs: &[String]) -> bool {
+        if self.inputs.is_empty() {
+            return true;
+        }
+        let mut recorded = self.inputs.clone();
+        let mut given: Vec<String> = inputs.iter().map(|input| normalize_source(input)).collect();
+        recorded.sort();
+        given.sort();
+        recorded == given
+    }
+
+    /// Delete the manifest file, once the job is done
+    pub fn remove(&self) -> Result<()> {
+        fs::remove_file(&self.path)
+            .storage_context(|| format!("Failed to remove job manifest: {}", self.path.display()))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn save(&mut self) -> Result<()> {
+        self.updated_at = Utc::now();
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)
+                .storage_context(|| format!("Failed to create job directory: {}", dir.display()))?;
+        }
+
+        // Write then rename, so a crash never leaves a truncated manifest
+        let content = serde_json::to_string_pretty(self)
+            .storage_context(|| "Failed to serialize job manifest")?;
+        let temp = self.path.with_extension("json.tmp");
+        fs::write(&temp, content)
+            .storage_context(|| format!("Failed to write job manifest: {}", temp.display()))?;
+        fs::rename(&temp, &self.path)
+            .storage_context(|| format!("Failed to write job manifest: {}", self.path.display()))
+    }
+
+    /// Sources still to process: pending documents and failed ones to retry
+    pub fn remaining(&self) -> Vec<String> {
+        self.documents.iter()
+            .filter(|d| d.status != DocumentStatus::Completed)
+            .map(|d| d.source.clone())
+            .collect()
+    }
+
+    pub fn count(&self, status: DocumentStatus) -> usize {
+        self.documents.iter().filter(|d| d.status == status).count()
+    }
+
+    /// Record the outcome of extracting `result.document_source`; results
+    /// carrying errors mark the document failed
+    pub fn record(&mut self, result: &ExtractionResult) {
+        // Manifests written on another platform may name sources differently
+        let source = normalize_source(&result.document_source);
+        let Some(document) = self.documents.iter_mut().find(|d| normalize_source(&d.source) == source) else {
+            return;
+        };
+
+        document.attempts += 1;
+        document.finished_at = Some(Utc::now());
+        document.triples = result.triples.len();
+        if result.errors.is_empty() {
+            document.status = DocumentStatus::Completed;
+            document.error = None;
+        } else {
+            document.status = DocumentStatus::Failed;
+            document.error = Some(result.errors.join("; "));
+        }
+    }
+
+    /// Manifests stored for `kg_path`, newest first
+    pub fn list_for(kg_path: &str) -> Result<Vec<Self>> {
+        let dir = Self::dir_for(kg_path);
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut manifests = Vec::new();
+        for entry in fs::read_dir(&dir).storage_context(|| format!("Failed to read job directory: {}", dir.display()))? {
+            let path = entry.storage_context(|| "Failed to read job directory entry")?.path();
+            if path.extension().and_then(|e| e.to_str()) == Some("json") {
+                manifests.push(Self::open_path(path)?);
+            }
+        }
+        manifests.sort_by_key(|m| std::cmp::Reverse(m.created_at));
+        Ok(manifests)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resume_remaining_documents() {
+        let dir = std::env::temp_dir().join(format!("jobs_test_{}", uuid::Uuid::new_v4()));
+        let kg_path = dir.join("kg.db").to_string_lossy().to_string();
+        let sources: Vec<String> = ["a.txt", "b.txt", "c.txt"].iter().map(|s| s.to_string()).collect();
+
+        let mut manifest = JobManifest::create(&kg_path, &sources, Some("run".to_string()));
+        manifest.record(&ExtractionResult::new("a.txt".to_string(), "cfg".to_string(), 0.1));
+        manifest.record(&ExtractionResult::new("b.txt".to_string(), "cfg".to_string(), 0.1).with_error("LLM extraction failed".to_string()));
+        manifest.save().unwrap();
+
+        let reopened = JobManifest::open(&kg_path, &manifest.job_id).unwrap();
+        assert_eq!(reopened.remaining(), vec!["b.txt".to_string(), "c.txt".to_string()]);
+        assert_eq!(reopened.count(DocumentStatus::Failed), 1);
+        assert_eq!(reopened.run_graph, manifest.run_graph);
+        assert_eq!(JobManifest::list_for(&kg_path).unwrap().len(), 1);
+
+        // A resume file lives outside the job directory
+        let resume_file = dir.join("crawl-state.json");
+        let seeds = vec!["https://ex.org/".to_string()];
+        let mut resumable = JobManifest::create(&kg_path, &sources, None)
+            .with_path(resume_file.clone())
+            .with_inputs(&seeds);
+        resumable.save().unwrap();
+        let reopened = JobManifest::open_path(resume_file.clone()).unwrap();
+        assert_eq!(reopened.job_id, resumable.job_id);
+        assert!(reopened.started_with(&seeds));
+        assert!(!reopened.started_with(&["https://other.org/".to_string()]));
+        assert_eq!(JobManifest::list_for(&kg_path).unwrap().len(), 1);
+        reopened.remove().unwrap();
+        assert!(!resume_file.exists());
+
+        fs::remove_dir_all(dir).ok();
+    }
+}
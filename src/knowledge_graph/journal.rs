@@ -0,0 +1,232 @@
+use crate::core::RdfTriple;
+use crate::error::{ErrorContext, Result};
+use crate::utils::integrity::sha256_hex;
+use super::repair::{QuarantinedRecord, Salvage};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Journals with fewer entries than this are not compacted on their own
+const MIN_COMPACTION_ENTRIES: usize = 10_000;
+
+/// First line of a journal: the store file the entries extend
+#[derive(Debug, Serialize, Deserialize)]
+struct Header {
+    /// SHA-256 of the store file's content
+    base: String,
+}
+
+/// Append-only log of the triples added since a store was last written in
+/// full, kept in `<kg_path>.journal` as JSON Lines after a header line.
+///
+/// The header names the store content the entries extend, so a journal left
+/// behind by a compaction interrupted between rewriting the store and
+/// resetting the journal is recognized as stale and ignored. A final line
+/// without its newline, from a crash mid-append, is dropped.
+#[derive(Debug)]
+pub struct Journal {
+    path: PathBuf,
+    entries: usize,
+    /// The journal can't be appended to until it is reset: it is missing,
+    /// stale or ends in a torn line
+    needs_reset: bool,
+}
+
+impl Journal {
+    /// Journal of the knowledge graph stored at `kg_path`
+    pub fn path_for(kg_path: &str) -> PathBuf {
+        PathBuf::from(format!("{}.journal", kg_path))
+    }
+
+    /// Open the journal of the store at `kg_path`, whose file holds `base`,
+    /// returning the triples it adds
+    pub fn open(kg_path: &str, base: Option<&[u8]>) -> Result<(Self, Vec<RdfTriple>)> {
+        let mut journal = Self { path: Self::path_for(kg_path), entries: 0, needs_reset: true };
+        let (Some(base), true) = (base, journal.path.exists()) else {
+            return Ok((journal, Vec::new()));
+        };
+
+        let content = fs::read_to_string(&journal.path)
+            .storage_context(|| format!("Failed to read journal: {}", journal.path.display()))?;
+        let mut lines = content.split_inclusive('\n');
+        let header: Option<Header> = lines.next().and_then(|line| serde_json::from_str(line).ok());
+        if header.is_none_or(|header| header.base != sha256_hex(base)) {
+            tracing::warn!("Ignoring stale journal: {}", journal.path.display());
+            return Ok((journal, Vec::new()));
+        }
+
+        let mut triples = Vec::new();
+        let mut torn = false;
+        for (number, line) in lines.enumerate() {
+            if !line.ends_with('\n') {
+                torn = true;
+                break;
+            }
+            let triple = serde_json::from_str(line)
+                .storage_context(|| format!("Failed to parse line {} of journal {}", number + 2, journal.path.display()))?;
+            triples.push(triple);
+        }
+        if torn {
+            tracing::warn!("Dropping incomplete last entry of journal: {}", journal.path.display());
+        }
+
+        journal.entries = triples.len();
+        journal.needs_reset = torn;
+        Ok((journal, triples))
+    }
+
+    /// Entries appended since the journal was last reset
+    pub fn len(&self) -> usize {
+        self.entries
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries == 0
+    }
+
+    /// Whether the store should be rewritten rather than appended to: the
+    /// journal can't be appended to, or has grown past the `base_triples`
+    /// in the store file
+    pub fn should_compact(&self, base_triples: usize) -> bool {
+        self.needs_reset || self.entries >= base_triples.max(MIN_COMPACTION_ENTRIES)
+    }
+
+    /// Append `triples` and sync them to disk
+    pub fn append(&mut self, triples: &[RdfTriple]) -> Result<()> {
+        if triples.is_empty() {
+            return Ok(());
+        }
+        let mut lines = Vec::new();
+        for triple in triples {
+            serde_json::to_writer(&mut lines, triple)
+                .storage_context(|| "Failed to serialize journal entry")?;
+            lines.push(b'\n');
+        }
+
+        let mut file = OpenOptions::new().append(true).open(&self.path)
+            .storage_context(|| format!("Failed to open journal: {}", self.path.display()))?;
+        file.write_all(&lines)
+            .and_then(|_| file.sync_data())
+            .storage_context(|| format!("Failed to append to journal: {}", self.path.display()))?;
+        self.entries += triples.len();
+        Ok(())
+    }
+
+    /// Start an empty journal extending a store file that now holds `base`
+    pub fn reset(&mut self, base: &[u8]) -> Result<()> {
+        let mut content = serde_json::to_vec(&Header { base: sha256_hex(base) })
+            .storage_context(|| "Failed to serialize journal header")?;
+        content.push(b'\n');
+        write_atomic(&self.path, &content)?;
+        self.entries = 0;
+        self.needs_reset = false;
+        Ok(())
+    }
+}
+
+/// Read every well-formed entry of the journal of the store at `kg_path`,
+/// for `kg repair`. Entries that don't parse and a torn last line are
+/// quarantined instead of failing the read. The header is not checked: the
+/// damage that made the store unreadable also changed its digest, so the
+/// entries may be pending or already in the store.
+pub fn salvage(kg_path: &str) -> Result<Salvage> {
+    let path = Journal::path_for(kg_path);
+    let mut salvage = Salvage::default();
+    if !path.exists() {
+        return Ok(salvage);
+    }
+    let content = fs::read(&path)
+        .storage_context(|| format!("Failed to read journal: {}", path.display()))?;
+    let content = String::from_utf8_lossy(&content);
+
+    for (number, line) in content.split_inclusive('\n').enumerate() {
+        if (number == 0 && serde_json::from_str::<Header>(line).is_ok()) || line.trim().is_empty() {
+            continue;
+        }
+        let error = match serde_json::from_str::<RdfTriple>(line) {
+            Ok(_) if !line.ends_with('\n') => "Incomplete entry".to_string(),
+            Ok(triple) => {
+                salvage.triples.push(triple);
+                continue;
+            }
+            Err(e) => e.to_string(),
+        };
+        salvage.quarantined.push(QuarantinedRecord {
+            file: Some(path.display().to_string()),
+            line: number + 1,
+            error,
+            record: line.trim_end().to_string(),
+        });
+    }
+    Ok(salvage)
+}
+
+/// Replace `path` with `content` by writing and syncing a temporary file
+/// next to it and renaming it over the original, so a crash leaves either
+/// the old content or the new
+pub fn write_atomic(path: &Path, content: &[u8]) -> Result<()> {
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    let temp = PathBuf::from(temp);
+
+    let mut file = fs::File::create(&temp)
+        .storage_context(|| format!("Failed to create {}", temp.display()))?;
+    file.write_all(content)
+        .and_then(|_| file.sync_all())
+        .storage_context(|| format!("Failed to write {}", temp.display()))?;
+    fs::rename(&temp, path)
+        .storage_context(|| format!("Failed to replace {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::knowledge_graph::{KnowledgeGraph, KnowledgeGraphConfig, TriplePattern};
+    use crate::config::RdfSchema;
+
+    fn triple(s: &str, o: &str) -> RdfTriple {
+        RdfTriple::new(format!("http://ex.org/{}", s), "http://ex.org/p".into(), o.into())
+    }
+
+    /// Triples in a JSON store file
+    fn stored(content: &[u8]) -> usize {
+        serde_json::from_slice::<Vec<RdfTriple>>(content).unwrap().len()
+    }
+
+    #[test]
+    fn test_journaled_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("kg.db").to_string_lossy().to_string();
+        let journal = Journal::path_for(&path);
+        let open = |read_only| KnowledgeGraph::new(KnowledgeGraphConfig { storage_path: path.clone(), read_only, ..Default::default() }, RdfSchema::default()).unwrap();
+
+        // The first write creates the store file, later additions are appended
+        let mut kg = open(false);
+        kg.add_triples(&[triple("a", "1")]).unwrap();
+        kg.add_triples(&[triple("b", "2"), triple("c", "3")]).unwrap();
+        let base = fs::read(&path).unwrap();
+        assert_eq!(stored(&base), 1);
+        assert_eq!(fs::read_to_string(&journal).unwrap().lines().count(), 3);
+        assert_eq!(open(true).triples().len(), 3);
+        drop(kg);
+
+        // A torn append is dropped, and the next write compacts the store
+        let mut file = OpenOptions::new().append(true).open(&journal).unwrap();
+        file.write_all(br#"{"subject":"http://ex.org/d","#).unwrap();
+        let mut kg = open(false);
+        assert_eq!(kg.triples().len(), 3);
+        kg.add_triples(&[triple("e", "5")]).unwrap();
+        assert_eq!(stored(&fs::read(&path).unwrap()), 4);
+        assert_eq!(fs::read_to_string(&journal).unwrap().lines().count(), 1);
+
+        // Removals rewrite the store; a journal for the old content is stale
+        let stale = fs::read(&journal).unwrap();
+        kg.add_triples(&[triple("f", "6")]).unwrap();
+        kg.remove_triples(&TriplePattern { object: Some("1".into()), ..Default::default() }).unwrap();
+        assert_eq!(stored(&fs::read(&path).unwrap()), 4);
+        fs::write(&journal, [stale, br#"{"subject":"http://ex.org/a","predicate":"http://ex.org/p","object":"1"}"#.to_vec(), b"\n".to_vec()].concat()).unwrap();
+        let objects: Vec<String> = open(true).triples().iter().map(|t| t.object.clone()).collect();
+        assert_eq!(objects, ["2", "3", "5", "6"]);
+    }
+}
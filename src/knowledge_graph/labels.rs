@@ -0,0 +1,211 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use unicode_normalization::UnicodeNormalization;
+
+use crate::core::RdfTriple;
+
+const RDFS_LABEL: &str = "http://www.w3.org/2000/01/rdf-schema#label";
+const SKOS_PREF_LABEL: &str = "http://www.w3.org/2004/02/skos/core#prefLabel";
+const SKOS_ALT_LABEL: &str = "http://www.w3.org/2004/02/skos/core#altLabel";
+
+/// How strongly `predicate` names its subject: `rdfs:label` (0), then
+/// `skos:prefLabel` (1), then any `hasName` or `name` predicate such as
+/// `foaf:name` (2); `None` for predicates that are not labels
+pub fn label_rank(predicate: &str) -> Option<u8> {
+    if predicate == RDFS_LABEL || predicate == "rdfs:label" {
+        return Some(0);
+    }
+    if predicate == SKOS_PREF_LABEL || predicate == "skos:prefLabel" {
+        return Some(1);
+    }
+
+    let local = predicate.rsplit(['/', '#', ':']).next().unwrap_or(predicate);
+    match local {
+        "hasName" | "name" => Some(2),
+        _ => None,
+    }
+}
+
+/// The best label of each labelled subject: the lowest [`label_rank`], then
+/// the alphabetically first label, so every caller names an entity alike.
+/// Empty labels and IRI objects are skipped.
+pub fn entity_labels<'a>(triples: impl IntoIterator<Item = &'a RdfTriple>) -> HashMap<String, String> {
+    let mut best: HashMap<&str, (u8, &str)> = HashMap::new();
+    for triple in triples {
+        let Some(rank) = label_rank(&triple.predicate) else {
+            continue;
+        };
+        let label = triple.object.trim();
+        if label.is_empty() || label.starts_with("http://") || label.starts_with("https://") {
+            continue;
+        }
+        let candidate = (rank, label);
+        best.entry(triple.subject.as_str())
+            .and_modify(|current| if candidate < *current { *current = candidate })
+            .or_insert(candidate);
+    }
+    best.into_iter().map(|(uri, (_, label))| (uri.to_string(), label.to_string())).collect()
+}
+
+/// Whether the objects of `predicate` are indexed for autocompletion: the
+/// naming predicates of [`label_rank`] and `skos:altLabel`
+fn is_indexed(predicate: &str) -> bool {
+    label_rank(predicate).is_some() || predicate == SKOS_ALT_LABEL || predicate == "skos:altLabel"
+}
+
+/// An entity whose label starts with the requested prefix
+#[derive(Debug, Clone, PartialEq, Serialize, utoipa::ToSchema)]
+pub struct LabelMatch {
+    pub uri: String,
+    pub label: String,
+}
+
+/// Label → URI index over the naming triples of a knowledge graph, kept up
+/// to date as triples are added and removed
+///
+/// Every word of a label is a key, so "acme" and "corp" both find "Acme
+/// Corporation". Keys are case-folded and NFC-normalized.
+#[derive(Debug, Clone, Default)]
+pub struct LabelIndex {
+    /// (key, uri, label) → number of triples asserting the label, so a label
+    /// stated in several graphs stays until its last triple is removed
+    entries: BTreeMap<(String, String, String), usize>,
+}
+
+impl LabelIndex {
+    pub fn from_triples<'a>(triples: impl IntoIterator<Item = &'a RdfTriple>) -> Self {
+        let mut index = Self::default();
+        for triple in triples {
+            index.insert(triple);
+        }
+        index
+    }
+
+    pub fn insert(&mut self, triple: &RdfTriple) {
+        for key in index_keys(triple) {
+            *self.entries.entry(key).or_default() += 1;
+        }
+    }
+
+    pub fn remove(&mut self, triple: &RdfTriple) {
+        for key in index_keys(triple) {
+            if let Some(count) = self.entries.get_mut(&key) {
+                *count -= 1;
+                if *count == 0 {
+                    self.entries.remove(&key);
+                }
+            }
+        }
+    }
+
+    /// Up to `limit` entities with a label word starting with `prefix`, one
+    /// match per entity: labels starting with the prefix come first, then
+    /// shorter labels, then alphabetically
+    pub fn autocomplete(&self, prefix: &str, limit: usize) -> Vec<LabelMatch> {
+        let prefix = fold(prefix);
+        if prefix.is_empty() {
+            return Vec::new();
+        }
+
+        let mut best: BTreeMap<&str, (bool, &str, &str)> = BTreeMap::new();
+        let start = (prefix.clone(), String::new(), String::new());
+        for ((key, uri, label), _) in self.entries.range(start..) {
+            if !key.starts_with(&prefix) {
+                break;
+            }
+            let candidate = (*key != fold(label), label.as_str(), uri.as_str());
+            best.entry(uri.as_str())
+                .and_modify(|current| if rank(&candidate) < rank(current) { *current = candidate })
+                .or_insert(candidate);
+        }
+
+        let mut matches: Vec<_> = best.into_values().collect();
+        matches.sort_by(|a, b| rank(a).cmp(&rank(b)).then(a.2.cmp(b.2)));
+        matches.into_iter()
+            .take(limit)
+            .map(|(_, label, uri)| LabelMatch { uri: uri.to_string(), label: label.to_string() })
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+fn rank<'a>(candidate: &(bool, &'a str, &str)) -> (bool, usize, &'a str) {
+    (candidate.0, candidate.1.chars().count(), candidate.1)
+}
+
+/// One key per word of the triple's label, each running to the end of the label
+fn index_keys(triple: &RdfTriple) -> Vec<(String, String, String)> {
+    let label = triple.object.trim();
+    if !is_indexed(&triple.predicate) || label.is_empty() || triple.object.starts_with("http://") || triple.object.starts_with("https://") {
+        return Vec::new();
+    }
+
+    let folded = fold(label);
+    let mut keys = vec![folded.clone()];
+    keys.extend(folded.char_indices()
+        .filter(|(i, c)| *i > 0 && !c.is_whitespace() && folded[..*i].ends_with(|p: char| p.is_whitespace() || matches!(p, '-' | '(' | '/')))
+        .map(|(i, _)| folded[i..].to_string()));
+    keys.dedup();
+
+    keys.into_iter()
+        .map(|key| (key, triple.subject.clone(), label.to_string()))
+        .collect()
+}
+
+/// Case-folded, NFC-normalized text with single spaces
+fn fold(text: &str) -> String {
+    text.nfc().collect::<String>().to_lowercase().split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RdfSchema;
+    use crate::knowledge_graph::{KnowledgeGraph, TriplePattern};
+
+    #[test]
+    fn test_autocomplete() {
+        let mut kg = KnowledgeGraph::in_memory(RdfSchema::default()).unwrap();
+        let triple = |s: &str, p: &str, o: &str| RdfTriple::new(format!("http://ex.org/{}", s), p.to_string(), o.to_string());
+        kg.add_triples(&[
+            triple("acme", RDFS_LABEL, "Acme Corporation"),
+            triple("acme", "http://ex.org/hasName", "ACME"),
+            triple("globex", RDFS_LABEL, "Globex"),
+            triple("ana", "http://xmlns.com/foaf/0.1/name", "Ana Ruiz"),
+            triple("acme_labs", SKOS_ALT_LABEL, "Acme Labs"),
+            triple("acme", "http://ex.org/basedIn", "Acme City"),
+        ]).unwrap();
+
+        let found = kg.autocomplete("acm", 10);
+        assert_eq!(found, [
+            LabelMatch { uri: "http://ex.org/acme".into(), label: "ACME".into() },
+            LabelMatch { uri: "http://ex.org/acme_labs".into(), label: "Acme Labs".into() },
+        ]);
+        assert_eq!(kg.autocomplete("  RUI", 10)[0].label, "Ana Ruiz");
+        assert_eq!(kg.autocomplete("corp", 10)[0].uri, "http://ex.org/acme");
+        assert_eq!(kg.autocomplete("acme", 1).len(), 1);
+        assert!(kg.autocomplete("", 10).is_empty());
+
+        // Removing triples keeps the index current
+        kg.remove_triples(&TriplePattern { subject: Some("http://ex.org/acme_labs".into()), ..Default::default() }).unwrap();
+        assert_eq!(kg.autocomplete("acme l", 10), []);
+        assert_eq!(kg.in_memory_copy().autocomplete("glob", 10)[0].label, "Globex");
+
+        // One label per entity: the best rank, then the first alphabetically
+        let labels = entity_labels(kg.triples().iter().chain(&[
+            triple("globex", RDFS_LABEL, "Globex Corporation"),
+            triple("initech", RDFS_LABEL, "http://ex.org/initech_label"),
+        ]));
+        assert_eq!(labels["http://ex.org/acme"], "Acme Corporation");
+        assert_eq!(labels["http://ex.org/globex"], "Globex");
+        assert_eq!(labels["http://ex.org/ana"], "Ana Ruiz");
+        assert!(!labels.contains_key("http://ex.org/initech"));
+    }
+}
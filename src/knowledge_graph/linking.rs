@@ -0,0 +1,351 @@
+//! Semantic entity linking: entities in newly extracted triples that the
+//! knowledge graph does not know yet are matched by the embedding of their
+//! label against the labelled entities already stored, so "Acme Corp." from
+//! one document takes the URI minted for "Acme Corporation" in another
+
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use super::embedding_index::EmbeddingIndex;
+use super::labels::entity_labels;
+use super::KnowledgeGraph;
+use crate::config::EntityLinkingSettings;
+use crate::core::embeddings::{cosine_similarity, EmbeddingClient};
+use crate::core::RdfTriple;
+use crate::error::Result;
+
+const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+const OWL_SAME_AS: &str = "http://www.w3.org/2002/07/owl#sameAs";
+
+/// A new entity matched to an existing one
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EntityLink {
+    pub uri: String,
+    pub target: String,
+    pub similarity: f32,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LinkReport {
+    /// Entities rewritten to the URI of the existing entity
+    pub linked: Vec<EntityLink>,
+    /// Entities that kept their URI and were stated `owl:sameAs` the existing one
+    pub same_as: Vec<EntityLink>,
+}
+
+/// Links new entities to existing ones before their triples are stored;
+/// label embeddings are kept in the knowledge graph's [`EmbeddingIndex`]
+/// so existing entities are embedded once
+pub struct EntityLinker {
+    client: EmbeddingClient,
+    threshold: f32,
+    same_as_threshold: Option<f32>,
+    cache_path: Option<PathBuf>,
+    cache: EmbeddingIndex,
+}
+
+impl EntityLinker {
+    /// Embedding index used for the knowledge graph stored at `kg_path`
+    pub fn cache_path_for(kg_path: &str) -> PathBuf {
+        EmbeddingIndex::path_for(kg_path)
+    }
+
+    pub fn new(client: EmbeddingClient, settings: &EntityLinkingSettings) -> Self {
+        let cache = EmbeddingIndex::new(client.model.clone());
+        Self {
+            client,
+            threshold: settings.threshold,
+            same_as_threshold: settings.same_as_threshold,
+            cache_path: None,
+            cache,
+        }
+    }
+
+    /// Read and write label embeddings in the index at `path`; vectors of
+    /// another model are discarded
+    pub fn with_cache<P: AsRef<Path>>(mut self, path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        self.cache = EmbeddingIndex::open(&path, &self.client.model)?;
+        self.cache_path = Some(path);
+        Ok(self)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        match &self.cache_path {
+            Some(path) => self.cache.save(path),
+            None => Ok(()),
+        }
+    }
+
+    /// Embed the labels of every labelled entity in `knowledge_graph` anew,
+    /// dropping vectors of labels it no longer holds
+    pub async fn rebuild(&mut self, knowledge_graph: &KnowledgeGraph) -> Result<&EmbeddingIndex> {
+        let mut labels: Vec<String> = entity_labels(knowledge_graph.triples()).into_values().collect();
+        labels.sort();
+        labels.dedup();
+
+        let mut index = EmbeddingIndex::new(self.client.model.clone());
+        let vectors = self.client.embed(&labels).await?;
+        for (label, vector) in labels.into_iter().zip(vectors) {
+            index.insert(label, vector);
+        }
+        self.cache = index;
+        Ok(&self.cache)
+    }
+
+    /// Rewrite entities of `triples` that are new to `knowledge_graph` to the
+    /// most similar existing entity at or above the threshold, or add an
+    /// `owl:sameAs` triple when the similarity only reaches `same_as_threshold`.
+    /// Entities whose types are both known but disjoint are never matched.
+    /// Only entities whose labels share a word are compared, so neither side
+    /// is embedded or scanned against the whole graph.
+    pub async fn link(&mut self, knowledge_graph: &KnowledgeGraph, triples: &mut Vec<RdfTriple>) -> Result<LinkReport> {
+        let existing = knowledge_graph.triples();
+        let known: HashSet<&str> = existing.iter().map(|t| t.subject.as_str()).collect();
+        let candidates: Vec<(String, String)> = entity_labels(existing).into_iter().collect();
+        if candidates.is_empty() {
+            return Ok(LinkReport::default());
+        }
+
+        let new_labels = entity_labels(triples.iter());
+        let mut new_entities: Vec<(String, String)> = Vec::new();
+        let mut seen = HashSet::new();
+        for triple in triples.iter() {
+            let mut uris = vec![&triple.subject];
+            if is_iri(&triple.object) && triple.predicate != RDF_TYPE {
+                uris.push(&triple.object);
+            }
+            for uri in uris {
+                if known.contains(uri.as_str()) || !seen.insert(uri.clone()) {
+                    continue;
+                }
+                let label = new_labels.get(uri).cloned().unwrap_or_else(|| local_name_label(uri));
+                if !label.is_empty() {
+                    new_entities.push((uri.clone(), label));
+                }
+            }
+        }
+        let mut by_word: HashMap<String, Vec<usize>> = HashMap::new();
+        for (index, (_, label)) in candidates.iter().enumerate() {
+            for word in label_words(label) {
+                by_word.entry(word).or_default().push(index);
+            }
+        }
+        let mut blocks: Vec<((String, String), Vec<usize>)> = Vec::new();
+        for (uri, label) in new_entities {
+            let mut block: Vec<usize> = label_words(&label)
+                .filter_map(|word| by_word.get(&word))
+                .flatten()
+                .copied()
+                .collect();
+            block.sort_unstable();
+            block.dedup();
+            if !block.is_empty() {
+                blocks.push(((uri, label), block));
+            }
+        }
+        if blocks.is_empty() {
+            return Ok(LinkReport::default());
+        }
+
+        let mut compared: Vec<usize> = blocks.iter().flat_map(|(_, block)| block.iter().copied()).collect();
+        compared.sort_unstable();
+        compared.dedup();
+        let compared_vectors = self.vectors(compared.iter().map(|&index| &candidates[index].1)).await?;
+        let candidate_vectors: HashMap<usize, Vec<f32>> = compared.into_iter().zip(compared_vectors).collect();
+        let new_vectors = self.vectors(blocks.iter().map(|((_, label), _)| label)).await?;
+        let existing_types = entity_types(existing);
+        let new_types = entity_types(triples.iter());
+
+        let mut report = LinkReport::default();
+        for (((uri, _), block), vector) in blocks.iter().zip(&new_vectors) {
+            let types = new_types.get(uri);
+            let best = block.iter()
+                .map(|&index| (&candidates[index].0, &candidate_vectors[&index]))
+                .filter(|(target, _)| compatible(types, existing_types.get(*target)))
+                .map(|(target, candidate)| (target, cosine_similarity(vector, candidate)))
+                .max_by(|a, b| a.1.total_cmp(&b.1));
+            let Some((target, similarity)) = best else {
+                continue;
+            };
+
+            let link = EntityLink { uri: uri.clone(), target: target.clone(), similarity };
+            if similarity >= self.threshold {
+                report.linked.push(link);
+            } else if self.same_as_threshold.is_some_and(|threshold| similarity >= threshold) {
+                report.same_as.push(link);
+            }
+        }
+
+        let rewrites: HashMap<&str, &str> = report.linked.iter()
+            .map(|link| (link.uri.as_str(), link.target.as_str()))
+            .collect();
+        for triple in triples.iter_mut() {
+            if let Some(target) = rewrites.get(triple.subject.as_str()) {
+                triple.subject = target.to_string();
+            }
+            if let Some(target) = rewrites.get(triple.object.as_str()) {
+                triple.object = target.to_string();
+            }
+        }
+
+        let source = triples.first().and_then(|t| t.source.clone());
+        for link in &report.same_as {
+            let mut triple = RdfTriple::new(link.uri.clone(), OWL_SAME_AS.to_string(), link.target.clone())
+                .with_confidence(link.similarity);
+            triple.source = source.clone();
+            triples.push(triple);
+        }
+
+        Ok(report)
+    }
+
+    /// Embeddings of `labels`, computing only those not cached yet
+    async fn vectors<'a>(&mut self, labels: impl Iterator<Item = &'a String>) -> Result<Vec<Vec<f32>>> {
+        let labels: Vec<&String> = labels.collect();
+        let mut missing: Vec<String> = labels.iter()
+            .filter(|label| !self.cache.contains(label))
+            .map(|label| label.to_string())
+            .collect();
+        missing.sort();
+        missing.dedup();
+
+        if !missing.is_empty() {
+            let vectors = self.client.embed(&missing).await?;
+            for (label, vector) in missing.into_iter().zip(vectors) {
+                self.cache.insert(label, vector);
+            }
+        }
+
+        Ok(labels.iter().map(|label| self.cache.get(label).unwrap_or_default().to_vec()).collect())
+    }
+}
+
+fn entity_types<'a>(triples: impl IntoIterator<Item = &'a RdfTriple>) -> HashMap<String, HashSet<String>> {
+    let mut types: HashMap<String, HashSet<String>> = HashMap::new();
+    for triple in triples.into_iter().filter(|t| t.predicate == RDF_TYPE) {
+        types.entry(triple.subject.clone()).or_default().insert(triple.object.clone());
+    }
+    types
+}
+
+/// Entities match unless both have types and share none
+fn compatible(a: Option<&HashSet<String>>, b: Option<&HashSet<String>>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => !a.is_disjoint(b),
+        _ => true,
+    }
+}
+
+/// Lowercase words of a label, used to pick the entities worth comparing
+fn label_words(label: &str) -> impl Iterator<Item = String> + '_ {
+    label.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// Label derived from the last segment of a URI, e.g. "Acme Corp" for
+/// `http://ex.org/resource/Acme_Corp`
+fn local_name_label(uri: &str) -> String {
+    let local = uri.trim_end_matches(['/', '#']).rsplit(['/', '#']).next().unwrap_or(uri);
+    local.split(['_', '-', '+']).filter(|word| !word.is_empty()).collect::<Vec<_>>().join(" ")
+}
+
+fn is_iri(value: &str) -> bool {
+    value.starts_with("http://") || value.starts_with("https://")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RdfSchema;
+
+    const LABEL: &str = "http://www.w3.org/2000/01/rdf-schema#label";
+
+    fn triple(s: &str, p: &str, o: &str) -> RdfTriple {
+        let iri = |v: &str| v.strip_prefix(':').map_or_else(|| v.to_string(), |local| format!("http://ex.org/{}", local));
+        RdfTriple::new(iri(s), iri(p), iri(o))
+    }
+
+    #[tokio::test]
+    async fn test_entity_linking() {
+        let mut server = mockito::Server::new_async().await;
+        let embeddings = server.mock("POST", "/v1/embeddings")
+            .with_body_from_request(|request| {
+                let body: serde_json::Value = serde_json::from_slice(request.body().unwrap()).unwrap();
+                let data: Vec<_> = body["input"].as_array().unwrap().iter().enumerate()
+                    .map(|(index, label)| {
+                        let embedding = match label.as_str().unwrap() {
+                            "Acme Corporation" => [1.0, 0.0, 0.0],
+                            "Acme Corp." => [0.99, 0.1, 0.0],
+                            "Globex" => [0.0, 1.0, 0.0],
+                            "Globex Holdings" => [0.3, 0.8, 0.5],
+                            _ => [0.0, 0.0, 1.0],
+                        };
+                        serde_json::json!({"object": "embedding", "index": index, "embedding": embedding})
+                    })
+                    .collect();
+                serde_json::json!({"object": "list", "data": data}).to_string().into()
+            })
+            .expect(3)
+            .create_async()
+            .await;
+
+        let mut kg = KnowledgeGraph::in_memory(RdfSchema::default()).unwrap();
+        kg.add_triples(&[
+            triple(":acme", LABEL, "Acme Corporation"),
+            triple(":globex", LABEL, "Globex"),
+        ]).unwrap();
+
+        let settings = EntityLinkingSettings {
+            enabled: true,
+            model: "embed".into(),
+            threshold: 0.95,
+            same_as_threshold: Some(0.8),
+            ..Default::default()
+        };
+        let client = EmbeddingClient::new(server.url(), None, "embed".into(), 5).unwrap();
+        let cache_path = std::env::temp_dir().join(format!("embeddings_test_{}", uuid::Uuid::new_v4()));
+        let mut linker = EntityLinker::new(client, &settings).with_cache(&cache_path).unwrap();
+
+        let mut triples = vec![
+            triple(":acme_corp", LABEL, "Acme Corp."),
+            triple(":ana", ":worksFor", ":acme_corp"),
+            triple(":globex_holdings", LABEL, "Globex Holdings"),
+            triple(":initech", LABEL, "Initech"),
+        ];
+        let report = linker.link(&kg, &mut triples).await.unwrap();
+
+        assert_eq!(report.linked.len(), 1);
+        assert_eq!((report.linked[0].uri.as_str(), report.linked[0].target.as_str()), ("http://ex.org/acme_corp", "http://ex.org/acme"));
+        assert_eq!(triples[0].subject, "http://ex.org/acme");
+        assert_eq!(triples[1].object, "http://ex.org/acme");
+        assert_eq!(report.same_as.len(), 1);
+        assert_eq!(report.same_as[0].target, "http://ex.org/globex");
+        assert!(triples.iter().any(|t| t.subject == "http://ex.org/globex_holdings" && t.predicate == OWL_SAME_AS));
+        assert!(!triples.iter().any(|t| t.subject == "http://ex.org/initech" && t.predicate == OWL_SAME_AS));
+        // Initech shares no word with a stored label, so it is not embedded
+        assert!(!linker.cache.contains("Initech"));
+
+        // Cached labels are not embedded again
+        linker.save().unwrap();
+        let client = EmbeddingClient::new(server.url(), None, "embed".into(), 5).unwrap();
+        let mut linker = EntityLinker::new(client, &settings).with_cache(&cache_path).unwrap();
+        let mut triples = vec![triple(":acme_corp", LABEL, "Acme Corp.")];
+        assert_eq!(linker.link(&kg, &mut triples).await.unwrap().linked.len(), 1);
+
+        // Rebuilding keeps only the labels of stored entities
+        let index = linker.rebuild(&kg).await.unwrap();
+        assert_eq!((index.len(), index.contains("Acme Corp.")), (2, false));
+        embeddings.assert_async().await;
+
+        std::fs::remove_file(cache_path).unwrap();
+    }
+
+    #[test]
+    fn test_local_name_label() {
+        assert_eq!(local_name_label("http://ex.org/resource/Acme_Corp"), "Acme Corp");
+        assert_eq!(local_name_label("http://ex.org/ns#Jane-Doe/"), "Jane Doe");
+    }
+}
@@ -0,0 +1,91 @@
+use crate::error::{Error, ErrorContext, Result};
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// How often a waiting process retries a held lock
+const RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Advisory lock on `<kg_path>.lock`, held by the one process that has a
+/// store open for writing and released when dropped. Read-only handles take
+/// no lock: writes only ever append to the journal or rename a complete file
+/// into place, so readers never see a half-written store.
+#[derive(Debug)]
+pub struct StoreLock {
+    _file: File,
+}
+
+impl StoreLock {
+    /// Lock file used for the knowledge graph stored at `kg_path`
+    pub fn path_for(kg_path: &str) -> PathBuf {
+        PathBuf::from(format!("{}.lock", kg_path))
+    }
+
+    /// Take the lock of the store at `kg_path`, waiting up to `timeout` for
+    /// another process to release it. Waiting blocks the calling thread
+    pub fn acquire(kg_path: &str, timeout: Option<Duration>) -> Result<Self> {
+        let path = Self::path_for(kg_path);
+        let mut file = OpenOptions::new().create(true).truncate(false).read(true).write(true).open(&path)
+            .storage_context(|| format!("Failed to open lock file: {}", path.display()))?;
+
+        let deadline = Instant::now() + timeout.unwrap_or_default();
+        loop {
+            match file.try_lock() {
+                Ok(()) => break,
+                Err(TryLockError::WouldBlock) if Instant::now() < deadline => std::thread::sleep(RETRY_INTERVAL),
+                Err(TryLockError::WouldBlock) => {
+                    let holder = fs::read_to_string(&path).unwrap_or_default();
+                    let holder = match holder.trim() {
+                        "" => String::new(),
+                        pid => format!(" (process {})", pid),
+                    };
+                    return Err(Error::storage(format!(
+                        "Knowledge graph {} is open for writing by another process{}; wait for it to finish or open the store read-only",
+                        kg_path, holder
+                    )));
+                }
+                Err(TryLockError::Error(e)) => {
+                    return Err(e).storage_context(|| format!("Failed to lock {}", path.display()));
+                }
+            }
+        }
+
+        // Name the holder for processes that find the store locked
+        file.set_len(0)
+            .and_then(|_| write!(file, "{}", std::process::id()))
+            .storage_context(|| format!("Failed to write lock file: {}", path.display()))?;
+        Ok(Self { _file: file })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RdfSchema;
+    use crate::knowledge_graph::{KnowledgeGraph, KnowledgeGraphConfig};
+
+    #[test]
+    fn test_store_lock() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("kg.db").to_string_lossy().to_string();
+        let open = |read_only| KnowledgeGraph::new(KnowledgeGraphConfig { storage_path: path.clone(), read_only, ..Default::default() }, RdfSchema::default());
+
+        let mut writer = open(false).unwrap();
+        writer.add_triples(&[crate::core::RdfTriple::new("http://ex.org/s".into(), "http://ex.org/p".into(), "o".into())]).unwrap();
+
+        let error = open(false).err().unwrap().to_string();
+        assert!(error.contains("open for writing by another process"), "{}", error);
+        assert!(error.contains(&std::process::id().to_string()), "{}", error);
+        assert_eq!(open(true).unwrap().triples().len(), 1);
+
+        // A waiting process gets the lock once the holder closes the store
+        let release = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(200));
+            drop(writer);
+        });
+        let config = KnowledgeGraphConfig { storage_path: path.clone(), lock_timeout_ms: Some(5_000), ..Default::default() };
+        assert_eq!(KnowledgeGraph::new(config, RdfSchema::default()).unwrap().triples().len(), 1);
+        release.join().unwrap();
+    }
+}
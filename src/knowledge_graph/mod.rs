@@ -1,39 +1,278 @@
-use anyhow::{Result, Context};
+use crate::error::{Error, ErrorContext, Result};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::io::{BufWriter, Write};
 use std::path::Path;
+use std::time::{Duration, Instant};
 use tracing::{debug, info, warn};
-use uuid::Uuid;
 use std::fs;
 
-use crate::config::RdfSchema;
+use crate::config::{LiteralNormalization, OutputFormat, RdfSchema};
 use crate::core::RdfTriple;
+use labels::LabelIndex;
+use crate::utils::RdfSerializer;
+use crate::utils::serialization::{compact_iri, turtle_prefixes};
 
 // #[cfg(feature = "oxigraph")]
 // pub mod oxigraph_store;
 // #[cfg(feature = "oxigraph")]
 // pub use oxigraph_store::OxigraphKnowledgeGraph;
 
+pub mod binding;
+pub use binding::Binding;
+pub mod drift;
+pub use drift::{SchemaDrift, UnknownPredicate};
+pub mod embedding_index;
+pub use embedding_index::EmbeddingIndex;
+pub mod enrichment;
+pub use enrichment::{EnrichOptions, EnrichReport, Enricher, ExternalProperty, ExternalSource};
+pub mod feed;
+pub use feed::{EntityFeed, EntitySummary, FeedUpdate};
+pub mod feedback;
+pub use feedback::{Correction, FeedbackStore};
+pub mod federation;
+pub use federation::SparqlEndpoint;
+pub mod index;
+pub use index::TripleIndex;
+pub mod inference;
+pub use inference::Reasoner;
+pub mod jobs;
+pub mod journal;
+pub use journal::Journal;
+pub mod labels;
+pub use labels::LabelMatch;
+pub mod linking;
+pub mod lock;
+pub use lock::StoreLock;
+pub use linking::{EntityLink, EntityLinker, LinkReport};
+pub use jobs::{DocumentStatus, JobDocument, JobManifest};
+pub mod namespaces;
+pub use namespaces::NamespaceMap;
+pub mod normalize;
+pub use normalize::Normalizer;
+pub mod queries;
+pub use queries::{QueryLibrary, SavedQuery};
+pub mod remote;
+pub use remote::{PushReport, RemoteStore};
+pub mod repair;
+pub mod shared;
+pub use shared::SharedKnowledgeGraph;
+pub use repair::{QuarantinedRecord, Salvage};
+pub mod snapshots;
+pub use snapshots::{SnapshotInfo, SnapshotStore};
+pub mod sparql;
+pub use sparql::{QueryLimits, SelectQuery};
+pub mod storage;
+pub use storage::StorageFormat;
+pub mod views;
+pub use views::{MaterializedView, ViewRefresh, ViewStore};
+
+/// Metadata key recording when a triple was added to the knowledge graph
+pub const ADDED_AT: &str = "added_at";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum SimpleSparqlResults {
-    Solutions(Vec<HashMap<String, String>>),
+    Solutions {
+        /// Result columns in SELECT clause order
+        #[serde(default)]
+        variables: Vec<String>,
+        rows: Vec<HashMap<String, Binding>>,
+    },
     Boolean(bool),
 }
 
+impl SimpleSparqlResults {
+    /// Column order for display; falls back to the sorted names bound in
+    /// `rows` when no variables were recorded
+    pub fn columns(variables: &[String], rows: &[HashMap<String, Binding>]) -> Vec<String> {
+        if !variables.is_empty() {
+            return variables.to_vec();
+        }
+        let mut columns: Vec<String> = rows.iter()
+            .flat_map(|row| row.keys().cloned())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        columns.sort();
+        columns
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct KnowledgeGraphConfig {
     pub storage_path: String,
-    pub namespaces: HashMap<String, String>,
     pub default_graph: Option<String>,
+    /// Answer queries over the stored triples plus everything the schema's
+    /// axioms entail, without storing the inferred triples
+    #[serde(default)]
+    pub inference: bool,
+    /// Abort queries running longer than this many milliseconds
+    #[serde(default = "default_query_timeout_ms")]
+    pub query_timeout_ms: Option<u64>,
+    /// Abort queries holding more solutions than this at any stage
+    #[serde(default = "default_max_query_results")]
+    pub max_query_results: Option<usize>,
+    /// Write to disk once this many triples have changed since the last
+    /// write. With neither this nor `flush_interval_ms` set, every change is
+    /// written immediately.
+    #[serde(default)]
+    pub write_batch_size: Option<usize>,
+    /// Write pending changes once this many milliseconds have passed since
+    /// the last write; checked whenever triples change
+    #[serde(default)]
+    pub flush_interval_ms: Option<u64>,
+    /// Open for reading only: the store must already exist and every
+    /// mutation fails
+    #[serde(default)]
+    pub read_only: bool,
+    /// Wait this many milliseconds for another process writing the store
+    /// to close it; without it, opening a store another process is writing
+    /// fails immediately
+    #[serde(default)]
+    pub lock_timeout_ms: Option<u64>,
+    /// Encoding for a new store; an existing store keeps the format it was
+    /// written in until `set_storage_format` changes it
+    #[serde(default)]
+    pub storage_format: StorageFormat,
+    /// Normalizers applied to literal objects before triples are stored
+    #[serde(default)]
+    pub literal_normalization: LiteralNormalization,
+}
+
+fn default_query_timeout_ms() -> Option<u64> { Some(30_000) }
+fn default_max_query_results() -> Option<usize> { Some(1_000_000) }
+
+impl KnowledgeGraphConfig {
+    pub fn query_limits(&self) -> QueryLimits {
+        QueryLimits {
+            timeout: self.query_timeout_ms.map(std::time::Duration::from_millis),
+            max_results: self.max_query_results,
+        }
+    }
 }
 
 impl Default for KnowledgeGraphConfig {
     fn default() -> Self {
         Self {
             storage_path: "knowledge_graph.db".to_string(),
-            namespaces: HashMap::new(),
             default_graph: None,
+            inference: false,
+            query_timeout_ms: default_query_timeout_ms(),
+            max_query_results: default_max_query_results(),
+            write_batch_size: None,
+            flush_interval_ms: None,
+            read_only: false,
+            lock_timeout_ms: None,
+            storage_format: StorageFormat::Json,
+            literal_normalization: LiteralNormalization::default(),
+        }
+    }
+}
+
+/// Builds a `KnowledgeGraph` with a storage location, namespaces and schema
+#[derive(Default)]
+pub struct KnowledgeGraphBuilder {
+    config: KnowledgeGraphConfig,
+    schema: RdfSchema,
+    namespaces: BTreeMap<String, String>,
+}
+
+impl KnowledgeGraphBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn storage_path(mut self, storage_path: impl Into<String>) -> Self {
+        self.config.storage_path = storage_path.into();
+        self
+    }
+
+    pub fn in_memory(mut self) -> Self {
+        self.config.storage_path = ":memory:".to_string();
+        self
+    }
+
+    /// Bind a prefix for this session without persisting it; prefixes in the
+    /// graph's namespace map take precedence
+    pub fn namespace(mut self, prefix: impl Into<String>, uri: impl Into<String>) -> Self {
+        self.namespaces.insert(prefix.into(), uri.into());
+        self
+    }
+
+    pub fn default_graph(mut self, graph: impl Into<String>) -> Self {
+        self.config.default_graph = Some(graph.into());
+        self
+    }
+
+    /// `None` disables the timeout
+    pub fn query_timeout(mut self, timeout: Option<std::time::Duration>) -> Self {
+        self.config.query_timeout_ms = timeout.map(|t| t.as_millis() as u64);
+        self
+    }
+
+    /// `None` disables the limit
+    pub fn max_query_results(mut self, max_results: Option<usize>) -> Self {
+        self.config.max_query_results = max_results;
+        self
+    }
+
+    pub fn inference(mut self, enabled: bool) -> Self {
+        self.config.inference = enabled;
+        self
+    }
+
+    /// `None` writes every change unless a flush interval is set
+    pub fn write_batch_size(mut self, batch_size: Option<usize>) -> Self {
+        self.config.write_batch_size = batch_size;
+        self
+    }
+
+    pub fn flush_interval(mut self, interval: Option<Duration>) -> Self {
+        self.config.flush_interval_ms = interval.map(|i| i.as_millis() as u64);
+        self
+    }
+
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.config.read_only = read_only;
+        self
+    }
+
+    /// Wait up to `timeout` for another process to release the store lock.
+    /// The wait blocks the thread, so build from `tokio::task::spawn_blocking`
+    /// when called from async code
+    pub fn lock_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.config.lock_timeout_ms = timeout.map(|t| t.as_millis() as u64);
+        self
+    }
+
+    pub fn storage_format(mut self, format: StorageFormat) -> Self {
+        self.config.storage_format = format;
+        self
+    }
+
+    pub fn literal_normalization(mut self, settings: LiteralNormalization) -> Self {
+        self.config.literal_normalization = settings;
+        self
+    }
+
+    pub fn schema(mut self, schema: RdfSchema) -> Self {
+        self.schema = schema;
+        self
+    }
+
+    pub fn build(self) -> Result<KnowledgeGraph> {
+        let mut kg = if self.config.storage_path == ":memory:" {
+            let mut kg = KnowledgeGraph::in_memory(self.schema)?;
+            kg.config = self.config;
+            kg
+        } else {
+            KnowledgeGraph::new(self.config, self.schema)?
+        };
+        for (prefix, uri) in self.namespaces {
+            kg.namespaces.entry(prefix).or_insert(uri);
         }
+        Ok(kg)
     }
 }
 
@@ -41,128 +280,721 @@ pub struct KnowledgeGraph {
     triples: Vec<RdfTriple>,
     config: KnowledgeGraphConfig,
     schema: RdfSchema,
+    /// Prefix map from `<storage_path>.namespaces.json`
+    namespaces: BTreeMap<String, String>,
+    /// Label → URI index for autocompletion
+    labels: LabelIndex,
+    /// Subject/predicate/object → position index over `triples`
+    index: TripleIndex,
+    /// Triples changed since the last write to disk
+    pending_writes: usize,
+    last_flush: Instant,
+    /// Log of the triples added since the store file was written; `None`
+    /// for in-memory graphs
+    journal: Option<Journal>,
+    /// Triples in the store file, not counting the journal
+    base_triples: usize,
+    /// Leading triples already on disk, in the store file or the journal
+    persisted: usize,
+    /// A change other than adding triples needs the store file rewritten
+    rewrite_pending: bool,
+    /// Held while the store is open for writing
+    _lock: Option<StoreLock>,
 }
 
 impl KnowledgeGraph {
-    pub fn new(config: KnowledgeGraphConfig, schema: RdfSchema) -> Result<Self> {
-        // Load existing triples if file exists
-        let triples = if Path::new(&config.storage_path).exists() {
-            let content = fs::read_to_string(&config.storage_path)
-                .with_context(|| format!("Failed to read knowledge graph file: {}", config.storage_path))?;
+    pub fn new(mut config: KnowledgeGraphConfig, schema: RdfSchema) -> Result<Self> {
+        if config.read_only && config.storage_path != ":memory:" && !Path::new(&config.storage_path).exists() {
+            return Err(Error::storage(format!("Knowledge graph not found: {}", config.storage_path)));
+        }
 
-            serde_json::from_str(&content)
-                .with_context(|| "Failed to parse knowledge graph JSON")?
+        // Lock before reading, so no other process writes what was read
+        let lock = if config.read_only || config.storage_path == ":memory:" {
+            None
         } else {
-            Vec::new()
+            Some(StoreLock::acquire(&config.storage_path, config.lock_timeout_ms.map(Duration::from_millis))?)
         };
 
+        // Load existing triples if file exists
+        let content = if Path::new(&config.storage_path).exists() {
+            Some(fs::read(&config.storage_path)
+                .storage_context(|| format!("Failed to read knowledge graph file: {}", config.storage_path))?)
+        } else {
+            None
+        };
+        let mut triples = match &content {
+            Some(content) => {
+                config.storage_format = StorageFormat::detect(content);
+                config.storage_format.decode(content)
+                    .storage_context(|| format!("Failed to parse knowledge graph {} (`kg repair` can recover its readable triples)", config.storage_path))?
+            }
+            None => Vec::new(),
+        };
+        let base_triples = triples.len();
+        let journal = if config.storage_path == ":memory:" {
+            None
+        } else {
+            let (journal, journaled) = Journal::open(&config.storage_path, content.as_deref())?;
+            triples.extend(journaled);
+            Some(journal)
+        };
+        let namespaces = NamespaceMap::open_for(&config.storage_path)?.into_namespaces();
+
         info!("Knowledge graph initialized with {} triples from: {}", triples.len(), config.storage_path);
 
         Ok(Self {
+            labels: LabelIndex::from_triples(&triples),
+            index: TripleIndex::from_triples(&triples),
+            persisted: triples.len(),
             triples,
             config,
             schema,
+            namespaces,
+            pending_writes: 0,
+            last_flush: Instant::now(),
+            journal,
+            base_triples,
+            rewrite_pending: false,
+            _lock: lock,
         })
     }
 
     pub fn in_memory(schema: RdfSchema) -> Result<Self> {
         let config = KnowledgeGraphConfig {
             storage_path: ":memory:".to_string(),
-            namespaces: HashMap::new(),
             default_graph: None,
+            inference: false,
+            query_timeout_ms: default_query_timeout_ms(),
+            max_query_results: default_max_query_results(),
+            write_batch_size: None,
+            flush_interval_ms: None,
+            read_only: false,
+            lock_timeout_ms: None,
+            storage_format: StorageFormat::Json,
+            literal_normalization: LiteralNormalization::default(),
         };
 
         Ok(Self {
             triples: Vec::new(),
             config,
             schema,
+            namespaces: BTreeMap::new(),
+            labels: LabelIndex::default(),
+            index: TripleIndex::default(),
+            pending_writes: 0,
+            last_flush: Instant::now(),
+            journal: None,
+            base_triples: 0,
+            persisted: 0,
+            rewrite_pending: false,
+            _lock: None,
         })
     }
 
-    fn save_to_disk(&self) -> Result<()> {
-        if self.config.storage_path != ":memory:" {
-            let json = serde_json::to_string_pretty(&self.triples)?;
-            fs::write(&self.config.storage_path, json)
-                .with_context(|| format!("Failed to save knowledge graph to: {}", self.config.storage_path))?;
+    /// In-memory copy of the graph; changes to the copy are never saved
+    pub fn in_memory_copy(&self) -> Self {
+        Self {
+            triples: self.triples.clone(),
+            config: KnowledgeGraphConfig { storage_path: ":memory:".to_string(), read_only: false, ..self.config.clone() },
+            schema: self.schema.clone(),
+            namespaces: self.namespaces.clone(),
+            labels: self.labels.clone(),
+            index: self.index.clone(),
+            pending_writes: 0,
+            last_flush: Instant::now(),
+            journal: None,
+            base_triples: 0,
+            persisted: 0,
+            rewrite_pending: false,
+            _lock: None,
+        }
+    }
+
+    /// Record the current triples as an immutable version named `tag`,
+    /// which `restore` can roll the store back to
+    pub fn snapshot(&self, tag: &str) -> Result<SnapshotInfo> {
+        let info = self.snapshot_store()?.create(tag, &self.triples, &self.namespaces)?;
+        info!("Recorded snapshot '{}' of {} triples", tag, info.triples);
+        Ok(info)
+    }
+
+    /// Snapshots of this store, oldest first
+    pub fn snapshots(&self) -> Result<Vec<SnapshotInfo>> {
+        self.snapshot_store()?.list()
+    }
+
+    /// Replace every triple with those recorded in snapshot `tag`, returning
+    /// how many the store now holds
+    pub fn restore(&mut self, tag: &str) -> Result<usize> {
+        self.ensure_writable()?;
+        let store = self.snapshot_store()?;
+        let triples = store.load(tag)?;
+        // Snapshots of earlier releases have no namespaces; the current ones are kept
+        if let Some(namespaces) = store.load_namespaces(tag)? {
+            self.namespaces = namespaces;
+            self.save_namespaces()?;
+        }
+        self.labels = LabelIndex::from_triples(&triples);
+        self.index = TripleIndex::from_triples(&triples);
+        self.triples = triples;
+        self.rewrite_pending = true;
+        self.write()?;
+        info!("Restored {} triples from snapshot '{}'", self.triples.len(), tag);
+        Ok(self.triples.len())
+    }
+
+    fn snapshot_store(&self) -> Result<SnapshotStore> {
+        if self.config.storage_path == ":memory:" {
+            return Err(Error::storage("In-memory knowledge graphs have no snapshots"));
+        }
+        Ok(SnapshotStore::open_for(&self.config.storage_path))
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.config.read_only
+    }
+
+    fn ensure_writable(&self) -> Result<()> {
+        if self.config.read_only {
+            return Err(Error::storage(format!("Knowledge graph is open read-only: {}", self.config.storage_path)));
+        }
+        Ok(())
+    }
+
+    /// Write pending changes to disk
+    pub fn flush(&mut self) -> Result<()> {
+        if self.pending_writes > 0 {
+            self.write()?;
         }
         Ok(())
     }
 
+    /// Triples changed since the last write to disk
+    pub fn pending_writes(&self) -> usize {
+        self.pending_writes
+    }
+
+    /// Record `count` changed triples and write them out once the
+    /// configured batch size or flush interval is reached
+    fn changed(&mut self, count: usize) -> Result<()> {
+        self.pending_writes += count;
+        let due = match (self.config.write_batch_size, self.config.flush_interval_ms) {
+            (None, None) => true,
+            (batch_size, interval) => {
+                batch_size.is_some_and(|size| self.pending_writes >= size)
+                    || interval.is_some_and(|ms| self.pending_writes > 0 && self.last_flush.elapsed() >= Duration::from_millis(ms))
+            }
+        };
+        if due {
+            self.write()?;
+        }
+        Ok(())
+    }
+
+    /// Append the triples added since the last write to the journal, or
+    /// rewrite the store file when other changes were made or the journal
+    /// is due for compaction
+    fn write(&mut self) -> Result<()> {
+        if let Some(journal) = &mut self.journal {
+            if self.rewrite_pending || journal.should_compact(self.base_triples) {
+                self.save_to_disk()?;
+            } else {
+                journal.append(&self.triples[self.persisted..])?;
+            }
+        }
+        self.persisted = self.triples.len();
+        self.pending_writes = 0;
+        self.last_flush = Instant::now();
+        Ok(())
+    }
+
+    /// Rewrite the store file with every triple, folding in the journal,
+    /// and return how many journal entries were folded in
+    pub fn compact(&mut self) -> Result<usize> {
+        self.ensure_writable()?;
+        let folded = self.journal.as_ref().map_or(0, Journal::len);
+        self.rewrite_pending = true;
+        self.write()?;
+        Ok(folded)
+    }
+
+    /// Triples in the journal, not yet folded into the store file
+    pub fn journaled_triples(&self) -> usize {
+        self.journal.as_ref().map_or(0, Journal::len)
+    }
+
+    /// Encoding the store is written in
+    pub fn storage_format(&self) -> StorageFormat {
+        self.config.storage_format
+    }
+
+    /// Rewrite the store in `format`, keeping every triple as it is
+    pub fn set_storage_format(&mut self, format: StorageFormat) -> Result<()> {
+        self.ensure_writable()?;
+        self.config.storage_format = format;
+        self.rewrite_pending = true;
+        self.write()
+    }
+
+    /// Atomically replace the store file with every triple and start an
+    /// empty journal for it
+    fn save_to_disk(&mut self) -> Result<()> {
+        let Some(journal) = &mut self.journal else {
+            return Ok(());
+        };
+        let content = self.config.storage_format.encode(&self.triples)?;
+        journal::write_atomic(Path::new(&self.config.storage_path), &content)
+            .storage_context(|| format!("Failed to save knowledge graph to: {}", self.config.storage_path))?;
+        journal.reset(&content)?;
+        self.base_triples = self.triples.len();
+        self.rewrite_pending = false;
+        Ok(())
+    }
+
+    /// Add triples, skipping exact duplicates. Triples without a graph go to
+    /// the configured `default_graph`, if any.
     pub fn add_triples(&mut self, triples: &[RdfTriple]) -> Result<usize> {
+        self.ensure_writable()?;
         let mut added_count = 0;
+        let now = Utc::now().to_rfc3339();
 
-        for triple in triples {
-            // Simple deduplication check
-            let exists = self.triples.iter().any(|existing| {
-                existing.subject == triple.subject
-                    && existing.predicate == triple.predicate
-                    && existing.object == triple.object
-            });
-
-            if !exists {
-                self.triples.push(triple.clone());
-                added_count += 1;
+        for mut triple in self.prepare(triples) {
+            if !self.index.contains(&triple) {
+                triple.metadata.insert(ADDED_AT.to_string(), now.clone());
                 debug!("Added triple: {}", triple.to_ntriple());
+                self.push_triple(triple);
+                added_count += 1;
             }
         }
 
-        // Save to disk
-        self.save_to_disk()?;
+        self.changed(added_count)?;
 
         info!("Added {} triples to knowledge graph", added_count);
         Ok(added_count)
     }
 
+    /// Triples as `add_triples` stores them: in the default graph when they
+    /// name none, with literals normalized
+    fn prepare(&self, triples: &[RdfTriple]) -> Vec<RdfTriple> {
+        let normalizer = Normalizer::new(&self.config.literal_normalization, &self.schema);
+        triples.iter()
+            .map(|triple| {
+                let mut triple = triple.clone();
+                if triple.graph.is_none() {
+                    triple.graph = self.config.default_graph.clone();
+                }
+                normalizer.apply(&mut triple);
+                triple
+            })
+            .collect()
+    }
+
+    /// What `add_triples` would change, without changing the graph. Adding
+    /// keeps every object, so `conflicts` only lists subject/predicate pairs
+    /// that would end up with objects from both sides.
+    pub fn preview_add(&self, triples: &[RdfTriple]) -> MergeReport {
+        self.plan_merge(&self.prepare(triples), MergeStrategy::KeepBoth)
+    }
+
+    pub fn triples(&self) -> &[RdfTriple] {
+        &self.triples
+    }
+
+    /// Up to `limit` entities with a label (`rdfs:label`, `skos:prefLabel`,
+    /// `skos:altLabel` or a `name` predicate) containing a word that starts
+    /// with `prefix`, case-insensitively
+    pub fn autocomplete(&self, prefix: &str, limit: usize) -> Vec<LabelMatch> {
+        self.labels.autocomplete(prefix, limit)
+    }
+
+    /// Triples matching `pattern`, in store order
+    pub fn find(&self, pattern: &TriplePattern) -> Vec<&RdfTriple> {
+        match self.index.matching(pattern.subject.as_deref(), pattern.predicate.as_deref(), pattern.object.as_deref()) {
+            Some(positions) => positions.into_iter()
+                .map(|i| &self.triples[i])
+                .filter(|t| pattern.matches(t))
+                .collect(),
+            None => self.triples.iter().filter(|t| pattern.matches(t)).collect(),
+        }
+    }
+
+    /// Triples with `uri` as subject or object, in store order
+    pub fn triples_about(&self, uri: &str) -> Vec<&RdfTriple> {
+        let mut positions = self.index.matching(Some(uri), None, None).unwrap_or_default();
+        positions.extend(self.index.matching(None, None, Some(uri)).unwrap_or_default());
+        positions.sort_unstable();
+        positions.dedup();
+        positions.into_iter().map(|i| &self.triples[i]).collect()
+    }
+
+    /// Triples at the positions the index holds for the given terms
+    fn lookup(&self, subject: Option<&str>, predicate: Option<&str>, object: Option<&str>) -> impl Iterator<Item = &RdfTriple> {
+        self.index.matching(subject, predicate, object)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|i| &self.triples[i])
+    }
+
+    fn push_triple(&mut self, triple: RdfTriple) {
+        self.labels.insert(&triple);
+        self.index.insert(&triple, self.triples.len());
+        self.triples.push(triple);
+    }
+
+    /// Keep only the triples `keep` accepts, dropping the others from the
+    /// label index and reindexing the remaining positions
+    fn retain_triples(&mut self, mut keep: impl FnMut(&RdfTriple) -> bool) {
+        let before = self.triples.len();
+        let labels = &mut self.labels;
+        self.triples.retain(|t| {
+            let kept = keep(t);
+            if !kept {
+                labels.remove(t);
+            }
+            kept
+        });
+        if self.triples.len() != before {
+            self.index = TripleIndex::from_triples(&self.triples);
+            self.rewrite_pending = true;
+        }
+    }
+
+    /// Triples added after `since`; triples stored without an `added_at`
+    /// timestamp predate delta tracking and are never included
+    pub fn triples_since(&self, since: DateTime<Utc>) -> Vec<RdfTriple> {
+        self.triples.iter()
+            .filter(|triple| {
+                triple.metadata.get(ADDED_AT)
+                    .and_then(|added| DateTime::parse_from_rfc3339(added).ok())
+                    .is_some_and(|added| added > since)
+            })
+            .cloned()
+            .collect()
+    }
+
+    pub fn schema(&self) -> &RdfSchema {
+        &self.schema
+    }
+
+    pub fn literal_normalization(&self) -> &LiteralNormalization {
+        &self.config.literal_normalization
+    }
+
+    /// Prefixes of the graph's namespace map, without the schema's
+    pub fn namespaces(&self) -> &BTreeMap<String, String> {
+        &self.namespaces
+    }
+
+    /// Every prefix queries can use: the namespace map's and the schema's,
+    /// which wins over a namespace-map prefix of the same name
+    pub fn prefixes(&self) -> HashMap<String, String> {
+        let mut prefixes: HashMap<String, String> = self.namespaces.clone().into_iter().collect();
+        prefixes.insert(self.schema.prefix.clone(), self.schema.namespace.clone());
+        prefixes
+    }
+
+    /// Bind `prefix` to `uri` in the namespace map and persist it; returns
+    /// the namespace the prefix was bound to before
+    pub fn add_namespace(&mut self, prefix: &str, uri: &str) -> Result<Option<String>> {
+        self.ensure_writable()?;
+        namespaces::validate(prefix, uri)?;
+        let previous = self.namespaces.insert(prefix.to_string(), uri.to_string());
+        self.save_namespaces()?;
+        Ok(previous)
+    }
+
+    /// Remove `prefix` from the namespace map; returns its namespace
+    pub fn remove_namespace(&mut self, prefix: &str) -> Result<Option<String>> {
+        self.ensure_writable()?;
+        let removed = self.namespaces.remove(prefix);
+        if removed.is_some() {
+            self.save_namespaces()?;
+        }
+        Ok(removed)
+    }
+
+    /// Persist the namespace map; the store lock makes this graph's copy
+    /// the current one
+    fn save_namespaces(&self) -> Result<()> {
+        if self.config.storage_path == ":memory:" {
+            return Ok(());
+        }
+        NamespaceMap::new(NamespaceMap::path_for(&self.config.storage_path), self.namespaces.clone()).save()
+    }
+
+    /// Serializer declaring and abbreviating with the graph's namespace map
+    pub fn serializer(&self) -> RdfSerializer {
+        RdfSerializer::new().with_namespaces(self.namespaces.clone())
+    }
+
+    /// File the graph is persisted to, or `:memory:`
+    pub fn storage_path(&self) -> &str {
+        &self.config.storage_path
+    }
+
+    /// Graph that triples added without one are stored in
+    pub fn default_graph(&self) -> Option<&str> {
+        self.config.default_graph.as_deref()
+    }
+
+    /// Triples in `graph`; `None` is the unnamed default graph
+    pub fn graph_triples(&self, graph: Option<&str>) -> Vec<RdfTriple> {
+        self.triples.iter().filter(|t| t.graph.as_deref() == graph).cloned().collect()
+    }
+
+    /// Remove every triple in `graph`, returning how many were removed
+    pub fn remove_graph(&mut self, graph: Option<&str>) -> Result<usize> {
+        self.ensure_writable()?;
+        let before = self.triples.len();
+        self.retain_triples(|t| t.graph.as_deref() != graph);
+        let removed = before - self.triples.len();
+        if removed > 0 {
+            self.changed(removed)?;
+            info!("Removed {} triples from graph {}", removed, graph.unwrap_or("(default)"));
+        }
+        Ok(removed)
+    }
+
+    /// Remove every triple matching `pattern`, returning how many were removed
+    pub fn remove_triples(&mut self, pattern: &TriplePattern) -> Result<usize> {
+        self.ensure_writable()?;
+        let before = self.triples.len();
+        self.retain_triples(|t| !pattern.matches(t));
+        let removed = before - self.triples.len();
+        if removed > 0 {
+            self.changed(removed)?;
+            info!("Removed {} triples matching {}", removed, pattern);
+        }
+        Ok(removed)
+    }
+
+    /// Replace the contents of `graph` with `triples`, whose own graph is ignored
+    pub fn replace_graph(&mut self, graph: Option<&str>, triples: &[RdfTriple]) -> Result<usize> {
+        self.ensure_writable()?;
+        self.retain_triples(|t| t.graph.as_deref() != graph);
+        self.add_triples_to_graph(graph, triples)
+    }
+
+    /// Add triples to `graph`, whose own graph is ignored
+    pub fn add_triples_to_graph(&mut self, graph: Option<&str>, triples: &[RdfTriple]) -> Result<usize> {
+        self.ensure_writable()?;
+        let mut added_count = 0;
+        let now = Utc::now().to_rfc3339();
+        let normalizer = Normalizer::new(&self.config.literal_normalization, &self.schema);
+        let triples: Vec<RdfTriple> = triples.iter()
+            .map(|triple| {
+                let mut triple = triple.clone();
+                triple.graph = graph.map(str::to_string);
+                normalizer.apply(&mut triple);
+                triple
+            })
+            .collect();
+
+        for mut triple in triples {
+            if !self.index.contains(&triple) {
+                triple.metadata.insert(ADDED_AT.to_string(), now.clone());
+                self.push_triple(triple);
+                added_count += 1;
+            }
+        }
+
+        self.changed(added_count)?;
+        info!("Added {} triples to graph {}", added_count, graph.unwrap_or("(default)"));
+        Ok(added_count)
+    }
+
+    /// Triples entailed by the schema's axioms and any RDFS/OWL axioms stored
+    /// in the graph, excluding those already stored
+    pub fn inferred_triples(&self) -> Vec<RdfTriple> {
+        let mut reasoner = Reasoner::from_schema(&self.schema);
+        reasoner.add_axioms_from(&self.triples);
+        reasoner.infer(&self.triples)
+    }
+
+    /// Store the inferred triples, returning how many were added
+    pub fn materialize_inferences(&mut self) -> Result<usize> {
+        self.ensure_writable()?;
+        let inferred = self.inferred_triples();
+        self.add_triples(&inferred)
+    }
+
+    /// Names of the graphs holding triples, in order of first appearance
+    pub fn named_graphs(&self) -> Vec<&str> {
+        let mut graphs: Vec<&str> = Vec::new();
+        for graph in self.triples.iter().filter_map(|t| t.graph.as_deref()) {
+            if !graphs.contains(&graph) {
+                graphs.push(graph);
+            }
+        }
+        graphs
+    }
+
+    /// Compare with another graph: `added` are triples only in `other`,
+    /// `removed` are triples only in `self`
+    pub fn diff(&self, other: &KnowledgeGraph) -> GraphDiff {
+        let own: HashSet<_> = self.triples.iter().map(triple_key).collect();
+        let theirs: HashSet<_> = other.triples.iter().map(triple_key).collect();
+
+        GraphDiff {
+            added: other.triples.iter()
+                .filter(|t| !own.contains(&triple_key(t)))
+                .cloned()
+                .collect(),
+            removed: self.triples.iter()
+                .filter(|t| !theirs.contains(&triple_key(t)))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Merge another graph into this one, deduplicating identical triples
+    /// and resolving subject/predicate conflicts according to `strategy`
+    pub fn merge(&mut self, other: &KnowledgeGraph, strategy: MergeStrategy) -> Result<MergeReport> {
+        self.ensure_writable()?;
+        let report = self.plan_merge(&other.triples, strategy);
+        let conflicting: HashSet<(String, String)> = report.conflicts.iter()
+            .map(|conflict| (conflict.subject.clone(), conflict.predicate.clone()))
+            .collect();
+
+        if strategy == MergeStrategy::PreferSource {
+            self.retain_triples(|t| !conflicting.contains(&(t.subject.clone(), t.predicate.clone())));
+        }
+
+        let now = Utc::now().to_rfc3339();
+        for triple in &other.triples {
+            let key = (triple.subject.clone(), triple.predicate.clone());
+            if strategy == MergeStrategy::PreferTarget && conflicting.contains(&key) {
+                continue;
+            }
+
+            if let Some(position) = self.index.position(triple) {
+                // Keep the higher confidence of the two
+                let existing_triple = &mut self.triples[position];
+                if triple.confidence > existing_triple.confidence {
+                    existing_triple.confidence = triple.confidence;
+                    self.rewrite_pending |= position < self.persisted;
+                }
+            } else {
+                let mut triple = triple.clone();
+                triple.metadata.insert(ADDED_AT.to_string(), now.clone());
+                self.push_triple(triple);
+            }
+        }
+
+        self.changed(report.added + report.duplicates + report.replaced)?;
+
+        info!(
+            "Merged knowledge graph: {} added, {} duplicates, {} conflicts",
+            report.added,
+            report.duplicates,
+            report.conflicts.len()
+        );
+        Ok(report)
+    }
+
+    /// What `merge` would change, without changing the graph
+    pub fn preview_merge(&self, other: &KnowledgeGraph, strategy: MergeStrategy) -> MergeReport {
+        self.plan_merge(&other.triples, strategy)
+    }
+
+    fn plan_merge(&self, incoming_triples: &[RdfTriple], strategy: MergeStrategy) -> MergeReport {
+        let mut report = MergeReport::default();
+
+        // Objects per (subject, predicate) on both sides
+        let mut existing: HashMap<(String, String), Vec<String>> = HashMap::new();
+        for triple in &self.triples {
+            existing.entry((triple.subject.clone(), triple.predicate.clone()))
+                .or_default()
+                .push(triple.object.clone());
+        }
+        let mut incoming: HashMap<(String, String), Vec<String>> = HashMap::new();
+        for triple in incoming_triples {
+            let objects = incoming.entry((triple.subject.clone(), triple.predicate.clone())).or_default();
+            if !objects.contains(&triple.object) {
+                objects.push(triple.object.clone());
+            }
+        }
+
+        let mut conflicting = HashSet::new();
+        for (key, incoming_objects) in &incoming {
+            if let Some(existing_objects) = existing.get(key) {
+                let differs = incoming_objects.iter().any(|o| !existing_objects.contains(o))
+                    || existing_objects.iter().any(|o| !incoming_objects.contains(o));
+                if differs {
+                    conflicting.insert(key.clone());
+                    report.conflicts.push(MergeConflict {
+                        subject: key.0.clone(),
+                        predicate: key.1.clone(),
+                        existing: existing_objects.clone(),
+                        incoming: incoming_objects.clone(),
+                    });
+                }
+            }
+        }
+        report.conflicts.sort_by(|a, b| (&a.subject, &a.predicate).cmp(&(&b.subject, &b.predicate)));
+
+        if strategy == MergeStrategy::PreferSource {
+            report.replaced = self.triples.iter()
+                .filter(|t| conflicting.contains(&(t.subject.clone(), t.predicate.clone())))
+                .count();
+        }
+
+        let known_predicates: HashSet<&str> = self.triples.iter().map(|t| t.predicate.as_str()).collect();
+        let mut new_predicates = BTreeSet::new();
+        let mut seen = HashSet::new();
+        for triple in incoming_triples {
+            let key = (triple.subject.clone(), triple.predicate.clone());
+            if strategy == MergeStrategy::PreferTarget && conflicting.contains(&key) {
+                report.skipped += 1;
+                continue;
+            }
+
+            // Replaced objects are removed first, so incoming copies of them count as added
+            let replaced = strategy == MergeStrategy::PreferSource && conflicting.contains(&key);
+            if !seen.insert(triple_key(triple)) || (!replaced && self.index.contains(triple)) {
+                report.duplicates += 1;
+            } else {
+                report.added += 1;
+                if !known_predicates.contains(triple.predicate.as_str()) {
+                    new_predicates.insert(triple.predicate.clone());
+                }
+            }
+        }
+        report.new_predicates = new_predicates.into_iter().collect();
+        report
+    }
+
     pub fn execute_sparql(&self, query: &str) -> Result<SimpleSparqlResults> {
         debug!("Executing simplified SPARQL query: {}", query);
 
         // Simple SPARQL implementation for basic SELECT queries
-        if query.trim().to_lowercase().starts_with("select") {
+        if query_form(query).eq_ignore_ascii_case("select") {
             self.execute_select_query(query)
         } else {
-            anyhow::bail!("Only SELECT queries are supported in this simplified implementation");
+            Err(Error::query("Only SELECT queries are supported in this simplified implementation"))
         }
     }
 
     fn execute_select_query(&self, query: &str) -> Result<SimpleSparqlResults> {
-        // Very basic SPARQL SELECT implementation
-        // This is a simplified version that handles basic patterns
-
-        let mut results = Vec::new();
-
-        // Parse basic SELECT queries like "SELECT ?var1 ?var2 WHERE { ?var1 predicate ?var2 }"
-        if query.contains("?name") && query.contains("hasName") {
-            // Handle name queries
-            for triple in &self.triples {
-                if triple.predicate.contains("hasName") {
-                    let mut row = HashMap::new();
-                    row.insert("name".to_string(), triple.object.clone());
-                    row.insert("entity".to_string(), triple.subject.clone());
-                    results.push(row);
-                }
-            }
-        } else if query.contains("?role") && query.contains("hasRole") {
-            // Handle role queries
-            for triple in &self.triples {
-                if triple.predicate.contains("hasRole") {
-                    let mut row = HashMap::new();
-                    row.insert("role".to_string(), triple.object.clone());
-                    row.insert("person".to_string(), triple.subject.clone());
-                    results.push(row);
-                }
-            }
+        let with_inferred;
+        let triples: &[RdfTriple] = if self.config.inference {
+            with_inferred = [self.triples.as_slice(), &self.inferred_triples()].concat();
+            &with_inferred
         } else {
-            // Generic query - return all triples as subject/predicate/object
-            for triple in &self.triples {
-                let mut row = HashMap::new();
-                row.insert("subject".to_string(), triple.subject.clone());
-                row.insert("predicate".to_string(), triple.predicate.clone());
-                row.insert("object".to_string(), triple.object.clone());
-                results.push(row);
-            }
-        }
+            &self.triples
+        };
 
-        Ok(SimpleSparqlResults::Solutions(results))
+        // Queries the engine cannot parse are reported, not answered with unrelated rows
+        let select = SelectQuery::parse_with_namespaces(query, &self.prefixes())?;
+        let rows = if self.config.inference {
+            select.execute_with_limits(triples, self.config.query_limits())?
+        } else {
+            select.execute_indexed(triples, &self.index, self.config.query_limits())?
+        };
+        Ok(SimpleSparqlResults::Solutions {
+            variables: select.variables(),
+            rows: rows.into_iter().map(to_bindings).collect(),
+        })
     }
 
     pub fn get_entities_by_type(&self, entity_type: &str) -> Result<Vec<String>> {
@@ -172,14 +1004,11 @@ impl KnowledgeGraph {
             format!("{}{}", self.schema.namespace, entity_type)
         };
 
-        let mut entities = Vec::new();
-
         // Look for triples with rdf:type predicate
-        for triple in &self.triples {
-            if triple.predicate.contains("type") && triple.object == type_uri {
-                entities.push(triple.subject.clone());
-            }
-        }
+        let entities = self.lookup(None, None, Some(&type_uri))
+            .filter(|triple| triple.predicate.contains("type"))
+            .map(|triple| triple.subject.clone())
+            .collect();
 
         Ok(entities)
     }
@@ -187,12 +1016,10 @@ impl KnowledgeGraph {
     pub fn get_entity_properties(&self, entity_uri: &str) -> Result<HashMap<String, Vec<String>>> {
         let mut properties = HashMap::new();
 
-        for triple in &self.triples {
-            if triple.subject == entity_uri {
-                properties.entry(triple.predicate.clone())
-                    .or_insert_with(Vec::new)
-                    .push(triple.object.clone());
-            }
+        for triple in self.lookup(Some(entity_uri), None, None) {
+            properties.entry(triple.predicate.clone())
+                .or_insert_with(Vec::new)
+                .push(triple.object.clone());
         }
 
         Ok(properties)
@@ -211,7 +1038,7 @@ impl KnowledgeGraph {
             visited.insert(current_uri.clone());
 
             // Find related entities in both directions
-            for triple in &self.triples {
+            for triple in self.triples_about(&current_uri) {
                 if triple.subject == current_uri {
                     // Object might be a related entity
                     if triple.object.starts_with("http") && !visited.contains(&triple.object) {
@@ -237,11 +1064,28 @@ impl KnowledgeGraph {
         let mut unique_subjects = std::collections::HashSet::new();
         let mut unique_predicates = std::collections::HashSet::new();
         let mut unique_objects = std::collections::HashSet::new();
+        let mut predicates = BTreeMap::new();
+        let mut typed_entities = HashSet::new();
+        let mut sources = BTreeMap::new();
+        let mut confidence = ConfidenceDistribution::default();
 
         for triple in &self.triples {
             unique_subjects.insert(&triple.subject);
             unique_predicates.insert(&triple.predicate);
             unique_objects.insert(&triple.object);
+
+            *predicates.entry(triple.predicate.clone()).or_insert(0) += 1;
+            if triple.predicate == inference::RDF_TYPE {
+                typed_entities.insert((&triple.object, &triple.subject));
+            }
+            let source = triple.source.clone().unwrap_or_else(|| UNKNOWN_SOURCE.to_string());
+            *sources.entry(source).or_insert(0) += 1;
+            confidence.add(triple.confidence);
+        }
+
+        let mut classes = BTreeMap::new();
+        for (class, _) in typed_entities {
+            *classes.entry(class.clone()).or_insert(0) += 1;
         }
 
         Ok(KnowledgeGraphStats {
@@ -249,6 +1093,10 @@ impl KnowledgeGraph {
             unique_subjects: unique_subjects.len(),
             unique_predicates: unique_predicates.len(),
             unique_objects: unique_objects.len(),
+            predicates,
+            classes,
+            sources,
+            confidence,
         })
     }
 
@@ -271,56 +1119,71 @@ impl KnowledgeGraph {
     }
 
     pub fn export_to_file(&self, file_path: &str, format: &str) -> Result<()> {
-        use std::fs::File;
-        use std::io::Write;
+        self.export_triples_to_file(&self.triples, file_path, format)
+    }
 
-        let mut file = File::create(file_path)
-            .with_context(|| format!("Failed to create export file: {}", file_path))?;
+    /// Export a subset of the graph's triples, e.g. from [`Self::triples_since`];
+    /// the file is written as triples are serialized
+    pub fn export_triples_to_file(&self, triples: &[RdfTriple], file_path: &str, format: &str) -> Result<()> {
+        let output_format = match format.to_lowercase().as_str() {
+            "turtle" | "ttl" | "ntriples" | "nt" | "json" => None,
+            "nquads" | "nq" => Some(OutputFormat::NQuads),
+            "trig" => Some(OutputFormat::TriG),
+            "jsonld" => Some(OutputFormat::JsonLd),
+            "rdfxml" => Some(OutputFormat::RdfXml),
+            "cypher" => Some(OutputFormat::Cypher),
+            "graphml" => Some(OutputFormat::GraphMl),
+            _ => {
+                return Err(Error::storage(format!("Unsupported export format: {}. Supported: turtle, ntriples, nquads, trig, jsonld, rdfxml, json, cypher, graphml", format)));
+            }
+        };
+
+        let file = fs::File::create(file_path)
+            .storage_context(|| format!("Failed to write export file: {}", file_path))?;
+        let mut writer = BufWriter::new(file);
+
+        if let Some(output_format) = output_format {
+            self.serializer().serialize_to_writer(triples, &output_format, &self.schema.namespace, &self.schema.prefix, &mut writer)?;
+        } else {
+            self.write_export(triples, format, &mut writer)
+                .and_then(|()| writer.flush())
+                .storage_context(|| format!("Failed to write export file: {}", file_path))?;
+        }
 
+        info!("Knowledge graph exported to: {} (format: {})", file_path, format);
+        Ok(())
+    }
+
+    /// Turtle, N-Triples and JSON exports, one triple at a time
+    fn write_export(&self, triples: &[RdfTriple], format: &str, out: &mut impl Write) -> std::io::Result<()> {
         match format.to_lowercase().as_str() {
             "turtle" | "ttl" => {
                 // Write turtle format with prefixes
-                file.write_all(b"@prefix rdf: <http://www.w3.org/1999/02/22-rdf-syntax-ns#> .\n")?;
-                file.write_all(b"@prefix rdfs: <http://www.w3.org/2000/01/rdf-schema#> .\n")?;
-                file.write_all(format!("@prefix {}: <{}> .\n\n", self.schema.prefix, self.schema.namespace).as_bytes())?;
-
-                for triple in &self.triples {
-                    let turtle_line = format!("{} {} {} .\n",
-                        self.format_uri_or_literal(&triple.subject, true),
-                        self.format_uri_or_literal(&triple.predicate, true),
-                        self.format_uri_or_literal(&triple.object, false)
-                    );
-                    file.write_all(turtle_line.as_bytes())?;
+                let prefixes = self.serializer().prefixes(&self.schema.namespace, &self.schema.prefix);
+                out.write_all(turtle_prefixes(&prefixes).as_bytes())?;
+
+                for triple in triples {
+                    writeln!(out, "{} {} {} .",
+                        self.format_uri_or_literal(&triple.subject, true, &prefixes),
+                        self.format_uri_or_literal(&triple.predicate, true, &prefixes),
+                        self.format_uri_or_literal(&triple.object, false, &prefixes)
+                    )?;
                 }
             }
             "ntriples" | "nt" => {
-                for triple in &self.triples {
-                    let ntriple = format!("{}\n", self.format_triple_as_ntriple(triple));
-                    file.write_all(ntriple.as_bytes())?;
+                for triple in triples {
+                    writeln!(out, "{}", self.format_triple_as_ntriple(triple))?;
                 }
             }
-            "json" => {
-                let json = serde_json::to_string_pretty(&self.triples)?;
-                file.write_all(json.as_bytes())?;
-            }
-            _ => {
-                anyhow::bail!("Unsupported export format: {}. Supported: turtle, ntriples, json", format);
-            }
+            _ => serde_json::to_writer_pretty(out, triples)?,
         }
-
-        info!("Knowledge graph exported to: {} (format: {})", file_path, format);
         Ok(())
     }
 
-    fn format_uri_or_literal(&self, value: &str, is_uri_context: bool) -> String {
+    fn format_uri_or_literal(&self, value: &str, is_uri_context: bool, prefixes: &[(String, String)]) -> String {
         if value.starts_with("http") {
             // Try to use prefix if available
-            if value.starts_with(&self.schema.namespace) {
-                let local_name = &value[self.schema.namespace.len()..];
-                format!("{}:{}", self.schema.prefix, local_name)
-            } else {
-                format!("<{}>", value)
-            }
+            compact_iri(value, prefixes).unwrap_or_else(|| format!("<{}>", value))
         } else if is_uri_context {
             // For subjects/predicates that should be URIs but aren't, wrap in quotes
             format!("\"{}\"", value)
@@ -331,12 +1194,178 @@ impl KnowledgeGraph {
     }
 }
 
+impl Drop for KnowledgeGraph {
+    fn drop(&mut self) {
+        if self.pending_writes > 0 {
+            if let Err(e) = self.write() {
+                warn!("Failed to write {} pending triples: {}", self.pending_writes, e);
+            }
+        }
+    }
+}
+
+fn to_bindings(row: HashMap<String, String>) -> HashMap<String, Binding> {
+    row.into_iter().map(|(var, value)| (var, Binding::from_term(&value))).collect()
+}
+
+/// First keyword after the PREFIX and BASE declarations, e.g. `SELECT`
+fn query_form(query: &str) -> &str {
+    let mut rest = query.trim_start();
+    loop {
+        let keyword = rest.split_whitespace().next().unwrap_or("");
+        if !(keyword.eq_ignore_ascii_case("PREFIX") || keyword.eq_ignore_ascii_case("BASE")) {
+            return rest.split(|c: char| !c.is_ascii_alphabetic()).next().unwrap_or("");
+        }
+        match rest.split_once('>') {
+            Some((_, after)) => rest = after.trim_start(),
+            None => return "",
+        }
+    }
+}
+
+fn triple_key(triple: &RdfTriple) -> (&str, &str, &str, Option<&str>) {
+    (&triple.subject, &triple.predicate, &triple.object, triple.graph.as_deref())
+}
+
+/// Graph name for triples extracted from `source`: URLs are used as-is,
+/// file paths become absolute `file://` IRIs
+pub fn source_graph_iri(source: &str) -> String {
+    if crate::utils::paths::is_remote(source) {
+        return source.to_string();
+    }
+    crate::utils::paths::file_uri(source)
+}
+
+/// Triples selected by subject, predicate, object and graph; unset parts
+/// match anything
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TriplePattern {
+    pub subject: Option<String>,
+    pub predicate: Option<String>,
+    pub object: Option<String>,
+    /// `Some(None)` matches only the unnamed default graph
+    pub graph: Option<Option<String>>,
+}
+
+impl TriplePattern {
+    pub fn matches(&self, triple: &RdfTriple) -> bool {
+        self.subject.as_ref().is_none_or(|s| *s == triple.subject)
+            && self.predicate.as_ref().is_none_or(|p| *p == triple.predicate)
+            && self.object.as_ref().is_none_or(|o| *o == triple.object)
+            && self.graph.as_ref().is_none_or(|g| *g == triple.graph)
+    }
+}
+
+impl std::fmt::Display for TriplePattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let term = |value: &Option<String>, variable| value.as_ref().map(|v| format!("<{}>", v)).unwrap_or_else(|| format!("?{}", variable));
+        write!(f, "{} {} {}", term(&self.subject, "s"), term(&self.predicate, "p"), term(&self.object, "o"))?;
+        match &self.graph {
+            Some(Some(graph)) => write!(f, " in <{}>", graph),
+            Some(None) => write!(f, " in the default graph"),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GraphDiff {
+    pub added: Vec<RdfTriple>,
+    pub removed: Vec<RdfTriple>,
+}
+
+/// How to resolve a subject/predicate pair that has different objects in each graph
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeStrategy {
+    /// Keep objects from both graphs
+    #[default]
+    KeepBoth,
+    /// Keep the target's objects and skip conflicting incoming triples
+    PreferTarget,
+    /// Replace the target's objects with the incoming ones
+    PreferSource,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MergeConflict {
+    pub subject: String,
+    pub predicate: String,
+    pub existing: Vec<String>,
+    pub incoming: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MergeReport {
+    pub added: usize,
+    pub duplicates: usize,
+    pub skipped: usize,
+    pub replaced: usize,
+    pub conflicts: Vec<MergeConflict>,
+    /// Predicates of added triples that the graph did not use before
+    #[serde(default)]
+    pub new_predicates: Vec<String>,
+}
+
+/// Key of triples without a source document in `KnowledgeGraphStats::sources`
+pub const UNKNOWN_SOURCE: &str = "(unknown)";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct KnowledgeGraphStats {
     pub total_triples: usize,
     pub unique_subjects: usize,
     pub unique_predicates: usize,
     pub unique_objects: usize,
+    /// Triples per predicate
+    #[serde(default)]
+    pub predicates: BTreeMap<String, usize>,
+    /// Distinct entities per `rdf:type` class
+    #[serde(default)]
+    pub classes: BTreeMap<String, usize>,
+    /// Triples per source document
+    #[serde(default)]
+    pub sources: BTreeMap<String, usize>,
+    #[serde(default)]
+    pub confidence: ConfidenceDistribution,
+}
+
+/// Triple confidences in ten bins of width 0.1, the last one including 1.0
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConfidenceDistribution {
+    pub min: Option<f32>,
+    pub max: Option<f32>,
+    pub mean: Option<f32>,
+    pub bins: [usize; 10],
+    #[serde(skip)]
+    sum: f64,
+    #[serde(skip)]
+    count: usize,
+}
+
+impl Default for ConfidenceDistribution {
+    fn default() -> Self {
+        Self { min: None, max: None, mean: None, bins: [0; 10], sum: 0.0, count: 0 }
+    }
+}
+
+impl ConfidenceDistribution {
+    fn add(&mut self, confidence: f32) {
+        let confidence = confidence.clamp(0.0, 1.0);
+        self.bins[((confidence * 10.0) as usize).min(9)] += 1;
+        self.min = Some(self.min.map_or(confidence, |min| min.min(confidence)));
+        self.max = Some(self.max.map_or(confidence, |max| max.max(confidence)));
+        self.sum += confidence as f64;
+        self.count += 1;
+        self.mean = Some((self.sum / self.count as f64) as f32);
+    }
+}
+
+/// The `limit` largest counts, ties in key order
+fn top_counts(counts: &BTreeMap<String, usize>, limit: usize) -> Vec<(&String, &usize)> {
+    let mut entries: Vec<_> = counts.iter().collect();
+    entries.sort_by(|a, b| b.1.cmp(a.1));
+    entries.truncate(limit);
+    entries
 }
 
 impl std::fmt::Display for KnowledgeGraphStats {
@@ -351,6 +1380,258 @@ impl std::fmt::Display for KnowledgeGraphStats {
             self.unique_subjects,
             self.unique_predicates,
             self.unique_objects
-        )
+        )?;
+
+        for (title, counts) in [("Predicates", &self.predicates), ("Classes (entities)", &self.classes), ("Sources", &self.sources)] {
+            if counts.is_empty() {
+                continue;
+            }
+            write!(f, "\n\n{}:", title)?;
+            for (key, count) in top_counts(counts, 10) {
+                write!(f, "\n  {:>8}  {}", count, key)?;
+            }
+            if counts.len() > 10 {
+                write!(f, "\n  ... and {} more", counts.len() - 10)?;
+            }
+        }
+
+        if let (Some(min), Some(max), Some(mean)) = (self.confidence.min, self.confidence.max, self.confidence.mean) {
+            write!(f, "\n\nConfidence: min {:.2}, mean {:.2}, max {:.2}", min, mean, max)?;
+            for (i, count) in self.confidence.bins.iter().enumerate().filter(|(_, count)| **count > 0) {
+                write!(f, "\n  {:.1}-{:.1}  {}", i as f32 / 10.0, (i + 1) as f32 / 10.0, count)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema() -> RdfSchema {
+        RdfSchema {
+            namespace: "http://ex.org/".to_string(),
+            prefix: "ex".to_string(),
+            base_uri: "http://ex.org/resource/".to_string(),
+            predicates: HashMap::new(),
+            classes: HashMap::new(),
+            custom_vocabularies: HashMap::new(),
+            subclass_of: HashMap::new(),
+            predicate_axioms: HashMap::new(),
+            ontology_file: None,
+        }
+    }
+
+    fn triple(s: &str, p: &str, o: &str) -> RdfTriple {
+        RdfTriple::new(s.to_string(), p.to_string(), o.to_string())
+    }
+
+    #[test]
+    fn test_snapshot_and_restore() {
+        let kg_path = std::env::temp_dir()
+            .join(format!("snapshot_test_{}.db", uuid::Uuid::new_v4()))
+            .to_string_lossy()
+            .to_string();
+        let config = KnowledgeGraphConfig { storage_path: kg_path.clone(), ..Default::default() };
+        let mut kg = KnowledgeGraph::new(config, schema()).unwrap();
+        kg.add_triples(&[triple("http://ex.org/acme", "http://ex.org/hasName", "Acme")]).unwrap();
+        kg.add_namespace("foaf", "http://xmlns.com/foaf/0.1/").unwrap();
+
+        assert_eq!(kg.snapshot("before-run").unwrap().triples, 1);
+        assert!(kg.snapshot("before-run").is_err());
+        assert!(kg.snapshot("../escape").is_err());
+
+        kg.add_triples(&[triple("http://ex.org/bad", "http://ex.org/hasName", "Bad")]).unwrap();
+        kg.remove_namespace("foaf").unwrap();
+        kg.add_namespace("bad", "http://ex.org/bad#").unwrap();
+        assert_eq!(kg.restore("before-run").unwrap(), 1);
+        assert!(kg.autocomplete("bad", 5).is_empty());
+        // Namespaces are restored with the triples
+        assert_eq!(kg.namespaces().keys().collect::<Vec<_>>(), ["foaf"]);
+        assert!(kg.restore("missing").is_err());
+
+        drop(kg);
+        let reopened = KnowledgeGraph::new(KnowledgeGraphConfig { storage_path: kg_path.clone(), ..Default::default() }, schema()).unwrap();
+        assert_eq!(reopened.triples().len(), 1);
+        assert_eq!(reopened.snapshots().unwrap()[0].tag, "before-run");
+        assert!(reopened.namespaces().contains_key("foaf"));
+        assert!(KnowledgeGraph::in_memory(schema()).unwrap().snapshot("x").is_err());
+
+        fs::remove_file(&kg_path).unwrap();
+        fs::remove_file(NamespaceMap::path_for(&kg_path)).unwrap();
+        fs::remove_file(Journal::path_for(&kg_path)).unwrap();
+        fs::remove_file(StoreLock::path_for(&kg_path)).unwrap();
+        fs::remove_dir_all(SnapshotStore::dir_for(&kg_path)).unwrap();
+    }
+
+    #[test]
+    fn test_diff_and_merge() {
+        let mut target = KnowledgeGraph::in_memory(schema()).unwrap();
+        target.add_triples(&[
+            triple("http://ex.org/acme", "http://ex.org/hasName", "Acme"),
+            triple("http://ex.org/acme", "http://ex.org/basedIn", "Paris"),
+        ]).unwrap();
+
+        let mut source = KnowledgeGraph::in_memory(schema()).unwrap();
+        source.add_triples(&[
+            triple("http://ex.org/acme", "http://ex.org/hasName", "Acme"),
+            triple("http://ex.org/acme", "http://ex.org/basedIn", "London"),
+            triple("http://ex.org/bob", "http://ex.org/worksFor", "http://ex.org/acme"),
+        ]).unwrap();
+
+        let diff = target.diff(&source);
+        assert_eq!(diff.added.len(), 2);
+        assert_eq!(diff.removed.len(), 1);
+
+        let preview = target.preview_merge(&source, MergeStrategy::PreferTarget);
+        assert_eq!(target.triples().len(), 2);
+        let report = target.merge(&source, MergeStrategy::PreferTarget).unwrap();
+        assert_eq!(report.added, 1);
+        assert_eq!(report.duplicates, 1);
+        assert_eq!(report.skipped, 1);
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(report.new_predicates, ["http://ex.org/worksFor"]);
+        assert_eq!(target.triples().len(), 3);
+        assert_eq!((preview.added, preview.duplicates, preview.skipped), (1, 1, 1));
+        assert_eq!(preview.new_predicates, report.new_predicates);
+
+        let preview = target.preview_merge(&source, MergeStrategy::PreferSource);
+        assert_eq!((preview.added, preview.duplicates, preview.replaced), (1, 2, 1));
+
+        // Importing keeps both objects, so a new one for basedIn is a conflict
+        let preview = target.preview_add(&[
+            triple("http://ex.org/acme", "http://ex.org/basedIn", "Berlin"),
+            triple("http://ex.org/acme", "http://ex.org/hasName", "Acme"),
+            triple("http://ex.org/acme", "http://ex.org/foundedIn", "1999"),
+            triple("http://ex.org/acme", "http://ex.org/foundedIn", "1999"),
+        ]);
+        assert_eq!((preview.added, preview.duplicates, preview.conflicts.len()), (2, 2, 1));
+        assert_eq!(preview.conflicts[0].predicate, "http://ex.org/basedIn");
+        assert_eq!(preview.new_predicates, ["http://ex.org/foundedIn"]);
+        assert_eq!(target.triples().len(), 3);
+    }
+
+    #[test]
+    fn test_indexed_lookups() {
+        let mut kg = KnowledgeGraph::in_memory(schema()).unwrap();
+        kg.add_triples(&[
+            triple("http://ex.org/acme", "http://ex.org/hasName", "Acme"),
+            triple("http://ex.org/acme", "http://ex.org/basedIn", "http://ex.org/paris"),
+            triple("http://ex.org/bob", "http://ex.org/worksFor", "http://ex.org/acme"),
+            triple("http://ex.org/acme", "http://ex.org/hasName", "Acme"),
+        ]).unwrap();
+        assert_eq!(kg.triples().len(), 3);
+
+        // Removing triples shifts positions, which the index must follow
+        kg.remove_triples(&TriplePattern { predicate: Some("http://ex.org/hasName".into()), ..Default::default() }).unwrap();
+        assert_eq!(kg.add_triples(&[triple("http://ex.org/acme", "http://ex.org/basedIn", "http://ex.org/paris")]).unwrap(), 0);
+
+        let about: Vec<&str> = kg.triples_about("http://ex.org/acme").iter().map(|t| t.predicate.as_str()).collect();
+        assert_eq!(about, ["http://ex.org/basedIn", "http://ex.org/worksFor"]);
+        let pattern = TriplePattern { object: Some("http://ex.org/acme".into()), ..Default::default() };
+        assert_eq!(kg.find(&pattern)[0].subject, "http://ex.org/bob");
+        assert_eq!(kg.find_related_entities("http://ex.org/bob", 2).unwrap(), ["http://ex.org/acme", "http://ex.org/paris"]);
+
+        let SimpleSparqlResults::Solutions { rows, .. } = kg.execute_sparql(
+            "SELECT ?p ?city WHERE { ?p <http://ex.org/worksFor> ?c . ?c <http://ex.org/basedIn> ?city }"
+        ).unwrap() else {
+            panic!("expected solutions");
+        };
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["city"].value(), "http://ex.org/paris");
+
+        // A query the engine cannot parse is an error, not every triple
+        let unterminated = kg.execute_sparql("SELECT ?name WHERE { ?e <http://ex.org/hasName> ?name");
+        assert!(matches!(unterminated, Err(Error::Query { .. })));
+    }
+
+    #[test]
+    fn test_statistics() {
+        let mut kg = KnowledgeGraph::in_memory(schema()).unwrap();
+        let from = |t: RdfTriple, source: &str, confidence: f32| t.with_source(source.to_string()).with_confidence(confidence);
+        kg.add_triples(&[
+            from(triple("http://ex.org/acme", inference::RDF_TYPE, "http://ex.org/Company"), "a.pdf", 0.95),
+            from(triple("http://ex.org/acme", "http://ex.org/hasName", "Acme"), "a.pdf", 1.0),
+            from(triple("http://ex.org/globex", inference::RDF_TYPE, "http://ex.org/Company"), "b.pdf", 0.42),
+            triple("http://ex.org/globex", "http://ex.org/hasName", "Globex"),
+        ]).unwrap();
+
+        let stats = kg.get_statistics().unwrap();
+        assert_eq!(stats.predicates[inference::RDF_TYPE], 2);
+        assert_eq!(stats.classes, BTreeMap::from([("http://ex.org/Company".to_string(), 2)]));
+        assert_eq!(stats.sources["a.pdf"], 2);
+        assert_eq!(stats.sources[UNKNOWN_SOURCE], 1);
+        assert_eq!(stats.confidence.bins[4], 1);
+        assert_eq!(stats.confidence.bins[9], 3);
+        assert_eq!(stats.confidence.min, Some(0.42));
+        assert!(stats.to_string().contains("Classes (entities):\n         2  http://ex.org/Company"));
+    }
+
+    #[test]
+    fn test_remove_triples() {
+        let mut kg = KnowledgeGraph::in_memory(schema()).unwrap();
+        kg.add_triples(&[
+            triple("http://ex.org/acme", "http://ex.org/hasName", "Acme"),
+            triple("http://ex.org/acme", "http://ex.org/basedIn", "Paris"),
+            triple("http://ex.org/acme", "http://ex.org/basedIn", "Paris").with_graph("http://ex.org/g".to_string()),
+            triple("http://ex.org/globex", "http://ex.org/basedIn", "Paris"),
+        ]).unwrap();
+
+        let in_default_graph = TriplePattern {
+            predicate: Some("http://ex.org/basedIn".to_string()),
+            object: Some("Paris".to_string()),
+            graph: Some(None),
+            ..TriplePattern::default()
+        };
+        assert_eq!(kg.remove_triples(&in_default_graph).unwrap(), 2);
+        assert_eq!(kg.triples().len(), 2);
+
+        let acme = TriplePattern { subject: Some("http://ex.org/acme".to_string()), ..TriplePattern::default() };
+        assert_eq!(kg.remove_triples(&acme).unwrap(), 2);
+        assert!(kg.triples().is_empty());
+    }
+
+    #[test]
+    fn test_write_batching() {
+        let path = std::env::temp_dir().join(format!("kg_batch_{}.json", uuid::Uuid::new_v4()));
+        // Triples on disk, in the store file or its journal
+        let stored = |path: &Path| -> usize {
+            let config = KnowledgeGraphConfig { storage_path: path.to_string_lossy().to_string(), read_only: true, ..Default::default() };
+            KnowledgeGraph::new(config, schema()).map_or(0, |kg| kg.triples().len())
+        };
+
+        let mut kg = KnowledgeGraphBuilder::new()
+            .storage_path(path.to_string_lossy())
+            .write_batch_size(Some(3))
+            .build()
+            .unwrap();
+        kg.add_triples(&[triple("http://ex.org/a", "http://ex.org/p", "1"), triple("http://ex.org/a", "http://ex.org/p", "2")]).unwrap();
+        assert_eq!((stored(&path), kg.pending_writes()), (0, 2));
+        kg.add_triples(&[triple("http://ex.org/a", "http://ex.org/p", "3")]).unwrap();
+        assert_eq!((stored(&path), kg.pending_writes()), (3, 0));
+
+        kg.add_triples(&[triple("http://ex.org/a", "http://ex.org/p", "4")]).unwrap();
+        assert_eq!(stored(&path), 3);
+        drop(kg);
+        assert_eq!(stored(&path), 4);
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(Journal::path_for(&path.to_string_lossy())).unwrap();
+        fs::remove_file(StoreLock::path_for(&path.to_string_lossy())).unwrap();
+    }
+
+    #[test]
+    fn test_triples_since() {
+        let mut kg = KnowledgeGraph::in_memory(schema()).unwrap();
+        let mut legacy = triple("http://ex.org/acme", "http://ex.org/hasName", "Acme");
+        legacy.metadata.insert(ADDED_AT.to_string(), "2020-01-01T00:00:00+00:00".to_string());
+        kg.add_triples(&[triple("http://ex.org/acme", "http://ex.org/basedIn", "Paris")]).unwrap();
+        kg.push_triple(legacy);
+
+        let since = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z").unwrap().with_timezone(&Utc);
+        let delta = kg.triples_since(since);
+        assert_eq!(delta.len(), 1);
+        assert_eq!(delta[0].object, "Paris");
+        assert!(kg.triples_since(Utc::now()).is_empty());
     }
 }
\ No newline at end of file
@@ -0,0 +1,141 @@
+use crate::error::{Error, ErrorContext, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Prefixes every serializer declares, which the namespace map cannot rebind
+pub const RESERVED_PREFIXES: &[(&str, &str)] = &[
+    ("rdf", "http://www.w3.org/1999/02/22-rdf-syntax-ns#"),
+    ("rdfs", "http://www.w3.org/2000/01/rdf-schema#"),
+];
+
+/// Prefix map of a knowledge graph, persisted as JSON in
+/// `<kg_path>.namespaces.json`
+pub struct NamespaceMap {
+    path: PathBuf,
+    namespaces: BTreeMap<String, String>,
+}
+
+impl NamespaceMap {
+    /// Namespace file used for the knowledge graph stored at `kg_path`
+    pub fn path_for(kg_path: &str) -> PathBuf {
+        PathBuf::from(format!("{}.namespaces.json", kg_path))
+    }
+
+    pub fn open_for(kg_path: &str) -> Result<Self> {
+        Self::open(Self::path_for(kg_path))
+    }
+
+    /// Map holding `namespaces`, to be saved at `path`
+    pub fn new<P: AsRef<Path>>(path: P, namespaces: BTreeMap<String, String>) -> Self {
+        Self { path: path.as_ref().to_path_buf(), namespaces }
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let namespaces = if path.exists() {
+            let content = fs::read_to_string(&path)
+                .storage_context(|| format!("Failed to read namespaces: {}", path.display()))?;
+            serde_json::from_str(&content)
+                .storage_context(|| format!("Failed to parse namespaces: {}", path.display()))?
+        } else {
+            BTreeMap::new()
+        };
+
+        Ok(Self { path, namespaces })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let content = serde_json::to_string_pretty(&self.namespaces)
+            .storage_context(|| "Failed to serialize namespaces")?;
+        fs::write(&self.path, content)
+            .storage_context(|| format!("Failed to write namespaces: {}", self.path.display()))
+    }
+
+    /// Bind `prefix` to `uri`; returns the namespace it was bound to before
+    pub fn insert(&mut self, prefix: &str, uri: &str) -> Result<Option<String>> {
+        validate(prefix, uri)?;
+        Ok(self.namespaces.insert(prefix.to_string(), uri.to_string()))
+    }
+
+    pub fn remove(&mut self, prefix: &str) -> Option<String> {
+        self.namespaces.remove(prefix)
+    }
+
+    pub fn get(&self, prefix: &str) -> Option<&String> {
+        self.namespaces.get(prefix)
+    }
+
+    pub fn namespaces(&self) -> &BTreeMap<String, String> {
+        &self.namespaces
+    }
+
+    pub fn into_namespaces(self) -> BTreeMap<String, String> {
+        self.namespaces
+    }
+}
+
+/// Check that `prefix` is a Turtle/SPARQL prefix name and `uri` an absolute
+/// IRI that is not already bound to a reserved prefix
+pub fn validate(prefix: &str, uri: &str) -> Result<()> {
+    let mut chars = prefix.chars();
+    let valid_prefix = chars.next().is_some_and(|c| c.is_alphabetic())
+        && chars.all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'))
+        && !prefix.ends_with('.');
+    if !valid_prefix {
+        return Err(Error::config(format!("Invalid namespace prefix '{}': use a letter followed by letters, digits, '_', '-' or '.'", prefix)));
+    }
+
+    let valid_uri = uri.split_once(':').is_some_and(|(scheme, rest)| {
+        scheme.chars().next().is_some_and(|c| c.is_ascii_alphabetic())
+            && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+            && !rest.is_empty()
+    }) && !uri.contains(|c: char| c.is_whitespace() || matches!(c, '<' | '>' | '"' | '{' | '}'));
+    if !valid_uri {
+        return Err(Error::config(format!("Invalid namespace IRI '{}': expected an absolute IRI such as http://example.org/ns#", uri)));
+    }
+
+    if let Some((_, reserved)) = RESERVED_PREFIXES.iter().find(|(p, _)| *p == prefix) {
+        if *reserved != uri {
+            return Err(Error::config(format!("Prefix '{}' is reserved for <{}>", prefix, reserved)));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{OutputFormat, RdfSchema};
+    use crate::core::RdfTriple;
+    use crate::knowledge_graph::{KnowledgeGraph, KnowledgeGraphConfig};
+
+    #[test]
+    fn test_namespace_map() {
+        let dir = tempfile::tempdir().unwrap();
+        let kg_path = dir.path().join("kg.db").to_string_lossy().to_string();
+
+        let mut map = NamespaceMap::open_for(&kg_path).unwrap();
+        assert_eq!(map.insert("foaf", "http://xmlns.com/foaf/0.1/").unwrap(), None);
+        assert!(map.insert("1x", "http://ex.org/").is_err());
+        assert!(map.insert("x", "not an iri").is_err());
+        assert!(map.insert("rdf", "http://ex.org/rdf#").is_err());
+        map.save().unwrap();
+
+        let config = KnowledgeGraphConfig { storage_path: kg_path.clone(), ..Default::default() };
+        let mut kg = KnowledgeGraph::new(config, RdfSchema::default()).unwrap();
+        assert_eq!(kg.namespaces().get("foaf").map(String::as_str), Some("http://xmlns.com/foaf/0.1/"));
+        kg.add_triples(&[RdfTriple::new("http://ex.org/jane".into(), "http://xmlns.com/foaf/0.1/name".into(), "Jane".into())]).unwrap();
+
+        let rows = kg.execute_sparql("SELECT ?n WHERE { ?p foaf:name ?n }").unwrap();
+        assert!(matches!(rows, crate::knowledge_graph::SimpleSparqlResults::Solutions { rows, .. } if rows.len() == 1));
+
+        let schema = kg.schema();
+        let turtle = kg.serializer().serialize(kg.triples(), &OutputFormat::Turtle, &schema.namespace, &schema.prefix).unwrap();
+        assert!(turtle.contains("@prefix foaf: <http://xmlns.com/foaf/0.1/> ."));
+        assert!(turtle.contains("<http://ex.org/jane> foaf:name \"Jane\" ."));
+
+        assert_eq!(kg.remove_namespace("foaf").unwrap().as_deref(), Some("http://xmlns.com/foaf/0.1/"));
+        assert!(NamespaceMap::open_for(&kg_path).unwrap().namespaces().is_empty());
+    }
+}
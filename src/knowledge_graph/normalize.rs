@@ -0,0 +1,302 @@
+//! Literal normalization applied as triples are stored, so that one value
+//! written differently in different documents ("March 5, 2024" and
+//! "2024-03-05", "$1,200" and "USD 1200.00") is stored, and matched, once
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use std::collections::HashSet;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::config::{LiteralNormalization, RdfSchema};
+use crate::core::RdfTriple;
+
+const XSD_DATE: &str = "http://www.w3.org/2001/XMLSchema#date";
+const XSD_DATE_TIME: &str = "http://www.w3.org/2001/XMLSchema#dateTime";
+
+/// Phone number predicates of common vocabularies
+const PHONE_PREDICATES: &[&str] = &[
+    "http://schema.org/telephone",
+    "https://schema.org/telephone",
+    "http://schema.org/faxNumber",
+    "https://schema.org/faxNumber",
+    "http://xmlns.com/foaf/0.1/phone",
+    "http://www.w3.org/2006/vcard/ns#tel",
+];
+
+const CURRENCY_SYMBOLS: &[(&str, &str)] = &[
+    ("US$", "USD"), ("C$", "CAD"), ("A$", "AUD"), ("$", "USD"), ("€", "EUR"),
+    ("£", "GBP"), ("¥", "JPY"), ("₹", "INR"), ("₩", "KRW"), ("CHF", "CHF"),
+];
+const CURRENCY_CODES: &[&str] = &[
+    "USD", "EUR", "GBP", "JPY", "CNY", "INR", "CHF", "CAD", "AUD", "NZD",
+    "SEK", "NOK", "DKK", "PLN", "CZK", "KRW", "SGD", "HKD", "BRL", "MXN", "ZAR",
+];
+
+/// Normalizes the literal objects of triples with the configured normalizers
+pub struct Normalizer<'a> {
+    settings: &'a LiteralNormalization,
+    /// Predicate IRIs normalization is limited to; `None` for all
+    predicates: Option<HashSet<String>>,
+    /// Predicate IRIs whose values are read as phone numbers
+    phone_predicates: HashSet<String>,
+}
+
+impl<'a> Normalizer<'a> {
+    pub fn new(settings: &'a LiteralNormalization, schema: &RdfSchema) -> Self {
+        let predicates = (!settings.predicates.is_empty())
+            .then(|| settings.predicates.iter().map(|p| schema.resolve_term(p)).collect());
+        let phone_predicates = if settings.phone_predicates.is_empty() {
+            PHONE_PREDICATES.iter().map(|p| p.to_string()).collect()
+        } else {
+            settings.phone_predicates.iter().map(|p| schema.resolve_term(p)).collect()
+        };
+        Self { settings, predicates, phone_predicates }
+    }
+
+    /// Normalize the triple's object in place if it is a literal; dates get
+    /// an `xsd:date` or `xsd:dateTime` datatype unless they have one
+    pub fn apply(&self, triple: &mut RdfTriple) {
+        if !self.settings.is_enabled() {
+            return;
+        }
+        let object = &triple.object;
+        if object.starts_with("http://") || object.starts_with("https://") || object.starts_with("_:") {
+            return;
+        }
+        if self.predicates.as_ref().is_some_and(|predicates| !predicates.contains(&triple.predicate)) {
+            return;
+        }
+
+        let settings = self.settings;
+        let mut value = if settings.unicode_nfc { object.nfc().collect() } else { object.clone() };
+        if settings.whitespace {
+            value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+        }
+
+        // Language-tagged text is prose, not a date, number or amount
+        if !triple.metadata.contains_key("lang") {
+            let datatype = triple.metadata.get("datatype").cloned();
+            if settings.dates && matches!(datatype.as_deref(), None | Some(XSD_DATE) | Some(XSD_DATE_TIME)) {
+                if let Some((date, date_type)) = normalize_date(&value, settings.day_first) {
+                    value = date;
+                    triple.metadata.entry("datatype".to_string()).or_insert_with(|| date_type.to_string());
+                }
+            }
+            if datatype.is_none() && settings.phone_numbers && self.phone_predicates.contains(&triple.predicate) {
+                if let Some(phone) = normalize_phone(&value, settings.default_country_code.as_deref()) {
+                    value = phone;
+                }
+            }
+            if datatype.is_none() && settings.currencies {
+                if let Some(amount) = normalize_currency(&value) {
+                    value = amount;
+                }
+            }
+        }
+
+        triple.object = value;
+    }
+}
+
+/// `YYYY-MM-DD` for a date, or an RFC 3339 date-time, with its datatype
+fn normalize_date(value: &str, day_first: bool) -> Option<(String, &'static str)> {
+    let value = value.trim().trim_end_matches('.');
+
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(value) {
+        return Some((datetime.to_rfc3339(), XSD_DATE_TIME));
+    }
+    for format in ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"] {
+        if let Ok(datetime) = NaiveDateTime::parse_from_str(value, format) {
+            return Some((datetime.format("%Y-%m-%dT%H:%M:%S").to_string(), XSD_DATE_TIME));
+        }
+    }
+
+    // "March 5th, 2024" reads like "March 5, 2024"
+    let value = strip_ordinals(value);
+    let numeric: &[&str] = if day_first {
+        &["%d/%m/%Y", "%d-%m-%Y"]
+    } else {
+        &["%m/%d/%Y", "%m-%d-%Y"]
+    };
+    let formats = [
+        "%Y-%m-%d", "%Y/%m/%d", "%Y.%m.%d", "%d.%m.%Y",
+        "%B %d, %Y", "%B %d %Y", "%d %B %Y", "%d %B, %Y", "%b. %d, %Y", "%d. %B %Y",
+    ];
+    formats.iter().chain(numeric)
+        .find_map(|format| NaiveDate::parse_from_str(&value, format).ok())
+        .filter(|date| (1000..=9999).contains(&chrono::Datelike::year(date)))
+        .map(|date| (date.format("%Y-%m-%d").to_string(), XSD_DATE))
+}
+
+/// Drop `st`, `nd`, `rd` and `th` after a day number
+fn strip_ordinals(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(c) = rest.chars().next() {
+        let after_digit = result.chars().last().is_some_and(|last: char| last.is_ascii_digit());
+        let suffix = ["st", "nd", "rd", "th"].iter().find(|suffix| {
+            rest.len() >= 2 && rest.is_char_boundary(2) && rest[..2].eq_ignore_ascii_case(suffix)
+                && !rest[2..].starts_with(|c: char| c.is_alphanumeric())
+        });
+        match suffix {
+            Some(_) if after_digit => rest = &rest[2..],
+            _ => {
+                result.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    result
+}
+
+/// E.164 (`+14155550100`) for a formatted phone number: digits with
+/// spaces, dashes, dots or parentheses, with a `+` or `00` international
+/// prefix, or 10+ digits and a default country code
+fn normalize_phone(value: &str, default_country_code: Option<&str>) -> Option<String> {
+    let value = value.trim();
+    if !value.chars().all(|c| c.is_ascii_digit() || " -.()+/".contains(c)) || value.matches('+').count() > 1 {
+        return None;
+    }
+    if !value.contains([' ', '-', '.', '(', ')', '/']) && !value.starts_with('+') {
+        return None;
+    }
+
+    let digits: String = value.chars().filter(char::is_ascii_digit).collect();
+    let international = if value.starts_with('+') {
+        digits
+    } else if let Some(rest) = digits.strip_prefix("00") {
+        rest.to_string()
+    } else {
+        let country = default_country_code?.trim_start_matches('+');
+        if digits.len() < 10 {
+            return None;
+        }
+        // A national number's trunk prefix is dropped: 020 7946 0000 -> +44 20 7946 0000
+        let national = digits.strip_prefix('0').unwrap_or(&digits);
+        if digits.starts_with(country) && digits.len() > 10 {
+            digits.clone()
+        } else {
+            format!("{}{}", country, national)
+        }
+    };
+
+    (8..=15).contains(&international.len()).then(|| format!("+{}", international))
+}
+
+/// `<amount> <ISO 4217 code>` with two decimals for an amount written with
+/// a currency symbol or code, thousands separators and `k`/`M`/`bn`
+/// multipliers: `$1,200.5` -> `1200.50 USD`, `1.200,50 €` -> `1200.50 EUR`
+fn normalize_currency(value: &str) -> Option<String> {
+    let value = value.trim();
+    let (code, amount) = currency_code(value)?;
+    let amount = amount.trim();
+
+    let lower = amount.to_lowercase();
+    let (number, multiplier) = [
+        (" billion", 1e9), ("bn", 1e9), (" million", 1e6), ("mn", 1e6), ("m", 1e6), ("b", 1e9), ("k", 1e3),
+    ]
+    .iter()
+    .find_map(|(suffix, multiplier)| lower.strip_suffix(suffix).map(|n| (n.trim_end().to_string(), *multiplier)))
+    .unwrap_or((lower, 1.0));
+
+    let number = parse_amount(&number)? * multiplier;
+    Some(format!("{:.2} {}", number, code))
+}
+
+/// The currency of an amount and the amount without it
+fn currency_code(value: &str) -> Option<(&'static str, &str)> {
+    for (symbol, code) in CURRENCY_SYMBOLS {
+        if let Some(rest) = value.strip_prefix(symbol).or_else(|| value.strip_suffix(symbol)) {
+            return Some((code, rest));
+        }
+    }
+    CURRENCY_CODES.iter().find_map(|code| {
+        value.strip_prefix(code).or_else(|| value.strip_suffix(code)).map(|rest| (*code, rest))
+    })
+}
+
+/// A number with `,`/`.`/space thousands separators; the last `,` or `.` is
+/// the decimal separator unless exactly three digits follow a single one
+fn parse_amount(number: &str) -> Option<f64> {
+    let number: String = number.chars().filter(|c| !c.is_whitespace() && *c != '\'').collect();
+    if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit() || c == ',' || c == '.') {
+        return None;
+    }
+
+    let separators = number.matches([',', '.']).count();
+    let decimal = match number.rfind([',', '.']) {
+        Some(position) => {
+            let last = number.as_bytes()[position] as char;
+            let digits_after = number.len() - position - 1;
+            let only_this_kind = number.matches(last).count() == separators;
+            // "1,200" and "1.200.000" are thousands; "1,200.50" and "12,5" have decimals
+            if only_this_kind && (digits_after == 3 && (separators == 1 || number.matches(last).count() > 1)) {
+                None
+            } else {
+                Some(position)
+            }
+        }
+        None => None,
+    };
+
+    let normalized: String = number.char_indices()
+        .filter_map(|(i, c)| match c {
+            ',' | '.' if Some(i) == decimal => Some('.'),
+            ',' | '.' => None,
+            c => Some(c),
+        })
+        .collect();
+    normalized.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::knowledge_graph::KnowledgeGraphBuilder;
+
+    #[test]
+    fn test_literal_normalization() {
+        let settings = LiteralNormalization {
+            whitespace: true,
+            unicode_nfc: true,
+            dates: true,
+            phone_numbers: true,
+            phone_predicates: vec!["http://ex.org/phone".into()],
+            default_country_code: Some("1".into()),
+            currencies: true,
+            ..Default::default()
+        };
+        let mut kg = KnowledgeGraphBuilder::new().in_memory().literal_normalization(settings.clone()).build().unwrap();
+        let triple = |p: &str, o: &str| RdfTriple::new("http://ex.org/acme".into(), format!("http://ex.org/{}", p), o.into());
+
+        // The same values, written differently in two documents, are stored once
+        let first = [triple("founded", "March 5th, 2024"), triple("phone", "(415) 555-0100"), triple("revenue", "$1.2M"), triple("name", "Cafe\u{301}  Acme ")];
+        let second = [triple("founded", "2024-03-05"), triple("phone", "+1 415.555.0100"), triple("revenue", "USD 1,200,000"), triple("name", "Café Acme")];
+        assert_eq!(kg.add_triples(&first).unwrap(), 4);
+        assert_eq!(kg.add_triples(&second).unwrap(), 0);
+
+        let objects: Vec<&str> = kg.triples().iter().map(|t| t.object.as_str()).collect();
+        assert_eq!(objects, ["2024-03-05", "+14155550100", "1200000.00 USD", "Café Acme"]);
+        assert_eq!(kg.triples()[0].metadata["datatype"], XSD_DATE);
+
+        // Only phone number predicates get phone normalization
+        let mut code = triple("postalCode", "123 456 7890");
+        Normalizer::new(&settings, &RdfSchema::default()).apply(&mut code);
+        assert_eq!(code.object, "123 456 7890");
+        let mut fax = RdfTriple::new("http://ex.org/acme".into(), "http://schema.org/faxNumber".into(), "(415) 555-0101".into());
+        Normalizer::new(&LiteralNormalization { phone_predicates: Vec::new(), ..settings.clone() }, &RdfSchema::default()).apply(&mut fax);
+        assert_eq!(fax.object, "+14155550101");
+
+        assert_eq!(normalize_date("05/03/2024", true).unwrap().0, "2024-03-05");
+        assert_eq!(normalize_date("2024-03-05T09:30:00Z", false).unwrap().0, "2024-03-05T09:30:00+00:00");
+        assert_eq!(normalize_currency("1.200,50 €").as_deref(), Some("1200.50 EUR"));
+        assert_eq!(normalize_phone("123-45-6789", Some("1")), None);
+        assert_eq!(normalize_date("Q3 2024", false), None);
+
+        // Normalization can be limited to some predicates
+        let limited = LiteralNormalization { predicates: vec!["http://ex.org/phone".into()], ..settings };
+        let normalizer = Normalizer::new(&limited, &RdfSchema::default());
+        let mut date = triple("founded", "March 5, 2024");
+        normalizer.apply(&mut date);
+        assert_eq!(date.object, "March 5, 2024");
+    }
+}
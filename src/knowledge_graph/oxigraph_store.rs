@@ -10,6 +10,7 @@ use tracing::{debug, info};
 
 use crate::config::RdfSchema;
 use crate::core::RdfTriple;
+use super::{Binding, QueryLimits};
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct OxigraphKnowledgeGraph {
@@ -17,6 +18,8 @@ pub struct OxigraphKnowledgeGraph {
     store: Option<Store>,
     storage_path: String,
     schema: RdfSchema,
+    #[serde(skip)]
+    limits: QueryLimits,
 }
 
 impl OxigraphKnowledgeGraph {
@@ -33,9 +36,15 @@ impl OxigraphKnowledgeGraph {
             store: Some(store),
             storage_path,
             schema,
+            limits: QueryLimits::default(),
         })
     }
 
+    pub fn with_limits(mut self, limits: QueryLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
     pub fn add_triple(&mut self, triple: &RdfTriple) -> Result<()> {
         let store = self.store.as_ref()
             .ok_or_else(|| anyhow::anyhow!("Store not initialized"))?;
@@ -82,22 +91,38 @@ impl OxigraphKnowledgeGraph {
 
         debug!("Executing SPARQL query: {}", query);
 
+        let deadline = self.limits.timeout.map(|timeout| std::time::Instant::now() + timeout);
         let results = store.query(query)?;
 
         match results {
             QueryResults::Solutions(solutions) => {
+                let variables = solutions.variables().iter()
+                    .map(|var| var.as_str().to_string())
+                    .collect();
                 let mut rows = Vec::new();
 
+                // Solutions are produced lazily, so stopping here also stops evaluation
                 for solution in solutions {
+                    if deadline.is_some_and(|deadline| std::time::Instant::now() >= deadline) {
+                        anyhow::bail!("Query exceeded the timeout of {:?}", self.limits.timeout.unwrap_or_default());
+                    }
+                    if self.limits.max_results.is_some_and(|max| rows.len() >= max) {
+                        anyhow::bail!("Query exceeded the limit of {} results", rows.len());
+                    }
+
                     let solution = solution?;
                     let mut row = HashMap::new();
 
                     for (var, term) in solution.iter() {
                         let value = match term {
-                            Term::NamedNode(n) => n.as_str().to_string(),
-                            Term::BlankNode(b) => b.as_str().to_string(),
-                            Term::Literal(l) => l.value().to_string(),
-                            Term::Triple(t) => format!("{:?}", t),
+                            Term::NamedNode(n) => Binding::iri(n.as_str()),
+                            Term::BlankNode(b) => Binding::BlankNode { value: format!("_:{}", b.as_str()) },
+                            Term::Literal(l) => match l.language() {
+                                Some(lang) => Binding::lang_literal(l.value(), lang),
+                                None if l.datatype() == vocab::xsd::STRING => Binding::literal(l.value()),
+                                None => Binding::typed_literal(l.value(), l.datatype().as_str()),
+                            },
+                            Term::Triple(t) => Binding::literal(format!("{:?}", t)),
                         };
                         row.insert(var.as_str().to_string(), value);
                     }
@@ -105,7 +130,7 @@ impl OxigraphKnowledgeGraph {
                     rows.push(row);
                 }
 
-                Ok(SimpleSparqlResults::Solutions(rows))
+                Ok(SimpleSparqlResults::Solutions { variables, rows })
             }
             QueryResults::Boolean(result) => {
                 Ok(SimpleSparqlResults::Boolean(result))
@@ -160,6 +185,9 @@ impl OxigraphKnowledgeGraph {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum SimpleSparqlResults {
-    Solutions(Vec<HashMap<String, String>>),
+    Solutions {
+        variables: Vec<String>,
+        rows: Vec<HashMap<String, Binding>>,
+    },
     Boolean(bool),
 }
\ No newline at end of file
@@ -0,0 +1,149 @@
+use crate::error::{ErrorContext, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A named SPARQL query kept next to a knowledge graph
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedQuery {
+    pub name: String,
+    pub query: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub last_run: Option<DateTime<Utc>>,
+}
+
+/// Named queries persisted as JSON in `<kg_path>.queries.json`
+pub struct QueryLibrary {
+    path: PathBuf,
+    queries: BTreeMap<String, SavedQuery>,
+}
+
+impl QueryLibrary {
+    /// Library file used for the knowledge graph stored at `kg_path`
+    pub fn path_for(kg_path: &str) -> PathBuf {
+        PathBuf::from(format!("{}.queries.json", kg_path))
+    }
+
+    pub fn open_for(kg_path: &str) -> Result<Self> {
+        Self::open(Self::path_for(kg_path))
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let queries = if path.exists() {
+            let content = fs::read_to_string(&path)
+                .storage_context(|| format!("Failed to read query library: {}", path.display()))?;
+            serde_json::from_str(&content)
+                .storage_context(|| format!("Failed to parse query library: {}", path.display()))?
+        } else {
+            BTreeMap::new()
+        };
+
+        Ok(Self { path, queries })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let content = serde_json::to_string_pretty(&self.queries)
+            .storage_context(|| "Failed to serialize query library")?;
+        fs::write(&self.path, content)
+            .storage_context(|| format!("Failed to write query library: {}", self.path.display()))
+    }
+
+    /// Add or replace a query; returns true when an existing query was replaced
+    pub fn insert(&mut self, name: &str, query: &str, description: Option<String>, tags: Vec<String>) -> bool {
+        let saved = SavedQuery {
+            name: name.to_string(),
+            query: query.to_string(),
+            description,
+            tags: normalize_tags(tags),
+            created_at: Utc::now(),
+            last_run: None,
+        };
+        self.queries.insert(name.to_string(), saved).is_some()
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<SavedQuery> {
+        self.queries.remove(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&SavedQuery> {
+        self.queries.get(name)
+    }
+
+    /// Queries sorted by name, optionally restricted to those carrying `tag`
+    pub fn list(&self, tag: Option<&str>) -> Vec<&SavedQuery> {
+        self.queries.values()
+            .filter(|q| tag.is_none_or(|tag| q.tags.iter().any(|t| t == tag)))
+            .collect()
+    }
+
+    pub fn add_tags(&mut self, name: &str, tags: Vec<String>) -> Result<()> {
+        let query = self.get_mut(name)?;
+        query.tags.extend(tags);
+        query.tags = normalize_tags(std::mem::take(&mut query.tags));
+        Ok(())
+    }
+
+    pub fn remove_tags(&mut self, name: &str, tags: &[String]) -> Result<()> {
+        let tags = normalize_tags(tags.to_vec());
+        let query = self.get_mut(name)?;
+        query.tags.retain(|t| !tags.contains(t));
+        Ok(())
+    }
+
+    /// Return the query text and record the run time
+    pub fn mark_run(&mut self, name: &str) -> Result<String> {
+        let query = self.get_mut(name)?;
+        query.last_run = Some(Utc::now());
+        Ok(query.query.clone())
+    }
+
+    fn get_mut(&mut self, name: &str) -> Result<&mut SavedQuery> {
+        self.queries.get_mut(name)
+            .storage_context(|| format!("No saved query named '{}'", name))
+    }
+}
+
+fn normalize_tags(tags: Vec<String>) -> Vec<String> {
+    let mut tags: Vec<String> = tags.into_iter()
+        .map(|t| t.trim().to_lowercase())
+        .filter(|t| !t.is_empty())
+        .collect();
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_library_roundtrip() {
+        let kg_path = std::env::temp_dir()
+            .join(format!("queries_test_{}.db", uuid::Uuid::new_v4()))
+            .to_string_lossy()
+            .to_string();
+
+        let mut library = QueryLibrary::open_for(&kg_path).unwrap();
+        library.insert("people", "SELECT ?name WHERE { ?p hasName ?name }", None, vec!["HR".to_string()]);
+        library.add_tags("people", vec!["weekly".to_string(), "hr".to_string()]).unwrap();
+        library.save().unwrap();
+
+        let mut reopened = QueryLibrary::open_for(&kg_path).unwrap();
+        assert_eq!(reopened.get("people").unwrap().tags, vec!["hr", "weekly"]);
+        assert_eq!(reopened.list(Some("weekly")).len(), 1);
+        assert!(reopened.list(Some("finance")).is_empty());
+        assert!(reopened.mark_run("people").unwrap().contains("hasName"));
+        assert!(reopened.mark_run("missing").is_err());
+
+        fs::remove_file(QueryLibrary::path_for(&kg_path)).unwrap();
+    }
+}
@@ -0,0 +1,188 @@
+use crate::core::RdfTriple;
+use crate::error::{Error, ErrorContext, Result};
+use crate::utils::serialization::{ntriples_object, ntriples_resource};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Outcome of pushing one set of triples to a `RemoteStore`
+#[derive(Debug, Default)]
+pub struct PushReport {
+    /// Triples in batches the store accepted
+    pub inserted: usize,
+    /// One message per batch that was still rejected after retrying
+    pub errors: Vec<String>,
+}
+
+/// A SPARQL 1.1 Update endpoint, such as an Oxigraph server's `/update`,
+/// that triples are written to in `INSERT DATA` batches
+///
+/// Each batch is retried on its own, so a failure loses at most one batch
+/// and never aborts the push.
+pub struct RemoteStore {
+    client: reqwest::Client,
+    update_url: String,
+    token: Option<String>,
+    batch_size: usize,
+    retries: u32,
+}
+
+impl RemoteStore {
+    pub fn new(update_url: impl Into<String>, batch_size: usize) -> Result<Self> {
+        if batch_size == 0 {
+            return Err(Error::config("Remote store batch size must be at least 1"));
+        }
+
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(60))
+            .build()
+            .storage_context(|| "Failed to create HTTP client")?;
+
+        Ok(Self {
+            client,
+            update_url: update_url.into(),
+            token: None,
+            batch_size,
+            retries: 2,
+        })
+    }
+
+    /// Bearer token sent with every update
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Extra attempts for a rejected batch, with doubling delays from 500ms
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    pub fn url(&self) -> &str {
+        &self.update_url
+    }
+
+    pub async fn push(&self, triples: &[RdfTriple]) -> PushReport {
+        let mut report = PushReport::default();
+        let batches = triples.len().div_ceil(self.batch_size);
+
+        for (i, batch) in triples.chunks(self.batch_size).enumerate() {
+            match self.insert(batch).await {
+                Ok(()) => {
+                    debug!("Stored batch {}/{} ({} triples) in {}", i + 1, batches, batch.len(), self.update_url);
+                    report.inserted += batch.len();
+                }
+                Err(e) => {
+                    warn!("Batch {}/{} not stored in {}: {}", i + 1, batches, self.update_url, e);
+                    report.errors.push(format!("Batch {}/{} ({} triples) not stored: {}", i + 1, batches, batch.len(), e));
+                }
+            }
+        }
+        report
+    }
+
+    async fn insert(&self, batch: &[RdfTriple]) -> Result<()> {
+        let update = insert_data(batch);
+        let mut delay = Duration::from_millis(500);
+
+        for attempt in 0.. {
+            match self.send(&update).await {
+                Err(e) if attempt < self.retries => {
+                    debug!("Retrying update after {:?}: {}", delay, e);
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                }
+                outcome => return outcome,
+            }
+        }
+        unreachable!("retry loop always returns")
+    }
+
+    async fn send(&self, update: &str) -> Result<()> {
+        let mut request = self.client.post(&self.update_url)
+            .header(reqwest::header::CONTENT_TYPE, "application/sparql-update")
+            .body(update.to_string());
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+
+        let response = request.send().await
+            .storage_context(|| format!("Failed to reach {}", self.update_url))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(Error::storage(format!("HTTP {}: {}", status, body.trim())));
+        }
+        Ok(())
+    }
+}
+
+/// `INSERT DATA` for a batch: default-graph triples, then one `GRAPH` block
+/// per named graph
+fn insert_data(triples: &[RdfTriple]) -> String {
+    let mut graphs: BTreeMap<Option<&str>, Vec<&RdfTriple>> = BTreeMap::new();
+    for triple in triples {
+        graphs.entry(triple.graph.as_deref()).or_default().push(triple);
+    }
+
+    let mut update = String::from("INSERT DATA {\n");
+    for (graph, triples) in graphs {
+        let indent = match graph {
+            Some(graph) => {
+                update.push_str(&format!("  GRAPH {} {{\n", ntriples_resource(graph)));
+                "    "
+            }
+            None => "  ",
+        };
+        for triple in triples {
+            update.push_str(&format!(
+                "{}{} <{}> {} .\n",
+                indent,
+                ntriples_resource(&triple.subject),
+                triple.predicate,
+                ntriples_object(triple)
+            ));
+        }
+        if graph.is_some() {
+            update.push_str("  }\n");
+        }
+    }
+    update.push('}');
+    update
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_push_in_batches() {
+        let mut server = mockito::Server::new_async().await;
+        let accepted = server.mock("POST", "/update")
+            .match_header("content-type", "application/sparql-update")
+            .match_body(mockito::Matcher::Regex("ex.org/s0".to_string()))
+            .with_status(204)
+            .create_async().await;
+        let rejected = server.mock("POST", "/update")
+            .match_body(mockito::Matcher::Regex("ex.org/s2".to_string()))
+            .with_status(500)
+            .with_body("store unavailable")
+            .create_async().await;
+
+        let triples: Vec<RdfTriple> = (0..3)
+            .map(|i| RdfTriple::new(format!("http://ex.org/s{}", i), "http://ex.org/p".to_string(), format!("line\n{}", i)))
+            .collect();
+        let store = RemoteStore::new(format!("{}/update", server.url()), 2).unwrap().with_retries(0);
+
+        let report = store.push(&triples).await;
+        assert_eq!(report.inserted, 2);
+        assert_eq!(report.errors.len(), 1);
+        assert!(report.errors[0].contains("Batch 2/2") && report.errors[0].contains("store unavailable"));
+        accepted.assert_async().await;
+        rejected.assert_async().await;
+
+        let update = insert_data(&[triples[0].clone().with_graph("http://ex.org/g".to_string()), triples[1].clone()]);
+        assert!(update.starts_with("INSERT DATA {\n  <http://ex.org/s1> <http://ex.org/p> \"line\\n1\" .\n  GRAPH <http://ex.org/g> {"));
+    }
+}
@@ -0,0 +1,219 @@
+use crate::core::RdfTriple;
+use crate::error::Result;
+use serde::Serialize;
+use std::collections::HashSet;
+
+use super::journal;
+use super::storage::read_json_lossy;
+
+/// A part of a damaged store that could not be read as a triple
+#[derive(Debug, Clone, Serialize)]
+pub struct QuarantinedRecord {
+    /// File the record was read from, when it is not the store itself
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// 1-based line where the record starts
+    pub line: usize,
+    pub error: String,
+    pub record: String,
+}
+
+/// What `salvage` could read from a damaged store
+#[derive(Debug, Default)]
+pub struct Salvage {
+    pub triples: Vec<RdfTriple>,
+    pub quarantined: Vec<QuarantinedRecord>,
+}
+
+/// Recover the store at `kg_path`, whose file holds `content`: the
+/// readable records of the file, then the readable entries of its journal
+/// that the file does not already hold
+pub fn salvage_store(kg_path: &str, content: &[u8]) -> Result<Salvage> {
+    let mut recovered = salvage(&read_json_lossy(content));
+    let pending = journal::salvage(kg_path)?;
+
+    let key = |t: &RdfTriple| (t.subject.clone(), t.predicate.clone(), t.object.clone(), t.graph.clone());
+    let mut seen: HashSet<_> = recovered.triples.iter().map(key).collect();
+    recovered.triples.extend(pending.triples.into_iter().filter(|t| seen.insert(key(t))));
+    recovered.quarantined.extend(pending.quarantined);
+    Ok(recovered)
+}
+
+/// Read every well-formed triple record from the content of a JSON store
+/// that no longer parses as a whole
+///
+/// Records are the top-level `{...}` objects of the array. A record that
+/// does not deserialize, is cut off, or has a string running over a line
+/// break is quarantined, and reading resumes at the next line holding only
+/// `{`, which is where the store's writer starts each record.
+pub fn salvage(content: &str) -> Salvage {
+    let lines = LineIndex::new(content);
+    let bytes = content.as_bytes();
+    let mut salvage = Salvage::default();
+    let mut stray: Option<usize> = None;
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        match bytes[pos] {
+            b'{' => {
+                if let Some(start) = stray.take() {
+                    salvage.quarantine(&lines, content, start, pos, "Unexpected text outside a record".to_string());
+                }
+
+                let (end, complete) = record_end(content, pos);
+                if !complete {
+                    salvage.quarantine(&lines, content, pos, end, "Incomplete record".to_string());
+                } else {
+                    match serde_json::from_str::<RdfTriple>(&content[pos..end]) {
+                        Ok(triple) => salvage.triples.push(triple),
+                        Err(e) => salvage.quarantine(&lines, content, pos, end, e.to_string()),
+                    }
+                }
+                pos = end;
+            }
+            b'[' | b']' | b',' if stray.is_none() => pos += 1,
+            b if b.is_ascii_whitespace() && stray.is_none() => pos += 1,
+            _ => {
+                stray.get_or_insert(pos);
+                pos += 1;
+            }
+        }
+    }
+
+    if let Some(start) = stray {
+        salvage.quarantine(&lines, content, start, bytes.len(), "Unexpected text outside a record".to_string());
+    }
+    salvage
+}
+
+impl Salvage {
+    fn quarantine(&mut self, lines: &LineIndex, content: &str, start: usize, end: usize, error: String) {
+        let record = content[start..end].trim_end_matches(|c: char| c.is_whitespace() || c == ',' || c == ']');
+        if !record.trim().is_empty() {
+            self.quarantined.push(QuarantinedRecord {
+                file: None,
+                line: lines.line(start),
+                error,
+                record: record.to_string(),
+            });
+        }
+    }
+}
+
+/// End of the record starting at `start`, and whether it closed properly
+fn record_end(content: &str, start: usize) -> (usize, bool) {
+    let bytes = content.as_bytes();
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (i, &b) in bytes.iter().enumerate().skip(start) {
+        if in_string {
+            match b {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                b'\n' => return (next_record_line(content, i + 1), false),
+                _ => {}
+            }
+            continue;
+        }
+
+        match b {
+            b'"' => in_string = true,
+            b'{' => depth += 1,
+            b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return (i + 1, true);
+                }
+            }
+            b'\n' if content[i + 1..].lines().next().is_some_and(|line| line.trim() == "{") => return (i + 1, false),
+            _ => {}
+        }
+    }
+    (bytes.len(), false)
+}
+
+/// Start of the first line at or after `from` that opens a record
+fn next_record_line(content: &str, from: usize) -> usize {
+    let mut pos = from;
+    for line in content[from..].split_inclusive('\n') {
+        if line.trim() == "{" {
+            return pos;
+        }
+        pos += line.len();
+    }
+    content.len()
+}
+
+struct LineIndex {
+    starts: Vec<usize>,
+}
+
+impl LineIndex {
+    fn new(content: &str) -> Self {
+        let starts = std::iter::once(0)
+            .chain(content.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self { starts }
+    }
+
+    fn line(&self, offset: usize) -> usize {
+        self.starts.partition_point(|&start| start <= offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_salvage() {
+        let good = |s: &str| format!("  {{\n    \"subject\": \"http://ex.org/{}\",\n    \"predicate\": \"http://ex.org/p\",\n    \"object\": \"v\",\n    \"metadata\": {{}}\n  }}", s);
+        let content = [
+            "[".to_string(),
+            format!("{},", good("a")),
+            "  {\n    \"subject\": \"http://ex.org/b\",\n    \"confidence\": \"high\"\n  },".to_string(),
+            "  {\n    \"subject\": \"http://ex.org/c\n    \"predicate\": \"p\"\n  },".to_string(),
+            format!("{},", good("d")),
+            "  garbage,".to_string(),
+            format!("{},", good("e")),
+            "  {\n    \"subject\": \"http://ex.org/f\",".to_string(),
+            format!("{},", good("g")),
+            "  {\n    \"subject\": \"http://ex.org/h\",\n    \"pred".to_string(),
+        ].join("\n");
+
+        let salvage = salvage(&content);
+        let subjects: Vec<&str> = salvage.triples.iter().map(|t| t.subject.as_str()).collect();
+        assert_eq!(subjects, ["http://ex.org/a", "http://ex.org/d", "http://ex.org/e", "http://ex.org/g"]);
+
+        let quarantined: Vec<(usize, &str)> = salvage.quarantined.iter()
+            .map(|q| (q.line, q.error.split(' ').next().unwrap()))
+            .collect();
+        assert_eq!(quarantined, [(8, "invalid"), (12, "Incomplete"), (22, "Unexpected"), (29, "Incomplete"), (37, "Incomplete")]);
+        assert_eq!(salvage.quarantined[2].record, "garbage");
+
+        // Entries still pending in the journal are recovered with the store
+        let dir = tempfile::tempdir().unwrap();
+        let kg_path = dir.path().join("kg.db").to_string_lossy().to_string();
+        let entry = |s: &str| format!("{{\"subject\":\"http://ex.org/{}\",\"predicate\":\"http://ex.org/p\",\"object\":\"v\"}}\n", s);
+        let journal = [
+            "{\"base\":\"0000\"}\n".to_string(),
+            entry("a"),
+            entry("i"),
+            "{\"subject\": 5}\n".to_string(),
+            entry("j").trim_end().to_string(),
+        ].concat();
+        std::fs::write(journal::Journal::path_for(&kg_path), journal).unwrap();
+
+        let recovered = salvage_store(&kg_path, content.as_bytes()).unwrap();
+        let subjects: Vec<&str> = recovered.triples.iter().map(|t| t.subject.as_str()).collect();
+        assert_eq!(subjects, ["http://ex.org/a", "http://ex.org/d", "http://ex.org/e", "http://ex.org/g", "http://ex.org/i"]);
+        let journal_records: Vec<(usize, &str)> = recovered.quarantined.iter()
+            .filter(|q| q.file.is_some())
+            .map(|q| (q.line, q.error.split(' ').next().unwrap()))
+            .collect();
+        assert_eq!(journal_records, [(4, "invalid"), (5, "Incomplete")]);
+    }
+}
@@ -0,0 +1,86 @@
+use crate::core::RdfTriple;
+use crate::error::{Error, Result};
+use std::sync::Arc;
+use tokio::sync::{OwnedRwLockReadGuard, RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError};
+
+use super::{KnowledgeGraph, SimpleSparqlResults};
+
+/// Handle to a knowledge graph shared between async tasks: any number of
+/// readers or one writer at a time. Clones are handles to the same graph.
+#[derive(Clone)]
+pub struct SharedKnowledgeGraph(Arc<RwLock<KnowledgeGraph>>);
+
+impl SharedKnowledgeGraph {
+    pub fn new(knowledge_graph: KnowledgeGraph) -> Self {
+        Self(Arc::new(RwLock::new(knowledge_graph)))
+    }
+
+    pub async fn read(&self) -> RwLockReadGuard<'_, KnowledgeGraph> {
+        self.0.read().await
+    }
+
+    pub async fn write(&self) -> RwLockWriteGuard<'_, KnowledgeGraph> {
+        self.0.write().await
+    }
+
+    /// Read access without waiting, for callers outside an async context;
+    /// fails while a writer holds the graph
+    pub fn try_read(&self) -> std::result::Result<RwLockReadGuard<'_, KnowledgeGraph>, TryLockError> {
+        self.0.try_read()
+    }
+
+    /// Read access that can be moved into a blocking task
+    pub async fn read_owned(&self) -> OwnedRwLockReadGuard<KnowledgeGraph> {
+        self.0.clone().read_owned().await
+    }
+
+    /// Whether both handles share the same graph
+    pub fn same_graph(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+
+    pub async fn add_triples(&self, triples: &[RdfTriple]) -> Result<usize> {
+        self.write().await.add_triples(triples)
+    }
+
+    /// Run `query` on a blocking thread, so a long query doesn't stall other tasks
+    pub async fn execute_sparql(&self, query: String) -> Result<SimpleSparqlResults> {
+        let knowledge_graph = self.read_owned().await;
+        tokio::task::spawn_blocking(move || knowledge_graph.execute_sparql(&query)).await
+            .map_err(|e| Error::storage(format!("Query task failed: {}", e)))?
+    }
+
+    pub async fn flush(&self) -> Result<()> {
+        self.write().await.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RdfSchema;
+
+    #[tokio::test]
+    async fn test_shared_knowledge_graph() {
+        let shared = SharedKnowledgeGraph::new(KnowledgeGraph::in_memory(RdfSchema::default()).unwrap());
+        let tasks: Vec<_> = (0..8)
+            .map(|i| {
+                let shared = shared.clone();
+                tokio::spawn(async move {
+                    let triple = RdfTriple::new(format!("http://ex.org/{}", i), "http://ex.org/p".into(), "o".into());
+                    shared.add_triples(&[triple]).await.unwrap();
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        assert!(shared.same_graph(&shared.clone()));
+        assert_eq!(shared.read().await.triples().len(), 8);
+        let SimpleSparqlResults::Solutions { rows, .. } = shared.execute_sparql("SELECT ?s WHERE { ?s <http://ex.org/p> \"o\" }".to_string()).await.unwrap() else {
+            panic!("expected solutions");
+        };
+        assert_eq!(rows.len(), 8);
+    }
+}
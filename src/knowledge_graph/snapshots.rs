@@ -0,0 +1,146 @@
+use crate::core::RdfTriple;
+use crate::error::{Error, ErrorContext, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::StorageFormat;
+
+/// A recorded version of a knowledge graph store
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotInfo {
+    pub tag: String,
+    pub created_at: DateTime<Utc>,
+    pub triples: usize,
+}
+
+/// Immutable versions of a store, kept in `<kg_path>.snapshots/`: a
+/// compressed copy of the triples per tag in `<tag>.kg` and its namespace
+/// map in `<tag>.namespaces.json`, listed in `snapshots.json`. Files are
+/// made read-only and a tag cannot be reused.
+pub struct SnapshotStore {
+    dir: PathBuf,
+}
+
+impl SnapshotStore {
+    /// Snapshot directory used for the knowledge graph stored at `kg_path`
+    pub fn dir_for(kg_path: &str) -> PathBuf {
+        PathBuf::from(format!("{}.snapshots", kg_path))
+    }
+
+    pub fn open_for(kg_path: &str) -> Self {
+        Self::open(Self::dir_for(kg_path))
+    }
+
+    pub fn open<P: AsRef<Path>>(dir: P) -> Self {
+        Self { dir: dir.as_ref().to_path_buf() }
+    }
+
+    /// Snapshots, oldest first
+    pub fn list(&self) -> Result<Vec<SnapshotInfo>> {
+        let index = self.index_path();
+        if !index.exists() {
+            return Ok(Vec::new());
+        }
+        let content = fs::read_to_string(&index)
+            .storage_context(|| format!("Failed to read snapshot index: {}", index.display()))?;
+        serde_json::from_str(&content)
+            .storage_context(|| format!("Failed to parse snapshot index: {}", index.display()))
+    }
+
+    pub fn get(&self, tag: &str) -> Result<SnapshotInfo> {
+        self.list()?.into_iter()
+            .find(|s| s.tag == tag)
+            .ok_or_else(|| Error::storage(format!("No snapshot tagged '{}'", tag)))
+    }
+
+    pub fn create(&self, tag: &str, triples: &[RdfTriple], namespaces: &BTreeMap<String, String>) -> Result<SnapshotInfo> {
+        validate_tag(tag)?;
+        let mut snapshots = self.list()?;
+        if snapshots.iter().any(|s| s.tag == tag) {
+            return Err(Error::storage(format!("Snapshot '{}' already exists", tag)));
+        }
+
+        fs::create_dir_all(&self.dir)
+            .storage_context(|| format!("Failed to create snapshot directory: {}", self.dir.display()))?;
+        let path = self.snapshot_path(tag);
+        fs::write(&path, StorageFormat::Compact.encode(triples)?)
+            .storage_context(|| format!("Failed to write snapshot: {}", path.display()))?;
+        set_read_only(&path)?;
+        let namespaces_path = self.namespaces_path(tag);
+        let content = serde_json::to_string_pretty(namespaces)
+            .storage_context(|| "Failed to serialize namespaces")?;
+        fs::write(&namespaces_path, content)
+            .storage_context(|| format!("Failed to write snapshot: {}", namespaces_path.display()))?;
+        set_read_only(&namespaces_path)?;
+
+        let info = SnapshotInfo { tag: tag.to_string(), created_at: Utc::now(), triples: triples.len() };
+        snapshots.push(info.clone());
+        let content = serde_json::to_string_pretty(&snapshots)
+            .storage_context(|| "Failed to serialize snapshot index")?;
+        fs::write(self.index_path(), content)
+            .storage_context(|| format!("Failed to write snapshot index: {}", self.index_path().display()))?;
+
+        Ok(info)
+    }
+
+    /// Triples recorded under `tag`
+    pub fn load(&self, tag: &str) -> Result<Vec<RdfTriple>> {
+        self.get(tag)?;
+        let path = self.snapshot_path(tag);
+        let content = fs::read(&path)
+            .storage_context(|| format!("Failed to read snapshot: {}", path.display()))?;
+        StorageFormat::detect(&content).decode(&content)
+            .storage_context(|| format!("Failed to parse snapshot: {}", path.display()))
+    }
+
+    /// Namespace map recorded under `tag`; `None` for snapshots taken before
+    /// namespaces were recorded
+    pub fn load_namespaces(&self, tag: &str) -> Result<Option<BTreeMap<String, String>>> {
+        self.get(tag)?;
+        let path = self.namespaces_path(tag);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&path)
+            .storage_context(|| format!("Failed to read snapshot: {}", path.display()))?;
+        serde_json::from_str(&content)
+            .map(Some)
+            .storage_context(|| format!("Failed to parse snapshot: {}", path.display()))
+    }
+
+    fn index_path(&self) -> PathBuf {
+        self.dir.join("snapshots.json")
+    }
+
+    fn snapshot_path(&self, tag: &str) -> PathBuf {
+        self.dir.join(format!("{}.kg", tag))
+    }
+
+    fn namespaces_path(&self, tag: &str) -> PathBuf {
+        self.dir.join(format!("{}.namespaces.json", tag))
+    }
+}
+
+/// Tags name files, so they are limited to letters, digits, `.`, `_` and `-`
+fn validate_tag(tag: &str) -> Result<()> {
+    let valid = !tag.is_empty()
+        && !tag.starts_with('.')
+        && tag.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'));
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::storage(format!("Invalid snapshot tag '{}': use letters, digits, '.', '_' and '-'", tag)))
+    }
+}
+
+fn set_read_only(path: &Path) -> Result<()> {
+    let mut permissions = fs::metadata(path)
+        .storage_context(|| format!("Failed to read snapshot: {}", path.display()))?
+        .permissions();
+    permissions.set_readonly(true);
+    fs::set_permissions(path, permissions)
+        .storage_context(|| format!("Failed to protect snapshot: {}", path.display()))
+}
@@ -0,0 +1,1353 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use super::TripleIndex;
+use crate::core::RdfTriple;
+use crate::error::{Error, Result};
+
+const STANDARD_PREFIXES: &[(&str, &str)] = &[
+    ("rdf", "http://www.w3.org/1999/02/22-rdf-syntax-ns#"),
+    ("rdfs", "http://www.w3.org/2000/01/rdf-schema#"),
+    ("xsd", "http://www.w3.org/2001/XMLSchema#"),
+    ("owl", "http://www.w3.org/2002/07/owl#"),
+];
+
+/// One row of variable bindings
+pub type Solution = HashMap<String, String>;
+
+/// Bounds on query evaluation; a query exceeding either is aborted with an error
+#[derive(Debug, Clone, Copy, Default)]
+pub struct QueryLimits {
+    pub timeout: Option<Duration>,
+    /// Most solutions held at any stage of evaluation, so joins that blow
+    /// up fail before exhausting memory
+    pub max_results: Option<usize>,
+}
+
+/// `QueryLimits` for one run, checked as solutions are produced
+struct Guard {
+    deadline: Option<Instant>,
+    timeout: Duration,
+    max_results: usize,
+}
+
+impl Guard {
+    fn new(limits: QueryLimits) -> Self {
+        Self {
+            deadline: limits.timeout.map(|timeout| Instant::now() + timeout),
+            timeout: limits.timeout.unwrap_or_default(),
+            max_results: limits.max_results.unwrap_or(usize::MAX),
+        }
+    }
+
+    fn check(&self, solutions: usize) -> Result<()> {
+        if solutions > self.max_results {
+            return Err(Error::query(format!(
+                "Query exceeded the limit of {} results", self.max_results
+            )));
+        }
+        if self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Err(Error::query(format!(
+                "Query exceeded the timeout of {:.1}s", self.timeout.as_secs_f64()
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// The subset of SPARQL SELECT understood by the built-in engine
+///
+/// Supports basic graph patterns (with `;` and `,` shorthand), `OPTIONAL`,
+/// `GRAPH`, `FILTER`, `DISTINCT`, `GROUP BY`, the COUNT/SUM/AVG/MIN/MAX aggregates,
+/// `ORDER BY`, `LIMIT` and `OFFSET`.
+/// Prefixed names whose prefix is declared (or passed in with
+/// `parse_with_namespaces`) are expanded to full URIs. Names with an unknown
+/// prefix and bare names are matched against the local name of a URI, so
+/// `biz:hasName` matches `http://example.org/ontology#hasName`. Patterns
+/// outside `GRAPH` match triples in every graph.
+#[derive(Debug, Clone)]
+pub struct SelectQuery {
+    distinct: bool,
+    projection: Option<Vec<SelectItem>>,
+    pattern: GroupPattern,
+    group_by: Vec<String>,
+    order_by: Vec<OrderKey>,
+    limit: Option<usize>,
+    offset: usize,
+}
+
+#[derive(Debug, Clone)]
+struct OrderKey {
+    expression: Expression,
+    descending: bool,
+}
+
+#[derive(Debug, Clone)]
+enum SelectItem {
+    Var(String),
+    Aggregate {
+        function: AggregateFunction,
+        distinct: bool,
+        /// `None` for `COUNT(*)`
+        argument: Option<String>,
+        alias: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AggregateFunction {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+#[derive(Debug, Clone, Default)]
+struct GroupPattern {
+    triples: Vec<TriplePattern>,
+    optionals: Vec<GroupPattern>,
+    /// `GRAPH <name> { ... }` blocks, joined with the rest of the group
+    graphs: Vec<(Term, GroupPattern)>,
+    filters: Vec<Expression>,
+}
+
+#[derive(Debug, Clone)]
+struct TriplePattern {
+    subject: Term,
+    predicate: Term,
+    object: Term,
+}
+
+/// FILTER expression
+#[derive(Debug, Clone)]
+enum Expression {
+    Term(Term),
+    Not(Box<Expression>),
+    And(Box<Expression>, Box<Expression>),
+    Or(Box<Expression>, Box<Expression>),
+    Compare(CompareOp, Box<Expression>, Box<Expression>),
+    Call(Function, Vec<Expression>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Function {
+    Regex,
+    Contains,
+    StrStarts,
+    StrEnds,
+    LCase,
+    UCase,
+    Str,
+    Bound,
+}
+
+/// Result of evaluating an expression; unbound variables and type errors
+/// evaluate to `None`, which filters the row out
+#[derive(Debug, Clone, PartialEq)]
+enum ExprValue {
+    Bool(bool),
+    Str(String),
+    /// Prefixed or bare name, compared by local name
+    Name(String),
+}
+
+#[derive(Debug, Clone)]
+enum Term {
+    Var(String),
+    Iri(String),
+    /// Prefixed or bare name, matched by local name
+    Name(String),
+    Literal(String),
+}
+
+impl SelectQuery {
+    pub fn parse(query: &str) -> Result<Self> {
+        Self::parse_with_namespaces(query, &HashMap::new())
+    }
+
+    /// Parse with default prefixes, e.g. from the schema; `PREFIX`
+    /// declarations in the query take precedence
+    pub fn parse_with_namespaces(query: &str, namespaces: &HashMap<String, String>) -> Result<Self> {
+        let mut prefixes: HashMap<String, String> = STANDARD_PREFIXES.iter()
+            .map(|(prefix, namespace)| (prefix.to_string(), namespace.to_string()))
+            .collect();
+        prefixes.extend(namespaces.iter().map(|(prefix, namespace)| (prefix.clone(), namespace.clone())));
+
+        Parser::new(tokenize(query)?, prefixes).parse_select()
+    }
+
+    /// Result columns in SELECT clause order; for `SELECT *`, the order in
+    /// which variables first appear in the pattern
+    pub fn variables(&self) -> Vec<String> {
+        match &self.projection {
+            Some(items) => items.iter()
+                .map(|item| match item {
+                    SelectItem::Var(var) => var.clone(),
+                    SelectItem::Aggregate { alias, .. } => alias.clone(),
+                })
+                .collect(),
+            None if self.is_aggregate() => self.group_by.clone(),
+            None => {
+                let mut variables = Vec::new();
+                self.pattern.collect_variables(&mut variables);
+                variables
+            }
+        }
+    }
+
+    pub fn execute(&self, triples: &[RdfTriple]) -> Vec<Solution> {
+        // Without limits evaluation cannot fail
+        self.execute_with_limits(triples, QueryLimits::default()).unwrap_or_default()
+    }
+
+    pub fn execute_with_limits(&self, triples: &[RdfTriple], limits: QueryLimits) -> Result<Vec<Solution>> {
+        self.run(Store { triples, index: None }, limits)
+    }
+
+    /// Like `execute_with_limits`, looking up triple patterns with bound terms
+    /// in `index`, which must index exactly `triples`
+    pub fn execute_indexed(&self, triples: &[RdfTriple], index: &TripleIndex, limits: QueryLimits) -> Result<Vec<Solution>> {
+        self.run(Store { triples, index: Some(index) }, limits)
+    }
+
+    fn run(&self, store: Store, limits: QueryLimits) -> Result<Vec<Solution>> {
+        let guard = Guard::new(limits);
+        let mut solutions = self.pattern.evaluate(store, vec![Solution::new()], &guard)?;
+
+        let aggregate = self.is_aggregate();
+        if aggregate {
+            solutions = self.aggregate(solutions);
+        }
+
+        // Sort before projecting so keys may use variables that are not selected
+        if !self.order_by.is_empty() {
+            solutions.sort_by(|a, b| self.compare(a, b));
+        }
+
+        if let (Some(items), false) = (&self.projection, aggregate) {
+            for solution in &mut solutions {
+                solution.retain(|var, _| items.iter().any(|item| matches!(item, SelectItem::Var(v) if v == var)));
+            }
+        }
+
+        if self.distinct {
+            let mut seen = HashSet::new();
+            solutions.retain(|solution| {
+                let mut key: Vec<(String, String)> = solution.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+                key.sort();
+                seen.insert(key)
+            });
+        }
+
+        let limit = self.limit.unwrap_or(usize::MAX);
+        Ok(solutions.into_iter().skip(self.offset).take(limit).collect())
+    }
+
+    /// Order two solutions by the ORDER BY keys; unbound values sort first
+    fn compare(&self, a: &Solution, b: &Solution) -> Ordering {
+        for key in &self.order_by {
+            let ordering = match (key.expression.evaluate(a), key.expression.evaluate(b)) {
+                (Some(x), Some(y)) => x.compare(&y),
+                (None, Some(_)) => Ordering::Less,
+                (Some(_), None) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            };
+            let ordering = if key.descending { ordering.reverse() } else { ordering };
+            if ordering.is_ne() {
+                return ordering;
+            }
+        }
+        Ordering::Equal
+    }
+
+    fn is_aggregate(&self) -> bool {
+        !self.group_by.is_empty()
+            || self.projection.iter().flatten().any(|item| matches!(item, SelectItem::Aggregate { .. }))
+    }
+
+    fn aggregate(&self, solutions: Vec<Solution>) -> Vec<Solution> {
+        // Groups keep the order in which their key was first seen
+        let mut index: HashMap<Vec<Option<String>>, usize> = HashMap::new();
+        let mut groups: Vec<(Vec<Option<String>>, Vec<Solution>)> = Vec::new();
+
+        for solution in solutions {
+            let key: Vec<Option<String>> = self.group_by.iter().map(|var| solution.get(var).cloned()).collect();
+            match index.get(&key) {
+                Some(&i) => groups[i].1.push(solution),
+                None => {
+                    index.insert(key.clone(), groups.len());
+                    groups.push((key, vec![solution]));
+                }
+            }
+        }
+
+        // Aggregates without GROUP BY still produce one row, e.g. COUNT(*) = 0
+        if groups.is_empty() && self.group_by.is_empty() {
+            groups.push((Vec::new(), Vec::new()));
+        }
+
+        let items = self.projection.clone()
+            .unwrap_or_else(|| self.group_by.iter().cloned().map(SelectItem::Var).collect());
+
+        groups.into_iter()
+            .map(|(key, rows)| {
+                let mut row = Solution::new();
+                for item in &items {
+                    match item {
+                        SelectItem::Var(var) => {
+                            let value = match self.group_by.iter().position(|v| v == var) {
+                                Some(i) => key[i].clone(),
+                                // Not grouped on: take any value, like SAMPLE()
+                                None => rows.iter().find_map(|r| r.get(var).cloned()),
+                            };
+                            if let Some(value) = value {
+                                row.insert(var.clone(), value);
+                            }
+                        }
+                        SelectItem::Aggregate { function, distinct, argument, alias } => {
+                            if let Some(value) = function.apply(&rows, argument.as_deref(), *distinct) {
+                                row.insert(alias.clone(), value);
+                            }
+                        }
+                    }
+                }
+                row
+            })
+            .collect()
+    }
+}
+
+impl AggregateFunction {
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_uppercase().as_str() {
+            "COUNT" => Some(Self::Count),
+            "SUM" => Some(Self::Sum),
+            "AVG" => Some(Self::Avg),
+            "MIN" => Some(Self::Min),
+            "MAX" => Some(Self::Max),
+            _ => None,
+        }
+    }
+
+    fn apply(self, rows: &[Solution], argument: Option<&str>, distinct: bool) -> Option<String> {
+        let mut values: Vec<&str> = match argument {
+            Some(var) => rows.iter().filter_map(|row| row.get(var).map(String::as_str)).collect(),
+            None => {
+                let count = if distinct {
+                    let mut seen: Vec<Vec<(&String, &String)>> = rows.iter()
+                        .map(|row| {
+                            let mut pairs: Vec<_> = row.iter().collect();
+                            pairs.sort();
+                            pairs
+                        })
+                        .collect();
+                    seen.sort();
+                    seen.dedup();
+                    seen.len()
+                } else {
+                    rows.len()
+                };
+                return Some(count.to_string());
+            }
+        };
+
+        if distinct {
+            let mut seen = HashSet::new();
+            values.retain(|value| seen.insert(*value));
+        }
+
+        let numbers: Vec<f64> = values.iter().filter_map(|value| value.trim().parse::<f64>().ok()).collect();
+
+        match self {
+            Self::Count => Some(values.len().to_string()),
+            Self::Sum => Some(format_number(numbers.iter().sum())),
+            Self::Avg if numbers.is_empty() => Some("0".to_string()),
+            Self::Avg => Some(format_number(numbers.iter().sum::<f64>() / numbers.len() as f64)),
+            Self::Min | Self::Max => {
+                // Compare numerically when every value is a number, lexically otherwise
+                let numeric = numbers.len() == values.len();
+                let ordering = |a: &&str, b: &&str| if numeric {
+                    let (a, b) = (a.trim().parse::<f64>().unwrap_or(0.0), b.trim().parse::<f64>().unwrap_or(0.0));
+                    a.total_cmp(&b)
+                } else {
+                    a.cmp(b)
+                };
+                let value = if self == Self::Min {
+                    values.into_iter().min_by(ordering)
+                } else {
+                    values.into_iter().max_by(ordering)
+                };
+                value.map(str::to_string)
+            }
+        }
+    }
+}
+
+fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{}", value as i64)
+    } else {
+        value.to_string()
+    }
+}
+
+/// Triples a query runs over, with their index when there is one
+#[derive(Clone, Copy)]
+struct Store<'a> {
+    triples: &'a [RdfTriple],
+    index: Option<&'a TripleIndex>,
+}
+
+impl GroupPattern {
+    fn evaluate(&self, store: Store, solutions: Vec<Solution>, guard: &Guard) -> Result<Vec<Solution>> {
+        self.evaluate_in(store, solutions, None, guard)
+    }
+
+    fn collect_variables(&self, variables: &mut Vec<String>) {
+        let mut add = |term: &Term| {
+            if let Term::Var(var) = term {
+                if !variables.contains(var) {
+                    variables.push(var.clone());
+                }
+            }
+        };
+        for pattern in &self.triples {
+            add(&pattern.subject);
+            add(&pattern.predicate);
+            add(&pattern.object);
+        }
+        for (name, _) in &self.graphs {
+            add(name);
+        }
+        for (_, group) in &self.graphs {
+            group.collect_variables(variables);
+        }
+        for optional in &self.optionals {
+            optional.collect_variables(variables);
+        }
+    }
+
+    /// Evaluate with triple patterns restricted to the named graph `graph`
+    fn evaluate_in(
+        &self,
+        store: Store,
+        mut solutions: Vec<Solution>,
+        graph: Option<&Term>,
+        guard: &Guard,
+    ) -> Result<Vec<Solution>> {
+        for pattern in &self.triples {
+            let mut extended = Vec::new();
+            for solution in &solutions {
+                match pattern.candidates(store, solution) {
+                    Some(positions) => extended.extend(
+                        positions.into_iter().filter_map(|i| pattern.bind(&store.triples[i], graph, solution))
+                    ),
+                    None => extended.extend(store.triples.iter().filter_map(|triple| pattern.bind(triple, graph, solution))),
+                }
+                guard.check(extended.len())?;
+            }
+            solutions = extended;
+        }
+
+        for (name, group) in &self.graphs {
+            let mut extended = Vec::new();
+            for solution in solutions {
+                extended.extend(group.evaluate_in(store, vec![solution], Some(name), guard)?);
+                guard.check(extended.len())?;
+            }
+            solutions = extended;
+        }
+
+        for optional in &self.optionals {
+            let mut extended = Vec::new();
+            for solution in solutions {
+                let matches = optional.evaluate_in(store, vec![solution.clone()], graph, guard)?;
+                if matches.is_empty() {
+                    extended.push(solution);
+                } else {
+                    extended.extend(matches);
+                }
+                guard.check(extended.len())?;
+            }
+            solutions = extended;
+        }
+
+        if !self.filters.is_empty() {
+            solutions.retain(|solution| {
+                self.filters.iter().all(|filter| filter.evaluate(solution).and_then(|v| v.as_bool()) == Some(true))
+            });
+        }
+
+        Ok(solutions)
+    }
+}
+
+impl Expression {
+    fn evaluate(&self, solution: &Solution) -> Option<ExprValue> {
+        match self {
+            Expression::Term(term) => match term {
+                Term::Var(var) => solution.get(var).map(|value| ExprValue::Str(value.clone())),
+                Term::Iri(iri) => Some(ExprValue::Str(iri.clone())),
+                Term::Name(name) => Some(ExprValue::Name(name.clone())),
+                Term::Literal(literal) => Some(ExprValue::Str(literal.clone())),
+            },
+            Expression::Not(inner) => Some(ExprValue::Bool(!inner.evaluate(solution)?.as_bool()?)),
+            // Logical operators follow SPARQL: an error on one side is
+            // masked when the other side decides the result
+            Expression::And(left, right) => {
+                let left = left.evaluate(solution).and_then(|v| v.as_bool());
+                let right = right.evaluate(solution).and_then(|v| v.as_bool());
+                match (left, right) {
+                    (Some(false), _) | (_, Some(false)) => Some(ExprValue::Bool(false)),
+                    (Some(true), Some(true)) => Some(ExprValue::Bool(true)),
+                    _ => None,
+                }
+            }
+            Expression::Or(left, right) => {
+                let left = left.evaluate(solution).and_then(|v| v.as_bool());
+                let right = right.evaluate(solution).and_then(|v| v.as_bool());
+                match (left, right) {
+                    (Some(true), _) | (_, Some(true)) => Some(ExprValue::Bool(true)),
+                    (Some(false), Some(false)) => Some(ExprValue::Bool(false)),
+                    _ => None,
+                }
+            }
+            Expression::Compare(op, left, right) => {
+                let ordering = left.evaluate(solution)?.compare(&right.evaluate(solution)?);
+                Some(ExprValue::Bool(match op {
+                    CompareOp::Eq => ordering.is_eq(),
+                    CompareOp::Ne => ordering.is_ne(),
+                    CompareOp::Lt => ordering.is_lt(),
+                    CompareOp::Le => ordering.is_le(),
+                    CompareOp::Gt => ordering.is_gt(),
+                    CompareOp::Ge => ordering.is_ge(),
+                }))
+            }
+            Expression::Call(Function::Bound, args) => match args.first() {
+                Some(Expression::Term(Term::Var(var))) => Some(ExprValue::Bool(solution.contains_key(var))),
+                _ => None,
+            },
+            Expression::Call(function, args) => {
+                let args = args.iter()
+                    .map(|arg| arg.evaluate(solution).map(|v| v.into_string()))
+                    .collect::<Option<Vec<String>>>()?;
+                function.apply(&args)
+            }
+        }
+    }
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_uppercase().as_str() {
+            "REGEX" => Some(Self::Regex),
+            "CONTAINS" => Some(Self::Contains),
+            "STRSTARTS" => Some(Self::StrStarts),
+            "STRENDS" => Some(Self::StrEnds),
+            "LCASE" => Some(Self::LCase),
+            "UCASE" => Some(Self::UCase),
+            "STR" => Some(Self::Str),
+            "BOUND" => Some(Self::Bound),
+            _ => None,
+        }
+    }
+
+    fn arity(self) -> std::ops::RangeInclusive<usize> {
+        match self {
+            Self::Regex => 2..=3,
+            Self::Contains | Self::StrStarts | Self::StrEnds => 2..=2,
+            Self::LCase | Self::UCase | Self::Str | Self::Bound => 1..=1,
+        }
+    }
+
+    fn apply(self, args: &[String]) -> Option<ExprValue> {
+        let value = match self {
+            Self::Regex => {
+                let flags = args.get(2).map(String::as_str).unwrap_or("");
+                let pattern = if flags.is_empty() { args[1].clone() } else { format!("(?{}){}", flags, args[1]) };
+                ExprValue::Bool(regex::Regex::new(&pattern).ok()?.is_match(&args[0]))
+            }
+            Self::Contains => ExprValue::Bool(args[0].contains(args[1].as_str())),
+            Self::StrStarts => ExprValue::Bool(args[0].starts_with(args[1].as_str())),
+            Self::StrEnds => ExprValue::Bool(args[0].ends_with(args[1].as_str())),
+            Self::LCase => ExprValue::Str(args[0].to_lowercase()),
+            Self::UCase => ExprValue::Str(args[0].to_uppercase()),
+            Self::Str => ExprValue::Str(args[0].clone()),
+            Self::Bound => return None,
+        };
+        Some(value)
+    }
+}
+
+impl ExprValue {
+    /// Effective boolean value
+    fn as_bool(&self) -> Option<bool> {
+        match self {
+            ExprValue::Bool(b) => Some(*b),
+            ExprValue::Str(s) => match s.as_str() {
+                "true" => Some(true),
+                "false" => Some(false),
+                s => Some(s.parse::<f64>().map(|n| n != 0.0 && !n.is_nan()).unwrap_or(!s.is_empty())),
+            },
+            ExprValue::Name(_) => None,
+        }
+    }
+
+    fn into_string(self) -> String {
+        match self {
+            ExprValue::Bool(b) => b.to_string(),
+            ExprValue::Str(s) | ExprValue::Name(s) => s,
+        }
+    }
+
+    /// Numbers compare numerically, names by local name, everything else as strings
+    fn compare(&self, other: &ExprValue) -> Ordering {
+        match (self, other) {
+            (ExprValue::Name(a), ExprValue::Name(b))
+            | (ExprValue::Name(a), ExprValue::Str(b))
+            | (ExprValue::Str(a), ExprValue::Name(b)) => local_name(a).cmp(local_name(b)),
+            _ => {
+                let (a, b) = (self.clone().into_string(), other.clone().into_string());
+                match (a.trim().parse::<f64>(), b.trim().parse::<f64>()) {
+                    (Ok(x), Ok(y)) => x.total_cmp(&y),
+                    _ => a.cmp(&b),
+                }
+            }
+        }
+    }
+}
+
+impl TriplePattern {
+    /// Positions of the triples that can match under `solution`, from the
+    /// store's index; `None` when every triple has to be tried
+    fn candidates(&self, store: Store, solution: &Solution) -> Option<Vec<usize>> {
+        store.index?.matching(
+            self.subject.value(solution),
+            self.predicate.value(solution),
+            self.object.value(solution),
+        )
+    }
+
+    fn bind(&self, triple: &RdfTriple, graph: Option<&Term>, solution: &Solution) -> Option<Solution> {
+        let mut extended = solution.clone();
+        let in_graph = match (graph, &triple.graph) {
+            (None, _) => true,
+            (Some(term), Some(name)) => term.bind(name, &mut extended),
+            (Some(_), None) => false,
+        };
+        let matched = in_graph
+            && self.subject.bind(&triple.subject, &mut extended)
+            && self.predicate.bind(&triple.predicate, &mut extended)
+            && self.object.bind(&triple.object, &mut extended);
+        matched.then_some(extended)
+    }
+}
+
+impl Term {
+    /// The exact value this term matches under `solution`, if it has one
+    fn value<'a>(&'a self, solution: &'a Solution) -> Option<&'a str> {
+        match self {
+            Term::Var(var) => solution.get(var).map(String::as_str),
+            Term::Iri(value) | Term::Literal(value) => Some(value),
+            Term::Name(_) => None,
+        }
+    }
+
+    fn bind(&self, value: &str, solution: &mut Solution) -> bool {
+        match self {
+            Term::Var(var) => match solution.get(var) {
+                Some(bound) => bound == value,
+                None => {
+                    solution.insert(var.clone(), value.to_string());
+                    true
+                }
+            },
+            Term::Iri(iri) => value == iri,
+            Term::Name(name) => local_name(value) == local_name(name),
+            Term::Literal(literal) => value == literal,
+        }
+    }
+}
+
+fn local_name(value: &str) -> &str {
+    value.rsplit(['/', '#', ':']).next().unwrap_or(value)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Iri(String),
+    Literal(String),
+    Var(String),
+    Word(String),
+    Punct(char),
+}
+
+fn tokenize(query: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = query.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '#' => {
+                // Comment to end of line
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '<' if !starts_iri(chars.clone()) => {
+                chars.next();
+                tokens.push(Token::Punct('<'));
+            }
+            '<' => {
+                chars.next();
+                let mut iri = String::new();
+                loop {
+                    match chars.next() {
+                        Some('>') => break,
+                        Some(c) => iri.push(c),
+                        None => return Err(Error::query("Unterminated IRI in query")),
+                    }
+                }
+                tokens.push(Token::Iri(iri));
+            }
+            '"' | '\'' => {
+                chars.next();
+                let mut literal = String::new();
+                loop {
+                    match chars.next() {
+                        Some('\\') => literal.extend(chars.next()),
+                        Some(q) if q == c => break,
+                        Some(c) => literal.push(c),
+                        None => return Err(Error::query("Unterminated string literal in query")),
+                    }
+                }
+                // Language tags and datatypes are not tracked; skip them
+                if chars.peek() == Some(&'@') {
+                    chars.next();
+                    while chars.next_if(|c| c.is_alphanumeric() || *c == '-').is_some() {}
+                } else if chars.peek() == Some(&'^') {
+                    chars.next();
+                    chars.next_if_eq(&'^');
+                    match chars.peek() {
+                        Some('<') => {
+                            for c in chars.by_ref() {
+                                if c == '>' {
+                                    break;
+                                }
+                            }
+                        }
+                        _ => while chars.next_if(|c| is_word_char(*c)).is_some() {},
+                    }
+                }
+                tokens.push(Token::Literal(literal));
+            }
+            '?' | '$' => {
+                chars.next();
+                let mut name = String::new();
+                while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_') {
+                    name.push(c);
+                }
+                if name.is_empty() {
+                    return Err(Error::query("Empty variable name in query"));
+                }
+                tokens.push(Token::Var(name));
+            }
+            c if is_word_char(c) && c != '.' => {
+                let mut word = String::new();
+                while let Some(c) = chars.next_if(|c| is_word_char(*c)) {
+                    word.push(c);
+                }
+                // A trailing '.' ends the triple rather than the name
+                let mut dots = 0;
+                while word.ends_with('.') && !word.chars().all(|c| c.is_ascii_digit() || c == '.') {
+                    word.pop();
+                    dots += 1;
+                }
+                tokens.push(Token::Word(word));
+                tokens.extend(std::iter::repeat_n(Token::Punct('.'), dots));
+            }
+            _ => {
+                chars.next();
+                tokens.push(Token::Punct(c));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Whether the '<' at the front of `chars` opens an IRI rather than being
+/// the less-than operator: IRIs contain no whitespace before the closing '>'
+fn starts_iri(mut chars: std::iter::Peekable<std::str::Chars>) -> bool {
+    chars.next();
+    match chars.peek() {
+        Some('=') | None => false,
+        _ => chars.take_while(|c| !c.is_whitespace()).any(|c| c == '>'),
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | ':' | '-' | '.')
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    prefixes: HashMap<String, String>,
+}
+
+impl Parser {
+    fn new(tokens: Vec<Token>, prefixes: HashMap<String, String>) -> Self {
+        Self { tokens, position: 0, prefixes }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.peek_keyword(keyword);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn eat_punct(&mut self, punct: char) -> bool {
+        let found = self.peek() == Some(&Token::Punct(punct));
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<()> {
+        if self.eat_keyword(keyword) {
+            Ok(())
+        } else {
+            Err(self.unexpected(keyword))
+        }
+    }
+
+    fn expect_punct(&mut self, punct: char) -> Result<()> {
+        if self.eat_punct(punct) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("'{}'", punct)))
+        }
+    }
+
+    fn expect_var(&mut self) -> Result<String> {
+        match self.next() {
+            Some(Token::Var(var)) => Ok(var),
+            _ => {
+                self.position -= 1;
+                Err(self.unexpected("a variable"))
+            }
+        }
+    }
+
+    fn unexpected(&self, expected: &str) -> Error {
+        match self.peek() {
+            Some(token) => Error::query(format!("Unsupported query: expected {} but found {:?}", expected, token)),
+            None => Error::query(format!("Unsupported query: expected {} but the query ended", expected)),
+        }
+    }
+
+    fn parse_select(&mut self) -> Result<SelectQuery> {
+        loop {
+            if self.eat_keyword("PREFIX") {
+                match (self.next(), self.next()) {
+                    (Some(Token::Word(prefix)), Some(Token::Iri(namespace))) if prefix.ends_with(':') => {
+                        self.prefixes.insert(prefix.trim_end_matches(':').to_string(), namespace);
+                    }
+                    _ => {
+                        self.position -= 2;
+                        return Err(self.unexpected("a prefix declaration"));
+                    }
+                }
+            } else if self.eat_keyword("BASE") {
+                self.next();
+            } else {
+                break;
+            }
+        }
+
+        self.expect_keyword("SELECT")?;
+        let distinct = self.eat_keyword("DISTINCT") || self.eat_keyword("REDUCED");
+        let projection = if self.eat_punct('*') {
+            None
+        } else {
+            let mut items = Vec::new();
+            loop {
+                match self.peek() {
+                    Some(Token::Var(_)) => items.push(SelectItem::Var(self.expect_var()?)),
+                    Some(Token::Punct('(')) => {
+                        self.position += 1;
+                        items.push(self.parse_aggregate()?);
+                    }
+                    _ => break,
+                }
+            }
+            if items.is_empty() {
+                return Err(self.unexpected("a variable or '*'"));
+            }
+            Some(items)
+        };
+
+        self.eat_keyword("WHERE");
+        let pattern = self.parse_group()?;
+
+        let mut query = SelectQuery {
+            distinct,
+            projection,
+            pattern,
+            group_by: Vec::new(),
+            order_by: Vec::new(),
+            limit: None,
+            offset: 0,
+        };
+        while self.peek().is_some() {
+            if self.eat_keyword("GROUP") {
+                self.expect_keyword("BY")?;
+                while let Some(Token::Var(_)) = self.peek() {
+                    query.group_by.push(self.expect_var()?);
+                }
+                if query.group_by.is_empty() {
+                    return Err(self.unexpected("a variable"));
+                }
+            } else if self.eat_keyword("ORDER") {
+                self.expect_keyword("BY")?;
+                while let Some(key) = self.parse_order_key()? {
+                    query.order_by.push(key);
+                }
+                if query.order_by.is_empty() {
+                    return Err(self.unexpected("an ordering key"));
+                }
+            } else if self.eat_keyword("LIMIT") {
+                query.limit = Some(self.parse_count()?);
+            } else if self.eat_keyword("OFFSET") {
+                query.offset = self.parse_count()?;
+            } else {
+                return Err(self.unexpected("GROUP BY, ORDER BY, LIMIT or OFFSET"));
+            }
+        }
+
+        Ok(query)
+    }
+
+    /// `?var`, `ASC(expr)`, `DESC(expr)` or a parenthesized expression
+    fn parse_order_key(&mut self) -> Result<Option<OrderKey>> {
+        let descending = if self.eat_keyword("DESC") {
+            true
+        } else if self.eat_keyword("ASC") {
+            false
+        } else {
+            return match self.peek() {
+                Some(Token::Var(_)) | Some(Token::Punct('(')) => {
+                    Ok(Some(OrderKey { expression: self.parse_primary()?, descending: false }))
+                }
+                _ => Ok(None),
+            };
+        };
+
+        self.expect_punct('(')?;
+        let expression = self.parse_expression()?;
+        self.expect_punct(')')?;
+        Ok(Some(OrderKey { expression, descending }))
+    }
+
+    /// `(FUNC([DISTINCT] ?var|*) AS ?alias)`, after the opening parenthesis
+    fn parse_aggregate(&mut self) -> Result<SelectItem> {
+        let function = match self.next() {
+            Some(Token::Word(name)) => AggregateFunction::from_name(&name)
+                .ok_or_else(|| Error::query(format!("Unsupported query: unknown aggregate function {}", name)))?,
+            _ => {
+                self.position -= 1;
+                return Err(self.unexpected("an aggregate function"));
+            }
+        };
+
+        self.expect_punct('(')?;
+        let distinct = self.eat_keyword("DISTINCT");
+        let argument = if function == AggregateFunction::Count && self.eat_punct('*') {
+            None
+        } else {
+            Some(self.expect_var()?)
+        };
+        self.expect_punct(')')?;
+        self.expect_keyword("AS")?;
+        let alias = self.expect_var()?;
+        self.expect_punct(')')?;
+
+        Ok(SelectItem::Aggregate { function, distinct, argument, alias })
+    }
+
+    fn parse_count(&mut self) -> Result<usize> {
+        match self.next() {
+            Some(Token::Word(word)) => word.parse()
+                .map_err(|_| Error::query(format!("Unsupported query: invalid number {}", word))),
+            _ => {
+                self.position -= 1;
+                Err(self.unexpected("a number"))
+            }
+        }
+    }
+
+    fn parse_group(&mut self) -> Result<GroupPattern> {
+        self.expect_punct('{')?;
+        let mut group = GroupPattern::default();
+
+        loop {
+            if self.eat_punct('}') {
+                return Ok(group);
+            }
+            if self.eat_punct('.') {
+                continue;
+            }
+            if self.eat_keyword("OPTIONAL") {
+                group.optionals.push(self.parse_group()?);
+                continue;
+            }
+            if self.eat_keyword("GRAPH") {
+                let name = self.parse_term()?;
+                group.graphs.push((name, self.parse_group()?));
+                continue;
+            }
+            if self.eat_keyword("FILTER") {
+                group.filters.push(self.parse_primary()?);
+                continue;
+            }
+
+            let subject = self.parse_term()?;
+            loop {
+                let predicate = self.parse_term()?;
+                loop {
+                    let object = self.parse_term()?;
+                    group.triples.push(TriplePattern {
+                        subject: subject.clone(),
+                        predicate: predicate.clone(),
+                        object,
+                    });
+                    if !self.eat_punct(',') {
+                        break;
+                    }
+                }
+                if !self.eat_punct(';') {
+                    break;
+                }
+                // Allow a trailing ';' before the end of the block
+                if matches!(self.peek(), Some(Token::Punct('.' | '}'))) {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Expand a prefixed name with a known prefix to a full URI
+    fn expand(&self, name: String) -> Term {
+        match name.split_once(':') {
+            Some((prefix, local)) => match self.prefixes.get(prefix) {
+                Some(namespace) => Term::Iri(format!("{}{}", namespace, local)),
+                None => Term::Name(name),
+            },
+            None => Term::Name(name),
+        }
+    }
+
+    fn parse_term(&mut self) -> Result<Term> {
+        match self.next() {
+            Some(Token::Var(var)) => Ok(Term::Var(var)),
+            Some(Token::Iri(iri)) => Ok(Term::Iri(iri)),
+            Some(Token::Literal(literal)) => Ok(Term::Literal(literal)),
+            Some(Token::Word(word)) if word == "a" => Ok(Term::Name("rdf:type".to_string())),
+            Some(Token::Word(word))
+                if word.parse::<f64>().is_ok() || word == "true" || word == "false" => Ok(Term::Literal(word)),
+            Some(Token::Word(word)) if !is_keyword(&word) => Ok(self.expand(word)),
+            _ => {
+                self.position -= 1;
+                Err(self.unexpected("a term"))
+            }
+        }
+    }
+}
+
+impl Parser {
+    fn parse_expression(&mut self) -> Result<Expression> {
+        let mut left = self.parse_and()?;
+        while self.eat_pair('|', '|') {
+            left = Expression::Or(Box::new(left), Box::new(self.parse_and()?));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Expression> {
+        let mut left = self.parse_unary()?;
+        while self.eat_pair('&', '&') {
+            left = Expression::And(Box::new(left), Box::new(self.parse_unary()?));
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expression> {
+        if self.peek() == Some(&Token::Punct('!')) && self.tokens.get(self.position + 1) != Some(&Token::Punct('=')) {
+            self.position += 1;
+            return Ok(Expression::Not(Box::new(self.parse_unary()?)));
+        }
+
+        let left = self.parse_primary()?;
+        let op = if self.eat_pair('!', '=') {
+            CompareOp::Ne
+        } else if self.eat_pair('<', '=') {
+            CompareOp::Le
+        } else if self.eat_pair('>', '=') {
+            CompareOp::Ge
+        } else if self.eat_punct('=') {
+            CompareOp::Eq
+        } else if self.eat_punct('<') {
+            CompareOp::Lt
+        } else if self.eat_punct('>') {
+            CompareOp::Gt
+        } else {
+            return Ok(left);
+        };
+
+        let right = self.parse_primary()?;
+        Ok(Expression::Compare(op, Box::new(left), Box::new(right)))
+    }
+
+    /// Parenthesized expression, function call or term
+    fn parse_primary(&mut self) -> Result<Expression> {
+        if self.eat_punct('(') {
+            let expression = self.parse_expression()?;
+            self.expect_punct(')')?;
+            return Ok(expression);
+        }
+
+        if let (Some(Token::Word(name)), Some(Token::Punct('('))) = (self.peek(), self.tokens.get(self.position + 1)) {
+            let function = Function::from_name(name)
+                .ok_or_else(|| Error::query(format!("Unsupported query: unknown function {}", name)))?;
+            self.position += 2;
+
+            let mut args = Vec::new();
+            if !self.eat_punct(')') {
+                loop {
+                    args.push(self.parse_expression()?);
+                    if self.eat_punct(')') {
+                        break;
+                    }
+                    self.expect_punct(',')?;
+                }
+            }
+
+            if !function.arity().contains(&args.len()) {
+                return Err(Error::query(format!(
+                    "Unsupported query: {:?} takes {:?} arguments, got {}",
+                    function,
+                    function.arity(),
+                    args.len()
+                )));
+            }
+            return Ok(Expression::Call(function, args));
+        }
+
+        Ok(Expression::Term(self.parse_term()?))
+    }
+
+    fn eat_pair(&mut self, first: char, second: char) -> bool {
+        let found = self.peek() == Some(&Token::Punct(first))
+            && self.tokens.get(self.position + 1) == Some(&Token::Punct(second));
+        if found {
+            self.position += 2;
+        }
+        found
+    }
+}
+
+fn is_keyword(word: &str) -> bool {
+    ["FILTER", "OPTIONAL", "UNION", "MINUS", "GRAPH", "BIND", "VALUES", "SERVICE"]
+        .iter()
+        .any(|keyword| word.eq_ignore_ascii_case(keyword))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn triple(s: &str, p: &str, o: &str) -> RdfTriple {
+        RdfTriple::new(s.to_string(), p.to_string(), o.to_string())
+    }
+
+    #[test]
+    fn test_prefix_expansion() {
+        let mut triples = sample();
+        triples.push(triple("http://ex.org/acme", "http://other.org/vocab#hasIndustry", "Mining"));
+
+        let run = |query: &str, namespaces: &HashMap<String, String>| {
+            SelectQuery::parse_with_namespaces(query, namespaces).unwrap().execute(&triples)
+        };
+        let none = HashMap::new();
+
+        // Declared and default prefixes match exactly; unknown prefixes fall back to local names
+        assert_eq!(run("PREFIX o: <http://other.org/vocab#> SELECT ?i WHERE { ?c o:hasIndustry ?i }", &none).len(), 1);
+        let schema = HashMap::from([("ex".to_string(), "http://ex.org/ns#".to_string())]);
+        assert_eq!(run("SELECT ?i WHERE { ?c ex:hasIndustry ?i }", &schema).len(), 3);
+        assert_eq!(run("SELECT ?i WHERE { ?c biz:hasIndustry ?i }", &none).len(), 4);
+
+        let overridden = "PREFIX ex: <http://other.org/vocab#> SELECT ?i WHERE { ?c ex:hasIndustry ?i }";
+        assert_eq!(run(overridden, &schema)[0]["i"], "Mining");
+    }
+
+    #[test]
+    fn test_graph_patterns() {
+        let triples = vec![
+            triple("http://ex.org/acme", "http://ex.org/ns#hasName", "Acme"),
+            triple("http://ex.org/acme", "http://ex.org/ns#hasCeo", "http://ex.org/jane")
+                .with_graph("http://ex.org/graphs/report".to_string()),
+        ];
+        let run = |query: &str| SelectQuery::parse(query).unwrap().execute(&triples);
+
+        assert_eq!(run("SELECT * WHERE { ?s ?p ?o }").len(), 2);
+
+        let results = run("SELECT ?g ?ceo WHERE { GRAPH ?g { ?c hasCeo ?ceo } ?c hasName ?name }");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0]["g"], "http://ex.org/graphs/report");
+
+        assert!(run("SELECT ?p WHERE { GRAPH <http://ex.org/graphs/other> { ?s ?p ?o } }").is_empty());
+    }
+
+    fn sample() -> Vec<RdfTriple> {
+        vec![
+            triple("http://ex.org/acme", "http://ex.org/ns#hasIndustry", "Tech"),
+            triple("http://ex.org/acme", "http://ex.org/ns#hasRevenue", "100"),
+            triple("http://ex.org/globex", "http://ex.org/ns#hasIndustry", "Tech"),
+            triple("http://ex.org/globex", "http://ex.org/ns#hasRevenue", "50"),
+            triple("http://ex.org/initech", "http://ex.org/ns#hasIndustry", "Retail"),
+            triple("http://ex.org/initech", "http://ex.org/ns#hasName", "Initech"),
+        ]
+    }
+
+    #[test]
+    fn test_basic_graph_pattern() {
+        let query = SelectQuery::parse(r#"
+            PREFIX ex: <http://ex.org/ns#>
+            SELECT ?company ?revenue WHERE {
+                ?company ex:hasIndustry "Tech" ;
+                         ex:hasRevenue ?revenue .
+                OPTIONAL { ?company ex:hasName ?name }
+            }"#).unwrap();
+
+        let results = query.execute(&sample());
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0]["company"], "http://ex.org/acme");
+        assert!(!results[0].contains_key("name"));
+    }
+
+    #[test]
+    fn test_filters() {
+        let run = |query: &str| SelectQuery::parse(query).unwrap().execute(&sample());
+
+        let results = run("SELECT ?c WHERE { ?c hasRevenue ?r . FILTER(?r > 60 || ?r < 10) }");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0]["c"], "http://ex.org/acme");
+
+        let results = run(r#"SELECT ?c WHERE { ?c hasIndustry ?i FILTER (regex(?i, "^tech", "i") && !CONTAINS(STR(?c), "globex")) }"#);
+        assert_eq!(results.len(), 1);
+
+        let results = run("SELECT ?c ?p WHERE { ?c ?p ?o . FILTER(?p = ex:hasName) }");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0]["c"], "http://ex.org/initech");
+
+        let results = run("SELECT ?c WHERE { ?c hasIndustry ?i OPTIONAL { ?c hasName ?n } FILTER(!bound(?n)) }");
+        assert_eq!(results.len(), 2);
+
+        assert!(SelectQuery::parse("SELECT ?c WHERE { ?c ?p ?o FILTER(unknown(?o)) }").is_err());
+    }
+
+    #[test]
+    fn test_distinct_and_order_by() {
+        let run = |query: &str| SelectQuery::parse(query).unwrap().execute(&sample());
+
+        let industries: Vec<String> = run("SELECT DISTINCT ?i WHERE { ?c hasIndustry ?i } ORDER BY ?i")
+            .into_iter()
+            .map(|row| row["i"].clone())
+            .collect();
+        assert_eq!(industries, ["Retail", "Tech"]);
+
+        let results = run("SELECT ?c WHERE { ?c hasIndustry ?i OPTIONAL { ?c hasRevenue ?r } } ORDER BY DESC(?i) ASC(?r)");
+        let companies: Vec<&str> = results.iter().map(|row| row["c"].as_str()).collect();
+        assert_eq!(companies, ["http://ex.org/globex", "http://ex.org/acme", "http://ex.org/initech"]);
+        assert!(!results[0].contains_key("r"));
+    }
+
+    #[test]
+    fn test_query_limits() {
+        let query = SelectQuery::parse("SELECT * WHERE { ?a ?p ?b . ?c ?q ?d }").unwrap();
+        let limits = QueryLimits { timeout: None, max_results: Some(10) };
+        assert!(query.execute_with_limits(&sample(), limits).is_err());
+
+        let limits = QueryLimits { timeout: Some(Duration::ZERO), max_results: None };
+        assert!(query.execute_with_limits(&sample(), limits).is_err());
+        assert!(query.execute_with_limits(&sample(), QueryLimits::default()).is_ok());
+    }
+
+    #[test]
+    fn test_variable_order() {
+        let vars = |query: &str| SelectQuery::parse(query).unwrap().variables();
+        assert_eq!(vars("SELECT ?z ?a ?m WHERE { ?a ?m ?z }"), ["z", "a", "m"]);
+        assert_eq!(vars("SELECT * WHERE { ?c hasName ?n OPTIONAL { ?c hasIndustry ?i } }"), ["c", "n", "i"]);
+        assert_eq!(vars("SELECT ?i (COUNT(?c) AS ?n) WHERE { ?c hasIndustry ?i } GROUP BY ?i"), ["i", "n"]);
+    }
+
+    #[test]
+    fn test_aggregates_with_group_by() {
+        let query = SelectQuery::parse(r#"
+            SELECT ?industry (COUNT(?company) AS ?companies) (SUM(?revenue) AS ?total)
+                   (AVG(?revenue) AS ?average) (MAX(?revenue) AS ?largest)
+            WHERE {
+                ?company hasIndustry ?industry .
+                OPTIONAL { ?company hasRevenue ?revenue }
+            }
+            GROUP BY ?industry"#).unwrap();
+
+        let results = query.execute(&sample());
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0]["industry"], "Tech");
+        assert_eq!(results[0]["companies"], "2");
+        assert_eq!(results[0]["total"], "150");
+        assert_eq!(results[0]["average"], "75");
+        assert_eq!(results[0]["largest"], "100");
+        assert_eq!(results[1]["companies"], "1");
+        assert_eq!(results[1]["total"], "0");
+
+        let count = SelectQuery::parse("SELECT (COUNT(*) AS ?n) WHERE { ?s hasMissing ?o }").unwrap();
+        assert_eq!(count.execute(&sample())[0]["n"], "0");
+    }
+}
@@ -0,0 +1,113 @@
+use crate::core::RdfTriple;
+use crate::error::{ErrorContext, Result};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// On-disk encoding of a knowledge graph store
+///
+/// Both hold the same triple records, so converting between them keeps
+/// every triple's graph, confidence, source and metadata.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageFormat {
+    /// Pretty-printed JSON array, readable and diffable
+    #[default]
+    Json,
+    /// Gzip-compressed JSON, typically a tenth of the size
+    Compact,
+}
+
+impl StorageFormat {
+    /// Format of a store's content, recognized by the gzip header
+    pub fn detect(content: &[u8]) -> Self {
+        if content.starts_with(&GZIP_MAGIC) { Self::Compact } else { Self::Json }
+    }
+
+    pub fn encode(&self, triples: &[RdfTriple]) -> Result<Vec<u8>> {
+        match self {
+            Self::Json => serde_json::to_vec_pretty(triples)
+                .storage_context(|| "Failed to serialize knowledge graph"),
+            Self::Compact => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                serde_json::to_writer(&mut encoder, triples)
+                    .storage_context(|| "Failed to serialize knowledge graph")?;
+                encoder.finish().storage_context(|| "Failed to compress knowledge graph")
+            }
+        }
+    }
+
+    pub fn decode(&self, content: &[u8]) -> std::io::Result<Vec<RdfTriple>> {
+        match self {
+            Self::Json => Ok(serde_json::from_slice(content)?),
+            Self::Compact => Ok(serde_json::from_reader(GzDecoder::new(content))?),
+        }
+    }
+}
+
+impl std::fmt::Display for StorageFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Json => write!(f, "json"),
+            Self::Compact => write!(f, "compact"),
+        }
+    }
+}
+
+/// The JSON text of a store in either format; a damaged compact store gives
+/// whatever decompressed before the damage
+pub fn read_json_lossy(content: &[u8]) -> String {
+    match StorageFormat::detect(content) {
+        StorageFormat::Json => String::from_utf8_lossy(content).into_owned(),
+        StorageFormat::Compact => {
+            let mut decoder = GzDecoder::new(content);
+            let mut json = Vec::new();
+            let mut buffer = [0; 8192];
+            while let Ok(read @ 1..) = decoder.read(&mut buffer) {
+                json.write_all(&buffer[..read]).expect("writing to a Vec cannot fail");
+            }
+            String::from_utf8_lossy(&json).into_owned()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RdfSchema;
+    use crate::knowledge_graph::{KnowledgeGraph, KnowledgeGraphBuilder, KnowledgeGraphConfig};
+
+    #[test]
+    fn test_storage_formats() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("kg.db").to_string_lossy().to_string();
+        let mut triple = RdfTriple::new("http://ex.org/s".into(), "http://ex.org/p".into(), "v".into())
+            .with_graph("http://ex.org/g".into());
+        triple.confidence = 0.5;
+        triple.source = Some("report.pdf".into());
+
+        let mut kg = KnowledgeGraphBuilder::new().storage_path(&path).storage_format(StorageFormat::Compact).build().unwrap();
+        kg.add_triples(&[triple]).unwrap();
+        drop(kg);
+        let content = std::fs::read(&path).unwrap();
+        assert_eq!(StorageFormat::detect(&content), StorageFormat::Compact);
+        assert!(read_json_lossy(&content[..content.len() - 8]).contains("report.pdf"));
+
+        // The format of an existing store wins over the configured one
+        let open = || KnowledgeGraph::new(KnowledgeGraphConfig { storage_path: path.clone(), ..Default::default() }, RdfSchema::default()).unwrap();
+        let mut kg = open();
+        assert_eq!(kg.storage_format(), StorageFormat::Compact);
+        kg.set_storage_format(StorageFormat::Json).unwrap();
+        drop(kg);
+
+        assert!(std::fs::read_to_string(&path).unwrap().starts_with('['));
+        let kg = open();
+        let stored = &kg.triples()[0];
+        assert_eq!((stored.graph.as_deref(), stored.confidence, stored.source.as_deref()), (Some("http://ex.org/g"), 0.5, Some("report.pdf")));
+        assert!(stored.metadata.contains_key("added_at"));
+    }
+}
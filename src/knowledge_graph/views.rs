@@ -0,0 +1,152 @@
+use crate::error::{ErrorContext, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use super::{KnowledgeGraph, SimpleSparqlResults};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ViewRefresh {
+    /// Only refreshed with `views refresh`
+    #[default]
+    OnDemand,
+    /// Also refreshed at the end of every extraction run
+    AfterExtraction,
+}
+
+/// A named query whose results are stored so templates can reuse them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaterializedView {
+    pub name: String,
+    pub query: String,
+    #[serde(default)]
+    pub refresh: ViewRefresh,
+    #[serde(default)]
+    pub refreshed_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub results: Option<SimpleSparqlResults>,
+}
+
+/// Materialized views persisted as JSON in `<kg_path>.views.json`
+pub struct ViewStore {
+    path: PathBuf,
+    views: BTreeMap<String, MaterializedView>,
+}
+
+impl ViewStore {
+    pub fn path_for(kg_path: &str) -> PathBuf {
+        PathBuf::from(format!("{}.views.json", kg_path))
+    }
+
+    pub fn open_for(kg_path: &str) -> Result<Self> {
+        Self::open(Self::path_for(kg_path))
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let views = if path.exists() {
+            let content = fs::read_to_string(&path)
+                .storage_context(|| format!("Failed to read views file: {}", path.display()))?;
+            serde_json::from_str(&content)
+                .storage_context(|| format!("Failed to parse views file: {}", path.display()))?
+        } else {
+            BTreeMap::new()
+        };
+
+        Ok(Self { path, views })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let content = serde_json::to_string_pretty(&self.views)
+            .storage_context(|| "Failed to serialize views")?;
+        fs::write(&self.path, content)
+            .storage_context(|| format!("Failed to write views file: {}", self.path.display()))
+    }
+
+    /// Define or redefine a view; previous results are discarded
+    pub fn define(&mut self, name: &str, query: &str, refresh: ViewRefresh) {
+        self.views.insert(name.to_string(), MaterializedView {
+            name: name.to_string(),
+            query: query.to_string(),
+            refresh,
+            refreshed_at: None,
+            results: None,
+        });
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<MaterializedView> {
+        self.views.remove(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&MaterializedView> {
+        self.views.get(name)
+    }
+
+    pub fn list(&self) -> Vec<&MaterializedView> {
+        self.views.values().collect()
+    }
+
+    /// Stored results of a view; errors if the view is unknown or was never refreshed
+    pub fn results(&self, name: &str) -> Result<&SimpleSparqlResults> {
+        let view = self.get(name)
+            .storage_context(|| format!("No materialized view named '{}'", name))?;
+        view.results.as_ref()
+            .storage_context(|| format!("View '{}' has not been refreshed yet", name))
+    }
+
+    pub fn refresh(&mut self, name: &str, kg: &KnowledgeGraph) -> Result<()> {
+        let view = self.views.get_mut(name)
+            .storage_context(|| format!("No materialized view named '{}'", name))?;
+        refresh_view(view, kg)
+    }
+
+    /// Refresh every view, returning how many were refreshed
+    pub fn refresh_all(&mut self, kg: &KnowledgeGraph) -> Result<usize> {
+        for view in self.views.values_mut() {
+            refresh_view(view, kg)?;
+        }
+        Ok(self.views.len())
+    }
+
+    /// Refresh the views marked `after_extraction`
+    pub fn refresh_after_extraction(&mut self, kg: &KnowledgeGraph) -> Result<usize> {
+        let mut refreshed = 0;
+        for view in self.views.values_mut().filter(|v| v.refresh == ViewRefresh::AfterExtraction) {
+            refresh_view(view, kg)?;
+            refreshed += 1;
+        }
+        Ok(refreshed)
+    }
+}
+
+fn refresh_view(view: &mut MaterializedView, kg: &KnowledgeGraph) -> Result<()> {
+    let results = kg.execute_sparql(&view.query)
+        .storage_context(|| format!("Failed to refresh view '{}'", view.name))?;
+    view.results = Some(results);
+    view.refreshed_at = Some(Utc::now());
+    info!("Refreshed materialized view: {}", view.name);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RdfSchema;
+
+    #[test]
+    fn test_refresh_after_extraction() {
+        let kg = KnowledgeGraph::in_memory(RdfSchema::default()).unwrap();
+        let mut store = ViewStore::open(std::env::temp_dir().join("unused.views.json")).unwrap();
+        store.define("manual", "SELECT ?s WHERE { ?s ?p ?o }", ViewRefresh::OnDemand);
+        store.define("auto", "SELECT ?s WHERE { ?s ?p ?o }", ViewRefresh::AfterExtraction);
+
+        assert_eq!(store.refresh_after_extraction(&kg).unwrap(), 1);
+        assert!(store.results("auto").is_ok());
+        assert!(store.results("manual").is_err());
+        assert!(store.results("missing").is_err());
+    }
+}
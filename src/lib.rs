@@ -1,12 +1,18 @@
 pub mod config;
+pub mod error;
 pub mod core;
 pub mod handlers;
 pub mod utils;
 pub mod knowledge_graph;
+pub mod mapping;
 pub mod templates;
+pub mod pipeline;
+pub mod server;
 
 pub use config::Configuration;
-pub use core::{RdfExtractor, ExtractionResult};
-pub use handlers::DocumentProcessor;
-pub use knowledge_graph::KnowledgeGraph;
-pub use templates::TemplateManager;
\ No newline at end of file
+pub use error::{Error, Result};
+pub use core::{RdfExtractor, RdfExtractorBuilder, ExtractionResult};
+pub use handlers::{DocumentHandler, DocumentProcessor};
+pub use knowledge_graph::{KnowledgeGraph, KnowledgeGraphBuilder};
+pub use templates::TemplateManager;
+pub use pipeline::{GraphTarget, Pipeline, PipelineReport};
\ No newline at end of file
@@ -1,17 +1,19 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand};
 use colored::*;
-use std::path::PathBuf;
-use tokio;
-use tracing::{info, warn, error};
-use tracing_subscriber;
+use std::path::{Path, PathBuf};
+use tracing::{warn, error};
 
 use rdf_knowledge_extractor::{
-    config::Configuration,
-    core::{VllmClient, RdfExtractor},
-    utils::RdfSerializer,
-    knowledge_graph::{KnowledgeGraph, KnowledgeGraphConfig, SimpleSparqlResults},
-    templates::{TemplateManager, TemplateGenerationRequest},
+    config::{Configuration, OutputFormat, RdfSchema, ServerSettings},
+    core::{VllmClient, EmbeddingClient, RdfExtractor, RdfExtractorBuilder, DocumentSubscriber, ReceivedDocument, PromptTestCase, PromptTestReport, DocumentWatcher, ExtractionComparison, ExtractionResult, RdfTriple},
+    utils::{CalibrationReport, ExportManifest, ProjectBundle, SignatureCheck, VisualFormat, calibration, canonicalize, normalize_source, parse_rdf, Progress, ProgressMode, Spreadsheet, visualization},
+    knowledge_graph::{Binding, EmbeddingIndex, EnrichOptions, Enricher, EntityFeed, EntityLinker, ExternalProperty, ExternalSource, FeedbackStore, KnowledgeGraph, KnowledgeGraphBuilder, KnowledgeGraphConfig, SimpleSparqlResults, SparqlEndpoint, MergeReport, MergeStrategy, NamespaceMap, QueryLibrary, RemoteStore, SchemaDrift, SnapshotStore, TriplePattern, ViewRefresh, ViewStore, DocumentStatus, JobManifest, StorageFormat, repair, feedback, federation},
+    templates::{TemplateManager, TemplateGenerationRequest, GenerationHistory, GeneratedDocument, ApprovalHooks, ApprovalQueue, ApprovalStatus, HookDecision, PendingDocument, TemplateValidation, QueryOutcome, validation::Severity},
+    handlers::{CrawlOptions, Crawler},
+    mapping::MappingDocument,
+    server::ServerState,
+    pipeline::{GraphTarget, Pipeline, PipelineReport, target_triples},
 };
 
 #[derive(Parser)]
@@ -34,6 +36,7 @@ struct Cli {
     debug: bool,
 }
 
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 enum Commands {
     /// PHASE 1: Extract RDF triples from documents and store in knowledge graph
@@ -43,13 +46,103 @@ enum Commands {
         config: PathBuf,
 
         /// Input documents or URLs
-        #[arg(short, long, required = true)]
+        #[arg(short, long, required_unless_present_any = ["watch", "resume", "nats_subject"])]
         input: Vec<String>,
 
+        /// Write a JSON report of LLM token usage and estimated cost
+        #[arg(long)]
+        usage_report: Option<PathBuf>,
+
+        /// Resume an interrupted extraction job ("latest" for the most recent),
+        /// skipping completed documents and retrying failed ones
+        #[arg(long, conflicts_with = "input")]
+        resume: Option<String>,
+
+        /// Watch a directory and extract new or changed documents continuously
+        #[arg(long)]
+        watch: Option<PathBuf>,
+
+        /// Crawl URL inputs, following links this many hops from each seed URL
+        #[arg(long, conflicts_with = "resume")]
+        crawl_depth: Option<usize>,
+
+        /// Follow links to other hosts too (crawling stays on the seed's host by default)
+        #[arg(long, requires = "crawl_depth")]
+        crawl_any_domain: bool,
+
+        /// Only crawl URLs matching one of these regexes
+        #[arg(long, requires = "crawl_depth")]
+        crawl_include: Vec<String>,
+
+        /// Skip URLs matching any of these regexes
+        #[arg(long, requires = "crawl_depth")]
+        crawl_exclude: Vec<String>,
+
+        /// Also crawl the pages listed in the seed host's sitemap.xml
+        #[arg(long, requires = "crawl_depth")]
+        crawl_sitemap: bool,
+
+        /// Delay between crawl requests to the same host, in milliseconds
+        /// [default: the config's crawl.per_host_delay_ms, else 500]
+        #[arg(long, requires = "crawl_depth")]
+        crawl_delay_ms: Option<u64>,
+
+        /// Maximum pages to crawl per seed URL [default: the config's crawl.max_pages, else 50]
+        #[arg(long, requires = "crawl_depth")]
+        crawl_max_pages: Option<usize>,
+
+        /// Extract each top-level section or chapter of a long document as a source
+        /// of its own (`<source>#section=<n>`), recording the section title with its triples
+        #[arg(long, conflicts_with_all = ["resume", "watch", "nats_subject"])]
+        split_sections: bool,
+
+        /// Consume documents (or URLs) published on a NATS subject and extract them continuously
+        #[arg(long)]
+        nats_subject: Option<String>,
+
+        /// NATS server URL
+        #[arg(long, default_value = "nats://127.0.0.1:4222", requires = "nats_subject")]
+        nats_url: String,
+
+        /// NATS queue group, so several extractors can share the subject's documents
+        #[arg(long, requires = "nats_subject")]
+        nats_queue_group: Option<String>,
+
+        /// NATS subject to publish each document's extracted triples to, in --format
+        #[arg(long, requires = "nats_subject")]
+        nats_publish: Option<String>,
+
         /// Knowledge graph database path
         #[arg(long, default_value = "knowledge_graph.db")]
         kg_path: String,
 
+        /// SPARQL 1.1 Update endpoint (e.g. an Oxigraph server's /update) to stream
+        /// triples into as each document completes, instead of the knowledge graph
+        #[arg(long, conflicts_with_all = ["merge", "output", "watch", "nats_subject"])]
+        remote_store: Option<String>,
+
+        /// Triples per update sent to --remote-store
+        #[arg(long, default_value_t = 1000, requires = "remote_store")]
+        remote_batch_size: usize,
+
+        /// Bearer token for --remote-store
+        #[arg(long, requires = "remote_store")]
+        remote_token: Option<String>,
+
+        /// Write the knowledge graph to disk once this many triples are pending,
+        /// instead of after every document
+        #[arg(long)]
+        write_batch_size: Option<usize>,
+
+        /// Write pending triples at least this often (milliseconds)
+        #[arg(long)]
+        flush_interval_ms: Option<u64>,
+
+        /// Wait up to this long (milliseconds) for another process writing
+        /// the knowledge graph to finish, instead of failing right away
+        #[arg(long)]
+        lock_timeout_ms: Option<u64>,
+
         /// Also export triples to file
         #[arg(short, long)]
         output: Option<PathBuf>,
@@ -77,6 +170,37 @@ enum Commands {
         /// Validate extracted triples
         #[arg(long)]
         validate: bool,
+
+        /// Compare two models or config files (e.g. model_a,model_b) instead of storing triples
+        #[arg(long, value_delimiter = ',', num_args = 1)]
+        compare: Option<Vec<String>>,
+
+        /// Bypass the on-disk LLM response cache
+        #[arg(long)]
+        no_cache: bool,
+
+        /// Value for a {{name}} placeholder in the extraction questions (name=value, repeatable)
+        #[arg(long = "var", value_name = "NAME=VALUE", value_parser = parse_variable)]
+        vars: Vec<(String, String)>,
+
+        /// Show the model up to this many recent reviewer corrections from the
+        /// knowledge graph's feedback store as counter-examples (0 to disable)
+        #[arg(long, default_value_t = feedback::DEFAULT_PROMPT_CORRECTIONS)]
+        corrections: usize,
+
+        /// Give up on a document after this many seconds, record it as failed and
+        /// continue (overrides the config's document_timeout)
+        #[arg(long)]
+        document_timeout: Option<u64>,
+
+        /// Store triples in a named graph: an IRI, "source" for one graph per document,
+        /// or "run" for a new graph per extraction run
+        #[arg(long)]
+        graph: Option<GraphTarget>,
+
+        /// Progress reporting: a progress bar, newline-delimited JSON events on stderr, or none
+        #[arg(long, value_enum, default_value = "bar")]
+        progress: ProgressArg,
     },
 
     /// PHASE 2: Generate documents from templates using knowledge graph
@@ -97,7 +221,7 @@ enum Commands {
         #[arg(long)]
         template_id: Option<String>,
 
-        /// Output file path
+        /// Output file path (output directory with --batch)
         #[arg(short, long)]
         output: Option<PathBuf>,
 
@@ -120,6 +244,30 @@ enum Commands {
         /// Enable LLM enhancement
         #[arg(long)]
         enhance: bool,
+
+        /// Bypass the on-disk LLM response cache
+        #[arg(long)]
+        no_cache: bool,
+
+        /// Render one file per row of the template's batch query
+        #[arg(long)]
+        batch: bool,
+
+        /// Start Markdown output with YAML front matter (title, date, template, KG version, tags)
+        #[arg(long)]
+        front_matter: bool,
+
+        /// Validate the template and run its queries without generating or calling the LLM
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Don't record the generated documents in the generation history
+        #[arg(long)]
+        no_history: bool,
+
+        /// Progress reporting for --batch: a progress bar, newline-delimited JSON events on stderr, or none
+        #[arg(long, value_enum, default_value = "bar")]
+        progress: ProgressArg,
     },
 
     /// Query the knowledge graph with SPARQL
@@ -133,12 +281,28 @@ enum Commands {
         query: Option<String>,
 
         /// SPARQL query file
-        #[arg(short, long)]
+        #[arg(long)]
         file: Option<PathBuf>,
 
         /// Output format
         #[arg(short, long, value_enum, default_value = "table")]
         format: QueryOutputFormat,
+
+        /// Configuration file whose RDF schema supplies prefixes and inference axioms
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+
+        /// Include triples entailed by the schema's class and predicate axioms
+        #[arg(long)]
+        infer: bool,
+
+        /// Send the query to this SPARQL endpoint instead of the knowledge graph
+        #[arg(long, value_name = "URL", conflicts_with = "infer")]
+        endpoint: Option<String>,
+
+        /// Write the results to an XLSX workbook instead of printing them
+        #[arg(long, value_name = "FILE")]
+        xlsx: Option<PathBuf>,
     },
 
     /// Show knowledge graph statistics
@@ -150,6 +314,41 @@ enum Commands {
         /// Configuration file path
         #[arg(short, long)]
         config: PathBuf,
+
+        /// Output format
+        #[arg(short, long, value_enum, default_value = "table")]
+        format: ReportFormat,
+
+        /// Write the statistics to an XLSX workbook instead of printing them
+        #[arg(long, value_name = "FILE")]
+        xlsx: Option<PathBuf>,
+    },
+
+    /// Report how model confidence correlates with validation outcomes or gold labels
+    Calibrate {
+        /// Knowledge graph database path
+        #[arg(long, default_value = "knowledge_graph.db")]
+        kg_path: String,
+
+        /// Configuration file path
+        #[arg(short, long)]
+        config: PathBuf,
+
+        /// Gold triples (JSON array) used as labels instead of validation
+        #[arg(long)]
+        gold: Option<PathBuf>,
+
+        /// Number of confidence bins
+        #[arg(long, default_value = "10")]
+        bins: usize,
+
+        /// Output format
+        #[arg(short, long, value_enum, default_value = "table")]
+        format: ReportFormat,
+
+        /// Write the report to an XLSX workbook instead of printing it
+        #[arg(long, value_name = "FILE")]
+        xlsx: Option<PathBuf>,
     },
 
     /// Export knowledge graph to file
@@ -169,6 +368,292 @@ enum Commands {
         /// Output format
         #[arg(short, long, value_enum, default_value = "turtle")]
         format: OutputFormatArg,
+
+        /// Only export triples added after this point: an extraction job id
+        /// ("latest" for the most recent) or an RFC 3339 timestamp / date
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Write an integrity manifest (<output>.manifest.json) with file and graph hashes
+        #[arg(long)]
+        manifest: bool,
+
+        /// Sign the manifest with an Ed25519 PKCS#8 PEM private key (implies --manifest)
+        #[arg(long)]
+        sign_key: Option<PathBuf>,
+
+        /// Write canonical N-Triples / N-Quads: sorted lines, canonical literal
+        /// escaping and RDFC-1.0 blank node labels, for reproducible diffs
+        #[arg(long)]
+        canonical: bool,
+
+        /// Write a zip archiving the whole project instead: canonical N-Quads,
+        /// the config, templates, job and generation records, and a manifest
+        #[arg(long, conflicts_with_all = ["format", "since", "manifest", "sign_key", "canonical"])]
+        bundle: bool,
+
+        /// Template directory to include in the bundle
+        #[arg(long, requires = "bundle")]
+        template_dir: Option<PathBuf>,
+    },
+
+    /// Map CSV files or SQLite tables into the knowledge graph with a YAML
+    /// mapping or CSVW metadata (.json), without the LLM
+    Import {
+        /// Configuration file whose RDF schema resolves terms and relative IRIs
+        #[arg(short, long)]
+        config: PathBuf,
+
+        /// Mapping file (YAML) or CSVW metadata (JSON)
+        #[arg(short, long)]
+        mapping: PathBuf,
+
+        /// Knowledge graph database path
+        #[arg(long, default_value = "knowledge_graph.db")]
+        kg_path: String,
+
+        /// Print the mapped triples instead of storing them
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Report how many triples would be added, duplicates and conflicts
+        /// with stored triples and new predicates, without storing anything
+        #[arg(long, conflicts_with = "dry_run")]
+        preview: bool,
+    },
+
+    /// Write a JSON summary per entity (properties, relations, sources) for embedding in other apps; reruns only rewrite entities that changed
+    EntityFeed {
+        /// Knowledge graph database path
+        #[arg(long, default_value = "knowledge_graph.db")]
+        kg_path: String,
+
+        /// Configuration file whose schema prefixes compact predicates and --type
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+
+        /// Feed directory, with one <entity>.json per entity and index.json
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Only include entities of these classes (IRIs or prefixed names)
+        #[arg(long = "type")]
+        types: Vec<String>,
+    },
+
+    /// Link labelled entities to Wikidata or DBpedia (owl:sameAs) and store
+    /// their type, country and industry; lookups are cached in <kg_path>.enrichment.json
+    Enrich {
+        /// Knowledge graph database path
+        #[arg(long, default_value = "knowledge_graph.db")]
+        kg_path: String,
+
+        /// Configuration file whose schema prefixes --type may use
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+
+        /// Knowledge base to look entities up in
+        #[arg(long, value_enum, default_value = "wikidata")]
+        source: ExternalSourceArg,
+
+        /// Properties of matched entities to store [default: all]
+        #[arg(long = "property", value_enum, value_delimiter = ',')]
+        properties: Vec<ExternalPropertyArg>,
+
+        /// Only look up entities of these classes (IRIs or prefixed names)
+        #[arg(long = "type")]
+        types: Vec<String>,
+
+        /// Language of the labels to match, and of the value labels stored
+        #[arg(long, default_value = "en")]
+        language: String,
+
+        /// Delay between requests to the knowledge base, in milliseconds
+        #[arg(long, default_value_t = 1000)]
+        delay_ms: u64,
+
+        /// Entities to look up at most
+        #[arg(long)]
+        limit: Option<usize>,
+
+        /// Only use cached lookups, without network requests
+        #[arg(long)]
+        offline: bool,
+
+        /// Print the links and properties without storing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Check an export against its integrity manifest and signature, or a
+    /// bundle against the manifest inside it
+    VerifyExport {
+        /// Manifest written by `export --manifest`, or a zip written by `export --bundle`
+        manifest: PathBuf,
+
+        /// Ed25519 PEM public key to check the detached signature against
+        #[arg(long)]
+        public_key: Option<PathBuf>,
+    },
+
+    /// Render the knowledge graph, or part of it, as Graphviz DOT, Mermaid or an interactive HTML page
+    Visualize {
+        /// Knowledge graph database path
+        #[arg(long, default_value = "knowledge_graph.db")]
+        kg_path: String,
+
+        /// Configuration file whose schema prefixes --query may use
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+
+        /// Output file (prints to stdout when omitted)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Output format; defaults to the output file's extension (.dot/.gv, .mmd, .html), else DOT
+        #[arg(short, long, value_enum)]
+        format: Option<VisualFormatArg>,
+
+        /// Only show triples about the resources this SPARQL SELECT query returns
+        #[arg(short, long, conflicts_with = "entity")]
+        query: Option<String>,
+
+        /// Only show the neighborhood of this entity IRI
+        #[arg(long)]
+        entity: Option<String>,
+
+        /// Links to follow from --entity
+        #[arg(long, default_value_t = 1, requires = "entity")]
+        hops: usize,
+
+        /// Render at most this many triples
+        #[arg(long, default_value_t = 500)]
+        limit: usize,
+    },
+
+    /// Serve the knowledge graph over HTTP (SPARQL endpoint and Graph Store Protocol)
+    Serve {
+        /// Knowledge graph database path
+        #[arg(long, default_value = "knowledge_graph.db")]
+        kg_path: String,
+
+        /// Configuration file whose RDF schema supplies prefixes
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:3030")]
+        bind: String,
+
+        /// Allow binding beyond localhost without API keys in the config
+        #[arg(long)]
+        allow_anonymous: bool,
+
+        /// Also serve the gRPC API on this address
+        #[arg(long)]
+        grpc_bind: Option<String>,
+
+        /// Template directory for gRPC document generation
+        #[arg(long)]
+        template_dir: Option<PathBuf>,
+
+        /// Serve queries only: graph store writes, deletes and ingestion jobs are refused
+        #[arg(long)]
+        read_only: bool,
+    },
+
+    /// Serve the knowledge graph to AI agents as Model Context Protocol tools
+    /// over stdin/stdout (query, entity lookup, adding triples, generation)
+    McpServe {
+        /// Knowledge graph database path
+        #[arg(long, default_value = "knowledge_graph.db")]
+        kg_path: String,
+
+        /// Configuration file supplying the RDF schema and, for document
+        /// generation, the LLM settings
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+
+        /// Template directory for the generate_document tool
+        #[arg(long)]
+        template_dir: Option<PathBuf>,
+
+        /// Offer read-only tools: add_triples is refused
+        #[arg(long)]
+        read_only: bool,
+    },
+
+    /// Knowledge graph maintenance (diff, merge, infer)
+    Kg {
+        #[command(subcommand)]
+        command: KgCommands,
+    },
+
+    /// Manage materialized views that templates can reference by name
+    Views {
+        /// Knowledge graph database path
+        #[arg(long, default_value = "knowledge_graph.db", global = true)]
+        kg_path: String,
+
+        #[command(subcommand)]
+        command: ViewsCommands,
+    },
+
+    /// Manage named SPARQL queries stored alongside the knowledge graph
+    Queries {
+        /// Knowledge graph database path
+        #[arg(long, default_value = "knowledge_graph.db", global = true)]
+        kg_path: String,
+
+        #[command(subcommand)]
+        command: QueriesCommands,
+    },
+
+    /// Browse and compare previously generated documents
+    Generations {
+        /// Knowledge graph database path
+        #[arg(long, default_value = "knowledge_graph.db", global = true)]
+        kg_path: String,
+
+        #[command(subcommand)]
+        command: GenerationsCommands,
+    },
+
+    /// Review documents held for approval and publish the approved ones
+    Approvals {
+        /// Knowledge graph database path
+        #[arg(long, default_value = "knowledge_graph.db", global = true)]
+        kg_path: String,
+
+        #[command(subcommand)]
+        command: ApprovalsCommands,
+    },
+
+    /// Manage the prefixes used by queries and serializers for a knowledge graph
+    Namespace {
+        /// Knowledge graph database path
+        #[arg(long, default_value = "knowledge_graph.db", global = true)]
+        kg_path: String,
+
+        #[command(subcommand)]
+        command: NamespaceCommands,
+    },
+
+    /// Work with the RDF schema defined in a configuration file
+    Schema {
+        #[command(subcommand)]
+        command: SchemaCommands,
+    },
+
+    /// Record reviewer corrections of extracted triples; recent ones are shown
+    /// to the model as counter-examples in later extractions
+    Feedback {
+        /// Knowledge graph database path
+        #[arg(long, default_value = "knowledge_graph.db", global = true)]
+        kg_path: String,
+
+        #[command(subcommand)]
+        command: FeedbackCommands,
     },
 
     /// List available templates
@@ -176,6 +661,33 @@ enum Commands {
         /// Template directory
         #[arg(short, long, default_value = "templates")]
         template_dir: String,
+
+        /// Render each template against sample data (no knowledge graph or LLM needed)
+        #[arg(long)]
+        preview: bool,
+    },
+
+    /// Check templates' syntax and query usage, and run their queries against the knowledge graph
+    ValidateTemplate {
+        /// Template file or directory
+        #[arg(short, long)]
+        template: String,
+
+        /// Template ID to check (default: every template loaded)
+        #[arg(long)]
+        template_id: Option<String>,
+
+        /// Knowledge graph database path
+        #[arg(long, default_value = "knowledge_graph.db")]
+        kg_path: String,
+
+        /// Configuration file whose RDF schema supplies prefixes
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+
+        /// Output format
+        #[arg(short, long, value_enum, default_value = "table")]
+        format: ReportFormat,
     },
 
     /// Validate configuration file
@@ -185,6 +697,42 @@ enum Commands {
         config: PathBuf,
     },
 
+    /// Run prompt regression tests: extract each test case's snippet and
+    /// compare the triples with the expected ones
+    PromptTest {
+        /// Configuration file path
+        #[arg(short, long)]
+        config: PathBuf,
+
+        /// Directory of test cases (YAML or JSON files with `text` or
+        /// `document`, `expected` and optionally `forbidden` triples)
+        cases: PathBuf,
+
+        /// vLLM server URL
+        #[arg(long, default_value = "http://localhost:8000")]
+        server_url: String,
+
+        /// API key for vLLM server
+        #[arg(long)]
+        api_key: Option<String>,
+
+        /// Model to test (overrides config)
+        #[arg(long)]
+        model: Option<String>,
+
+        /// Bypass the on-disk LLM response cache
+        #[arg(long)]
+        no_cache: bool,
+
+        /// Value for a {{name}} placeholder in the extraction questions (name=value, repeatable)
+        #[arg(long = "var", value_name = "NAME=VALUE", value_parser = parse_variable)]
+        vars: Vec<(String, String)>,
+
+        /// Write the results as JSON
+        #[arg(long)]
+        report: Option<PathBuf>,
+    },
+
     /// Check vLLM server status
     CheckServer {
         /// vLLM server URL
@@ -236,15 +784,545 @@ enum Commands {
         #[arg(long, default_value = "Qwen/Qwen2.5-32B-Instruct")]
         model: String,
     },
+
+    /// Print shell completions, or write man pages for every command
+    Completions {
+        /// Shell to generate completions for
+        #[arg(required_unless_present = "man_dir")]
+        shell: Option<clap_complete::Shell>,
+
+        /// Write man pages into this directory instead
+        #[arg(long, conflicts_with = "shell")]
+        man_dir: Option<PathBuf>,
+    },
 }
 
-#[derive(clap::ValueEnum, Clone)]
+#[derive(Subcommand)]
+enum KgCommands {
+    /// Report triples added and removed between two knowledge graph stores
+    Diff {
+        /// Base knowledge graph database path
+        base: String,
+
+        /// Knowledge graph database path to compare against the base
+        other: String,
+
+        /// Write the diff as JSON to this file
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Merge knowledge graph stores into a target store
+    Merge {
+        /// Target knowledge graph database path
+        #[arg(long, default_value = "knowledge_graph.db")]
+        kg_path: String,
+
+        /// Knowledge graph database paths to merge into the target
+        #[arg(required = true)]
+        sources: Vec<String>,
+
+        /// How to resolve conflicting objects for the same subject and predicate
+        #[arg(long, value_enum, default_value = "keep-both")]
+        strategy: MergeStrategyArg,
+
+        /// Report what merging each source would change without changing the target
+        #[arg(long)]
+        preview: bool,
+    },
+
+    /// Store the triples entailed by the schema's class and predicate axioms
+    Infer {
+        /// Knowledge graph database path
+        #[arg(long, default_value = "knowledge_graph.db")]
+        kg_path: String,
+
+        /// Configuration file with the RDF schema
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+
+        /// Print the inferred triples without storing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Add a single triple, e.g. one the extraction missed
+    AddTriple {
+        /// Knowledge graph database path
+        #[arg(long, default_value = "knowledge_graph.db")]
+        kg_path: String,
+
+        /// Subject IRI
+        subject: String,
+
+        /// Predicate IRI
+        predicate: String,
+
+        /// Object: an http(s) IRI or a literal value
+        object: String,
+
+        /// Named graph to add the triple to
+        #[arg(long)]
+        graph: Option<String>,
+
+        /// Language tag of a literal object
+        #[arg(long, conflicts_with = "datatype")]
+        lang: Option<String>,
+
+        /// Datatype IRI of a literal object
+        #[arg(long)]
+        datatype: Option<String>,
+    },
+
+    /// Remove the triples matching a subject, predicate and/or object
+    #[command(group(clap::ArgGroup::new("pattern").required(true).multiple(true).args(["subject", "predicate", "object"])))]
+    RemoveTriple {
+        /// Knowledge graph database path
+        #[arg(long, default_value = "knowledge_graph.db")]
+        kg_path: String,
+
+        /// Subject IRI to match
+        #[arg(long)]
+        subject: Option<String>,
+
+        /// Predicate IRI to match
+        #[arg(long)]
+        predicate: Option<String>,
+
+        /// Object IRI or literal value to match
+        #[arg(long)]
+        object: Option<String>,
+
+        /// Only match triples in this named graph ("default" for the unnamed graph)
+        #[arg(long)]
+        graph: Option<String>,
+
+        /// List the matching triples without removing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Recover the readable triples of a store that no longer parses
+    Repair {
+        /// Damaged knowledge graph database path
+        #[arg(long, default_value = "knowledge_graph.db")]
+        kg_path: String,
+
+        /// Recovered store to write (defaults to <kg-path>.recovered)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// JSON Lines file for unreadable records (defaults to <kg-path>.quarantine.jsonl)
+        #[arg(long)]
+        quarantine: Option<PathBuf>,
+    },
+
+    /// Convert a store between the JSON and compact (gzip-compressed JSON)
+    /// storage formats, keeping all triple provenance
+    MigrateStore {
+        /// Knowledge graph database path
+        #[arg(long, default_value = "knowledge_graph.db")]
+        kg_path: String,
+
+        /// Storage format to convert to
+        #[arg(long, value_enum)]
+        to: StorageFormatArg,
+
+        /// Write the converted store here instead of converting in place,
+        /// along with the original's namespaces, views, feedback, snapshots
+        /// and other state
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+    /// Record the store's current triples as an immutable, tagged version
+    Snapshot {
+        /// Knowledge graph database path
+        #[arg(long, default_value = "knowledge_graph.db")]
+        kg_path: String,
+
+        /// Snapshot tag (defaults to the current UTC time, e.g. 20240301-093000)
+        tag: Option<String>,
+
+        /// List the existing snapshots instead of recording one
+        #[arg(long, conflicts_with = "tag")]
+        list: bool,
+    },
+
+    /// Roll the store back to a snapshot
+    Restore {
+        /// Knowledge graph database path
+        #[arg(long, default_value = "knowledge_graph.db")]
+        kg_path: String,
+
+        /// Tag of the snapshot to restore
+        tag: String,
+
+        /// Don't snapshot the current triples (as pre-restore-<time>) first
+        #[arg(long)]
+        no_backup: bool,
+    },
+
+    /// Fold the journal of added triples into the store file
+    Compact {
+        /// Knowledge graph database path
+        #[arg(long, default_value = "knowledge_graph.db")]
+        kg_path: String,
+    },
+
+    /// Embed every labelled entity anew and rewrite the embedding index
+    /// (<kg-path>.embeddings), e.g. after changing the embedding model
+    RebuildEmbeddings {
+        /// Knowledge graph database path
+        #[arg(long, default_value = "knowledge_graph.db")]
+        kg_path: String,
+
+        /// Configuration file with the entity_linking embedding model
+        #[arg(short, long)]
+        config: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum SchemaCommands {
+    /// Write the schema's classes and predicates as an RDFS/OWL ontology (Turtle)
+    Export {
+        /// Configuration file path
+        #[arg(short, long)]
+        config: PathBuf,
+
+        /// Output file (prints to stdout when omitted)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Report the predicates outside the schema that extraction produced and dropped
+    Drift {
+        /// Knowledge graph database path the extractions were stored in
+        #[arg(long, default_value = "knowledge_graph.db")]
+        kg_path: String,
+
+        /// Number of predicates to show, most frequent first
+        #[arg(long, default_value_t = 10)]
+        top: usize,
+
+        /// Forget the recorded predicates, e.g. after updating the schema
+        #[arg(long)]
+        clear: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum FeedbackCommands {
+    /// Reject a wrong triple and remove it from the knowledge graph
+    Reject {
+        /// Subject IRI
+        subject: String,
+
+        /// Predicate IRI
+        predicate: String,
+
+        /// Object IRI or literal value
+        object: String,
+
+        /// Why the triple is wrong, shown to the model with the correction
+        #[arg(long)]
+        note: Option<String>,
+    },
+
+    /// Correct the object of a triple in the knowledge graph
+    Fix {
+        /// Subject IRI
+        subject: String,
+
+        /// Predicate IRI
+        predicate: String,
+
+        /// The wrong object
+        object: String,
+
+        /// The object the triple should have
+        corrected_object: String,
+
+        /// Why the object was wrong, shown to the model with the correction
+        #[arg(long)]
+        note: Option<String>,
+    },
+
+    /// List recorded corrections, newest first
+    List {
+        /// Number of corrections to show
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+
+    /// Forget a correction, so it's no longer shown to the model
+    Remove {
+        /// Correction id, as shown by `feedback list`
+        id: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum ApprovalsCommands {
+    /// List documents under review, oldest first
+    List {
+        /// Only show documents with this status
+        #[arg(long, value_enum)]
+        status: Option<ApprovalStatusArg>,
+    },
+
+    /// Approve a pending document
+    Approve {
+        /// Document id
+        id: String,
+
+        /// Comment recorded with the decision
+        #[arg(long)]
+        note: Option<String>,
+    },
+
+    /// Reject a pending document
+    Reject {
+        /// Document id
+        id: String,
+
+        /// Comment recorded with the decision
+        #[arg(long)]
+        note: Option<String>,
+    },
+
+    /// Move approved documents to their destinations
+    Publish {
+        /// Document ids (default: every approved document)
+        ids: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum GenerationsCommands {
+    /// List recorded generations, oldest first
+    List {
+        /// Only show generations of this template
+        #[arg(short, long)]
+        template: Option<String>,
+    },
+
+    /// Show a generation's record and content
+    Show {
+        /// Generation id (or a unique prefix of it)
+        id: String,
+    },
+
+    /// Compare two generations (default: with the previous one of the same template)
+    Diff {
+        /// Generation id (or a unique prefix of it)
+        from: String,
+
+        /// Generation id to compare against; when omitted, `from` is compared
+        /// with the generation of the same template before it
+        to: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum QueriesCommands {
+    /// Save (or replace) a named query
+    Save {
+        /// Query name
+        name: String,
+
+        /// SPARQL query string
+        #[arg(short, long)]
+        query: Option<String>,
+
+        /// SPARQL query file
+        #[arg(short, long)]
+        file: Option<PathBuf>,
+
+        /// Short description of what the query answers
+        #[arg(long)]
+        description: Option<String>,
+
+        /// Tags (comma separated)
+        #[arg(long, value_delimiter = ',')]
+        tags: Vec<String>,
+    },
+
+    /// List saved queries
+    List {
+        /// Only show queries with this tag
+        #[arg(long)]
+        tag: Option<String>,
+    },
+
+    /// Add or remove tags on a saved query
+    Tag {
+        /// Query name
+        name: String,
+
+        /// Tags to add
+        #[arg(required = true)]
+        tags: Vec<String>,
+
+        /// Remove the tags instead of adding them
+        #[arg(long)]
+        remove: bool,
+    },
+
+    /// Run a saved query against the knowledge graph
+    Run {
+        /// Query name
+        name: String,
+
+        /// Output format
+        #[arg(short, long, value_enum, default_value = "table")]
+        format: QueryOutputFormat,
+
+        /// Write the results to an XLSX workbook instead of printing them
+        #[arg(long, value_name = "FILE")]
+        xlsx: Option<PathBuf>,
+    },
+
+    /// Delete a saved query
+    Delete {
+        /// Query name
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum NamespaceCommands {
+    /// Bind a prefix to a namespace IRI, replacing any existing binding
+    Add {
+        /// Prefix, e.g. foaf
+        prefix: String,
+
+        /// Namespace IRI, e.g. http://xmlns.com/foaf/0.1/
+        uri: String,
+    },
+
+    /// List the bound prefixes
+    List,
+
+    /// Remove a prefix
+    Remove {
+        /// Prefix
+        prefix: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum ViewsCommands {
+    /// Define (or redefine) a materialized view
+    Define {
+        /// View name
+        name: String,
+
+        /// SPARQL query string
+        #[arg(short, long)]
+        query: Option<String>,
+
+        /// SPARQL query file
+        #[arg(short, long)]
+        file: Option<PathBuf>,
+
+        /// Refresh the view automatically after each extraction run
+        #[arg(long)]
+        after_extraction: bool,
+    },
+
+    /// List materialized views
+    List,
+
+    /// Recompute a view, or all views when no name is given
+    Refresh {
+        /// View name
+        name: Option<String>,
+    },
+
+    /// Print the stored results of a view
+    Show {
+        /// View name
+        name: String,
+
+        /// Output format
+        #[arg(short, long, value_enum, default_value = "table")]
+        format: QueryOutputFormat,
+
+        /// Write the results to an XLSX workbook instead of printing them
+        #[arg(long, value_name = "FILE")]
+        xlsx: Option<PathBuf>,
+    },
+
+    /// Delete a materialized view
+    Delete {
+        /// View name
+        name: String,
+    },
+}
+
+#[derive(clap::ValueEnum, Clone, Copy)]
+enum MergeStrategyArg {
+    KeepBoth,
+    PreferTarget,
+    PreferSource,
+}
+
+impl From<MergeStrategyArg> for MergeStrategy {
+    fn from(strategy: MergeStrategyArg) -> Self {
+        match strategy {
+            MergeStrategyArg::KeepBoth => Self::KeepBoth,
+            MergeStrategyArg::PreferTarget => Self::PreferTarget,
+            MergeStrategyArg::PreferSource => Self::PreferSource,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy)]
+enum ApprovalStatusArg {
+    Pending,
+    Approved,
+    Rejected,
+    Published,
+}
+
+impl From<ApprovalStatusArg> for ApprovalStatus {
+    fn from(status: ApprovalStatusArg) -> Self {
+        match status {
+            ApprovalStatusArg::Pending => Self::Pending,
+            ApprovalStatusArg::Approved => Self::Approved,
+            ApprovalStatusArg::Rejected => Self::Rejected,
+            ApprovalStatusArg::Published => Self::Published,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy)]
+enum StorageFormatArg {
+    Json,
+    Compact,
+}
+
+impl From<StorageFormatArg> for StorageFormat {
+    fn from(format: StorageFormatArg) -> Self {
+        match format {
+            StorageFormatArg::Json => Self::Json,
+            StorageFormatArg::Compact => Self::Compact,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone)]
 enum OutputFormatArg {
     Turtle,
     JsonLd,
     NTriples,
     RdfXml,
     Json,
+    NQuads,
+    Trig,
+    Cypher,
+    Graphml,
 }
 
 impl From<OutputFormatArg> for rdf_knowledge_extractor::config::OutputFormat {
@@ -255,6 +1333,27 @@ impl From<OutputFormatArg> for rdf_knowledge_extractor::config::OutputFormat {
             OutputFormatArg::NTriples => Self::NTriples,
             OutputFormatArg::RdfXml => Self::RdfXml,
             OutputFormatArg::Json => Self::Json,
+            OutputFormatArg::NQuads => Self::NQuads,
+            OutputFormatArg::Trig => Self::TriG,
+            OutputFormatArg::Cypher => Self::Cypher,
+            OutputFormatArg::Graphml => Self::GraphMl,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy)]
+enum ProgressArg {
+    Bar,
+    Json,
+    None,
+}
+
+impl From<ProgressArg> for ProgressMode {
+    fn from(mode: ProgressArg) -> Self {
+        match mode {
+            ProgressArg::Bar => Self::Bar,
+            ProgressArg::Json => Self::Json,
+            ProgressArg::None => Self::None,
         }
     }
 }
@@ -265,15 +1364,70 @@ enum ConfigFormat {
     Json,
 }
 
-#[derive(clap::ValueEnum, Clone)]
-enum QueryOutputFormat {
+#[derive(clap::ValueEnum, Clone, Copy)]
+enum ReportFormat {
     Table,
     Json,
-    Csv,
-    Turtle,
 }
 
-#[tokio::main]
+#[derive(clap::ValueEnum, Clone)]
+enum VisualFormatArg {
+    Dot,
+    Mermaid,
+    Html,
+}
+
+impl From<VisualFormatArg> for VisualFormat {
+    fn from(format: VisualFormatArg) -> Self {
+        match format {
+            VisualFormatArg::Dot => Self::Dot,
+            VisualFormatArg::Mermaid => Self::Mermaid,
+            VisualFormatArg::Html => Self::Html,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone)]
+enum ExternalSourceArg {
+    Wikidata,
+    Dbpedia,
+}
+
+impl From<ExternalSourceArg> for ExternalSource {
+    fn from(source: ExternalSourceArg) -> Self {
+        match source {
+            ExternalSourceArg::Wikidata => Self::Wikidata,
+            ExternalSourceArg::Dbpedia => Self::Dbpedia,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone)]
+enum ExternalPropertyArg {
+    Type,
+    Country,
+    Industry,
+}
+
+impl From<ExternalPropertyArg> for ExternalProperty {
+    fn from(property: ExternalPropertyArg) -> Self {
+        match property {
+            ExternalPropertyArg::Type => Self::Type,
+            ExternalPropertyArg::Country => Self::Country,
+            ExternalPropertyArg::Industry => Self::Industry,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone)]
+enum QueryOutputFormat {
+    Table,
+    Json,
+    Csv,
+    Turtle,
+}
+
+#[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
 
@@ -286,15 +1440,21 @@ async fn main() -> Result<()> {
         tracing::Level::WARN
     };
 
-    tracing_subscriber::fmt()
+    let subscriber = tracing_subscriber::fmt()
         .with_max_level(log_level)
-        .with_target(false)
-        .init();
+        .with_target(false);
+    // MCP messages go to stdout, so logs must not
+    if matches!(cli.command, Commands::McpServe { .. }) {
+        subscriber.with_writer(std::io::stderr).init();
+    } else {
+        subscriber.init();
+    }
 
     match cli.command {
         Commands::Extract {
             config,
             input,
+            watch,
             kg_path,
             output,
             format,
@@ -303,9 +1463,78 @@ async fn main() -> Result<()> {
             model,
             merge,
             validate,
+            compare,
+            no_cache,
+            graph,
+            usage_report,
+            resume,
+            nats_subject,
+            nats_url,
+            nats_queue_group,
+            nats_publish,
+            crawl_depth,
+            crawl_any_domain,
+            crawl_include,
+            crawl_exclude,
+            crawl_sitemap,
+            crawl_delay_ms,
+            crawl_max_pages,
+            split_sections,
+            vars,
+            corrections,
+            document_timeout,
+            progress,
+            remote_store,
+            remote_batch_size,
+            remote_token,
+            write_batch_size,
+            flush_interval_ms,
+            lock_timeout_ms,
         } => {
+            if let Some(variants) = compare {
+                return compare_command(config, input, output, server_url, api_key, variants, no_cache, vars).await;
+            }
+            let nats = nats_subject.map(|subject| NatsOptions {
+                url: nats_url,
+                subject,
+                queue_group: nats_queue_group,
+                publish: nats_publish,
+            });
+            let crawl = match crawl_depth {
+                Some(depth) => {
+                    // Flags override the config's crawl profile
+                    let profile = Configuration::from_file(&config)?.crawl;
+                    let defaults = CrawlOptions::default();
+                    Some(CrawlOptions {
+                        depth,
+                        same_domain: !crawl_any_domain,
+                        include: crawl_include.iter().map(|re| regex::Regex::new(re)).collect::<Result<_, _>>()?,
+                        exclude: crawl_exclude.iter().map(|re| regex::Regex::new(re)).collect::<Result<_, _>>()?,
+                        sitemap: crawl_sitemap,
+                        delay: crawl_delay_ms.or(profile.as_ref().map(|p| p.per_host_delay_ms))
+                            .map(std::time::Duration::from_millis)
+                            .unwrap_or(defaults.delay),
+                        max_pages: crawl_max_pages.or(profile.as_ref().and_then(|p| p.max_pages))
+                            .unwrap_or(defaults.max_pages),
+                        retry: profile.map(|p| p.retry).unwrap_or(defaults.retry),
+                    })
+                }
+                None => None,
+            };
+            let remote = match remote_store {
+                Some(url) => {
+                    let store = RemoteStore::new(url, remote_batch_size)?;
+                    Some(match remote_token {
+                        Some(token) => store.with_token(token),
+                        None => store,
+                    })
+                }
+                None => None,
+            };
             extract_command(
-                config, input, kg_path, output, format, server_url, api_key, model, merge, validate,
+                config, input, watch, nats, crawl, split_sections, remote, kg_path, write_batch_size, flush_interval_ms, lock_timeout_ms, output, format,
+                server_url, api_key, model, merge, validate, no_cache, graph, usage_report, resume, vars, corrections,
+                document_timeout, progress.into(),
             ).await
         }
         Commands::Generate {
@@ -319,24 +1548,66 @@ async fn main() -> Result<()> {
             model,
             context,
             enhance,
+            no_cache,
+            batch,
+            front_matter,
+            dry_run,
+            no_history,
+            progress,
         } => {
             generate_command(
-                config, kg_path, template, template_id, output, server_url, api_key, model, context, enhance,
+                config, kg_path, template, template_id, output, server_url, api_key, model, context, enhance, no_cache, batch,
+                front_matter, dry_run, no_history, progress.into(),
             ).await
         }
-        Commands::Query { kg_path, query, file, format } => {
-            query_command(kg_path, query, file, format).await
+        Commands::Query { kg_path, query, file, format, config, infer, endpoint, xlsx } => {
+            query_command(kg_path, query, file, format, config, infer, endpoint, xlsx).await
+        }
+        Commands::Stats { kg_path, config, format, xlsx } => {
+            stats_command(kg_path, config, format, xlsx).await
+        }
+        Commands::Calibrate { kg_path, config, gold, bins, format, xlsx } => {
+            calibrate_command(kg_path, config, gold, bins, format, xlsx).await
         }
-        Commands::Stats { kg_path, config } => {
-            stats_command(kg_path, config).await
+        Commands::Export { kg_path, config, output, bundle: true, template_dir, .. } => {
+            export_bundle_command(kg_path, config, output, template_dir)
         }
-        Commands::Export { kg_path, config, output, format } => {
-            export_command(kg_path, config, output, format).await
+        Commands::Export { kg_path, config, output, format, since, manifest, sign_key, canonical, .. } => {
+            export_command(kg_path, config, output, format, since, manifest, sign_key, canonical).await
         }
-        Commands::ListTemplates { template_dir } => {
-            list_templates_command(template_dir).await
+        Commands::Import { config, mapping, kg_path, dry_run, preview } => {
+            import_command(config, mapping, kg_path, dry_run, preview).await
+        }
+        Commands::VerifyExport { manifest, public_key } => verify_export_command(manifest, public_key).await,
+        Commands::EntityFeed { kg_path, config, output, types } => entity_feed_command(kg_path, config, output, types),
+        Commands::Enrich { kg_path, config, source, properties, types, language, delay_ms, limit, offline, dry_run } => {
+            enrich_command(kg_path, config, source.into(), properties, types, language, delay_ms, limit, offline, dry_run).await
+        }
+        Commands::Visualize { kg_path, config, output, format, query, entity, hops, limit } => {
+            visualize_command(kg_path, config, output, format, query, entity, hops, limit).await
+        }
+        Commands::Serve { kg_path, config, bind, allow_anonymous, grpc_bind, template_dir, read_only } => {
+            serve_command(kg_path, config, bind, allow_anonymous, grpc_bind, template_dir, read_only).await
+        }
+        Commands::McpServe { kg_path, config, template_dir, read_only } => mcp_serve_command(kg_path, config, template_dir, read_only).await,
+        Commands::Kg { command } => kg_command(command).await,
+        Commands::Generations { kg_path, command } => generations_command(kg_path, command),
+        Commands::Approvals { kg_path, command } => approvals_command(kg_path, command),
+        Commands::Queries { kg_path, command } => queries_command(kg_path, command).await,
+        Commands::Namespace { kg_path, command } => namespace_command(kg_path, command),
+        Commands::Views { kg_path, command } => views_command(kg_path, command).await,
+        Commands::Schema { command } => schema_command(command).await,
+        Commands::Feedback { kg_path, command } => feedback_command(kg_path, command),
+        Commands::ListTemplates { template_dir, preview } => {
+            list_templates_command(template_dir, preview).await
+        }
+        Commands::ValidateTemplate { template, template_id, kg_path, config, format } => {
+            validate_template_command(template, template_id, kg_path, config, format)
         }
         Commands::Validate { config } => validate_command(config).await,
+        Commands::PromptTest { config, cases, server_url, api_key, model, no_cache, vars, report } => {
+            prompt_test_command(config, cases, server_url, api_key, model, no_cache, vars, report).await
+        }
         Commands::CheckServer { server_url, api_key } => {
             check_server_command(server_url, api_key).await
         }
@@ -355,13 +1626,83 @@ async fn main() -> Result<()> {
         } => {
             demo_command(skip_extraction, template, output, server_url, model).await
         }
+        Commands::Completions { shell, man_dir } => {
+            completions_command(shell, man_dir)
+        }
+    }
+}
+
+/// `--var name=value`
+fn parse_variable(arg: &str) -> std::result::Result<(String, String), String> {
+    arg.split_once('=')
+        .filter(|(name, _)| !name.is_empty())
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .ok_or_else(|| format!("expected NAME=VALUE, got '{}'", arg))
+}
+
+/// Replace each URL input with the pages crawled from it
+async fn crawl_inputs(input: Vec<String>, options: CrawlOptions) -> Result<Vec<String>> {
+    let crawler = Crawler::new(options)?;
+    let mut expanded = Vec::new();
+    for source in input {
+        if !(source.starts_with("http://") || source.starts_with("https://")) {
+            expanded.push(source);
+            continue;
+        }
+
+        let pages = crawler.crawl(&source).await?;
+        println!(" Crawled {}: {} page(s)", source.bright_green(), pages.len());
+        for page in pages {
+            if !expanded.contains(&page) {
+                expanded.push(page);
+            }
+        }
     }
+    Ok(expanded)
+}
+
+/// Replace each input with the sources of its top-level sections
+async fn split_inputs(extractor: &RdfExtractor, input: Vec<String>) -> Vec<String> {
+    let mut expanded = Vec::new();
+    for source in input {
+        // A document that cannot be read is kept whole, so extracting it records the failure
+        match extractor.section_sources(&source).await {
+            Ok(sections) => {
+                if sections.len() > 1 {
+                    println!(" Split {}: {} section(s)", source.bright_green(), sections.len());
+                }
+                expanded.extend(sections);
+            }
+            Err(e) => {
+                warn!(" Could not split {} into sections: {}", source, e);
+                expanded.push(source);
+            }
+        }
+    }
+    expanded
+}
+
+/// Where `extract --nats-subject` consumes documents from and publishes triples to
+struct NatsOptions {
+    url: String,
+    subject: String,
+    queue_group: Option<String>,
+    publish: Option<String>,
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn extract_command(
     config_path: PathBuf,
     input: Vec<String>,
+    watch: Option<PathBuf>,
+    nats: Option<NatsOptions>,
+    crawl: Option<CrawlOptions>,
+    split_sections: bool,
+    remote: Option<RemoteStore>,
     kg_path: String,
+    write_batch_size: Option<usize>,
+    flush_interval_ms: Option<u64>,
+    lock_timeout_ms: Option<u64>,
     output: Option<PathBuf>,
     format: OutputFormatArg,
     server_url: String,
@@ -369,443 +1710,2359 @@ async fn extract_command(
     model_override: Option<String>,
     merge: bool,
     validate: bool,
+    no_cache: bool,
+    graph: Option<GraphTarget>,
+    usage_report: Option<PathBuf>,
+    resume: Option<String>,
+    vars: Vec<(String, String)>,
+    corrections: usize,
+    document_timeout: Option<u64>,
+    progress: ProgressMode,
 ) -> Result<()> {
     println!("{}", "Starting RDF extraction...".bright_blue().bold());
 
-    // Load configuration
-    let mut config = Configuration::from_file(&config_path)?;
-    config.validate()?;
+    // Load configuration and apply overrides
+    let mut builder = RdfExtractorBuilder::from_config_file(&config_path)?
+        .cache(!no_cache)
+        .budget_confirmation(Box::new(confirm_budget_overrun));
+    if server_url != "http://localhost:8000" {
+        builder = builder.server_url(server_url);
+    }
+    if let Some(key) = api_key {
+        builder = builder.api_key(key);
+    }
+    if let Some(model) = model_override {
+        builder = builder.model(model);
+    }
+    for (name, value) in vars {
+        builder = builder.variable(name, value);
+    }
+    if let Some(seconds) = document_timeout {
+        builder = builder.document_timeout(std::time::Duration::from_secs(seconds));
+    }
+    let corrections = FeedbackStore::open_for(&kg_path)?.recent(corrections);
+    let correction_count = corrections.len();
+    builder = builder.corrections(corrections);
+    let config = builder.config().clone();
+    let format: OutputFormat = format.into();
+    let mut extractor = builder.build()?;
+
+    // Track per-document progress so an interrupted run can be resumed; a
+    // crawl profile's resume file carries the job across runs by itself
+    let resume_file = config.crawl.as_ref().and_then(|profile| profile.resume_file.clone());
+    let resuming = resume.is_some() || resume_file.as_ref().is_some_and(|path| path.exists());
+    let mut manifest = match (&resume, &resume_file) {
+        (Some(job_id), _) if job_id == "latest" => JobManifest::list_for(&kg_path)?.into_iter().next()
+            .ok_or_else(|| anyhow::anyhow!("No extraction jobs recorded for {}", kg_path))?,
+        (Some(job_id), _) => JobManifest::open(&kg_path, job_id)?,
+        (None, Some(path)) if path.exists() => {
+            let manifest = JobManifest::open_path(path.clone())?;
+            if !input.is_empty() && !manifest.started_with(&input) {
+                warn!(
+                    " {} holds job {}, started with other inputs; continuing it. Delete the file to start a new job",
+                    path.display(),
+                    manifest.job_id
+                );
+            }
+            manifest
+        }
+        (None, _) => {
+            let given = input.clone();
+            let input = match &crawl {
+                Some(options) => crawl_inputs(input, options.clone()).await?,
+                None => input,
+            };
+            let input = match split_sections {
+                true => split_inputs(&extractor, input).await,
+                false => input,
+            };
+            let mut manifest = JobManifest::create(&kg_path, &input, graph.as_ref().map(ToString::to_string));
+            manifest.split_sections = split_sections;
+            match &resume_file {
+                Some(path) => manifest.with_path(path.clone()).with_inputs(&given),
+                None => manifest,
+            }
+        }
+    };
+    let graph = graph.or_else(|| manifest.graph.as_deref().and_then(|g| g.parse().ok()));
+    let input = manifest.remaining();
+    extractor.split_sections(manifest.split_sections);
+
+    println!(" Configuration: {}", config.name.bright_green());
+    println!(" Questions: {}", config.extraction_questions.len());
+    println!(" Documents: {}", input.len());
+    if correction_count > 0 {
+        println!(" Reviewer corrections: {}", correction_count);
+    }
+    let track_job = !manifest.documents.is_empty();
+    if track_job {
+        if resuming {
+            println!(
+                " Resuming job {} ({} completed, {} to go)",
+                manifest.job_id.bright_cyan(),
+                manifest.count(DocumentStatus::Completed),
+                input.len()
+            );
+        } else if resume_file.is_some() {
+            println!(" Job: {} (rerun to resume from {})", manifest.job_id.bright_cyan(), manifest.path().display());
+        } else {
+            println!(" Job: {} (resume with --resume {})", manifest.job_id.bright_cyan(), manifest.job_id);
+        }
+        manifest.save()?;
+    }
+
+    // Check server health
+    if !extractor.llm_client().check_health().await? {
+        error!(" vLLM server is not responding at {}", config.llm_settings.base_url);
+        return Err(anyhow::anyhow!("vLLM server health check failed"));
+    }
+
+    println!(" vLLM server is healthy");
+
+    // Create knowledge graph; waiting for the store lock blocks, so keep it
+    // off the async workers
+    let builder = KnowledgeGraphBuilder::new()
+        .storage_path(kg_path.clone())
+        .schema(config.rdf_schema.clone())
+        .literal_normalization(config.literal_normalization.clone())
+        .write_batch_size(write_batch_size)
+        .flush_interval(flush_interval_ms.map(std::time::Duration::from_millis))
+        .lock_timeout(lock_timeout_ms.map(std::time::Duration::from_millis));
+    let knowledge_graph = tokio::task::spawn_blocking(move || builder.build()).await??;
+
+    // Detect model capabilities
+    match extractor.auto_configure().await {
+        Ok(Some(caps)) => println!(
+            " Model context window: {}, guided JSON: {}, tool calling: {}",
+            caps.context_window.map(|w| w.to_string()).unwrap_or_else(|| "unknown".to_string()),
+            caps.supports_guided_json,
+            caps.supports_tool_calling
+        ),
+        Ok(None) => {}
+        Err(e) => warn!(" Could not detect model capabilities: {}", e),
+    }
+
+    // Link new entities to existing ones before their URIs are stored
+    let linker = match (config.entity_linking.enabled, &remote) {
+        (true, None) => {
+            let client = EmbeddingClient::from_settings(&config.entity_linking, &config.llm_settings)?;
+            Some(EntityLinker::new(client, &config.entity_linking).with_cache(EntityLinker::cache_path_for(&kg_path))?)
+        }
+        (true, Some(_)) => {
+            warn!(" Entity linking needs the local knowledge graph and is skipped with --remote");
+            None
+        }
+        (false, _) => None,
+    };
+
+    // The first Ctrl-C stops after storing the documents done so far; the second exits
+    let cancellation = extractor.cancellation_token();
+    let interrupt = tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            warn!(" Interrupted; recording the remaining documents as cancelled (Ctrl-C again to exit)");
+            cancellation.cancel();
+            if tokio::signal::ctrl_c().await.is_ok() {
+                std::process::exit(130);
+            }
+        }
+    });
+
+    // Documents are stored as each one completes unless merging
+    let mut pipeline = Pipeline::new(extractor, knowledge_graph)
+        .merge_results(merge)
+        .validate(validate)
+        .graph(graph.clone())
+        .manifest(manifest)
+        .progress(progress);
+    if let Some(output_path) = &output {
+        pipeline = pipeline.export(output_path, format.clone());
+    }
+    if let Some(linker) = linker {
+        pipeline = pipeline.link_entities(linker);
+    }
+    let remote_url = remote.as_ref().map(|remote| remote.url().to_string());
+    if let Some(remote) = remote {
+        pipeline = pipeline.remote(remote);
+    }
+    let report = pipeline.run(input).await?;
+    interrupt.abort();
+
+    if !report.unknown_predicates.is_empty() {
+        let predicates: std::collections::HashSet<_> = report.unknown_predicates.iter().map(|t| &t.predicate).collect();
+        println!(
+            " Dropped {} triples with {} predicate(s) outside the schema; review them with: schema drift --kg-path {}",
+            report.unknown_predicates.len().to_string().bright_yellow(),
+            predicates.len(),
+            kg_path
+        );
+    }
+    if config.entity_linking.enabled && remote_url.is_none() {
+        println!(
+            " Linked {} new entities to existing ones ({} owl:sameAs)",
+            report.linked_entities + report.same_as_links,
+            report.same_as_links
+        );
+    }
+    match &remote_url {
+        Some(url) => println!(" Stored {} triples in remote store: {}", report.stored_triples.to_string().bright_cyan(), url.bright_green()),
+        None => println!(" Stored {} triples in knowledge graph: {}", report.stored_triples.to_string().bright_cyan(), kg_path.bright_green()),
+    }
+    if report.refreshed_views > 0 {
+        println!(" Refreshed {} materialized view(s)", report.refreshed_views);
+    }
+    for path in &report.exported_files {
+        println!(" Export written to: {}", path.display().to_string().bright_green());
+    }
+
+    // Summary
+    let total_time: f64 = report.results.iter().map(|r| r.processing_time_seconds).sum();
+    let usage = &report.usage;
+
+    println!("\n{}", " Extraction Summary".bright_green().bold());
+    println!(" Total triples extracted: {}", report.extracted_triples.to_string().bright_cyan());
+    println!(" Total processing time: {:.2}s", total_time);
+    println!(" LLM usage: {}", usage.total);
+    if usage.documents.len() > 1 {
+        let documents = usage.documents.len() as f64;
+        println!(
+            " Per document: {:.0} tokens, estimated cost {:.4} on average",
+            usage.total.total_tokens as f64 / documents,
+            usage.total.estimated_cost / documents
+        );
+    }
+    if let Some(path) = &usage_report {
+        let json = serde_json::to_string_pretty(usage)?;
+        std::fs::write(path, json)?;
+        println!(" Usage report written to: {}", path.display().to_string().bright_green());
+    }
+    if let Some(manifest) = report.manifest.as_ref().filter(|_| track_job) {
+        let failed = manifest.count(DocumentStatus::Failed);
+        if failed > 0 {
+            // A resume file is picked up by rerunning; --resume only finds jobs in the job directory
+            let retry = match &resume_file {
+                Some(_) => "rerun the same command".to_string(),
+                None => format!("--resume {}", manifest.job_id),
+            };
+            println!(" {} document(s) failed; retry them with {}", failed.to_string().bright_yellow(), retry);
+        } else if resume_file.is_some() && manifest.remaining().is_empty() {
+            // A finished job's resume file would make the next run skip its inputs
+            manifest.remove()?;
+            println!(" Job {} complete; removed {}", manifest.job_id.bright_cyan(), manifest.path().display());
+        }
+    }
+
+    if report.has_errors() {
+        println!(" {} completed with some errors", "Extraction".bright_yellow());
+    } else {
+        println!(" {} completed successfully!", "Extraction".bright_green());
+    }
+
+    let PipelineReport { extractor, mut knowledge_graph, run_graph, .. } = report;
+    let mut views = ViewStore::open_for(&kg_path)?;
+    if let Some(watch_dir) = watch {
+        watch_and_extract(&extractor, &mut knowledge_graph, &mut views, &watch_dir, graph.as_ref(), &run_graph).await?;
+    }
+
+    if let Some(nats) = nats {
+        consume_and_extract(&extractor, &mut knowledge_graph, &mut views, &nats, &format, graph.as_ref(), &run_graph).await?;
+    }
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn compare_command(
+    config_path: PathBuf,
+    input: Vec<String>,
+    output: Option<PathBuf>,
+    server_url: String,
+    api_key: Option<String>,
+    variants: Vec<String>,
+    no_cache: bool,
+    vars: Vec<(String, String)>,
+) -> Result<()> {
+    println!("{}", "Starting A/B extraction comparison...".bright_blue().bold());
+
+    if variants.len() != 2 {
+        anyhow::bail!("--compare expects exactly two models or config files, got {}", variants.len());
+    }
+
+    let mut base_config = Configuration::from_file(&config_path)?;
+    base_config.validate()?;
+    if server_url != "http://localhost:8000" {
+        base_config.llm_settings.base_url = server_url;
+    }
+    if let Some(key) = api_key {
+        base_config.llm_settings.api_key = Some(key);
+    }
+
+    let mut runs = Vec::new();
+    for variant in &variants {
+        // A variant is either a config profile file or a model name
+        let mut config = if std::path::Path::new(variant).is_file() {
+            let config = Configuration::from_file(variant)?;
+            config.validate()?;
+            config
+        } else {
+            let mut config = base_config.clone();
+            config.llm_settings.model = variant.clone();
+            config
+        };
+
+        if no_cache {
+            config.llm_settings.cache.enabled = false;
+        }
+        config.variables.extend(vars.iter().cloned());
+        config.resolve_variables()?;
+
+        println!(" Running {} ({})", variant.bright_green(), config.llm_settings.model);
+
+        let llm_client = VllmClient::from_settings(&config.llm_settings)?;
+
+        let extractor = RdfExtractor::new(config, llm_client);
+        let results = extractor.extract_from_multiple(input.clone()).await?;
+        for result in &results {
+            if !result.errors.is_empty() {
+                warn!(" Errors in {} ({}): {}", result.document_source, variant, result.errors.join(", "));
+            }
+        }
+        runs.push(results);
+    }
+
+    let comparison = ExtractionComparison::new(&variants[0], &runs[0], &variants[1], &runs[1]);
+
+    println!("\n{}", comparison);
+
+    if let Some(output_path) = output {
+        tokio::fs::write(&output_path, serde_json::to_string_pretty(&comparison)?).await?;
+        println!(" Comparison written to: {}", output_path.display().to_string().bright_green());
+    }
+
+    Ok(())
+}
+
+async fn watch_and_extract(
+    extractor: &RdfExtractor,
+    knowledge_graph: &mut KnowledgeGraph,
+    views: &mut ViewStore,
+    watch_dir: &std::path::Path,
+    graph: Option<&GraphTarget>,
+    run_graph: &str,
+) -> Result<()> {
+    println!(
+        "\n Watching {} for new documents (Ctrl+C to stop)...",
+        watch_dir.display().to_string().bright_green()
+    );
+
+    let mut watcher = DocumentWatcher::new(
+        watch_dir,
+        extractor.supported_extensions(),
+        std::time::Duration::from_secs(2),
+    )?;
+
+    loop {
+        let path = tokio::select! {
+            next = watcher.next_document() => match next {
+                Some(path) => path,
+                None => break,
+            },
+            _ = tokio::signal::ctrl_c() => break,
+        };
+
+        let source = path.to_string_lossy().to_string();
+        let result = extractor.extract_within_limits(&source).await?;
+        if !result.errors.is_empty() {
+            warn!(" Errors in {}: {}", result.document_source, result.errors.join(", "));
+        }
+
+        let stored = store_result(knowledge_graph, &result, graph, run_graph)?;
+        println!(" {} → {} new triples", source.bright_cyan(), stored.to_string().bright_cyan());
+
+        if stored > 0 && views.refresh_after_extraction(knowledge_graph)? > 0 {
+            views.save()?;
+        }
+    }
+
+    println!(" Stopped watching. LLM usage: {}", extractor.budget_usage());
+    Ok(())
+}
+
+async fn consume_and_extract(
+    extractor: &RdfExtractor,
+    knowledge_graph: &mut KnowledgeGraph,
+    views: &mut ViewStore,
+    nats: &NatsOptions,
+    format: &OutputFormat,
+    graph: Option<&GraphTarget>,
+    run_graph: &str,
+) -> Result<()> {
+    let mut subscriber = DocumentSubscriber::connect(&nats.url, &nats.subject, nats.queue_group.clone()).await?;
+    println!(
+        "\n Consuming documents from {} on {} (Ctrl+C to stop)...",
+        nats.subject.bright_green(),
+        nats.url
+    );
+
+    loop {
+        let document = tokio::select! {
+            next = subscriber.next_document() => match next {
+                Some(Ok(document)) => document,
+                Some(Err(e)) => {
+                    warn!(" Skipping message: {}", e);
+                    continue;
+                }
+                None => break,
+            },
+            _ = tokio::signal::ctrl_c() => break,
+        };
+
+        let outcome = consume_document(extractor, knowledge_graph, views, &subscriber, &document, nats, format, graph, run_graph).await;
+        subscriber.finish(&document).await;
+        if let Err(e) = outcome {
+            warn!(" Failed to process {}: {}", document.name, e);
+        }
+    }
+
+    println!(" Stopped consuming. LLM usage: {}", extractor.budget_usage());
+    Ok(())
+}
+
+/// Extract and store one document from the queue, then acknowledge it and
+/// publish its triples; a document is only acknowledged once stored
+#[allow(clippy::too_many_arguments)]
+async fn consume_document(
+    extractor: &RdfExtractor,
+    knowledge_graph: &mut KnowledgeGraph,
+    views: &mut ViewStore,
+    subscriber: &DocumentSubscriber,
+    document: &ReceivedDocument,
+    nats: &NatsOptions,
+    format: &OutputFormat,
+    graph: Option<&GraphTarget>,
+    run_graph: &str,
+) -> Result<()> {
+    let result = extractor.extract_within_limits(&document.source).await?.with_source_name(&document.name);
+    if !result.errors.is_empty() {
+        warn!(" Errors in {}: {}", result.document_source, result.errors.join(", "));
+    }
+
+    let stored = store_result(knowledge_graph, &result, graph, run_graph)?;
+    println!(" {} → {} new triples", document.name.bright_cyan(), stored.to_string().bright_cyan());
+    subscriber.acknowledge(document, result.triples.len()).await?;
+
+    if stored > 0 && views.refresh_after_extraction(knowledge_graph)? > 0 {
+        views.save()?;
+    }
+
+    if let Some(subject) = &nats.publish {
+        let schema = &extractor.config().rdf_schema;
+        let payload = knowledge_graph.serializer().serialize(&result.triples, format, &schema.namespace, &schema.prefix)?;
+        subscriber.publish(subject, &document.name, format.media_type(), result.triples.len(), payload).await?;
+    }
+    Ok(())
+}
+
+/// Add a result's triples to the knowledge graph, in the graph chosen by `--graph`
+fn store_result(
+    knowledge_graph: &mut KnowledgeGraph,
+    result: &ExtractionResult,
+    graph: Option<&GraphTarget>,
+    run_graph: &str,
+) -> Result<usize> {
+    Ok(knowledge_graph.add_triples(&target_triples(result, graph, run_graph))?)
+}
+
+fn confirm_budget_overrun(reason: &str) -> bool {
+    use std::io::Write;
+
+    print!(" {} {}. Continue? [y/N] ", "Budget:".bright_yellow(), reason);
+    if std::io::stdout().flush().is_err() {
+        return false;
+    }
+
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn prompt_test_command(
+    config_path: PathBuf,
+    cases_dir: PathBuf,
+    server_url: String,
+    api_key: Option<String>,
+    model_override: Option<String>,
+    no_cache: bool,
+    vars: Vec<(String, String)>,
+    report_path: Option<PathBuf>,
+) -> Result<()> {
+    println!("{}", " Running prompt tests...".bright_blue().bold());
+
+    let cases = PromptTestCase::load_dir(&cases_dir)?;
+    if cases.is_empty() {
+        anyhow::bail!("No test cases (.yaml, .yml or .json) in {}", cases_dir.display());
+    }
+
+    let mut builder = RdfExtractorBuilder::from_config_file(&config_path)?.cache(!no_cache);
+    if server_url != "http://localhost:8000" {
+        builder = builder.server_url(server_url);
+    }
+    if let Some(key) = api_key {
+        builder = builder.api_key(key);
+    }
+    if let Some(model) = model_override {
+        builder = builder.model(model);
+    }
+    for (name, value) in vars {
+        builder = builder.variable(name, value);
+    }
+    let mut extractor = builder.build()?;
+    if let Err(e) = extractor.auto_configure().await {
+        warn!(" Could not detect model capabilities: {}", e);
+    }
+    let config = extractor.config();
+    println!(" Configuration: {}", config.name.bright_green());
+    println!(" Model: {}", config.llm_settings.model.bright_green());
+    println!(" Test cases: {}\n", cases.len());
+
+    let mut outcomes = Vec::new();
+    for case in &cases {
+        let outcome = case.run(&extractor).await;
+        let status = if outcome.passed { "PASS".bright_green() } else { "FAIL".bright_red() };
+        println!(" {} {} ({} triples)", status, outcome.name, outcome.extracted);
+        if let Some(error) = &outcome.error {
+            println!("   {}", error.bright_red());
+        }
+        for triple in &outcome.missing {
+            println!("   {} missing   {}", "-".bright_red(), triple.to_ntriple());
+        }
+        for triple in &outcome.forbidden {
+            println!("   {} forbidden {}", "!".bright_red(), triple.to_ntriple());
+        }
+        if case.exact {
+            for triple in &outcome.unexpected {
+                println!("   {} unexpected {}", "+".bright_yellow(), triple.to_ntriple());
+            }
+        }
+        outcomes.push(outcome);
+    }
+
+    let report = PromptTestReport::new(&config.llm_settings.model, &config.name, outcomes);
+    if let Some(report_path) = report_path {
+        std::fs::write(&report_path, serde_json::to_string_pretty(&report)?)?;
+        println!("\n Report written to: {}", report_path.display().to_string().bright_cyan());
+    }
+
+    println!("\n {} passed, {} failed", report.passed.to_string().bright_green(), report.failed.to_string().bright_red());
+    if report.failed > 0 {
+        anyhow::bail!("{} of {} prompt tests failed", report.failed, report.cases.len());
+    }
+    Ok(())
+}
+
+async fn validate_command(config_path: PathBuf) -> Result<()> {
+    println!("{}", " Validating configuration...".bright_blue().bold());
+
+    match Configuration::from_file(&config_path) {
+        Ok(config) => {
+            match config.validate() {
+                Ok(()) => {
+                    println!(" Configuration is valid!");
+                    println!(" Name: {}", config.name.bright_green());
+                    println!(" Version: {}", config.version);
+                    println!(" Questions: {}", config.extraction_questions.len());
+                    println!(" Namespace: {}", config.rdf_schema.namespace);
+                    println!(" Model: {}", config.llm_settings.model);
+                    Ok(())
+                }
+                Err(e) => {
+                    error!(" Configuration validation failed: {}", e);
+                    Err(e.into())
+                }
+            }
+        }
+        Err(e) => {
+            error!(" Failed to load configuration: {}", e);
+            Err(e.into())
+        }
+    }
+}
+
+async fn check_server_command(server_url: String, api_key: Option<String>) -> Result<()> {
+    println!("{}", " Checking vLLM server...".bright_blue().bold());
+
+    let client = VllmClient::new(
+        server_url.clone(),
+        api_key,
+        "test".to_string(),
+        0.3,
+        1024,
+        30,
+    )?;
+
+    // Check health
+    let is_healthy = client.check_health().await?;
+    if is_healthy {
+        println!(" Server is healthy at {}", server_url.bright_green());
+    } else {
+        println!(" Server is not responding at {}", server_url.bright_red());
+        return Ok(());
+    }
+
+    // List models
+    match client.list_models().await {
+        Ok(models) => {
+            println!(" Available models:");
+            for model in models {
+                println!("  • {}", model.bright_cyan());
+            }
+        }
+        Err(e) => {
+            warn!(" Could not list models: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+async fn generate_config_command(output_path: PathBuf, format: ConfigFormat) -> Result<()> {
+    println!("{}", " Generating example configuration...".bright_blue().bold());
+
+    let config = Configuration::example();
+
+    let content = match format {
+        ConfigFormat::Yaml => serde_yaml::to_string(&config)?,
+        ConfigFormat::Json => serde_json::to_string_pretty(&config)?,
+    };
+
+    tokio::fs::write(&output_path, content).await?;
+
+    println!(" Example configuration generated at: {}", output_path.display().to_string().bright_green());
+    println!(" Edit the file to customize for your use case");
+
+    Ok(())
+}
+
+fn completions_command(shell: Option<clap_complete::Shell>, man_dir: Option<PathBuf>) -> Result<()> {
+    let mut command = Cli::command();
+
+    if let Some(shell) = shell {
+        let name = command.get_name().to_string();
+        clap_complete::generate(shell, &mut command, name, &mut std::io::stdout());
+        return Ok(());
+    }
+
+    let Some(man_dir) = man_dir else { return Ok(()) };
+    std::fs::create_dir_all(&man_dir)?;
+    clap_mangen::generate_to(command, &man_dir)?;
+
+    let pages = std::fs::read_dir(&man_dir)?.filter(|entry| {
+        entry.as_ref().is_ok_and(|e| e.path().extension().is_some_and(|ext| ext == "1"))
+    }).count();
+    println!(" Wrote {} man pages to: {}", pages, man_dir.display().to_string().bright_green());
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn generate_command(
+    config_path: PathBuf,
+    kg_path: String,
+    template_path: String,
+    template_id: Option<String>,
+    output: Option<PathBuf>,
+    server_url: String,
+    api_key: Option<String>,
+    model_override: Option<String>,
+    context: Option<String>,
+    _enhance: bool,
+    no_cache: bool,
+    batch: bool,
+    front_matter: bool,
+    dry_run: bool,
+    no_history: bool,
+    progress: ProgressMode,
+) -> Result<()> {
+    println!("{}", " Starting document generation...".bright_blue().bold());
+
+    // Load configuration
+    let mut config = Configuration::from_file(&config_path)?;
+    config.validate()?;
+
+    // Override LLM settings if provided
+    if server_url != "http://localhost:8000" {
+        config.llm_settings.base_url = server_url;
+    }
+    if let Some(key) = api_key {
+        config.llm_settings.api_key = Some(key);
+    }
+    if let Some(model) = model_override {
+        config.llm_settings.model = model;
+    }
+    if no_cache {
+        config.llm_settings.cache.enabled = false;
+    }
+
+    // Create LLM client
+    let llm_client = VllmClient::from_settings(&config.llm_settings)?;
+
+    // Load knowledge graph
+    let kg_config = KnowledgeGraphConfig {
+        storage_path: kg_path.clone(),
+        read_only: true,
+        ..Default::default()
+    };
+    let knowledge_graph = KnowledgeGraph::new(kg_config, config.rdf_schema.clone())?;
+
+    // Create template manager
+    let mut template_manager = TemplateManager::new(knowledge_graph, Some(llm_client))
+        .with_views(ViewStore::open_for(&kg_path)?)
+        .with_front_matter(front_matter);
+
+    // Load templates
+    if std::path::Path::new(&template_path).is_dir() {
+        template_manager.load_templates_from_directory(&template_path)?;
+    } else {
+        template_manager.load_template(&template_path)?;
+    }
+
+    // Determine template ID
+    let final_template_id = if let Some(id) = template_id {
+        id
+    } else if std::path::Path::new(&template_path).is_file() {
+        // Extract template ID from file (use filename without extension)
+        std::path::Path::new(&template_path)
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("default")
+            .to_string()
+    } else {
+        anyhow::bail!("Template ID required when template path is a directory");
+    };
+
+    if dry_run {
+        let validation = template_manager.validate_template(&final_template_id)?;
+        print_template_validation(&validation);
+        if !validation.is_valid() {
+            anyhow::bail!("Template '{}' has errors", final_template_id);
+        }
+        return Ok(());
+    }
+
+    // Parse additional context
+    let additional_context = if let Some(ctx_str) = context {
+        Some(serde_json::from_str(&ctx_str)?)
+    } else {
+        None
+    };
+
+    // Create generation request
+    let request = TemplateGenerationRequest {
+        template_id: final_template_id.clone(),
+        context: additional_context,
+        override_queries: None,
+        output_path: output.as_ref().map(|p| p.to_string_lossy().to_string()),
+    };
+
+    println!(" Template: {}", final_template_id.bright_green());
+    println!(" Knowledge graph: {}", kg_path.bright_cyan());
+
+    let history = (!no_history).then(|| GenerationHistory::open_for(&kg_path));
+    let approval = config.approval.enabled
+        .then(|| (ApprovalQueue::open_for(&kg_path), ApprovalHooks::new(&config.approval)));
+    if approval.is_some() && output.is_none() && !batch {
+        anyhow::bail!("Approval is enabled: --output is required as the destination to publish to");
+    }
+
+    if batch {
+        let output_dir = output.unwrap_or_else(|| PathBuf::from("."));
+        tokio::fs::create_dir_all(&output_dir).await?;
+
+        let mut reporter: Option<Progress> = None;
+        let mut generated = 0;
+        template_manager.generate_batch_each(&request, async |batch_document, total| {
+            let path = output_dir.join(&batch_document.file_name);
+            match &approval {
+                Some((queue, hooks)) => {
+                    submit_for_approval(queue, hooks, &batch_document.document, &path).await?;
+                }
+                None => tokio::fs::write(&path, &batch_document.document.generated_content).await?,
+            }
+            if let Some(history) = &history {
+                history.record(&batch_document.document, &config.llm_settings.model, Some(&path))?;
+            }
+            let reporter = reporter.get_or_insert_with(|| Progress::start(progress, "generate", total));
+            reporter.println(&format!("   {}", path.display().to_string().bright_green()));
+            reporter.item(&path.display().to_string(), None, None);
+            generated += 1;
+            Ok::<_, anyhow::Error>(())
+        }).await?;
+        reporter.unwrap_or_else(|| Progress::start(progress, "generate", 0)).finish();
+
+        println!(" Generated {} documents in: {}", generated.to_string().bright_cyan(), output_dir.display());
+        return Ok(());
+    }
+
+    // Generate document
+    let generated = template_manager.generate_document(&request).await?;
+
+    // Output or save result
+    if let (Some((queue, hooks)), Some(output_path)) = (&approval, &output) {
+        submit_for_approval(queue, hooks, &generated, output_path).await?;
+    } else if let Some(output_path) = &output {
+        tokio::fs::write(&output_path, &generated.generated_content).await?;
+        println!(" Generated document saved to: {}", output_path.display().to_string().bright_green());
+    } else {
+        println!("\n{}", " Generated Document:".bright_yellow().bold());
+        println!("{}", generated.generated_content);
+    }
+
+    if let Some(history) = &history {
+        let record = history.record(&generated, &config.llm_settings.model, output.as_deref())?;
+        println!(" Recorded generation: {}", record.id.bright_cyan());
+    }
+
+    // Show metadata
+    println!("\n{}", " Generation Metadata:".bright_green().bold());
+    println!(" Word count: {}", generated.metadata.word_count.to_string().bright_cyan());
+    println!(" Processing time: {:.2}s", generated.metadata.processing_time_seconds);
+    println!(" Queries executed: {}", generated.metadata.queries_executed.len());
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn query_command(
+    kg_path: String,
+    query: Option<String>,
+    file: Option<PathBuf>,
+    format: QueryOutputFormat,
+    config_path: Option<PathBuf>,
+    infer: bool,
+    endpoint: Option<String>,
+    xlsx: Option<PathBuf>,
+) -> Result<()> {
+    println!("{}", " Executing SPARQL query...".bright_blue().bold());
+
+    // Get query string
+    let query_string = if let Some(q) = query {
+        q
+    } else if let Some(file_path) = file {
+        tokio::fs::read_to_string(file_path).await?
+    } else {
+        anyhow::bail!("Either --query or --file must be provided");
+    };
+
+    if let Some(endpoint) = endpoint {
+        let results = SparqlEndpoint::new(endpoint)?.query(&query_string).await?;
+        return print_query_results(results, format, xlsx.map(|path| ("Results", path)));
+    }
+
+    // Load knowledge graph
+    let schema = match config_path {
+        Some(path) => Configuration::from_file(&path)?.rdf_schema,
+        None => RdfSchema::default(),
+    };
+    let kg_config = KnowledgeGraphConfig {
+        storage_path: kg_path.clone(),
+        read_only: true,
+        inference: infer,
+        ..Default::default()
+    };
+    let knowledge_graph = KnowledgeGraph::new(kg_config, schema)?;
+
+    // Execute query, fetching SERVICE blocks from their endpoints
+    let results = federation::execute_federated(&knowledge_graph, &query_string).await?;
+    print_query_results(results, format, xlsx.map(|path| ("Results", path)))
+}
+
+/// Print results in `format`, or write them to a workbook sheet when `xlsx`
+/// names one
+fn print_query_results(results: SimpleSparqlResults, format: QueryOutputFormat, xlsx: Option<(&str, PathBuf)>) -> Result<()> {
+    if let Some((sheet, path)) = xlsx {
+        let mut spreadsheet = Spreadsheet::new();
+        spreadsheet.add_query_results(sheet, &results)?;
+        spreadsheet.save(&path)?;
+        println!(" Query results written to {}", path.display().to_string().bright_green());
+        return Ok(());
+    }
+
+    match format {
+        QueryOutputFormat::Table => {
+            println!("{}", " Query Results:".bright_yellow().bold());
+            display_results_as_table(results)?;
+        }
+        QueryOutputFormat::Json => {
+            println!("{}", " Query Results (JSON):".bright_yellow().bold());
+            display_results_as_json(results)?;
+        }
+        QueryOutputFormat::Csv => {
+            println!("{}", " Query Results (CSV):".bright_yellow().bold());
+            display_results_as_csv(results)?;
+        }
+        QueryOutputFormat::Turtle => {
+            println!("{}", " Query Results (Turtle):".bright_yellow().bold());
+            display_results_as_turtle(results)?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn queries_command(kg_path: String, command: QueriesCommands) -> Result<()> {
+    let mut library = QueryLibrary::open_for(&kg_path)?;
+
+    match command {
+        QueriesCommands::Save { name, query, file, description, tags } => {
+            let query_string = if let Some(q) = query {
+                q
+            } else if let Some(file_path) = file {
+                tokio::fs::read_to_string(file_path).await?
+            } else {
+                anyhow::bail!("Either --query or --file must be provided");
+            };
+
+            let replaced = library.insert(&name, &query_string, description, tags);
+            library.save()?;
+            let action = if replaced { "Updated" } else { "Saved" };
+            println!(" {} query: {}", action, name.bright_green());
+        }
+        QueriesCommands::List { tag } => {
+            let queries = library.list(tag.as_deref());
+            if queries.is_empty() {
+                println!(" No saved queries");
+            }
+            for query in queries {
+                let tags = if query.tags.is_empty() {
+                    String::new()
+                } else {
+                    format!(" [{}]", query.tags.join(", "))
+                };
+                println!(" {}{}", query.name.bright_green(), tags.bright_cyan());
+                if let Some(description) = &query.description {
+                    println!("   {}", description);
+                }
+                if let Some(last_run) = query.last_run {
+                    println!("   last run: {}", last_run.format("%Y-%m-%d %H:%M"));
+                }
+            }
+        }
+        QueriesCommands::Tag { name, tags, remove } => {
+            if remove {
+                library.remove_tags(&name, &tags)?;
+            } else {
+                library.add_tags(&name, tags)?;
+            }
+            library.save()?;
+            let tags = library.get(&name).map(|q| q.tags.join(", ")).unwrap_or_default();
+            println!(" Tags for {}: {}", name.bright_green(), tags);
+        }
+        QueriesCommands::Run { name, format, xlsx } => {
+            let query_string = library.mark_run(&name)?;
+            library.save()?;
+            println!("{}", format!(" Running saved query '{}'...", name).bright_blue().bold());
+
+            let knowledge_graph = open_knowledge_graph(&kg_path)?;
+            let results = federation::execute_federated(&knowledge_graph, &query_string).await?;
+            print_query_results(results, format, xlsx.map(|path| (name.as_str(), path)))?;
+        }
+        QueriesCommands::Delete { name } => {
+            if library.remove(&name).is_none() {
+                anyhow::bail!("No saved query named '{}'", name);
+            }
+            library.save()?;
+            println!(" Deleted query: {}", name.bright_green());
+        }
+    }
+
+    Ok(())
+}
+
+/// Hold a document for review instead of writing it to `destination`, and
+/// apply the decision of the approval hooks
+async fn submit_for_approval(
+    queue: &ApprovalQueue,
+    hooks: &ApprovalHooks,
+    document: &GeneratedDocument,
+    destination: &std::path::Path,
+) -> Result<PendingDocument> {
+    let pending = queue.submit(document, destination)?;
+    let pending = match hooks.run(&pending, &queue.content_path(&pending.id)).await {
+        HookDecision::Approve => queue.decide(&pending.id, true, Some("approved by hook".to_string()))?,
+        HookDecision::Reject => queue.decide(&pending.id, false, Some("rejected by hook".to_string()))?,
+        HookDecision::Hold => pending,
+    };
+
+    println!(
+        " Document {} for {} is {}",
+        pending.id.bright_cyan(),
+        destination.display(),
+        pending.status.to_string().bright_yellow()
+    );
+    Ok(pending)
+}
+
+fn approvals_command(kg_path: String, command: ApprovalsCommands) -> Result<()> {
+    let queue = ApprovalQueue::open_for(&kg_path);
+
+    match command {
+        ApprovalsCommands::List { status } => {
+            let documents = queue.list(status.map(ApprovalStatus::from))?;
+            if documents.is_empty() {
+                println!(" No documents under review");
+            }
+            for document in documents {
+                println!(
+                    " {}  {}  {}  -> {}",
+                    document.id.bright_green(),
+                    document.status.to_string().bright_yellow(),
+                    document.template_id.bright_cyan(),
+                    document.destination.display()
+                );
+                if let Some(note) = &document.note {
+                    println!("   {}", note);
+                }
+                if document.status == ApprovalStatus::Pending {
+                    println!("   review: {}", queue.content_path(&document.id).display());
+                }
+            }
+        }
+        ApprovalsCommands::Approve { id, note } => {
+            let document = queue.decide(&id, true, note)?;
+            println!(" Approved {}; publish it with `approvals publish {}`", document.id.bright_green(), document.id);
+        }
+        ApprovalsCommands::Reject { id, note } => {
+            let document = queue.decide(&id, false, note)?;
+            println!(" Rejected {}", document.id.bright_red());
+        }
+        ApprovalsCommands::Publish { ids } => {
+            let ids = if ids.is_empty() {
+                queue.list(Some(ApprovalStatus::Approved))?.into_iter().map(|d| d.id).collect()
+            } else {
+                ids
+            };
+            if ids.is_empty() {
+                println!(" No approved documents to publish");
+            }
+            for id in ids {
+                let document = queue.publish(&id)?;
+                println!(" Published {} to {}", document.id.bright_cyan(), document.destination.display().to_string().bright_green());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn generations_command(kg_path: String, command: GenerationsCommands) -> Result<()> {
+    let history = GenerationHistory::open_for(&kg_path);
+
+    match command {
+        GenerationsCommands::List { template } => {
+            let records = history.list(template.as_deref())?;
+            if records.is_empty() {
+                println!(" No recorded generations");
+            }
+            for record in records {
+                println!(
+                    " {}  {}  {}  {} words  content {}",
+                    record.id.bright_green(),
+                    record.generated_at.format("%Y-%m-%d %H:%M"),
+                    record.template_id.bright_cyan(),
+                    record.word_count,
+                    short_hash(&record.content_hash),
+                );
+            }
+        }
+        GenerationsCommands::Show { id } => {
+            let record = history.get(&id)?;
+            println!("{}", format!(" Generation {}", record.id).bright_blue().bold());
+            println!(" Template: {} (version {})", record.template_id.bright_cyan(), short_hash(&record.template_version));
+            println!(" Generated: {}", record.generated_at.format("%Y-%m-%d %H:%M:%S UTC"));
+            println!(" Model: {}", record.model);
+            if let Some(output_path) = &record.output_path {
+                println!(" Output: {}", output_path);
+            }
+            for (query, hash) in &record.query_hashes {
+                println!("   Query {}: {}", query, short_hash(hash));
+            }
+            println!("\n{}", history.content(&record)?);
+        }
+        GenerationsCommands::Diff { from, to } => {
+            let from = history.get(&from)?;
+            let (from, to) = match to {
+                Some(to) => (from, history.get(&to)?),
+                None => match history.previous(&from)? {
+                    Some(previous) => (previous, from),
+                    None => anyhow::bail!("Generation {} is the first of template '{}'", from.id, from.template_id),
+                },
+            };
+
+            let diff = history.diff(&from, &to)?;
+            println!("{}", format!(" Comparing {} -> {}", diff.from, diff.to).bright_blue().bold());
+            if diff.template_changed {
+                println!(" {}", "Template definition changed".bright_yellow());
+            }
+            if diff.model_changed {
+                println!(" {} {} -> {}", "Model changed:".bright_yellow(), from.model, to.model);
+            }
+            if !diff.changed_queries.is_empty() {
+                println!(" Query results changed: {}", diff.changed_queries.join(", ").bright_yellow());
+            }
+            if diff.content_diff.is_empty() {
+                println!(" {}", "Content unchanged".bright_green());
+            }
+            for line in diff.content_diff.lines() {
+                match line.chars().next() {
+                    Some('+') => println!("{}", line.bright_green()),
+                    Some('-') => println!("{}", line.bright_red()),
+                    Some('@') => println!("{}", line.bright_cyan()),
+                    _ => println!("{}", line),
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn short_hash(hash: &str) -> &str {
+    hash.get(..12).unwrap_or(hash)
+}
+
+fn namespace_command(kg_path: String, command: NamespaceCommands) -> Result<()> {
+    if !std::path::Path::new(&kg_path).exists() {
+        anyhow::bail!("Knowledge graph not found: {}", kg_path);
+    }
+    let open_writable = || KnowledgeGraph::new(
+        KnowledgeGraphConfig { storage_path: kg_path.clone(), ..Default::default() },
+        RdfSchema::default(),
+    );
+
+    match command {
+        NamespaceCommands::Add { prefix, uri } => {
+            match open_writable()?.add_namespace(&prefix, &uri)? {
+                Some(previous) if previous != uri => println!(" Rebound {}: <{}> (was <{}>)", prefix.bright_green(), uri, previous),
+                _ => println!(" Added {}: <{}>", prefix.bright_green(), uri),
+            }
+        }
+        NamespaceCommands::List => {
+            let knowledge_graph = open_knowledge_graph(&kg_path)?;
+            if knowledge_graph.namespaces().is_empty() {
+                println!(" No namespaces defined");
+            }
+            for (prefix, uri) in knowledge_graph.namespaces() {
+                println!(" {}: <{}>", prefix.bright_green(), uri);
+            }
+        }
+        NamespaceCommands::Remove { prefix } => {
+            if open_writable()?.remove_namespace(&prefix)?.is_none() {
+                anyhow::bail!("No namespace with prefix '{}'", prefix);
+            }
+            println!(" Removed namespace: {}", prefix.bright_green());
+        }
+    }
+
+    Ok(())
+}
+
+async fn views_command(kg_path: String, command: ViewsCommands) -> Result<()> {
+    let mut views = ViewStore::open_for(&kg_path)?;
+
+    match command {
+        ViewsCommands::Define { name, query, file, after_extraction } => {
+            let query_string = if let Some(q) = query {
+                q
+            } else if let Some(file_path) = file {
+                tokio::fs::read_to_string(file_path).await?
+            } else {
+                anyhow::bail!("Either --query or --file must be provided");
+            };
+
+            let refresh = if after_extraction { ViewRefresh::AfterExtraction } else { ViewRefresh::OnDemand };
+            views.define(&name, &query_string, refresh);
+            views.save()?;
+            println!(" Defined view: {} (run `views refresh {}` to materialize it)", name.bright_green(), name);
+        }
+        ViewsCommands::List => {
+            let all = views.list();
+            if all.is_empty() {
+                println!(" No materialized views");
+            }
+            for view in all {
+                let refreshed = view.refreshed_at
+                    .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_else(|| "never".to_string());
+                println!(" {} [{:?}] refreshed: {}", view.name.bright_green(), view.refresh, refreshed);
+            }
+        }
+        ViewsCommands::Refresh { name } => {
+            let knowledge_graph = open_knowledge_graph(&kg_path)?;
+            let refreshed = match name {
+                Some(name) => {
+                    views.refresh(&name, &knowledge_graph)?;
+                    1
+                }
+                None => views.refresh_all(&knowledge_graph)?,
+            };
+            views.save()?;
+            println!(" Refreshed {} view(s)", refreshed.to_string().bright_cyan());
+        }
+        ViewsCommands::Show { name, format, xlsx } => {
+            let results = views.results(&name)?.clone();
+            print_query_results(results, format, xlsx.map(|path| (name.as_str(), path)))?;
+        }
+        ViewsCommands::Delete { name } => {
+            if views.remove(&name).is_none() {
+                anyhow::bail!("No materialized view named '{}'", name);
+            }
+            views.save()?;
+            println!(" Deleted view: {}", name.bright_green());
+        }
+    }
+
+    Ok(())
+}
+
+async fn stats_command(kg_path: String, config_path: PathBuf, format: ReportFormat, xlsx: Option<PathBuf>) -> Result<()> {
+    // Load configuration for schema
+    let config = Configuration::from_file(&config_path)?;
+
+    // Load knowledge graph
+    let kg_config = KnowledgeGraphConfig {
+        storage_path: kg_path.clone(),
+        read_only: true,
+        ..Default::default()
+    };
+    let knowledge_graph = KnowledgeGraph::new(kg_config, config.rdf_schema)?;
+
+    // Get statistics
+    let stats = knowledge_graph.get_statistics()?;
+    if let Some(path) = xlsx {
+        let mut spreadsheet = Spreadsheet::new();
+        spreadsheet.add_statistics(&stats)?;
+        spreadsheet.save(&path)?;
+        println!(" Statistics written to {}", path.display().to_string().bright_green());
+        return Ok(());
+    }
+    match format {
+        ReportFormat::Table => {
+            println!("{}", " Knowledge Graph Statistics".bright_blue().bold());
+            println!("{}", stats);
+        }
+        ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&stats)?),
+    }
+
+    Ok(())
+}
+
+async fn calibrate_command(
+    kg_path: String,
+    config_path: PathBuf,
+    gold: Option<PathBuf>,
+    bins: usize,
+    format: ReportFormat,
+    xlsx: Option<PathBuf>,
+) -> Result<()> {
+    let config = Configuration::from_file(&config_path)?;
+
+    let kg_config = KnowledgeGraphConfig {
+        storage_path: kg_path.clone(),
+        read_only: true,
+        ..Default::default()
+    };
+    let knowledge_graph = KnowledgeGraph::new(kg_config, config.rdf_schema.clone())?;
+    let triples = knowledge_graph.triples();
+
+    let report = if let Some(gold_path) = gold {
+        let content = tokio::fs::read_to_string(&gold_path).await?;
+        let gold_triples: Vec<rdf_knowledge_extractor::core::RdfTriple> = serde_json::from_str(&content)?;
+        let labels = calibration::gold_labels(triples, &gold_triples);
+        CalibrationReport::new(triples, &labels, bins, "gold")
+    } else {
+        let labels = calibration::validation_labels(triples, &config.rdf_schema);
+        CalibrationReport::new(triples, &labels, bins, "validation")
+    };
 
-    // Override settings if provided
-    if server_url != "http://localhost:8000" {
-        config.llm_settings.base_url = server_url;
-    }
-    if let Some(key) = api_key {
-        config.llm_settings.api_key = Some(key);
+    if let Some(path) = xlsx {
+        let mut spreadsheet = Spreadsheet::new();
+        spreadsheet.add_calibration(&report)?;
+        spreadsheet.save(&path)?;
+        println!(" Calibration report written to {}", path.display().to_string().bright_green());
+        return Ok(());
     }
-    if let Some(model) = model_override {
-        config.llm_settings.model = model;
+
+    match format {
+        ReportFormat::Table => {
+            println!("{}", " Confidence Calibration Report".bright_blue().bold());
+            println!("{}", report);
+        }
+        ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
     }
 
-    println!(" Configuration: {}", config.name.bright_green());
-    println!(" Questions: {}", config.extraction_questions.len());
-    println!(" Documents: {}", input.len());
+    Ok(())
+}
 
-    // Create LLM client
-    let llm_client = VllmClient::new(
-        config.llm_settings.base_url.clone(),
-        config.llm_settings.api_key.clone(),
-        config.llm_settings.model.clone(),
-        config.llm_settings.temperature,
-        config.llm_settings.max_tokens,
-        config.llm_settings.timeout,
-    )?;
+#[allow(clippy::too_many_arguments)]
+async fn export_command(
+    kg_path: String,
+    config_path: PathBuf,
+    output: PathBuf,
+    format: OutputFormatArg,
+    since: Option<String>,
+    manifest: bool,
+    sign_key: Option<PathBuf>,
+    canonical: bool,
+) -> Result<()> {
+    println!("{}", "📤 Exporting knowledge graph...".bright_blue().bold());
 
-    // Check server health
-    if !llm_client.check_health().await? {
-        error!(" vLLM server is not responding at {}", config.llm_settings.base_url);
-        return Err(anyhow::anyhow!("vLLM server health check failed"));
-    }
+    let with_graphs = match format {
+        OutputFormatArg::NTriples => false,
+        OutputFormatArg::NQuads => true,
+        _ if canonical => anyhow::bail!("--canonical needs --format n-triples or n-quads"),
+        _ => false,
+    };
 
-    println!(" vLLM server is healthy");
+    // Load configuration for schema
+    let config = Configuration::from_file(&config_path)?;
 
-    // Create knowledge graph
+    // Load knowledge graph
     let kg_config = KnowledgeGraphConfig {
         storage_path: kg_path.clone(),
+        read_only: true,
         ..Default::default()
     };
-    let mut knowledge_graph = KnowledgeGraph::new(kg_config, config.rdf_schema.clone())?;
-
-    // Create extractor
-    let extractor = RdfExtractor::new(config.clone(), llm_client);
+    let knowledge_graph = KnowledgeGraph::new(kg_config, config.rdf_schema)?;
 
-    // Process documents
-    let results = extractor.extract_from_multiple(input).await?;
+    // Export to file
+    let output_format: OutputFormat = format.clone().into();
+    let format_str = match format {
+        OutputFormatArg::Turtle => "turtle",
+        OutputFormatArg::JsonLd => "jsonld",
+        OutputFormatArg::NTriples => "ntriples",
+        OutputFormatArg::RdfXml => "rdfxml",
+        OutputFormatArg::Json => "json",
+        OutputFormatArg::NQuads => "nquads",
+        OutputFormatArg::Trig => "trig",
+        OutputFormatArg::Cypher => "cypher",
+        OutputFormatArg::Graphml => "graphml",
+    };
 
-    // Check for errors
-    let mut has_errors = false;
-    for result in &results {
-        if !result.errors.is_empty() {
-            has_errors = true;
-            warn!(" Errors in {}: {}", result.document_source, result.errors.join(", "));
+    let mut triples = match since {
+        Some(since) => {
+            let since = resolve_since(&kg_path, &since)?;
+            let triples = knowledge_graph.triples_since(since);
+            println!(" Triples added since {}: {}", since.to_rfc3339(), triples.len().to_string().bright_cyan());
+            std::borrow::Cow::Owned(triples)
         }
+        None => std::borrow::Cow::Borrowed(knowledge_graph.triples()),
+    };
+    if canonical {
+        let content = canonicalize(&triples, with_graphs);
+        std::fs::write(&output, &content)?;
+        // The manifest must describe the file as written, with canonical labels
+        triples = std::borrow::Cow::Owned(parse_rdf(&content, &output_format)?);
+    } else {
+        knowledge_graph.export_triples_to_file(&triples, output.to_str().unwrap(), format_str)?;
     }
 
-    // Merge results if requested
-    let final_results = if merge && results.len() > 1 {
-        println!(" Merging results...");
-        vec![extractor.merge_results(results)?]
-    } else {
-        results
-    };
+    println!(" Export completed: {}", output.display().to_string().bright_green());
 
-    // Validate triples if requested
-    if validate {
-        for result in &final_results {
-            let issues = rdf_knowledge_extractor::utils::validate_rdf_triples(&result.triples);
-            if !issues.is_empty() {
-                warn!(" Validation issues in {}: {}", result.document_source, issues.join(", "));
+    if manifest || sign_key.is_some() {
+        let manifest_path = ExportManifest::path_for(&output);
+        let mut manifest = ExportManifest::new();
+        manifest.add_file(&output, &output_format, &triples)?;
+        match &sign_key {
+            Some(key) => {
+                let signature = manifest.write_signed(&manifest_path, key)?;
+                println!(" Signature written to: {}", signature.display().to_string().bright_green());
             }
+            None => manifest.write(&manifest_path)?,
         }
+        println!(
+            " Manifest written to: {} (graph hash {})",
+            manifest_path.display().to_string().bright_green(),
+            manifest.graph_hash
+        );
     }
 
-    // Store triples in knowledge graph
-    let mut total_stored = 0;
-    for result in &final_results {
-        let stored = knowledge_graph.add_triples(&result.triples)?;
-        total_stored += stored;
-    }
-    println!(" Stored {} triples in knowledge graph: {}", total_stored.to_string().bright_cyan(), kg_path.bright_green());
-
-    // Export to file if requested
-    if let Some(output_path) = &output {
-        let mut serializer = RdfSerializer::new();
-        let output_format = format.into();
-
-        for (i, result) in final_results.iter().enumerate() {
-            let serialized = serializer.serialize(
-                &result.triples,
-                &output_format,
-                &config.rdf_schema.namespace,
-                &config.rdf_schema.prefix,
-            )?;
+    Ok(())
+}
 
-            let final_path = if final_results.len() > 1 && !merge {
-                let stem = output_path.file_stem().unwrap().to_str().unwrap();
-                let extension = output_path.extension().unwrap_or_default().to_str().unwrap();
-                output_path.with_file_name(format!("{}_{}.{}", stem, i + 1, extension))
-            } else {
-                output_path.clone()
-            };
+async fn import_command(config_path: PathBuf, mapping_path: PathBuf, kg_path: String, dry_run: bool, preview: bool) -> Result<()> {
+    println!("{}", "Importing structured data...".bright_blue().bold());
 
-            tokio::fs::write(&final_path, &serialized).await?;
-            println!(" Export written to: {}", final_path.display().to_string().bright_green());
+    let config = Configuration::from_file(&config_path)?;
+    let mapping = MappingDocument::from_file(&mapping_path)?;
+    let triples = mapping.apply(&config.rdf_schema)?;
+    println!(
+        " Mapped {} triples from {} table(s)",
+        triples.len().to_string().bright_cyan(),
+        mapping.triples_maps.len()
+    );
+
+    if dry_run {
+        for triple in &triples {
+            println!("{}", triple.to_ntriple());
         }
+        return Ok(());
     }
 
-    // Summary
-    let total_triples: usize = final_results.iter().map(|r| r.triples.len()).sum();
-    let total_time: f64 = final_results.iter().map(|r| r.processing_time_seconds).sum();
+    let builder = KnowledgeGraphBuilder::new()
+        .schema(config.rdf_schema.clone())
+        .literal_normalization(config.literal_normalization.clone());
+    if preview {
+        // A store that does not exist yet previews as empty
+        let knowledge_graph = if Path::new(&kg_path).exists() {
+            builder.storage_path(kg_path.clone()).read_only(true).build()?
+        } else {
+            builder.in_memory().build()?
+        };
+        print_merge_preview(&mapping_path.display().to_string(), &knowledge_graph.preview_add(&triples));
+        return Ok(());
+    }
 
-    println!("\n{}", " Extraction Summary".bright_green().bold());
-    println!(" Total triples extracted: {}", total_triples.to_string().bright_cyan());
-    println!(" Total processing time: {:.2}s", total_time);
+    let mut knowledge_graph = builder.storage_path(kg_path.clone()).build()?;
+    let added = knowledge_graph.add_triples(&triples)?;
+    println!(" Stored {} new triples in knowledge graph: {}", added.to_string().bright_cyan(), kg_path.bright_green());
 
-    if has_errors {
-        println!(" {} completed with some errors", "Extraction".bright_yellow());
-    } else {
-        println!(" {} completed successfully!", "Extraction".bright_green());
+    let mut views = ViewStore::open_for(&kg_path)?;
+    if added > 0 && views.refresh_after_extraction(&knowledge_graph)? > 0 {
+        views.save()?;
     }
 
     Ok(())
 }
 
-async fn validate_command(config_path: PathBuf) -> Result<()> {
-    println!("{}", " Validating configuration...".bright_blue().bold());
+/// Archive the knowledge graph with everything needed to reproduce it
+fn export_bundle_command(kg_path: String, config_path: PathBuf, output: PathBuf, template_dir: Option<PathBuf>) -> Result<()> {
+    println!("{}", "📦 Bundling knowledge project...".bright_blue().bold());
 
-    match Configuration::from_file(&config_path) {
-        Ok(config) => {
-            match config.validate() {
-                Ok(()) => {
-                    println!(" Configuration is valid!");
-                    println!(" Name: {}", config.name.bright_green());
-                    println!(" Version: {}", config.version);
-                    println!(" Questions: {}", config.extraction_questions.len());
-                    println!(" Namespace: {}", config.rdf_schema.namespace);
-                    println!(" Model: {}", config.llm_settings.model);
-                    Ok(())
-                }
-                Err(e) => {
-                    error!(" Configuration validation failed: {}", e);
-                    Err(e)
-                }
-            }
+    let config = Configuration::from_file(&config_path)?;
+    let kg_config = KnowledgeGraphConfig {
+        storage_path: kg_path.clone(),
+        read_only: true,
+        ..Default::default()
+    };
+    let knowledge_graph = KnowledgeGraph::new(kg_config, config.rdf_schema.clone())?;
+    let mut bundle = ProjectBundle::new(&config.name, knowledge_graph.triples());
+
+    // The config, with its ontology where the config expects it
+    bundle.add_path("config", &config_path)?;
+    if let Some(ontology) = config.rdf_schema.ontology_file.as_ref().filter(|path| path.is_relative()) {
+        if ontology.components().any(|c| c == std::path::Component::ParentDir) {
+            anyhow::bail!(
+                "The ontology file {} is outside the config's directory; move it below {} to bundle it",
+                ontology.display(),
+                config_path.parent().unwrap_or(Path::new(".")).display()
+            );
         }
-        Err(e) => {
-            error!(" Failed to load configuration: {}", e);
-            Err(e)
+        let path = config_path.parent().unwrap_or(Path::new(".")).join(ontology);
+        let content = std::fs::read(&path)?;
+        bundle.add_file(&format!("config/{}", normalize_source(&ontology.to_string_lossy())), content)?;
+    }
+    if let Some(template_dir) = &template_dir {
+        let count = bundle.add_path("templates", template_dir)?;
+        println!(" Templates: {} file(s) from {}", count, template_dir.display());
+    }
+
+    // State kept next to the store: namespace prefixes, reviewer feedback and pending approvals
+    for (name, path) in [("namespaces.json", NamespaceMap::path_for(&kg_path)), ("feedback.json", FeedbackStore::path_for(&kg_path))] {
+        if path.exists() {
+            bundle.add_file(&format!("state/{}", name), std::fs::read(&path)?)?;
         }
     }
-}
+    bundle.add_path("state/approvals", &ApprovalQueue::dir_for(&kg_path))?;
+
+    // Run records: extraction jobs and generated documents
+    let jobs = bundle.add_path("runs/jobs", &JobManifest::dir_for(&kg_path))?;
+    let history = GenerationHistory::open_for(&kg_path);
+    let generations = history.list(None)?;
+    let history_path = GenerationHistory::dir_for(&kg_path).join("history.jsonl");
+    if history_path.exists() {
+        bundle.add_file("runs/generations.jsonl", std::fs::read(&history_path)?)?;
+    }
+    let templates_used: std::collections::BTreeSet<String> = generations.iter().map(|r| r.template_id.clone()).collect();
+    bundle.set_templates_used(templates_used.into_iter().collect());
 
-async fn check_server_command(server_url: String, api_key: Option<String>) -> Result<()> {
-    println!("{}", " Checking vLLM server...".bright_blue().bold());
+    bundle.write(&output)?;
 
-    let client = VllmClient::new(
-        server_url.clone(),
-        api_key,
-        "test".to_string(),
-        0.3,
-        1024,
-        30,
-    )?;
+    let manifest = bundle.manifest();
+    println!(" Triples: {} (graph hash {})", manifest.triple_count.to_string().bright_cyan(), manifest.graph_hash);
+    println!(" Runs: {} extraction job(s), {} generated document(s)", jobs, generations.len());
+    println!(" Bundle written to: {} ({} files)", output.display().to_string().bright_green(), manifest.files.len() + 1);
+    Ok(())
+}
 
-    // Check health
-    let is_healthy = client.check_health().await?;
-    if is_healthy {
-        println!(" Server is healthy at {}", server_url.bright_green());
-    } else {
-        println!(" Server is not responding at {}", server_url.bright_red());
+async fn verify_export_command(manifest: PathBuf, public_key: Option<PathBuf>) -> Result<()> {
+    if manifest.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("zip")) {
+        let verification = ProjectBundle::verify(&manifest)?;
+        for file in &verification.manifest.files {
+            let status = if verification.mismatched.contains(&file.path) { "sha256 mismatch".red() } else { "sha256 ok".green() };
+            println!(" {}: {}", file.path, status);
+        }
+        for path in &verification.unlisted {
+            println!(" {}: {}", path, "not in the manifest".red());
+        }
+        if !verification.is_valid() {
+            anyhow::bail!("Bundle does not match its manifest");
+        }
+        let bundle = verification.manifest;
+        println!(" {} ({} triples from {}, created {})", "Bundle verified".bright_green(), bundle.triple_count, bundle.config_name, bundle.created_at.to_rfc3339());
         return Ok(());
     }
 
-    // List models
-    match client.list_models().await {
-        Ok(models) => {
-            println!(" Available models:");
-            for model in models {
-                println!("  • {}", model.bright_cyan());
-            }
-        }
-        Err(e) => {
-            warn!(" Could not list models: {}", e);
-        }
+    let verification = ExportManifest::verify(&manifest, public_key.as_deref())?;
+
+    for file in &verification.files {
+        let graph = match file.graph_hash_matches {
+            Some(true) => "graph hash ok".green(),
+            Some(false) => "graph hash mismatch".red(),
+            None => "graph hash not checked".yellow(),
+        };
+        let sha = if file.sha256_matches { "sha256 ok".green() } else { "sha256 mismatch".red() };
+        println!(" {}: {}, {}", file.path, sha, graph);
+    }
+    match &verification.signature {
+        SignatureCheck::Valid => println!(" Signature: {}", "valid".green()),
+        SignatureCheck::Invalid(reason) => println!(" Signature: {} ({})", "invalid".red(), reason),
+        SignatureCheck::Unsigned => println!(" Signature: {}", "none".yellow()),
+        SignatureCheck::NotChecked => println!(" Signature: {} (pass --public-key)", "not checked".yellow()),
     }
 
+    if !verification.is_valid() {
+        anyhow::bail!("Export does not match its manifest");
+    }
+    println!(" {}", "Export verified".bright_green());
     Ok(())
 }
 
-async fn generate_config_command(output_path: PathBuf, format: ConfigFormat) -> Result<()> {
-    println!("{}", " Generating example configuration...".bright_blue().bold());
-
-    let config = Configuration::example();
-
-    let content = match format {
-        ConfigFormat::Yaml => serde_yaml::to_string(&config)?,
-        ConfigFormat::Json => serde_json::to_string_pretty(&config)?,
+fn entity_feed_command(kg_path: String, config_path: Option<PathBuf>, output: PathBuf, types: Vec<String>) -> Result<()> {
+    let schema = match config_path {
+        Some(path) => Configuration::from_file(&path)?.rdf_schema,
+        None => RdfSchema::default(),
     };
-
-    tokio::fs::write(&output_path, content).await?;
-
-    println!(" Example configuration generated at: {}", output_path.display().to_string().bright_green());
-    println!(" Edit the file to customize for your use case");
-
+    let types: Vec<String> = types.iter().map(|t| schema.resolve_term(t)).collect();
+    let kg_config = KnowledgeGraphConfig {
+        storage_path: kg_path,
+        read_only: true,
+        ..Default::default()
+    };
+    let knowledge_graph = KnowledgeGraph::new(kg_config, schema)?;
+
+    let mut feed = EntityFeed::open(&output)?;
+    let update = feed.update(&knowledge_graph, &types)?;
+    println!(
+        " Entity feed {}: {} written, {} unchanged, {} removed",
+        output.display().to_string().bright_green(),
+        update.written.to_string().bright_cyan(),
+        update.unchanged,
+        update.removed
+    );
     Ok(())
 }
 
-async fn generate_command(
-    config_path: PathBuf,
+#[allow(clippy::too_many_arguments)]
+async fn enrich_command(
     kg_path: String,
-    template_path: String,
-    template_id: Option<String>,
-    output: Option<PathBuf>,
-    server_url: String,
-    api_key: Option<String>,
-    model_override: Option<String>,
-    context: Option<String>,
-    enhance: bool,
+    config_path: Option<PathBuf>,
+    source: ExternalSource,
+    properties: Vec<ExternalPropertyArg>,
+    types: Vec<String>,
+    language: String,
+    delay_ms: u64,
+    limit: Option<usize>,
+    offline: bool,
+    dry_run: bool,
 ) -> Result<()> {
-    println!("{}", " Starting document generation...".bright_blue().bold());
-
-    // Load configuration
-    let mut config = Configuration::from_file(&config_path)?;
-    config.validate()?;
-
-    // Override LLM settings if provided
-    if server_url != "http://localhost:8000" {
-        config.llm_settings.base_url = server_url;
+    let schema = match config_path {
+        Some(path) => Configuration::from_file(&path)?.rdf_schema,
+        None => RdfSchema::default(),
+    };
+    let options = EnrichOptions {
+        properties: match properties.is_empty() {
+            true => ExternalProperty::ALL.to_vec(),
+            false => properties.into_iter().map(Into::into).collect(),
+        },
+        types: types.iter().map(|t| schema.resolve_term(t)).collect(),
+        limit,
+    };
+    let kg_config = KnowledgeGraphConfig {
+        storage_path: kg_path.clone(),
+        read_only: dry_run,
+        ..Default::default()
+    };
+    let mut knowledge_graph = KnowledgeGraph::new(kg_config, schema)?;
+
+    println!("{}", format!(" Looking up entities in {}...", source.name()).bright_blue().bold());
+    let mut enricher = Enricher::new(source, language, std::time::Duration::from_millis(delay_ms))?
+        .offline(offline)
+        .with_cache(Enricher::cache_path_for(&kg_path))?;
+    let report = enricher.enrich(&knowledge_graph, &options).await;
+    // Keep the lookups made so far, even when the run failed
+    enricher.save()?;
+    let report = report?;
+
+    for link in &report.linked {
+        println!(" {} {} → {}", "+".bright_green(), link.label, link.target.bright_cyan());
     }
-    if let Some(key) = api_key {
-        config.llm_settings.api_key = Some(key);
+    if dry_run {
+        for triple in &report.triples {
+            println!("{} {}", "+".bright_green(), triple.to_ntriple());
+        }
+        println!("\n {} triples would be added", report.triples.len().to_string().bright_cyan());
+    } else {
+        let added = knowledge_graph.add_triples(&report.triples)?;
+        println!(" Added {} triples to: {}", added.to_string().bright_green(), kg_path.bright_cyan());
     }
-    if let Some(model) = model_override {
-        config.llm_settings.model = model;
+    println!(
+        " Linked {} entities; {} without a match, {} already linked",
+        report.linked.len().to_string().bright_green(),
+        report.not_found.len(),
+        report.already_linked
+    );
+    if report.uncached > 0 {
+        println!(" {} entities not in the cache were skipped (--offline)", report.uncached);
     }
+    if report.failed > 0 {
+        warn!(" {} lookups failed and will be retried on the next run", report.failed);
+    }
+    Ok(())
+}
 
-    // Create LLM client
-    let llm_client = VllmClient::new(
-        config.llm_settings.base_url.clone(),
-        config.llm_settings.api_key.clone(),
-        config.llm_settings.model.clone(),
-        config.llm_settings.temperature,
-        config.llm_settings.max_tokens,
-        config.llm_settings.timeout,
-    )?;
-
-    // Load knowledge graph
+#[allow(clippy::too_many_arguments)]
+async fn visualize_command(
+    kg_path: String,
+    config_path: Option<PathBuf>,
+    output: Option<PathBuf>,
+    format: Option<VisualFormatArg>,
+    query: Option<String>,
+    entity: Option<String>,
+    hops: usize,
+    limit: usize,
+) -> Result<()> {
+    let schema = match config_path {
+        Some(path) => Configuration::from_file(&path)?.rdf_schema,
+        None => RdfSchema::default(),
+    };
     let kg_config = KnowledgeGraphConfig {
         storage_path: kg_path.clone(),
+        read_only: true,
         ..Default::default()
     };
-    let mut knowledge_graph = KnowledgeGraph::new(kg_config, config.rdf_schema.clone())?;
+    let knowledge_graph = KnowledgeGraph::new(kg_config, schema)?;
+
+    let (mut triples, title) = if let Some(query) = &query {
+        let SimpleSparqlResults::Solutions { rows, .. } = knowledge_graph.execute_sparql(query)? else {
+            anyhow::bail!("--query must be a SELECT query");
+        };
+        let resources: std::collections::HashSet<String> = rows.into_iter()
+            .flat_map(|row| row.into_values())
+            .filter_map(|binding| match binding {
+                Binding::Iri { value } | Binding::BlankNode { value } => Some(value),
+                Binding::Literal { .. } => None,
+            })
+            .collect();
+        let triples: Vec<RdfTriple> = knowledge_graph.triples().iter()
+            .filter(|t| resources.contains(&t.subject))
+            .cloned()
+            .collect();
+        (triples, format!("{} (query)", kg_path))
+    } else if let Some(entity) = &entity {
+        (visualization::neighborhood(knowledge_graph.triples(), entity, hops), entity.clone())
+    } else {
+        (knowledge_graph.triples().to_vec(), kg_path.clone())
+    };
 
-    // Create template manager
-    let mut template_manager = TemplateManager::new(knowledge_graph, llm_client);
+    if triples.len() > limit {
+        warn!(" Showing {} of {} triples; raise --limit to see more", limit, triples.len());
+        triples.truncate(limit);
+    }
 
-    // Load templates
-    if std::path::Path::new(&template_path).is_dir() {
-        template_manager.load_templates_from_directory(&template_path)?;
-    } else {
-        template_manager.load_template(&template_path)?;
+    let format = format.map(VisualFormat::from)
+        .or_else(|| output.as_deref().and_then(VisualFormat::from_path))
+        .unwrap_or(VisualFormat::Dot);
+    let rendered = visualization::render(&triples, format, &title);
+
+    match output {
+        Some(output_path) => {
+            tokio::fs::write(&output_path, rendered).await?;
+            println!(
+                " {} triples rendered to: {}",
+                triples.len().to_string().bright_cyan(),
+                output_path.display().to_string().bright_green()
+            );
+        }
+        None => print!("{}", rendered),
     }
+    Ok(())
+}
 
-    // Determine template ID
-    let final_template_id = if let Some(id) = template_id {
-        id
-    } else if std::path::Path::new(&template_path).is_file() {
-        // Extract template ID from file (use filename without extension)
-        std::path::Path::new(&template_path)
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("default")
-            .to_string()
-    } else {
-        anyhow::bail!("Template ID required when template path is a directory");
-    };
+/// Point in time for `export --since`: a timestamp, a date (midnight UTC),
+/// or the last update of an extraction job, so that job's triples are excluded
+fn resolve_since(kg_path: &str, since: &str) -> Result<chrono::DateTime<chrono::Utc>> {
+    if let Ok(timestamp) = chrono::DateTime::parse_from_rfc3339(since) {
+        return Ok(timestamp.with_timezone(&chrono::Utc));
+    }
+    if let Ok(date) = chrono::NaiveDate::parse_from_str(since, "%Y-%m-%d") {
+        return Ok(date.and_time(chrono::NaiveTime::MIN).and_utc());
+    }
 
-    // Parse additional context
-    let additional_context = if let Some(ctx_str) = context {
-        Some(serde_json::from_str(&ctx_str)?)
+    let job = if since == "latest" {
+        JobManifest::list_for(kg_path)?.into_iter().next()
+            .ok_or_else(|| anyhow::anyhow!("No extraction jobs recorded for {}", kg_path))?
     } else {
-        None
+        JobManifest::open(kg_path, since)
+            .map_err(|e| anyhow::anyhow!("--since is neither a timestamp nor a job id: {}", e))?
     };
+    Ok(job.updated_at)
+}
 
-    // Create generation request
-    let request = TemplateGenerationRequest {
-        template_id: final_template_id.clone(),
-        context: additional_context,
-        override_queries: None,
-        output_path: output.as_ref().map(|p| p.to_string_lossy().to_string()),
+fn feedback_command(kg_path: String, command: FeedbackCommands) -> Result<()> {
+    let mut feedback = FeedbackStore::open_for(&kg_path)?;
+
+    let (triple, corrected_object, note) = match command {
+        FeedbackCommands::Reject { subject, predicate, object, note } => (RdfTriple::new(subject, predicate, object), None, note),
+        FeedbackCommands::Fix { subject, predicate, object, corrected_object, note } => {
+            (RdfTriple::new(subject, predicate, object), Some(corrected_object), note)
+        }
+        FeedbackCommands::List { limit } => {
+            if feedback.is_empty() {
+                println!(" No corrections recorded for {}", kg_path.bright_cyan());
+            }
+            for correction in feedback.list().iter().rev().take(limit) {
+                let triple = RdfTriple::new(correction.subject.clone(), correction.predicate.clone(), correction.object.clone());
+                println!("\n {}  {}", correction.id.bright_green(), correction.recorded_at.format("%Y-%m-%d %H:%M:%S"));
+                match &correction.corrected_object {
+                    Some(object) => println!("   {} {}\n   {} {}", "-".bright_red(), triple.to_ntriple(), "+".bright_green(), object),
+                    None => println!("   {} {}", "-".bright_red(), triple.to_ntriple()),
+                }
+                if let Some(note) = &correction.note {
+                    println!("   {}", note);
+                }
+            }
+            return Ok(());
+        }
+        FeedbackCommands::Remove { id } => {
+            if feedback.remove(&id).is_none() {
+                anyhow::bail!("No correction with id '{}'", id);
+            }
+            feedback.save()?;
+            println!(" Removed correction: {}", id.bright_green());
+            return Ok(());
+        }
     };
 
-    println!(" Template: {}", final_template_id.bright_green());
-    println!(" Knowledge graph: {}", kg_path.bright_cyan());
+    // Keep the source of the stored triple, so the correction names the document
+    let store_exists = std::path::Path::new(&kg_path).exists();
+    let mut knowledge_graph = match store_exists {
+        true => Some(KnowledgeGraph::new(KnowledgeGraphConfig { storage_path: kg_path.clone(), ..Default::default() }, RdfSchema::default())?),
+        false => None,
+    };
+    let pattern = TriplePattern {
+        subject: Some(triple.subject.clone()),
+        predicate: Some(triple.predicate.clone()),
+        object: Some(triple.object.clone()),
+        graph: None,
+    };
+    let triple = knowledge_graph.as_ref()
+        .and_then(|kg| kg.find(&pattern).first().map(|&stored| stored.clone()))
+        .unwrap_or(triple);
+
+    let correction = match corrected_object {
+        Some(object) => feedback.fix(&triple, object, note),
+        None => feedback.reject(&triple, note),
+    }.clone();
+    // Only record corrections that could be applied
+    let removed = knowledge_graph.as_mut().map(|knowledge_graph| correction.apply(knowledge_graph)).transpose()?;
+    feedback.save()?;
+    println!(" Recorded correction {}", correction.id.bright_green());
+
+    match removed {
+        Some(removed) => {
+            let action = if correction.is_rejection() { "Removed" } else { "Corrected" };
+            println!(" {} {} triples in: {}", action, removed.to_string().bright_cyan(), kg_path.bright_cyan());
+        }
+        None => println!(" Knowledge graph not found, recorded the correction only: {}", kg_path.bright_cyan()),
+    }
+    Ok(())
+}
 
-    // Generate document
-    let generated = template_manager.generate_document(&request).await?;
+async fn schema_command(command: SchemaCommands) -> Result<()> {
+    match command {
+        SchemaCommands::Export { config, output } => {
+            let config = Configuration::from_file(&config)?;
+            let ontology = config.rdf_schema.to_ontology_turtle();
+
+            match output {
+                Some(output_path) => {
+                    tokio::fs::write(&output_path, ontology).await?;
+                    println!(
+                        " Ontology with {} classes and {} predicates written to: {}",
+                        config.rdf_schema.classes.len(),
+                        config.rdf_schema.predicates.len(),
+                        output_path.display().to_string().bright_green()
+                    );
+                }
+                None => print!("{}", ontology),
+            }
+        }
+        SchemaCommands::Drift { kg_path, top, clear } => {
+            let mut drift = SchemaDrift::open_for(&kg_path)?;
+            if clear {
+                drift.clear();
+                drift.save()?;
+                println!(" Cleared schema drift recorded for {}", kg_path.bright_cyan());
+                return Ok(());
+            }
+            if drift.is_empty() {
+                println!(" No predicates outside the schema recorded for {}", kg_path.bright_cyan());
+                return Ok(());
+            }
 
-    // Output or save result
-    if let Some(output_path) = output {
-        tokio::fs::write(&output_path, &generated.generated_content).await?;
-        println!(" Generated document saved to: {}", output_path.display().to_string().bright_green());
-    } else {
-        println!("\n{}", " Generated Document:".bright_yellow().bold());
-        println!("{}", generated.generated_content);
+            println!("{}", " Predicates outside the schema".bright_blue().bold());
+            for (predicate, unknown) in drift.top(top) {
+                println!(
+                    "\n {}  {} triples from {} document(s), last seen {}",
+                    predicate.bright_yellow(),
+                    unknown.count.to_string().bright_cyan(),
+                    unknown.sources.len(),
+                    unknown.last_seen.format("%Y-%m-%d %H:%M:%S")
+                );
+                for example in &unknown.examples {
+                    let triple = RdfTriple::new(example.subject.clone(), predicate.to_string(), example.object.clone());
+                    match &example.source {
+                        Some(source) => println!("   {}  ({})", triple.to_ntriple(), source),
+                        None => println!("   {}", triple.to_ntriple()),
+                    }
+                }
+            }
+            if drift.len() > top {
+                println!("\n ... and {} more (--top {})", drift.len() - top, drift.len());
+            }
+        }
     }
 
-    // Show metadata
-    println!("\n{}", " Generation Metadata:".bright_green().bold());
-    println!(" Word count: {}", generated.metadata.word_count.to_string().bright_cyan());
-    println!(" Processing time: {:.2}s", generated.metadata.processing_time_seconds);
-    println!(" Queries executed: {}", generated.metadata.queries_executed.len());
-
     Ok(())
 }
 
-async fn query_command(
-    kg_path: String,
-    query: Option<String>,
-    file: Option<PathBuf>,
-    format: QueryOutputFormat,
-) -> Result<()> {
-    println!("{}", " Executing SPARQL query...".bright_blue().bold());
+/// Print what adding `source` would change, as reported by `preview_add` or `preview_merge`
+fn print_merge_preview(source: &str, report: &MergeReport) {
+    println!(" {}:", source.bright_cyan());
+    println!("   {} triples would be added", report.added.to_string().bright_green());
+    println!("   {} duplicates would be skipped", report.duplicates);
+    if report.skipped > 0 {
+        println!("   {} conflicting triples would be skipped", report.skipped);
+    }
+    if report.replaced > 0 {
+        println!("   {} stored triples would be replaced", report.replaced.to_string().bright_yellow());
+    }
+    println!("   {} conflicts", report.conflicts.len());
+    for conflict in &report.conflicts {
+        println!(
+            "     {} {} {}: {:?} vs {:?}",
+            "conflict".bright_yellow(),
+            conflict.subject,
+            conflict.predicate,
+            conflict.existing,
+            conflict.incoming
+        );
+    }
+    if !report.new_predicates.is_empty() {
+        println!("   {} new predicates:", report.new_predicates.len());
+        for predicate in &report.new_predicates {
+            println!("     {}", predicate);
+        }
+    }
+}
 
-    // Get query string
-    let query_string = if let Some(q) = query {
-        q
-    } else if let Some(file_path) = file {
-        tokio::fs::read_to_string(file_path).await?
-    } else {
-        anyhow::bail!("Either --query or --file must be provided");
+/// Open an existing knowledge graph for reading
+fn open_knowledge_graph(kg_path: &str) -> Result<KnowledgeGraph> {
+    let kg_config = KnowledgeGraphConfig {
+        storage_path: kg_path.to_string(),
+        read_only: true,
+        ..Default::default()
     };
+    Ok(KnowledgeGraph::new(kg_config, RdfSchema::default())?)
+}
 
-    // Load knowledge graph
+async fn serve_command(
+    kg_path: String,
+    config_path: Option<PathBuf>,
+    bind: String,
+    allow_anonymous: bool,
+    grpc_bind: Option<String>,
+    template_dir: Option<PathBuf>,
+    read_only: bool,
+) -> Result<()> {
+    let config = config_path.map(Configuration::from_file).transpose()?;
+    let (schema, settings, literal_normalization) = match &config {
+        Some(config) => (config.rdf_schema.clone(), config.server.clone(), config.literal_normalization.clone()),
+        None => (RdfSchema::default(), ServerSettings::default(), Default::default()),
+    };
     let kg_config = KnowledgeGraphConfig {
         storage_path: kg_path.clone(),
+        read_only,
+        literal_normalization,
         ..Default::default()
     };
-    // Create a minimal schema for the knowledge graph
-    let minimal_schema = rdf_knowledge_extractor::config::RdfSchema {
-        namespace: "http://example.org/".to_string(),
-        prefix: "ex".to_string(),
-        base_uri: "http://example.org/resource/".to_string(),
-        predicates: std::collections::HashMap::new(),
-        classes: std::collections::HashMap::new(),
-        custom_vocabularies: std::collections::HashMap::new(),
+    let knowledge_graph = KnowledgeGraph::new(kg_config, schema)?;
+    let mut state = ServerState::new(knowledge_graph).with_settings(&settings)?;
+
+    // Document ingestion needs a full extraction config
+    let mut ingestion = false;
+    if let Some(config) = config.filter(|_| !read_only) {
+        let corrections = FeedbackStore::open_for(&kg_path)?.recent(feedback::DEFAULT_PROMPT_CORRECTIONS);
+        match RdfExtractorBuilder::new(config).corrections(corrections).build() {
+            Ok(extractor) => {
+                state = state.with_extractor(extractor);
+                ingestion = true;
+            }
+            Err(e) => warn!(" Document ingestion disabled: {}", e),
+        }
+    }
+    if let Some(template_dir) = &template_dir {
+        state = state.with_templates(template_dir);
+    }
+
+    let loopback = |addr: &str| match addr.parse::<std::net::SocketAddr>() {
+        Ok(addr) => addr.ip().is_loopback(),
+        Err(_) => addr.starts_with("localhost:"),
     };
-    let knowledge_graph = KnowledgeGraph::new(kg_config, minimal_schema)?;
+    for addr in std::iter::once(&bind).chain(grpc_bind.as_ref()) {
+        if !loopback(addr) && !state.requires_auth() && !allow_anonymous {
+            anyhow::bail!(
+                "Refusing to serve on {} without API keys; add server.api_keys to the config or pass --allow-anonymous",
+                addr
+            );
+        }
+    }
 
-    // Execute query
-    let results = knowledge_graph.execute_sparql(&query_string)?;
+    println!("{}", " Serving knowledge graph...".bright_blue().bold());
+    println!(" Knowledge graph: {}", kg_path.bright_cyan());
+    println!(" SPARQL endpoint: {}", format!("http://{}/sparql", bind).bright_green());
+    println!(" Graph store:     {}{}", format!("http://{}/graph-store", bind).bright_green(), if read_only { " (read-only)" } else { "" });
+    println!(" API docs:        {}", format!("http://{}/docs", bind).bright_green());
+    if ingestion {
+        println!(" Ingestion jobs:  {}", format!("http://{}/jobs", bind).bright_green());
+    }
+    if let Some(grpc_bind) = &grpc_bind {
+        println!(" gRPC API:        {}", grpc_bind.bright_green());
+    }
+    if state.requires_auth() {
+        println!(" Authentication:  {}", "API keys required".bright_yellow());
+    }
+    if !settings.datasets.is_empty() {
+        let mut names: Vec<&str> = settings.datasets.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        println!(" Datasets:        {}", names.join(", ").bright_cyan());
+    }
 
-    // Format and display results
-    match format {
-        QueryOutputFormat::Table => {
-            println!("{}", " Query Results:".bright_yellow().bold());
-            display_results_as_table(results)?;
-        }
-        QueryOutputFormat::Json => {
-            println!("{}", " Query Results (JSON):".bright_yellow().bold());
-            display_results_as_json(results)?;
-        }
-        QueryOutputFormat::Csv => {
-            println!("{}", " Query Results (CSV):".bright_yellow().bold());
-            display_results_as_csv(results)?;
-        }
-        QueryOutputFormat::Turtle => {
-            println!("{}", " Query Results (Turtle):".bright_yellow().bold());
-            display_results_as_turtle(results)?;
+    match grpc_bind {
+        Some(grpc_bind) => {
+            tokio::try_join!(
+                rdf_knowledge_extractor::server::serve(state.clone(), &bind),
+                rdf_knowledge_extractor::server::grpc::serve(state, &grpc_bind),
+            )?;
         }
+        None => rdf_knowledge_extractor::server::serve(state, &bind).await?,
     }
-
     Ok(())
 }
 
-async fn stats_command(kg_path: String, config_path: PathBuf) -> Result<()> {
-    println!("{}", " Knowledge Graph Statistics".bright_blue().bold());
-
-    // Load configuration for schema
-    let config = Configuration::from_file(&config_path)?;
-
-    // Load knowledge graph
+async fn mcp_serve_command(kg_path: String, config_path: Option<PathBuf>, template_dir: Option<PathBuf>, read_only: bool) -> Result<()> {
+    let config = config_path.map(Configuration::from_file).transpose()?;
+    let (schema, literal_normalization) = match &config {
+        Some(config) => (config.rdf_schema.clone(), config.literal_normalization.clone()),
+        None => (RdfSchema::default(), Default::default()),
+    };
     let kg_config = KnowledgeGraphConfig {
         storage_path: kg_path.clone(),
+        read_only,
+        literal_normalization,
         ..Default::default()
     };
-    let knowledge_graph = KnowledgeGraph::new(kg_config, config.rdf_schema)?;
+    let mut state = ServerState::new(KnowledgeGraph::new(kg_config, schema)?);
 
-    // Get statistics
-    let stats = knowledge_graph.get_statistics()?;
-    println!("{}", stats);
+    // generate_document needs the LLM settings of a full config and templates
+    if let (Some(config), Some(template_dir)) = (config, &template_dir) {
+        match RdfExtractorBuilder::new(config).build() {
+            Ok(extractor) => state = state.with_extractor(extractor).with_templates(template_dir),
+            Err(e) => warn!(" Document generation disabled: {}", e),
+        }
+    }
 
+    // stdout carries the protocol, so report on stderr
+    eprintln!(" Serving {} as MCP tools on stdio{}", kg_path, if read_only { " (read-only)" } else { "" });
+    rdf_knowledge_extractor::server::mcp::serve_stdio(state).await?;
     Ok(())
 }
 
-async fn export_command(
-    kg_path: String,
-    config_path: PathBuf,
-    output: PathBuf,
-    format: OutputFormatArg,
-) -> Result<()> {
-    println!("{}", "📤 Exporting knowledge graph...".bright_blue().bold());
+async fn kg_command(command: KgCommands) -> Result<()> {
+    match command {
+        KgCommands::Diff { base, other, output } => {
+            println!("{}", " Comparing knowledge graphs...".bright_blue().bold());
 
-    // Load configuration for schema
-    let config = Configuration::from_file(&config_path)?;
+            let base_kg = open_knowledge_graph(&base)?;
+            let other_kg = open_knowledge_graph(&other)?;
+            let diff = base_kg.diff(&other_kg);
 
-    // Load knowledge graph
-    let kg_config = KnowledgeGraphConfig {
-        storage_path: kg_path.clone(),
-        ..Default::default()
-    };
-    let knowledge_graph = KnowledgeGraph::new(kg_config, config.rdf_schema)?;
+            for triple in &diff.added {
+                println!("{} {}", "+".bright_green(), triple.to_ntriple());
+            }
+            for triple in &diff.removed {
+                println!("{} {}", "-".bright_red(), triple.to_ntriple());
+            }
 
-    // Export to file
-    let format_str = match format {
-        OutputFormatArg::Turtle => "turtle",
-        OutputFormatArg::JsonLd => "jsonld",
-        OutputFormatArg::NTriples => "ntriples",
-        OutputFormatArg::RdfXml => "rdfxml",
-        OutputFormatArg::Json => "json",
-    };
+            println!(
+                "\n {} added, {} removed ({} → {})",
+                diff.added.len().to_string().bright_green(),
+                diff.removed.len().to_string().bright_red(),
+                base,
+                other
+            );
+
+            if let Some(output_path) = output {
+                tokio::fs::write(&output_path, serde_json::to_string_pretty(&diff)?).await?;
+                println!(" Diff written to: {}", output_path.display().to_string().bright_green());
+            }
+        }
+        KgCommands::Merge { kg_path, sources, strategy, preview: true } => {
+            println!("{}", " Previewing merge...".bright_blue().bold());
 
-    knowledge_graph.export_to_file(output.to_str().unwrap(), format_str)?;
+            let target = if Path::new(&kg_path).exists() {
+                open_knowledge_graph(&kg_path)?
+            } else {
+                KnowledgeGraph::in_memory(RdfSchema::default())?
+            };
+            // Each source is compared with the target as it is now
+            for source in &sources {
+                let source_kg = open_knowledge_graph(source)?;
+                print_merge_preview(source, &target.preview_merge(&source_kg, strategy.into()));
+            }
+        }
+        KgCommands::Merge { kg_path, sources, strategy, .. } => {
+            println!("{}", " Merging knowledge graphs...".bright_blue().bold());
 
-    println!(" Export completed: {}", output.display().to_string().bright_green());
+            let kg_config = KnowledgeGraphConfig {
+                storage_path: kg_path.clone(),
+                ..Default::default()
+            };
+            let mut target = KnowledgeGraph::new(kg_config, RdfSchema::default())?;
+
+            for source in &sources {
+                let source_kg = open_knowledge_graph(source)?;
+                let report = target.merge(&source_kg, strategy.into())?;
+
+                println!(
+                    " {}: {} added, {} duplicates, {} skipped, {} replaced",
+                    source.bright_cyan(),
+                    report.added,
+                    report.duplicates,
+                    report.skipped,
+                    report.replaced
+                );
+                for conflict in &report.conflicts {
+                    println!(
+                        "   {} {} {}: {:?} vs {:?}",
+                        "conflict".bright_yellow(),
+                        conflict.subject,
+                        conflict.predicate,
+                        conflict.existing,
+                        conflict.incoming
+                    );
+                }
+            }
+
+            println!(" Merged into: {}", kg_path.bright_green());
+        }
+        KgCommands::Infer { kg_path, config, dry_run } => {
+            println!("{}", " Running inference...".bright_blue().bold());
+
+            let schema = match config {
+                Some(path) => Configuration::from_file(&path)?.rdf_schema,
+                None => RdfSchema::default(),
+            };
+            let kg_config = KnowledgeGraphConfig {
+                storage_path: kg_path.clone(),
+                ..Default::default()
+            };
+            let mut knowledge_graph = KnowledgeGraph::new(kg_config, schema)?;
+
+            if dry_run {
+                let inferred = knowledge_graph.inferred_triples();
+                for triple in &inferred {
+                    println!("{} {}", "+".bright_green(), triple.to_ntriple());
+                }
+                println!("\n {} triples would be inferred", inferred.len().to_string().bright_cyan());
+            } else {
+                let added = knowledge_graph.materialize_inferences()?;
+                println!(" Added {} inferred triples to: {}", added.to_string().bright_green(), kg_path.bright_cyan());
+            }
+        }
+        KgCommands::AddTriple { kg_path, subject, predicate, object, graph, lang, datatype } => {
+            let kg_config = KnowledgeGraphConfig {
+                storage_path: kg_path.clone(),
+                ..Default::default()
+            };
+            let mut knowledge_graph = KnowledgeGraph::new(kg_config, RdfSchema::default())?;
+
+            let mut triple = RdfTriple::new(subject, predicate, object);
+            triple.graph = graph;
+            if let Some(lang) = lang {
+                triple.metadata.insert("lang".to_string(), lang);
+            }
+            if let Some(datatype) = datatype {
+                triple.metadata.insert("datatype".to_string(), datatype);
+            }
+
+            if knowledge_graph.add_triples(std::slice::from_ref(&triple))? > 0 {
+                println!("{} {}", "+".bright_green(), triple.to_ntriple());
+                println!(" Added 1 triple to: {}", kg_path.bright_cyan());
+            } else {
+                println!(" Triple already in: {}", kg_path.bright_cyan());
+            }
+        }
+        KgCommands::RemoveTriple { kg_path, subject, predicate, object, graph, dry_run } => {
+            if !std::path::Path::new(&kg_path).exists() {
+                anyhow::bail!("Knowledge graph not found: {}", kg_path);
+            }
+            let kg_config = KnowledgeGraphConfig {
+                storage_path: kg_path.clone(),
+                ..Default::default()
+            };
+            let mut knowledge_graph = KnowledgeGraph::new(kg_config, RdfSchema::default())?;
+            let pattern = TriplePattern {
+                subject,
+                predicate,
+                object,
+                graph: graph.map(|graph| Some(graph).filter(|g| g != "default")),
+            };
+
+            let matching: Vec<&RdfTriple> = knowledge_graph.find(&pattern);
+            for triple in &matching {
+                println!("{} {}", "-".bright_red(), triple.to_ntriple());
+            }
+
+            if dry_run {
+                println!("\n {} triples would be removed", matching.len().to_string().bright_cyan());
+            } else {
+                let removed = knowledge_graph.remove_triples(&pattern)?;
+                println!("\n Removed {} triples from: {}", removed.to_string().bright_red(), kg_path.bright_cyan());
+            }
+        }
+        KgCommands::Repair { kg_path, output, quarantine } => {
+            println!("{}", " Repairing knowledge graph...".bright_blue().bold());
+
+            let content = std::fs::read(&kg_path)
+                .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", kg_path, e))?;
+            let output = output.unwrap_or_else(|| PathBuf::from(format!("{}.recovered", kg_path)));
+            let quarantine = quarantine.unwrap_or_else(|| PathBuf::from(format!("{}.quarantine.jsonl", kg_path)));
+            if output.exists() {
+                anyhow::bail!("{} already exists; remove it or choose another --output", output.display());
+            }
+
+            let salvage = repair::salvage_store(&kg_path, &content)?;
+            std::fs::write(&output, StorageFormat::Json.encode(&salvage.triples)?)?;
+            println!(" Recovered {} triples into: {}", salvage.triples.len().to_string().bright_green(), output.display().to_string().bright_cyan());
+
+            if salvage.quarantined.is_empty() {
+                println!(" No unreadable records found");
+            } else {
+                let mut lines = String::new();
+                for record in &salvage.quarantined {
+                    let file = record.file.as_deref().unwrap_or(&kg_path);
+                    println!("  {} {} line {}: {}", "!".bright_yellow(), file, record.line, record.error);
+                    lines.push_str(&serde_json::to_string(record)?);
+                    lines.push('\n');
+                }
+                std::fs::write(&quarantine, lines)?;
+                println!(" Quarantined {} records in: {}", salvage.quarantined.len().to_string().bright_yellow(), quarantine.display().to_string().bright_cyan());
+            }
+        }
+        KgCommands::Snapshot { kg_path, tag, list } => {
+            let knowledge_graph = open_knowledge_graph(&kg_path)?;
+
+            if list {
+                let snapshots = knowledge_graph.snapshots()?;
+                if snapshots.is_empty() {
+                    println!(" No snapshots of {}", kg_path.bright_cyan());
+                }
+                for snapshot in snapshots {
+                    println!(
+                        " {}  {}  {} triples",
+                        snapshot.tag.bright_green(),
+                        snapshot.created_at.format("%Y-%m-%d %H:%M:%S"),
+                        snapshot.triples
+                    );
+                }
+                return Ok(());
+            }
+
+            let tag = tag.unwrap_or_else(|| chrono::Utc::now().format("%Y%m%d-%H%M%S").to_string());
+            let snapshot = knowledge_graph.snapshot(&tag)?;
+            println!(" Recorded snapshot {} ({} triples)", snapshot.tag.bright_green(), snapshot.triples);
+        }
+        KgCommands::Restore { kg_path, tag, no_backup } => {
+            if !std::path::Path::new(&kg_path).exists() {
+                anyhow::bail!("Knowledge graph not found: {}", kg_path);
+            }
+            let kg_config = KnowledgeGraphConfig { storage_path: kg_path.clone(), ..Default::default() };
+            let mut knowledge_graph = KnowledgeGraph::new(kg_config, RdfSchema::default())?;
+
+            if !no_backup {
+                let backup = format!("pre-restore-{}", chrono::Utc::now().format("%Y%m%d-%H%M%S"));
+                knowledge_graph.snapshot(&backup)?;
+                println!(" Saved current triples as snapshot {}", backup.bright_cyan());
+            }
+
+            let before = knowledge_graph.triples().len();
+            let restored = knowledge_graph.restore(&tag)?;
+            println!(
+                " Restored {} from snapshot {}: {} -> {} triples",
+                kg_path.bright_cyan(),
+                tag.bright_green(),
+                before,
+                restored
+            );
+        }
+        KgCommands::Compact { kg_path } => {
+            if !std::path::Path::new(&kg_path).exists() {
+                anyhow::bail!("Knowledge graph not found: {}", kg_path);
+            }
+            let kg_config = KnowledgeGraphConfig { storage_path: kg_path.clone(), ..Default::default() };
+            let mut knowledge_graph = KnowledgeGraph::new(kg_config, RdfSchema::default())?;
+            let folded = knowledge_graph.compact()?;
+            println!(
+                " Compacted {}: folded {} journaled triples into {} stored triples",
+                kg_path.bright_cyan(),
+                folded.to_string().bright_green(),
+                knowledge_graph.triples().len()
+            );
+        }
+        KgCommands::RebuildEmbeddings { kg_path, config } => {
+            if !std::path::Path::new(&kg_path).exists() {
+                anyhow::bail!("Knowledge graph not found: {}", kg_path);
+            }
+            let config = Configuration::from_file(&config)?;
+            let client = EmbeddingClient::from_settings(&config.entity_linking, &config.llm_settings)?;
+            let knowledge_graph = open_knowledge_graph(&kg_path)?;
+
+            println!("{}", " Rebuilding embedding index...".bright_blue().bold());
+            // The current index is replaced without being read, so a damaged one can be rebuilt too
+            let index_path = EntityLinker::cache_path_for(&kg_path);
+            let mut linker = EntityLinker::new(client, &config.entity_linking);
+            let index = linker.rebuild(&knowledge_graph).await?;
+            index.save(&index_path)?;
+            println!(
+                " Embedded {} entity labels with {}: {}",
+                index.len().to_string().bright_green(),
+                config.entity_linking.model,
+                index_path.display().to_string().bright_cyan()
+            );
+        }
+        KgCommands::MigrateStore { kg_path, to, output } => {
+            if !std::path::Path::new(&kg_path).exists() {
+                anyhow::bail!("Knowledge graph not found: {}", kg_path);
+            }
+            let format = StorageFormat::from(to);
+            let kg_config = KnowledgeGraphConfig {
+                storage_path: kg_path.clone(),
+                read_only: output.is_some(),
+                ..Default::default()
+            };
+            let mut knowledge_graph = KnowledgeGraph::new(kg_config, RdfSchema::default())?;
+            let from = knowledge_graph.storage_format();
+            let size_before = std::fs::metadata(&kg_path)?.len();
+
+            let target = match output {
+                Some(output) => {
+                    if std::path::Path::new(&output).exists() {
+                        anyhow::bail!("{} already exists; remove it or choose another --output", output);
+                    }
+                    let sidecars = store_sidecars(&output);
+                    if let Some(existing) = sidecars.iter().find(|path| path.exists()) {
+                        anyhow::bail!("{} already exists; remove it or choose another --output", existing.display());
+                    }
+                    std::fs::write(&output, format.encode(knowledge_graph.triples())?)?;
+                    for (from, to) in store_sidecars(&kg_path).into_iter().zip(sidecars) {
+                        copy_path(&from, &to)?;
+                    }
+                    output
+                }
+                None if from == format => {
+                    println!(" {} is already in {} format", kg_path.bright_cyan(), format);
+                    return Ok(());
+                }
+                None => {
+                    knowledge_graph.set_storage_format(format)?;
+                    kg_path.clone()
+                }
+            };
+
+            // Read the result back before reporting success
+            let migrated = KnowledgeGraph::new(
+                KnowledgeGraphConfig { storage_path: target.clone(), read_only: true, ..Default::default() },
+                RdfSchema::default(),
+            )?;
+            if migrated.triples().len() != knowledge_graph.triples().len() {
+                anyhow::bail!("{} holds {} triples after migration, expected {}", target, migrated.triples().len(), knowledge_graph.triples().len());
+            }
+            if migrated.namespaces() != knowledge_graph.namespaces() {
+                anyhow::bail!("{} lost its namespaces in the migration", target);
+            }
+
+            println!(
+                " Migrated {} triples from {} to {}: {} ({} → {} bytes)",
+                migrated.triples().len().to_string().bright_green(),
+                from,
+                format,
+                target.bright_cyan(),
+                size_before,
+                std::fs::metadata(&target)?.len()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// State kept next to the store at `kg_path`, which a copy of the store
+/// takes along; the journal is folded into the copy and the lock stays
+/// with the original
+fn store_sidecars(kg_path: &str) -> Vec<PathBuf> {
+    vec![
+        NamespaceMap::path_for(kg_path),
+        FeedbackStore::path_for(kg_path),
+        SchemaDrift::path_for(kg_path),
+        QueryLibrary::path_for(kg_path),
+        ViewStore::path_for(kg_path),
+        Enricher::cache_path_for(kg_path),
+        EmbeddingIndex::path_for(kg_path),
+        SnapshotStore::dir_for(kg_path),
+        JobManifest::dir_for(kg_path),
+        ApprovalQueue::dir_for(kg_path),
+        GenerationHistory::dir_for(kg_path),
+    ]
+}
 
+/// Copy the file or directory at `from` to `to`, if it exists
+fn copy_path(from: &Path, to: &Path) -> Result<()> {
+    if from.is_dir() {
+        std::fs::create_dir_all(to)?;
+        for entry in std::fs::read_dir(from)? {
+            let entry = entry?;
+            copy_path(&entry.path(), &to.join(entry.file_name()))?;
+        }
+    } else if from.exists() {
+        std::fs::copy(from, to)?;
+    }
     Ok(())
 }
 
-async fn list_templates_command(template_dir: String) -> Result<()> {
+async fn list_templates_command(template_dir: String, preview: bool) -> Result<()> {
     println!("{}", " Available Templates".bright_blue().bold());
 
     if !std::path::Path::new(&template_dir).exists() {
@@ -813,35 +4070,33 @@ async fn list_templates_command(template_dir: String) -> Result<()> {
         return Ok(());
     }
 
-    // Create a dummy knowledge graph and LLM client for template manager
-    let kg = KnowledgeGraph::in_memory(rdf_knowledge_extractor::config::RdfSchema {
-        namespace: "http://example.org/".to_string(),
-        prefix: "ex".to_string(),
-        base_uri: "http://example.org/resource/".to_string(),
-        predicates: std::collections::HashMap::new(),
-        classes: std::collections::HashMap::new(),
-        custom_vocabularies: std::collections::HashMap::new(),
-    })?;
-    let llm_client = VllmClient::new(
-        "http://localhost:8000".to_string(),
-        None,
-        "test".to_string(),
-        0.3,
-        1024,
-        30,
-    )?;
-
-    let mut template_manager = TemplateManager::new(kg, llm_client);
+    // Listing needs neither triples nor an LLM
+    let kg = KnowledgeGraph::in_memory(RdfSchema::default())?;
+    let mut template_manager = TemplateManager::new(kg, None);
 
     match template_manager.load_templates_from_directory(&template_dir) {
         Ok(count) => {
             println!(" Found {} templates in {}", count.to_string().bright_cyan(), template_dir.bright_green());
 
-            for template in template_manager.list_templates() {
+            let mut templates = template_manager.list_templates();
+            templates.sort_by(|a, b| a.id.cmp(&b.id));
+            for template in templates {
                 println!("\n {} ({})", template.name.bright_yellow(), template.id.bright_cyan());
-                println!("    Type: {}", template.template_type.to_string());
+                println!("    Type: {}", template.template_type);
                 println!("    Description: {}", template.description);
                 println!("    Queries: {}", template.data_queries.len());
+
+                if preview {
+                    match template_manager.preview(&template.id) {
+                        Ok(rendered) => {
+                            println!("    Preview (sample data):");
+                            for line in rendered.trim_end().lines() {
+                                println!("    │ {}", line);
+                            }
+                        }
+                        Err(e) => println!("    Preview failed: {}", e.to_string().bright_red()),
+                    }
+                }
             }
         }
         Err(e) => {
@@ -852,6 +4107,85 @@ async fn list_templates_command(template_dir: String) -> Result<()> {
     Ok(())
 }
 
+fn validate_template_command(
+    template_path: String,
+    template_id: Option<String>,
+    kg_path: String,
+    config_path: Option<PathBuf>,
+    format: ReportFormat,
+) -> Result<()> {
+    let schema = match config_path {
+        Some(path) => Configuration::from_file(&path)?.rdf_schema,
+        None => RdfSchema::default(),
+    };
+    let kg_config = KnowledgeGraphConfig {
+        storage_path: kg_path.clone(),
+        read_only: true,
+        ..Default::default()
+    };
+    let knowledge_graph = KnowledgeGraph::new(kg_config, schema)?;
+
+    // Validation runs the queries only, so no LLM is needed
+    let mut template_manager = TemplateManager::new(knowledge_graph, None)
+        .with_views(ViewStore::open_for(&kg_path)?);
+
+    if std::path::Path::new(&template_path).is_dir() {
+        template_manager.load_templates_from_directory(&template_path)?;
+    } else {
+        template_manager.load_template(&template_path)?;
+    }
+
+    let template_ids = match template_id {
+        Some(id) => vec![id],
+        None => {
+            let mut ids: Vec<String> = template_manager.list_templates().iter().map(|t| t.id.clone()).collect();
+            ids.sort();
+            ids
+        }
+    };
+
+    let validations = template_ids.iter()
+        .map(|id| template_manager.validate_template(id))
+        .collect::<rdf_knowledge_extractor::Result<Vec<_>>>()?;
+
+    match format {
+        ReportFormat::Table => validations.iter().for_each(print_template_validation),
+        ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&validations)?),
+    }
+
+    let invalid = validations.iter().filter(|v| !v.is_valid()).count();
+    if invalid > 0 {
+        anyhow::bail!("{} of {} templates have errors", invalid, validations.len());
+    }
+    Ok(())
+}
+
+fn print_template_validation(validation: &TemplateValidation) {
+    println!("\n{} {}", " Template".bright_blue().bold(), validation.template_id.bright_cyan());
+
+    for query in &validation.queries {
+        let outcome = match &query.outcome {
+            QueryOutcome::Rows(0) => "no results".bright_yellow().to_string(),
+            QueryOutcome::Rows(rows) => format!("{} rows", rows).bright_green().to_string(),
+            QueryOutcome::Boolean(result) => result.to_string().bright_green().to_string(),
+            QueryOutcome::Failed(_) => "failed".bright_red().to_string(),
+            QueryOutcome::Remote => "remote endpoint, not run".bright_yellow().to_string(),
+        };
+        println!("    Query {}: {}", query.id, outcome);
+    }
+
+    for issue in &validation.issues {
+        match issue.severity {
+            Severity::Error => println!("  {} {}", "error:".bright_red().bold(), issue.message),
+            Severity::Warning => println!("  {} {}", "warning:".bright_yellow(), issue.message),
+        }
+    }
+
+    if validation.is_valid() {
+        println!(" {}", "Template is valid".bright_green());
+    }
+}
+
 async fn generate_templates_command(output_dir: PathBuf) -> Result<()> {
     println!("{}", " Generating example templates...".bright_blue().bold());
 
@@ -965,30 +4299,17 @@ post_processing:
 // Helper functions for query result display
 fn display_results_as_table(results: SimpleSparqlResults) -> Result<()> {
     match results {
-        SimpleSparqlResults::Solutions(solutions) => {
-            let mut rows = Vec::new();
-            let mut headers = std::collections::HashSet::new();
-
-            // Collect all data
-            for solution in solutions {
-                let mut row = std::collections::HashMap::new();
-
-                for (var_name, value) in solution {
-                    headers.insert(var_name.clone());
-                    row.insert(var_name, value);
-                }
-                rows.push(row);
-            }
+        SimpleSparqlResults::Solutions { variables, rows } => {
+            let headers = SimpleSparqlResults::columns(&variables, &rows);
 
             // Print table
-            let header_vec: Vec<String> = headers.into_iter().collect();
-            println!("{}", header_vec.join(" | ").bright_cyan());
-            println!("{}", "─".repeat(header_vec.len() * 20));
+            println!("{}", headers.join(" | ").bright_cyan());
+            println!("{}", "─".repeat(headers.len() * 20));
 
             for row in rows {
                 let mut values = Vec::new();
-                for header in &header_vec {
-                    let value = row.get(header).map(|s| s.as_str()).unwrap_or("");
+                for header in &headers {
+                    let value = row.get(header).map(|b| b.value()).unwrap_or("");
                     values.push(value.to_string());
                 }
                 println!("{}", values.join(" | "));
@@ -1004,14 +4325,17 @@ fn display_results_as_table(results: SimpleSparqlResults) -> Result<()> {
 
 fn display_results_as_json(results: SimpleSparqlResults) -> Result<()> {
     match results {
-        SimpleSparqlResults::Solutions(solutions) => {
+        SimpleSparqlResults::Solutions { variables, rows } => {
+            let headers = SimpleSparqlResults::columns(&variables, &rows);
             let mut json_results = Vec::new();
 
-            for solution in solutions {
+            for mut solution in rows {
                 let mut row = serde_json::Map::new();
 
-                for (var_name, value) in solution {
-                    row.insert(var_name, serde_json::Value::String(value));
+                for header in &headers {
+                    if let Some(value) = solution.remove(header) {
+                        row.insert(header.clone(), serde_json::to_value(value)?);
+                    }
                 }
                 json_results.push(serde_json::Value::Object(row));
             }
@@ -1030,29 +4354,16 @@ fn display_results_as_json(results: SimpleSparqlResults) -> Result<()> {
 fn display_results_as_csv(results: SimpleSparqlResults) -> Result<()> {
 
     match results {
-        SimpleSparqlResults::Solutions(solutions) => {
-            let mut rows = Vec::new();
-            let mut headers = std::collections::HashSet::new();
-
-            // Collect all data
-            for solution in solutions {
-                let mut row = std::collections::HashMap::new();
-
-                for (var_name, value) in solution {
-                    headers.insert(var_name.clone());
-                    row.insert(var_name, value);
-                }
-                rows.push(row);
-            }
+        SimpleSparqlResults::Solutions { variables, rows } => {
+            let headers = SimpleSparqlResults::columns(&variables, &rows);
 
             // Print CSV
-            let header_vec: Vec<String> = headers.into_iter().collect();
-            println!("{}", header_vec.join(","));
+            println!("{}", headers.join(","));
 
             for row in rows {
                 let mut values = Vec::new();
-                for header in &header_vec {
-                    let value = row.get(header).map(|s| s.as_str()).unwrap_or("");
+                for header in &headers {
+                    let value = row.get(header).map(|b| b.value()).unwrap_or("");
                     values.push(if value.contains(',') { format!("\"{}\"", value) } else { value.to_string() });
                 }
                 println!("{}", values.join(","));
@@ -1068,11 +4379,14 @@ fn display_results_as_csv(results: SimpleSparqlResults) -> Result<()> {
 
 fn display_results_as_turtle(results: SimpleSparqlResults) -> Result<()> {
     match results {
-        SimpleSparqlResults::Solutions(solutions) => {
+        SimpleSparqlResults::Solutions { variables, rows } => {
+            let headers = SimpleSparqlResults::columns(&variables, &rows);
             println!("# SPARQL Solutions as Turtle-like format");
-            for solution in solutions {
-                for (var, value) in solution {
-                    println!("# {}: {}", var, value);
+            for solution in rows {
+                for var in &headers {
+                    if let Some(value) = solution.get(var) {
+                        println!("# {}: {}", var, value.to_ntriples());
+                    }
                 }
                 println!();
             }
@@ -1134,6 +4448,7 @@ async fn demo_command(
     // Initialize knowledge graph
     let kg_config = KnowledgeGraphConfig {
         storage_path: kg_path.to_string(),
+        literal_normalization: config.literal_normalization.clone(),
         ..Default::default()
     };
     let mut knowledge_graph = KnowledgeGraph::new(kg_config, config.rdf_schema.clone())?;
@@ -1172,7 +4487,7 @@ async fn demo_command(
     println!("\n{}", " PHASE 2: Populating template with knowledge graph data...".bright_green().bold());
 
     // Initialize template manager
-    let mut template_manager = TemplateManager::new(knowledge_graph, llm_client);
+    let mut template_manager = TemplateManager::new(knowledge_graph, Some(llm_client));
 
     // Load the template
     template_manager.load_template(&template_path.to_string_lossy())?;
@@ -1208,3 +4523,13 @@ async fn demo_command(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cli_definition() {
+        Cli::command().debug_assert();
+    }
+}
@@ -0,0 +1,220 @@
+//! The subset of CSVW metadata (https://www.w3.org/TR/tabular-metadata/)
+//! needed to map CSV files to triples
+//!
+//! Supported: single tables and table groups, `aboutUrl`, `propertyUrl` and
+//! `valueUrl` templates on the schema or its columns, `datatype`, `lang`,
+//! `suppressOutput`, virtual columns and the dialect's `delimiter` and
+//! `header`. Columns without a `propertyUrl` map to the predicate named
+//! after the column in the schema namespace.
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::{LogicalSource, MappingDocument, PropertyMap, TriplesMap};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Metadata {
+    #[serde(default)]
+    url: Option<String>,
+    #[serde(default)]
+    table_schema: Option<Schema>,
+    #[serde(default)]
+    tables: Vec<Table>,
+    #[serde(default)]
+    dialect: Option<Dialect>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Table {
+    url: String,
+    table_schema: Schema,
+    #[serde(default)]
+    dialect: Option<Dialect>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct Schema {
+    #[serde(default)]
+    columns: Vec<Column>,
+    #[serde(default)]
+    about_url: Option<String>,
+    #[serde(default)]
+    property_url: Option<String>,
+    #[serde(default)]
+    value_url: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct Column {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    titles: Option<Value>,
+    #[serde(default)]
+    about_url: Option<String>,
+    #[serde(default)]
+    property_url: Option<String>,
+    #[serde(default)]
+    value_url: Option<String>,
+    #[serde(default)]
+    datatype: Option<Value>,
+    #[serde(default)]
+    lang: Option<String>,
+    #[serde(default)]
+    suppress_output: bool,
+    #[serde(default, rename = "virtual")]
+    is_virtual: bool,
+}
+
+#[derive(Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+struct Dialect {
+    #[serde(default)]
+    delimiter: Option<char>,
+    #[serde(default)]
+    header: Option<bool>,
+}
+
+/// Convert CSVW metadata into triples maps, one per table
+pub fn from_metadata(content: &str) -> serde_json::Result<MappingDocument> {
+    let metadata: Metadata = serde_json::from_str(content)?;
+
+    let mut tables = metadata.tables;
+    if let (Some(url), Some(table_schema)) = (metadata.url, metadata.table_schema) {
+        tables.insert(0, Table { url, table_schema, dialect: None });
+    }
+
+    let triples_maps = tables.into_iter()
+        .map(|table| {
+            let dialect = table.dialect.or_else(|| metadata.dialect.clone()).unwrap_or_default();
+            triples_map(table.url, table.table_schema, dialect)
+        })
+        .collect();
+    Ok(MappingDocument { triples_maps })
+}
+
+fn triples_map(url: String, schema: Schema, dialect: Dialect) -> TriplesMap {
+    let names: Vec<String> = schema.columns.iter()
+        .enumerate()
+        .map(|(i, column)| column_name(column, i))
+        .collect();
+
+    let properties = schema.columns.iter()
+        .zip(&names)
+        .filter(|(column, _)| !column.suppress_output)
+        .filter_map(|(column, name)| {
+            let predicate = column.property_url.as_ref().or(schema.property_url.as_ref())
+                .map(|template| template.replace("{#_name}", &format!("#{}", name)).replace("{_name}", name))
+                .unwrap_or_else(|| name.clone());
+            let template = column.value_url.clone().or_else(|| schema.value_url.clone());
+            if column.is_virtual && template.is_none() {
+                return None;
+            }
+
+            Some(PropertyMap {
+                predicate,
+                column: template.is_none().then(|| name.clone()),
+                template,
+                datatype: column.datatype.as_ref().and_then(datatype_name),
+                lang: column.lang.clone(),
+                subject: column.about_url.clone(),
+                ..PropertyMap::default()
+            })
+        })
+        .collect();
+
+    // Virtual columns have no cell in the file
+    let columns = schema.columns.iter()
+        .zip(&names)
+        .filter(|(column, _)| !column.is_virtual)
+        .map(|(_, name)| name.clone())
+        .collect();
+
+    TriplesMap {
+        name: Some(url.trim_end_matches(".csv").to_string()),
+        source: LogicalSource {
+            csv: Some(url.into()),
+            delimiter: dialect.delimiter.unwrap_or(','),
+            header: dialect.header.unwrap_or(true),
+            columns,
+            ..LogicalSource::default()
+        },
+        subject: schema.about_url,
+        classes: Vec::new(),
+        graph: None,
+        properties,
+    }
+}
+
+/// `name`, else the first title, else `_col.N`
+fn column_name(column: &Column, index: usize) -> String {
+    let title = match &column.titles {
+        Some(Value::String(title)) => Some(title.clone()),
+        Some(Value::Array(titles)) => titles.first().and_then(Value::as_str).map(str::to_string),
+        Some(Value::Object(by_lang)) => by_lang.values().next().and_then(|t| match t {
+            Value::String(title) => Some(title.clone()),
+            Value::Array(titles) => titles.first().and_then(Value::as_str).map(str::to_string),
+            _ => None,
+        }),
+        _ => None,
+    };
+    column.name.clone().or(title).unwrap_or_else(|| format!("_col.{}", index + 1))
+}
+
+/// Datatype given as a name or as `{"base": name}`
+fn datatype_name(datatype: &Value) -> Option<String> {
+    match datatype {
+        Value::String(name) => Some(name.clone()),
+        Value::Object(description) => description.get("base").and_then(Value::as_str).map(str::to_string),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RdfSchema;
+
+    #[test]
+    fn test_csvw_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("people.csv"), "ID;Full name;Born;Internal\n7;Grace Hopper;1906-12-09;x\n").unwrap();
+        let metadata = dir.path().join("people.csv-metadata.json");
+        std::fs::write(&metadata, r#"{
+            "@context": "http://www.w3.org/ns/csvw",
+            "url": "people.csv",
+            "dialect": {"delimiter": ";"},
+            "tableSchema": {
+                "aboutUrl": "person/{id}",
+                "columns": [
+                    {"name": "id", "titles": "ID", "suppressOutput": true},
+                    {"titles": ["Full name"], "propertyUrl": "http://xmlns.com/foaf/0.1/name", "lang": "en"},
+                    {"name": "born", "datatype": {"base": "date"}},
+                    {"name": "internal", "suppressOutput": true},
+                    {"virtual": true, "propertyUrl": "rdf:type", "valueUrl": "ex:Person"}
+                ]
+            }
+        }"#).unwrap();
+
+        let schema = RdfSchema {
+            namespace: "http://ex.org/vocab#".to_string(),
+            prefix: "ex".to_string(),
+            base_uri: "http://ex.org/".to_string(),
+            ..RdfSchema::default()
+        };
+
+        let triples = MappingDocument::from_file(&metadata).unwrap().apply(&schema).unwrap();
+        assert_eq!(triples.len(), 3);
+        assert!(triples.iter().all(|t| t.subject == "http://ex.org/person/7"));
+
+        let name = triples.iter().find(|t| t.predicate == "http://xmlns.com/foaf/0.1/name").unwrap();
+        assert_eq!((name.object.as_str(), name.metadata["lang"].as_str()), ("Grace Hopper", "en"));
+        let born = triples.iter().find(|t| t.predicate == "http://ex.org/vocab#born").unwrap();
+        assert_eq!(born.metadata["datatype"], "http://www.w3.org/2001/XMLSchema#date");
+        assert!(triples.iter().any(|t| t.object == "http://ex.org/vocab#Person"));
+    }
+}
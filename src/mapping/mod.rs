@@ -0,0 +1,476 @@
+//! Deterministic mapping of CSV files, spreadsheets and SQLite tables to
+//! triples, from a
+//! simplified R2RML-style YAML mapping or CSVW metadata
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::config::RdfSchema;
+use crate::core::RdfTriple;
+use crate::error::{Error, ErrorContext, Result};
+use crate::handlers::spreadsheet::{column_letter, read_workbook};
+
+pub mod csvw;
+
+const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+const XSD: &str = "http://www.w3.org/2001/XMLSchema#";
+
+/// A set of triples maps, each turning the rows of one table into triples
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MappingDocument {
+    pub triples_maps: Vec<TriplesMap>,
+}
+
+/// Maps each row of a logical table to a subject and its properties
+///
+/// Templates reference columns as `{column}` (and the 1-based row number as
+/// `{_row}`); values are percent-encoded. Relative IRIs are resolved against
+/// the schema's `base_uri`, and `prefix:name` terms are expanded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TriplesMap {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub source: LogicalSource,
+    /// IRI template of each row's subject; a blank node per row when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subject: Option<String>,
+    /// Classes every subject is typed with
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub classes: Vec<String>,
+    /// Named graph for the triples
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub graph: Option<String>,
+    #[serde(default)]
+    pub properties: Vec<PropertyMap>,
+}
+
+/// Where rows come from: a CSV file, a spreadsheet sheet, or a SQLite table
+/// or query
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LogicalSource {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub csv: Option<PathBuf>,
+    #[serde(default = "default_delimiter")]
+    pub delimiter: char,
+    /// Whether the CSV file or sheet starts with a header row
+    #[serde(default = "default_true")]
+    pub header: bool,
+    /// Column names in order, instead of the header row
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub columns: Vec<String>,
+    /// Spreadsheet file (.xlsx, .xlsm, .xlsb, .xls or .ods)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spreadsheet: Option<PathBuf>,
+    /// Sheet of the spreadsheet; the first sheet when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sheet: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sqlite: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub table: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
+}
+
+/// One predicate of the subject; the object is a column value, an IRI
+/// template or a constant
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PropertyMap {
+    pub predicate: String,
+    /// Literal from this column; rows where it is empty are skipped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub column: Option<String>,
+    /// IRI template for the object
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    /// The same object for every row; an IRI if it contains "://"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub constant: Option<String>,
+    /// Literal datatype: an XSD name ("integer", "date") or a term
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub datatype: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lang: Option<String>,
+    /// Subject template for this property instead of the map's subject
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subject: Option<String>,
+}
+
+fn default_delimiter() -> char { ',' }
+fn default_true() -> bool { true }
+
+/// A row's non-empty cells; SQLite and spreadsheet numbers (and spreadsheet
+/// booleans and dates) carry their XSD datatype
+struct Row {
+    number: usize,
+    cells: HashMap<String, Cell>,
+}
+
+struct Cell {
+    value: String,
+    datatype: Option<&'static str>,
+}
+
+impl MappingDocument {
+    /// Load a YAML mapping, or CSVW metadata from a `.json` file; source
+    /// paths are relative to the mapping file
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .config_context(|| format!("Failed to read mapping file: {}", path.display()))?;
+
+        let mut document = if path.extension().and_then(|e| e.to_str()) == Some("json") {
+            csvw::from_metadata(&content)
+                .config_context(|| format!("Failed to parse CSVW metadata: {}", path.display()))?
+        } else {
+            serde_yaml::from_str(&content)
+                .config_context(|| format!("Failed to parse mapping file: {}", path.display()))?
+        };
+
+        let base_dir = path.parent().unwrap_or(Path::new("."));
+        for map in &mut document.triples_maps {
+            for file in [&mut map.source.csv, &mut map.source.spreadsheet, &mut map.source.sqlite].into_iter().flatten() {
+                *file = base_dir.join(&*file);
+            }
+        }
+        Ok(document)
+    }
+
+    /// Map every table's rows to triples
+    pub fn apply(&self, schema: &RdfSchema) -> Result<Vec<RdfTriple>> {
+        let mut triples = Vec::new();
+        for (index, map) in self.triples_maps.iter().enumerate() {
+            map.validate()?;
+            let label = map.name.as_deref()
+                .map(|name| name.replace(|c: char| !c.is_ascii_alphanumeric(), "_"))
+                .unwrap_or_else(|| format!("map{}", index + 1));
+            for row in map.source.rows()? {
+                map.apply_row(&row, &label, schema, &mut triples);
+            }
+        }
+        Ok(triples)
+    }
+}
+
+impl TriplesMap {
+    fn validate(&self) -> Result<()> {
+        let name = self.name.as_deref().unwrap_or("triples map");
+        for property in &self.properties {
+            let objects = [&property.column, &property.template, &property.constant].iter().filter(|o| o.is_some()).count();
+            if objects != 1 {
+                return Err(Error::config(format!(
+                    "Property '{}' of {} needs exactly one of `column`, `template` or `constant`",
+                    property.predicate, name
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn apply_row(&self, row: &Row, label: &str, schema: &RdfSchema, triples: &mut Vec<RdfTriple>) {
+        let subject = match &self.subject {
+            Some(template) => match expand(template, row) {
+                Some(iri) => resolve_iri(&iri, schema),
+                None => return,
+            },
+            None => format!("_:{}r{}", label, row.number),
+        };
+        let source = self.source.label();
+
+        let mut push = |subject: String, predicate: String, object: String, metadata: Vec<(&str, String)>| {
+            let mut triple = RdfTriple::new(subject, predicate, object);
+            triple.source = Some(source.clone());
+            triple.graph = self.graph.as_deref().map(|g| resolve_iri(g, schema));
+            triple.metadata.extend(metadata.into_iter().map(|(k, v)| (k.to_string(), v)));
+            triples.push(triple);
+        };
+
+        for class in &self.classes {
+            push(subject.clone(), RDF_TYPE.to_string(), schema.resolve_term(class), Vec::new());
+        }
+
+        for property in &self.properties {
+            let property_subject = match &property.subject {
+                Some(template) => match expand(template, row) {
+                    Some(iri) => resolve_iri(&iri, schema),
+                    None => continue,
+                },
+                None => subject.clone(),
+            };
+
+            let mut metadata = Vec::new();
+            let object = if let Some(column) = &property.column {
+                let Some(cell) = row.cells.get(column) else { continue };
+                match (&property.lang, &property.datatype) {
+                    (Some(lang), _) => metadata.push(("lang", lang.clone())),
+                    (None, Some(datatype)) => metadata.extend(datatype_iri(datatype, schema).map(|d| ("datatype", d))),
+                    (None, None) => metadata.extend(cell.datatype.map(|d| ("datatype", d.to_string()))),
+                }
+                cell.value.clone()
+            } else if let Some(template) = &property.template {
+                let Some(iri) = expand(template, row) else { continue };
+                resolve_iri(&iri, schema)
+            } else {
+                property.constant.clone().unwrap_or_default()
+            };
+
+            push(property_subject, schema.resolve_term(&property.predicate), object, metadata);
+        }
+    }
+}
+
+impl LogicalSource {
+    /// Reported as each triple's source
+    fn label(&self) -> String {
+        match (&self.csv, &self.spreadsheet, &self.sqlite) {
+            (Some(csv), _, _) => csv.display().to_string(),
+            (None, Some(file), _) => match &self.sheet {
+                Some(sheet) => format!("{}#{}", file.display(), sheet),
+                None => file.display().to_string(),
+            },
+            (None, None, Some(db)) => format!("{}#{}", db.display(), self.table.as_deref().unwrap_or("query")),
+            (None, None, None) => String::new(),
+        }
+    }
+
+    fn rows(&self) -> Result<Vec<Row>> {
+        match (&self.csv, &self.spreadsheet, &self.sqlite) {
+            (Some(csv), None, None) => self.csv_rows(csv),
+            (None, Some(file), None) => self.spreadsheet_rows(file),
+            (None, None, Some(db)) => self.sqlite_rows(db),
+            _ => Err(Error::config("A mapping source needs exactly one of `csv`, `spreadsheet` or `sqlite`")),
+        }
+    }
+
+    fn csv_rows(&self, path: &Path) -> Result<Vec<Row>> {
+        let source = path.display().to_string();
+        let delimiter = u8::try_from(self.delimiter)
+            .map_err(|_| Error::config(format!("CSV delimiter must be a single-byte character: {:?}", self.delimiter)))?;
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .has_headers(self.header)
+            .flexible(true)
+            .from_path(path)
+            .document_context(&source, || "Failed to open CSV file")?;
+
+        let names: Vec<String> = if self.columns.is_empty() {
+            reader.headers().document_context(&source, || "Failed to read CSV header")?
+                .iter()
+                .map(|name| name.trim().to_string())
+                .collect()
+        } else {
+            self.columns.clone()
+        };
+
+        let mut rows = Vec::new();
+        for (index, record) in reader.records().enumerate() {
+            let record = record.document_context(&source, || format!("Failed to read CSV row {}", index + 1))?;
+            let cells = names.iter()
+                .zip(record.iter())
+                .filter(|(_, value)| !value.is_empty())
+                .map(|(name, value)| (name.clone(), Cell { value: value.to_string(), datatype: None }))
+                .collect();
+            rows.push(Row { number: index + 1, cells });
+        }
+        Ok(rows)
+    }
+
+    fn spreadsheet_rows(&self, path: &Path) -> Result<Vec<Row>> {
+        let mut sheets = read_workbook(path)?;
+        let index = match &self.sheet {
+            Some(name) => sheets.iter().position(|sheet| &sheet.name == name)
+                .ok_or_else(|| Error::document(path.display().to_string(), format!("No sheet named '{}'", name)))?,
+            None => 0,
+        };
+        if index >= sheets.len() {
+            return Ok(Vec::new());
+        }
+        let sheet_rows = std::mem::take(&mut sheets[index].rows);
+        let width = sheet_rows.iter().map(Vec::len).max().unwrap_or_default();
+        let mut sheet_rows = sheet_rows.into_iter();
+
+        // Columns are named by the `columns` list, the header row, or letter
+        let header = if self.header { sheet_rows.next() } else { None };
+        let names: Vec<String> = if !self.columns.is_empty() {
+            self.columns.clone()
+        } else if let Some(header) = header {
+            header.into_iter().map(|cell| cell.map(|c| c.value).unwrap_or_default()).collect()
+        } else {
+            (0..width).map(column_letter).collect()
+        };
+
+        let rows = sheet_rows.enumerate()
+            .map(|(index, row)| {
+                let cells = names.iter()
+                    .zip(row)
+                    .filter_map(|(name, cell)| cell.map(|c| (name.clone(), Cell { value: c.value, datatype: c.datatype })))
+                    .collect();
+                Row { number: index + 1, cells }
+            })
+            .collect();
+        Ok(rows)
+    }
+
+    fn sqlite_rows(&self, path: &Path) -> Result<Vec<Row>> {
+        use rusqlite::types::ValueRef;
+
+        let query = match (&self.query, &self.table) {
+            (Some(query), _) => query.clone(),
+            (None, Some(table)) => format!("SELECT * FROM \"{}\"", table.replace('"', "\"\"")),
+            (None, None) => return Err(Error::config("A SQLite source needs a `table` or `query`")),
+        };
+
+        let source = self.label();
+        let connection = rusqlite::Connection::open_with_flags(path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .storage_context(|| format!("Failed to open SQLite database: {}", path.display()))?;
+        let mut statement = connection.prepare(&query)
+            .storage_context(|| format!("Invalid query for {}", source))?;
+        let names: Vec<String> = statement.column_names().into_iter().map(str::to_string).collect();
+
+        let mut rows = Vec::new();
+        let mut results = statement.query([]).storage_context(|| format!("Failed to query {}", source))?;
+        while let Some(result) = results.next().storage_context(|| format!("Failed to read rows of {}", source))? {
+            let mut cells = HashMap::new();
+            for (i, name) in names.iter().enumerate() {
+                let cell = match result.get_ref(i).storage_context(|| format!("Failed to read column {}", name))? {
+                    ValueRef::Integer(n) => Cell { value: n.to_string(), datatype: Some("http://www.w3.org/2001/XMLSchema#integer") },
+                    ValueRef::Real(n) => Cell { value: n.to_string(), datatype: Some("http://www.w3.org/2001/XMLSchema#double") },
+                    ValueRef::Text(text) if !text.is_empty() => Cell { value: String::from_utf8_lossy(text).into_owned(), datatype: None },
+                    _ => continue,
+                };
+                cells.insert(name.clone(), cell);
+            }
+            rows.push(Row { number: rows.len() + 1, cells });
+        }
+        Ok(rows)
+    }
+}
+
+/// Expand `{column}` references with percent-encoded cell values; `None`
+/// when a referenced cell is empty
+fn expand(template: &str, row: &Row) -> Option<String> {
+    let mut expanded = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = start + rest[start..].find('}')?;
+        expanded.push_str(&rest[..start]);
+        let name = &rest[start + 1..end];
+        let value = match name {
+            "_row" => row.number.to_string(),
+            _ => row.cells.get(name)?.value.clone(),
+        };
+        expanded.push_str(&percent_encode(&value));
+        rest = &rest[end + 1..];
+    }
+    expanded.push_str(rest);
+    Some(expanded)
+}
+
+fn percent_encode(value: &str) -> String {
+    value.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Absolute IRIs and blank nodes are kept, known `prefix:name` terms are
+/// expanded and anything else is relative to `base_uri`
+fn resolve_iri(value: &str, schema: &RdfSchema) -> String {
+    if value.contains("://") || value.starts_with("urn:") || value.starts_with("_:") {
+        return value.to_string();
+    }
+    if let Some((prefix, local)) = value.split_once(':') {
+        if let Some(namespace) = schema.namespace_for(prefix) {
+            return format!("{}{}", namespace, local);
+        }
+    }
+    format!("{}{}", schema.base_uri, value)
+}
+
+/// Datatype IRI for a literal; plain strings need none
+fn datatype_iri(datatype: &str, schema: &RdfSchema) -> Option<String> {
+    let iri = if datatype.contains(':') { schema.resolve_term(datatype) } else { format!("{}{}", XSD, datatype) };
+    (iri != format!("{}string", XSD)).then_some(iri)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_yaml_mapping_csv_and_sqlite() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("people.csv"), "id,name,org\n1,Ada Lovelace,acme\n2,Bob,\n").unwrap();
+        let db = rusqlite::Connection::open(dir.path().join("orgs.db")).unwrap();
+        db.execute_batch("CREATE TABLE orgs (slug TEXT, name TEXT, founded INTEGER);
+                          INSERT INTO orgs VALUES ('acme', 'Acme Corp', 1999);").unwrap();
+
+        let mapping = dir.path().join("mapping.yaml");
+        std::fs::write(&mapping, r#"
+triples_maps:
+  - name: people
+    source: { csv: people.csv }
+    subject: "person/{id}"
+    classes: [Person]
+    properties:
+      - { predicate: hasName, column: name, lang: en }
+      - { predicate: worksFor, template: "org/{org}" }
+  - source: { sqlite: orgs.db, table: orgs }
+    subject: "org/{slug}"
+    properties:
+      - { predicate: hasName, column: name }
+      - { predicate: "ex:founded", column: founded }
+      - { predicate: hasType, constant: Company }
+"#).unwrap();
+
+        let schema = RdfSchema {
+            namespace: "http://ex.org/vocab#".to_string(),
+            prefix: "ex".to_string(),
+            base_uri: "http://ex.org/".to_string(),
+            ..RdfSchema::default()
+        };
+
+        let triples = MappingDocument::from_file(&mapping).unwrap().apply(&schema).unwrap();
+        let find = |s: &str, p: &str| triples.iter().find(|t| t.subject == s && t.predicate == p);
+
+        let ada = find("http://ex.org/person/1", "http://ex.org/vocab#hasName").unwrap();
+        assert_eq!(ada.object, "Ada Lovelace");
+        assert_eq!(ada.metadata["lang"], "en");
+        assert_eq!(find("http://ex.org/person/1", "http://ex.org/vocab#worksFor").unwrap().object, "http://ex.org/org/acme");
+        assert_eq!(find("http://ex.org/person/2", RDF_TYPE).unwrap().object, "http://ex.org/vocab#Person");
+        assert!(find("http://ex.org/person/2", "http://ex.org/vocab#worksFor").is_none());
+
+        let founded = find("http://ex.org/org/acme", "http://ex.org/vocab#founded").unwrap();
+        assert_eq!(founded.object, "1999");
+        assert_eq!(founded.metadata["datatype"], "http://www.w3.org/2001/XMLSchema#integer");
+        assert_eq!(triples.len(), 8);
+    }
+
+    #[test]
+    fn test_yaml_mapping_spreadsheet() {
+        let dir = tempfile::tempdir().unwrap();
+        crate::handlers::spreadsheet::tests::write_xlsx(&dir.path().join("crm.xlsx"), &[
+            ("Summary", &[&["Deals: 2"]]),
+            ("Deals", &[&["id", "account", "value"], &["=7", "Acme", "=12000.5"], &["=8", "Globex", ""]]),
+        ]);
+        let mapping = dir.path().join("mapping.yaml");
+        std::fs::write(&mapping, r#"
+triples_maps:
+  - source: { spreadsheet: crm.xlsx, sheet: Deals }
+    subject: "deal/{id}"
+    properties:
+      - { predicate: account, template: "org/{account}" }
+      - { predicate: value, column: value }
+"#).unwrap();
+
+        let schema = RdfSchema { base_uri: "http://ex.org/".to_string(), ..RdfSchema::default() };
+        let triples = MappingDocument::from_file(&mapping).unwrap().apply(&schema).unwrap();
+        assert_eq!(triples.len(), 3);
+        assert_eq!(triples[0].subject, "http://ex.org/deal/7");
+        assert_eq!(triples[1].object, "12000.5");
+        assert_eq!(triples[1].metadata["datatype"], "http://www.w3.org/2001/XMLSchema#double");
+        assert!(triples[0].source.as_deref().unwrap().ends_with("crm.xlsx#Deals"));
+    }
+}
@@ -0,0 +1,491 @@
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+use crate::config::OutputFormat;
+use crate::error::{Error, Result};
+use crate::core::{ExtractionResult, RdfExtractor, RdfTriple, UsageReport};
+use crate::knowledge_graph::{source_graph_iri, EntityLinker, JobManifest, KnowledgeGraph, RemoteStore, SchemaDrift, ViewStore};
+use crate::templates::{GeneratedDocument, TemplateGenerationRequest, TemplateManager};
+use crate::utils::{validate_rdf_triples, Progress, ProgressMode};
+
+/// Named graph that extracted triples are stored in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GraphTarget {
+    /// One graph per document, named after its source
+    Source,
+    /// A new graph for each extraction run
+    Run,
+    Iri(String),
+}
+
+impl GraphTarget {
+    /// A result's triples placed in this graph; triples that already name a
+    /// graph keep it
+    pub fn place<'a>(&self, result: &'a ExtractionResult, run_graph: &str) -> Cow<'a, [RdfTriple]> {
+        let name = match self {
+            Self::Source => source_graph_iri(&result.document_source),
+            Self::Run => run_graph.to_string(),
+            Self::Iri(iri) => iri.clone(),
+        };
+        result.triples.iter()
+            .cloned()
+            .map(|triple| if triple.graph.is_some() { triple } else { triple.with_graph(name.clone()) })
+            .collect()
+    }
+}
+
+impl std::fmt::Display for GraphTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Source => write!(f, "source"),
+            Self::Run => write!(f, "run"),
+            Self::Iri(iri) => write!(f, "{}", iri),
+        }
+    }
+}
+
+impl std::str::FromStr for GraphTarget {
+    type Err = std::convert::Infallible;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        Ok(match value {
+            "source" => Self::Source,
+            "run" => Self::Run,
+            iri => Self::Iri(iri.to_string()),
+        })
+    }
+}
+
+/// A result's triples placed in `graph`, or as extracted without one
+pub fn target_triples<'a>(result: &'a ExtractionResult, graph: Option<&GraphTarget>, run_graph: &str) -> Cow<'a, [RdfTriple]> {
+    match graph {
+        Some(graph) => graph.place(result, run_graph),
+        None => Cow::Borrowed(&result.triples),
+    }
+}
+
+/// End-to-end extract → store → export → generate run for embedding programs
+/// and the `extract` command
+pub struct Pipeline {
+    extractor: RdfExtractor,
+    knowledge_graph: KnowledgeGraph,
+    merge_results: bool,
+    validate: bool,
+    export: Option<(PathBuf, OutputFormat)>,
+    template_path: Option<PathBuf>,
+    generation_requests: Vec<TemplateGenerationRequest>,
+    graph: Option<GraphTarget>,
+    manifest: Option<JobManifest>,
+    remote: Option<RemoteStore>,
+    linker: Option<EntityLinker>,
+    progress: ProgressMode,
+}
+
+pub struct PipelineReport {
+    pub results: Vec<ExtractionResult>,
+    pub stored_triples: usize,
+    /// Triples extracted, counting those streamed to a remote store, whose
+    /// results are returned without their triples
+    pub extracted_triples: usize,
+    /// Validation issues per document source, only populated with `validate(true)`
+    pub validation_issues: Vec<(String, Vec<String>)>,
+    /// Triples dropped for predicates outside the schema, recorded as schema drift
+    pub unknown_predicates: Vec<RdfTriple>,
+    /// New entities rewritten to an existing entity's URI
+    pub linked_entities: usize,
+    /// New entities stated `owl:sameAs` an existing one
+    pub same_as_links: usize,
+    /// Materialized views refreshed after the run
+    pub refreshed_views: usize,
+    pub exported_files: Vec<PathBuf>,
+    pub documents: Vec<GeneratedDocument>,
+    pub usage: UsageReport,
+    /// The job manifest with every processed document recorded
+    pub manifest: Option<JobManifest>,
+    /// Graph the run's triples went to with `GraphTarget::Run`
+    pub run_graph: String,
+    pub extractor: RdfExtractor,
+    pub knowledge_graph: KnowledgeGraph,
+}
+
+impl PipelineReport {
+    pub fn has_errors(&self) -> bool {
+        self.results.iter().any(|result| !result.errors.is_empty())
+    }
+}
+
+impl Pipeline {
+    pub fn new(extractor: RdfExtractor, knowledge_graph: KnowledgeGraph) -> Self {
+        Self {
+            extractor,
+            knowledge_graph,
+            merge_results: false,
+            validate: false,
+            export: None,
+            template_path: None,
+            generation_requests: Vec::new(),
+            graph: None,
+            manifest: None,
+            remote: None,
+            linker: None,
+            progress: ProgressMode::None,
+        }
+    }
+
+    /// Merge the results of all documents into one before storing
+    pub fn merge_results(mut self, merge: bool) -> Self {
+        self.merge_results = merge;
+        self
+    }
+
+    pub fn validate(mut self, validate: bool) -> Self {
+        self.validate = validate;
+        self
+    }
+
+    /// Write the extracted triples to `path`; unmerged results get numbered files
+    pub fn export<P: AsRef<Path>>(mut self, path: P, format: OutputFormat) -> Self {
+        self.export = Some((path.as_ref().to_path_buf(), format));
+        self
+    }
+
+    /// Template file or directory used by `generate`
+    pub fn load_templates<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.template_path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Generate a document from the knowledge graph once extraction is done
+    pub fn generate(mut self, request: TemplateGenerationRequest) -> Self {
+        self.generation_requests.push(request);
+        self
+    }
+
+    /// Named graph to store triples in; without one they go to the default graph
+    pub fn graph(mut self, graph: Option<GraphTarget>) -> Self {
+        self.graph = graph;
+        self
+    }
+
+    /// Record each document in `manifest` as it is stored, so an interrupted
+    /// run can be resumed; the manifest's run graph is used for `GraphTarget::Run`
+    pub fn manifest(mut self, manifest: JobManifest) -> Self {
+        self.manifest = Some(manifest);
+        self
+    }
+
+    /// Stream each document's triples to a SPARQL Update endpoint instead of
+    /// the knowledge graph; results are returned without their triples
+    pub fn remote(mut self, remote: RemoteStore) -> Self {
+        self.remote = Some(remote);
+        self
+    }
+
+    /// Link new entities to existing ones before their triples are stored
+    pub fn link_entities(mut self, linker: EntityLinker) -> Self {
+        self.linker = Some(linker);
+        self
+    }
+
+    pub fn progress(mut self, mode: ProgressMode) -> Self {
+        self.progress = mode;
+        self
+    }
+
+    pub async fn run(self, inputs: Vec<String>) -> Result<PipelineReport> {
+        let Pipeline {
+            extractor,
+            mut knowledge_graph,
+            merge_results,
+            validate,
+            export,
+            template_path,
+            generation_requests,
+            graph,
+            mut manifest,
+            remote,
+            mut linker,
+            progress,
+        } = self;
+
+        // Without a remote store, merged results are stored once all are extracted
+        let merge_results = merge_results && remote.is_none();
+        let track_job = manifest.as_ref().is_some_and(|manifest| !manifest.documents.is_empty());
+        let run_graph = match &manifest {
+            Some(manifest) => manifest.run_graph.clone(),
+            None => format!("urn:uuid:{}", uuid::Uuid::new_v4()),
+        };
+
+        let mut results = Vec::new();
+        let mut stored_triples = 0;
+        let mut streamed_triples = 0;
+        let mut validation_issues = Vec::new();
+        let (mut linked_entities, mut same_as_links) = (0, 0);
+        let mut progress = Progress::start(progress, "extract", inputs.len());
+        extractor.extract_each(inputs, async |mut result| {
+            let triple_count = result.triples.len();
+            if !result.errors.is_empty() {
+                warn!("Errors in {}: {}", result.document_source, result.errors.join(", "));
+            }
+
+            if let Some(remote) = &remote {
+                // Failed batches fail the document, so a resumed job sends it again
+                let report = remote.push(&target_triples(&result, graph.as_ref(), &run_graph)).await;
+                stored_triples += report.inserted;
+                result.errors.extend(report.errors);
+                if validate {
+                    let issues = validate_rdf_triples(&result.triples);
+                    if !issues.is_empty() {
+                        validation_issues.push((result.document_source.clone(), issues));
+                    }
+                }
+                if let Some(manifest) = manifest.as_mut().filter(|_| track_job) {
+                    manifest.record(&result);
+                    manifest.save()?;
+                }
+
+                // Keep only the counts and usage, so memory does not grow with the run
+                streamed_triples += result.triples.len();
+                result.triples = Vec::new();
+            } else {
+                if let Some(linker) = &mut linker {
+                    match linker.link(&knowledge_graph, &mut result.triples).await {
+                        Ok(report) => {
+                            linked_entities += report.linked.len();
+                            same_as_links += report.same_as.len();
+                        }
+                        Err(e) => warn!("Entity linking failed for {}, storing its triples unlinked: {}", result.document_source, e),
+                    }
+                }
+                if !merge_results {
+                    stored_triples += knowledge_graph.add_triples(&target_triples(&result, graph.as_ref(), &run_graph))?;
+                    if let Some(manifest) = manifest.as_mut().filter(|_| track_job) {
+                        manifest.record(&result);
+                        // A document only counts as done once its triples are on disk
+                        if knowledge_graph.pending_writes() == 0 {
+                            manifest.save()?;
+                        }
+                    }
+                }
+            }
+
+            let error = (!result.errors.is_empty()).then(|| result.errors.join("; "));
+            progress.item(&result.document_source, Some(triple_count), error);
+            results.push(result);
+            Ok::<_, Error>(())
+        }).await?;
+        progress.finish();
+
+        let persistent = knowledge_graph.storage_path() != ":memory:";
+
+        // Record predicates outside the schema so they can be reviewed
+        let unknown_predicates: Vec<RdfTriple> = results.iter().flat_map(|r| r.unknown_predicates.iter().cloned()).collect();
+        if persistent && !unknown_predicates.is_empty() {
+            let mut drift = SchemaDrift::open_for(knowledge_graph.storage_path())?;
+            drift.record(&unknown_predicates);
+            drift.save()?;
+        }
+
+        let usage = extractor.usage_report(&results);
+
+        // Merged documents count as done only once the merged triples are stored
+        if let Some(manifest) = manifest.as_mut().filter(|_| track_job && merge_results) {
+            for result in &results {
+                manifest.record(result);
+            }
+        }
+
+        let results = if merge_results && results.len() > 1 {
+            info!("Merging {} results", results.len());
+            vec![extractor.merge_results(results)?]
+        } else {
+            results
+        };
+
+        if validate && remote.is_none() {
+            for result in &results {
+                let issues = validate_rdf_triples(&result.triples);
+                if !issues.is_empty() {
+                    validation_issues.push((result.document_source.clone(), issues));
+                }
+            }
+        }
+        for (source, issues) in &validation_issues {
+            warn!("Validation issues in {}: {}", source, issues.join(", "));
+        }
+
+        if merge_results {
+            for result in &results {
+                stored_triples += knowledge_graph.add_triples(&target_triples(result, graph.as_ref(), &run_graph))?;
+            }
+        }
+        knowledge_graph.flush()?;
+        if let Some(manifest) = manifest.as_mut().filter(|_| track_job) {
+            manifest.save()?;
+        }
+        if let Some(linker) = &linker {
+            linker.save()?;
+        }
+        info!("Pipeline stored {} triples", stored_triples);
+
+        // Refresh views that track extraction runs
+        let mut refreshed_views = 0;
+        if persistent && remote.is_none() {
+            let mut views = ViewStore::open_for(knowledge_graph.storage_path())?;
+            refreshed_views = views.refresh_after_extraction(&knowledge_graph)?;
+            if refreshed_views > 0 {
+                views.save()?;
+            }
+        }
+
+        let mut exported_files = Vec::new();
+        if let Some((path, format)) = &export {
+            let schema = &extractor.config().rdf_schema;
+            exported_files = knowledge_graph.serializer().write_results(
+                &results,
+                path,
+                format,
+                &schema.namespace,
+                &schema.prefix,
+                !merge_results,
+            )?;
+        }
+
+        let mut documents = Vec::new();
+        if !generation_requests.is_empty() {
+            let mut template_manager = TemplateManager::new(knowledge_graph, Some(extractor.llm_client().clone()));
+            if let Some(path) = &template_path {
+                let path = path.to_string_lossy();
+                if Path::new(path.as_ref()).is_dir() {
+                    template_manager.load_templates_from_directory(&path)?;
+                } else {
+                    template_manager.load_template(&path)?;
+                }
+            }
+
+            for request in &generation_requests {
+                documents.push(template_manager.generate_document(request).await?);
+            }
+            knowledge_graph = template_manager.into_knowledge_graph();
+        }
+
+        let extracted_triples = streamed_triples + results.iter().map(|r| r.triples.len()).sum::<usize>();
+        Ok(PipelineReport {
+            results,
+            stored_triples,
+            extracted_triples,
+            validation_issues,
+            unknown_predicates,
+            linked_entities,
+            same_as_links,
+            refreshed_views,
+            exported_files,
+            documents,
+            usage,
+            manifest,
+            run_graph,
+            extractor,
+            knowledge_graph,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Configuration;
+    use crate::core::VllmClient;
+    use crate::knowledge_graph::{DocumentStatus, KnowledgeGraphBuilder};
+
+    const REPLY: &str = r#"{"id":"1","object":"chat.completion","created":0,"model":"m",
+        "choices":[{"index":0,"message":{"role":"assistant","content":"[{\"subject\":\"acme\",\"predicate\":\"hasName\",\"object\":\"Acme\"},{\"subject\":\"acme\",\"predicate\":\"inventedPredicate\",\"object\":\"x\"}]"},"finish_reason":"stop"}],
+        "usage":{"prompt_tokens":1,"completion_tokens":1,"total_tokens":2}}"#;
+
+    /// An extractor answered by a mock server, and a knowledge graph in `dir`
+    async fn fixture(server: &mockito::Server, dir: &Path) -> (RdfExtractor, KnowledgeGraph, String) {
+        let mut config = Configuration::example();
+        config.llm_settings.base_url = server.url();
+        config.llm_settings.cache.enabled = false;
+        let extractor = RdfExtractor::new(config.clone(), VllmClient::from_settings(&config.llm_settings).unwrap());
+
+        let kg_path = dir.join("kg.db").to_string_lossy().to_string();
+        let knowledge_graph = KnowledgeGraphBuilder::new()
+            .storage_path(kg_path.clone())
+            .schema(config.rdf_schema.clone())
+            .build()
+            .unwrap();
+        (extractor, knowledge_graph, kg_path)
+    }
+
+    fn document(dir: &Path, name: &str) -> String {
+        let path = dir.join(name);
+        std::fs::write(&path, "Acme is a company.").unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_run_graph_job_and_drift() {
+        let mut server = mockito::Server::new_async().await;
+        server.mock("POST", "/v1/chat/completions").with_body(REPLY).create_async().await;
+        let dir = tempfile::tempdir().unwrap();
+        let (extractor, knowledge_graph, kg_path) = fixture(&server, dir.path()).await;
+        let inputs = vec![document(dir.path(), "acme.txt")];
+        let manifest = JobManifest::create(&kg_path, &inputs, Some("run".to_string()));
+        let job_id = manifest.job_id.clone();
+
+        let report = Pipeline::new(extractor, knowledge_graph)
+            .graph(Some(GraphTarget::Run))
+            .manifest(manifest)
+            .validate(true)
+            .export(dir.path().join("out.ttl"), OutputFormat::Turtle)
+            .run(inputs)
+            .await
+            .unwrap();
+
+        assert!(!report.has_errors(), "{:?}", report.results[0].errors);
+        assert_eq!((report.stored_triples, report.extracted_triples), (1, 1));
+        assert_eq!(report.knowledge_graph.graph_triples(Some(&report.run_graph)).len(), 1);
+        assert_eq!(report.usage.total.calls, 1);
+        assert!(report.exported_files.iter().all(|path| path.exists()));
+
+        // The out-of-schema predicate is recorded as drift
+        assert_eq!(report.unknown_predicates.len(), 1);
+        assert!(SchemaDrift::path_for(&kg_path).exists());
+
+        // The job is saved with the document done, in the manifest's run graph
+        let saved = JobManifest::open(&kg_path, &job_id).unwrap();
+        assert_eq!(saved.count(DocumentStatus::Completed), 1);
+        assert_eq!(saved.run_graph, report.run_graph);
+    }
+
+    #[tokio::test]
+    async fn test_merged_results_stored_once() {
+        let mut server = mockito::Server::new_async().await;
+        server.mock("POST", "/v1/chat/completions").with_body(REPLY).expect(2).create_async().await;
+        let dir = tempfile::tempdir().unwrap();
+        let (extractor, knowledge_graph, kg_path) = fixture(&server, dir.path()).await;
+        let inputs = vec![document(dir.path(), "a.txt"), document(dir.path(), "b.txt")];
+        let manifest = JobManifest::create(&kg_path, &inputs, None);
+
+        let report = Pipeline::new(extractor, knowledge_graph)
+            .merge_results(true)
+            .graph(Some(GraphTarget::Iri("http://example.org/graphs/merged".to_string())))
+            .manifest(manifest)
+            .run(inputs)
+            .await
+            .unwrap();
+
+        assert_eq!(report.results.len(), 1);
+        assert_eq!(report.stored_triples, 1);
+        assert_eq!(report.knowledge_graph.graph_triples(Some("http://example.org/graphs/merged")).len(), 1);
+        assert_eq!(report.manifest.unwrap().count(DocumentStatus::Completed), 2);
+    }
+
+    #[test]
+    fn test_graph_target_round_trip() {
+        for target in ["source", "run", "http://example.org/g"] {
+            assert_eq!(target.parse::<GraphTarget>().unwrap().to_string(), target);
+        }
+    }
+}
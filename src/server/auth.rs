@@ -0,0 +1,167 @@
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderMap, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use std::time::Instant;
+use tracing::info;
+
+use crate::config::{Permission, ServerSettings};
+use crate::error::{Error, Result};
+use super::{ApiError, Dataset, ServerState};
+
+/// API keys resolved from [`ServerSettings`]
+#[derive(Debug, Clone, Default)]
+pub struct ApiKeys {
+    keys: Vec<ResolvedKey>,
+}
+
+#[derive(Debug, Clone)]
+struct ResolvedKey {
+    name: String,
+    key: String,
+    permission: Permission,
+    dataset: Option<String>,
+}
+
+impl ApiKeys {
+    /// Resolve each key from its `key` or `key_env` setting
+    pub fn from_settings(settings: &ServerSettings) -> Result<Self> {
+        let mut keys = Vec::new();
+        for api_key in &settings.api_keys {
+            let key = match (&api_key.key, &api_key.key_env) {
+                (Some(key), _) => key.clone(),
+                (None, Some(var)) => std::env::var(var)
+                    .map_err(|_| Error::config(format!("API key '{}': environment variable {} is not set", api_key.name, var)))?,
+                (None, None) => return Err(Error::config(format!("API key '{}' needs 'key' or 'key_env'", api_key.name))),
+            };
+            if key.is_empty() {
+                return Err(Error::config(format!("API key '{}' is empty", api_key.name)));
+            }
+            if let Some(dataset) = &api_key.dataset {
+                if !settings.datasets.contains_key(dataset) {
+                    return Err(Error::config(format!("API key '{}' refers to unknown dataset '{}'", api_key.name, dataset)));
+                }
+            }
+            keys.push(ResolvedKey {
+                name: api_key.name.clone(),
+                key,
+                permission: api_key.permission,
+                dataset: api_key.dataset.clone(),
+            });
+        }
+        Ok(Self { keys })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// The key presented as `token`
+    fn lookup(&self, token: &str) -> Option<&ResolvedKey> {
+        self.keys.iter().find(|k| constant_time_eq(k.key.as_bytes(), token.as_bytes()))
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Token from `Authorization: Bearer <key>` or `X-API-Key: <key>`
+fn presented_token(headers: &HeaderMap) -> Option<&str> {
+    let bearer = headers.get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer ").or_else(|| value.strip_prefix("bearer ")));
+    bearer
+        .or_else(|| headers.get("x-api-key").and_then(|value| value.to_str().ok()))
+        .map(str::trim)
+}
+
+/// Permission a request needs: graph deletion is admin-only, other writes
+/// to the graph store, job submission and corrections need ingest,
+/// everything else is read
+fn required_permission(method: &Method, path: &str) -> Permission {
+    match (method, path) {
+        (&Method::DELETE, _) => Permission::Admin,
+        (&Method::PUT | &Method::POST, "/graph-store") | (&Method::POST, "/jobs" | "/feedback") => Permission::Ingest,
+        _ => Permission::Read,
+    }
+}
+
+/// Refusal for requests that need more than read access on a read-only server
+fn read_only_refusal(state: &ServerState, required: Permission) -> Option<ApiError> {
+    (state.read_only && required > Permission::Read)
+        .then(|| ApiError::new(StatusCode::FORBIDDEN, "Server is read-only"))
+}
+
+/// Paths served without an API key so integrators can discover the API
+const PUBLIC_PATHS: [&str; 2] = ["/openapi.json", "/docs"];
+
+/// Middleware that checks the API key (when keys are configured) and logs
+/// the request
+pub async fn authorize(State(state): State<ServerState>, request: Request, next: Next) -> Response {
+    let started = Instant::now();
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+
+    let (caller, dataset, response) = if let Some(refusal) = read_only_refusal(&state, required_permission(&method, &path)) {
+        ("-".to_string(), None, refusal.into_response())
+    } else if state.api_keys.is_empty() || PUBLIC_PATHS.contains(&path.as_str()) {
+        ("anonymous".to_string(), None, next.run(request).await)
+    } else {
+        match presented_token(request.headers()).map(|token| state.api_keys.lookup(token).cloned()) {
+            None => ("-".to_string(), None, unauthorized("Missing API key")),
+            Some(None) => ("-".to_string(), None, unauthorized("Invalid API key")),
+            Some(Some(key)) => {
+                let required = required_permission(&method, &path);
+                let response = if key.permission < required {
+                    let message = format!("API key '{}' lacks {:?} permission", key.name, required);
+                    ApiError::new(StatusCode::FORBIDDEN, message).into_response()
+                } else {
+                    let mut request = request;
+                    if let Some(dataset) = key.dataset.as_deref().and_then(|name| state.dataset(name)) {
+                        request.extensions_mut().insert(dataset);
+                    }
+                    next.run(request).await
+                };
+                (key.name, key.dataset, response)
+            }
+        }
+    };
+
+    if state.log_requests {
+        info!(
+            "{} {} {} key={} dataset={} {}ms",
+            method, path, response.status().as_u16(), caller,
+            dataset.as_deref().unwrap_or("default"), started.elapsed().as_millis()
+        );
+    }
+    response
+}
+
+/// Caller name and dataset for a gRPC call, checked like an HTTP request
+/// that needs `required`
+pub(super) fn authorize_call(state: &ServerState, headers: &HeaderMap, required: Permission) -> std::result::Result<(String, Dataset), ApiError> {
+    if let Some(refusal) = read_only_refusal(state, required) {
+        return Err(refusal);
+    }
+
+    let main = || Dataset(state.knowledge_graph.clone());
+    if state.api_keys.is_empty() {
+        return Ok(("anonymous".to_string(), main()));
+    }
+
+    let token = presented_token(headers).ok_or_else(|| ApiError::new(StatusCode::UNAUTHORIZED, "Missing API key"))?;
+    let key = state.api_keys.lookup(token).ok_or_else(|| ApiError::new(StatusCode::UNAUTHORIZED, "Invalid API key"))?;
+    if key.permission < required {
+        return Err(ApiError::new(StatusCode::FORBIDDEN, format!("API key '{}' lacks {:?} permission", key.name, required)));
+    }
+
+    let dataset = key.dataset.as_deref().and_then(|name| state.dataset(name)).unwrap_or_else(main);
+    Ok((key.name.clone(), dataset))
+}
+
+fn unauthorized(message: &str) -> Response {
+    let mut response = ApiError::new(StatusCode::UNAUTHORIZED, message).into_response();
+    response.headers_mut().insert(header::WWW_AUTHENTICATE, header::HeaderValue::from_static("Bearer"));
+    response
+}
@@ -0,0 +1,148 @@
+use axum::extract::Query;
+use axum::http::{header, HeaderMap, StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+use crate::core::RdfTriple;
+use crate::knowledge_graph::{KnowledgeGraph, LabelMatch};
+use crate::templates::LabelIndex;
+use super::{accepts, negotiate_rdf_format, ApiError, Dataset};
+
+type HandlerResult = std::result::Result<Response, ApiError>;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct EntityParams {
+    /// IRI of the entity
+    uri: String,
+}
+
+/// `GET /entity?uri=<iri>` describes any entity in the graph
+#[utoipa::path(
+    get, path = "/entity", tag = "entities", params(EntityParams),
+    responses(
+        (status = 200, description = "Triples with the entity as subject or object", content(
+            (String = "text/turtle"), (String = "application/n-triples"), (String = "application/ld+json"),
+            (String = "application/rdf+xml"), (String = "text/html"),
+        )),
+        (status = 404, description = "Unknown entity"),
+    )
+)]
+pub async fn describe_by_uri(Dataset(knowledge_graph): Dataset, Query(params): Query<EntityParams>, headers: HeaderMap) -> HandlerResult {
+    let knowledge_graph = knowledge_graph.read().await;
+    describe(&knowledge_graph, &params.uri, &headers)
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AutocompleteParams {
+    /// Start of a word in the entity's label
+    prefix: String,
+    /// Maximum number of entities returned
+    #[serde(default = "default_autocomplete_limit")]
+    limit: usize,
+}
+
+fn default_autocomplete_limit() -> usize { 10 }
+
+/// `GET /autocomplete?prefix=<text>` suggests entities by label
+#[utoipa::path(
+    get, path = "/autocomplete", tag = "entities", params(AutocompleteParams),
+    responses(
+        (status = 200, description = "Entities with a label word starting with the prefix, best matches first", body = Vec<LabelMatch>),
+    )
+)]
+pub async fn autocomplete(Dataset(knowledge_graph): Dataset, Query(params): Query<AutocompleteParams>) -> Json<Vec<LabelMatch>> {
+    let knowledge_graph = knowledge_graph.read().await;
+    Json(knowledge_graph.autocomplete(&params.prefix, params.limit.min(100)))
+}
+
+/// Requests under the path of the schema's `base_uri` dereference the
+/// entity with that URI, so `/resource/acme` describes
+/// `http://example.org/resource/acme`
+pub async fn dereference(Dataset(knowledge_graph): Dataset, uri: Uri, headers: HeaderMap) -> HandlerResult {
+    let knowledge_graph = knowledge_graph.read().await;
+    let base_uri = &knowledge_graph.schema().base_uri;
+
+    let entity = base_path(base_uri)
+        .and_then(|base_path| uri.path().strip_prefix(base_path))
+        .filter(|local| !local.is_empty())
+        .map(|local| format!("{}{}", base_uri, local))
+        .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, format!("No resource at {}", uri.path())))?;
+
+    describe(&knowledge_graph, &entity, &headers)
+}
+
+/// Path component of `base_uri`, e.g. `/resource/`
+fn base_path(base_uri: &str) -> Option<&str> {
+    let after_scheme = base_uri.split_once("://")?.1;
+    after_scheme.find('/').map(|i| &after_scheme[i..])
+}
+
+/// Local path under which `entity` is served, if it lives under `base_uri`
+fn local_link(entity: &str, base_uri: &str) -> String {
+    match (entity.strip_prefix(base_uri), base_path(base_uri)) {
+        (Some(local), Some(base_path)) if !local.is_empty() => format!("{}{}", base_path, local),
+        _ => format!("/entity?uri={}", url::form_urlencoded::byte_serialize(entity.as_bytes()).collect::<String>()),
+    }
+}
+
+/// Outgoing and incoming triples of `entity` in the negotiated format
+fn describe(knowledge_graph: &KnowledgeGraph, entity: &str, headers: &HeaderMap) -> HandlerResult {
+    let triples: Vec<RdfTriple> = knowledge_graph.triples_about(entity).into_iter().cloned().collect();
+    if triples.is_empty() {
+        return Err(ApiError::new(StatusCode::NOT_FOUND, format!("Unknown entity: {}", entity)));
+    }
+
+    let schema = knowledge_graph.schema();
+    let (body, media_type) = if accepts(headers, "text/html") {
+        (render_html(knowledge_graph, entity, &triples), "text/html; charset=utf-8")
+    } else {
+        let (format, media_type) = negotiate_rdf_format(headers);
+        (knowledge_graph.serializer().serialize(&triples, &format, &schema.namespace, &schema.prefix)?, media_type)
+    };
+
+    Ok(([(header::CONTENT_TYPE, media_type), (header::VARY, "Accept")], body).into_response())
+}
+
+fn render_html(knowledge_graph: &KnowledgeGraph, entity: &str, triples: &[RdfTriple]) -> String {
+    let labels = LabelIndex::from_graph(knowledge_graph);
+    let base_uri = &knowledge_graph.schema().base_uri;
+    let escape = |value: &str| html_escape::encode_text(value).into_owned();
+    let link = |value: &str| {
+        if value.starts_with("http://") || value.starts_with("https://") {
+            format!(
+                "<a href=\"{}\" title=\"{}\">{}</a>",
+                html_escape::encode_double_quoted_attribute(&local_link(value, base_uri)),
+                html_escape::encode_double_quoted_attribute(value),
+                escape(&labels.resolve(value))
+            )
+        } else {
+            escape(value)
+        }
+    };
+
+    let title = escape(&labels.resolve(entity));
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>{}</title></head>\n<body>\n<h1>{}</h1>\n<p><code>{}</code></p>\n",
+        title, title, escape(entity)
+    );
+
+    for (heading, incoming) in [("Properties", false), ("Referenced by", true)] {
+        let rows: Vec<String> = triples.iter()
+            .filter(|t| if incoming { t.object == entity && t.subject != entity } else { t.subject == entity })
+            .map(|t| {
+                let other = if incoming { &t.subject } else { &t.object };
+                format!("<tr><td>{}</td><td>{}</td></tr>\n", link(&t.predicate), link(other))
+            })
+            .collect();
+        if !rows.is_empty() {
+            html.push_str(&format!("<h2>{}</h2>\n<table>\n{}</table>\n", heading, rows.concat()));
+        }
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
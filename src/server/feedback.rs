@@ -0,0 +1,95 @@
+use axum::extract::{Json, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::core::RdfTriple;
+use crate::knowledge_graph::feedback::DEFAULT_PROMPT_CORRECTIONS;
+use crate::knowledge_graph::{Correction, FeedbackStore, TriplePattern};
+use super::{ApiError, Dataset, ServerState};
+
+type HandlerResult = std::result::Result<Response, ApiError>;
+
+/// JSON body of `POST /feedback`
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SubmitCorrection {
+    subject: String,
+    predicate: String,
+    /// The wrong object
+    object: String,
+    /// The object the triple should have; omit to reject the triple
+    #[serde(default)]
+    corrected_object: Option<String>,
+    /// Why the triple is wrong, shown to the model with the correction
+    #[serde(default)]
+    note: Option<String>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FeedbackParams {
+    /// Maximum number of corrections returned
+    #[serde(default = "default_feedback_limit")]
+    limit: usize,
+}
+
+fn default_feedback_limit() -> usize { 50 }
+
+/// `POST /feedback`: record a reviewer's correction and apply it to the
+/// graph. Later extractions show recent corrections to the model.
+#[utoipa::path(
+    post, path = "/feedback", tag = "feedback", request_body = SubmitCorrection,
+    responses(
+        (status = 201, description = "Correction recorded and applied to the graph", body = Correction),
+        (status = 409, description = "The graph is not stored on disk"),
+    )
+)]
+pub async fn submit(State(state): State<ServerState>, Dataset(dataset): Dataset, Json(body): Json<SubmitCorrection>) -> HandlerResult {
+    let mut knowledge_graph = dataset.write().await;
+    let kg_path = knowledge_graph.storage_path().to_string();
+    if kg_path == ":memory:" {
+        return Err(ApiError::new(StatusCode::CONFLICT, "Corrections are kept next to a stored knowledge graph; this graph is in memory"));
+    }
+
+    // Keep the source of the stored triple, so the correction names the document
+    let triple = RdfTriple::new(body.subject, body.predicate, body.object);
+    let pattern = TriplePattern {
+        subject: Some(triple.subject.clone()),
+        predicate: Some(triple.predicate.clone()),
+        object: Some(triple.object.clone()),
+        graph: None,
+    };
+    let triple = knowledge_graph.find(&pattern).first().map(|&stored| stored.clone()).unwrap_or(triple);
+
+    let mut feedback = FeedbackStore::open_for(&kg_path)?;
+    let correction = match body.corrected_object {
+        Some(object) => feedback.fix(&triple, object, body.note),
+        None => feedback.reject(&triple, body.note),
+    }.clone();
+    correction.apply(&mut knowledge_graph)?;
+    feedback.save()?;
+
+    // Jobs extract with the main graph's corrections
+    if let Some(extractor) = state.extractor.as_ref().filter(|_| dataset.same_graph(&state.knowledge_graph)) {
+        extractor.set_corrections(feedback.recent(DEFAULT_PROMPT_CORRECTIONS));
+    }
+    Ok((StatusCode::CREATED, Json(correction)).into_response())
+}
+
+/// `GET /feedback`: recorded corrections, newest first
+#[utoipa::path(
+    get, path = "/feedback", tag = "feedback", params(FeedbackParams),
+    responses(
+        (status = 200, description = "Recorded corrections, newest first", body = Vec<Correction>),
+    )
+)]
+pub async fn list(Dataset(dataset): Dataset, Query(params): Query<FeedbackParams>) -> HandlerResult {
+    let kg_path = dataset.read().await.storage_path().to_string();
+    let corrections: Vec<Correction> = if kg_path == ":memory:" {
+        Vec::new()
+    } else {
+        FeedbackStore::open_for(&kg_path)?.list().iter().rev().take(params.limit).cloned().collect()
+    };
+    Ok(Json(corrections).into_response())
+}
@@ -0,0 +1,127 @@
+use axum::body::Bytes;
+use axum::extract::Query;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+use crate::config::OutputFormat;
+use crate::utils::parse_rdf;
+use super::{content_type, negotiate_rdf_format, ApiError, Dataset};
+
+type HandlerResult = std::result::Result<Response, ApiError>;
+
+/// `?graph=<iri>` names a graph; `?default` is the default graph
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct GraphParams {
+    /// IRI of the named graph
+    graph: Option<String>,
+    /// Present (with any value) to target the default graph
+    default: Option<String>,
+}
+
+/// The graph a request targets, with `None` for the unnamed default graph
+fn target_graph(params: &GraphParams, default_graph: Option<&str>) -> std::result::Result<Option<String>, ApiError> {
+    match (&params.graph, &params.default) {
+        (Some(graph), None) => Ok(Some(graph.clone())),
+        (None, Some(_)) => Ok(default_graph.map(str::to_string)),
+        _ => Err(ApiError::bad_request("Exactly one of 'graph' or 'default' is required")),
+    }
+}
+
+fn parse_body(headers: &HeaderMap, body: &Bytes) -> std::result::Result<Vec<crate::core::RdfTriple>, ApiError> {
+    let format = match content_type(headers).as_str() {
+        "" | "text/turtle" | "application/x-turtle" => OutputFormat::Turtle,
+        "application/n-triples" | "text/plain" => OutputFormat::NTriples,
+        other => {
+            return Err(ApiError::new(StatusCode::UNSUPPORTED_MEDIA_TYPE, format!("Unsupported content type: {}", other)));
+        }
+    };
+    let content = std::str::from_utf8(body).map_err(|_| ApiError::bad_request("Body is not valid UTF-8"))?;
+    parse_rdf(content, &format).map_err(|e| ApiError::bad_request(e.to_string()))
+}
+
+/// Serialize the graph in the negotiated RDF format
+#[utoipa::path(
+    get, path = "/graph-store", tag = "graph-store", params(GraphParams),
+    responses(
+        (status = 200, description = "Triples of the graph", content(
+            (String = "text/turtle"), (String = "application/n-triples"),
+            (String = "application/ld+json"), (String = "application/rdf+xml"),
+        )),
+        (status = 404, description = "No such named graph"),
+    )
+)]
+pub async fn get_graph(Dataset(knowledge_graph): Dataset, Query(params): Query<GraphParams>, headers: HeaderMap) -> HandlerResult {
+    let knowledge_graph = knowledge_graph.read().await;
+    let graph = target_graph(&params, knowledge_graph.default_graph())?;
+
+    let triples = knowledge_graph.graph_triples(graph.as_deref());
+    if triples.is_empty() && graph.is_some() && graph.as_deref() != knowledge_graph.default_graph() {
+        return Err(ApiError::new(StatusCode::NOT_FOUND, format!("No graph named {}", graph.unwrap_or_default())));
+    }
+
+    let (format, media_type) = negotiate_rdf_format(&headers);
+    let schema = knowledge_graph.schema();
+    let body = knowledge_graph.serializer().serialize(&triples, &format, &schema.namespace, &schema.prefix)?;
+    Ok(([(header::CONTENT_TYPE, media_type)], body).into_response())
+}
+
+/// Replace the graph with the request body
+#[utoipa::path(
+    put, path = "/graph-store", tag = "graph-store", params(GraphParams),
+    request_body(content((String = "text/turtle"), (String = "application/n-triples"))),
+    responses(
+        (status = 201, description = "Graph created"),
+        (status = 204, description = "Graph replaced"),
+        (status = 415, description = "Unsupported RDF format"),
+    )
+)]
+pub async fn put_graph(Dataset(knowledge_graph): Dataset, Query(params): Query<GraphParams>, headers: HeaderMap, body: Bytes) -> HandlerResult {
+    let triples = parse_body(&headers, &body)?;
+    let mut knowledge_graph = knowledge_graph.write().await;
+    let graph = target_graph(&params, knowledge_graph.default_graph())?;
+
+    let existed = !knowledge_graph.graph_triples(graph.as_deref()).is_empty();
+    knowledge_graph.replace_graph(graph.as_deref(), &triples)?;
+    Ok(if existed { StatusCode::NO_CONTENT } else { StatusCode::CREATED }.into_response())
+}
+
+/// Merge the request body into the graph
+#[utoipa::path(
+    post, path = "/graph-store", tag = "graph-store", params(GraphParams),
+    request_body(content((String = "text/turtle"), (String = "application/n-triples"))),
+    responses(
+        (status = 201, description = "Graph created"),
+        (status = 204, description = "Triples added to the existing graph"),
+        (status = 415, description = "Unsupported RDF format"),
+    )
+)]
+pub async fn post_graph(Dataset(knowledge_graph): Dataset, Query(params): Query<GraphParams>, headers: HeaderMap, body: Bytes) -> HandlerResult {
+    let triples = parse_body(&headers, &body)?;
+    let mut knowledge_graph = knowledge_graph.write().await;
+    let graph = target_graph(&params, knowledge_graph.default_graph())?;
+
+    let existed = !knowledge_graph.graph_triples(graph.as_deref()).is_empty();
+    knowledge_graph.add_triples_to_graph(graph.as_deref(), &triples)?;
+    Ok(if existed { StatusCode::NO_CONTENT } else { StatusCode::CREATED }.into_response())
+}
+
+/// Remove every triple of the graph
+#[utoipa::path(
+    delete, path = "/graph-store", tag = "graph-store", params(GraphParams),
+    responses(
+        (status = 204, description = "Graph removed"),
+        (status = 404, description = "Graph is empty or does not exist"),
+    )
+)]
+pub async fn delete_graph(Dataset(knowledge_graph): Dataset, Query(params): Query<GraphParams>) -> HandlerResult {
+    let mut knowledge_graph = knowledge_graph.write().await;
+    let graph = target_graph(&params, knowledge_graph.default_graph())?;
+
+    if knowledge_graph.remove_graph(graph.as_deref())? == 0 {
+        return Err(ApiError::new(StatusCode::NOT_FOUND, "Graph is empty or does not exist"));
+    }
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
@@ -0,0 +1,441 @@
+//! GraphQL API whose types are generated from the RDF schema: one object
+//! type per class with the schema's predicates as fields, so the graph can
+//! be browsed without writing SPARQL.
+
+use async_graphql::dynamic::{Field, FieldFuture, FieldValue, InputValue, Object, ResolverContext, Schema, TypeRef};
+use axum::extract::{RawQuery, State};
+use axum::http::StatusCode;
+use axum::response::{Html, IntoResponse, Response};
+use axum::Json;
+use std::collections::{BTreeMap, HashSet};
+use std::time::{Duration, Instant};
+use tokio::sync::OwnedRwLockReadGuard;
+
+use crate::config::RdfSchema;
+use crate::knowledge_graph::inference::RDF_TYPE;
+use crate::knowledge_graph::labels::label_rank;
+use crate::knowledge_graph::{KnowledgeGraph, TriplePattern};
+use crate::utils::serialization::is_resource;
+use super::{ApiError, Dataset, ServerState};
+
+type HandlerResult = std::result::Result<Response, ApiError>;
+
+/// Read access to the graph for the duration of one GraphQL request
+type Graph = OwnedRwLockReadGuard<KnowledgeGraph>;
+
+/// Type returned by `entity` and `search`, with every schema predicate
+const RESOURCE_TYPE: &str = "Resource";
+const DEFAULT_LIST_LIMIT: i64 = 100;
+const MAX_LIST_LIMIT: i64 = 1000;
+/// Most values a predicate field lists per entity, so nested relations
+/// cannot multiply into the whole graph
+const MAX_NESTED_LIMIT: i64 = 100;
+/// Deepest selection accepted, so nested relations cannot walk the whole graph
+const MAX_QUERY_DEPTH: usize = 16;
+/// Most fields one query may select
+const MAX_QUERY_COMPLEXITY: usize = 500;
+/// Time one request may take before its remaining fields fail
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// When the current request runs out of time
+struct Deadline(Instant);
+
+/// An entity being resolved
+struct Node(String);
+
+/// A schema predicate exposed as a field
+struct PredicateField {
+    name: String,
+    uri: String,
+    description: String,
+    /// Type name of the predicate's range, when it is a schema class
+    range: Option<String>,
+    /// Domain class URI; the field is on every type when unset
+    domain: Option<String>,
+}
+
+/// Build the GraphQL schema for `schema`. Classes become object types
+/// named after the class, each with `uri`, `label`, `types` and a field per
+/// predicate whose domain allows the class. Predicates whose range is a
+/// schema class link to that type; the others list their values as strings.
+/// The query root has `entity(uri)`, `search(text)`, and for each class a
+/// lookup by URI (`person(uri)`) and a list of its instances (`allPerson`).
+pub fn build_schema(schema: &RdfSchema) -> std::result::Result<Schema, String> {
+    let mut type_names = HashSet::from(["Query".to_string(), RESOURCE_TYPE.to_string()]);
+    let classes: BTreeMap<String, (String, String)> = schema.classes.iter()
+        .map(|(name, description)| {
+            let type_name = unique_name(graphql_name(name, true), &mut type_names);
+            (schema.resolve_term(name), (type_name, description.clone()))
+        })
+        .collect();
+
+    let mut predicates: Vec<PredicateField> = Vec::new();
+    let mut field_names: HashSet<String> = ["uri", "label", "types"].into_iter().map(String::from).collect();
+    let mut names: Vec<_> = schema.predicates.iter().collect();
+    names.sort();
+    for (name, description) in names {
+        let axioms = schema.predicate_axioms.get(name);
+        predicates.push(PredicateField {
+            name: unique_name(graphql_name(name, false), &mut field_names),
+            uri: schema.resolve_term(name),
+            description: description.clone(),
+            range: axioms.and_then(|a| a.range.as_deref())
+                .and_then(|range| classes.get(&schema.resolve_term(range)))
+                .map(|(type_name, _)| type_name.clone()),
+            domain: axioms.and_then(|a| a.domain.as_deref()).map(|domain| schema.resolve_term(domain)),
+        });
+    }
+
+    let mut query = Object::new("Query")
+        .field(Field::new("entity", TypeRef::named(RESOURCE_TYPE), |ctx| {
+            FieldFuture::new(async move { resolve_entity(&ctx, None) })
+        })
+        .description("Any entity in the graph by URI")
+        .argument(InputValue::new("uri", TypeRef::named_nn(TypeRef::STRING))))
+        .field(Field::new("search", TypeRef::named_nn_list_nn(RESOURCE_TYPE), |ctx| {
+            FieldFuture::Value(Some(search(&ctx)))
+        })
+        .description("Entities with a label word starting with the text, best matches first")
+        .argument(InputValue::new("text", TypeRef::named_nn(TypeRef::STRING)))
+        .argument(InputValue::new("limit", TypeRef::named(TypeRef::INT)).default_value(10)));
+
+    let mut root_fields: HashSet<String> = ["entity", "search"].into_iter().map(String::from).collect();
+    let mut types = vec![entity_type(RESOURCE_TYPE, "Any entity in the graph", predicates.iter())];
+    for (class_uri, (type_name, description)) in &classes {
+        let fields = predicates.iter()
+            .filter(|p| p.domain.as_ref().is_none_or(|domain| schema.is_subclass_of(class_uri, domain)));
+        types.push(entity_type(type_name, description, fields));
+
+        let lookup = unique_name(lower_first(type_name), &mut root_fields);
+        let class = class_uri.clone();
+        query = query.field(Field::new(lookup, TypeRef::named(type_name), move |ctx| {
+            let class = class.clone();
+            FieldFuture::new(async move { resolve_entity(&ctx, Some(&class)) })
+        })
+        .description(format!("A {} by URI", type_name))
+        .argument(InputValue::new("uri", TypeRef::named_nn(TypeRef::STRING))));
+
+        let list = unique_name(format!("all{}", type_name), &mut root_fields);
+        let class = class_uri.clone();
+        query = query.field(Field::new(list, TypeRef::named_nn_list_nn(type_name), move |ctx| {
+            FieldFuture::Value(Some(instances(&ctx, &class)))
+        })
+        .description(format!("Instances of {}, including those of its subclasses", type_name))
+        .argument(InputValue::new("limit", TypeRef::named(TypeRef::INT)).default_value(DEFAULT_LIST_LIMIT))
+        .argument(InputValue::new("offset", TypeRef::named(TypeRef::INT)).default_value(0)));
+    }
+
+    types.into_iter()
+        .fold(Schema::build("Query", None, None).register(query), |builder, ty| builder.register(ty))
+        .limit_depth(MAX_QUERY_DEPTH)
+        .limit_complexity(MAX_QUERY_COMPLEXITY)
+        .finish()
+        .map_err(|e| e.to_string())
+}
+
+/// Object type for entities with `uri`, `label`, `types` and the given predicates
+fn entity_type<'a>(name: &str, description: &str, predicates: impl Iterator<Item = &'a PredicateField>) -> Object {
+    let mut object = Object::new(name)
+        .description(description)
+        .field(Field::new("uri", TypeRef::named_nn(TypeRef::STRING), |ctx| {
+            FieldFuture::Value(node(&ctx).map(|uri| FieldValue::value(uri.to_string())))
+        }))
+        .field(Field::new("label", TypeRef::named(TypeRef::STRING), |ctx| {
+            FieldFuture::Value(label(&ctx).map(FieldValue::value))
+        }))
+        .field(Field::new("types", TypeRef::named_nn_list_nn(TypeRef::STRING), |ctx| {
+            let types = match (node(&ctx), ctx.data::<Graph>()) {
+                (Some(uri), Ok(graph)) => objects(graph, uri, RDF_TYPE).into_iter()
+                    .map(|class| FieldValue::value(graph.schema().term_name(&class)))
+                    .collect(),
+                _ => Vec::new(),
+            };
+            FieldFuture::Value(Some(FieldValue::list(types)))
+        }).description("Classes of the entity, as schema names where possible"));
+
+    for predicate in predicates {
+        let uri = predicate.uri.clone();
+        let field = match &predicate.range {
+            Some(range) => Field::new(&predicate.name, TypeRef::named_nn_list_nn(range), move |ctx| {
+                let uri = uri.clone();
+                FieldFuture::new(async move {
+                    within_deadline(&ctx)?;
+                    let linked = related(&ctx, &uri).into_iter()
+                        .take(list_limit(&ctx, "limit", MAX_NESTED_LIMIT, MAX_NESTED_LIMIT))
+                        .map(|object| FieldValue::owned_any(Node(object)));
+                    Ok(Some(FieldValue::list(linked)))
+                })
+            }),
+            None => Field::new(&predicate.name, TypeRef::named_nn_list_nn(TypeRef::STRING), move |ctx| {
+                let uri = uri.clone();
+                FieldFuture::new(async move {
+                    within_deadline(&ctx)?;
+                    let values = match (node(&ctx), ctx.data::<Graph>()) {
+                        (Some(subject), Ok(graph)) => objects(graph, subject, &uri),
+                        _ => Vec::new(),
+                    };
+                    let limit = list_limit(&ctx, "limit", MAX_NESTED_LIMIT, MAX_NESTED_LIMIT);
+                    Ok(Some(FieldValue::list(values.into_iter().take(limit).map(FieldValue::value))))
+                })
+            }),
+        };
+        let field = field.argument(InputValue::new("limit", TypeRef::named(TypeRef::INT)).default_value(MAX_NESTED_LIMIT));
+        object = object.field(field.description(&predicate.description));
+    }
+    object
+}
+
+fn node<'a>(ctx: &'a ResolverContext) -> Option<&'a str> {
+    ctx.parent_value.downcast_ref::<Node>().map(|node| node.0.as_str())
+}
+
+/// Objects of the entity's `predicate` triples, in store order without repeats
+fn objects(graph: &KnowledgeGraph, subject: &str, predicate: &str) -> Vec<String> {
+    let pattern = TriplePattern {
+        subject: Some(subject.to_string()),
+        predicate: Some(predicate.to_string()),
+        ..Default::default()
+    };
+    let mut seen = HashSet::new();
+    graph.find(&pattern).into_iter()
+        .map(|t| t.object.clone())
+        .filter(|object| seen.insert(object.clone()))
+        .collect()
+}
+
+/// Resources linked from the current entity by `predicate`
+fn related(ctx: &ResolverContext, predicate: &str) -> Vec<String> {
+    match (node(ctx), ctx.data::<Graph>()) {
+        (Some(subject), Ok(graph)) => objects(graph, subject, predicate).into_iter()
+            .filter(|object| is_resource(object))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Best label of the current entity: `rdfs:label`, then `skos:prefLabel`, then a name
+fn label(ctx: &ResolverContext) -> Option<String> {
+    let (uri, graph) = (node(ctx)?, ctx.data::<Graph>().ok()?);
+    let pattern = TriplePattern { subject: Some(uri.to_string()), ..Default::default() };
+    graph.find(&pattern).into_iter()
+        .filter_map(|t| label_rank(&t.predicate).map(|rank| (rank, t.object.trim())))
+        .filter(|(_, label)| !label.is_empty())
+        .min_by_key(|(rank, _)| *rank)
+        .map(|(_, label)| label.to_string())
+}
+
+fn has_class(graph: &KnowledgeGraph, uri: &str, class: &str) -> bool {
+    objects(graph, uri, RDF_TYPE).iter().any(|t| graph.schema().is_subclass_of(t, class))
+}
+
+/// The entity named by the `uri` argument, if the graph mentions it (and,
+/// with `class`, if it is an instance of that class)
+fn resolve_entity<'a>(ctx: &ResolverContext, class: Option<&str>) -> async_graphql::Result<Option<FieldValue<'a>>> {
+    let uri = ctx.args.try_get("uri")?.string()?;
+    let graph = ctx.data::<Graph>()?;
+    let known = match class {
+        Some(class) => has_class(graph, uri, class),
+        None => !graph.triples_about(uri).is_empty(),
+    };
+    Ok(known.then(|| FieldValue::owned_any(Node(uri.to_string()))))
+}
+
+fn search<'a>(ctx: &ResolverContext) -> FieldValue<'a> {
+    let text = ctx.args.get("text").and_then(|v| v.string().ok()).unwrap_or_default();
+    let limit = list_limit(ctx, "limit", 10, MAX_LIST_LIMIT);
+    let matches = match ctx.data::<Graph>() {
+        Ok(graph) => graph.autocomplete(text, limit),
+        Err(_) => Vec::new(),
+    };
+    FieldValue::list(matches.into_iter().map(|m| FieldValue::owned_any(Node(m.uri))))
+}
+
+/// Subjects typed with `class` or one of its subclasses, in store order
+fn instances<'a>(ctx: &ResolverContext, class: &str) -> FieldValue<'a> {
+    let limit = list_limit(ctx, "limit", DEFAULT_LIST_LIMIT, MAX_LIST_LIMIT);
+    let offset = ctx.args.get("offset").and_then(|v| v.i64().ok()).unwrap_or(0).max(0) as usize;
+    let Ok(graph) = ctx.data::<Graph>() else {
+        return FieldValue::list(Vec::<FieldValue>::new());
+    };
+
+    let pattern = TriplePattern { predicate: Some(RDF_TYPE.to_string()), ..Default::default() };
+    let mut seen = HashSet::new();
+    let subjects: Vec<String> = graph.find(&pattern).into_iter()
+        .filter(|t| graph.schema().is_subclass_of(&t.object, class))
+        .filter(|t| seen.insert(t.subject.as_str()))
+        .skip(offset)
+        .take(limit)
+        .map(|t| t.subject.clone())
+        .collect();
+    FieldValue::list(subjects.into_iter().map(|uri| FieldValue::owned_any(Node(uri))))
+}
+
+fn list_limit(ctx: &ResolverContext, name: &str, default: i64, max: i64) -> usize {
+    ctx.args.get(name).and_then(|v| v.i64().ok()).unwrap_or(default).clamp(0, max) as usize
+}
+
+/// Fails once the request has used up its time, so the fields still to
+/// be resolved end with an error rather than holding the graph lock
+fn within_deadline(ctx: &ResolverContext) -> async_graphql::Result<()> {
+    match ctx.data_opt::<Deadline>() {
+        Some(Deadline(deadline)) if Instant::now() >= *deadline => {
+            Err(format!("Query exceeded the {}s time limit", REQUEST_TIMEOUT.as_secs()).into())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// A GraphQL name from a class or predicate name: the local part of
+/// prefixed names and URIs, other characters replaced by `_`
+fn graphql_name(name: &str, is_type: bool) -> String {
+    let local = name.rsplit(['/', '#', ':']).find(|part| !part.is_empty()).unwrap_or(name);
+    let mut sanitized: String = local.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if sanitized.is_empty() || sanitized.starts_with(|c: char| c.is_ascii_digit()) {
+        sanitized.insert(0, '_');
+    }
+    if sanitized.starts_with("__") {
+        sanitized.replace_range(..2, "_x");
+    }
+    if is_type {
+        let mut chars = sanitized.chars();
+        chars.next().map(|first| first.to_ascii_uppercase().to_string() + chars.as_str()).unwrap_or(sanitized)
+    } else {
+        sanitized
+    }
+}
+
+fn lower_first(name: &str) -> String {
+    let mut chars = name.chars();
+    chars.next().map(|first| first.to_ascii_lowercase().to_string() + chars.as_str()).unwrap_or_default()
+}
+
+/// `name`, or `name` with `_` appended until it is not yet used
+fn unique_name(mut name: String, used: &mut HashSet<String>) -> String {
+    while used.contains(&name) {
+        name.push('_');
+    }
+    used.insert(name.clone());
+    name
+}
+
+/// `POST /graphql` with a JSON body (`query`, `variables`, `operationName`)
+#[utoipa::path(
+    post, path = "/graphql", tag = "graphql",
+    request_body(content = Object, content_type = "application/json", description = "GraphQL request: query, variables and operationName"),
+    responses(
+        (status = 200, description = "GraphQL response with data and errors", body = Object),
+        (status = 503, description = "The schema could not be turned into a GraphQL schema"),
+        (status = 504, description = "The query exceeded the time limit"),
+    )
+)]
+pub async fn execute(State(state): State<ServerState>, Dataset(knowledge_graph): Dataset, Json(request): Json<async_graphql::Request>) -> HandlerResult {
+    run(&state, knowledge_graph.read_owned().await, request).await
+}
+
+/// `GET /graphql?query=...` runs a query; without one it serves GraphiQL
+pub async fn query_or_playground(State(state): State<ServerState>, Dataset(knowledge_graph): Dataset, RawQuery(params): RawQuery) -> HandlerResult {
+    match params.filter(|params| !params.is_empty()) {
+        Some(params) => {
+            let request = async_graphql::http::parse_query_string(&params)
+                .map_err(|e| ApiError::bad_request(format!("Invalid GraphQL request: {}", e)))?;
+            run(&state, knowledge_graph.read_owned().await, request).await
+        }
+        None => Ok(Html(async_graphql::http::GraphiQLSource::build().endpoint("/graphql").finish()).into_response()),
+    }
+}
+
+async fn run(state: &ServerState, graph: Graph, request: async_graphql::Request) -> HandlerResult {
+    let schema = state.graphql.as_ref()
+        .map_err(|e| ApiError::new(StatusCode::SERVICE_UNAVAILABLE, format!("GraphQL is unavailable: {}", e)))?;
+    let request = request.data(graph).data(Deadline(Instant::now() + REQUEST_TIMEOUT));
+    let response = tokio::time::timeout(REQUEST_TIMEOUT, schema.execute(request)).await
+        .map_err(|_| ApiError::new(StatusCode::GATEWAY_TIMEOUT, format!("Query exceeded the {}s time limit", REQUEST_TIMEOUT.as_secs())))?;
+    Ok(Json(response).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PredicateAxioms;
+    use crate::core::RdfTriple;
+    use crate::knowledge_graph::SharedKnowledgeGraph;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_graphql_schema() {
+        let mut schema = RdfSchema {
+            namespace: "http://ex.org/ont#".into(),
+            base_uri: "http://ex.org/res/".into(),
+            ..Default::default()
+        };
+        for (name, description) in [("Person", "A human being"), ("Manager", "Manages people"), ("Organization", "A company")] {
+            schema.classes.insert(name.into(), description.into());
+        }
+        for name in ["worksFor", "hasName", "foundedIn", "foaf:nick"] {
+            schema.predicates.insert(name.into(), format!("The {}", name));
+        }
+        schema.subclass_of.insert("Manager".into(), vec!["Person".into()]);
+        schema.predicate_axioms.insert("worksFor".into(), PredicateAxioms {
+            domain: Some("Person".into()),
+            range: Some("Organization".into()),
+            ..Default::default()
+        });
+        schema.predicate_axioms.insert("foundedIn".into(), PredicateAxioms { domain: Some("Organization".into()), ..Default::default() });
+
+        let graphql = build_schema(&schema).unwrap();
+        let sdl = graphql.sdl();
+        assert!(sdl.contains("type Manager {"), "{}", sdl);
+        assert!(sdl.contains("worksFor(limit: Int = 100): [Organization!]!"), "{}", sdl);
+        assert!(sdl.contains("allManager(limit: Int = 100, offset: Int = 0): [Manager!]!"));
+        assert!(sdl.contains("nick(limit: Int = 100): [String!]!"));
+
+        let ont = |name: &str| format!("http://ex.org/ont#{}", name);
+        let mut kg = KnowledgeGraph::in_memory(schema).unwrap();
+        kg.add_triples(&[
+            RdfTriple::new("http://ex.org/res/ana".into(), RDF_TYPE.into(), ont("Manager")),
+            RdfTriple::new("http://ex.org/res/ana".into(), ont("hasName"), "Ana Lopez".into()),
+            RdfTriple::new("http://ex.org/res/ana".into(), ont("worksFor"), "http://ex.org/res/acme".into()),
+            RdfTriple::new("http://ex.org/res/acme".into(), RDF_TYPE.into(), ont("Organization")),
+            RdfTriple::new("http://ex.org/res/acme".into(), ont("foundedIn"), "1999".into()),
+        ]).unwrap();
+        let graph = SharedKnowledgeGraph::new(kg);
+
+        let run = async |query: &str| {
+            let response = graphql.execute(async_graphql::Request::new(query).data(graph.read_owned().await)).await;
+            assert!(response.errors.is_empty(), "{:?}", response.errors);
+            serde_json::to_value(response.data).unwrap()
+        };
+
+        // Subclass instances are listed with their superclass
+        let people = run("{ allPerson { uri label types worksFor { uri foundedIn } } }").await;
+        assert_eq!(people, json!({"allPerson": [{
+            "uri": "http://ex.org/res/ana",
+            "label": "Ana Lopez",
+            "types": ["Manager"],
+            "worksFor": [{"uri": "http://ex.org/res/acme", "foundedIn": ["1999"]}],
+        }]}));
+
+        let lookup = run(r#"{ person(uri: "http://ex.org/res/acme") { uri } organization(uri: "http://ex.org/res/acme") { hasName }
+            entity(uri: "http://ex.org/res/missing") { uri } search(text: "lop") { uri } }"#).await;
+        assert_eq!(lookup, json!({
+            "person": null,
+            "organization": {"hasName": []},
+            "entity": null,
+            "search": [{"uri": "http://ex.org/res/ana"}],
+        }));
+
+        // Nested lists are capped, and so are the fields of one query and its time
+        let capped = run(r#"{ person(uri: "http://ex.org/res/ana") { worksFor(limit: 0) { uri } hasName(limit: 5000) } }"#).await;
+        assert_eq!(capped, json!({"person": {"worksFor": [], "hasName": ["Ana Lopez"]}}));
+        let wide = format!("{{ {} }}", (0..MAX_QUERY_COMPLEXITY).map(|i| format!("e{}: entity(uri: \"x\") {{ uri }}", i)).collect::<Vec<_>>().join(" "));
+        let response = graphql.execute(async_graphql::Request::new(wide).data(graph.read_owned().await)).await;
+        assert!(response.errors[0].message.contains("complex"), "{:?}", response.errors);
+        let late = async_graphql::Request::new("{ allPerson { hasName } }")
+            .data(graph.read_owned().await)
+            .data(Deadline(Instant::now()));
+        let response = graphql.execute(late).await;
+        assert!(response.errors[0].message.contains("time limit"), "{:?}", response.errors);
+    }
+}
@@ -0,0 +1,368 @@
+use axum::http::StatusCode;
+use std::collections::HashMap;
+use std::time::Instant;
+use tokio::sync::{broadcast, mpsc};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+use tracing::info;
+
+use crate::config::Permission;
+use crate::error::{ErrorContext, Result};
+use crate::knowledge_graph::{Binding, DocumentStatus, JobDocument, SimpleSparqlResults};
+use super::ingest::{self, IngestJob, JobState};
+use super::{auth, ApiError, Dataset, ServerState};
+
+pub mod proto {
+    tonic::include_proto!("rdf_extractor.v1");
+}
+
+use proto::knowledge_extractor_server::{KnowledgeExtractor, KnowledgeExtractorServer};
+
+type RpcResult<T> = std::result::Result<Response<T>, Status>;
+
+/// gRPC counterpart of the REST API, sharing its state, API keys and jobs
+pub struct GrpcService {
+    state: ServerState,
+}
+
+impl GrpcService {
+    pub fn new(state: ServerState) -> Self {
+        Self { state }
+    }
+
+    /// Check the call's API key and log it like an HTTP request
+    fn authorize<T>(&self, method: &str, request: &Request<T>, required: Permission) -> std::result::Result<Dataset, Status> {
+        let started = Instant::now();
+        let headers = request.metadata().clone().into_headers();
+        let result = auth::authorize_call(&self.state, &headers, required);
+
+        if self.state.log_requests {
+            let (caller, status) = match &result {
+                Ok((caller, _)) => (caller.as_str(), "OK".to_string()),
+                Err(error) => ("-", error.status.as_u16().to_string()),
+            };
+            info!("gRPC {} {} key={} {}ms", method, status, caller, started.elapsed().as_millis());
+        }
+
+        result.map(|(_, dataset)| dataset).map_err(Status::from)
+    }
+}
+
+impl From<ApiError> for Status {
+    fn from(error: ApiError) -> Self {
+        let message = error.message;
+        match error.status {
+            StatusCode::BAD_REQUEST | StatusCode::UNSUPPORTED_MEDIA_TYPE => Status::invalid_argument(message),
+            StatusCode::UNAUTHORIZED => Status::unauthenticated(message),
+            StatusCode::FORBIDDEN => Status::permission_denied(message),
+            StatusCode::NOT_FOUND => Status::not_found(message),
+            StatusCode::SERVICE_UNAVAILABLE => Status::failed_precondition(message),
+            _ => Status::internal(message),
+        }
+    }
+}
+
+impl From<crate::error::Error> for Status {
+    fn from(error: crate::error::Error) -> Self {
+        match error {
+            crate::error::Error::Query { .. } => Status::invalid_argument(error.to_string()),
+            _ => Status::internal(error.to_string()),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl KnowledgeExtractor for GrpcService {
+    async fn submit_extraction(&self, request: Request<proto::SubmitExtractionRequest>) -> RpcResult<proto::Job> {
+        let Dataset(knowledge_graph) = self.authorize("SubmitExtraction", &request, Permission::Ingest)?;
+
+        let upload_dir = std::env::temp_dir().join(format!("rdf-ingest-{}", uuid::Uuid::new_v4()));
+        let mut inputs = Vec::new();
+        for document in request.into_inner().documents {
+            let input = match document.source {
+                Some(proto::document::Source::Url(url)) => ingest::url_input(&self.state, url).await?,
+                Some(proto::document::Source::File(file)) => {
+                    ingest::save_upload(&upload_dir, inputs.len(), &file.name, &file.content).await?
+                }
+                None => return Err(Status::invalid_argument("Document without a url or file")),
+            };
+            inputs.push(input);
+        }
+
+        let job_id = ingest::start_job(&self.state, knowledge_graph.clone(), inputs, upload_dir).await?;
+        let job = ingest::find_job(&self.state, &knowledge_graph, &job_id, job_message)?;
+        Ok(Response::new(job))
+    }
+
+    async fn get_job(&self, request: Request<proto::GetJobRequest>) -> RpcResult<proto::Job> {
+        let Dataset(knowledge_graph) = self.authorize("GetJob", &request, Permission::Read)?;
+        let job = ingest::find_job(&self.state, &knowledge_graph, &request.get_ref().job_id, job_message)?;
+        Ok(Response::new(job))
+    }
+
+    type WatchJobStream = ReceiverStream<std::result::Result<proto::JobEvent, Status>>;
+
+    async fn watch_job(&self, request: Request<proto::GetJobRequest>) -> RpcResult<Self::WatchJobStream> {
+        let Dataset(knowledge_graph) = self.authorize("WatchJob", &request, Permission::Read)?;
+        let job_id = request.into_inner().job_id;
+
+        // Subscribe before the first snapshot so no update is missed
+        let mut updates = self.state.job_updates.subscribe();
+        let first = ingest::find_job(&self.state, &knowledge_graph, &job_id, job_message)?;
+
+        let (sender, receiver) = mpsc::channel(16);
+        let state = self.state.clone();
+        tokio::spawn(async move {
+            let mut done = first.state == proto::JobState::Completed as i32;
+            if sender.send(Ok(proto::JobEvent { job: Some(first), document: None })).await.is_err() {
+                return;
+            }
+
+            while !done {
+                let document = match updates.recv().await {
+                    Ok(update) if update.job_id == job_id => update.document,
+                    Ok(_) => continue,
+                    // Missed updates are covered by the next snapshot
+                    Err(broadcast::error::RecvError::Lagged(_)) => None,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                let Ok(job) = ingest::find_job(&state, &knowledge_graph, &job_id, job_message) else { break };
+                done = job.state == proto::JobState::Completed as i32;
+                let document = document.and_then(|index| job.documents.get(index).cloned());
+                if sender.send(Ok(proto::JobEvent { job: Some(job), document })).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+
+    async fn query(&self, request: Request<proto::QueryRequest>) -> RpcResult<proto::QueryResponse> {
+        let Dataset(knowledge_graph) = self.authorize("Query", &request, Permission::Read)?;
+        let query = request.into_inner().query;
+
+        let knowledge_graph = knowledge_graph.read_owned().await;
+        let results = tokio::task::spawn_blocking(move || knowledge_graph.execute_sparql(&query)).await
+            .map_err(|e| Status::internal(e.to_string()))??;
+
+        let response = match results {
+            SimpleSparqlResults::Solutions { variables, rows } => proto::QueryResponse {
+                variables: SimpleSparqlResults::columns(&variables, &rows),
+                solutions: rows.into_iter()
+                    .map(|row| proto::Solution {
+                        bindings: row.into_iter().map(|(var, binding)| (var, term_message(binding))).collect(),
+                    })
+                    .collect(),
+                boolean: None,
+            },
+            SimpleSparqlResults::Boolean(result) => proto::QueryResponse { boolean: Some(result), ..Default::default() },
+        };
+        Ok(Response::new(response))
+    }
+
+    async fn generate(&self, request: Request<proto::GenerateRequest>) -> RpcResult<proto::GenerateResponse> {
+        let Dataset(knowledge_graph) = self.authorize("Generate", &request, Permission::Ingest)?;
+        let request = request.into_inner();
+
+        let context: Option<HashMap<String, serde_json::Value>> = request.context_json.as_deref()
+            .map(serde_json::from_str)
+            .transpose()
+            .map_err(|e| Status::invalid_argument(format!("Invalid context_json: {}", e)))?;
+
+        let document = super::generate_document(&self.state, &knowledge_graph, request.template_id, context).await?;
+
+        Ok(Response::new(proto::GenerateResponse {
+            content: document.generated_content,
+            word_count: document.metadata.word_count as u64,
+            queries_executed: document.metadata.queries_executed,
+            sources: document.metadata.sources,
+        }))
+    }
+}
+
+fn job_message(job: &IngestJob) -> proto::Job {
+    let state = match job.state {
+        JobState::Queued => proto::JobState::Queued,
+        JobState::Running => proto::JobState::Running,
+        JobState::Completed => proto::JobState::Completed,
+    };
+    let manifest = &job.manifest;
+    proto::Job {
+        job_id: manifest.job_id.clone(),
+        state: state as i32,
+        completed: manifest.count(DocumentStatus::Completed) as u32,
+        failed: manifest.count(DocumentStatus::Failed) as u32,
+        pending: manifest.count(DocumentStatus::Pending) as u32,
+        documents: manifest.documents.iter().map(document_message).collect(),
+        run_graph: manifest.run_graph.clone(),
+    }
+}
+
+fn document_message(document: &JobDocument) -> proto::DocumentProgress {
+    let status = match document.status {
+        DocumentStatus::Pending => proto::DocumentStatus::Pending,
+        DocumentStatus::Completed => proto::DocumentStatus::Completed,
+        DocumentStatus::Failed => proto::DocumentStatus::Failed,
+    };
+    proto::DocumentProgress {
+        source: document.source.clone(),
+        status: status as i32,
+        attempts: document.attempts,
+        triples: document.triples as u64,
+        error: document.error.clone(),
+    }
+}
+
+fn term_message(binding: Binding) -> proto::Term {
+    use proto::term::Kind;
+
+    match binding {
+        Binding::Iri { value } => proto::Term { kind: Kind::Iri as i32, value, ..Default::default() },
+        Binding::BlankNode { value } => proto::Term {
+            kind: Kind::BlankNode as i32,
+            value: value.trim_start_matches("_:").to_string(),
+            ..Default::default()
+        },
+        Binding::Literal { value, datatype, lang } => proto::Term {
+            kind: Kind::Literal as i32,
+            value,
+            datatype,
+            language: lang,
+        },
+    }
+}
+
+/// Serve the gRPC API until the process is stopped
+pub async fn serve(state: ServerState, addr: &str) -> Result<()> {
+    let addr: std::net::SocketAddr = addr.parse()
+        .server_context(|| format!("Invalid gRPC address: {}", addr))?;
+    info!("Serving gRPC API on {}", addr);
+
+    tonic::transport::Server::builder()
+        .add_service(KnowledgeExtractorServer::new(GrpcService::new(state)))
+        .serve(addr)
+        .await
+        .server_context(|| "gRPC server stopped unexpectedly")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RdfSchema;
+    use crate::core::RdfTriple;
+    use crate::knowledge_graph::KnowledgeGraph;
+    use proto::knowledge_extractor_client::KnowledgeExtractorClient;
+    use tokio_stream::StreamExt;
+
+    #[tokio::test]
+    async fn test_grpc_query_and_job_events() {
+        let mut config = crate::config::Configuration::example();
+        config.llm_settings.base_url = "http://127.0.0.1:9".to_string();
+        config.llm_settings.auto_configure = false;
+        config.llm_settings.cache.enabled = false;
+        let extractor = crate::core::RdfExtractorBuilder::new(config).build().unwrap();
+
+        let mut kg = KnowledgeGraph::in_memory(RdfSchema::default()).unwrap();
+        kg.add_triples(&[RdfTriple::new(
+            "http://ex.org/acme".to_string(),
+            "http://ex.org/hasName".to_string(),
+            "Acme".to_string(),
+        )]).unwrap();
+        let state = ServerState::new(kg).with_extractor(extractor);
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            tonic::transport::Server::builder()
+                .add_service(KnowledgeExtractorServer::new(GrpcService::new(state)))
+                .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener))
+                .await
+                .unwrap()
+        });
+        let mut client = KnowledgeExtractorClient::connect(format!("http://{}", addr)).await.unwrap();
+
+        let query = "SELECT ?name WHERE { ?c <http://ex.org/hasName> ?name }".to_string();
+        let results = client.query(proto::QueryRequest { query }).await.unwrap().into_inner();
+        assert_eq!(results.variables, vec!["name".to_string()]);
+        assert_eq!(results.solutions[0].bindings["name"].value, "Acme");
+
+        let empty = client.submit_extraction(proto::SubmitExtractionRequest { documents: vec![] }).await.unwrap_err();
+        assert_eq!(empty.code(), tonic::Code::InvalidArgument);
+
+        let file = proto::UploadedFile { name: "notes.txt".to_string(), content: b"Acme was founded in 1999.".to_vec() };
+        let documents = vec![proto::Document { source: Some(proto::document::Source::File(file)) }];
+        let job = client.submit_extraction(proto::SubmitExtractionRequest { documents }).await.unwrap().into_inner();
+
+        // The LLM server is unreachable, so the document fails
+        let mut events = client.watch_job(proto::GetJobRequest { job_id: job.job_id.clone() }).await.unwrap().into_inner();
+        let mut last = None;
+        while let Some(event) = events.next().await {
+            last = Some(event.unwrap());
+        }
+        let last = last.unwrap().job.unwrap();
+        assert_eq!(last.state, proto::JobState::Completed as i32);
+        assert_eq!(last.failed, 1);
+        assert_eq!(last.documents[0].source, "notes.txt");
+
+        let missing = client.get_job(proto::GetJobRequest { job_id: "nope".to_string() }).await.unwrap_err();
+        assert_eq!(missing.code(), tonic::Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_grpc_api_key_permissions() {
+        use crate::config::{ApiKeySettings, ServerSettings};
+
+        let key = |name: &str, permission| ApiKeySettings {
+            name: name.to_string(),
+            key: Some(format!("{}-secret", name)),
+            key_env: None,
+            permission,
+            dataset: None,
+        };
+        let settings = ServerSettings {
+            api_keys: vec![key("reader", Permission::Read), key("loader", Permission::Ingest)],
+            log_requests: false,
+            ..Default::default()
+        };
+        let kg = KnowledgeGraph::in_memory(RdfSchema::default()).unwrap();
+        let state = ServerState::new(kg).with_settings(&settings).unwrap();
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            tonic::transport::Server::builder()
+                .add_service(KnowledgeExtractorServer::new(GrpcService::new(state)))
+                .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener))
+                .await
+                .unwrap()
+        });
+        let mut client = KnowledgeExtractorClient::connect(format!("http://{}", addr)).await.unwrap();
+        fn with_key<T>(key: Option<&str>, message: T) -> Request<T> {
+            let mut request = Request::new(message);
+            if let Some(key) = key {
+                request.metadata_mut().insert("authorization", format!("Bearer {}", key).parse().unwrap());
+            }
+            request
+        }
+        let generate = || proto::GenerateRequest { template_id: "report".to_string(), context_json: None };
+        let query = || proto::QueryRequest { query: "SELECT ?s WHERE { ?s ?p ?o }".to_string() };
+
+        let anonymous = client.query(with_key(None, query())).await.unwrap_err();
+        assert_eq!(anonymous.code(), tonic::Code::Unauthenticated);
+        let unknown = client.query(with_key(Some("guess"), query())).await.unwrap_err();
+        assert_eq!(unknown.code(), tonic::Code::Unauthenticated);
+        assert!(client.query(with_key(Some("reader-secret"), query())).await.is_ok());
+
+        // Generation spends LLM calls, so a read key is not enough
+        let denied = client.generate(with_key(Some("reader-secret"), generate())).await.unwrap_err();
+        assert_eq!(denied.code(), tonic::Code::PermissionDenied);
+        let submit = proto::SubmitExtractionRequest { documents: vec![] };
+        let denied = client.submit_extraction(with_key(Some("reader-secret"), submit)).await.unwrap_err();
+        assert_eq!(denied.code(), tonic::Code::PermissionDenied);
+
+        // An ingest key gets past authorization; generation is not configured
+        let unconfigured = client.generate(with_key(Some("loader-secret"), generate())).await.unwrap_err();
+        assert_eq!(unconfigured.code(), tonic::Code::FailedPrecondition);
+    }
+}
@@ -0,0 +1,408 @@
+use axum::extract::{FromRequest, Json, Multipart, Path, Request, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
+use tracing::warn;
+
+use crate::core::{ExtractionResult, RdfTriple};
+use crate::knowledge_graph::{DocumentStatus, JobDocument, JobManifest, SchemaDrift, SharedKnowledgeGraph};
+use super::{content_type, negotiate_rdf_format, ApiError, Dataset, ServerState};
+
+type HandlerResult = std::result::Result<Response, ApiError>;
+
+/// Largest request accepted by `POST /jobs`
+pub const MAX_UPLOAD_BYTES: usize = 256 * 1024 * 1024;
+
+/// How long a completed job stays queryable before it is forgotten; its
+/// triples stay in the run graph
+pub const FINISHED_JOB_TTL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Queued,
+    Running,
+    Completed,
+}
+
+/// An extraction job submitted over HTTP or gRPC
+pub struct IngestJob {
+    pub(super) dataset: SharedKnowledgeGraph,
+    pub(super) state: JobState,
+    pub(super) manifest: JobManifest,
+    persist: bool,
+    /// When the job completed
+    finished_at: Option<Instant>,
+}
+
+/// Broadcast when a job changes state or finishes a document
+#[derive(Debug, Clone)]
+pub struct JobUpdate {
+    pub job_id: String,
+    /// Index of the document that was just processed
+    pub document: Option<usize>,
+}
+
+/// JSON body of `POST /jobs`
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SubmitJob {
+    /// http(s) URLs of the documents to extract
+    urls: Vec<String>,
+}
+
+/// Multipart body of `POST /jobs`
+#[derive(ToSchema)]
+#[allow(dead_code)]
+pub struct JobUpload {
+    /// Documents to extract; the file name selects the document handler
+    #[schema(value_type = Vec<String>, format = Binary)]
+    file: Vec<Vec<u8>>,
+    /// http(s) URLs of further documents
+    url: Vec<String>,
+}
+
+/// Response of `POST /jobs`
+#[derive(Debug, Serialize, ToSchema)]
+pub struct JobSubmitted {
+    job_id: String,
+    state: JobState,
+    status_url: String,
+    triples_url: String,
+}
+
+/// Response of `GET /jobs/{id}`
+#[derive(Debug, Serialize, ToSchema)]
+pub struct JobStatus {
+    job_id: String,
+    state: JobState,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    completed: usize,
+    failed: usize,
+    pending: usize,
+    documents: Vec<JobDocument>,
+    triples_url: String,
+}
+
+/// A document to extract: the name reported in job status and the path or
+/// URL it is read from
+pub(super) struct JobInput {
+    name: String,
+    source: String,
+}
+
+/// `POST /jobs` with `multipart/form-data` (file parts and `url` fields) or
+/// JSON `{"urls": [...]}`; responds `202 Accepted` with the job id
+#[utoipa::path(
+    post, path = "/jobs", tag = "jobs",
+    request_body(content(
+        (JobUpload = "multipart/form-data"),
+        (SubmitJob = "application/json"),
+    )),
+    responses(
+        (status = 202, description = "Job queued", body = JobSubmitted,
+            headers(("Location" = String, description = "Job status URL"))),
+        (status = 400, description = "No documents, a URL that is not http(s), or one whose host resolves to an internal address"),
+        (status = 413, description = "The uploaded documents exceed 256 MB"),
+        (status = 503, description = "Extraction is not configured"),
+    )
+)]
+pub async fn submit_job(State(state): State<ServerState>, Dataset(knowledge_graph): Dataset, request: Request) -> HandlerResult {
+    if state.extractor.is_none() {
+        return Err(ApiError::new(StatusCode::SERVICE_UNAVAILABLE, "Extraction is not configured; start serve with a config file"));
+    }
+
+    let upload_dir = std::env::temp_dir().join(format!("rdf-ingest-{}", uuid::Uuid::new_v4()));
+    let inputs = match content_type(request.headers()).as_str() {
+        "multipart/form-data" => {
+            let multipart = Multipart::from_request(request, &state).await
+                .map_err(|e| ApiError::bad_request(e.body_text()))?;
+            let inputs = read_multipart(&state, multipart, &upload_dir).await;
+            if inputs.is_err() {
+                tokio::fs::remove_dir_all(&upload_dir).await.ok();
+            }
+            inputs?
+        }
+        "application/json" => {
+            let Json(body) = Json::<SubmitJob>::from_request(request, &state).await
+                .map_err(|e| ApiError::bad_request(e.body_text()))?;
+            let mut inputs = Vec::with_capacity(body.urls.len());
+            for url in body.urls {
+                inputs.push(url_input(&state, url).await?);
+            }
+            inputs
+        }
+        other => {
+            return Err(ApiError::new(StatusCode::UNSUPPORTED_MEDIA_TYPE, format!("Unsupported content type: {}", other)));
+        }
+    };
+
+    let job_id = start_job(&state, knowledge_graph, inputs, upload_dir).await?;
+    let body = JobSubmitted {
+        job_id: job_id.clone(),
+        state: JobState::Queued,
+        status_url: format!("/jobs/{}", job_id),
+        triples_url: format!("/jobs/{}/triples", job_id),
+    };
+    Ok((StatusCode::ACCEPTED, [(header::LOCATION, format!("/jobs/{}", job_id))], Json(body)).into_response())
+}
+
+/// Queue a job over `inputs` for `knowledge_graph` and start its worker;
+/// `upload_dir` is removed once the job finishes
+pub(super) async fn start_job(
+    state: &ServerState,
+    knowledge_graph: SharedKnowledgeGraph,
+    inputs: Vec<JobInput>,
+    upload_dir: PathBuf,
+) -> std::result::Result<String, ApiError> {
+    let extractor = state.extractor.clone()
+        .ok_or_else(|| ApiError::new(StatusCode::SERVICE_UNAVAILABLE, "Extraction is not configured; start serve with a config file"))?;
+    if inputs.is_empty() {
+        return Err(ApiError::bad_request("No documents or URLs submitted"));
+    }
+
+    let (manifest, persist) = {
+        let knowledge_graph = knowledge_graph.read().await;
+        let names: Vec<String> = inputs.iter().map(|i| i.name.clone()).collect();
+        let persist = knowledge_graph.storage_path() != ":memory:";
+        (JobManifest::create(knowledge_graph.storage_path(), &names, Some("run".to_string())), persist)
+    };
+    let job_id = manifest.job_id.clone();
+
+    let mut job = IngestJob { dataset: knowledge_graph, state: JobState::Queued, manifest, persist, finished_at: None };
+    if job.persist {
+        job.manifest.save()?;
+    }
+    {
+        let mut jobs = state.jobs.lock().unwrap();
+        evict_finished(&mut jobs, FINISHED_JOB_TTL);
+        jobs.insert(job_id.clone(), job);
+    }
+
+    let worker_state = state.clone();
+    let worker_id = job_id.clone();
+    tokio::spawn(async move {
+        run_job(worker_state, extractor, worker_id, inputs).await;
+        tokio::fs::remove_dir_all(&upload_dir).await.ok();
+    });
+
+    Ok(job_id)
+}
+
+/// Forget jobs that completed more than `ttl` ago
+pub(super) fn evict_finished(jobs: &mut HashMap<String, IngestJob>, ttl: Duration) {
+    jobs.retain(|_, job| job.finished_at.is_none_or(|finished| finished.elapsed() < ttl));
+}
+
+/// Only remote URLs are accepted, so clients cannot make the server read its
+/// own files. Hosts resolving to loopback, private or link-local addresses
+/// are refused too, unless listed in `server.allowed_hosts`; the job's
+/// fetch checks each redirect again
+pub(super) async fn url_input(state: &ServerState, url: String) -> std::result::Result<JobInput, ApiError> {
+    let url = url.trim().to_string();
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return Err(ApiError::bad_request(format!("Not an http(s) URL: {}", url)));
+    }
+    let parsed = url::Url::parse(&url).map_err(|e| ApiError::bad_request(format!("Invalid URL {}: {}", url, e)))?;
+    state.url_guard.check(&parsed).await.map_err(|e| ApiError::bad_request(e.to_string()))?;
+    Ok(JobInput { name: url.clone(), source: url })
+}
+
+async fn read_multipart(state: &ServerState, mut multipart: Multipart, upload_dir: &std::path::Path) -> std::result::Result<Vec<JobInput>, ApiError> {
+    let mut inputs = Vec::new();
+    let mut received = 0;
+
+    while let Some(mut field) = multipart.next_field().await.map_err(|e| ApiError::bad_request(e.body_text()))? {
+        if let Some(file_name) = field.file_name().map(str::to_string) {
+            // Written as it arrives, so uploads are never held in memory whole
+            let (name, path) = upload_path(upload_dir, inputs.len(), &file_name).await?;
+            let mut file = tokio::fs::File::create(&path).await.map_err(internal_error)?;
+            while let Some(chunk) = field.chunk().await.map_err(|e| ApiError::bad_request(e.body_text()))? {
+                received += chunk.len();
+                if received > MAX_UPLOAD_BYTES {
+                    return Err(ApiError::new(
+                        StatusCode::PAYLOAD_TOO_LARGE,
+                        format!("Uploads are limited to {} MB per request", MAX_UPLOAD_BYTES / (1024 * 1024)),
+                    ));
+                }
+                file.write_all(&chunk).await.map_err(internal_error)?;
+            }
+            file.flush().await.map_err(internal_error)?;
+            inputs.push(JobInput { name, source: path.to_string_lossy().to_string() });
+        } else if field.name() == Some("url") {
+            let url = field.text().await.map_err(|e| ApiError::bad_request(e.body_text()))?;
+            inputs.push(url_input(state, url).await?);
+        }
+    }
+
+    Ok(inputs)
+}
+
+/// Store an uploaded document under `upload_dir`
+pub(super) async fn save_upload(upload_dir: &std::path::Path, index: usize, file_name: &str, bytes: &[u8]) -> std::result::Result<JobInput, ApiError> {
+    let (name, path) = upload_path(upload_dir, index, file_name).await?;
+    tokio::fs::write(&path, bytes).await.map_err(internal_error)?;
+    Ok(JobInput { name, source: path.to_string_lossy().to_string() })
+}
+
+/// Sanitized name and path of the `index`th upload under `upload_dir`. One
+/// directory per upload keeps the original file name (and so the extension
+/// used to detect the document type)
+async fn upload_path(upload_dir: &std::path::Path, index: usize, file_name: &str) -> std::result::Result<(String, PathBuf), ApiError> {
+    let file_name = sanitize_file_name(file_name);
+    let dir: PathBuf = upload_dir.join(index.to_string());
+    tokio::fs::create_dir_all(&dir).await.map_err(internal_error)?;
+    let path = dir.join(&file_name);
+    Ok((file_name, path))
+}
+
+fn internal_error(e: std::io::Error) -> ApiError {
+    ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+}
+
+/// File name without directory components
+fn sanitize_file_name(name: &str) -> String {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or_default().trim();
+    if name.is_empty() || name == "." || name == ".." {
+        "upload".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// Extract the job's documents one at a time (jobs run one after another),
+/// storing each document's triples in the job's run graph as it completes
+async fn run_job(state: ServerState, extractor: Arc<crate::core::RdfExtractor>, job_id: String, inputs: Vec<JobInput>) {
+    let _permit = state.job_queue.acquire().await;
+
+    let (dataset, run_graph) = {
+        let mut jobs = state.jobs.lock().unwrap();
+        let Some(job) = jobs.get_mut(&job_id) else { return };
+        job.state = JobState::Running;
+        (job.dataset.clone(), job.manifest.run_graph.clone())
+    };
+    notify(&state, &job_id, None);
+
+    for (index, input) in inputs.into_iter().enumerate() {
+        let mut result = match extractor.extract_within_limits(&input.source).await {
+            Ok(result) => result,
+            Err(e) => ExtractionResult::new(input.source.clone(), extractor.config().name.clone(), 0.0)
+                .with_error(e.to_string()),
+        }
+        .with_source_name(&input.name);
+
+        for triple in &mut result.triples {
+            if triple.graph.is_none() {
+                triple.graph = Some(run_graph.clone());
+            }
+        }
+
+        if let Err(e) = dataset.write().await.add_triples(&result.triples) {
+            result = result.with_error(format!("Failed to store triples: {}", e));
+        }
+        if !result.unknown_predicates.is_empty() {
+            let storage_path = dataset.read().await.storage_path().to_string();
+            if storage_path != ":memory:" {
+                if let Err(e) = record_drift(&storage_path, &result.unknown_predicates) {
+                    warn!("Failed to record schema drift for {}: {}", storage_path, e);
+                }
+            }
+        }
+
+        if let Some(job) = state.jobs.lock().unwrap().get_mut(&job_id) {
+            job.manifest.record(&result);
+            if job.persist {
+                if let Err(e) = job.manifest.save() {
+                    warn!("Failed to save job manifest {}: {}", job_id, e);
+                }
+            }
+        }
+        notify(&state, &job_id, Some(index));
+    }
+
+    if let Some(job) = state.jobs.lock().unwrap().get_mut(&job_id) {
+        job.state = JobState::Completed;
+        job.finished_at = Some(Instant::now());
+    }
+    notify(&state, &job_id, None);
+}
+
+fn record_drift(kg_path: &str, triples: &[RdfTriple]) -> crate::error::Result<()> {
+    let mut drift = SchemaDrift::open_for(kg_path)?;
+    drift.record(triples);
+    drift.save()
+}
+
+fn notify(state: &ServerState, job_id: &str, document: Option<usize>) {
+    // Nobody may be watching, so a send error is expected
+    state.job_updates.send(JobUpdate { job_id: job_id.to_string(), document }).ok();
+}
+
+/// The job with `job_id` if it belongs to the caller's dataset
+pub(super) fn find_job<T>(state: &ServerState, dataset: &SharedKnowledgeGraph, job_id: &str, read: impl FnOnce(&IngestJob) -> T) -> std::result::Result<T, ApiError> {
+    let jobs = state.jobs.lock().unwrap();
+    jobs.get(job_id)
+        .filter(|job| job.dataset.same_graph(dataset))
+        .map(read)
+        .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, format!("No job {}", job_id)))
+}
+
+/// `GET /jobs/{id}`: job state and per-document progress
+#[utoipa::path(
+    get, path = "/jobs/{id}", tag = "jobs",
+    params(("id" = String, Path, description = "Job id")),
+    responses(
+        (status = 200, description = "Job state and per-document progress", body = JobStatus),
+        (status = 404, description = "No such job in the caller's dataset"),
+    )
+)]
+pub async fn job_status(State(state): State<ServerState>, Dataset(knowledge_graph): Dataset, Path(job_id): Path<String>) -> HandlerResult {
+    let body = find_job(&state, &knowledge_graph, &job_id, |job| JobStatus {
+        job_id: job.manifest.job_id.clone(),
+        state: job.state,
+        created_at: job.manifest.created_at,
+        updated_at: job.manifest.updated_at,
+        completed: job.manifest.count(DocumentStatus::Completed),
+        failed: job.manifest.count(DocumentStatus::Failed),
+        pending: job.manifest.count(DocumentStatus::Pending),
+        documents: job.manifest.documents.clone(),
+        triples_url: format!("/jobs/{}/triples", job_id),
+    })?;
+    Ok(Json(body).into_response())
+}
+
+/// `GET /jobs/{id}/triples`: triples extracted so far, in the negotiated
+/// RDF format
+#[utoipa::path(
+    get, path = "/jobs/{id}/triples", tag = "jobs",
+    params(("id" = String, Path, description = "Job id")),
+    responses(
+        (status = 200, description = "Triples extracted so far", content(
+            (String = "text/turtle"), (String = "application/n-triples"),
+            (String = "application/ld+json"), (String = "application/rdf+xml"),
+        )),
+        (status = 404, description = "No such job in the caller's dataset"),
+    )
+)]
+pub async fn job_triples(
+    State(state): State<ServerState>,
+    Dataset(knowledge_graph): Dataset,
+    Path(job_id): Path<String>,
+    headers: HeaderMap,
+) -> HandlerResult {
+    let run_graph = find_job(&state, &knowledge_graph, &job_id, |job| job.manifest.run_graph.clone())?;
+
+    let knowledge_graph = knowledge_graph.read().await;
+    let triples = knowledge_graph.graph_triples(Some(&run_graph));
+    let (format, media_type) = negotiate_rdf_format(&headers);
+    let schema = knowledge_graph.schema();
+    let body = knowledge_graph.serializer().serialize(&triples, &format, &schema.namespace, &schema.prefix)?;
+    Ok(([(header::CONTENT_TYPE, media_type)], body).into_response())
+}
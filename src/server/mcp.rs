@@ -0,0 +1,308 @@
+//! Model Context Protocol server, so agents can use the knowledge graph as
+//! a tool backend. Speaks JSON-RPC 2.0 over stdin/stdout, one message per line.
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tracing::{info, warn};
+
+use crate::config::OutputFormat;
+use crate::core::RdfTriple;
+use crate::error::{ErrorContext, Result};
+use super::{generate_document, results_json, ServerState};
+
+/// Protocol revisions the server speaks, newest first
+const PROTOCOL_VERSIONS: [&str; 3] = ["2025-06-18", "2025-03-26", "2024-11-05"];
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+#[derive(Debug, Deserialize)]
+struct QueryArguments {
+    query: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct EntityArguments {
+    uri: String,
+    #[serde(default)]
+    format: Option<OutputFormat>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AddTriplesArguments {
+    triples: Vec<RdfTriple>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GenerateArguments {
+    template_id: String,
+    #[serde(default)]
+    context: Option<HashMap<String, Value>>,
+}
+
+/// Answers MCP requests against the server's knowledge graph: the
+/// `query_knowledge_graph`, `get_entity`, `add_triples` and
+/// `generate_document` tools
+pub struct McpServer {
+    state: ServerState,
+}
+
+impl McpServer {
+    pub fn new(state: ServerState) -> Self {
+        Self { state }
+    }
+
+    /// Response to a JSON-RPC message; `None` for notifications, which get no reply
+    pub async fn handle(&self, message: Value) -> Option<Value> {
+        let id = message.get("id").cloned()?;
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+        let result = match message.get("method").and_then(Value::as_str) {
+            Some("initialize") => Ok(self.initialize(&params).await),
+            Some("ping") => Ok(json!({})),
+            Some("tools/list") => Ok(json!({ "tools": tools() })),
+            Some("tools/call") => self.call_tool(params).await,
+            Some(method) => Err((METHOD_NOT_FOUND, format!("Method not found: {}", method))),
+            None => Err((INVALID_REQUEST, "Request has no method".to_string())),
+        };
+
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => error_response(id, code, &message),
+        })
+    }
+
+    async fn initialize(&self, params: &Value) -> Value {
+        let requested = params.get("protocolVersion").and_then(Value::as_str);
+        let version = PROTOCOL_VERSIONS.iter().copied()
+            .find(|version| Some(*version) == requested)
+            .unwrap_or(PROTOCOL_VERSIONS[0]);
+
+        // Tell the agent how entities are named, so its SPARQL matches the graph
+        let schema = self.state.knowledge_graph.read().await.schema().clone();
+        let mut instructions = format!(
+            "Knowledge graph of extracted RDF triples. Entities are IRIs under {}, predicates under {} (prefix {}:). Query it with SPARQL.",
+            schema.base_uri, schema.namespace, schema.prefix
+        );
+        if self.state.read_only {
+            instructions.push_str(" The graph is read-only.");
+        }
+
+        json!({
+            "protocolVersion": version,
+            "capabilities": { "tools": { "listChanged": false } },
+            "serverInfo": { "name": env!("CARGO_PKG_NAME"), "version": env!("CARGO_PKG_VERSION") },
+            "instructions": instructions,
+        })
+    }
+
+    /// `tools/call`: failures of the tool itself are reported in the result
+    /// with `isError`, so the agent can see them and retry
+    async fn call_tool(&self, params: Value) -> std::result::Result<Value, (i64, String)> {
+        let name = params.get("name").and_then(Value::as_str)
+            .ok_or((INVALID_PARAMS, "Missing tool name".to_string()))?;
+        let arguments = params.get("arguments").cloned().unwrap_or_else(|| json!({}));
+
+        let output = match name {
+            "query_knowledge_graph" => self.query(parse_arguments(arguments)?).await,
+            "get_entity" => self.get_entity(parse_arguments(arguments)?).await,
+            "add_triples" => self.add_triples(parse_arguments(arguments)?).await,
+            "generate_document" => self.generate(parse_arguments(arguments)?).await,
+            _ => return Err((INVALID_PARAMS, format!("Unknown tool: {}", name))),
+        };
+
+        let (text, is_error) = match output {
+            Ok(text) => (text, false),
+            Err(message) => (message, true),
+        };
+        Ok(json!({ "content": [{ "type": "text", "text": text }], "isError": is_error }))
+    }
+
+    async fn query(&self, arguments: QueryArguments) -> std::result::Result<String, String> {
+        let results = self.state.knowledge_graph.execute_sparql(arguments.query).await
+            .map_err(|e| e.to_string())?;
+        serde_json::to_string_pretty(&results_json(&results)).map_err(|e| e.to_string())
+    }
+
+    async fn get_entity(&self, arguments: EntityArguments) -> std::result::Result<String, String> {
+        let knowledge_graph = self.state.knowledge_graph.read().await;
+        let triples: Vec<RdfTriple> = knowledge_graph.triples_about(&arguments.uri).into_iter().cloned().collect();
+        if triples.is_empty() {
+            return Err(format!("Unknown entity: {}", arguments.uri));
+        }
+
+        let schema = knowledge_graph.schema();
+        let format = arguments.format.unwrap_or(OutputFormat::Turtle);
+        knowledge_graph.serializer().serialize(&triples, &format, &schema.namespace, &schema.prefix)
+            .map_err(|e| e.to_string())
+    }
+
+    async fn add_triples(&self, arguments: AddTriplesArguments) -> std::result::Result<String, String> {
+        if self.state.read_only {
+            return Err("The knowledge graph is read-only".to_string());
+        }
+        let added = self.state.knowledge_graph.add_triples(&arguments.triples).await
+            .map_err(|e| e.to_string())?;
+        Ok(format!("Added {} of {} triples ({} already present)", added, arguments.triples.len(), arguments.triples.len() - added))
+    }
+
+    async fn generate(&self, arguments: GenerateArguments) -> std::result::Result<String, String> {
+        let document = generate_document(&self.state, &self.state.knowledge_graph, arguments.template_id, arguments.context).await
+            .map_err(|e| e.message)?;
+        Ok(document.generated_content)
+    }
+}
+
+fn parse_arguments<T: serde::de::DeserializeOwned>(arguments: Value) -> std::result::Result<T, (i64, String)> {
+    serde_json::from_value(arguments).map_err(|e| (INVALID_PARAMS, format!("Invalid arguments: {}", e)))
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+/// Tool definitions returned by `tools/list`
+fn tools() -> Value {
+    let triple = json!({
+        "type": "object",
+        "properties": {
+            "subject": { "type": "string", "description": "Subject IRI" },
+            "predicate": { "type": "string", "description": "Predicate IRI" },
+            "object": { "type": "string", "description": "Object: an http(s) IRI or a literal value" },
+            "graph": { "type": "string", "description": "Named graph; omit for the default graph" },
+        },
+        "required": ["subject", "predicate", "object"],
+    });
+
+    json!([
+        {
+            "name": "query_knowledge_graph",
+            "description": "Run a SPARQL SELECT or ASK query against the knowledge graph and return SPARQL 1.1 JSON results.",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "query": { "type": "string", "description": "SPARQL query" } },
+                "required": ["query"],
+            },
+            "annotations": { "readOnlyHint": true },
+        },
+        {
+            "name": "get_entity",
+            "description": "Describe an entity: every triple with it as subject or object.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "uri": { "type": "string", "description": "Entity IRI" },
+                    "format": {
+                        "type": "string",
+                        "enum": ["turtle", "n-triples", "json-ld", "json"],
+                        "description": "Serialization of the triples (default turtle)",
+                    },
+                },
+                "required": ["uri"],
+            },
+            "annotations": { "readOnlyHint": true },
+        },
+        {
+            "name": "add_triples",
+            "description": "Add triples to the knowledge graph. Triples already present are skipped.",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "triples": { "type": "array", "items": triple } },
+                "required": ["triples"],
+            },
+            "annotations": { "readOnlyHint": false, "destructiveHint": false, "idempotentHint": true },
+        },
+        {
+            "name": "generate_document",
+            "description": "Render a document template with data queried from the knowledge graph and return the generated text.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "template_id": { "type": "string", "description": "Template id" },
+                    "context": { "type": "object", "description": "Values for the template's variables" },
+                },
+                "required": ["template_id"],
+            },
+            "annotations": { "readOnlyHint": true },
+        },
+    ])
+}
+
+/// Serve MCP on stdin/stdout until stdin is closed
+pub async fn serve_stdio(state: ServerState) -> Result<()> {
+    let server = McpServer::new(state);
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    let mut stdout = tokio::io::stdout();
+    info!("Serving MCP on stdio");
+
+    while let Some(line) = lines.next_line().await.server_context(|| "Failed to read from stdin")? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Value>(&line) {
+            Ok(message) => server.handle(message).await,
+            Err(e) => {
+                warn!("Unparseable MCP message: {}", e);
+                Some(error_response(Value::Null, PARSE_ERROR, &format!("Parse error: {}", e)))
+            }
+        };
+
+        if let Some(response) = response {
+            let mut bytes = response.to_string().into_bytes();
+            bytes.push(b'\n');
+            stdout.write_all(&bytes).await
+                .and(stdout.flush().await)
+                .server_context(|| "Failed to write to stdout")?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RdfSchema;
+    use crate::knowledge_graph::KnowledgeGraph;
+
+    #[tokio::test]
+    async fn test_mcp_tools() {
+        let server = McpServer::new(ServerState::new(KnowledgeGraph::in_memory(RdfSchema::default()).unwrap()));
+        let request = |id: u64, method: &str, params: Value| json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        let call = |id: u64, name: &str, arguments: Value| request(id, "tools/call", json!({ "name": name, "arguments": arguments }));
+        let text = |response: &Value| response["result"]["content"][0]["text"].as_str().unwrap().to_string();
+
+        let init = server.handle(request(1, "initialize", json!({ "protocolVersion": "2025-03-26" }))).await.unwrap();
+        assert_eq!(init["result"]["protocolVersion"], "2025-03-26");
+        assert!(server.handle(json!({ "jsonrpc": "2.0", "method": "notifications/initialized" })).await.is_none());
+        let list = server.handle(request(2, "tools/list", json!({}))).await.unwrap();
+        let names: Vec<&str> = list["result"]["tools"].as_array().unwrap().iter().map(|t| t["name"].as_str().unwrap()).collect();
+        assert_eq!(names, ["query_knowledge_graph", "get_entity", "add_triples", "generate_document"]);
+
+        let added = server.handle(call(3, "add_triples", json!({ "triples": [
+            { "subject": "http://ex.org/acme", "predicate": "http://ex.org/basedIn", "object": "http://ex.org/berlin" },
+            { "subject": "http://ex.org/acme", "predicate": "http://ex.org/name", "object": "Acme" },
+        ] }))).await.unwrap();
+        assert_eq!(text(&added), "Added 2 of 2 triples (0 already present)");
+
+        let query = server.handle(call(4, "query_knowledge_graph", json!({ "query": "SELECT ?n WHERE { ?s <http://ex.org/name> ?n }" }))).await.unwrap();
+        let results: Value = serde_json::from_str(&text(&query)).unwrap();
+        assert_eq!(results["results"]["bindings"][0]["n"]["value"], "Acme");
+
+        let entity = server.handle(call(5, "get_entity", json!({ "uri": "http://ex.org/berlin", "format": "n-triples" }))).await.unwrap();
+        assert!(text(&entity).contains("<http://ex.org/acme> <http://ex.org/basedIn> <http://ex.org/berlin>"));
+
+        // Tool failures are results the agent sees; protocol errors are JSON-RPC errors
+        let unknown = server.handle(call(6, "get_entity", json!({ "uri": "http://ex.org/nobody" }))).await.unwrap();
+        assert_eq!(unknown["result"]["isError"], true);
+        let generate = server.handle(call(7, "generate_document", json!({ "template_id": "report" }))).await.unwrap();
+        assert!(text(&generate).contains("not configured"));
+        let invalid = server.handle(call(8, "add_triples", json!({}))).await.unwrap();
+        assert_eq!(invalid["error"]["code"], INVALID_PARAMS);
+        let missing = server.handle(request(9, "resources/list", json!({}))).await.unwrap();
+        assert_eq!(missing["error"]["code"], METHOD_NOT_FOUND);
+    }
+}
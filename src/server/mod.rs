@@ -0,0 +1,716 @@
+use axum::body::Bytes;
+use axum::extract::{DefaultBodyLimit, FromRequestParts, RawQuery};
+use axum::http::request::Parts;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::middleware;
+use axum::routing::{get, post};
+use axum::Router;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{broadcast, Semaphore};
+use tracing::{info, warn};
+
+use crate::config::{OutputFormat, ServerSettings};
+use crate::core::{RdfExtractor, VllmClient};
+use crate::error::{Error, ErrorContext, Result};
+use crate::handlers::AddressGuard;
+use crate::knowledge_graph::{Binding, KnowledgeGraph, KnowledgeGraphConfig, SharedKnowledgeGraph, SimpleSparqlResults, ViewStore};
+use crate::templates::{GeneratedDocument, TemplateGenerationRequest, TemplateManager};
+
+pub mod auth;
+pub mod entities;
+pub mod feedback;
+pub mod graph_store;
+pub mod graphql;
+pub mod grpc;
+pub mod ingest;
+pub mod mcp;
+pub mod openapi;
+
+/// State shared by the request handlers
+#[derive(Clone)]
+pub struct ServerState {
+    knowledge_graph: SharedKnowledgeGraph,
+    datasets: Arc<HashMap<String, SharedKnowledgeGraph>>,
+    api_keys: Arc<auth::ApiKeys>,
+    log_requests: bool,
+    extractor: Option<Arc<RdfExtractor>>,
+    jobs: Arc<Mutex<HashMap<String, ingest::IngestJob>>>,
+    /// Refuses job URLs on internal addresses, except `server.allowed_hosts`
+    url_guard: AddressGuard,
+    job_queue: Arc<Semaphore>,
+    job_updates: broadcast::Sender<ingest::JobUpdate>,
+    template_dir: Option<PathBuf>,
+    /// GraphQL schema generated from the RDF schema, or why it could not be
+    graphql: std::result::Result<async_graphql::dynamic::Schema, String>,
+    /// Refuse every request that would change a graph
+    read_only: bool,
+}
+
+impl ServerState {
+    /// State without authentication or request logging; the server is
+    /// read-only when `knowledge_graph` was opened read-only
+    pub fn new(knowledge_graph: KnowledgeGraph) -> Self {
+        let graphql = graphql::build_schema(knowledge_graph.schema());
+        if let Err(e) = &graphql {
+            warn!("GraphQL endpoint disabled: {}", e);
+        }
+
+        Self {
+            graphql,
+            read_only: knowledge_graph.is_read_only(),
+            knowledge_graph: SharedKnowledgeGraph::new(knowledge_graph),
+            datasets: Arc::new(HashMap::new()),
+            api_keys: Arc::new(auth::ApiKeys::default()),
+            log_requests: false,
+            extractor: None,
+            jobs: Arc::new(Mutex::new(HashMap::new())),
+            url_guard: AddressGuard::default(),
+            job_queue: Arc::new(Semaphore::new(1)),
+            job_updates: broadcast::channel(256).0,
+            template_dir: None,
+        }
+    }
+
+    /// Enable gRPC `Generate` with the templates in `template_dir`
+    pub fn with_templates(mut self, template_dir: impl Into<PathBuf>) -> Self {
+        self.template_dir = Some(template_dir.into());
+        self
+    }
+
+    /// Enable `POST /jobs` document ingestion with this extractor, which
+    /// then only fetches URLs from public addresses
+    pub fn with_extractor(mut self, mut extractor: RdfExtractor) -> Self {
+        extractor.guard_urls(self.url_guard.clone());
+        self.extractor = Some(Arc::new(extractor));
+        self
+    }
+
+    /// Apply API keys, datasets, request logging and the hosts jobs may
+    /// fetch from the `server` settings; datasets share the main knowledge
+    /// graph's schema and literal normalization
+    pub fn with_settings(mut self, settings: &ServerSettings) -> Result<Self> {
+        let (schema, literal_normalization) = {
+            let knowledge_graph = self.knowledge_graph.try_read()
+                .map_err(|_| Error::server("Knowledge graph is in use"))?;
+            (knowledge_graph.schema().clone(), knowledge_graph.literal_normalization().clone())
+        };
+
+        let mut datasets = HashMap::new();
+        for (name, dataset) in &settings.datasets {
+            let config = KnowledgeGraphConfig {
+                storage_path: dataset.kg_path.clone(),
+                read_only: self.read_only,
+                literal_normalization: literal_normalization.clone(),
+                ..Default::default()
+            };
+            let knowledge_graph = KnowledgeGraph::new(config, schema.clone())
+                .server_context(|| format!("Failed to open dataset '{}'", name))?;
+            datasets.insert(name.clone(), SharedKnowledgeGraph::new(knowledge_graph));
+        }
+
+        self.datasets = Arc::new(datasets);
+        self.api_keys = Arc::new(auth::ApiKeys::from_settings(settings)?);
+        self.log_requests = settings.log_requests;
+        self.url_guard = AddressGuard::new(&settings.allowed_hosts);
+        if let Some(extractor) = self.extractor.as_mut().and_then(Arc::get_mut) {
+            extractor.guard_urls(self.url_guard.clone());
+        }
+        Ok(self)
+    }
+
+    fn dataset(&self, name: &str) -> Option<Dataset> {
+        self.datasets.get(name).map(|knowledge_graph| Dataset(knowledge_graph.clone()))
+    }
+
+    /// Whether requests must present an API key
+    pub fn requires_auth(&self) -> bool {
+        !self.api_keys.is_empty()
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+}
+
+/// Knowledge graph a request works on: the dataset its API key is bound
+/// to, or the main knowledge graph
+#[derive(Clone)]
+pub struct Dataset(SharedKnowledgeGraph);
+
+impl FromRequestParts<ServerState> for Dataset {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &ServerState) -> std::result::Result<Self, ApiError> {
+        Ok(parts.extensions.get::<Dataset>()
+            .cloned()
+            .unwrap_or_else(|| Dataset(state.knowledge_graph.clone())))
+    }
+}
+
+/// Routes: the SPARQL protocol endpoint at `/sparql`, the SPARQL 1.1
+/// Graph Store HTTP Protocol at `/graph-store`, document ingestion jobs at
+/// `/jobs`, linked-data entity descriptions at `/entity?uri=` and under
+/// the schema's `base_uri` path, label suggestions at `/autocomplete`,
+/// reviewer corrections at `/feedback`, a GraphQL API generated from the
+/// schema at `/graphql` (GraphiQL on `GET` without a query), and the
+/// OpenAPI document at `/openapi.json` (browsable at `/docs`)
+pub fn router(state: ServerState) -> Router {
+    Router::new()
+        .route("/openapi.json", get(openapi::openapi_json))
+        .route("/docs", get(openapi::docs))
+        .route("/sparql", get(sparql_get).post(sparql_post))
+        .route(
+            "/graph-store",
+            get(graph_store::get_graph)
+                .put(graph_store::put_graph)
+                .post(graph_store::post_graph)
+                .delete(graph_store::delete_graph),
+        )
+        .route(
+            "/jobs",
+            post(ingest::submit_job).layer(DefaultBodyLimit::max(ingest::MAX_UPLOAD_BYTES)),
+        )
+        .route("/jobs/{id}", get(ingest::job_status))
+        .route("/jobs/{id}/triples", get(ingest::job_triples))
+        .route("/entity", get(entities::describe_by_uri))
+        .route("/autocomplete", get(entities::autocomplete))
+        .route("/feedback", get(feedback::list).post(feedback::submit))
+        .route("/graphql", get(graphql::query_or_playground).post(graphql::execute))
+        .fallback(get(entities::dereference))
+        .layer(middleware::from_fn_with_state(state.clone(), auth::authorize))
+        .with_state(state)
+}
+
+/// Serve the knowledge graph over HTTP until the process is stopped
+pub async fn serve(state: ServerState, addr: &str) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind(addr).await
+        .server_context(|| format!("Failed to bind to {}", addr))?;
+    info!("Serving knowledge graph on http://{}", addr);
+
+    axum::serve(listener, router(state)).await
+        .server_context(|| "Server stopped unexpectedly")
+}
+
+/// Render `template_id` from the server's template directory against
+/// `knowledge_graph`, read in place; writers wait until the document is done
+pub(crate) async fn generate_document(
+    state: &ServerState,
+    knowledge_graph: &SharedKnowledgeGraph,
+    template_id: String,
+    context: Option<HashMap<String, Value>>,
+) -> std::result::Result<GeneratedDocument, ApiError> {
+    let template_dir = state.template_dir.clone()
+        .ok_or_else(|| ApiError::new(StatusCode::SERVICE_UNAVAILABLE, "Generation is not configured; start the server with --template-dir"))?;
+    let extractor = state.extractor.clone()
+        .ok_or_else(|| ApiError::new(StatusCode::SERVICE_UNAVAILABLE, "Generation is not configured; start the server with a config file"))?;
+
+    let knowledge_graph = knowledge_graph.read().await;
+    let storage_path = knowledge_graph.storage_path().to_string();
+    let mut manager = TemplateManager::new(&*knowledge_graph, Some(VllmClient::from_settings(&extractor.config().llm_settings)?));
+    if storage_path != ":memory:" {
+        manager = manager.with_views(ViewStore::open_for(&storage_path)?);
+    }
+    manager.load_templates_from_directory(&template_dir.to_string_lossy())?;
+
+    let generation = TemplateGenerationRequest {
+        template_id,
+        context,
+        override_queries: None,
+        output_path: None,
+    };
+    manager.generate_document(&generation).await
+        .map_err(|e| ApiError::bad_request(e.to_string()))
+}
+
+/// Error response with a plain-text message
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self { status, message: message.into() }
+    }
+
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, message)
+    }
+}
+
+/// A query the engine rejects is the client's mistake; every other failure
+/// is the server's
+impl From<Error> for ApiError {
+    fn from(error: Error) -> Self {
+        let status = match error {
+            Error::Query { .. } => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        Self::new(status, error.to_string())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, format!("{}\n", self.message)).into_response()
+    }
+}
+
+/// Whether the `Accept` header lists `media_type`
+pub(crate) fn accepts(headers: &HeaderMap, media_type: &str) -> bool {
+    headers.get_all(header::ACCEPT).iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|range| range.split(';').next().unwrap_or_default().trim().eq_ignore_ascii_case(media_type))
+}
+
+/// Media type of the request body, without parameters
+pub(crate) fn content_type(headers: &HeaderMap) -> String {
+    headers.get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase()
+}
+
+/// RDF serialization chosen from the `Accept` header, Turtle by default
+pub(crate) fn negotiate_rdf_format(headers: &HeaderMap) -> (OutputFormat, &'static str) {
+    if accepts(headers, "application/n-triples") {
+        (OutputFormat::NTriples, "application/n-triples")
+    } else if accepts(headers, "application/ld+json") {
+        (OutputFormat::JsonLd, "application/ld+json")
+    } else if accepts(headers, "application/rdf+xml") {
+        (OutputFormat::RdfXml, "application/rdf+xml")
+    } else {
+        (OutputFormat::Turtle, "text/turtle")
+    }
+}
+
+fn form_value(encoded: &[u8], key: &str) -> Option<String> {
+    url::form_urlencoded::parse(encoded)
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v.into_owned())
+}
+
+/// SPARQL query via `?query=`
+#[utoipa::path(
+    get, path = "/sparql", tag = "sparql",
+    params(("query" = String, Query, description = "SPARQL query")),
+    responses(
+        (status = 200, description = "Query results", content(
+            (Object = "application/sparql-results+json"), (String = "text/csv"),
+        )),
+        (status = 400, description = "Missing or invalid query"),
+        (status = 500, description = "The query could not be run"),
+    )
+)]
+async fn sparql_get(
+    Dataset(knowledge_graph): Dataset,
+    RawQuery(params): RawQuery,
+    headers: HeaderMap,
+) -> std::result::Result<Response, ApiError> {
+    let query = params.as_deref()
+        .and_then(|params| form_value(params.as_bytes(), "query"))
+        .ok_or_else(|| ApiError::bad_request("Missing 'query' parameter"))?;
+    run_query(knowledge_graph, query, &headers).await
+}
+
+/// SPARQL query as the body or a form field
+#[utoipa::path(
+    post, path = "/sparql", tag = "sparql",
+    request_body(content(
+        (String = "application/sparql-query"),
+        (String = "application/x-www-form-urlencoded"),
+    )),
+    responses(
+        (status = 200, description = "Query results", content(
+            (Object = "application/sparql-results+json"), (String = "text/csv"),
+        )),
+        (status = 400, description = "Missing or invalid query"),
+        (status = 415, description = "Unsupported content type"),
+        (status = 500, description = "The query could not be run"),
+    )
+)]
+async fn sparql_post(
+    Dataset(knowledge_graph): Dataset,
+    headers: HeaderMap,
+    body: Bytes,
+) -> std::result::Result<Response, ApiError> {
+    let query = match content_type(&headers).as_str() {
+        "application/sparql-query" => String::from_utf8(body.to_vec())
+            .map_err(|_| ApiError::bad_request("Query is not valid UTF-8"))?,
+        "application/x-www-form-urlencoded" => form_value(&body, "query")
+            .ok_or_else(|| ApiError::bad_request("Missing 'query' parameter"))?,
+        other => {
+            return Err(ApiError::new(StatusCode::UNSUPPORTED_MEDIA_TYPE, format!("Unsupported content type: {}", other)));
+        }
+    };
+    run_query(knowledge_graph, query, &headers).await
+}
+
+async fn run_query(
+    knowledge_graph: SharedKnowledgeGraph,
+    query: String,
+    headers: &HeaderMap,
+) -> std::result::Result<Response, ApiError> {
+    let knowledge_graph = knowledge_graph.read_owned().await;
+    let results = tokio::task::spawn_blocking(move || knowledge_graph.execute_sparql(&query)).await
+        .map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))??;
+
+    if accepts(headers, "text/csv") {
+        Ok(([(header::CONTENT_TYPE, "text/csv")], results_csv(&results)).into_response())
+    } else {
+        let body = results_json(&results).to_string();
+        Ok(([(header::CONTENT_TYPE, "application/sparql-results+json")], body).into_response())
+    }
+}
+
+/// SPARQL 1.1 Query Results JSON
+fn results_json(results: &SimpleSparqlResults) -> Value {
+    match results {
+        SimpleSparqlResults::Solutions { variables, rows } => {
+            let bindings: Vec<Value> = rows.iter()
+                .map(|row| {
+                    let terms: Map<String, Value> = row.iter()
+                        .map(|(var, binding)| (var.clone(), binding_json(binding)))
+                        .collect();
+                    Value::Object(terms)
+                })
+                .collect();
+            json!({
+                "head": { "vars": SimpleSparqlResults::columns(variables, rows) },
+                "results": { "bindings": bindings },
+            })
+        }
+        SimpleSparqlResults::Boolean(result) => json!({ "head": {}, "boolean": result }),
+    }
+}
+
+fn binding_json(binding: &Binding) -> Value {
+    match binding {
+        Binding::Iri { value } => json!({ "type": "uri", "value": value }),
+        Binding::BlankNode { value } => json!({ "type": "bnode", "value": value.trim_start_matches("_:") }),
+        Binding::Literal { value, datatype, lang } => {
+            let mut term = json!({ "type": "literal", "value": value });
+            if let Some(lang) = lang {
+                term["xml:lang"] = json!(lang);
+            } else if let Some(datatype) = datatype {
+                term["datatype"] = json!(datatype);
+            }
+            term
+        }
+    }
+}
+
+/// SPARQL 1.1 Query Results CSV
+fn results_csv(results: &SimpleSparqlResults) -> String {
+    let escape = |value: &str| {
+        if value.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value.to_string()
+        }
+    };
+
+    match results {
+        SimpleSparqlResults::Solutions { variables, rows } => {
+            let columns = SimpleSparqlResults::columns(variables, rows);
+            let mut output = format!("{}\r\n", columns.join(","));
+            for row in rows {
+                let values: Vec<String> = columns.iter()
+                    .map(|column| row.get(column).map(|b| escape(b.value())).unwrap_or_default())
+                    .collect();
+                output.push_str(&format!("{}\r\n", values.join(",")));
+            }
+            output
+        }
+        SimpleSparqlResults::Boolean(result) => format!("_askResult\r\n{}\r\n", result),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RdfSchema;
+
+    async fn spawn() -> String {
+        spawn_with(&ServerSettings::default()).await
+    }
+
+    async fn spawn_with(settings: &ServerSettings) -> String {
+        let kg = KnowledgeGraph::in_memory(RdfSchema::default()).unwrap();
+        let state = ServerState::new(kg).with_settings(settings).unwrap();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router(state)).await.unwrap() });
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn test_graph_store_and_sparql() {
+        let base = spawn().await;
+        let client = reqwest::Client::new();
+        let store = format!("{}/graph-store", base);
+        let graph = [("graph", "http://ex.org/g1")];
+
+        let put = client.put(&store).query(&graph)
+            .header("Content-Type", "text/turtle")
+            .body("<http://ex.org/acme> <http://ex.org/hasName> \"Acme\" .")
+            .send().await.unwrap();
+        assert_eq!(put.status(), StatusCode::CREATED);
+
+        let post = client.post(&store).query(&graph)
+            .header("Content-Type", "application/n-triples")
+            .body("<http://ex.org/acme> <http://ex.org/basedIn> <http://ex.org/paris> .\n")
+            .send().await.unwrap();
+        assert_eq!(post.status(), StatusCode::NO_CONTENT);
+
+        let get = client.get(&store).query(&graph).header("Accept", "application/n-triples").send().await.unwrap();
+        assert_eq!(get.headers()["content-type"], "application/n-triples");
+        assert_eq!(get.text().await.unwrap().lines().count(), 2);
+
+        let results: Value = client.get(format!("{}/sparql", base))
+            .query(&[("query", "SELECT ?name WHERE { GRAPH <http://ex.org/g1> { ?c <http://ex.org/hasName> ?name } }")])
+            .send().await.unwrap()
+            .json().await.unwrap();
+        assert_eq!(results["head"]["vars"], json!(["name"]));
+        assert_eq!(results["results"]["bindings"][0]["name"]["value"], "Acme");
+        let invalid = client.get(format!("{}/sparql", base)).query(&[("query", "SELECT ?name WHERE {")]).send().await.unwrap();
+        assert_eq!(invalid.status(), StatusCode::BAD_REQUEST);
+        let unsupported = client.get(format!("{}/sparql", base)).query(&[("query", "DESCRIBE <http://ex.org/acme>")]).send().await.unwrap();
+        assert_eq!(unsupported.status(), StatusCode::BAD_REQUEST);
+
+        let graphql: Value = client.post(format!("{}/graphql", base))
+            .json(&json!({ "query": "query($uri: String!) { entity(uri: $uri) { uri label } }", "variables": { "uri": "http://ex.org/acme" } }))
+            .send().await.unwrap()
+            .json().await.unwrap();
+        assert_eq!(graphql["data"]["entity"], json!({ "uri": "http://ex.org/acme", "label": "Acme" }));
+        let playground = client.get(format!("{}/graphql", base)).send().await.unwrap();
+        assert!(playground.text().await.unwrap().contains("graphiql"));
+
+        let delete = client.delete(&store).query(&graph).send().await.unwrap();
+        assert_eq!(delete.status(), StatusCode::NO_CONTENT);
+        let missing = client.get(&store).query(&graph).send().await.unwrap();
+        assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_entity_content_negotiation() {
+        let base = spawn().await;
+        let client = reqwest::Client::new();
+        let acme = "http://example.org/resource/acme";
+
+        client.post(format!("{}/graph-store", base)).query(&[("default", "")])
+            .header("Content-Type", "text/turtle")
+            .body(format!("<{0}> <http://example.org/ontology/basedIn> <http://example.org/resource/paris> .\n\
+                <http://example.org/resource/bob> <http://example.org/ontology/worksFor> <{0}> .", acme))
+            .send().await.unwrap();
+
+        let turtle = client.get(format!("{}/resource/acme", base)).send().await.unwrap();
+        assert_eq!(turtle.headers()["content-type"], "text/turtle");
+        assert!(turtle.text().await.unwrap().contains("paris"));
+
+        let html = client.get(format!("{}/entity", base)).query(&[("uri", acme)])
+            .header("Accept", "text/html,application/xhtml+xml")
+            .send().await.unwrap();
+        assert!(html.headers()["content-type"].to_str().unwrap().starts_with("text/html"));
+        let page = html.text().await.unwrap();
+        assert!(page.contains("href=\"/resource/paris\""));
+        assert!(page.contains("Referenced by"));
+
+        let jsonld = client.get(format!("{}/resource/acme", base))
+            .header("Accept", "application/ld+json")
+            .send().await.unwrap();
+        assert_eq!(jsonld.headers()["content-type"], "application/ld+json");
+
+        client.post(format!("{}/graph-store", base)).query(&[("default", "")])
+            .header("Content-Type", "text/turtle")
+            .body(format!("<{}> <http://www.w3.org/2000/01/rdf-schema#label> \"Acme Corporation\" .", acme))
+            .send().await.unwrap();
+        let suggestions: Value = client.get(format!("{}/autocomplete", base)).query(&[("prefix", "corp")])
+            .send().await.unwrap()
+            .json().await.unwrap();
+        assert_eq!(suggestions, json!([{"uri": acme, "label": "Acme Corporation"}]));
+
+        let missing = client.get(format!("{}/resource/nobody", base)).send().await.unwrap();
+        assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_api_key_permissions() {
+        use crate::config::{ApiKeySettings, Permission};
+
+        let key = |name: &str, permission| ApiKeySettings {
+            name: name.to_string(),
+            key: Some(format!("{}-secret", name)),
+            key_env: None,
+            permission,
+            dataset: None,
+        };
+        let settings = ServerSettings {
+            api_keys: vec![key("reader", Permission::Read), key("loader", Permission::Ingest)],
+            log_requests: false,
+            ..Default::default()
+        };
+        let base = spawn_with(&settings).await;
+        let client = reqwest::Client::new();
+        let store = format!("{}/graph-store?default", base);
+        let query = format!("{}/sparql?query=SELECT%20%3Fs%20WHERE%20%7B%20%3Fs%20%3Fp%20%3Fo%20%7D", base);
+
+        assert_eq!(client.get(&query).send().await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(client.get(&query).bearer_auth("wrong").send().await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(client.get(&query).header("X-API-Key", "reader-secret").send().await.unwrap().status(), StatusCode::OK);
+
+        let body = "<http://ex.org/a> <http://ex.org/p> <http://ex.org/b> .";
+        let denied = client.post(&store).bearer_auth("reader-secret").body(body).send().await.unwrap();
+        assert_eq!(denied.status(), StatusCode::FORBIDDEN);
+        let loaded = client.post(&store).bearer_auth("loader-secret").body(body).send().await.unwrap();
+        assert_eq!(loaded.status(), StatusCode::CREATED);
+        let delete = client.delete(&store).bearer_auth("loader-secret").send().await.unwrap();
+        assert_eq!(delete.status(), StatusCode::FORBIDDEN);
+
+        // The API description is public
+        let spec: Value = client.get(format!("{}/openapi.json", base)).send().await.unwrap().json().await.unwrap();
+        let paths = spec["paths"].as_object().unwrap();
+        for path in ["/sparql", "/graph-store", "/jobs", "/jobs/{id}", "/jobs/{id}/triples", "/entity"] {
+            assert!(paths.contains_key(path), "{} missing from OpenAPI document", path);
+        }
+        assert!(spec["components"]["securitySchemes"]["api_key"].is_object());
+    }
+
+    #[tokio::test]
+    async fn test_read_only() {
+        let path = std::env::temp_dir().join(format!("kg_read_only_{}.json", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "[]").unwrap();
+        let config = KnowledgeGraphConfig {
+            storage_path: path.to_string_lossy().to_string(),
+            read_only: true,
+            ..Default::default()
+        };
+        let mut kg = KnowledgeGraph::new(config, RdfSchema::default()).unwrap();
+        assert!(kg.add_triples(&[crate::core::RdfTriple::new("a".into(), "b".into(), "c".into())]).is_err());
+
+        let state = ServerState::new(kg);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router(state)).await.unwrap() });
+
+        let client = reqwest::Client::new();
+        let store = format!("{}/graph-store?default", base);
+        let post = client.post(&store).body("<http://ex.org/a> <http://ex.org/p> <http://ex.org/b> .").send().await.unwrap();
+        assert_eq!(post.status(), StatusCode::FORBIDDEN);
+        assert_eq!(client.delete(&store).send().await.unwrap().status(), StatusCode::FORBIDDEN);
+        assert_eq!(client.get(&store).send().await.unwrap().status(), StatusCode::OK);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "[]");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_dataset_isolation() {
+        use crate::config::{ApiKeySettings, DatasetSettings, Permission};
+
+        let key = |name: &str, dataset: Option<&str>| ApiKeySettings {
+            name: name.to_string(),
+            key: Some(format!("{}-secret", name)),
+            key_env: None,
+            permission: Permission::Ingest,
+            dataset: dataset.map(str::to_string),
+        };
+        let dataset = DatasetSettings { kg_path: ":memory:".to_string() };
+        let settings = ServerSettings {
+            api_keys: vec![key("team-a", Some("a")), key("team-b", Some("b")), key("main", None)],
+            log_requests: false,
+            datasets: [("a".to_string(), dataset.clone()), ("b".to_string(), dataset)].into(),
+            ..Default::default()
+        };
+        let base = spawn_with(&settings).await;
+        let client = reqwest::Client::new();
+        let count = |key: &'static str| {
+            let client = client.clone();
+            let url = format!("{}/sparql", base);
+            async move {
+                let results: Value = client.get(url).bearer_auth(key)
+                    .query(&[("query", "SELECT ?s WHERE { ?s ?p ?o }")])
+                    .send().await.unwrap()
+                    .json().await.unwrap();
+                results["results"]["bindings"].as_array().unwrap().len()
+            }
+        };
+
+        let stored = client.post(format!("{}/graph-store?default", base)).bearer_auth("team-a-secret")
+            .body("<http://ex.org/a> <http://ex.org/p> <http://ex.org/b> .")
+            .send().await.unwrap();
+        assert_eq!(stored.status(), StatusCode::CREATED);
+
+        assert_eq!(count("team-a-secret").await, 1);
+        assert_eq!(count("team-b-secret").await, 0);
+        assert_eq!(count("main-secret").await, 0);
+
+        let unknown = ServerSettings { api_keys: vec![key("stray", Some("missing"))], ..Default::default() };
+        let kg = KnowledgeGraph::in_memory(RdfSchema::default()).unwrap();
+        assert!(ServerState::new(kg).with_settings(&unknown).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_ingestion_job() {
+        let mut config = crate::config::Configuration::example();
+        config.llm_settings.base_url = "http://127.0.0.1:9".to_string();
+        config.llm_settings.auto_configure = false;
+        config.llm_settings.cache.enabled = false;
+        let extractor = crate::core::RdfExtractorBuilder::new(config).build().unwrap();
+
+        let kg = KnowledgeGraph::in_memory(RdfSchema::default()).unwrap();
+        let state = ServerState::new(kg).with_extractor(extractor);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let server_state = state.clone();
+        tokio::spawn(async move { axum::serve(listener, router(server_state)).await.unwrap() });
+        let client = reqwest::Client::new();
+
+        let rejected = client.post(format!("{}/jobs", base)).json(&json!({ "urls": ["/etc/passwd"] })).send().await.unwrap();
+        assert_eq!(rejected.status(), StatusCode::BAD_REQUEST);
+
+        // Services behind the server cannot be reached through a job
+        for url in ["http://localhost:8080/admin", "http://169.254.169.254/latest/meta-data", "http://[::ffff:10.0.0.1]/", "http://[fd00::1]/"] {
+            let rejected = client.post(format!("{}/jobs", base)).json(&json!({ "urls": [url] })).send().await.unwrap();
+            assert_eq!(rejected.status(), StatusCode::BAD_REQUEST, "{}", url);
+        }
+        let settings = ServerSettings { allowed_hosts: vec!["LocalHost".to_string()], ..Default::default() };
+        let allowed = ServerState::new(KnowledgeGraph::in_memory(RdfSchema::default()).unwrap()).with_settings(&settings).unwrap();
+        assert!(ingest::url_input(&allowed, "http://localhost:8080/doc".to_string()).await.is_ok());
+        assert!(ingest::url_input(&allowed, "http://127.0.0.1:8080/doc".to_string()).await.is_err());
+
+        let form = reqwest::multipart::Form::new()
+            .part("file", reqwest::multipart::Part::text("Acme was founded in 1999.").file_name("notes.txt"));
+        let submitted = client.post(format!("{}/jobs", base)).multipart(form).send().await.unwrap();
+        assert_eq!(submitted.status(), StatusCode::ACCEPTED);
+        let job: Value = submitted.json().await.unwrap();
+        let status_url = format!("{}{}", base, job["status_url"].as_str().unwrap());
+
+        // The LLM server is unreachable, so the document fails
+        let mut status = Value::Null;
+        for _ in 0..50 {
+            status = client.get(&status_url).send().await.unwrap().json().await.unwrap();
+            if status["state"] == "completed" {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+        assert_eq!(status["state"], "completed");
+        assert_eq!(status["failed"], 1);
+        assert_eq!(status["documents"][0]["source"], "notes.txt");
+
+        // Completed jobs are forgotten once their time is up
+        let mut jobs = state.jobs.lock().unwrap();
+        ingest::evict_finished(&mut jobs, std::time::Duration::from_secs(60));
+        assert_eq!(jobs.len(), 1);
+        ingest::evict_finished(&mut jobs, std::time::Duration::ZERO);
+        assert!(jobs.is_empty());
+    }
+}
@@ -0,0 +1,78 @@
+use axum::http::header;
+use axum::response::{Html, IntoResponse, Response};
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+use super::{entities, feedback, graph_store, graphql, ingest};
+use crate::knowledge_graph::{Correction, DocumentStatus, JobDocument, LabelMatch};
+
+/// OpenAPI description of the REST endpoints
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "RDF Knowledge Extractor",
+        description = "SPARQL endpoint, graph store, document ingestion jobs, linked-data entity pages, \
+            reviewer corrections and a GraphQL API for a knowledge graph. Entities under the schema's base URI path can also be dereferenced \
+            directly, e.g. `GET /resource/acme`."
+    ),
+    paths(
+        super::sparql_get,
+        super::sparql_post,
+        graph_store::get_graph,
+        graph_store::put_graph,
+        graph_store::post_graph,
+        graph_store::delete_graph,
+        ingest::submit_job,
+        ingest::job_status,
+        ingest::job_triples,
+        entities::describe_by_uri,
+        entities::autocomplete,
+        feedback::submit,
+        feedback::list,
+        graphql::execute,
+    ),
+    components(schemas(
+        ingest::SubmitJob, ingest::JobUpload, ingest::JobSubmitted, ingest::JobStatus, ingest::JobState,
+        JobDocument, DocumentStatus, LabelMatch, feedback::SubmitCorrection, Correction,
+    )),
+    modifiers(&ApiKeyAuth),
+    security(("bearer" = []), ("api_key" = [])),
+    tags(
+        (name = "sparql", description = "SPARQL 1.1 Protocol"),
+        (name = "graph-store", description = "SPARQL 1.1 Graph Store HTTP Protocol"),
+        (name = "jobs", description = "Background extraction of uploaded files and URLs"),
+        (name = "entities", description = "Linked-data descriptions of entities"),
+        (name = "feedback", description = "Reviewer corrections of extracted triples"),
+        (name = "graphql", description = "GraphQL API generated from the RDF schema"),
+    )
+)]
+pub struct ApiDoc;
+
+/// API keys are accepted as a bearer token or in `X-API-Key`
+struct ApiKeyAuth;
+
+impl Modify for ApiKeyAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme("bearer", SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()));
+        components.add_security_scheme("api_key", SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("X-API-Key"))));
+    }
+}
+
+/// `GET /openapi.json`
+pub async fn openapi_json() -> Response {
+    let body = ApiDoc::openapi().to_pretty_json().unwrap_or_default();
+    ([(header::CONTENT_TYPE, "application/json")], body).into_response()
+}
+
+/// `GET /docs`: Swagger UI for the OpenAPI document
+pub async fn docs() -> Html<&'static str> {
+    Html(concat!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>RDF Knowledge Extractor API</title>\n",
+        "<link rel=\"stylesheet\" href=\"https://unpkg.com/swagger-ui-dist@5/swagger-ui.css\">\n</head>\n<body>\n",
+        "<div id=\"swagger-ui\"></div>\n",
+        "<script src=\"https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js\"></script>\n",
+        "<script>SwaggerUIBundle({ url: '/openapi.json', dom_id: '#swagger-ui' });</script>\n",
+        "</body>\n</html>\n",
+    ))
+}
@@ -0,0 +1,353 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+use super::GeneratedDocument;
+use crate::config::ApprovalSettings;
+use crate::error::{Error, ErrorContext, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalStatus {
+    Pending,
+    Approved,
+    Rejected,
+    Published,
+}
+
+impl std::fmt::Display for ApprovalStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApprovalStatus::Pending => write!(f, "pending"),
+            ApprovalStatus::Approved => write!(f, "approved"),
+            ApprovalStatus::Rejected => write!(f, "rejected"),
+            ApprovalStatus::Published => write!(f, "published"),
+        }
+    }
+}
+
+/// A generated document waiting for review, and where it goes once published
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingDocument {
+    pub id: String,
+    pub template_id: String,
+    pub destination: PathBuf,
+    pub status: ApprovalStatus,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Reviewer or hook comment on the decision
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+/// Decision of an approval hook
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookDecision {
+    Approve,
+    Reject,
+    /// Leave the document pending for a reviewer
+    Hold,
+}
+
+impl HookDecision {
+    /// First word of the answer, e.g. `approve` or `reject: missing totals`
+    fn parse(answer: &str) -> Self {
+        let word = answer.split(|c: char| c.is_whitespace() || c == ':').next().unwrap_or_default();
+        match word.to_ascii_lowercase().as_str() {
+            "approve" | "approved" => HookDecision::Approve,
+            "reject" | "rejected" => HookDecision::Reject,
+            _ => HookDecision::Hold,
+        }
+    }
+}
+
+/// Documents under review, kept in `<kg_path>.approvals/` as `<id>.json`
+/// with the content in `<id>.out`
+pub struct ApprovalQueue {
+    dir: PathBuf,
+}
+
+impl ApprovalQueue {
+    /// Queue directory used for the knowledge graph stored at `kg_path`
+    pub fn dir_for(kg_path: &str) -> PathBuf {
+        PathBuf::from(format!("{}.approvals", kg_path))
+    }
+
+    pub fn open_for(kg_path: &str) -> Self {
+        Self::open(Self::dir_for(kg_path))
+    }
+
+    pub fn open<P: AsRef<Path>>(dir: P) -> Self {
+        Self { dir: dir.as_ref().to_path_buf() }
+    }
+
+    /// Hold `document` as pending until it is approved and published to `destination`
+    pub fn submit(&self, document: &GeneratedDocument, destination: &Path) -> Result<PendingDocument> {
+        fs::create_dir_all(&self.dir)
+            .storage_context(|| format!("Failed to create approval queue: {}", self.dir.display()))?;
+
+        let now = Utc::now();
+        let pending = PendingDocument {
+            id: format!("{}-{}", now.format("%Y%m%d-%H%M%S"), &uuid::Uuid::new_v4().simple().to_string()[..8]),
+            template_id: document.template_id.clone(),
+            destination: destination.to_path_buf(),
+            status: ApprovalStatus::Pending,
+            created_at: now,
+            updated_at: now,
+            note: None,
+        };
+
+        fs::write(self.content_path(&pending.id), &document.generated_content)
+            .storage_context(|| format!("Failed to write pending document {}", pending.id))?;
+        self.save(&pending)?;
+        Ok(pending)
+    }
+
+    /// Documents, oldest first, optionally only those with `status`
+    pub fn list(&self, status: Option<ApprovalStatus>) -> Result<Vec<PendingDocument>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let entries = fs::read_dir(&self.dir)
+            .storage_context(|| format!("Failed to read approval queue: {}", self.dir.display()))?;
+        let mut documents = Vec::new();
+        for entry in entries {
+            let path = entry.storage_context(|| "Failed to read approval queue entry")?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                let document = read_document(&path)?;
+                if status.is_none_or(|status| document.status == status) {
+                    documents.push(document);
+                }
+            }
+        }
+        documents.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(documents)
+    }
+
+    pub fn get(&self, id: &str) -> Result<PendingDocument> {
+        let path = self.dir.join(format!("{}.json", id));
+        if !path.exists() {
+            return Err(Error::storage(format!("No document '{}' awaiting approval", id)));
+        }
+        read_document(&path)
+    }
+
+    /// Path of a document's content while it is under review
+    pub fn content_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.out", id))
+    }
+
+    /// Approve or reject a document that has not been published yet
+    pub fn decide(&self, id: &str, approve: bool, note: Option<String>) -> Result<PendingDocument> {
+        let mut document = self.get(id)?;
+        if document.status == ApprovalStatus::Published {
+            return Err(Error::storage(format!("Document '{}' is already published", id)));
+        }
+
+        document.status = if approve { ApprovalStatus::Approved } else { ApprovalStatus::Rejected };
+        document.note = note.or(document.note);
+        document.updated_at = Utc::now();
+        self.save(&document)?;
+        Ok(document)
+    }
+
+    /// Move an approved document to its destination
+    pub fn publish(&self, id: &str) -> Result<PendingDocument> {
+        let mut document = self.get(id)?;
+        if document.status != ApprovalStatus::Approved {
+            return Err(Error::storage(format!("Document '{}' is {}, not approved", id, document.status)));
+        }
+
+        if let Some(parent) = document.destination.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)
+                .storage_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        fs::copy(self.content_path(id), &document.destination)
+            .storage_context(|| format!("Failed to publish {} to {}", id, document.destination.display()))?;
+        // The content now lives at the destination; the record is kept as the audit trail
+        let _ = fs::remove_file(self.content_path(id));
+
+        document.status = ApprovalStatus::Published;
+        document.updated_at = Utc::now();
+        self.save(&document)?;
+        Ok(document)
+    }
+
+    fn save(&self, document: &PendingDocument) -> Result<()> {
+        let path = self.dir.join(format!("{}.json", document.id));
+        let content = serde_json::to_string_pretty(document)
+            .storage_context(|| "Failed to serialize pending document")?;
+        fs::write(&path, content)
+            .storage_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+fn read_document(path: &Path) -> Result<PendingDocument> {
+    let content = fs::read_to_string(path)
+        .storage_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&content)
+        .storage_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// Post-generation hooks from the `approval` settings
+pub struct ApprovalHooks {
+    command: Vec<String>,
+    webhook: Option<String>,
+    client: reqwest::Client,
+}
+
+impl ApprovalHooks {
+    pub fn new(settings: &ApprovalSettings) -> Self {
+        Self {
+            command: settings.command.clone(),
+            webhook: settings.webhook.clone(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Run the configured hooks for a submitted document. Any rejection
+    /// rejects it, and it is approved only when every hook approves; hooks
+    /// that fail leave it pending.
+    pub async fn run(&self, document: &PendingDocument, content_path: &Path) -> HookDecision {
+        let mut decisions = Vec::new();
+
+        if !self.command.is_empty() {
+            decisions.push(self.run_command(content_path).await.unwrap_or_else(|e| {
+                warn!("Approval command failed for {}: {}", document.id, e);
+                HookDecision::Hold
+            }));
+        }
+        if let Some(url) = &self.webhook {
+            decisions.push(self.call_webhook(url, document, content_path).await.unwrap_or_else(|e| {
+                warn!("Approval webhook failed for {}: {}", document.id, e);
+                HookDecision::Hold
+            }));
+        }
+
+        if decisions.contains(&HookDecision::Reject) {
+            HookDecision::Reject
+        } else if !decisions.is_empty() && decisions.iter().all(|d| *d == HookDecision::Approve) {
+            HookDecision::Approve
+        } else {
+            HookDecision::Hold
+        }
+    }
+
+    async fn run_command(&self, content_path: &Path) -> Result<HookDecision> {
+        let (program, args) = self.command.split_first().expect("command is not empty");
+        let path = content_path.display().to_string();
+        let mut args: Vec<String> = args.iter().map(|arg| arg.replace("{document}", &path)).collect();
+        if !self.command.iter().any(|arg| arg.contains("{document}")) {
+            args.push(path);
+        }
+
+        let output = tokio::process::Command::new(program)
+            .args(&args)
+            .output()
+            .await
+            .template_context(|| format!("Failed to run {}", program))?;
+        if !output.status.success() {
+            return Err(Error::template(format!(
+                "{} exited with {}: {}",
+                program,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        Ok(HookDecision::parse(stdout.lines().rev().find(|line| !line.trim().is_empty()).unwrap_or_default().trim()))
+    }
+
+    async fn call_webhook(&self, url: &str, document: &PendingDocument, content_path: &Path) -> Result<HookDecision> {
+        let content = fs::read_to_string(content_path)
+            .template_context(|| format!("Failed to read pending document {}", document.id))?;
+        let payload = serde_json::json!({
+            "id": document.id,
+            "template_id": document.template_id,
+            "destination": document.destination,
+            "content": content,
+        });
+
+        let response = self.client.post(url).json(&payload).send().await
+            .template_context(|| format!("Failed to call approval webhook {}", url))?
+            .error_for_status()
+            .template_context(|| format!("Approval webhook {} returned an error", url))?;
+        let body: serde_json::Value = response.json().await.unwrap_or_default();
+        Ok(HookDecision::parse(body.get("decision").and_then(|d| d.as_str()).unwrap_or_default()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::templates::DocumentMetadata;
+
+    fn document(content: &str) -> GeneratedDocument {
+        GeneratedDocument {
+            template_id: "weekly".into(),
+            generated_content: content.into(),
+            metadata: DocumentMetadata {
+                generation_timestamp: Utc::now(),
+                template_name: "Weekly".into(),
+                queries_executed: Vec::new(),
+                word_count: 1,
+                processing_time_seconds: 0.0,
+                sources: Vec::new(),
+                template_version: String::new(),
+                query_hashes: Default::default(),
+            },
+            data_context: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_approval_queue() {
+        let dir = std::env::temp_dir().join(format!("approvals_test_{}", uuid::Uuid::new_v4()));
+        let queue = ApprovalQueue::open(dir.join("queue"));
+        let destination = dir.join("published").join("weekly.md");
+
+        let pending = queue.submit(&document("# Weekly"), &destination).unwrap();
+        assert_eq!(queue.list(Some(ApprovalStatus::Pending)).unwrap().len(), 1);
+        assert!(queue.publish(&pending.id).is_err());
+
+        queue.decide(&pending.id, true, Some("looks good".into())).unwrap();
+        let published = queue.publish(&pending.id).unwrap();
+        assert_eq!(published.status, ApprovalStatus::Published);
+        assert_eq!(published.note.as_deref(), Some("looks good"));
+        assert_eq!(fs::read_to_string(&destination).unwrap(), "# Weekly");
+        assert!(queue.decide(&pending.id, false, None).is_err());
+        assert!(queue.get("missing").is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_approval_hooks() {
+        let mut server = mockito::Server::new_async().await;
+        let webhook = server.mock("POST", "/review")
+            .match_body(mockito::Matcher::PartialJson(serde_json::json!({"template_id": "weekly", "content": "# Weekly"})))
+            .with_body(r#"{"decision": "approve"}"#)
+            .create_async().await;
+
+        let dir = std::env::temp_dir().join(format!("approval_hooks_test_{}", uuid::Uuid::new_v4()));
+        let queue = ApprovalQueue::open(&dir);
+        let pending = queue.submit(&document("# Weekly"), Path::new("weekly.md")).unwrap();
+        let content_path = queue.content_path(&pending.id);
+
+        let settings = |command: &[&str]| ApprovalSettings {
+            enabled: true,
+            command: command.iter().map(|s| s.to_string()).collect(),
+            webhook: Some(format!("{}/review", server.url())),
+        };
+        assert_eq!(ApprovalHooks::new(&settings(&[])).run(&pending, &content_path).await, HookDecision::Approve);
+        assert_eq!(ApprovalHooks::new(&settings(&["echo", "reject"])).run(&pending, &content_path).await, HookDecision::Reject);
+        assert_eq!(ApprovalHooks::new(&settings(&["false"])).run(&pending, &content_path).await, HookDecision::Hold);
+        webhook.expect(3).assert_async().await;
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,94 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+
+use crate::core::RdfTriple;
+use crate::error::{ErrorContext, Result};
+use crate::utils::canonicalize;
+
+/// YAML front matter for a generated Markdown document, as read by static
+/// site generators and wikis
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FrontMatter {
+    pub title: String,
+    pub date: String,
+    pub template: String,
+    pub kg_version: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl FrontMatter {
+    /// Title and tags come from the `title` and `tags` context values when
+    /// present; `tags` may be a list or a comma-separated string
+    pub fn new(
+        template_id: &str,
+        template_name: &str,
+        context: &Map<String, Value>,
+        date: chrono::DateTime<chrono::Utc>,
+        kg_version: String,
+    ) -> Self {
+        let title = match context.get("title") {
+            Some(Value::String(title)) => title.clone(),
+            _ => template_name.to_string(),
+        };
+        let tags = match context.get("tags") {
+            Some(Value::Array(tags)) => tags.iter()
+                .map(|tag| tag.as_str().map_or_else(|| tag.to_string(), str::to_string))
+                .collect(),
+            Some(Value::String(tags)) => tags.split(',')
+                .map(str::trim)
+                .filter(|tag| !tag.is_empty())
+                .map(str::to_string)
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        Self {
+            title,
+            date: date.format("%Y-%m-%d").to_string(),
+            template: template_id.to_string(),
+            kg_version,
+            tags,
+        }
+    }
+
+    /// `content` with the front matter block in front of it
+    pub fn prepend_to(&self, content: &str) -> Result<String> {
+        let yaml = serde_yaml::to_string(self).template_context(|| "Failed to serialize front matter")?;
+        Ok(format!("---\n{}---\n\n{}", yaml, content))
+    }
+}
+
+/// Short hash of the canonical N-Quads of the graph, which changes whenever
+/// a triple is added or removed
+pub fn graph_version(triples: &[RdfTriple]) -> String {
+    let digest = Sha256::digest(canonicalize(triples, true).as_bytes());
+    digest.iter().take(6).map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_front_matter() {
+        let date = chrono::Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap();
+        let context = serde_json::json!({"title": "Q1: Acme", "tags": "sales, acme,"});
+        let front_matter = FrontMatter::new("quarterly", "Quarterly", context.as_object().unwrap(), date, "abc123".into());
+
+        assert_eq!(
+            front_matter.prepend_to("# Report\n").unwrap(),
+            "---\ntitle: 'Q1: Acme'\ndate: 2024-03-01\ntemplate: quarterly\nkg_version: abc123\ntags:\n- sales\n- acme\n---\n\n# Report\n"
+        );
+
+        let untitled = FrontMatter::new("quarterly", "Quarterly", &Map::new(), date, String::new());
+        assert_eq!(untitled.title, "Quarterly");
+        assert!(untitled.tags.is_empty());
+
+        let triple = RdfTriple::new("http://ex.org/a".into(), "http://ex.org/p".into(), "x".into());
+        assert_eq!(graph_version(std::slice::from_ref(&triple)), graph_version(&[triple.clone(), triple]));
+        assert_eq!(graph_version(&[]).len(), 12);
+    }
+}
@@ -0,0 +1,255 @@
+use handlebars::{Context, Handlebars, Helper, HelperDef, HelperResult, Output, RenderContext, RenderErrorReason};
+use serde_json::Value;
+use std::sync::Arc;
+
+use crate::knowledge_graph::namespaces::RESERVED_PREFIXES;
+use crate::knowledge_graph::KnowledgeGraph;
+use crate::utils::serialization::compact_iri;
+use super::transforms::{format_date, parse_date};
+
+/// Register the formatting helpers; `prefix` compacts URIs with the
+/// knowledge graph's prefixes
+pub fn register(handlebars: &mut Handlebars<'static>, knowledge_graph: &KnowledgeGraph) {
+    handlebars.register_helper("format_date", Box::new(format_date_helper));
+    handlebars.register_helper("format_number", Box::new(format_number_helper));
+    handlebars.register_helper("round", Box::new(round_helper));
+    handlebars.register_helper("table", Box::new(table_helper));
+    handlebars.register_helper("local_name", Box::new(local_name_helper));
+    handlebars.register_helper("prefix", Box::new(PrefixHelper::new(knowledge_graph)));
+}
+
+/// `{{format_date value "%d %B %Y"}}` reformats an RFC 3339 or `%Y-%m-%d`
+/// date (`%Y-%m-%d` by default); other values are written unchanged, and
+/// an invalid format fails rendering
+fn format_date_helper(h: &Helper, _: &Handlebars, _: &Context, _: &mut RenderContext, out: &mut dyn Output) -> HelperResult {
+    let Some(value) = h.param(0).map(|p| p.value()) else { return Ok(()) };
+    let text = value_text(value);
+    let format = h.param(1).and_then(|p| p.value().as_str()).unwrap_or("%Y-%m-%d");
+    let input = h.hash_get("input").and_then(|p| p.value().as_str());
+
+    match parse_date(&text, input) {
+        Some(date) => {
+            let text = format_date(&date, format)
+                .ok_or_else(|| RenderErrorReason::Other(format!("format_date: invalid date format \"{}\"", format)))?;
+            out.write(&text)?
+        }
+        None => out.write(&text)?,
+    }
+    Ok(())
+}
+
+/// `{{format_number value 2}}` rounds to the given decimals (2 by default)
+/// and groups thousands with `,`, or with `separator="..."`; values that
+/// are not numbers are written unchanged
+fn format_number_helper(h: &Helper, _: &Handlebars, _: &Context, _: &mut RenderContext, out: &mut dyn Output) -> HelperResult {
+    let Some(value) = h.param(0).map(|p| p.value()) else { return Ok(()) };
+    let decimals = h.param(1).and_then(|p| p.value().as_u64()).unwrap_or(2) as usize;
+    let separator = h.hash_get("separator").and_then(|p| p.value().as_str()).unwrap_or(",");
+
+    match number(value) {
+        Some(number) => out.write(&format_number(number, decimals, separator))?,
+        None => out.write(&value_text(value))?,
+    }
+    Ok(())
+}
+
+/// `{{round value 1}}` rounds to the given decimals (0 by default), without grouping
+fn round_helper(h: &Helper, _: &Handlebars, _: &Context, _: &mut RenderContext, out: &mut dyn Output) -> HelperResult {
+    let Some(value) = h.param(0).map(|p| p.value()) else { return Ok(()) };
+    let decimals = h.param(1).and_then(|p| p.value().as_u64()).unwrap_or(0) as usize;
+
+    match number(value) {
+        Some(number) => out.write(&format_number(number, decimals, ""))?,
+        None => out.write(&value_text(value))?,
+    }
+    Ok(())
+}
+
+/// `{{table rows}}` writes an array of objects as a Markdown table with a
+/// column per key, in order of first appearance; `{{table rows "name" "founded"}}`
+/// picks the columns
+fn table_helper(h: &Helper, _: &Handlebars, _: &Context, _: &mut RenderContext, out: &mut dyn Output) -> HelperResult {
+    let Some(rows) = h.param(0).and_then(|p| p.value().as_array()) else { return Ok(()) };
+    let mut columns: Vec<String> = h.params().iter().skip(1)
+        .filter_map(|p| p.value().as_str().map(str::to_string))
+        .collect();
+    if columns.is_empty() {
+        for row in rows.iter().filter_map(Value::as_object) {
+            for key in row.keys() {
+                if !columns.contains(key) {
+                    columns.push(key.clone());
+                }
+            }
+        }
+    }
+    if columns.is_empty() {
+        return Ok(());
+    }
+
+    out.write(&markdown_table(rows, &columns))?;
+    Ok(())
+}
+
+/// `{{local_name uri}}` writes the last segment of a URI
+/// (`http://ex.org/ns#Acme_Corp` → `Acme_Corp`); other values are unchanged
+fn local_name_helper(h: &Helper, _: &Handlebars, _: &Context, _: &mut RenderContext, out: &mut dyn Output) -> HelperResult {
+    if let Some(value) = h.param(0).and_then(|p| p.value().as_str()) {
+        out.write(&local_name(value))?;
+    }
+    Ok(())
+}
+
+/// `{{prefix uri}}` writes a URI as `prefix:local` with the schema prefix,
+/// the knowledge graph's namespaces or `rdf`/`rdfs`; URIs outside them are
+/// written in full
+pub struct PrefixHelper {
+    prefixes: Arc<Vec<(String, String)>>,
+}
+
+impl PrefixHelper {
+    pub fn new(knowledge_graph: &KnowledgeGraph) -> Self {
+        let mut prefixes: Vec<(String, String)> = knowledge_graph.prefixes().into_iter().collect();
+        prefixes.extend(knowledge_graph.schema().custom_vocabularies.iter().map(|(p, ns)| (p.clone(), ns.clone())));
+        for (prefix, namespace) in RESERVED_PREFIXES {
+            if !prefixes.iter().any(|(p, _)| p == prefix) {
+                prefixes.push((prefix.to_string(), namespace.to_string()));
+            }
+        }
+        prefixes.sort();
+        Self { prefixes: Arc::new(prefixes) }
+    }
+}
+
+impl HelperDef for PrefixHelper {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        if let Some(value) = h.param(0).and_then(|p| p.value().as_str()) {
+            out.write(&compact_iri(value, &self.prefixes).unwrap_or_else(|| value.to_string()))?;
+        }
+        Ok(())
+    }
+}
+
+fn is_uri(value: &str) -> bool {
+    value.starts_with("http://") || value.starts_with("https://") || value.starts_with("urn:")
+}
+
+fn local_name(value: &str) -> String {
+    if !is_uri(value) {
+        return value.to_string();
+    }
+    value.trim_end_matches(['/', '#'])
+        .rsplit(['/', '#', ':'])
+        .next()
+        .unwrap_or(value)
+        .to_string()
+}
+
+/// Number held by a value, including numeric strings such as SPARQL literals
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok().filter(|n: &f64| n.is_finite()),
+        _ => None,
+    }
+}
+
+fn format_number(number: f64, decimals: usize, separator: &str) -> String {
+    let formatted = format!("{:.*}", decimals, number);
+    let (sign, unsigned) = match formatted.strip_prefix('-') {
+        // Rounding can leave "-0.00"
+        Some(rest) if rest.chars().any(|c| c.is_ascii_digit() && c != '0') => ("-", rest),
+        Some(rest) => ("", rest),
+        None => ("", formatted.as_str()),
+    };
+    let (whole, fraction) = match unsigned.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (unsigned, None),
+    };
+
+    let mut grouped = String::new();
+    for (i, digit) in whole.chars().enumerate() {
+        if i > 0 && (whole.len() - i) % 3 == 0 {
+            grouped.push_str(separator);
+        }
+        grouped.push(digit);
+    }
+    match fraction {
+        Some(fraction) => format!("{}{}.{}", sign, grouped, fraction),
+        None => format!("{}{}", sign, grouped),
+    }
+}
+
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        Value::Array(items) => items.iter().map(value_text).collect::<Vec<_>>().join(", "),
+        other => other.to_string(),
+    }
+}
+
+fn markdown_table(rows: &[Value], columns: &[String]) -> String {
+    let cell = |value: Option<&Value>| value.map(value_text).unwrap_or_default()
+        .replace('|', "\\|")
+        .replace(['\r', '\n'], " ");
+
+    let mut table = format!("| {} |\n|{}|\n", columns.join(" | "), vec![" --- "; columns.len()].join("|"));
+    for row in rows {
+        let cells: Vec<String> = columns.iter().map(|column| cell(row.get(column))).collect();
+        table.push_str(&format!("| {} |\n", cells.join(" | ")));
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RdfSchema;
+    use serde_json::json;
+
+    #[test]
+    fn test_formatting_helpers() {
+        let schema = RdfSchema {
+            namespace: "http://ex.org/ns#".into(),
+            prefix: "ex".into(),
+            ..Default::default()
+        };
+        let kg = KnowledgeGraph::in_memory(schema).unwrap();
+        let mut handlebars = Handlebars::new();
+        register(&mut handlebars, &kg);
+        let render = |template: &str, data: Value| handlebars.render_template(template, &data).unwrap();
+
+        assert_eq!(render(r#"{{format_date d "%d %B %Y"}}"#, json!({"d": "2024-03-05T10:00:00Z"})), "05 March 2024");
+        assert_eq!(render("{{format_date d}}|{{format_date x}}", json!({"d": "2024-03-05", "x": "soon"})), "2024-03-05|soon");
+        assert_eq!(render(r#"{{format_date d "%Y" input="%d/%m/%Y"}}"#, json!({"d": "05/03/2024"})), "2024");
+        let invalid = handlebars.render_template(r#"{{format_date d "%Q"}}"#, &json!({"d": "2024-03-05"}));
+        assert!(invalid.unwrap_err().to_string().contains("invalid date format"));
+
+        assert_eq!(render("{{format_number n}}", json!({"n": 1234567.891})), "1,234,567.89");
+        assert_eq!(render(r#"{{format_number n 0 separator=" "}}"#, json!({"n": "-9876543"})), "-9 876 543");
+        assert_eq!(render("{{format_number n 1}}|{{format_number x}}", json!({"n": -0.01, "x": "n/a"})), "0.0|n/a");
+        assert_eq!(render("{{round n}}|{{round n 2}}", json!({"n": "12345.678"})), "12346|12345.68");
+
+        let rows = json!({"rows": [
+            {"name": "Acme", "founded": 1999},
+            {"name": "Globex | Intl", "industry": ["Tech", "Energy"]},
+        ]});
+        assert_eq!(render("{{table rows}}", rows.clone()),
+            "| name | founded | industry |\n| --- | --- | --- |\n| Acme | 1999 |  |\n| Globex \\| Intl |  | Tech, Energy |\n");
+        assert_eq!(render(r#"{{table rows "industry" "name"}}"#, rows),
+            "| industry | name |\n| --- | --- |\n|  | Acme |\n| Tech, Energy | Globex \\| Intl |\n");
+
+        let uris = json!({"a": "http://ex.org/ns#Acme_Corp", "b": "http://www.w3.org/2000/01/rdf-schema#label",
+            "c": "http://other.org/x/y", "d": "urn:isbn:0451450523", "e": "plain/text"});
+        assert_eq!(render("{{local_name a}} {{local_name c}} {{local_name d}} {{local_name e}}", uris.clone()),
+            "Acme_Corp y 0451450523 plain/text");
+        assert_eq!(render("{{prefix a}} {{prefix b}} {{prefix c}}", uris), "ex:Acme_Corp rdfs:label http://other.org/x/y");
+    }
+}
@@ -0,0 +1,231 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use super::{GeneratedDocument, Template};
+use crate::error::{Error, ErrorContext, Result};
+use crate::utils::integrity::sha256_hex;
+
+/// One generated document, as recorded in the generation history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenerationRecord {
+    pub id: String,
+    pub template_id: String,
+    pub generated_at: DateTime<Utc>,
+    /// Hash of the resolved template definition
+    pub template_version: String,
+    pub content_hash: String,
+    /// Hash of each data query's results, by query id
+    pub query_hashes: BTreeMap<String, String>,
+    pub model: String,
+    pub word_count: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_path: Option<String>,
+}
+
+/// What changed between two generations
+#[derive(Debug, Clone, Serialize)]
+pub struct GenerationDiff {
+    pub from: String,
+    pub to: String,
+    pub template_changed: bool,
+    pub model_changed: bool,
+    /// Queries whose results changed, were added or were removed
+    pub changed_queries: Vec<String>,
+    /// Unified diff of the document content, empty when it is unchanged
+    pub content_diff: String,
+}
+
+/// Generated documents of the knowledge graph at `kg_path`, kept in
+/// `<kg_path>.generations/`: one JSON line per generation in `history.jsonl`
+/// and the content of each in `<id>.out`
+pub struct GenerationHistory {
+    dir: PathBuf,
+}
+
+impl GenerationHistory {
+    /// History directory used for the knowledge graph stored at `kg_path`
+    pub fn dir_for(kg_path: &str) -> PathBuf {
+        PathBuf::from(format!("{}.generations", kg_path))
+    }
+
+    pub fn open_for(kg_path: &str) -> Self {
+        Self::open(Self::dir_for(kg_path))
+    }
+
+    pub fn open<P: AsRef<Path>>(dir: P) -> Self {
+        Self { dir: dir.as_ref().to_path_buf() }
+    }
+
+    /// Append `document` to the history
+    pub fn record(&self, document: &GeneratedDocument, model: &str, output_path: Option<&Path>) -> Result<GenerationRecord> {
+        fs::create_dir_all(&self.dir)
+            .storage_context(|| format!("Failed to create generation history: {}", self.dir.display()))?;
+
+        let now = Utc::now();
+        let record = GenerationRecord {
+            id: format!("{}-{}", now.format("%Y%m%d-%H%M%S"), &uuid::Uuid::new_v4().simple().to_string()[..8]),
+            template_id: document.template_id.clone(),
+            generated_at: document.metadata.generation_timestamp,
+            template_version: document.metadata.template_version.clone(),
+            content_hash: sha256_hex(document.generated_content.as_bytes()),
+            query_hashes: document.metadata.query_hashes.clone(),
+            model: model.to_string(),
+            word_count: document.metadata.word_count,
+            output_path: output_path.map(|p| p.display().to_string()),
+        };
+
+        fs::write(self.content_path(&record.id), &document.generated_content)
+            .storage_context(|| format!("Failed to write generated document {}", record.id))?;
+
+        let line = serde_json::to_string(&record).storage_context(|| "Failed to serialize generation record")?;
+        let log_path = self.dir.join("history.jsonl");
+        let mut log = fs::OpenOptions::new().create(true).append(true).open(&log_path)
+            .storage_context(|| format!("Failed to open generation history: {}", log_path.display()))?;
+        writeln!(log, "{}", line)
+            .storage_context(|| format!("Failed to write generation history: {}", log_path.display()))?;
+
+        Ok(record)
+    }
+
+    /// Generations, oldest first, optionally only those of `template_id`
+    pub fn list(&self, template_id: Option<&str>) -> Result<Vec<GenerationRecord>> {
+        let log_path = self.dir.join("history.jsonl");
+        if !log_path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = fs::read_to_string(&log_path)
+            .storage_context(|| format!("Failed to read generation history: {}", log_path.display()))?;
+        let mut records = Vec::new();
+        for line in content.lines().filter(|line| !line.trim().is_empty()) {
+            let record: GenerationRecord = serde_json::from_str(line)
+                .storage_context(|| format!("Failed to parse generation history: {}", log_path.display()))?;
+            if template_id.is_none_or(|id| record.template_id == id) {
+                records.push(record);
+            }
+        }
+        Ok(records)
+    }
+
+    /// The generation with this id, or the only one whose id starts with it
+    pub fn get(&self, id: &str) -> Result<GenerationRecord> {
+        let mut matches: Vec<_> = self.list(None)?.into_iter().filter(|r| r.id.starts_with(id)).collect();
+        if let Some(exact) = matches.iter().position(|r| r.id == id) {
+            return Ok(matches.swap_remove(exact));
+        }
+        match matches.len() {
+            1 => Ok(matches.remove(0)),
+            0 => Err(Error::storage(format!("No generation with id '{}'", id))),
+            n => Err(Error::storage(format!("Generation id '{}' is ambiguous ({} matches)", id, n))),
+        }
+    }
+
+    /// The generation of the same template recorded before `record`
+    pub fn previous(&self, record: &GenerationRecord) -> Result<Option<GenerationRecord>> {
+        let records = self.list(Some(&record.template_id))?;
+        let position = records.iter().position(|r| r.id == record.id);
+        Ok(position.and_then(|i| i.checked_sub(1)).map(|i| records[i].clone()))
+    }
+
+    pub fn content(&self, record: &GenerationRecord) -> Result<String> {
+        fs::read_to_string(self.content_path(&record.id))
+            .storage_context(|| format!("Failed to read generated document {}", record.id))
+    }
+
+    pub fn diff(&self, from: &GenerationRecord, to: &GenerationRecord) -> Result<GenerationDiff> {
+        let mut changed_queries: Vec<String> = from.query_hashes.keys()
+            .chain(to.query_hashes.keys())
+            .filter(|id| from.query_hashes.get(*id) != to.query_hashes.get(*id))
+            .cloned()
+            .collect();
+        changed_queries.sort();
+        changed_queries.dedup();
+
+        let content_diff = if from.content_hash == to.content_hash {
+            String::new()
+        } else {
+            let (old, new) = (self.content(from)?, self.content(to)?);
+            similar::TextDiff::from_lines(&old, &new)
+                .unified_diff()
+                .header(&from.id, &to.id)
+                .to_string()
+        };
+
+        Ok(GenerationDiff {
+            from: from.id.clone(),
+            to: to.id.clone(),
+            template_changed: from.template_version != to.template_version,
+            model_changed: from.model != to.model,
+            changed_queries,
+            content_diff,
+        })
+    }
+
+    fn content_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.out", id))
+    }
+}
+
+/// Hash of a resolved template's definition
+pub fn template_version(template: &Template) -> String {
+    sha256_hex(serde_json::to_string(template).unwrap_or_default().as_bytes())
+}
+
+/// Hash of a data query's processed results
+pub fn results_hash(results: &Value) -> String {
+    sha256_hex(results.to_string().as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::templates::DocumentMetadata;
+
+    fn document(content: &str, companies: Value) -> GeneratedDocument {
+        GeneratedDocument {
+            template_id: "weekly".into(),
+            generated_content: content.into(),
+            metadata: DocumentMetadata {
+                generation_timestamp: Utc::now(),
+                template_name: "Weekly".into(),
+                queries_executed: vec!["companies".into()],
+                word_count: content.split_whitespace().count(),
+                processing_time_seconds: 0.0,
+                sources: Vec::new(),
+                template_version: "v1".into(),
+                query_hashes: BTreeMap::from([("companies".to_string(), results_hash(&companies))]),
+            },
+            data_context: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_generation_history() {
+        let dir = std::env::temp_dir().join(format!("generations_test_{}", uuid::Uuid::new_v4()));
+        let history = GenerationHistory::open(&dir);
+        assert!(history.list(None).unwrap().is_empty());
+
+        let first = history.record(&document("# Weekly\nAcme\n", serde_json::json!(["Acme"])), "m", None).unwrap();
+        let second = history.record(&document("# Weekly\nAcme\nGlobex\n", serde_json::json!(["Acme", "Globex"])), "m", None).unwrap();
+
+        assert_eq!(history.list(Some("weekly")).unwrap().len(), 2);
+        assert!(history.list(Some("other")).unwrap().is_empty());
+        assert_eq!(history.get(&second.id).unwrap().content_hash, second.content_hash);
+        assert_eq!(history.previous(&second).unwrap().unwrap().id, first.id);
+        assert!(history.previous(&first).unwrap().is_none());
+        assert!(history.get("nope").is_err());
+
+        let diff = history.diff(&first, &second).unwrap();
+        assert!(!diff.template_changed);
+        assert_eq!(diff.changed_queries, ["companies"]);
+        assert!(diff.content_diff.contains("+Globex\n"));
+        assert!(history.diff(&first, &first).unwrap().content_diff.is_empty());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,81 @@
+use serde_json::Value;
+
+use crate::error::{Error, Result};
+
+/// Parse a generated JSON document, tolerating a Markdown code fence around
+/// it, and check it against `schema`; the problems found are returned as
+/// messages suitable for a repair prompt
+pub fn parse_document(content: &str, schema: Option<&Value>) -> Result<std::result::Result<Value, Vec<String>>> {
+    let document: Value = match serde_json::from_str(strip_fence(content)) {
+        Ok(document) => document,
+        Err(e) => return Ok(Err(vec![format!("not valid JSON: {}", e)])),
+    };
+
+    let Some(schema) = schema else {
+        return Ok(Ok(document));
+    };
+    let validator = jsonschema::validator_for(schema)
+        .map_err(|e| Error::template(format!("Invalid JSON Schema: {}", e)))?;
+
+    let problems: Vec<String> = validator.iter_errors(&document)
+        .map(|e| match e.instance_path.to_string() {
+            path if path.is_empty() => e.to_string(),
+            path => format!("{}: {}", path, e),
+        })
+        .collect();
+
+    Ok(if problems.is_empty() { Ok(document) } else { Err(problems) })
+}
+
+/// Prompt asking the LLM to fix `content` so that it parses and satisfies `schema`
+pub fn repair_prompt(content: &str, problems: &[String], schema: Option<&Value>) -> String {
+    let mut prompt = format!(
+        "The following JSON document is invalid:\n\n{}\n\nProblems:\n{}",
+        content,
+        problems.iter().map(|p| format!("- {}", p)).collect::<Vec<_>>().join("\n")
+    );
+
+    if let Some(schema) = schema {
+        prompt.push_str(&format!(
+            "\n\nIt must satisfy this JSON Schema:\n{}",
+            serde_json::to_string_pretty(schema).unwrap_or_default()
+        ));
+    }
+
+    prompt.push_str("\n\nRespond with the corrected JSON document only, keeping its data unchanged where possible.");
+    prompt
+}
+
+fn strip_fence(content: &str) -> &str {
+    let trimmed = content.trim();
+    let Some(rest) = trimmed.strip_prefix("```") else {
+        return trimmed;
+    };
+    let body = rest.split_once('\n').map_or("", |(_, body)| body);
+    body.trim_end().strip_suffix("```").unwrap_or(body).trim()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_document() {
+        let schema = json!({
+            "type": "object",
+            "required": ["companies"],
+            "properties": { "companies": { "type": "array", "items": { "type": "string" } } }
+        });
+
+        let document = parse_document("```json\n{\"companies\": [\"Acme\"]}\n```", Some(&schema)).unwrap();
+        assert_eq!(document, Ok(json!({"companies": ["Acme"]})));
+
+        let problems = parse_document("{\"companies\": [1]}", Some(&schema)).unwrap().unwrap_err();
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].starts_with("/companies/0: "));
+
+        assert!(parse_document("{\"companies\": ", None).unwrap().unwrap_err()[0].starts_with("not valid JSON"));
+        assert!(parse_document("{}", Some(&json!({"type": 5}))).is_err());
+    }
+}
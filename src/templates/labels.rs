@@ -0,0 +1,110 @@
+use handlebars::{Context, Handlebars, Helper, HelperDef, HelperResult, Output, RenderContext};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::knowledge_graph::labels::label_rank;
+use crate::knowledge_graph::KnowledgeGraph;
+use super::transforms::uri_label;
+
+/// Human-readable names of the entities in a knowledge graph
+///
+/// Labels come from `rdfs:label`, then `skos:prefLabel`, then any `hasName`
+/// or `name` predicate (e.g. `foaf:name`); URIs without one fall back to
+/// their local name.
+#[derive(Debug, Clone, Default)]
+pub struct LabelIndex {
+    labels: HashMap<String, (u8, String)>,
+}
+
+impl LabelIndex {
+    pub fn from_graph(knowledge_graph: &KnowledgeGraph) -> Self {
+        let mut labels: HashMap<String, (u8, String)> = HashMap::new();
+
+        for triple in knowledge_graph.triples() {
+            let Some(rank) = label_rank(&triple.predicate) else { continue };
+            let label = triple.object.trim();
+            if label.is_empty() {
+                continue;
+            }
+
+            match labels.get(&triple.subject) {
+                Some((existing, _)) if *existing <= rank => {}
+                _ => {
+                    labels.insert(triple.subject.clone(), (rank, label.to_string()));
+                }
+            }
+        }
+
+        Self { labels }
+    }
+
+    /// Label stored in the graph for `uri`, if any
+    pub fn get(&self, uri: &str) -> Option<&str> {
+        self.labels.get(uri).map(|(_, label)| label.as_str())
+    }
+
+    /// Label for `value` if it is a URI, falling back to its local name;
+    /// other values are returned unchanged
+    pub fn resolve(&self, value: &str) -> String {
+        match self.get(value) {
+            Some(label) => label.to_string(),
+            None => uri_label(value),
+        }
+    }
+}
+
+/// `{{label uri}}` renders an entity's label from the knowledge graph
+pub struct LabelHelper {
+    labels: Arc<LabelIndex>,
+}
+
+impl LabelHelper {
+    pub fn new(labels: Arc<LabelIndex>) -> Self {
+        Self { labels }
+    }
+}
+
+impl HelperDef for LabelHelper {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        if let Some(param) = h.param(0) {
+            if let Some(value) = param.value().as_str() {
+                out.write(&self.labels.resolve(value))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RdfSchema;
+    use crate::core::RdfTriple;
+
+    fn triple(subject: &str, predicate: &str, object: &str) -> RdfTriple {
+        RdfTriple::new(subject.to_string(), predicate.to_string(), object.to_string())
+    }
+
+    #[test]
+    fn test_label_resolution() {
+        let mut kg = KnowledgeGraph::in_memory(RdfSchema::default()).unwrap();
+        kg.add_triples(&[
+            triple("http://ex.org/acme", "http://ex.org/hasName", "Acme"),
+            triple("http://ex.org/acme", "http://www.w3.org/2000/01/rdf-schema#label", "Acme Corporation"),
+            triple("http://ex.org/jane", "http://xmlns.com/foaf/0.1/name", "Jane Doe"),
+        ]).unwrap();
+
+        let labels = LabelIndex::from_graph(&kg);
+        assert_eq!(labels.resolve("http://ex.org/acme"), "Acme Corporation");
+        assert_eq!(labels.resolve("http://ex.org/jane"), "Jane Doe");
+        assert_eq!(labels.resolve("http://ex.org/Globex_Inc"), "Globex Inc");
+        assert_eq!(labels.resolve("plain text"), "plain text");
+    }
+}
@@ -1,8 +1,9 @@
-use anyhow::{Result, Context};
+use crate::error::Result;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use tracing::{debug, info};
 
+use crate::config::{GenerationOptions, LlmPhase};
 use crate::core::llm_client::VllmClient;
 use crate::knowledge_graph::SimpleSparqlResults;
 
@@ -26,11 +27,20 @@ pub struct TemplatePopulationRequest {
 
 pub struct TemplatePopulator {
     llm_client: VllmClient,
+    options: GenerationOptions,
 }
 
 impl TemplatePopulator {
     pub fn new(llm_client: VllmClient) -> Self {
-        Self { llm_client }
+        let options = llm_client.phase_options(LlmPhase::Generation);
+        Self { llm_client, options }
+    }
+
+    /// Sampling options for population calls, replacing the client's
+    /// `generation` settings
+    pub fn with_options(mut self, options: GenerationOptions) -> Self {
+        self.options = options;
+        self
     }
 
     /// Populates a template by sending the template and extracted data to the LLM
@@ -63,7 +73,7 @@ Instructions:
 Return ONLY the completed template with all fields populated."#;
 
         // Send to LLM for population
-        let response = self.llm_client.generate(&prompt, Some(system_prompt)).await?;
+        let response = self.llm_client.generate(&prompt, Some(system_prompt), self.options).await?;
 
         debug!("Template populated successfully");
         Ok(response.content)
@@ -90,18 +100,21 @@ Return ONLY the completed template with all fields populated."#;
             prompt.push_str(&format!("### Query: {}\n", query_id));
 
             match results {
-                SimpleSparqlResults::Solutions(rows) => {
+                SimpleSparqlResults::Solutions { variables, rows } => {
                     if rows.is_empty() {
                         prompt.push_str("No results found.\n\n");
                     } else {
                         // Format the results as a readable list
+                        let columns = SimpleSparqlResults::columns(variables, rows);
                         for (idx, row) in rows.iter().enumerate() {
                             prompt.push_str(&format!("Result {}:\n", idx + 1));
-                            for (key, value) in row {
-                                prompt.push_str(&format!("  - {}: {}\n", key, value));
+                            for key in &columns {
+                                if let Some(value) = row.get(key) {
+                                    prompt.push_str(&format!("  - {}: {}\n", key, value));
+                                }
                             }
                         }
-                        prompt.push_str("\n");
+                        prompt.push('\n');
                     }
                 }
                 SimpleSparqlResults::Boolean(result) => {
@@ -1,17 +1,36 @@
-use anyhow::{Result, Context};
-use handlebars::{Handlebars, RenderError};
+use crate::error::{Error, ErrorContext, Result};
+use handlebars::Handlebars;
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
-use std::collections::HashMap;
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::Path;
+use std::sync::{Arc, OnceLock};
 use tracing::{debug, info, warn};
 
-use crate::knowledge_graph::{KnowledgeGraph, SimpleSparqlResults};
+use crate::knowledge_graph::{federation, KnowledgeGraph, SimpleSparqlResults, ViewStore};
+use crate::config::{GenerationOptions, LlmPhase};
 use crate::core::llm_client::VllmClient;
 
+pub mod approval;
+pub mod front_matter;
+pub mod helpers;
+pub mod history;
+pub mod json_output;
+pub mod labels;
 pub mod llm_population;
+pub mod preview;
+pub mod sections;
+pub mod transforms;
+pub mod validation;
+pub use approval::{ApprovalHooks, ApprovalQueue, ApprovalStatus, HookDecision, PendingDocument};
+pub use front_matter::FrontMatter;
+pub use history::{GenerationHistory, GenerationRecord};
+pub use labels::LabelIndex;
 pub use llm_population::{TemplatePopulator, TemplateField, TemplatePopulationRequest};
+pub use transforms::RowTransform;
+pub use validation::{QueryOutcome, TemplateValidation};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Template {
@@ -19,11 +38,47 @@ pub struct Template {
     pub name: String,
     pub description: String,
     pub template_type: TemplateType,
+    #[serde(default)]
     pub data_queries: Vec<DataQuery>,
+    /// Handlebars source; unused when `extends` is set, as the base's is rendered
+    #[serde(default)]
     pub template_content: String,
     pub output_format: OutputFormat,
     pub llm_instructions: Option<String>,
     pub post_processing: Option<PostProcessingConfig>,
+    /// Replace URIs in query results with entity labels from the knowledge graph
+    #[serde(default)]
+    pub resolve_labels: bool,
+    /// Id of a base template whose content and data queries this one inherits
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<String>,
+    /// Content for the base template's `{{#> name}}default{{/name}}` blocks
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub blocks: BTreeMap<String, String>,
+    /// Render one document per row of an iterator query with `generate_batch`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch: Option<BatchSettings>,
+    /// Sampling overrides for this template's LLM calls, over the
+    /// configured `generation` and `enhancement` settings
+    #[serde(default, skip_serializing_if = "GenerationOptions::is_empty")]
+    pub llm_options: GenerationOptions,
+    /// Put YAML front matter at the top of the generated Markdown
+    #[serde(default)]
+    pub front_matter: bool,
+    /// JSON Schema the document must satisfy when `output_format` is `json`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub json_schema: Option<Value>,
+}
+
+/// Iterator query for batch generation
+///
+/// Each row's bindings are added to the template context and substituted
+/// into the data queries, e.g. `FILTER(?c = <{{company}}>)`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchSettings {
+    pub query: String,
+    /// Handlebars template for the output file name, e.g. `{{name}}-report.md`
+    pub file_name: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -52,9 +107,19 @@ pub enum OutputFormat {
 pub struct DataQuery {
     pub id: String,
     pub description: String,
+    #[serde(default)]
     pub sparql_query: String,
+    /// Read results from this materialized view instead of running `sparql_query`
+    #[serde(default)]
+    pub view: Option<String>,
+    /// Run `sparql_query` against this remote SPARQL endpoint instead of
+    /// the knowledge graph
+    #[serde(default)]
+    pub endpoint: Option<String>,
     pub required: bool,
-    pub transform: Option<String>,
+    /// Transforms applied to the result rows before they reach the template
+    #[serde(default)]
+    pub transform: Vec<RowTransform>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -63,6 +128,13 @@ pub struct PostProcessingConfig {
     pub style_guide: Option<String>,
     pub word_limit: Option<usize>,
     pub include_sources: bool,
+    /// Enhance the document one section at a time, splitting at headings of
+    /// at most this level; the word limit is shared out by section length
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub section_level: Option<usize>,
+    /// Extra instructions for sections, keyed by heading title
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub section_instructions: BTreeMap<String, String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -81,6 +153,13 @@ pub struct GeneratedDocument {
     pub data_context: Map<String, Value>,
 }
 
+/// A document rendered for one row of a batch iterator query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchDocument {
+    pub file_name: String,
+    pub document: GeneratedDocument,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DocumentMetadata {
     pub generation_timestamp: chrono::DateTime<chrono::Utc>,
@@ -89,40 +168,127 @@ pub struct DocumentMetadata {
     pub word_count: usize,
     pub processing_time_seconds: f64,
     pub sources: Vec<String>,
+    /// Hash of the resolved template definition
+    #[serde(default)]
+    pub template_version: String,
+    /// Hash of each data query's results, by query id
+    #[serde(default)]
+    pub query_hashes: BTreeMap<String, String>,
 }
 
-pub struct TemplateManager {
+/// Renders templates over a knowledge graph, which it either owns or, as
+/// `TemplateManager<&KnowledgeGraph>`, borrows for the generation
+pub struct TemplateManager<G = KnowledgeGraph> {
     templates: HashMap<String, Template>,
     handlebars: Handlebars<'static>,
-    knowledge_graph: KnowledgeGraph,
-    llm_client: VllmClient,
+    knowledge_graph: G,
+    /// Client for generation; `None` when templates are only validated
+    llm_client: Option<VllmClient>,
+    views: Option<ViewStore>,
+    labels: Arc<LabelIndex>,
+    front_matter: bool,
+    kg_version: OnceLock<String>,
 }
 
-impl TemplateManager {
-    pub fn new(knowledge_graph: KnowledgeGraph, llm_client: VllmClient) -> Self {
+impl<G: Borrow<KnowledgeGraph>> TemplateManager<G> {
+    pub fn new(knowledge_graph: G, llm_client: Option<VllmClient>) -> Self {
         let mut handlebars = Handlebars::new();
 
         // Register custom helpers
         handlebars.register_helper("format_list", Box::new(format_list_helper));
         handlebars.register_helper("truncate", Box::new(truncate_helper));
         handlebars.register_helper("capitalize", Box::new(capitalize_helper));
+        helpers::register(&mut handlebars, knowledge_graph.borrow());
+
+        let labels = Arc::new(LabelIndex::from_graph(knowledge_graph.borrow()));
+        handlebars.register_helper("label", Box::new(labels::LabelHelper::new(labels.clone())));
 
         Self {
             templates: HashMap::new(),
             handlebars,
             knowledge_graph,
             llm_client,
+            views: None,
+            labels,
+            front_matter: false,
+            kg_version: OnceLock::new(),
+        }
+    }
+
+    /// Make materialized views available to data queries that reference them by name
+    pub fn with_views(mut self, views: ViewStore) -> Self {
+        self.views = Some(views);
+        self
+    }
+
+    /// Add front matter to every Markdown document, not only those of
+    /// templates that ask for it
+    pub fn with_front_matter(mut self, front_matter: bool) -> Self {
+        self.front_matter = front_matter;
+        self
+    }
+
+    /// Register a partial usable as `{{> name}}` in template content
+    pub fn register_partial(&mut self, name: &str, content: &str) -> Result<()> {
+        self.handlebars.register_partial(name, content)
+            .template_context(|| format!("Failed to register partial: {}", name))
+    }
+
+    /// Register every .hbs/.handlebars/.md/.html/.txt file under `dir_path` as
+    /// a partial named by its path relative to the directory, without extension
+    pub fn load_partials_from_directory(&mut self, dir_path: &Path) -> Result<usize> {
+        let mut loaded_count = 0;
+        for entry in walkdir::WalkDir::new(dir_path) {
+            let entry = entry
+                .template_context(|| format!("Failed to read partials directory: {}", dir_path.display()))?;
+            let path = entry.path();
+
+            if !path.extension().is_some_and(|ext| ["hbs", "handlebars", "md", "html", "txt"].iter().any(|e| ext == *e)) {
+                continue;
+            }
+
+            let name = path.strip_prefix(dir_path).unwrap_or(path)
+                .with_extension("")
+                .to_string_lossy()
+                .replace('\\', "/");
+            let content = fs::read_to_string(path)
+                .template_context(|| format!("Failed to read partial: {}", path.display()))?;
+            self.register_partial(&name, &content)?;
+            debug!("Registered partial: {}", name);
+            loaded_count += 1;
+        }
+
+        Ok(loaded_count)
+    }
+
+    /// Register partials from `partials/` next to a template file or inside a template directory
+    fn load_sibling_partials(&mut self, dir: &Path) -> Result<()> {
+        let partials_dir = dir.join("partials");
+        if partials_dir.is_dir() {
+            let count = self.load_partials_from_directory(&partials_dir)?;
+            info!("Loaded {} partials from: {}", count, partials_dir.display());
         }
+        Ok(())
     }
 
     pub fn load_template(&mut self, template_path: &str) -> Result<()> {
+        if let Some(dir) = Path::new(template_path).parent() {
+            self.load_sibling_partials(dir)?;
+        }
+
+        self.parse_template_file(template_path)
+    }
+
+    fn parse_template_file(&mut self, template_path: &str) -> Result<()> {
         let content = fs::read_to_string(template_path)
-            .with_context(|| format!("Failed to read template file: {}", template_path))?;
+            .template_context(|| format!("Failed to read template file: {}", template_path))?;
 
         let template: Template = if template_path.ends_with(".json") {
-            serde_json::from_str(&content)?
+            serde_json::from_str(&content)
+                .template_context(|| format!("Failed to parse template file: {}", template_path))?
         } else {
-            serde_yaml::from_str(&content)?
+            serde_yaml::from_str(&content)
+                .template_context(|| format!("Failed to parse template file: {}", template_path))?
         };
 
         info!("Loaded template: {} ({})", template.name, template.id);
@@ -133,16 +299,19 @@ impl TemplateManager {
     pub fn load_templates_from_directory(&mut self, dir_path: &str) -> Result<usize> {
         let dir = Path::new(dir_path);
         if !dir.exists() {
-            anyhow::bail!("Template directory does not exist: {}", dir_path);
+            return Err(Error::template(format!("Template directory does not exist: {}", dir_path)));
         }
 
+        self.load_sibling_partials(dir)?;
+
         let mut loaded_count = 0;
         for entry in walkdir::WalkDir::new(dir) {
-            let entry = entry?;
+            let entry = entry
+                .template_context(|| format!("Failed to read template directory: {}", dir_path))?;
             let path = entry.path();
 
-            if path.extension().map_or(false, |ext| ext == "yaml" || ext == "yml" || ext == "json") {
-                if let Err(e) = self.load_template(path.to_str().unwrap()) {
+            if path.extension().is_some_and(|ext| ext == "yaml" || ext == "yml" || ext == "json") {
+                if let Err(e) = self.parse_template_file(path.to_str().unwrap()) {
                     warn!("Failed to load template {}: {}", path.display(), e);
                 } else {
                     loaded_count += 1;
@@ -155,36 +324,96 @@ impl TemplateManager {
     }
 
     pub async fn generate_document(&self, request: &TemplateGenerationRequest) -> Result<GeneratedDocument> {
-        let start_time = std::time::Instant::now();
-
-        let template = self.templates.get(&request.template_id)
-            .ok_or_else(|| anyhow::anyhow!("Template not found: {}", request.template_id))?;
+        let template = &self.resolve_template(&request.template_id)?;
 
         info!("Generating document from template: {}", template.name);
+        self.render_document(template, request, None).await
+    }
+
+    /// Render one document per row of the template's batch iterator query
+    pub async fn generate_batch(&self, request: &TemplateGenerationRequest) -> Result<Vec<BatchDocument>> {
+        let mut documents = Vec::new();
+        self.generate_batch_each(request, async |document, _| {
+            documents.push(document);
+            Ok::<_, Error>(())
+        }).await?;
+        Ok(documents)
+    }
+
+    /// Render the batch one document at a time, handing each document to
+    /// `on_document` as soon as it is rendered, with the number of documents
+    /// the batch query selected
+    pub async fn generate_batch_each<F, E>(
+        &self,
+        request: &TemplateGenerationRequest,
+        mut on_document: F,
+    ) -> std::result::Result<(), E>
+    where
+        F: AsyncFnMut(BatchDocument, usize) -> std::result::Result<(), E>,
+        E: From<Error>,
+    {
+        let template = &self.resolve_template(&request.template_id)?;
+        let batch = template.batch.as_ref()
+            .ok_or_else(|| Error::template(format!("Template '{}' has no batch iterator query", template.id)))?;
+
+        let rows = match self.graph().execute_sparql(&batch.query)
+            .template_context(|| format!("Batch query for template '{}' failed", template.id))?
+        {
+            SimpleSparqlResults::Solutions { rows, .. } => rows,
+            SimpleSparqlResults::Boolean(_) => {
+                return Err(Error::template(format!("Batch query for template '{}' must be a SELECT query", template.id)).into());
+            }
+        };
+
+        info!("Generating {} documents from template: {}", rows.len(), template.name);
+        let total = rows.len();
+
+        let plain = plain_handlebars();
+        let mut file_names: HashMap<String, usize> = HashMap::new();
+
+        for row in rows {
+            let row: Map<String, Value> = row.into_iter().map(|(k, v)| (k, Value::String(v.into_value()))).collect();
+
+            let rendered = plain.render_template(&batch.file_name, &row)
+                .template_context(|| format!("Failed to render file name '{}'", batch.file_name))?;
+            let file_name = unique_file_name(sanitize_file_name(&rendered), &mut file_names);
+
+            let document = self.render_document(template, request, Some(&row)).await?;
+            on_document(BatchDocument { file_name, document }, total).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Run the data queries and render the template; `row` is the current
+    /// batch row, substituted into the queries and added to the context
+    async fn render_document(
+        &self,
+        template: &Template,
+        request: &TemplateGenerationRequest,
+        row: Option<&Map<String, Value>>,
+    ) -> Result<GeneratedDocument> {
+        let start_time = std::time::Instant::now();
 
         // Execute data queries
         let mut data_context = Map::new();
         let mut queries_executed = Vec::new();
-        let mut sources = Vec::new();
+        let mut query_hashes = BTreeMap::new();
+        let sources = Vec::new();
 
         for query in &template.data_queries {
-            let sparql_query = if let Some(ref overrides) = request.override_queries {
-                overrides.get(&query.id).unwrap_or(&query.sparql_query).clone()
-            } else {
-                query.sparql_query.clone()
-            };
-
-            debug!("Executing query '{}': {}", query.id, sparql_query);
+            let sparql_query = data_query_text(query, request, row)?;
 
-            match self.knowledge_graph.execute_sparql(&sparql_query) {
+            match self.run_data_query(query, request, &sparql_query).await {
                 Ok(results) => {
-                    let processed_data = self.process_query_results(results, query)?;
+                    let processed_data = self.process_query_results(results, query, template.resolve_labels)?;
+                    query_hashes.insert(query.id.clone(), history::results_hash(&processed_data));
                     data_context.insert(query.id.clone(), processed_data);
                     queries_executed.push(query.id.clone());
                 }
                 Err(e) => {
                     if query.required {
-                        return Err(anyhow::anyhow!("Required query '{}' failed: {}", query.id, e));
+                        return Err(Error::template(format!("Required query '{}' failed: {}", query.id, e)));
                     } else {
                         warn!("Optional query '{}' failed: {}", query.id, e);
                         data_context.insert(query.id.clone(), Value::Null);
@@ -193,6 +422,12 @@ impl TemplateManager {
             }
         }
 
+        if let Some(row) = row {
+            for (key, value) in row {
+                data_context.insert(key.clone(), value.clone());
+            }
+        }
+
         // Add context from request
         if let Some(ref context) = request.context {
             for (key, value) in context {
@@ -204,7 +439,7 @@ impl TemplateManager {
         let mut generated_content = self.handlebars.render_template(
             &template.template_content,
             &Value::Object(data_context.clone())
-        ).with_context(|| "Failed to render template")?;
+        ).template_context(|| "Failed to render template")?;
 
         // Apply LLM enhancement if configured
         if let Some(ref post_processing) = template.post_processing {
@@ -217,6 +452,10 @@ impl TemplateManager {
             }
         }
 
+        if matches!(template.output_format, OutputFormat::Json) {
+            generated_content = self.finish_json(generated_content, template).await?;
+        }
+
         let processing_time = start_time.elapsed().as_secs_f64();
 
         let metadata = DocumentMetadata {
@@ -226,8 +465,21 @@ impl TemplateManager {
             word_count: generated_content.split_whitespace().count(),
             processing_time_seconds: processing_time,
             sources,
+            template_version: history::template_version(template),
+            query_hashes,
         };
 
+        if (template.front_matter || self.front_matter) && matches!(template.output_format, OutputFormat::Markdown) {
+            let kg_version = self.kg_version.get_or_init(|| front_matter::graph_version(self.graph().triples()));
+            generated_content = FrontMatter::new(
+                &template.id,
+                &template.name,
+                &data_context,
+                metadata.generation_timestamp,
+                kg_version.clone(),
+            ).prepend_to(&generated_content)?;
+        }
+
         Ok(GeneratedDocument {
             template_id: template.id.clone(),
             generated_content,
@@ -236,33 +488,71 @@ impl TemplateManager {
         })
     }
 
-    fn process_query_results(&self, results: SimpleSparqlResults, query: &DataQuery) -> Result<Value> {
+    /// Read a data query's results from its view, or run it against its
+    /// endpoint or the knowledge graph (fetching SERVICE blocks from theirs);
+    /// an override in the request takes precedence over the view
+    async fn run_data_query(&self, query: &DataQuery, request: &TemplateGenerationRequest, sparql_query: &str) -> Result<SimpleSparqlResults> {
+        if let Some(results) = self.view_results(query, request, sparql_query) {
+            return results;
+        }
+        debug!("Executing query '{}': {}", query.id, sparql_query);
+        federation::execute_on(self.graph(), query.endpoint.as_deref(), sparql_query).await
+    }
+
+    /// A data query's results read from its view; `None` when it runs as a query
+    fn view_results(&self, query: &DataQuery, request: &TemplateGenerationRequest, sparql_query: &str) -> Option<Result<SimpleSparqlResults>> {
+        let overridden = request.override_queries.as_ref().is_some_and(|o| o.contains_key(&query.id));
+        match (&query.view, &self.views) {
+            (Some(view), Some(views)) if !overridden => {
+                debug!("Reading query '{}' from view: {}", query.id, view);
+                Some(views.results(view).cloned())
+            }
+            (Some(view), None) if sparql_query.is_empty() => {
+                Some(Err(Error::template(format!("Query '{}' references view '{}' but no views are loaded", query.id, view))))
+            }
+            _ => None,
+        }
+    }
+
+    fn process_query_results(&self, results: SimpleSparqlResults, query: &DataQuery, resolve_labels: bool) -> Result<Value> {
         match results {
-            SimpleSparqlResults::Solutions(solutions) => {
+            SimpleSparqlResults::Solutions { variables, rows } => {
+                let columns = SimpleSparqlResults::columns(&variables, &rows);
                 let mut processed_results = Vec::new();
 
-                for solution in solutions {
+                for mut solution in rows {
                     let mut row = Map::new();
 
-                    for (var, value_str) in solution {
-                        // Try to parse as different types
-                        let value = if let Ok(int_val) = value_str.parse::<i64>() {
-                            Value::Number(serde_json::Number::from(int_val))
-                        } else if let Ok(float_val) = value_str.parse::<f64>() {
-                            Value::Number(serde_json::Number::from_f64(float_val).unwrap_or_else(|| serde_json::Number::from(0)))
-                        } else if let Ok(bool_val) = value_str.parse::<bool>() {
-                            Value::Bool(bool_val)
-                        } else {
-                            Value::String(value_str)
+                    for var in &columns {
+                        let Some(binding) = solution.remove(var) else { continue };
+                        // Typed literals become JSON numbers and booleans
+                        let value = match binding.xsd_type() {
+                            Some("integer" | "int" | "long" | "short" | "nonNegativeInteger" | "positiveInteger") => {
+                                match binding.value().parse::<i64>() {
+                                    Ok(int_val) => Value::Number(serde_json::Number::from(int_val)),
+                                    Err(_) => Value::String(binding.into_value()),
+                                }
+                            }
+                            Some("decimal" | "double" | "float") => {
+                                match binding.value().parse::<f64>().ok().and_then(serde_json::Number::from_f64) {
+                                    Some(number) => Value::Number(number),
+                                    None => Value::String(binding.into_value()),
+                                }
+                            }
+                            Some("boolean") => Value::Bool(binding.value() == "true" || binding.value() == "1"),
+                            _ if resolve_labels && binding.is_iri() => Value::String(self.labels.resolve(binding.value())),
+                            _ => Value::String(binding.into_value()),
                         };
 
-                        row.insert(var, value);
+                        row.insert(var.clone(), value);
                     }
 
-                    processed_results.push(Value::Object(row));
+                    processed_results.push(row);
                 }
 
-                Ok(Value::Array(processed_results))
+                let processed_results = transforms::apply_transforms(processed_results, &query.transform)
+                    .template_context(|| format!("Failed to transform the results of query '{}'", query.id))?;
+                Ok(Value::Array(processed_results.into_iter().map(Value::Object).collect()))
             }
             SimpleSparqlResults::Boolean(result) => {
                 Ok(Value::Bool(result))
@@ -276,33 +566,227 @@ impl TemplateManager {
         template: &Template,
         post_processing: &PostProcessingConfig,
     ) -> Result<String> {
-        let mut enhancement_prompt = format!(
-            "Please enhance and improve the following {} content:\n\n{}",
-            template.template_type.to_string(),
-            content
-        );
+        let Some(level) = post_processing.section_level else {
+            let prompt = enhancement_prompt(content, template, post_processing, None, post_processing.word_limit);
+            return self.enhance_prompt(&prompt, template).await;
+        };
 
-        if let Some(ref style_guide) = post_processing.style_guide {
-            enhancement_prompt.push_str(&format!("\n\nStyle Guide: {}", style_guide));
-        }
+        let mut sections = sections::split_sections(content, level);
+        let total_words = sections.iter().map(|s| s.body.split_whitespace().count()).sum::<usize>().max(1);
+        debug!("Enhancing {} sections of template {}", sections.len(), template.id);
 
-        if let Some(word_limit) = post_processing.word_limit {
-            enhancement_prompt.push_str(&format!("\n\nWord limit: {} words", word_limit));
+        for section in &mut sections {
+            let body = section.body.trim();
+            if body.is_empty() {
+                continue;
+            }
+            let word_limit = post_processing.word_limit
+                .map(|limit| (limit * body.split_whitespace().count()).div_ceil(total_words).max(1));
+            let prompt = enhancement_prompt(body, template, post_processing, Some(&*section), word_limit);
+            let enhanced = self.enhance_prompt(&prompt, template).await?;
+
+            let leading = &section.body[..section.body.len() - section.body.trim_start().len()];
+            let trailing = &section.body[section.body.trim_end().len()..];
+            section.body = format!("{}{}{}", leading, enhanced.trim(), trailing);
         }
 
-        if let Some(ref instructions) = template.llm_instructions {
-            enhancement_prompt.push_str(&format!("\n\nAdditional instructions: {}", instructions));
-        }
+        Ok(sections::join_sections(&sections))
+    }
 
-        enhancement_prompt.push_str("\n\nProvide the enhanced content as your response.");
+    /// Check a JSON document parses and satisfies the template's schema, and
+    /// pretty-print it; when LLM enhancement is on, an invalid document is
+    /// sent back to the LLM with the problems found, up to `JSON_REPAIR_ATTEMPTS` times
+    async fn finish_json(&self, mut content: String, template: &Template) -> Result<String> {
+        let schema = template.json_schema.as_ref();
+        let can_repair = template.post_processing.as_ref().is_some_and(|p| p.enhance_with_llm);
+        let mut attempts = 0;
+
+        loop {
+            let problems = match json_output::parse_document(&content, schema)? {
+                Ok(document) => {
+                    return serde_json::to_string_pretty(&document)
+                        .template_context(|| "Failed to format JSON document");
+                }
+                Err(problems) => problems,
+            };
 
+            if !can_repair || attempts == JSON_REPAIR_ATTEMPTS {
+                return Err(Error::template(format!(
+                    "Template '{}' produced an invalid JSON document: {}",
+                    template.id,
+                    problems.join("; ")
+                )));
+            }
+
+            attempts += 1;
+            warn!("Template {} produced invalid JSON, asking the LLM to repair it (attempt {}): {}", template.id, attempts, problems.join("; "));
+            content = self.enhance_prompt(&json_output::repair_prompt(&content, &problems, schema), template).await?;
+        }
+    }
+
+    async fn enhance_prompt(&self, prompt: &str, template: &Template) -> Result<String> {
         let system_prompt = "You are a skilled editor and writer. Your task is to enhance and improve the provided content while maintaining its core information and structure. Make the text more engaging, clear, and professional while preserving all important facts and data.";
 
-        let response = self.llm_client.generate(&enhancement_prompt, Some(system_prompt)).await?;
+        let llm_client = self.llm_client()?;
+        let options = template.llm_options.or(llm_client.phase_options(LlmPhase::Enhancement));
+        let response = llm_client.generate(prompt, Some(system_prompt), options).await?;
 
         Ok(response.content)
     }
 
+    /// Give back the knowledge graph the manager was created with
+    pub fn into_knowledge_graph(self) -> G {
+        self.knowledge_graph
+    }
+
+    fn graph(&self) -> &KnowledgeGraph {
+        self.knowledge_graph.borrow()
+    }
+
+    fn llm_client(&self) -> Result<&VllmClient> {
+        self.llm_client.as_ref()
+            .ok_or_else(|| Error::llm("No LLM client configured; templates can only be validated"))
+    }
+
+    /// Apply inheritance: the root base template's content with the `blocks`
+    /// of the chain as inline partials (most derived wins), and the data
+    /// queries of the chain, where a derived query replaces a base query with
+    /// the same id
+    pub fn resolve_template(&self, template_id: &str) -> Result<Template> {
+        let mut chain = vec![self.get_template(template_id)
+            .ok_or_else(|| Error::template(format!("Template not found: {}", template_id)))?];
+
+        while let Some(base_id) = &chain[chain.len() - 1].extends {
+            if chain.iter().any(|t| &t.id == base_id) {
+                return Err(Error::template(format!("Template '{}' has an inheritance cycle through '{}'", template_id, base_id)));
+            }
+            chain.push(self.get_template(base_id)
+                .ok_or_else(|| Error::template(format!("Base template not found: {}", base_id)))?);
+        }
+
+        let mut resolved = chain[0].clone();
+        let mut blocks = BTreeMap::new();
+        let mut data_queries: Vec<DataQuery> = Vec::new();
+
+        for template in chain.iter().rev() {
+            blocks.extend(template.blocks.iter());
+            for query in &template.data_queries {
+                match data_queries.iter_mut().find(|q| q.id == query.id) {
+                    Some(existing) => *existing = query.clone(),
+                    None => data_queries.push(query.clone()),
+                }
+            }
+            if template.llm_instructions.is_some() && chain[0].llm_instructions.is_none() {
+                resolved.llm_instructions = template.llm_instructions.clone();
+            }
+            if template.post_processing.is_some() && chain[0].post_processing.is_none() {
+                resolved.post_processing = template.post_processing.clone();
+            }
+            if template.batch.is_some() && chain[0].batch.is_none() {
+                resolved.batch = template.batch.clone();
+            }
+        }
+
+        let mut content = String::new();
+        for (name, block) in blocks {
+            content.push_str(&format!("{{{{#*inline \"{}\"}}}}{}{{{{/inline}}}}", name, block));
+        }
+        content.push_str(&chain[chain.len() - 1].template_content);
+
+        resolved.template_content = content;
+        resolved.data_queries = data_queries;
+        Ok(resolved)
+    }
+
+    /// Check a template without the LLM: its Handlebars syntax, that the
+    /// content uses every data query, and that each query runs against the
+    /// knowledge graph and returns results. Batch templates substitute the
+    /// first row of their iterator query.
+    pub fn validate_template(&self, template_id: &str) -> Result<TemplateValidation> {
+        let template = self.resolve_template(template_id)?;
+        let mut validation = TemplateValidation::new(&template.id);
+
+        match handlebars::Template::compile(&template.template_content) {
+            Err(e) => validation.error(format!("Invalid Handlebars syntax: {}", e)),
+            Ok(_) => match preview::sample_context(&template) {
+                Ok(context) => {
+                    if let Err(e) = self.handlebars.render_template(&template.template_content, &Value::Object(context)) {
+                        validation.error(format!("Rendering against sample data failed: {}", e));
+                    }
+                }
+                Err(e) => validation.error(e.to_string()),
+            },
+        }
+
+        let referenced = validation::referenced_names(&template.template_content);
+        for query in template.data_queries.iter().filter(|q| !referenced.contains(&q.id)) {
+            validation.warning(format!("Query '{}' is not referenced in the template content", query.id));
+        }
+
+        let mut row = None;
+        if let Some(batch) = &template.batch {
+            if let Err(e) = handlebars::Template::compile(&batch.file_name) {
+                validation.error(format!("Invalid Handlebars syntax in batch file name: {}", e));
+            }
+            let batch_query = DataQuery {
+                id: "batch".into(),
+                description: "Batch iterator query".into(),
+                sparql_query: batch.query.clone(),
+                view: None,
+                endpoint: None,
+                required: true,
+                transform: Vec::new(),
+            };
+            let outcome = match self.graph().execute_sparql(&batch.query) {
+                Ok(SimpleSparqlResults::Solutions { rows, .. }) => {
+                    let count = rows.len();
+                    row = rows.into_iter().next()
+                        .map(|r| r.into_iter().map(|(k, v)| (k, Value::String(v.into_value()))).collect::<Map<_, _>>());
+                    QueryOutcome::Rows(count)
+                }
+                Ok(SimpleSparqlResults::Boolean(_)) => QueryOutcome::Failed("must be a SELECT query".into()),
+                Err(e) => QueryOutcome::Failed(e.to_string()),
+            };
+            validation.add_query(&batch_query, outcome);
+        }
+
+        let request = TemplateGenerationRequest {
+            template_id: template.id.clone(),
+            context: None,
+            override_queries: None,
+            output_path: None,
+        };
+        for query in &template.data_queries {
+            let results = data_query_text(query, &request, row.as_ref()).and_then(|sparql_query| {
+                match self.view_results(query, &request, &sparql_query) {
+                    Some(results) => results.map(Some),
+                    // Validation does not send queries to other endpoints
+                    None if query.endpoint.is_some() || federation::uses_service(&sparql_query) => Ok(None),
+                    None => self.graph().execute_sparql(&sparql_query).map(Some),
+                }
+            });
+            let outcome = match results {
+                Ok(Some(SimpleSparqlResults::Solutions { rows, .. })) => QueryOutcome::Rows(rows.len()),
+                Ok(Some(SimpleSparqlResults::Boolean(result))) => QueryOutcome::Boolean(result),
+                Ok(None) => QueryOutcome::Remote,
+                Err(e) => QueryOutcome::Failed(e.to_string()),
+            };
+            validation.add_query(query, outcome);
+        }
+
+        Ok(validation)
+    }
+
+    /// Render a template against a synthetic context from its query
+    /// variables, without the knowledge graph or the LLM; LLM enhancement
+    /// is skipped
+    pub fn preview(&self, template_id: &str) -> Result<String> {
+        let template = self.resolve_template(template_id)?;
+        let context = preview::sample_context(&template)?;
+        self.handlebars.render_template(&template.template_content, &Value::Object(context))
+            .template_context(|| format!("Failed to render preview of template '{}'", template_id))
+    }
+
     pub fn list_templates(&self) -> Vec<&Template> {
         self.templates.values().collect()
     }
@@ -317,8 +801,7 @@ impl TemplateManager {
         template_id: &str,
         template_text: &str,
     ) -> Result<String> {
-        let template = self.templates.get(template_id)
-            .ok_or_else(|| anyhow::anyhow!("Template not found: {}", template_id))?;
+        let template = &self.resolve_template(template_id)?;
 
         info!("Generating document with LLM population for template: {}", template.name);
 
@@ -328,13 +811,13 @@ impl TemplateManager {
         for query in &template.data_queries {
             debug!("Executing query '{}': {}", query.id, query.sparql_query);
 
-            match self.knowledge_graph.execute_sparql(&query.sparql_query) {
+            match federation::execute_on(self.graph(), query.endpoint.as_deref(), &query.sparql_query).await {
                 Ok(results) => {
                     query_results.insert(query.id.clone(), results);
                 }
                 Err(e) => {
                     if query.required {
-                        return Err(anyhow::anyhow!("Required query '{}' failed: {}", query.id, e));
+                        return Err(Error::template(format!("Required query '{}' failed: {}", query.id, e)));
                     } else {
                         warn!("Optional query '{}' failed: {}", query.id, e);
                     }
@@ -343,7 +826,9 @@ impl TemplateManager {
         }
 
         // Use the TemplatePopulator to have LLM fill in the template
-        let populator = TemplatePopulator::new(self.llm_client.clone());
+        let llm_client = self.llm_client()?;
+        let options = template.llm_options.or(llm_client.phase_options(LlmPhase::Generation));
+        let populator = TemplatePopulator::new(llm_client.clone()).with_options(options);
         let populated_content = populator.populate_template(
             template_text,
             &query_results,
@@ -368,6 +853,49 @@ impl std::fmt::Display for TemplateType {
     }
 }
 
+/// A data query's SPARQL, taken from the request's overrides when present,
+/// with the batch row substituted into it
+fn data_query_text(query: &DataQuery, request: &TemplateGenerationRequest, row: Option<&Map<String, Value>>) -> Result<String> {
+    let sparql_query = request.override_queries.as_ref()
+        .and_then(|overrides| overrides.get(&query.id))
+        .unwrap_or(&query.sparql_query);
+
+    match row {
+        Some(row) => plain_handlebars().render_template(sparql_query, row)
+            .template_context(|| format!("Failed to substitute batch row into query '{}'", query.id)),
+        None => Ok(sparql_query.clone()),
+    }
+}
+
+/// Registry for query and file name substitution, where HTML escaping would
+/// corrupt URIs
+fn plain_handlebars() -> Handlebars<'static> {
+    let mut handlebars = Handlebars::new();
+    handlebars.register_escape_fn(handlebars::no_escape);
+    handlebars
+}
+
+fn sanitize_file_name(name: &str) -> String {
+    let sanitized: String = name.trim()
+        .chars()
+        .map(|c| if matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') || c.is_control() { '_' } else { c })
+        .collect();
+    if sanitized.is_empty() { "document".to_string() } else { sanitized }
+}
+
+/// Suffix repeated names with a counter so rows never overwrite each other
+fn unique_file_name(name: String, seen: &mut HashMap<String, usize>) -> String {
+    let count = seen.entry(name.clone()).or_insert(0);
+    *count += 1;
+    if *count == 1 {
+        return name;
+    }
+    match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => format!("{}-{}.{}", stem, count, extension),
+        _ => format!("{}-{}", name, count),
+    }
+}
+
 // Handlebars helpers
 fn format_list_helper(
     h: &handlebars::Helper,
@@ -436,4 +964,316 @@ fn capitalize_helper(
         }
     }
     Ok(())
-}
\ No newline at end of file
+}
+const JSON_REPAIR_ATTEMPTS: usize = 2;
+
+/// Prompt for enhancing `content`, either a whole document or the body of
+/// `section`, whose heading is kept out of the LLM's reach
+fn enhancement_prompt(
+    content: &str,
+    template: &Template,
+    post_processing: &PostProcessingConfig,
+    section: Option<&sections::Section>,
+    word_limit: Option<usize>,
+) -> String {
+    let title = section.and_then(|section| section.title());
+    let mut prompt = match &title {
+        Some(title) => format!(
+            "Please enhance and improve the following section \"{}\" of a {}. Return only the section text, without its heading:\n\n{}",
+            title, template.template_type, content
+        ),
+        None => format!("Please enhance and improve the following {} content:\n\n{}", template.template_type, content),
+    };
+
+    if let Some(ref style_guide) = post_processing.style_guide {
+        prompt.push_str(&format!("\n\nStyle Guide: {}", style_guide));
+    }
+
+    if let Some(word_limit) = word_limit {
+        prompt.push_str(&format!("\n\nWord limit: {} words", word_limit));
+    }
+
+    if let Some(ref instructions) = template.llm_instructions {
+        prompt.push_str(&format!("\n\nAdditional instructions: {}", instructions));
+    }
+
+    if let Some(instructions) = title.and_then(|title| post_processing.section_instructions.get(&title)) {
+        prompt.push_str(&format!("\n\nSection instructions: {}", instructions));
+    }
+
+    prompt.push_str("\n\nProvide the enhanced content as your response.");
+    prompt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RdfSchema;
+
+    fn manager() -> TemplateManager {
+        manager_with(&[])
+    }
+
+    fn manager_with(triples: &[(&str, &str, &str)]) -> TemplateManager {
+        let mut kg = KnowledgeGraph::in_memory(RdfSchema::default()).unwrap();
+        let triples: Vec<_> = triples.iter()
+            .map(|(s, p, o)| crate::core::RdfTriple::new(s.to_string(), p.to_string(), o.to_string()))
+            .collect();
+        kg.add_triples(&triples).unwrap();
+        let llm_client = VllmClient::new("http://localhost:8000".into(), None, "test".into(), 0.0, 16, 1).unwrap();
+        TemplateManager::new(kg, Some(llm_client))
+    }
+
+    fn template(yaml: &str) -> Template {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_partials_and_inheritance() {
+        let mut manager = manager();
+        manager.register_partial("footer", "-- {{author}}").unwrap();
+
+        for t in [
+            template(r#"
+id: base
+name: Base
+description: Layout
+template_type: report
+output_format: markdown
+data_queries:
+  - { id: rows, description: All, sparql_query: "SELECT * WHERE { ?s ?p ?o }", required: false }
+template_content: "{{#> title}}Untitled{{/title}}|{{#> body}}empty{{/body}}|{{> footer}}"
+"#),
+            template(r#"
+id: child
+name: Child
+description: Report
+template_type: report
+output_format: markdown
+extends: base
+blocks:
+  title: "Report for {{author}}"
+"#),
+        ] {
+            manager.templates.insert(t.id.clone(), t);
+        }
+
+        let resolved = manager.resolve_template("child").unwrap();
+        assert_eq!(resolved.data_queries.len(), 1);
+
+        let request = TemplateGenerationRequest {
+            template_id: "child".into(),
+            context: Some(HashMap::from([("author".to_string(), Value::from("Jane"))])),
+            override_queries: None,
+            output_path: None,
+        };
+        let document = manager.generate_document(&request).await.unwrap();
+        assert_eq!(document.generated_content, "Report for Jane|empty|-- Jane");
+
+        manager.templates.get_mut("base").unwrap().extends = Some("child".into());
+        assert!(manager.resolve_template("child").is_err());
+    }
+
+    #[test]
+    fn test_preview() {
+        let mut manager = manager();
+        let t = template(r##"
+id: companies
+name: Companies
+description: Company list
+template_type: report
+output_format: markdown
+batch: { query: "SELECT ?industry WHERE { ?c <http://ex.org/ns#hasIndustry> ?industry }", file_name: "{{industry}}.md" }
+data_queries:
+  - id: companies
+    description: Companies
+    sparql_query: "SELECT ?name ?founded ?employees WHERE { ?c <http://ex.org/ns#hasName> ?name }"
+    required: true
+    transform: [{ type: rename, from: employees, to: headcount }]
+  - { id: any, description: Any, sparql_query: "ASK { ?s ?p ?o }", required: false }
+template_content: "# {{industry}}\n{{#each companies}}- {{name}} ({{founded}}): {{headcount}}\n{{/each}}{{#if any}}done{{/if}}"
+post_processing: { enhance_with_llm: true, include_sources: false }
+"##);
+        manager.templates.insert(t.id.clone(), t);
+
+        assert_eq!(
+            manager.preview("companies").unwrap(),
+            "# Sample Industry 1\n- Sample Name 1 (2024-01-15): 42\n- Sample Name 2 (2024-02-15): 84\ndone"
+        );
+        assert!(manager.preview("missing").is_err());
+    }
+
+    #[test]
+    fn test_validate_template() {
+        let mut manager = manager_with(&[("http://ex.org/acme", "http://ex.org/ns#hasName", "Acme")]);
+        for t in [
+            template(r#"
+id: ok
+name: Ok
+description: Companies
+template_type: report
+output_format: markdown
+data_queries:
+  - { id: names, description: Names, sparql_query: "SELECT ?n WHERE { ?c <http://ex.org/ns#hasName> ?n }", required: true }
+  - { id: ceos, description: CEOs, sparql_query: "SELECT ?p WHERE { ?p <http://ex.org/ns#ceoOf> ?c }", required: false }
+  - { id: unused, description: Unused, sparql_query: "SELECT ?s WHERE { ?s ?p ?o }", required: false }
+template_content: "{{#each names}}{{n}}{{/each}}{{#each ceos}}{{p}}{{/each}}"
+"#),
+            template(r#"
+id: broken
+name: Broken
+description: Broken
+template_type: report
+output_format: markdown
+data_queries:
+  - { id: names, description: Names, sparql_query: "DESCRIBE <http://ex.org/acme>", required: true }
+template_content: "{{#each names}}"
+"#),
+        ] {
+            manager.templates.insert(t.id.clone(), t);
+        }
+
+        let ok = manager.validate_template("ok").unwrap();
+        assert!(ok.is_valid());
+        let outcomes: Vec<_> = ok.queries.iter().map(|q| (q.id.as_str(), q.outcome.clone())).collect();
+        assert_eq!(outcomes, [("names", QueryOutcome::Rows(1)), ("ceos", QueryOutcome::Rows(0)), ("unused", QueryOutcome::Rows(1))]);
+        let warnings: Vec<_> = ok.issues.iter().map(|i| i.message.as_str()).collect();
+        assert_eq!(warnings, ["Query 'unused' is not referenced in the template content", "Query 'ceos' returned no results"]);
+
+        let broken = manager.validate_template("broken").unwrap();
+        assert!(!broken.is_valid());
+        assert!(broken.issues[0].message.starts_with("Invalid Handlebars syntax"));
+        assert!(broken.issues[1].message.starts_with("Required query 'names' failed"));
+        assert!(manager.validate_template("missing").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_batch_generation() {
+        let mut manager = manager_with(&[
+            ("http://ex.org/acme?id=1&v=2", "http://ex.org/ns#hasName", "Acme"),
+            ("http://ex.org/acme?id=1&v=2", "http://ex.org/ns#hasIndustry", "Mining"),
+            ("http://ex.org/globex", "http://ex.org/ns#hasName", "Globex/EU"),
+            ("http://ex.org/globex", "http://ex.org/ns#hasIndustry", "Energy"),
+        ]);
+        let t = template(r#"
+id: account
+name: Account
+description: Per-account report
+template_type: report
+output_format: markdown
+batch:
+  query: "SELECT ?company ?name WHERE { ?company hasName ?name } ORDER BY ?name"
+  file_name: "{{name}}-report.md"
+data_queries:
+  - id: industry
+    description: Industry of the company
+    sparql_query: "SELECT ?i WHERE { <{{company}}> hasIndustry ?i }"
+    required: true
+template_content: "{{name}}: {{#each industry}}{{i}}{{/each}}"
+"#);
+        manager.templates.insert(t.id.clone(), t);
+
+        let request = TemplateGenerationRequest {
+            template_id: "account".into(),
+            context: None,
+            override_queries: None,
+            output_path: None,
+        };
+        let documents = manager.generate_batch(&request).await.unwrap();
+        let rendered: Vec<_> = documents.iter()
+            .map(|d| (d.file_name.as_str(), d.document.generated_content.as_str()))
+            .collect();
+        assert_eq!(rendered, [("Acme-report.md", "Acme: Mining"), ("Globex_EU-report.md", "Globex/EU: Energy")]);
+
+        assert_eq!(unique_file_name("a.md".into(), &mut HashMap::from([("a.md".to_string(), 1)])), "a-2.md");
+    }
+
+    #[tokio::test]
+    async fn test_section_enhancement() {
+        let mut server = mockito::Server::new_async().await;
+        let reply = |content: &str| serde_json::json!({
+            "id": "1", "object": "chat.completion", "created": 0, "model": "m",
+            "choices": [{"index": 0, "message": {"role": "assistant", "content": content}, "finish_reason": "stop"}],
+            "usage": {"prompt_tokens": 1, "completion_tokens": 1, "total_tokens": 2}
+        }).to_string();
+        let companies = server.mock("POST", "/v1/chat/completions")
+            .match_body(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::Regex("section \\\\\"Companies\\\\\"".into()),
+                mockito::Matcher::Regex("Section instructions: Use a table".into()),
+                mockito::Matcher::Regex("Word limit: 2 words".into()),
+            ]))
+            .with_body(reply("Acme leads."))
+            .create_async().await;
+        let people = server.mock("POST", "/v1/chat/completions")
+            .match_body(mockito::Matcher::Regex("section \\\\\"People\\\\\"".into()))
+            .with_body(reply("\nJane runs Acme.\n"))
+            .create_async().await;
+
+        let kg = KnowledgeGraph::in_memory(RdfSchema::default()).unwrap();
+        let llm_client = VllmClient::new(server.url(), None, "m".into(), 0.0, 16, 1).unwrap();
+        let manager = TemplateManager::new(kg, Some(llm_client));
+        let t = template(r#"
+id: report
+name: Report
+description: Report
+template_type: report
+output_format: markdown
+template_content: ""
+post_processing:
+  enhance_with_llm: true
+  include_sources: false
+  word_limit: 6
+  section_level: 2
+  section_instructions: { Companies: Use a table }
+"#);
+
+        let content = "## Companies\nAcme.\n\n## People\nJane, Acme.\n## Empty\n";
+        let enhanced = manager.enhance_with_llm(content, &t, t.post_processing.as_ref().unwrap()).await.unwrap();
+        assert_eq!(enhanced, "## Companies\nAcme leads.\n\n## People\nJane runs Acme.\n## Empty\n");
+
+        companies.assert_async().await;
+        people.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_json_output_repair() {
+        let mut server = mockito::Server::new_async().await;
+        let reply = serde_json::json!({
+            "id": "1", "object": "chat.completion", "created": 0, "model": "m",
+            "choices": [{"index": 0, "message": {"role": "assistant", "content": "```json\n{\"count\": 2}\n```"}, "finish_reason": "stop"}],
+            "usage": {"prompt_tokens": 1, "completion_tokens": 1, "total_tokens": 2}
+        }).to_string();
+        let repair = server.mock("POST", "/v1/chat/completions")
+            .match_body(mockito::Matcher::Regex("/count: .* is not of type".into()))
+            .with_body(reply)
+            .create_async().await;
+
+        let kg = KnowledgeGraph::in_memory(RdfSchema::default()).unwrap();
+        let llm_client = VllmClient::new(server.url(), None, "m".into(), 0.0, 16, 1).unwrap();
+        let mut manager = TemplateManager::new(kg, Some(llm_client));
+        let mut t = template(r#"
+id: counts
+name: Counts
+description: Counts
+template_type: report
+output_format: json
+template_content: '{"count": "{{count}}"}'
+json_schema: { type: object, properties: { count: { type: integer } } }
+post_processing: { enhance_with_llm: false, include_sources: false }
+"#);
+        manager.templates.insert(t.id.clone(), t.clone());
+
+        let request = TemplateGenerationRequest {
+            template_id: "counts".into(),
+            context: Some(HashMap::from([("count".to_string(), Value::from(2))])),
+            override_queries: None,
+            output_path: None,
+        };
+        assert!(manager.generate_document(&request).await.is_err());
+
+        t.post_processing.as_mut().unwrap().enhance_with_llm = true;
+        let repaired = manager.finish_json("{\"count\": \"2\"}".into(), &t).await.unwrap();
+        assert_eq!(repaired, "{\n  \"count\": 2\n}");
+        repair.assert_async().await;
+    }
+}
@@ -0,0 +1,67 @@
+use serde_json::{Map, Value};
+
+use super::{transforms, Template};
+use crate::error::Result;
+use crate::knowledge_graph::SelectQuery;
+
+/// Rows given to each data query in a preview
+const SAMPLE_ROWS: usize = 2;
+
+/// Synthetic template context: each data query's SELECT variables filled
+/// with sample values (after the query's transforms), and the batch query's
+/// variables for the current row
+///
+/// ASK queries are `true`; queries whose variables cannot be read, such as
+/// view-backed ones, get rows without columns.
+pub fn sample_context(template: &Template) -> Result<Map<String, Value>> {
+    let mut context = Map::new();
+
+    if let Some(batch) = &template.batch {
+        context.extend(sample_row(&query_variables(&batch.query), 1));
+    }
+
+    for query in &template.data_queries {
+        let value = if query.sparql_query.trim_start().to_ascii_uppercase().starts_with("ASK") {
+            Value::Bool(true)
+        } else {
+            let variables = query_variables(&query.sparql_query);
+            let rows = (1..=SAMPLE_ROWS).map(|n| sample_row(&variables, n)).collect();
+            let rows = transforms::apply_transforms(rows, &query.transform)?;
+            Value::Array(rows.into_iter().map(Value::Object).collect())
+        };
+        context.insert(query.id.clone(), value);
+    }
+
+    Ok(context)
+}
+
+fn query_variables(query: &str) -> Vec<String> {
+    SelectQuery::parse(query).map(|q| q.variables()).unwrap_or_default()
+}
+
+fn sample_row(variables: &[String], n: usize) -> Map<String, Value> {
+    variables.iter().map(|variable| (variable.clone(), sample_value(variable, n))).collect()
+}
+
+/// Value shaped after the variable's name: dates, numbers, URLs, email
+/// addresses, or `Sample <Name> <n>`
+fn sample_value(variable: &str, n: usize) -> Value {
+    let name = variable.to_lowercase();
+    let has = |words: &[&str]| words.iter().any(|w| name.contains(w));
+
+    if has(&["date", "founded", "since", "year", "time"]) {
+        Value::from(format!("2024-0{}-15", n))
+    } else if has(&["count", "total", "number", "num", "amount", "revenue", "size", "employees", "age", "sum", "avg"]) {
+        Value::from(42 * n)
+    } else if has(&["url", "uri", "website", "homepage", "link", "iri"]) {
+        Value::from(format!("http://example.org/resource/sample_{}", n))
+    } else if has(&["email", "mail"]) {
+        Value::from(format!("sample{}@example.org", n))
+    } else {
+        let mut label = variable.replace('_', " ");
+        if let Some(first) = label.get(..1) {
+            label.replace_range(..1, &first.to_uppercase());
+        }
+        Value::from(format!("Sample {} {}", label, n))
+    }
+}
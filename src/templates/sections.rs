@@ -0,0 +1,96 @@
+/// Part of a rendered document: a heading line and the text up to the next
+/// heading of the same or a higher level. The text before the first heading
+/// is a section without one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Section {
+    /// Heading line, without its line break
+    pub heading: Option<String>,
+    /// Everything after the heading line, line breaks included
+    pub body: String,
+}
+
+impl Section {
+    /// Heading text without Markdown `#`s or HTML tags
+    pub fn title(&self) -> Option<String> {
+        let heading = self.heading.as_deref()?;
+        let mut title = String::new();
+        let mut in_tag = false;
+        for c in heading.trim().trim_start_matches('#').chars() {
+            match c {
+                '<' => in_tag = true,
+                '>' => in_tag = false,
+                c if !in_tag => title.push(c),
+                _ => {}
+            }
+        }
+        Some(title.trim().trim_end_matches('#').trim().to_string())
+    }
+}
+
+/// Split at Markdown (`#`, `##`, ...) or HTML (`<h1>`, `<h2>`, ...) headings
+/// of at most `max_level`, ignoring Markdown inside fenced code blocks;
+/// concatenating the sections' headings and bodies gives back `content`
+pub fn split_sections(content: &str, max_level: usize) -> Vec<Section> {
+    let mut sections = vec![Section { heading: None, body: String::new() }];
+    let mut in_fence = false;
+
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        }
+        let is_heading = !in_fence && heading_level(trimmed).is_some_and(|level| level <= max_level);
+
+        if is_heading {
+            let (heading, line_break) = match line.strip_suffix('\n') {
+                Some(heading) => (heading, "\n"),
+                None => (line, ""),
+            };
+            sections.push(Section { heading: Some(heading.to_string()), body: line_break.to_string() });
+        } else {
+            sections.last_mut().expect("there is always a section").body.push_str(line);
+        }
+    }
+
+    if sections[0].body.is_empty() && sections.len() > 1 {
+        sections.remove(0);
+    }
+    sections
+}
+
+pub fn join_sections(sections: &[Section]) -> String {
+    sections.iter()
+        .map(|section| format!("{}{}", section.heading.as_deref().unwrap_or_default(), section.body))
+        .collect()
+}
+
+fn heading_level(line: &str) -> Option<usize> {
+    let hashes = line.chars().take_while(|c| *c == '#').count();
+    if (1..=6).contains(&hashes) && line[hashes..].starts_with([' ', '\t']) {
+        return Some(hashes);
+    }
+
+    let lower = line.get(..4)?.to_ascii_lowercase();
+    let level = lower.strip_prefix("<h")?.chars().next()?.to_digit(10)? as usize;
+    ((1..=6).contains(&level) && matches!(lower.chars().nth(3), Some('>' | ' '))).then_some(level)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_sections() {
+        let content = "# Report\nIntro.\n\n## Companies\nAcme.\n```\n## not a heading\n```\n### Detail\nMore.\n<h2 class=\"x\">People</h2>\n<p>Jane</p>";
+        let sections = split_sections(content, 2);
+
+        let titles: Vec<_> = sections.iter().map(|s| s.title()).collect();
+        assert_eq!(titles, [Some("Report".to_string()), Some("Companies".to_string()), Some("People".to_string())]);
+        assert_eq!(sections[1].body, "\nAcme.\n```\n## not a heading\n```\n### Detail\nMore.\n");
+        assert_eq!(join_sections(&sections), content);
+
+        let preamble = split_sections("Dear team,\n# Update\n", 1);
+        assert_eq!(preamble[0], Section { heading: None, body: "Dear team,\n".into() });
+        assert_eq!(preamble.len(), 2);
+    }
+}
@@ -0,0 +1,395 @@
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashSet};
+
+use crate::error::{Error, Result};
+
+/// Declarative transform applied to query result rows before rendering
+///
+/// ```yaml
+/// transform:
+///   - { type: rename, from: name, to: company }
+///   - { type: label, columns: [company] }
+///   - { type: parse_date, column: founded, output: "%B %Y" }
+///   - { type: sort_by, column: founded, descending: true }
+///   - { type: limit, count: 10 }
+///   - { type: count, by: industry }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RowTransform {
+    /// Rename a column
+    Rename { from: String, to: String },
+    /// Replace URIs with their local name (`http://ex.org/Acme_Corp` → `Acme Corp`);
+    /// applies to every column when `columns` is empty
+    Label {
+        #[serde(default)]
+        columns: Vec<String>,
+    },
+    /// Parse a date column and reformat it; values that fail to parse are left as-is
+    ParseDate {
+        column: String,
+        /// chrono format of the input; RFC 3339 and `%Y-%m-%d` are tried when unset
+        #[serde(default)]
+        format: Option<String>,
+        #[serde(default = "default_date_output")]
+        output: String,
+    },
+    /// Replace the rows with counts, grouped by a column when `by` is set
+    Count {
+        #[serde(default)]
+        by: Option<String>,
+        #[serde(default = "default_count_column")]
+        column: String,
+    },
+    /// Rename several columns at once (`{ fields: { name: company, founded: year } }`)
+    Map { fields: BTreeMap<String, String> },
+    /// Sort rows by a column: numbers first, numerically, then other values
+    /// as text; rows without the column go last
+    SortBy {
+        column: String,
+        #[serde(default)]
+        descending: bool,
+    },
+    /// Keep at most `count` rows, after skipping `offset`
+    Limit {
+        count: usize,
+        #[serde(default)]
+        offset: usize,
+    },
+    /// Drop rows repeating an earlier row's values in `columns` (all columns when empty)
+    Distinct {
+        #[serde(default)]
+        columns: Vec<String>,
+    },
+    /// One row per value of `by`, holding the grouped rows (without `by`)
+    /// under `items`, in order of first appearance
+    GroupBy {
+        by: String,
+        #[serde(default = "default_items_column")]
+        items: String,
+    },
+    /// Replace the rows with the sum of a numeric column, grouped by a
+    /// column when `by` is set; values that are not numbers are skipped
+    Sum {
+        column: String,
+        #[serde(default)]
+        by: Option<String>,
+        /// Column holding the sum; defaults to `column`
+        #[serde(default)]
+        output: Option<String>,
+    },
+}
+
+fn default_date_output() -> String { "%Y-%m-%d".to_string() }
+fn default_count_column() -> String { "count".to_string() }
+fn default_items_column() -> String { "items".to_string() }
+
+pub type Row = Map<String, Value>;
+
+/// Apply transforms in order, once they have all been checked
+pub fn apply_transforms(rows: Vec<Row>, transforms: &[RowTransform]) -> Result<Vec<Row>> {
+    for transform in transforms {
+        transform.validate()?;
+    }
+    Ok(transforms.iter().fold(rows, |rows, transform| transform.apply(rows)))
+}
+
+impl RowTransform {
+    /// Reject settings that cannot be applied, such as an invalid date format
+    pub fn validate(&self) -> Result<()> {
+        if let RowTransform::ParseDate { column, output, .. } = self {
+            if StrftimeItems::new(output).any(|item| item == Item::Error) {
+                return Err(Error::template(format!("parse_date on '{}': invalid output format \"{}\"", column, output)));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn apply(&self, rows: Vec<Row>) -> Vec<Row> {
+        match self {
+            RowTransform::Rename { from, to } => rows.into_iter()
+                .map(|mut row| {
+                    if let Some(value) = row.remove(from) {
+                        row.insert(to.clone(), value);
+                    }
+                    row
+                })
+                .collect(),
+            RowTransform::Label { columns } => rows.into_iter()
+                .map(|mut row| {
+                    for (column, value) in row.iter_mut() {
+                        if !columns.is_empty() && !columns.contains(column) {
+                            continue;
+                        }
+                        if let Value::String(s) = value {
+                            *s = uri_label(s);
+                        }
+                    }
+                    row
+                })
+                .collect(),
+            RowTransform::ParseDate { column, format, output } => rows.into_iter()
+                .map(|mut row| {
+                    if let Some(Value::String(s)) = row.get_mut(column) {
+                        if let Some(text) = parse_date(s, format.as_deref()).and_then(|date| format_date(&date, output)) {
+                            *s = text;
+                        }
+                    }
+                    row
+                })
+                .collect(),
+            RowTransform::Count { by: None, column } => {
+                let mut row = Map::new();
+                row.insert(column.clone(), Value::from(rows.len()));
+                vec![row]
+            }
+            RowTransform::Count { by: Some(by), column } => {
+                let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+                for row in &rows {
+                    *counts.entry(group_key(row, by)).or_default() += 1;
+                }
+
+                counts.into_iter()
+                    .map(|(key, count)| {
+                        let mut row = Map::new();
+                        row.insert(by.clone(), Value::String(key));
+                        row.insert(column.clone(), Value::from(count));
+                        row
+                    })
+                    .collect()
+            }
+            RowTransform::Map { fields } => rows.into_iter()
+                .map(|row| row.into_iter()
+                    .map(|(column, value)| (fields.get(&column).cloned().unwrap_or(column), value))
+                    .collect())
+                .collect(),
+            RowTransform::SortBy { column, descending } => {
+                let mut rows = rows;
+                rows.sort_by(|a, b| match (a.get(column), b.get(column)) {
+                    (Some(a), Some(b)) if !a.is_null() && !b.is_null() => {
+                        let ordering = compare_values(a, b);
+                        if *descending { ordering.reverse() } else { ordering }
+                    }
+                    (a, b) => b.is_some_and(|v| !v.is_null()).cmp(&a.is_some_and(|v| !v.is_null())),
+                });
+                rows
+            }
+            RowTransform::Limit { count, offset } => rows.into_iter().skip(*offset).take(*count).collect(),
+            RowTransform::Distinct { columns } => {
+                let mut seen = HashSet::new();
+                rows.into_iter()
+                    .filter(|row| {
+                        let key: Vec<String> = if columns.is_empty() {
+                            row.iter().map(|(column, value)| format!("{}={}", column, value)).collect()
+                        } else {
+                            columns.iter().map(|column| row.get(column).map(Value::to_string).unwrap_or_default()).collect()
+                        };
+                        seen.insert(key)
+                    })
+                    .collect()
+            }
+            RowTransform::GroupBy { by, items } => {
+                let mut groups: Vec<(Value, Vec<Value>)> = Vec::new();
+                for mut row in rows {
+                    let key = row.remove(by).unwrap_or(Value::Null);
+                    match groups.iter_mut().find(|(existing, _)| *existing == key) {
+                        Some((_, members)) => members.push(Value::Object(row)),
+                        None => groups.push((key, vec![Value::Object(row)])),
+                    }
+                }
+
+                groups.into_iter()
+                    .map(|(key, members)| {
+                        let mut row = Map::new();
+                        row.insert(by.clone(), key);
+                        row.insert(items.clone(), Value::Array(members));
+                        row
+                    })
+                    .collect()
+            }
+            RowTransform::Sum { column, by, output } => {
+                let output = output.as_ref().unwrap_or(column);
+                let mut sums: BTreeMap<String, f64> = BTreeMap::new();
+                for row in &rows {
+                    let key = by.as_ref().map(|by| group_key(row, by)).unwrap_or_default();
+                    let sum = sums.entry(key).or_default();
+                    *sum += row.get(column).and_then(numeric).unwrap_or(0.0);
+                }
+                if by.is_none() && sums.is_empty() {
+                    sums.insert(String::new(), 0.0);
+                }
+
+                sums.into_iter()
+                    .map(|(key, sum)| {
+                        let mut row = Map::new();
+                        if let Some(by) = by {
+                            row.insert(by.clone(), Value::String(key));
+                        }
+                        row.insert(output.clone(), number(sum));
+                        row
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Text a row is grouped under for `by`; missing values group together
+fn group_key(row: &Row, by: &str) -> String {
+    match row.get(by) {
+        Some(Value::Null) | None => String::new(),
+        Some(value) => value_text(value),
+    }
+}
+
+/// Number held by a value, including numeric strings such as SPARQL literals
+fn numeric(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// JSON number for `value`, without a fraction when it is whole
+fn number(value: f64) -> Value {
+    if value.fract() == 0.0 && value.abs() < i64::MAX as f64 {
+        Value::from(value as i64)
+    } else {
+        serde_json::Number::from_f64(value).map(Value::Number).unwrap_or(Value::Null)
+    }
+}
+
+/// Total order for sorting: numbers first, by value, then everything else as text
+fn compare_values(a: &Value, b: &Value) -> Ordering {
+    match (numeric(a), numeric(b)) {
+        (Some(a), Some(b)) => a.total_cmp(&b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => value_text(a).cmp(&value_text(b)),
+    }
+}
+
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+pub(crate) fn uri_label(value: &str) -> String {
+    if !value.starts_with("http://") && !value.starts_with("https://") && !value.starts_with("urn:") {
+        return value.to_string();
+    }
+
+    let local = value.trim_end_matches(['/', '#'])
+        .rsplit(['/', '#', ':'])
+        .next()
+        .unwrap_or(value);
+    local.replace('_', " ")
+}
+
+pub(crate) fn parse_date(value: &str, format: Option<&str>) -> Option<NaiveDateTime> {
+    let value = value.trim();
+    if let Some(format) = format {
+        return NaiveDateTime::parse_from_str(value, format).ok()
+            .or_else(|| NaiveDate::parse_from_str(value, format).ok().and_then(|d| d.and_hms_opt(0, 0, 0)));
+    }
+
+    DateTime::parse_from_rfc3339(value).ok().map(|d| d.naive_utc())
+        .or_else(|| NaiveDate::parse_from_str(value, "%Y-%m-%d").ok().and_then(|d| d.and_hms_opt(0, 0, 0)))
+}
+
+/// `date` written with a strftime `format`; `None` when the format is invalid
+pub(crate) fn format_date(date: &NaiveDateTime, format: &str) -> Option<String> {
+    use std::fmt::Write;
+    let mut text = String::new();
+    write!(text, "{}", date.format(format)).ok()?;
+    Some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rows(values: Value) -> Vec<Row> {
+        values.as_array().unwrap().iter().map(|v| v.as_object().unwrap().clone()).collect()
+    }
+
+    #[test]
+    fn test_apply_transforms() {
+        let transforms: Vec<RowTransform> = serde_yaml::from_str(r#"
+- { type: rename, from: name, to: company }
+- { type: label, columns: [company] }
+- { type: parse_date, column: founded, output: "%B %Y" }
+"#).unwrap();
+
+        let result = apply_transforms(
+            rows(json!([{ "name": "http://ex.org/Acme_Corp", "founded": "1999-03-01" }])),
+            &transforms,
+        ).unwrap();
+        assert_eq!(Value::Object(result[0].clone()), json!({ "company": "Acme Corp", "founded": "March 1999" }));
+
+        let invalid: Vec<RowTransform> = serde_yaml::from_str(r#"[{ type: parse_date, column: founded, output: "%Q" }]"#).unwrap();
+        let error = apply_transforms(rows(json!([{ "founded": "1999-03-01" }])), &invalid).unwrap_err();
+        assert!(error.to_string().contains("invalid output format"));
+
+        let counted = RowTransform::Count { by: Some("industry".into()), column: "count".into() }.apply(rows(json!([
+            { "industry": "Tech" }, { "industry": "Retail" }, { "industry": "Tech" }
+        ])));
+        assert_eq!(Value::Array(counted.into_iter().map(Value::Object).collect()), json!([
+            { "industry": "Retail", "count": 1 },
+            { "industry": "Tech", "count": 2 }
+        ]));
+    }
+
+    #[test]
+    fn test_reshaping_transforms() {
+        let companies = || rows(json!([
+            { "name": "Globex", "industry": "Tech", "employees": "1200" },
+            { "name": "Acme", "industry": "Retail", "employees": 300 },
+            { "name": "Initech", "industry": "Tech", "employees": "80.5" },
+            { "name": "Acme", "industry": "Retail", "employees": 300 },
+            { "name": "Hooli", "industry": "Tech" },
+        ]));
+        let apply = |yaml: &str| {
+            let transforms: Vec<RowTransform> = serde_yaml::from_str(yaml).unwrap();
+            Value::Array(apply_transforms(companies(), &transforms).unwrap().into_iter().map(Value::Object).collect())
+        };
+
+        // Numeric strings sort as numbers; rows without the column go last
+        assert_eq!(apply("[{ type: distinct }, { type: sort_by, column: employees, descending: true }, { type: map, fields: { name: company } }]"), json!([
+            { "company": "Globex", "industry": "Tech", "employees": "1200" },
+            { "company": "Acme", "industry": "Retail", "employees": 300 },
+            { "company": "Initech", "industry": "Tech", "employees": "80.5" },
+            { "company": "Hooli", "industry": "Tech" },
+        ]));
+        assert_eq!(apply("[{ type: sort_by, column: name }, { type: distinct, columns: [name] }, { type: limit, count: 2, offset: 1 }]"), json!([
+            { "name": "Globex", "industry": "Tech", "employees": "1200" },
+            { "name": "Hooli", "industry": "Tech" },
+        ]));
+        assert_eq!(apply("[{ type: distinct }, { type: group_by, by: industry, items: companies }, { type: limit, count: 1 }]"), json!([
+            { "industry": "Tech", "companies": [
+                { "name": "Globex", "employees": "1200" },
+                { "name": "Initech", "employees": "80.5" },
+                { "name": "Hooli" },
+            ] },
+        ]));
+        assert_eq!(apply("[{ type: distinct }, { type: sum, column: employees, by: industry, output: headcount }]"), json!([
+            { "industry": "Retail", "headcount": 300 },
+            { "industry": "Tech", "headcount": 1280.5 },
+        ]));
+        assert_eq!(apply("[{ type: limit, count: 0 }, { type: sum, column: employees }]"), json!([{ "employees": 0 }]));
+
+        // Mixed numbers and text sort consistently: numbers first
+        let mixed = rows(json!([{ "v": "1a" }, { "v": 10 }, { "v": "b" }, { "v": "9" }, { "v": "10a" }, { "v": 2.5 }]));
+        let sorted = RowTransform::SortBy { column: "v".into(), descending: false }.apply(mixed);
+        let values: Vec<Value> = sorted.into_iter().map(|row| row["v"].clone()).collect();
+        assert_eq!(Value::Array(values), json!([2.5, "9", 10, "10a", "1a", "b"]));
+    }
+}
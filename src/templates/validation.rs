@@ -0,0 +1,109 @@
+use serde::Serialize;
+use std::collections::HashSet;
+
+use super::DataQuery;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ValidationIssue {
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// What a data query (or the batch query, with id `batch`) returned
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryOutcome {
+    Rows(usize),
+    Boolean(bool),
+    Failed(String),
+    /// Runs against a remote endpoint, which validation does not query
+    Remote,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryCheck {
+    pub id: String,
+    pub outcome: QueryOutcome,
+}
+
+/// Result of checking a template without rendering it for real
+#[derive(Debug, Clone, Serialize)]
+pub struct TemplateValidation {
+    pub template_id: String,
+    pub queries: Vec<QueryCheck>,
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl TemplateValidation {
+    pub fn new(template_id: &str) -> Self {
+        Self { template_id: template_id.to_string(), queries: Vec::new(), issues: Vec::new() }
+    }
+
+    /// No errors; warnings are allowed
+    pub fn is_valid(&self) -> bool {
+        self.issues.iter().all(|issue| issue.severity != Severity::Error)
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.issues.push(ValidationIssue { severity: Severity::Error, message: message.into() });
+    }
+
+    pub fn warning(&mut self, message: impl Into<String>) {
+        self.issues.push(ValidationIssue { severity: Severity::Warning, message: message.into() });
+    }
+
+    /// Record a query's outcome; a failure is an error for required queries,
+    /// and a failed optional query or an empty result set is a warning
+    pub fn add_query(&mut self, query: &DataQuery, outcome: QueryOutcome) {
+        match &outcome {
+            QueryOutcome::Failed(e) if query.required => self.error(format!("Required query '{}' failed: {}", query.id, e)),
+            QueryOutcome::Failed(e) => self.warning(format!("Optional query '{}' failed: {}", query.id, e)),
+            QueryOutcome::Rows(0) => self.warning(format!("Query '{}' returned no results", query.id)),
+            _ => {}
+        }
+        self.queries.push(QueryCheck { id: query.id.clone(), outcome });
+    }
+}
+
+/// Identifiers used inside `{{...}}` expressions, e.g. `companies` and
+/// `name` for `{{#each companies}}{{this.name}}`
+pub fn referenced_names(content: &str) -> HashSet<String> {
+    let mut names = HashSet::new();
+    let mut rest = content;
+
+    while let Some(start) = rest.find("{{") {
+        let expression = &rest[start + 2..];
+        let end = expression.find("}}").unwrap_or(expression.len());
+        names.extend(
+            expression[..end]
+                .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-'))
+                .filter(|token| !token.is_empty())
+                .map(str::to_string),
+        );
+        rest = &expression[end..];
+    }
+
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_referenced_names() {
+        let names = referenced_names("# {{title}}\n{{#each top_people}}- {{this.name}} ({{label ../company}}){{/each}} {{ x");
+        for name in ["title", "top_people", "name", "company", "label", "x"] {
+            assert!(names.contains(name), "{}", name);
+        }
+        assert!(!names.contains("#"));
+        assert!(referenced_names("no expressions").is_empty());
+    }
+}
@@ -0,0 +1,259 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::path::Path;
+
+use crate::core::RdfTriple;
+use crate::error::{Error, ErrorContext, Result};
+use crate::utils::canonicalize;
+use crate::utils::integrity::sha256_hex;
+
+/// Path of the graph inside a bundle
+pub const BUNDLE_GRAPH: &str = "graph.nq";
+/// Path of the manifest inside a bundle
+pub const BUNDLE_MANIFEST: &str = "manifest.json";
+
+/// Everything needed to reproduce a knowledge project in one zip: the graph
+/// as canonical N-Quads, the config, templates and the record of extraction
+/// jobs and generated documents, listed with their hashes in `manifest.json`
+pub struct ProjectBundle {
+    manifest: BundleManifest,
+    files: Vec<(String, Vec<u8>)>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleManifest {
+    pub generator: String,
+    pub created_at: DateTime<Utc>,
+    /// Name of the configuration the graph was extracted with
+    pub config_name: String,
+    pub triple_count: usize,
+    /// SHA-256 of the canonical N-Quads, independent of how the graph is stored
+    pub graph_hash: String,
+    /// Templates with documents in the generation history
+    #[serde(default)]
+    pub templates_used: Vec<String>,
+    pub files: Vec<BundleFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleFile {
+    /// Path inside the bundle
+    pub path: String,
+    pub bytes: u64,
+    pub sha256: String,
+}
+
+impl ProjectBundle {
+    /// Bundle holding `triples` as canonical N-Quads
+    pub fn new(config_name: &str, triples: &[RdfTriple]) -> Self {
+        let graph = canonicalize(triples, true);
+        let mut bundle = Self {
+            manifest: BundleManifest {
+                generator: format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+                created_at: Utc::now(),
+                config_name: config_name.to_string(),
+                triple_count: graph.lines().count(),
+                graph_hash: sha256_hex(graph.as_bytes()),
+                templates_used: Vec::new(),
+                files: Vec::new(),
+            },
+            files: Vec::new(),
+        };
+        bundle.add_file(BUNDLE_GRAPH, graph.into_bytes()).expect("valid bundle path");
+        bundle
+    }
+
+    /// Add `content` at `path`, a relative `/`-separated path that stays
+    /// inside the bundle when extracted
+    pub fn add_file(&mut self, path: &str, content: Vec<u8>) -> Result<()> {
+        check_entry_path(path)?;
+        self.manifest.files.push(BundleFile {
+            path: path.to_string(),
+            bytes: content.len() as u64,
+            sha256: sha256_hex(&content),
+        });
+        self.files.push((path.to_string(), content));
+        Ok(())
+    }
+
+    /// Add a file, or every file below a directory, under `prefix`; missing
+    /// paths are skipped. Returns the number of files added.
+    pub fn add_path(&mut self, prefix: &str, path: &Path) -> Result<usize> {
+        if path.is_file() {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            let content = std::fs::read(path)
+                .storage_context(|| format!("Failed to read {}", path.display()))?;
+            self.add_file(&format!("{}/{}", prefix, name), content)?;
+            return Ok(1);
+        }
+        if !path.is_dir() {
+            return Ok(0);
+        }
+
+        let mut entries: Vec<_> = walkdir::WalkDir::new(path)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file())
+            .collect();
+        entries.sort_by(|a, b| a.path().cmp(b.path()));
+
+        for entry in &entries {
+            let relative = entry.path().strip_prefix(path).unwrap_or(entry.path());
+            let relative: Vec<_> = relative.components().map(|c| c.as_os_str().to_string_lossy()).collect();
+            let content = std::fs::read(entry.path())
+                .storage_context(|| format!("Failed to read {}", entry.path().display()))?;
+            self.add_file(&format!("{}/{}", prefix, relative.join("/")), content)?;
+        }
+        Ok(entries.len())
+    }
+
+    pub fn set_templates_used(&mut self, template_ids: Vec<String>) {
+        self.manifest.templates_used = template_ids;
+    }
+
+    pub fn manifest(&self) -> &BundleManifest {
+        &self.manifest
+    }
+
+    /// Write the bundle as a zip, with the manifest as its last entry
+    pub fn write(&self, output: &Path) -> Result<()> {
+        let file = std::fs::File::create(output)
+            .storage_context(|| format!("Failed to create bundle: {}", output.display()))?;
+        let mut zip = zip::ZipWriter::new(file);
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated);
+
+        let manifest = serde_json::to_vec_pretty(&self.manifest)
+            .storage_context(|| "Failed to serialize bundle manifest")?;
+        let entries = self.files.iter().map(|(path, content)| (path.as_str(), content.as_slice()))
+            .chain(std::iter::once((BUNDLE_MANIFEST, manifest.as_slice())));
+        for (path, content) in entries {
+            zip.start_file(path, options)
+                .storage_context(|| format!("Failed to add {} to bundle", path))?;
+            zip.write_all(content)
+                .storage_context(|| format!("Failed to add {} to bundle", path))?;
+        }
+        zip.finish()
+            .storage_context(|| format!("Failed to write bundle: {}", output.display()))?;
+        Ok(())
+    }
+
+    /// Read the manifest of a bundle and check every file listed against
+    /// it, and that the bundle holds no other files
+    pub fn verify(bundle: &Path) -> Result<BundleVerification> {
+        let file = std::fs::File::open(bundle)
+            .storage_context(|| format!("Failed to open bundle: {}", bundle.display()))?;
+        let mut zip = zip::ZipArchive::new(file)
+            .storage_context(|| format!("Not a zip bundle: {}", bundle.display()))?;
+
+        let manifest: BundleManifest = {
+            let entry = zip.by_name(BUNDLE_MANIFEST)
+                .map_err(|_| Error::storage(format!("Bundle has no {}: {}", BUNDLE_MANIFEST, bundle.display())))?;
+            serde_json::from_reader(entry)
+                .storage_context(|| format!("Failed to parse bundle manifest: {}", bundle.display()))?
+        };
+
+        let mut mismatched = Vec::new();
+        for listed in &manifest.files {
+            let mut content = Vec::new();
+            let matches = check_entry_path(&listed.path).is_ok() && match zip.by_name(&listed.path) {
+                Ok(mut entry) => entry.read_to_end(&mut content).is_ok() && sha256_hex(&content) == listed.sha256,
+                Err(_) => false,
+            };
+            if !matches {
+                mismatched.push(listed.path.clone());
+            }
+        }
+
+        let unlisted = zip.file_names()
+            .filter(|name| *name != BUNDLE_MANIFEST && !manifest.files.iter().any(|listed| listed.path == *name))
+            .map(str::to_string)
+            .collect();
+        Ok(BundleVerification { manifest, mismatched, unlisted })
+    }
+}
+
+/// Outcome of [`ProjectBundle::verify`]
+#[derive(Debug)]
+pub struct BundleVerification {
+    pub manifest: BundleManifest,
+    /// Listed files missing from the bundle or with other content
+    pub mismatched: Vec<String>,
+    /// Files in the bundle its manifest does not list
+    pub unlisted: Vec<String>,
+}
+
+impl BundleVerification {
+    pub fn is_valid(&self) -> bool {
+        self.mismatched.is_empty() && self.unlisted.is_empty()
+    }
+}
+
+/// Fails unless `path` is relative, `/`-separated and without `.` or `..`
+/// segments, so extracting the bundle cannot write outside its directory
+fn check_entry_path(path: &str) -> Result<()> {
+    let valid = !path.starts_with('/')
+        && !path.contains(['\\', ':'])
+        && path.split('/').all(|segment| !matches!(segment, "" | "." | ".."));
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::storage(format!("Invalid path for a bundle file: {}", path)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_project_bundle() {
+        let dir = tempfile::tempdir().unwrap();
+        let templates = dir.path().join("templates");
+        std::fs::create_dir_all(templates.join("partials")).unwrap();
+        std::fs::write(templates.join("report.yaml"), "id: report").unwrap();
+        std::fs::write(templates.join("partials").join("header.hbs"), "# {{title}}").unwrap();
+
+        let triples = vec![
+            RdfTriple::new("http://ex.org/b".into(), "http://ex.org/p".into(), "B".into()),
+            RdfTriple::new("http://ex.org/a".into(), "http://ex.org/p".into(), "http://ex.org/b".into())
+                .with_graph("http://ex.org/g".into()),
+        ];
+        let mut bundle = ProjectBundle::new("Example", &triples);
+        assert_eq!(bundle.add_path("templates", &templates).unwrap(), 2);
+        assert_eq!(bundle.add_path("runs/jobs", &dir.path().join("missing")).unwrap(), 0);
+        bundle.set_templates_used(vec!["report".into()]);
+
+        let output = dir.path().join("project.zip");
+        bundle.write(&output).unwrap();
+
+        let verification = ProjectBundle::verify(&output).unwrap();
+        assert!(verification.is_valid());
+        let manifest = verification.manifest;
+        assert_eq!(manifest.triple_count, 2);
+        let paths: Vec<&str> = manifest.files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, [BUNDLE_GRAPH, "templates/partials/header.hbs", "templates/report.yaml"]);
+
+        // Paths leaving the bundle directory are refused, and so are files the manifest does not list
+        for path in ["../ontology.ttl", "config/../../x", "/etc/passwd", "C:\\x"] {
+            assert!(bundle.add_file(path, Vec::new()).is_err(), "{}", path);
+        }
+        let tampered = dir.path().join("tampered.zip");
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&tampered).unwrap());
+        let mut source = zip::ZipArchive::new(std::fs::File::open(&output).unwrap()).unwrap();
+        for i in 0..source.len() {
+            zip.raw_copy_file(source.by_index(i).unwrap()).unwrap();
+        }
+        zip.start_file("../evil.sh", zip::write::SimpleFileOptions::default()).unwrap();
+        zip.write_all(b"echo").unwrap();
+        zip.finish().unwrap();
+        let verification = ProjectBundle::verify(&tampered).unwrap();
+        assert!(!verification.is_valid());
+        assert_eq!(verification.unlisted, ["../evil.sh"]);
+
+        // The graph hash does not depend on the order triples are stored in
+        let reversed: Vec<RdfTriple> = triples.into_iter().rev().collect();
+        assert_eq!(ProjectBundle::new("Example", &reversed).manifest().graph_hash, manifest.graph_hash);
+    }
+}
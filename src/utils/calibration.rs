@@ -0,0 +1,186 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::config::RdfSchema;
+use crate::core::RdfTriple;
+use crate::utils::validate_rdf_triples;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalibrationBin {
+    pub lower: f32,
+    pub upper: f32,
+    pub count: usize,
+    pub correct: usize,
+    pub mean_confidence: f64,
+    pub accuracy: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalibrationReport {
+    /// "gold" when labels come from a gold set, "validation" otherwise
+    pub label_source: String,
+    pub total: usize,
+    pub correct: usize,
+    pub bins: Vec<CalibrationBin>,
+    pub expected_calibration_error: f64,
+    pub recommended_min_confidence: Option<f32>,
+}
+
+/// Label triples as correct when they appear in the gold set
+pub fn gold_labels(triples: &[RdfTriple], gold: &[RdfTriple]) -> Vec<bool> {
+    let gold_keys: HashSet<_> = gold.iter()
+        .map(|t| (t.subject.as_str(), t.predicate.as_str(), t.object.as_str()))
+        .collect();
+
+    triples.iter()
+        .map(|t| gold_keys.contains(&(t.subject.as_str(), t.predicate.as_str(), t.object.as_str())))
+        .collect()
+}
+
+/// Label triples as correct when they pass structural validation and use a
+/// predicate known to the schema
+pub fn validation_labels(triples: &[RdfTriple], schema: &RdfSchema) -> Vec<bool> {
+    triples.iter()
+        .map(|triple| {
+            let valid = validate_rdf_triples(std::slice::from_ref(triple)).is_empty();
+            let local_name = triple.predicate
+                .rsplit(['/', '#'])
+                .next()
+                .unwrap_or("");
+            valid && (schema.predicates.is_empty() || schema.predicates.contains_key(local_name))
+        })
+        .collect()
+}
+
+impl CalibrationReport {
+    pub fn new(triples: &[RdfTriple], labels: &[bool], num_bins: usize, label_source: &str) -> Self {
+        let num_bins = num_bins.max(1);
+        let width = 1.0 / num_bins as f32;
+
+        let mut bins: Vec<CalibrationBin> = (0..num_bins)
+            .map(|i| CalibrationBin {
+                lower: i as f32 * width,
+                upper: (i + 1) as f32 * width,
+                count: 0,
+                correct: 0,
+                mean_confidence: 0.0,
+                accuracy: 0.0,
+            })
+            .collect();
+
+        for (triple, &correct) in triples.iter().zip(labels) {
+            let confidence = triple.confidence.clamp(0.0, 1.0);
+            let index = ((confidence / width) as usize).min(num_bins - 1);
+            let bin = &mut bins[index];
+            bin.count += 1;
+            bin.mean_confidence += confidence as f64;
+            if correct {
+                bin.correct += 1;
+            }
+        }
+
+        let total = triples.len().min(labels.len());
+        let mut expected_calibration_error = 0.0;
+        for bin in &mut bins {
+            if bin.count > 0 {
+                bin.mean_confidence /= bin.count as f64;
+                bin.accuracy = bin.correct as f64 / bin.count as f64;
+                expected_calibration_error +=
+                    (bin.count as f64 / total as f64) * (bin.accuracy - bin.mean_confidence).abs();
+            }
+        }
+
+        Self {
+            label_source: label_source.to_string(),
+            total,
+            correct: labels.iter().take(total).filter(|&&c| c).count(),
+            bins,
+            expected_calibration_error,
+            recommended_min_confidence: recommend_threshold(triples, labels),
+        }
+    }
+}
+
+/// Pick the confidence threshold that maximizes F1 of the kept triples
+fn recommend_threshold(triples: &[RdfTriple], labels: &[bool]) -> Option<f32> {
+    let total_correct = labels.iter().filter(|&&c| c).count();
+    if total_correct == 0 {
+        return None;
+    }
+
+    let mut candidates: Vec<f32> = triples.iter().map(|t| t.confidence).collect();
+    candidates.sort_by(|a, b| a.total_cmp(b));
+    candidates.dedup();
+
+    let mut best: Option<(f64, f32)> = None;
+    for threshold in candidates {
+        let (kept, kept_correct) = triples.iter()
+            .zip(labels)
+            .filter(|(t, _)| t.confidence >= threshold)
+            .fold((0usize, 0usize), |(k, c), (_, &correct)| (k + 1, c + correct as usize));
+
+        if kept == 0 {
+            continue;
+        }
+
+        let precision = kept_correct as f64 / kept as f64;
+        let recall = kept_correct as f64 / total_correct as f64;
+        let f1 = if precision + recall > 0.0 {
+            2.0 * precision * recall / (precision + recall)
+        } else {
+            0.0
+        };
+
+        if best.is_none_or(|(best_f1, _)| f1 > best_f1) {
+            best = Some((f1, threshold));
+        }
+    }
+
+    best.map(|(_, threshold)| threshold)
+}
+
+impl std::fmt::Display for CalibrationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Confidence Calibration ({} labels)", self.label_source)?;
+        writeln!(f, "Triples: {} ({} correct)", self.total, self.correct)?;
+        writeln!(f, "\nConfidence     Count  Accuracy  Mean conf.")?;
+
+        for bin in &self.bins {
+            let bar = "#".repeat((bin.accuracy * 20.0).round() as usize);
+            writeln!(f,
+                "{:.2} - {:.2}  {:>6}  {:>8.2}  {:>10.2}  {}",
+                bin.lower, bin.upper, bin.count, bin.accuracy, bin.mean_confidence, bar
+            )?;
+        }
+
+        writeln!(f, "\nExpected calibration error: {:.4}", self.expected_calibration_error)?;
+        match self.recommended_min_confidence {
+            Some(threshold) => write!(f, "Recommended min_confidence: {:.2}", threshold),
+            None => write!(f, "Recommended min_confidence: n/a (no correct triples)"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calibration_report() {
+        let triples: Vec<RdfTriple> = [0.9, 0.8, 0.3, 0.2]
+            .iter()
+            .enumerate()
+            .map(|(i, &c)| RdfTriple::new(
+                format!("http://ex.org/s{}", i),
+                "http://ex.org/p".to_string(),
+                "o".to_string(),
+            ).with_confidence(c))
+            .collect();
+        let labels = vec![true, true, false, false];
+
+        let report = CalibrationReport::new(&triples, &labels, 5, "gold");
+        assert_eq!(report.correct, 2);
+        assert_eq!(report.bins.iter().map(|b| b.count).sum::<usize>(), 4);
+        assert_eq!(report.recommended_min_confidence, Some(0.8));
+    }
+}
@@ -0,0 +1,381 @@
+//! Canonical N-Triples / N-Quads, so that the same graph always serializes
+//! to the same bytes
+//!
+//! Literals use the canonical escaping of RDF 1.1 N-Triples, language tags
+//! are lowercased, plain strings drop the implicit `xsd:string` datatype,
+//! and blank nodes are relabelled
+//! `_:c14n0`, `_:c14n1`, ... with the RDF Dataset Canonicalization
+//! algorithm (RDFC-1.0, https://www.w3.org/TR/rdf-canon/). Lines are sorted
+//! and deduplicated.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::core::RdfTriple;
+use crate::utils::integrity::sha256_hex;
+
+const XSD_STRING: &str = "http://www.w3.org/2001/XMLSchema#string";
+
+/// Canonical N-Quads for `triples`, or canonical N-Triples when
+/// `with_graphs` is false
+pub fn canonicalize(triples: &[RdfTriple], with_graphs: bool) -> String {
+    let quads: Vec<Quad> = triples.iter()
+        .map(|triple| Quad {
+            subject: Term::resource(&triple.subject),
+            predicate: triple.predicate.clone(),
+            object: Term::object(triple),
+            graph: triple.graph.as_deref().filter(|_| with_graphs).map(Term::resource),
+        })
+        .collect();
+
+    let labels = Canonicalizer::new(&quads).run();
+    let mut lines: Vec<String> = quads.iter()
+        .map(|quad| quad.to_line(&|label| format!("_:{}", labels[label])))
+        .collect();
+    lines.sort();
+    lines.dedup();
+    lines.concat()
+}
+
+#[derive(Debug, Clone)]
+enum Term {
+    Iri(String),
+    Blank(String),
+    Literal(String),
+}
+
+impl Term {
+    fn resource(value: &str) -> Self {
+        match value.strip_prefix("_:") {
+            Some(label) => Term::Blank(label.to_string()),
+            None => Term::Iri(value.to_string()),
+        }
+    }
+
+    fn object(triple: &RdfTriple) -> Self {
+        let value = &triple.object;
+        if value.starts_with("http://") || value.starts_with("https://") || value.starts_with("_:") {
+            return Term::resource(value);
+        }
+
+        let escaped = escape(value);
+        let literal = match (triple.metadata.get("lang"), triple.metadata.get("datatype")) {
+            (Some(lang), _) => format!("\"{}\"@{}", escaped, lang.to_lowercase()),
+            (None, Some(datatype)) if datatype != XSD_STRING => format!("\"{}\"^^<{}>", escaped, datatype),
+            _ => format!("\"{}\"", escaped),
+        };
+        Term::Literal(literal)
+    }
+
+    fn blank(&self) -> Option<&str> {
+        match self {
+            Term::Blank(label) => Some(label),
+            _ => None,
+        }
+    }
+
+    fn write(&self, out: &mut String, blank: &dyn Fn(&str) -> String) {
+        match self {
+            Term::Iri(iri) => {
+                out.push('<');
+                out.push_str(iri);
+                out.push('>');
+            }
+            Term::Blank(label) => out.push_str(&blank(label)),
+            Term::Literal(literal) => out.push_str(literal),
+        }
+    }
+}
+
+/// Canonical string escaping: `ECHAR` for backspace, tab, line feed, form
+/// feed, carriage return, `"` and `\`, `UCHAR` for the other control
+/// characters, everything else as is
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\u{8}' => escaped.push_str("\\b"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\u{c}' => escaped.push_str("\\f"),
+            '\r' => escaped.push_str("\\r"),
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\u{0}'..='\u{1f}' | '\u{7f}' => escaped.push_str(&format!("\\u{:04X}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+struct Quad {
+    subject: Term,
+    predicate: String,
+    object: Term,
+    graph: Option<Term>,
+}
+
+impl Quad {
+    /// The quad as an N-Quads line, writing blank nodes with `blank`
+    fn to_line(&self, blank: &dyn Fn(&str) -> String) -> String {
+        let mut line = String::new();
+        self.subject.write(&mut line, blank);
+        line.push_str(" <");
+        line.push_str(&self.predicate);
+        line.push_str("> ");
+        self.object.write(&mut line, blank);
+        if let Some(graph) = &self.graph {
+            line.push(' ');
+            graph.write(&mut line, blank);
+        }
+        line.push_str(" .\n");
+        line
+    }
+
+    /// Blank nodes of the quad with their position: `s`, `o` or `g`
+    fn blanks(&self) -> impl Iterator<Item = (&str, &'static str)> {
+        [(Some(&self.subject), "s"), (Some(&self.object), "o"), (self.graph.as_ref(), "g")]
+            .into_iter()
+            .filter_map(|(term, position)| term.and_then(Term::blank).map(|label| (label, position)))
+    }
+}
+
+/// Issues identifiers with a prefix and counter, remembering their order
+#[derive(Clone)]
+struct IdentifierIssuer {
+    prefix: &'static str,
+    issued: HashMap<String, String>,
+    order: Vec<String>,
+}
+
+impl IdentifierIssuer {
+    fn new(prefix: &'static str) -> Self {
+        Self { prefix, issued: HashMap::new(), order: Vec::new() }
+    }
+
+    fn issue(&mut self, label: &str) -> String {
+        if let Some(id) = self.issued.get(label) {
+            return id.clone();
+        }
+        let id = format!("{}{}", self.prefix, self.order.len());
+        self.issued.insert(label.to_string(), id.clone());
+        self.order.push(label.to_string());
+        id
+    }
+
+    fn get(&self, label: &str) -> Option<&String> {
+        self.issued.get(label)
+    }
+}
+
+struct Canonicalizer<'a> {
+    quads: &'a [Quad],
+    /// Quads mentioning each blank node
+    blank_quads: HashMap<&'a str, Vec<&'a Quad>>,
+    canonical: IdentifierIssuer,
+    first_degree: HashMap<&'a str, String>,
+}
+
+impl<'a> Canonicalizer<'a> {
+    fn new(quads: &'a [Quad]) -> Self {
+        let mut blank_quads: HashMap<&str, Vec<&Quad>> = HashMap::new();
+        for quad in quads {
+            for (label, _) in quad.blanks() {
+                let entry = blank_quads.entry(label).or_default();
+                if !entry.iter().any(|q| std::ptr::eq(*q, quad)) {
+                    entry.push(quad);
+                }
+            }
+        }
+        Self { quads, blank_quads, canonical: IdentifierIssuer::new("c14n"), first_degree: HashMap::new() }
+    }
+
+    /// Canonical identifier of every blank node label
+    fn run(mut self) -> HashMap<String, String> {
+        let mut labels: Vec<&str> = self.blank_quads.keys().copied().collect();
+        labels.sort_unstable();
+
+        let mut by_hash: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+        for label in labels {
+            let hash = self.hash_first_degree(label);
+            by_hash.entry(hash).or_default().push(label);
+        }
+
+        // Unique first-degree hashes get their identifiers in hash order
+        let mut shared = Vec::new();
+        for (_, labels) in by_hash {
+            match labels.as_slice() {
+                [label] => {
+                    self.canonical.issue(label);
+                }
+                _ => shared.push(labels),
+            }
+        }
+
+        for labels in shared {
+            let mut paths = Vec::new();
+            for label in labels {
+                if self.canonical.get(label).is_some() {
+                    continue;
+                }
+                let mut issuer = IdentifierIssuer::new("b");
+                issuer.issue(label);
+                paths.push(self.hash_n_degree(label, issuer));
+            }
+            paths.sort_by(|a, b| a.0.cmp(&b.0));
+            for (_, issuer) in paths {
+                for label in &issuer.order {
+                    self.canonical.issue(label);
+                }
+            }
+        }
+
+        debug_assert!(self.quads.iter().all(|q| q.blanks().all(|(label, _)| self.canonical.get(label).is_some())));
+        self.canonical.issued
+    }
+
+    fn hash_first_degree(&mut self, label: &'a str) -> String {
+        if let Some(hash) = self.first_degree.get(label) {
+            return hash.clone();
+        }
+
+        let mut lines: Vec<String> = self.blank_quads[label].iter()
+            .map(|quad| quad.to_line(&|other| if other == label { "_:a".to_string() } else { "_:z".to_string() }))
+            .collect();
+        lines.sort();
+        let hash = sha256_hex(lines.concat().as_bytes());
+        self.first_degree.insert(label, hash.clone());
+        hash
+    }
+
+    fn hash_related(&mut self, related: &'a str, quad: &Quad, issuer: &IdentifierIssuer, position: &str) -> String {
+        let mut input = position.to_string();
+        if position != "g" {
+            input.push('<');
+            input.push_str(&quad.predicate);
+            input.push('>');
+        }
+        match self.canonical.get(related).or_else(|| issuer.get(related)) {
+            Some(id) => {
+                input.push_str("_:");
+                input.push_str(id);
+            }
+            None => input.push_str(&self.hash_first_degree(related)),
+        }
+        sha256_hex(input.as_bytes())
+    }
+
+    fn hash_n_degree(&mut self, label: &'a str, mut issuer: IdentifierIssuer) -> (String, IdentifierIssuer) {
+        let mut related_by_hash: BTreeMap<String, Vec<&'a str>> = BTreeMap::new();
+        let quads = self.blank_quads[label].clone();
+        for quad in quads {
+            for (related, position) in quad.blanks() {
+                if related != label {
+                    let hash = self.hash_related(related, quad, &issuer, position);
+                    related_by_hash.entry(hash).or_default().push(related);
+                }
+            }
+        }
+
+        let mut data = String::new();
+        for (hash, related) in related_by_hash {
+            data.push_str(&hash);
+            let mut chosen: Option<(String, IdentifierIssuer)> = None;
+
+            'permutations: for permutation in permutations(&related) {
+                let mut issuer_copy = issuer.clone();
+                let mut path = String::new();
+                let mut recursion = Vec::new();
+
+                for node in &permutation {
+                    match self.canonical.get(node) {
+                        Some(id) => path.push_str(&format!("_:{}", id)),
+                        None => {
+                            if issuer_copy.get(node).is_none() {
+                                recursion.push(*node);
+                            }
+                            path.push_str(&format!("_:{}", issuer_copy.issue(node)));
+                        }
+                    }
+                    if worse(&path, chosen.as_ref()) {
+                        continue 'permutations;
+                    }
+                }
+
+                for node in recursion {
+                    let (hash, result_issuer) = self.hash_n_degree(node, issuer_copy.clone());
+                    path.push_str(&format!("_:{}", issuer_copy.issue(node)));
+                    path.push_str(&format!("<{}>", hash));
+                    issuer_copy = result_issuer;
+                    if worse(&path, chosen.as_ref()) {
+                        continue 'permutations;
+                    }
+                }
+
+                if chosen.as_ref().is_none_or(|(chosen_path, _)| path < *chosen_path) {
+                    chosen = Some((path, issuer_copy));
+                }
+            }
+
+            if let Some((path, chosen_issuer)) = chosen {
+                data.push_str(&path);
+                issuer = chosen_issuer;
+            }
+        }
+
+        (sha256_hex(data.as_bytes()), issuer)
+    }
+}
+
+/// Whether a partial path can no longer beat the chosen one
+fn worse(path: &str, chosen: Option<&(String, IdentifierIssuer)>) -> bool {
+    chosen.is_some_and(|(chosen, _)| path.len() >= chosen.len() && path > chosen.as_str())
+}
+
+fn permutations<'a>(items: &[&'a str]) -> Vec<Vec<&'a str>> {
+    if items.len() <= 1 {
+        return vec![items.to_vec()];
+    }
+    let mut result = Vec::new();
+    for i in 0..items.len() {
+        let mut rest = items.to_vec();
+        let first = rest.remove(i);
+        for mut permutation in permutations(&rest) {
+            permutation.insert(0, first);
+            result.push(permutation);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn triple(s: &str, p: &str, o: &str) -> RdfTriple {
+        RdfTriple::new(s.to_string(), p.to_string(), o.to_string())
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        let p = "http://ex.org/p";
+        let mut literal = triple("http://ex.org/s", p, "tab\there \"quoted\"\u{1}");
+        literal.metadata.insert("datatype".to_string(), XSD_STRING.to_string());
+        let mut tagged = triple("http://ex.org/s", p, "hallo");
+        tagged.metadata.insert("lang".to_string(), "DE".to_string());
+
+        let nt = canonicalize(&[tagged.clone(), literal.clone(), literal.with_graph("http://ex.org/g".to_string())], false);
+        assert_eq!(nt, "<http://ex.org/s> <http://ex.org/p> \"hallo\"@de .\n<http://ex.org/s> <http://ex.org/p> \"tab\\there \\\"quoted\\\"\\u0001\" .\n");
+
+        // A blank node cycle: every node has the same first-degree hash, so
+        // only n-degree hashing can order them
+        let cycle = |labels: [&str; 3]| (0..3)
+            .map(|i| triple(&format!("_:{}", labels[i]), p, &format!("_:{}", labels[(i + 1) % 3])))
+            .chain([triple(&format!("_:{}", labels[0]), "http://ex.org/q", "http://ex.org/o")])
+            .collect::<Vec<_>>();
+        let first = canonicalize(&cycle(["e0", "e1", "e2"]), true);
+        assert_eq!(first, canonicalize(&cycle(["zz", "aa", "mm"]), true));
+        assert_eq!(first, canonicalize(&cycle(["e0", "e1", "e2"]).into_iter().rev().collect::<Vec<_>>(), true));
+        assert_eq!(first.lines().filter(|line| line.contains("_:c14n")).count(), 4);
+        assert!(!first.contains("_:e0"));
+    }
+}
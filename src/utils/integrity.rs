@@ -0,0 +1,317 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use chrono::{DateTime, Utc};
+use ed25519_dalek::pkcs8::{DecodePrivateKey, DecodePublicKey};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+use crate::config::OutputFormat;
+use crate::core::RdfTriple;
+use crate::error::{Error, ErrorContext, Result};
+use crate::utils::parse_rdf;
+use crate::utils::serialization::{ntriples_object, ntriples_resource};
+
+/// Integrity manifest written next to an export: the SHA-256 of each file,
+/// its triple count and a canonical graph hash
+///
+/// The graph hash is the SHA-256 of the file's statements as sorted,
+/// deduplicated N-Quads lines, so it is independent of statement order and
+/// serialization. Formats that cannot carry named graphs are hashed without
+/// them. Blank node labels are hashed as written.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportManifest {
+    pub generator: String,
+    pub created_at: DateTime<Utc>,
+    pub files: Vec<ManifestFile>,
+    pub triple_count: usize,
+    /// Canonical hash over the statements of all files
+    pub graph_hash: String,
+    /// Base64 Ed25519 public key of the detached signature, if signed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signing_key: Option<String>,
+    #[serde(skip)]
+    lines: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestFile {
+    /// Path relative to the manifest
+    pub path: String,
+    pub format: OutputFormat,
+    pub bytes: u64,
+    pub sha256: String,
+    pub triples: usize,
+    pub graph_hash: String,
+}
+
+/// Result of checking an export against its manifest
+#[derive(Debug, Clone)]
+pub struct Verification {
+    pub files: Vec<FileCheck>,
+    pub signature: SignatureCheck,
+}
+
+#[derive(Debug, Clone)]
+pub struct FileCheck {
+    pub path: String,
+    pub sha256_matches: bool,
+    /// `None` when the file is missing or its format cannot be parsed back
+    pub graph_hash_matches: Option<bool>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignatureCheck {
+    Valid,
+    Invalid(String),
+    /// The manifest has no signature
+    Unsigned,
+    /// Signed, but no public key was given to check it against
+    NotChecked,
+}
+
+impl Verification {
+    /// Every file matches and the signature, if checked, is valid
+    pub fn is_valid(&self) -> bool {
+        self.files.iter().all(|f| f.sha256_matches && f.graph_hash_matches != Some(false))
+            && !matches!(self.signature, SignatureCheck::Invalid(_))
+    }
+}
+
+impl Default for ExportManifest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExportManifest {
+    pub fn new() -> Self {
+        Self {
+            generator: format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+            created_at: Utc::now(),
+            files: Vec::new(),
+            triple_count: 0,
+            graph_hash: graph_hash(Vec::new()),
+            signing_key: None,
+            lines: Vec::new(),
+        }
+    }
+
+    /// Manifest path for an export file: `<export>.manifest.json`
+    pub fn path_for(export: &Path) -> PathBuf {
+        let mut name = export.file_name().unwrap_or_default().to_os_string();
+        name.push(".manifest.json");
+        export.with_file_name(name)
+    }
+
+    /// Detached signature path for a manifest: `<manifest>.sig`
+    pub fn signature_path(manifest: &Path) -> PathBuf {
+        let mut name = manifest.file_name().unwrap_or_default().to_os_string();
+        name.push(".sig");
+        manifest.with_file_name(name)
+    }
+
+    /// Record an export file holding `triples` in `format`. Both hashes are
+    /// taken from the file as written, read back the way `verify` reads it;
+    /// `triples` only stand in for formats that cannot be parsed back.
+    pub fn add_file(&mut self, path: &Path, format: &OutputFormat, triples: &[RdfTriple]) -> Result<()> {
+        let content = std::fs::read(path)
+            .storage_context(|| format!("Failed to read export: {}", path.display()))?;
+        let written = file_triples(&content, format);
+        let written = written.as_deref().unwrap_or(triples);
+        let lines = canonical_lines(written, format);
+
+        self.files.push(ManifestFile {
+            path: path.file_name().unwrap_or_default().to_string_lossy().to_string(),
+            format: format.clone(),
+            bytes: content.len() as u64,
+            sha256: sha256_hex(&content),
+            triples: written.len(),
+            graph_hash: graph_hash(lines.clone()),
+        });
+        self.triple_count += written.len();
+        self.lines.extend(lines);
+        self.graph_hash = graph_hash(self.lines.clone());
+        Ok(())
+    }
+
+    /// Write the manifest as JSON
+    pub fn write(&self, manifest_path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)
+            .storage_context(|| "Failed to serialize export manifest")?;
+        std::fs::write(manifest_path, json)
+            .storage_context(|| format!("Failed to write manifest: {}", manifest_path.display()))
+    }
+
+    /// Write the manifest and a detached Ed25519 signature of it, using a
+    /// PKCS#8 PEM private key (`openssl genpkey -algorithm ed25519`)
+    ///
+    /// The signature file holds the raw 64-byte signature, so it can also be
+    /// checked with `openssl pkeyutl -verify -rawin`.
+    pub fn write_signed(&mut self, manifest_path: &Path, private_key_pem: &Path) -> Result<PathBuf> {
+        let pem = std::fs::read_to_string(private_key_pem)
+            .config_context(|| format!("Failed to read signing key: {}", private_key_pem.display()))?;
+        let key = SigningKey::from_pkcs8_pem(&pem)
+            .map_err(|e| Error::config(format!("Invalid Ed25519 signing key {}: {}", private_key_pem.display(), e)))?;
+
+        self.signing_key = Some(STANDARD.encode(key.verifying_key().as_bytes()));
+        self.write(manifest_path)?;
+
+        let content = std::fs::read(manifest_path)
+            .storage_context(|| format!("Failed to read manifest: {}", manifest_path.display()))?;
+        let signature_path = Self::signature_path(manifest_path);
+        std::fs::write(&signature_path, key.sign(&content).to_bytes())
+            .storage_context(|| format!("Failed to write signature: {}", signature_path.display()))?;
+        Ok(signature_path)
+    }
+
+    /// Check the files listed in a manifest, and its signature against a
+    /// PKCS#8 PEM public key when one is given
+    pub fn verify(manifest_path: &Path, public_key_pem: Option<&Path>) -> Result<Verification> {
+        let content = std::fs::read(manifest_path)
+            .storage_context(|| format!("Failed to read manifest: {}", manifest_path.display()))?;
+        let manifest: Self = serde_json::from_slice(&content)
+            .storage_context(|| format!("Failed to parse manifest: {}", manifest_path.display()))?;
+
+        let dir = manifest_path.parent().unwrap_or(Path::new("."));
+        let files = manifest.files.iter().map(|file| check_file(dir, file)).collect();
+        let signature = check_signature(manifest_path, &content, public_key_pem)?;
+
+        Ok(Verification { files, signature })
+    }
+}
+
+fn check_file(dir: &Path, file: &ManifestFile) -> FileCheck {
+    let Ok(content) = std::fs::read(dir.join(&file.path)) else {
+        return FileCheck { path: file.path.clone(), sha256_matches: false, graph_hash_matches: None };
+    };
+
+    let triples = file_triples(&content, &file.format);
+    FileCheck {
+        path: file.path.clone(),
+        sha256_matches: sha256_hex(&content) == file.sha256,
+        graph_hash_matches: triples.map(|triples| graph_hash(canonical_lines(&triples, &file.format)) == file.graph_hash),
+    }
+}
+
+fn check_signature(manifest_path: &Path, content: &[u8], public_key_pem: Option<&Path>) -> Result<SignatureCheck> {
+    let Ok(signature) = std::fs::read(ExportManifest::signature_path(manifest_path)) else {
+        return Ok(SignatureCheck::Unsigned);
+    };
+    let Some(public_key_pem) = public_key_pem else {
+        return Ok(SignatureCheck::NotChecked);
+    };
+
+    let pem = std::fs::read_to_string(public_key_pem)
+        .config_context(|| format!("Failed to read public key: {}", public_key_pem.display()))?;
+    let key = VerifyingKey::from_public_key_pem(&pem)
+        .map_err(|e| Error::config(format!("Invalid Ed25519 public key {}: {}", public_key_pem.display(), e)))?;
+
+    let Ok(signature) = Signature::from_slice(&signature) else {
+        return Ok(SignatureCheck::Invalid("malformed signature".to_string()));
+    };
+    Ok(match key.verify(content, &signature) {
+        Ok(()) => SignatureCheck::Valid,
+        Err(e) => SignatureCheck::Invalid(e.to_string()),
+    })
+}
+
+/// Triples of an export file, when its format can be parsed back
+fn file_triples(content: &[u8], format: &OutputFormat) -> Option<Vec<RdfTriple>> {
+    match format {
+        OutputFormat::Json => serde_json::from_slice(content).ok(),
+        _ => parse_rdf(&String::from_utf8_lossy(content), format).ok(),
+    }
+}
+
+pub(crate) fn sha256_hex(content: &[u8]) -> String {
+    Sha256::digest(content).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn graph_hash(mut lines: Vec<String>) -> String {
+    lines.sort();
+    lines.dedup();
+    sha256_hex(lines.concat().as_bytes())
+}
+
+/// One N-Quads line per triple; the graph is dropped for formats that
+/// cannot carry it
+fn canonical_lines(triples: &[RdfTriple], format: &OutputFormat) -> Vec<String> {
+    let with_graph = matches!(format, OutputFormat::NQuads | OutputFormat::TriG | OutputFormat::Json);
+    triples.iter()
+        .map(|triple| {
+            let graph = triple.graph.as_deref()
+                .filter(|_| with_graph)
+                .map(|graph| format!(" {}", ntriples_resource(graph)))
+                .unwrap_or_default();
+            format!("{} <{}> {}{} .\n", ntriples_resource(&triple.subject), triple.predicate, ntriples_object(triple), graph)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::pkcs8::{EncodePrivateKey, EncodePublicKey};
+    use ed25519_dalek::pkcs8::spki::der::pem::LineEnding;
+
+    #[test]
+    fn test_signed_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let export = dir.path().join("graph.nt");
+        std::fs::write(&export, "<http://ex.org/b> <http://ex.org/p> \"B\" .\n<http://ex.org/a> <http://ex.org/p> <http://ex.org/b> .\n").unwrap();
+        let triples = parse_rdf(&std::fs::read_to_string(&export).unwrap(), &OutputFormat::NTriples).unwrap();
+
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let private_key = dir.path().join("key.pem");
+        let public_key = dir.path().join("key.pub.pem");
+        std::fs::write(&private_key, key.to_pkcs8_pem(LineEnding::LF).unwrap().as_bytes()).unwrap();
+        std::fs::write(&public_key, key.verifying_key().to_public_key_pem(LineEnding::LF).unwrap()).unwrap();
+
+        let mut manifest = ExportManifest::new();
+        manifest.add_file(&export, &OutputFormat::NTriples, &triples).unwrap();
+        let manifest_path = ExportManifest::path_for(&export);
+        manifest.write_signed(&manifest_path, &private_key).unwrap();
+        assert_eq!(manifest.triple_count, 2);
+
+        let reversed: Vec<RdfTriple> = triples.iter().rev().cloned().collect();
+        assert_eq!(graph_hash(canonical_lines(&reversed, &OutputFormat::Turtle)), manifest.graph_hash);
+
+        let verification = ExportManifest::verify(&manifest_path, Some(&public_key)).unwrap();
+        assert!(verification.is_valid());
+        assert_eq!(verification.signature, SignatureCheck::Valid);
+        assert_eq!(verification.files[0].graph_hash_matches, Some(true));
+
+        std::fs::write(&export, "<http://ex.org/a> <http://ex.org/p> \"tampered\" .\n").unwrap();
+        let verification = ExportManifest::verify(&manifest_path, None).unwrap();
+        assert!(!verification.is_valid());
+        assert_eq!(verification.signature, SignatureCheck::NotChecked);
+    }
+
+    #[test]
+    fn test_manifest_of_tagged_literals() {
+        let mut label = RdfTriple::new("http://ex.org/a".into(), "http://ex.org/label".into(), "Acme \"AG\"\nBerlin \\ HQ".into());
+        label.metadata.insert("lang".into(), "de".into());
+        let mut founded = RdfTriple::new("http://ex.org/a".into(), "http://ex.org/founded".into(), "1999".into());
+        founded.metadata.insert("datatype".into(), "http://www.w3.org/2001/XMLSchema#gYear".into());
+        let triples = [label, founded.with_graph("http://ex.org/g".into())];
+
+        let dir = tempfile::tempdir().unwrap();
+        for (name, format) in [("a.nt", OutputFormat::NTriples), ("a.ttl", OutputFormat::Turtle), ("a.nq", OutputFormat::NQuads), ("a.trig", OutputFormat::TriG)] {
+            let export = dir.path().join(name);
+            let content = crate::utils::RdfSerializer::new().serialize(&triples, &format, "http://ex.org/", "ex").unwrap();
+            std::fs::write(&export, &content).unwrap();
+            let parsed = parse_rdf(&content, &format).unwrap();
+            assert_eq!(parsed[0].object, triples[0].object, "{}", name);
+            assert_eq!(parsed[0].metadata["lang"], "de", "{}", name);
+            assert_eq!(parsed[1].metadata["datatype"], "http://www.w3.org/2001/XMLSchema#gYear", "{}", name);
+
+            let mut manifest = ExportManifest::new();
+            manifest.add_file(&export, &format, &triples).unwrap();
+            let manifest_path = ExportManifest::path_for(&export);
+            manifest.write(&manifest_path).unwrap();
+            assert!(ExportManifest::verify(&manifest_path, None).unwrap().is_valid(), "{}", name);
+        }
+    }
+}
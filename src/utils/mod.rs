@@ -1,3 +1,21 @@
 pub mod serialization;
+pub mod parsing;
+pub mod calibration;
+pub mod integrity;
+pub mod visualization;
+pub mod canonical;
+pub mod paths;
+pub mod bundle;
+pub mod progress;
+pub mod spreadsheet;
 
-pub use serialization::{RdfSerializer, validate_rdf_triples};
\ No newline at end of file
+pub use serialization::{RdfSerializer, validate_rdf_triples};
+pub use parsing::parse_rdf;
+pub use calibration::CalibrationReport;
+pub use integrity::{ExportManifest, SignatureCheck, Verification};
+pub use visualization::VisualFormat;
+pub use canonical::canonicalize;
+pub use paths::normalize_source;
+pub use bundle::{BundleManifest, BundleVerification, ProjectBundle};
+pub use progress::{Progress, ProgressMode};
+pub use spreadsheet::Spreadsheet;
@@ -0,0 +1,98 @@
+use rio_api::model::{GraphName, Literal, Subject, Term};
+use rio_api::parser::{QuadsParser, TriplesParser};
+use rio_turtle::{NQuadsParser, NTriplesParser, TriGParser, TurtleError, TurtleParser};
+
+use crate::config::OutputFormat;
+use crate::core::RdfTriple;
+use crate::error::{Error, ErrorContext, Result};
+
+/// Parse Turtle, N-Triples, N-Quads or TriG into triples
+///
+/// Literals keep their lexical form; a language tag or non-string datatype
+/// is recorded in the triple's `metadata` as `lang` or `datatype`. Blank
+/// nodes become `_:label`. RDF-star quoted triples are rejected.
+pub fn parse_rdf(content: &str, format: &OutputFormat) -> Result<Vec<RdfTriple>> {
+    let mut triples = Vec::new();
+
+    let parsed = match format {
+        OutputFormat::Turtle => TurtleParser::new(content.as_bytes(), None)
+            .parse_all(&mut |t| push(&mut triples, t.subject, t.predicate.iri, t.object, None)),
+        OutputFormat::NTriples => NTriplesParser::new(content.as_bytes())
+            .parse_all(&mut |t| push(&mut triples, t.subject, t.predicate.iri, t.object, None)),
+        OutputFormat::NQuads => NQuadsParser::new(content.as_bytes())
+            .parse_all(&mut |q| push(&mut triples, q.subject, q.predicate.iri, q.object, q.graph_name)),
+        OutputFormat::TriG => TriGParser::new(content.as_bytes(), None)
+            .parse_all(&mut |q| push(&mut triples, q.subject, q.predicate.iri, q.object, q.graph_name)),
+        other => return Err(Error::storage(format!("Parsing {:?} is not supported", other))),
+    };
+
+    parsed.storage_context(|| format!("Failed to parse {:?} input", format))?;
+    Ok(triples)
+}
+
+fn push(
+    triples: &mut Vec<RdfTriple>,
+    subject: Subject,
+    predicate: &str,
+    object: Term,
+    graph: Option<GraphName>,
+) -> std::result::Result<(), TurtleError> {
+    let subject = match subject {
+        Subject::NamedNode(node) => node.iri.to_string(),
+        Subject::BlankNode(node) => format!("_:{}", node.id),
+        Subject::Triple(_) => return Ok(()),
+    };
+
+    let mut metadata = Vec::new();
+    let object = match object {
+        Term::NamedNode(node) => node.iri.to_string(),
+        Term::BlankNode(node) => format!("_:{}", node.id),
+        Term::Literal(Literal::Simple { value }) => value.to_string(),
+        Term::Literal(Literal::LanguageTaggedString { value, language }) => {
+            metadata.push(("lang", language.to_string()));
+            value.to_string()
+        }
+        Term::Literal(Literal::Typed { value, datatype }) => {
+            if datatype.iri != "http://www.w3.org/2001/XMLSchema#string" {
+                metadata.push(("datatype", datatype.iri.to_string()));
+            }
+            value.to_string()
+        }
+        Term::Triple(_) => return Ok(()),
+    };
+
+    let mut triple = RdfTriple::new(subject, predicate.to_string(), object);
+    triple.graph = match graph {
+        Some(GraphName::NamedNode(node)) => Some(node.iri.to_string()),
+        Some(GraphName::BlankNode(node)) => Some(format!("_:{}", node.id)),
+        None => None,
+    };
+    triple.metadata.extend(metadata.into_iter().map(|(k, v)| (k.to_string(), v)));
+    triples.push(triple);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_turtle_and_trig() {
+        let turtle = r#"
+            @prefix ex: <http://ex.org/> .
+            ex:acme ex:hasName "Acme"@en ; ex:founded "1999"^^<http://www.w3.org/2001/XMLSchema#gYear> ; ex:partner _:b .
+        "#;
+        let triples = parse_rdf(turtle, &OutputFormat::Turtle).unwrap();
+        assert_eq!(triples.len(), 3);
+        assert_eq!(triples[0].object, "Acme");
+        assert_eq!(triples[0].metadata["lang"], "en");
+        assert!(triples[1].metadata["datatype"].ends_with("gYear"));
+        assert_eq!(triples[2].object, "_:b");
+
+        let trig = "<http://ex.org/g> { <http://ex.org/a> <http://ex.org/p> <http://ex.org/b> . }";
+        let quads = parse_rdf(trig, &OutputFormat::TriG).unwrap();
+        assert_eq!(quads[0].graph.as_deref(), Some("http://ex.org/g"));
+
+        assert!(parse_rdf("<http://ex.org/a> <http://ex.org/p> .", &OutputFormat::NTriples).is_err());
+    }
+}
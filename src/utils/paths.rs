@@ -0,0 +1,174 @@
+use percent_encoding::percent_decode_str;
+use std::path::{Path, PathBuf};
+
+/// Whether `source` is a URL other than a `file://` URI
+pub fn is_remote(source: &str) -> bool {
+    source.split_once("://").is_some_and(|(scheme, _)| {
+        !scheme.eq_ignore_ascii_case("file") && is_scheme(scheme)
+    })
+}
+
+/// The form a document source is recorded in, so the same file gets the
+/// same provenance whichever platform or notation named it: `file://` URIs
+/// become paths, and on Windows paths use `/` as separator and lose the
+/// verbatim prefix (`\\?\`). Other URLs are left alone.
+pub fn normalize_source(source: &str) -> String {
+    let source = source.trim();
+    if is_remote(source) {
+        return source.to_string();
+    }
+
+    let path = match file_uri_path(source) {
+        Some(path) => path,
+        None => windows_separators(source),
+    };
+    if !cfg!(windows) {
+        return path;
+    }
+
+    if let Some(unc) = path.strip_prefix("//?/UNC/") {
+        format!("//{}", unc)
+    } else if let Some(verbatim) = path.strip_prefix("//?/") {
+        verbatim.to_string()
+    } else {
+        path
+    }
+}
+
+/// Local path of a source given as a path or a `file://` URI
+pub fn local_path(source: &str) -> PathBuf {
+    PathBuf::from(normalize_source(source))
+}
+
+/// Lower-cased extension of a source's file name, ignoring any URL query or fragment
+pub fn source_extension(source: &str) -> Option<String> {
+    let path = if is_remote(source) {
+        source.split(['?', '#']).next().unwrap_or(source)
+    } else {
+        source
+    };
+    let name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+    Path::new(name).extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+}
+
+/// `file://` IRI of a local file, absolute where the file exists
+pub fn file_uri(source: &str) -> String {
+    let path = local_path(source);
+    let path = std::fs::canonicalize(&path).unwrap_or(path);
+    let path = normalize_source(&path.to_string_lossy());
+
+    let encoded: String = path.split('/')
+        .map(|segment| percent_encoding::utf8_percent_encode(segment, PATH_SEGMENT).to_string())
+        .collect::<Vec<_>>()
+        .join("/");
+    if let Some(unc) = encoded.strip_prefix("//") {
+        format!("file://{}", unc)
+    } else if encoded.starts_with('/') {
+        format!("file://{}", encoded)
+    } else {
+        format!("file:///{}", encoded)
+    }
+}
+
+/// Characters escaped in a path segment of a `file://` IRI
+const PATH_SEGMENT: &percent_encoding::AsciiSet = &percent_encoding::CONTROLS
+    .add(b' ').add(b'"').add(b'#').add(b'%').add(b'<').add(b'>').add(b'?')
+    .add(b'`').add(b'{').add(b'}').add(b'|').add(b'\\').add(b'^').add(b'[').add(b']');
+
+/// Path of a `file://` URI, decoded; a host other than `localhost` becomes
+/// a UNC path (`//host/share/...`) and `/C:/...` a drive path
+fn file_uri_path(source: &str) -> Option<String> {
+    let (scheme, rest) = source.split_once("://")?;
+    if !scheme.eq_ignore_ascii_case("file") {
+        return None;
+    }
+
+    let (host, path) = match rest.find('/') {
+        Some(index) => rest.split_at(index),
+        None => (rest, ""),
+    };
+    let path = path.split(['?', '#']).next().unwrap_or(path);
+    let path = windows_separators(&percent_decode_str(path).decode_utf8_lossy());
+
+    let is_drive = |p: &str| {
+        let bytes = p.as_bytes();
+        bytes.len() >= 3 && bytes[0] == b'/' && bytes[1].is_ascii_alphabetic() && matches!(bytes[2], b':' | b'|')
+    };
+    Some(if !host.is_empty() && !host.eq_ignore_ascii_case("localhost") {
+        format!("//{}{}", host, path)
+    } else if is_drive(&path) {
+        format!("{}:{}", &path[1..2], &path[3..])
+    } else {
+        path
+    })
+}
+
+/// `\` separators as `/` on Windows; elsewhere `\` is part of a file name
+fn windows_separators(path: &str) -> String {
+    if cfg!(windows) {
+        path.replace('\\', "/")
+    } else {
+        path.to_string()
+    }
+}
+
+fn is_scheme(scheme: &str) -> bool {
+    // A single letter is a Windows drive, as in C://data
+    scheme.len() > 1
+        && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_source() {
+        if cfg!(windows) {
+            assert_eq!(normalize_source("docs\\reports\\q1.pdf"), "docs/reports/q1.pdf");
+            assert_eq!(normalize_source("C:\\Users\\ana\\q1.pdf"), "C:/Users/ana/q1.pdf");
+            assert_eq!(normalize_source("\\\\?\\C:\\Users\\ana\\q1.pdf"), "C:/Users/ana/q1.pdf");
+            assert_eq!(normalize_source("\\\\?\\UNC\\server\\share\\q1.pdf"), "//server/share/q1.pdf");
+        } else {
+            // A backslash is part of a POSIX file name
+            assert_eq!(normalize_source("docs/q1\\draft.pdf"), "docs/q1\\draft.pdf");
+            assert_eq!(normalize_source("file:///home/ana/q1%5Cdraft.pdf"), "/home/ana/q1\\draft.pdf");
+        }
+        assert_eq!(normalize_source("file:///home/ana/Q1%20report.pdf"), "/home/ana/Q1 report.pdf");
+        assert_eq!(normalize_source("file://localhost/home/ana/q1.pdf"), "/home/ana/q1.pdf");
+        assert_eq!(normalize_source("FILE:///C:/Users/ana/q1.pdf"), "C:/Users/ana/q1.pdf");
+        assert_eq!(normalize_source("file://server/share/q1.pdf"), "//server/share/q1.pdf");
+        assert_eq!(normalize_source("https://example.com/a\\b?x=1"), "https://example.com/a\\b?x=1");
+        assert_eq!(normalize_source("s3://bucket/q1.pdf"), "s3://bucket/q1.pdf");
+
+        assert_eq!(source_extension("C:\\Docs\\Q1.PDF").as_deref(), Some("pdf"));
+        assert_eq!(source_extension("https://example.com/q1.pdf?download=1").as_deref(), Some("pdf"));
+        assert_eq!(source_extension("https://example.com/v1.2/page"), None);
+        assert!(is_remote("https://example.com") && !is_remote("file:///a.txt") && !is_remote("C://a.txt"));
+    }
+
+    #[test]
+    fn test_file_uri() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Q1 report.txt");
+        std::fs::write(&path, "text").unwrap();
+
+        let uri = file_uri(&path.to_string_lossy());
+        assert!(uri.starts_with("file:///") && uri.ends_with("/Q1%20report.txt"), "{}", uri);
+        // The URI names the same file as the path and round-trips
+        assert_eq!(file_uri(&uri), uri);
+        assert_eq!(std::fs::read_to_string(local_path(&uri)).unwrap(), "text");
+
+        if !cfg!(windows) {
+            let path = dir.path().join("q1\\draft.txt");
+            std::fs::write(&path, "draft").unwrap();
+            let uri = file_uri(&path.to_string_lossy());
+            assert!(uri.ends_with("/q1%5Cdraft.txt"), "{}", uri);
+            assert_eq!(std::fs::read_to_string(local_path(&uri)).unwrap(), "draft");
+            assert_eq!(std::fs::read_to_string(local_path(&path.to_string_lossy())).unwrap(), "draft");
+        }
+    }
+}
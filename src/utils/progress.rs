@@ -0,0 +1,196 @@
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::Serialize;
+use std::io::Write;
+use std::time::Instant;
+
+/// How a long-running command reports its progress
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProgressMode {
+    /// A progress bar on the terminal, hidden when stderr is not one
+    #[default]
+    Bar,
+    /// Newline-delimited JSON events on stderr, for orchestration tools
+    Json,
+    None,
+}
+
+/// One line of `--progress json` output
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ProgressEvent {
+    Started {
+        task: String,
+        total: usize,
+    },
+    /// An item (document, generated file) finished
+    Item {
+        task: String,
+        /// 1-based position of the item in the run
+        index: usize,
+        total: usize,
+        item: String,
+        status: ItemStatus,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        triples: Option<usize>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+        elapsed_secs: f64,
+    },
+    Finished {
+        task: String,
+        total: usize,
+        completed: usize,
+        failed: usize,
+        elapsed_secs: f64,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemStatus {
+    Completed,
+    Failed,
+}
+
+/// Progress of a run over a known number of items, drawn as a bar or
+/// written as JSON events depending on the mode
+pub struct Progress {
+    task: String,
+    total: usize,
+    done: usize,
+    failed: usize,
+    started: Instant,
+    bar: Option<ProgressBar>,
+    events: Option<Box<dyn Write + Send>>,
+}
+
+impl Progress {
+    /// Start reporting `task` over `total` items
+    pub fn start(mode: ProgressMode, task: &str, total: usize) -> Self {
+        let (bar, events): (_, Option<Box<dyn Write + Send>>) = match mode {
+            ProgressMode::Bar => (Some(progress_bar(task, total)), None),
+            ProgressMode::Json => (None, Some(Box::new(std::io::stderr()))),
+            ProgressMode::None => (None, None),
+        };
+        Self::with_outputs(task, total, bar, events)
+    }
+
+    fn with_outputs(task: &str, total: usize, bar: Option<ProgressBar>, events: Option<Box<dyn Write + Send>>) -> Self {
+        let mut progress = Self {
+            task: task.to_string(),
+            total,
+            done: 0,
+            failed: 0,
+            started: Instant::now(),
+            bar,
+            events,
+        };
+        progress.emit(ProgressEvent::Started { task: task.to_string(), total });
+        progress
+    }
+
+    /// Record a finished item; `error` marks it as failed
+    pub fn item(&mut self, item: &str, triples: Option<usize>, error: Option<String>) {
+        self.done += 1;
+        let status = if error.is_some() {
+            self.failed += 1;
+            ItemStatus::Failed
+        } else {
+            ItemStatus::Completed
+        };
+
+        if let Some(bar) = &self.bar {
+            bar.set_message(item.to_string());
+            bar.inc(1);
+        }
+        self.emit(ProgressEvent::Item {
+            task: self.task.clone(),
+            index: self.done,
+            total: self.total,
+            item: item.to_string(),
+            status,
+            triples,
+            error,
+            elapsed_secs: self.started.elapsed().as_secs_f64(),
+        });
+    }
+
+    /// Print a line of regular output without garbling the bar
+    pub fn println(&self, line: &str) {
+        match &self.bar {
+            Some(bar) => bar.suspend(|| println!("{}", line)),
+            None => println!("{}", line),
+        }
+    }
+
+    pub fn finish(mut self) {
+        if let Some(bar) = self.bar.take() {
+            bar.finish_and_clear();
+        }
+        self.emit(ProgressEvent::Finished {
+            task: self.task.clone(),
+            total: self.total,
+            completed: self.done - self.failed,
+            failed: self.failed,
+            elapsed_secs: self.started.elapsed().as_secs_f64(),
+        });
+    }
+
+    fn emit(&mut self, event: ProgressEvent) {
+        if let Some(events) = &mut self.events {
+            // Progress is best effort: a closed pipe must not fail the run
+            if let Ok(line) = serde_json::to_string(&event) {
+                let _ = writeln!(events, "{}", line).and_then(|_| events.flush());
+            }
+        }
+    }
+}
+
+fn progress_bar(task: &str, total: usize) -> ProgressBar {
+    let bar = ProgressBar::new(total as u64);
+    let style = ProgressStyle::with_template("{prefix} [{bar:30.cyan/blue}] {pos}/{len} ({eta}) {wide_msg}")
+        .unwrap_or_else(|_| ProgressStyle::default_bar())
+        .progress_chars("=> ");
+    bar.set_style(style);
+    bar.set_prefix(task.to_string());
+    bar
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_progress_events() {
+        let buffer = Buffer::default();
+        let mut progress = Progress::with_outputs("extract", 2, None, Some(Box::new(buffer.clone())));
+        progress.item("a.txt", Some(3), None);
+        progress.item("b.txt", Some(0), Some("Timed out after 5.0s".into()));
+        progress.finish();
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let events: Vec<serde_json::Value> = output.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[0], serde_json::json!({"event": "started", "task": "extract", "total": 2}));
+        assert_eq!(events[1]["status"], "completed");
+        assert_eq!((events[1]["index"].as_u64(), events[1]["triples"].as_u64()), (Some(1), Some(3)));
+        assert!(events[1].get("error").is_none());
+        assert_eq!((events[2]["status"].as_str(), events[2]["error"].as_str()), (Some("failed"), Some("Timed out after 5.0s")));
+        assert_eq!((events[3]["event"].as_str(), events[3]["completed"].as_u64(), events[3]["failed"].as_u64()),
+            (Some("finished"), Some(1), Some(1)));
+    }
+}
@@ -1,14 +1,49 @@
-use anyhow::{Result, Context};
-use std::collections::HashMap;
+use crate::error::{ErrorContext, Result};
+use std::collections::{BTreeMap, HashMap};
 
 use crate::config::OutputFormat;
-use crate::core::RdfTriple;
+use crate::core::{ExtractionResult, RdfTriple};
+use crate::knowledge_graph::namespaces::RESERVED_PREFIXES;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+pub struct RdfSerializer {
+    /// Prefixes declared and used to abbreviate IRIs besides the schema's
+    namespaces: BTreeMap<String, String>,
+}
 
-pub struct RdfSerializer;
+impl Default for RdfSerializer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl RdfSerializer {
     pub fn new() -> Self {
-        Self
+        Self { namespaces: BTreeMap::new() }
+    }
+
+    /// Also declare `namespaces` in Turtle, TriG, JSON-LD and RDF/XML output
+    /// and abbreviate IRIs with them; the schema's prefix and the reserved
+    /// `rdf`/`rdfs` prefixes keep their namespaces
+    pub fn with_namespaces(mut self, namespaces: BTreeMap<String, String>) -> Self {
+        self.namespaces = namespaces;
+        self
+    }
+
+    /// Prefixes declared for output with the schema's `namespace` and
+    /// `prefix`: the schema's, `rdf`, `rdfs`, then the others by prefix
+    pub fn prefixes(&self, namespace: &str, prefix: &str) -> Vec<(String, String)> {
+        let mut prefixes = vec![(prefix.to_string(), namespace.to_string())];
+        let others = RESERVED_PREFIXES.iter()
+            .map(|(p, ns)| (p.to_string(), ns.to_string()))
+            .chain(self.namespaces.iter().map(|(p, ns)| (p.clone(), ns.clone())));
+        for (p, ns) in others {
+            if !prefixes.iter().any(|(declared, _)| *declared == p) {
+                prefixes.push((p, ns));
+            }
+        }
+        prefixes
     }
 
     pub fn serialize(
@@ -18,160 +53,489 @@ impl RdfSerializer {
         namespace: &str,
         prefix: &str,
     ) -> Result<String> {
+        let mut output = Vec::new();
+        self.serialize_to_writer(triples, format, namespace, prefix, &mut output)?;
+        String::from_utf8(output).storage_context(|| "Serialized output is not valid UTF-8")
+    }
+
+    /// Serialize `triples` into `writer` as they are formatted, without
+    /// building the whole output in memory. N-Triples, N-Quads, Turtle and
+    /// JSON are written triple by triple; the grouped formats (TriG, JSON-LD,
+    /// RDF/XML, Cypher, GraphML) keep only their grouping in memory.
+    pub fn serialize_to_writer(
+        &mut self,
+        triples: &[RdfTriple],
+        format: &OutputFormat,
+        namespace: &str,
+        prefix: &str,
+        writer: &mut impl Write,
+    ) -> Result<()> {
+        let out: &mut dyn Write = writer;
         match format {
-            OutputFormat::Turtle => self.serialize_turtle(triples, namespace, prefix),
-            OutputFormat::JsonLd => self.serialize_json_ld(triples, namespace, prefix),
-            OutputFormat::NTriples => self.serialize_ntriples(triples),
-            OutputFormat::RdfXml => self.serialize_rdf_xml(triples, namespace, prefix),
-            OutputFormat::Json => self.serialize_json(triples),
+            OutputFormat::Turtle => self.serialize_turtle(triples, namespace, prefix, out),
+            OutputFormat::JsonLd => self.serialize_json_ld(triples, namespace, prefix, out),
+            OutputFormat::NTriples => self.serialize_ntriples(triples, out),
+            OutputFormat::RdfXml => self.serialize_rdf_xml(triples, namespace, prefix, out),
+            OutputFormat::Json => self.serialize_json(triples, out),
+            OutputFormat::NQuads => self.serialize_nquads(triples, out),
+            OutputFormat::TriG => self.serialize_trig(triples, namespace, prefix, out),
+            OutputFormat::Cypher => self.serialize_cypher(triples, out),
+            OutputFormat::GraphMl => self.serialize_graphml(triples, out),
         }
+        .and_then(|()| out.flush())
+        .storage_context(|| format!("Failed to write {:?} output", format))
     }
 
-    fn serialize_turtle(&self, triples: &[RdfTriple], namespace: &str, prefix: &str) -> Result<String> {
-        let mut output = String::new();
+    /// Serialize each result to `output_path`; when there are several results
+    /// and `split` is set, files are suffixed `_1`, `_2`, ...
+    pub fn write_results(
+        &mut self,
+        results: &[ExtractionResult],
+        output_path: &Path,
+        format: &OutputFormat,
+        namespace: &str,
+        prefix: &str,
+        split: bool,
+    ) -> Result<Vec<PathBuf>> {
+        let mut written = Vec::new();
+
+        for (i, result) in results.iter().enumerate() {
+            let final_path = if results.len() > 1 && split {
+                let stem = output_path.file_stem().and_then(|s| s.to_str()).unwrap_or("output");
+                let extension = output_path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+                output_path.with_file_name(format!("{}_{}.{}", stem, i + 1, extension))
+            } else {
+                output_path.to_path_buf()
+            };
+
+            let file = std::fs::File::create(&final_path)
+                .storage_context(|| format!("Failed to write export: {}", final_path.display()))?;
+            self.serialize_to_writer(&result.triples, format, namespace, prefix, &mut BufWriter::new(file))?;
+            written.push(final_path);
+        }
+
+        Ok(written)
+    }
 
-        // Add prefix declarations
-        output.push_str(&format!("@prefix {}: <{}> .\n", prefix, namespace));
-        output.push_str("@prefix rdf: <http://www.w3.org/1999/02/22-rdf-syntax-ns#> .\n");
-        output.push_str("@prefix rdfs: <http://www.w3.org/2000/01/rdf-schema#> .\n\n");
+    fn serialize_turtle(&self, triples: &[RdfTriple], namespace: &str, prefix: &str, out: &mut dyn Write) -> io::Result<()> {
+        let prefixes = self.prefixes(namespace, prefix);
+        out.write_all(turtle_prefixes(&prefixes).as_bytes())?;
 
-        // Add triples
         for triple in triples {
-            let subject = self.format_uri_for_turtle(&triple.subject, namespace, prefix);
-            let predicate = self.format_uri_for_turtle(&triple.predicate, namespace, prefix);
-            let object = self.format_object_for_turtle(&triple.object);
+            let subject = self.format_uri_for_turtle(&triple.subject, &prefixes);
+            let predicate = self.format_uri_for_turtle(&triple.predicate, &prefixes);
+            let object = ntriples_object(triple);
 
-            output.push_str(&format!("{} {} {} .\n", subject, predicate, object));
+            writeln!(out, "{} {} {} .", subject, predicate, object)?;
         }
 
-        Ok(output)
+        Ok(())
     }
 
-    fn serialize_json_ld(&self, triples: &[RdfTriple], namespace: &str, prefix: &str) -> Result<String> {
+    fn serialize_json_ld(&self, triples: &[RdfTriple], namespace: &str, prefix: &str, out: &mut dyn Write) -> io::Result<()> {
+        let prefixes = self.prefixes(namespace, prefix);
         let mut context = serde_json::Map::new();
-        context.insert(prefix.to_string(), serde_json::Value::String(namespace.to_string()));
+        for (prefix, namespace) in &prefixes {
+            context.insert(prefix.clone(), serde_json::Value::String(namespace.clone()));
+        }
 
-        let mut graph = Vec::new();
-        let mut subjects: HashMap<String, serde_json::Map<String, serde_json::Value>> = HashMap::new();
+        // One node per subject, in order of first appearance
+        let mut nodes: Vec<serde_json::Map<String, serde_json::Value>> = Vec::new();
+        let mut positions: HashMap<&str, usize> = HashMap::new();
 
         for triple in triples {
-            let subject_entry = subjects.entry(triple.subject.clone()).or_insert_with(|| {
+            let position = *positions.entry(&triple.subject).or_insert_with(|| {
                 let mut map = serde_json::Map::new();
                 map.insert("@id".to_string(), serde_json::Value::String(triple.subject.clone()));
-                map
+                nodes.push(map);
+                nodes.len() - 1
             });
 
-            let predicate_key = if triple.predicate.starts_with(namespace) {
-                format!("{}:{}", prefix, &triple.predicate[namespace.len()..])
-            } else {
-                triple.predicate.clone()
-            };
+            let predicate_key = compact_iri(&triple.predicate, &prefixes).unwrap_or_else(|| triple.predicate.clone());
 
-            let object_value = if triple.object.starts_with("http://") || triple.object.starts_with("https://") {
+            let object_value = if is_resource(&triple.object) {
                 serde_json::json!({"@id": triple.object})
+            } else if let Some(lang) = triple.metadata.get("lang") {
+                serde_json::json!({"@value": triple.object, "@language": lang})
+            } else if let Some(datatype) = triple.metadata.get("datatype") {
+                serde_json::json!({"@value": triple.object, "@type": datatype})
             } else {
                 serde_json::Value::String(triple.object.clone())
             };
 
-            subject_entry.insert(predicate_key, object_value);
+            // Further values of a predicate turn its entry into an array
+            match nodes[position].get_mut(&predicate_key) {
+                Some(serde_json::Value::Array(values)) => values.push(object_value),
+                Some(existing) => *existing = serde_json::Value::Array(vec![existing.take(), object_value]),
+                None => {
+                    nodes[position].insert(predicate_key, object_value);
+                }
+            }
         }
 
-        for (_, subject_data) in subjects {
-            graph.push(serde_json::Value::Object(subject_data));
+        // The layout of `serde_json::to_string_pretty`, one node at a time
+        let context = serde_json::to_string_pretty(&context)?;
+        write!(out, "{{\n  \"@context\": {},\n  \"@graph\": [", indent_json(&context, "  "))?;
+        for (i, node) in nodes.iter().enumerate() {
+            let node = serde_json::to_string_pretty(node)?;
+            write!(out, "{}\n    {}", if i == 0 { "" } else { "," }, indent_json(&node, "    "))?;
         }
+        out.write_all(if nodes.is_empty() { b"]\n}" } else { b"\n  ]\n}" })
+    }
 
-        let json_ld = serde_json::json!({
-            "@context": context,
-            "@graph": graph
-        });
+    fn serialize_ntriples(&self, triples: &[RdfTriple], out: &mut dyn Write) -> io::Result<()> {
+        for triple in triples {
+            writeln!(out, "{} <{}> {} .", ntriples_resource(&triple.subject), triple.predicate, ntriples_object(triple))?;
+        }
+
+        Ok(())
+    }
+
+    fn serialize_nquads(&self, triples: &[RdfTriple], out: &mut dyn Write) -> io::Result<()> {
+        for triple in triples {
+            let subject = ntriples_resource(&triple.subject);
+            let object = ntriples_object(triple);
+            match &triple.graph {
+                Some(graph) => writeln!(out, "{} <{}> {} {} .", subject, triple.predicate, object, ntriples_resource(graph))?,
+                None => writeln!(out, "{} <{}> {} .", subject, triple.predicate, object)?,
+            }
+        }
 
-        serde_json::to_string_pretty(&json_ld)
-            .context("Failed to serialize JSON-LD")
+        Ok(())
     }
 
-    fn serialize_ntriples(&self, triples: &[RdfTriple]) -> Result<String> {
-        let mut output = String::new();
+    fn serialize_trig(&self, triples: &[RdfTriple], namespace: &str, prefix: &str, out: &mut dyn Write) -> io::Result<()> {
+        let prefixes = self.prefixes(namespace, prefix);
+        out.write_all(turtle_prefixes(&prefixes).as_bytes())?;
 
+        // Default graph first, then named graphs in the order they appear
+        let mut graphs: Vec<Option<&str>> = vec![None];
         for triple in triples {
-            let subject = format!("<{}>", triple.subject);
-            let predicate = format!("<{}>", triple.predicate);
-            let object = if triple.object.starts_with("http://") || triple.object.starts_with("https://") {
-                format!("<{}>", triple.object)
+            if !graphs.contains(&triple.graph.as_deref()) {
+                graphs.push(triple.graph.as_deref());
+            }
+        }
+
+        for graph in graphs {
+            let indent = if let Some(graph) = graph {
+                write!(out, "\n<{}> {{\n", graph)?;
+                "    "
             } else {
-                format!("\"{}\"", triple.object.replace("\"", "\\\""))
+                ""
             };
 
-            output.push_str(&format!("{} {} {} .\n", subject, predicate, object));
+            for triple in triples.iter().filter(|t| t.graph.as_deref() == graph) {
+                writeln!(out, "{}{} {} {} .",
+                    indent,
+                    self.format_uri_for_turtle(&triple.subject, &prefixes),
+                    self.format_uri_for_turtle(&triple.predicate, &prefixes),
+                    ntriples_object(triple)
+                )?;
+            }
+
+            if graph.is_some() {
+                out.write_all(b"}\n")?;
+            }
         }
 
-        Ok(output)
+        Ok(())
     }
 
-    fn serialize_rdf_xml(&self, triples: &[RdfTriple], namespace: &str, prefix: &str) -> Result<String> {
-        let mut output = String::new();
-
+    fn serialize_rdf_xml(&self, triples: &[RdfTriple], namespace: &str, prefix: &str, out: &mut dyn Write) -> io::Result<()> {
         // XML header and RDF root
-        output.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
-        output.push_str(&format!(
-            "<rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\" xmlns:{}=\"{}\">\n",
-            prefix, namespace
-        ));
+        out.write_all(b"<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n")?;
+        let prefixes = self.prefixes(namespace, prefix);
+        out.write_all(b"<rdf:RDF")?;
+        for (prefix, namespace) in &prefixes {
+            write!(out, " xmlns:{}=\"{}\"", prefix, html_escape::encode_double_quoted_attribute(namespace))?;
+        }
+        out.write_all(b">\n")?;
 
-        // Group triples by subject
-        let mut subjects: HashMap<String, Vec<&RdfTriple>> = HashMap::new();
+        // Group triples by subject, in order of first appearance
+        let mut subjects: Vec<(&str, Vec<&RdfTriple>)> = Vec::new();
+        let mut positions: HashMap<&str, usize> = HashMap::new();
         for triple in triples {
-            subjects.entry(triple.subject.clone()).or_default().push(triple);
+            let position = *positions.entry(&triple.subject).or_insert_with(|| {
+                subjects.push((&triple.subject, Vec::new()));
+                subjects.len() - 1
+            });
+            subjects[position].1.push(triple);
         }
 
         // Generate RDF/XML for each subject
         for (subject, subject_triples) in subjects {
-            output.push_str(&format!("  <rdf:Description rdf:about=\"{}\">\n", subject));
+            match subject.strip_prefix("_:") {
+                Some(id) => writeln!(out, "  <rdf:Description rdf:nodeID=\"{}\">", html_escape::encode_double_quoted_attribute(id))?,
+                None => writeln!(out, "  <rdf:Description rdf:about=\"{}\">", html_escape::encode_double_quoted_attribute(subject))?,
+            }
 
             for triple in subject_triples {
-                let predicate_name = if triple.predicate.starts_with(namespace) {
-                    format!("{}:{}", prefix, &triple.predicate[namespace.len()..])
+                let predicate_name = compact_iri(&triple.predicate, &prefixes).unwrap_or_else(|| {
+                    triple.predicate.split('#').next_back().unwrap_or(&triple.predicate).to_string()
+                });
+
+                if let Some(id) = triple.object.strip_prefix("_:") {
+                    writeln!(out, "    <{} rdf:nodeID=\"{}\"/>", predicate_name, html_escape::encode_double_quoted_attribute(id))?;
+                } else if is_resource(&triple.object) {
+                    writeln!(out, "    <{} rdf:resource=\"{}\"/>", predicate_name, html_escape::encode_double_quoted_attribute(&triple.object))?;
                 } else {
-                    triple.predicate.split('#').last().unwrap_or(&triple.predicate).to_string()
-                };
-
-                if triple.object.starts_with("http://") || triple.object.starts_with("https://") {
-                    output.push_str(&format!("    <{} rdf:resource=\"{}\"/>\n", predicate_name, triple.object));
-                } else {
-                    output.push_str(&format!("    <{}>{}</{}>\n",
+                    let attribute = match (triple.metadata.get("lang"), triple.metadata.get("datatype")) {
+                        (Some(lang), _) => format!(" xml:lang=\"{}\"", html_escape::encode_double_quoted_attribute(lang)),
+                        (None, Some(datatype)) => format!(" rdf:datatype=\"{}\"", html_escape::encode_double_quoted_attribute(datatype)),
+                        (None, None) => String::new(),
+                    };
+                    writeln!(out, "    <{}{}>{}</{}>",
                         predicate_name,
+                        attribute,
                         html_escape::encode_text(&triple.object),
                         predicate_name
-                    ));
+                    )?;
                 }
             }
 
-            output.push_str("  </rdf:Description>\n");
+            out.write_all(b"  </rdf:Description>\n")?;
         }
 
-        output.push_str("</rdf:RDF>\n");
-
-        Ok(output)
+        out.write_all(b"</rdf:RDF>\n")
     }
 
-    fn serialize_json(&self, triples: &[RdfTriple]) -> Result<String> {
-        serde_json::to_string_pretty(triples)
-            .context("Failed to serialize to JSON")
+    /// Cypher statements that MERGE one `Resource` node per URI and one
+    /// relationship per predicate between URIs, for loading into Neo4j
+    fn serialize_cypher(&self, triples: &[RdfTriple], out: &mut dyn Write) -> io::Result<()> {
+        let graph = PropertyGraph::from_triples(triples);
+
+        out.write_all(b"CREATE CONSTRAINT resource_uri IF NOT EXISTS FOR (n:Resource) REQUIRE n.uri IS UNIQUE;\n\n")?;
+
+        for node in &graph.nodes {
+            write!(out, "MERGE (n:Resource {{uri: {}}})", cypher_string(&node.uri))?;
+            for label in &node.labels {
+                write!(out, " SET n:{}", cypher_identifier(label))?;
+            }
+            for (key, values) in &node.properties {
+                let value = match values.as_slice() {
+                    [value] => cypher_string(value),
+                    values => format!("[{}]", values.iter().map(|v| cypher_string(v)).collect::<Vec<_>>().join(", ")),
+                };
+                write!(out, " SET n.{} = {}", cypher_identifier(key), value)?;
+            }
+            out.write_all(b";\n")?;
+        }
+
+        out.write_all(b"\n")?;
+        for edge in &graph.edges {
+            writeln!(out,
+                "MATCH (a:Resource {{uri: {}}}), (b:Resource {{uri: {}}}) MERGE (a)-[r:{}]->(b) SET r.predicate = {}, r.confidence = {};",
+                cypher_string(&graph.nodes[edge.source].uri),
+                cypher_string(&graph.nodes[edge.target].uri),
+                cypher_identifier(&local_name(&edge.predicate)),
+                cypher_string(&edge.predicate),
+                edge.confidence
+            )?;
+        }
+
+        Ok(())
     }
 
-    fn format_uri_for_turtle(&self, uri: &str, namespace: &str, prefix: &str) -> String {
-        if uri.starts_with(namespace) {
-            format!("{}:{}", prefix, &uri[namespace.len()..])
-        } else {
-            format!("<{}>", uri)
+    /// GraphML document with node and edge attributes, for Gephi or yEd
+    fn serialize_graphml(&self, triples: &[RdfTriple], out: &mut dyn Write) -> io::Result<()> {
+        let graph = PropertyGraph::from_triples(triples);
+        let attr = |value: &str| html_escape::encode_double_quoted_attribute(value).into_owned();
+        let text = |value: &str| html_escape::encode_text(value).into_owned();
+
+        // One key per literal property, shared by all nodes
+        let mut property_keys: Vec<&str> = graph.nodes.iter()
+            .flat_map(|node| node.properties.keys().map(String::as_str))
+            .collect();
+        property_keys.sort_unstable();
+        property_keys.dedup();
+
+        out.write_all(b"<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n")?;
+        out.write_all(b"<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n")?;
+        out.write_all(b"  <key id=\"label\" for=\"node\" attr.name=\"label\" attr.type=\"string\"/>\n")?;
+        out.write_all(b"  <key id=\"uri\" for=\"node\" attr.name=\"uri\" attr.type=\"string\"/>\n")?;
+        out.write_all(b"  <key id=\"types\" for=\"node\" attr.name=\"types\" attr.type=\"string\"/>\n")?;
+        for (i, key) in property_keys.iter().enumerate() {
+            writeln!(out, "  <key id=\"p{}\" for=\"node\" attr.name=\"{}\" attr.type=\"string\"/>", i, attr(key))?;
+        }
+        out.write_all(b"  <key id=\"predicate\" for=\"edge\" attr.name=\"predicate\" attr.type=\"string\"/>\n")?;
+        out.write_all(b"  <key id=\"relation\" for=\"edge\" attr.name=\"label\" attr.type=\"string\"/>\n")?;
+        out.write_all(b"  <key id=\"confidence\" for=\"edge\" attr.name=\"confidence\" attr.type=\"double\"/>\n")?;
+        out.write_all(b"  <graph id=\"G\" edgedefault=\"directed\">\n")?;
+
+        for (i, node) in graph.nodes.iter().enumerate() {
+            writeln!(out, "    <node id=\"n{}\">", i)?;
+            writeln!(out, "      <data key=\"label\">{}</data>", text(&node.label()))?;
+            writeln!(out, "      <data key=\"uri\">{}</data>", text(&node.uri))?;
+            if !node.labels.is_empty() {
+                writeln!(out, "      <data key=\"types\">{}</data>", text(&node.labels.join(", ")))?;
+            }
+            for (key, values) in &node.properties {
+                let index = property_keys.binary_search(&key.as_str()).unwrap_or_default();
+                writeln!(out, "      <data key=\"p{}\">{}</data>", index, text(&values.join("; ")))?;
+            }
+            out.write_all(b"    </node>\n")?;
         }
+
+        for (i, edge) in graph.edges.iter().enumerate() {
+            writeln!(out, "    <edge id=\"e{}\" source=\"n{}\" target=\"n{}\">", i, edge.source, edge.target)?;
+            writeln!(out, "      <data key=\"predicate\">{}</data>", text(&edge.predicate))?;
+            writeln!(out, "      <data key=\"relation\">{}</data>", text(&local_name(&edge.predicate)))?;
+            writeln!(out, "      <data key=\"confidence\">{}</data>", edge.confidence)?;
+            out.write_all(b"    </edge>\n")?;
+        }
+
+        out.write_all(b"  </graph>\n</graphml>\n")
+    }
+
+    fn serialize_json(&self, triples: &[RdfTriple], out: &mut dyn Write) -> io::Result<()> {
+        serde_json::to_writer_pretty(out, triples)?;
+        Ok(())
     }
 
-    fn format_object_for_turtle(&self, object: &str) -> String {
-        if object.starts_with("http://") || object.starts_with("https://") {
-            format!("<{}>", object)
-        } else {
-            format!("\"{}\"", object.replace("\"", "\\\""))
+    fn format_uri_for_turtle(&self, uri: &str, prefixes: &[(String, String)]) -> String {
+        compact_iri(uri, prefixes).unwrap_or_else(|| ntriples_resource(uri))
+    }
+}
+
+/// Pretty-printed JSON re-indented to sit at `indent` inside a larger document
+fn indent_json(json: &str, indent: &str) -> String {
+    json.replace('\n', &format!("\n{}", indent))
+}
+
+/// `@prefix` lines for `prefixes`, followed by a blank line
+pub(crate) fn turtle_prefixes(prefixes: &[(String, String)]) -> String {
+    let mut output: String = prefixes.iter()
+        .map(|(prefix, namespace)| format!("@prefix {}: <{}> .\n", prefix, namespace))
+        .collect();
+    output.push('\n');
+    output
+}
+
+/// `prefix:local` with the longest namespace in `prefixes` that `uri` starts
+/// with, when the rest of `uri` can be written as a local name
+pub(crate) fn compact_iri(uri: &str, prefixes: &[(String, String)]) -> Option<String> {
+    prefixes.iter()
+        .filter(|(_, namespace)| !namespace.is_empty())
+        .filter_map(|(prefix, namespace)| Some((prefix, namespace.len(), uri.strip_prefix(namespace.as_str())?)))
+        .filter(|(_, _, local)| {
+            !local.starts_with(['-', '.']) && !local.ends_with('.')
+                && local.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'))
+        })
+        .max_by_key(|(_, length, _)| *length)
+        .map(|(prefix, _, local)| format!("{}:{}", prefix, local))
+}
+
+pub(crate) const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+
+/// Property-graph view of a set of triples: every subject or object URI is a
+/// node, `rdf:type` objects become node labels, literal objects become node
+/// properties and the remaining triples become relationships
+pub(crate) struct PropertyGraph {
+    pub(crate) nodes: Vec<PropertyNode>,
+    pub(crate) edges: Vec<PropertyEdge>,
+}
+
+pub(crate) struct PropertyNode {
+    pub(crate) uri: String,
+    pub(crate) labels: Vec<String>,
+    pub(crate) properties: BTreeMap<String, Vec<String>>,
+}
+
+pub(crate) struct PropertyEdge {
+    pub(crate) source: usize,
+    pub(crate) target: usize,
+    pub(crate) predicate: String,
+    pub(crate) confidence: f32,
+}
+
+impl PropertyGraph {
+    pub(crate) fn from_triples(triples: &[RdfTriple]) -> Self {
+        let mut graph = Self { nodes: Vec::new(), edges: Vec::new() };
+        let mut index: HashMap<String, usize> = HashMap::new();
+        let mut node = |graph: &mut Self, uri: &str| -> usize {
+            *index.entry(uri.to_string()).or_insert_with(|| {
+                graph.nodes.push(PropertyNode { uri: uri.to_string(), labels: Vec::new(), properties: BTreeMap::new() });
+                graph.nodes.len() - 1
+            })
+        };
+
+        for triple in triples {
+            let source = node(&mut graph, &triple.subject);
+            if triple.predicate == RDF_TYPE || triple.predicate == "rdf:type" {
+                let label = local_name(&triple.object);
+                if !graph.nodes[source].labels.contains(&label) {
+                    graph.nodes[source].labels.push(label);
+                }
+            } else if is_resource(&triple.object) {
+                let target = node(&mut graph, &triple.object);
+                graph.edges.push(PropertyEdge {
+                    source,
+                    target,
+                    predicate: triple.predicate.clone(),
+                    confidence: triple.confidence,
+                });
+            } else {
+                let values = graph.nodes[source].properties.entry(local_name(&triple.predicate)).or_default();
+                if !values.contains(&triple.object) {
+                    values.push(triple.object.clone());
+                }
+            }
         }
+
+        graph
+    }
+}
+
+impl PropertyNode {
+    /// Display name: a `label` or `name`-like property, else the local name
+    pub(crate) fn label(&self) -> String {
+        ["label", "prefLabel", "name", "hasName"].iter()
+            .find_map(|key| self.properties.get(*key).and_then(|values| values.first()))
+            .cloned()
+            .unwrap_or_else(|| local_name(&self.uri))
+    }
+}
+
+pub(crate) fn is_resource(value: &str) -> bool {
+    value.starts_with("http://") || value.starts_with("https://") || value.starts_with("_:")
+}
+
+/// Last segment of a URI after `#` or `/`
+pub(crate) fn local_name(uri: &str) -> String {
+    uri.trim_end_matches(['/', '#'])
+        .rsplit(['/', '#'])
+        .next()
+        .unwrap_or(uri)
+        .to_string()
+}
+
+fn cypher_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"))
+}
+
+/// Backtick-quoted identifier, safe for labels, relationship types and keys
+fn cypher_identifier(name: &str) -> String {
+    format!("`{}`", name.replace('`', "``"))
+}
+
+/// An IRI or blank node in N-Triples syntax
+pub(crate) fn ntriples_resource(value: &str) -> String {
+    if value.starts_with("_:") { value.to_string() } else { format!("<{}>", value) }
+}
+
+/// An object in N-Triples syntax, with the `lang` or `datatype` from the
+/// triple's metadata
+pub(crate) fn ntriples_object(triple: &RdfTriple) -> String {
+    let value = &triple.object;
+    if value.starts_with("http://") || value.starts_with("https://") || value.starts_with("_:") {
+        return ntriples_resource(value);
+    }
+
+    let escaped = value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n").replace('\r', "\\r");
+    match (triple.metadata.get("lang"), triple.metadata.get("datatype")) {
+        (Some(lang), _) => format!("\"{}\"@{}", escaped, lang),
+        (None, Some(datatype)) => format!("\"{}\"^^<{}>", escaped, datatype),
+        (None, None) => format!("\"{}\"", escaped),
     }
 }
 
@@ -228,6 +592,113 @@ mod tests {
         assert!(issues[0].contains("Invalid subject URI"));
     }
 
+    #[test]
+    fn test_serialize_named_graphs() {
+        let mut serializer = RdfSerializer::new();
+        let triples = vec![
+            RdfTriple::new(
+                "http://example.org/acme".to_string(),
+                "http://example.org/hasName".to_string(),
+                "Acme".to_string(),
+            ),
+            RdfTriple::new(
+                "http://example.org/acme".to_string(),
+                "http://example.org/basedIn".to_string(),
+                "http://example.org/berlin".to_string(),
+            ).with_graph("http://example.org/graphs/report".to_string()),
+        ];
+
+        let nquads = serializer.serialize(&triples, &OutputFormat::NQuads, "http://example.org/", "ex").unwrap();
+        assert_eq!(nquads.lines().nth(1), Some(
+            "<http://example.org/acme> <http://example.org/basedIn> <http://example.org/berlin> <http://example.org/graphs/report> ."
+        ));
+
+        let trig = serializer.serialize(&triples, &OutputFormat::TriG, "http://example.org/", "ex").unwrap();
+        assert!(trig.contains("ex:acme ex:hasName \"Acme\" .\n\n<http://example.org/graphs/report> {\n    ex:acme ex:basedIn <http://example.org/berlin> .\n}"));
+    }
+
+    #[test]
+    fn test_serialize_tagged_literals() {
+        let mut serializer = RdfSerializer::new();
+        let mut name = RdfTriple::new("http://example.org/acme".into(), "http://example.org/hasName".into(), "Acme".into());
+        name.metadata.insert("lang".into(), "en".into());
+        let mut alias = name.clone();
+        alias.object = "Akme".into();
+        alias.metadata.insert("lang".into(), "pl".into());
+        let mut founded = RdfTriple::new("http://example.org/acme".into(), "http://example.org/founded".into(), "1999".into());
+        founded.metadata.insert("datatype".into(), "http://www.w3.org/2001/XMLSchema#gYear".into());
+        let triples = [name, alias, founded];
+
+        let json_ld: serde_json::Value = serde_json::from_str(
+            &serializer.serialize(&triples, &OutputFormat::JsonLd, "http://example.org/", "ex").unwrap()
+        ).unwrap();
+        let node = &json_ld["@graph"][0];
+        assert_eq!(node["ex:hasName"], serde_json::json!([{"@value": "Acme", "@language": "en"}, {"@value": "Akme", "@language": "pl"}]));
+        assert_eq!(node["ex:founded"]["@type"], "http://www.w3.org/2001/XMLSchema#gYear");
+
+        let rdf_xml = serializer.serialize(&triples, &OutputFormat::RdfXml, "http://example.org/", "ex").unwrap();
+        assert!(rdf_xml.contains("<ex:hasName xml:lang=\"en\">Acme</ex:hasName>"));
+        assert!(rdf_xml.contains("<ex:founded rdf:datatype=\"http://www.w3.org/2001/XMLSchema#gYear\">1999</ex:founded>"));
+    }
+
+    #[test]
+    fn test_serialize_property_graphs() {
+        let mut serializer = RdfSerializer::new();
+        let triples = vec![
+            RdfTriple::new("http://example.org/acme".to_string(), RDF_TYPE.to_string(), "http://example.org/Company".to_string()),
+            RdfTriple::new("http://example.org/acme".to_string(), "http://example.org/hasName".to_string(), "Acme \"Ltd\"".to_string()),
+            RdfTriple::new("http://example.org/acme".to_string(), "http://example.org/basedIn".to_string(), "http://example.org/berlin".to_string()),
+        ];
+
+        let cypher = serializer.serialize(&triples, &OutputFormat::Cypher, "http://example.org/", "ex").unwrap();
+        assert!(cypher.contains("MERGE (n:Resource {uri: \"http://example.org/acme\"}) SET n:`Company` SET n.`hasName` = \"Acme \\\"Ltd\\\"\";"));
+        assert!(cypher.contains("MERGE (a)-[r:`basedIn`]->(b)"));
+
+        let graphml = serializer.serialize(&triples, &OutputFormat::GraphMl, "http://example.org/", "ex").unwrap();
+        assert_eq!(graphml.matches("<node ").count(), 2);
+        assert!(graphml.contains("<data key=\"label\">Acme \"Ltd\"</data>"));
+        assert!(graphml.contains("<edge id=\"e0\" source=\"n0\" target=\"n1\">"));
+    }
+
+    #[test]
+    fn test_serialize_to_writer() {
+        /// Accepts a few bytes, then fails like a full disk
+        struct FullDisk(usize);
+        impl Write for FullDisk {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                if self.0 < buf.len() {
+                    return Err(io::Error::other("no space left"));
+                }
+                self.0 -= buf.len();
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut serializer = RdfSerializer::new();
+        let triples = vec![
+            RdfTriple::new("http://example.org/acme".into(), "http://example.org/hasName".into(), "Acme".into()),
+            RdfTriple::new("http://example.org/acme".into(), "http://example.org/basedIn".into(), "http://example.org/berlin".into()),
+            RdfTriple::new("http://example.org/ana".into(), "http://example.org/worksFor".into(), "http://example.org/acme".into()),
+        ];
+
+        let mut ntriples = Vec::new();
+        serializer.serialize_to_writer(&triples, &OutputFormat::NTriples, "http://example.org/", "ex", &mut ntriples).unwrap();
+        assert_eq!(String::from_utf8(ntriples).unwrap().lines().count(), 3);
+
+        let json_ld = serializer.serialize(&triples, &OutputFormat::JsonLd, "http://example.org/", "ex").unwrap();
+        let json_ld: serde_json::Value = serde_json::from_str(&json_ld).unwrap();
+        assert_eq!(json_ld["@graph"][0]["ex:basedIn"]["@id"], "http://example.org/berlin");
+        assert_eq!(json_ld["@graph"][1]["@id"], "http://example.org/ana");
+        let empty = serializer.serialize(&[], &OutputFormat::JsonLd, "http://example.org/", "ex").unwrap();
+        assert!(serde_json::from_str::<serde_json::Value>(&empty).unwrap()["@graph"].as_array().unwrap().is_empty());
+
+        let error = serializer.serialize_to_writer(&triples, &OutputFormat::Turtle, "http://example.org/", "ex", &mut FullDisk(100));
+        assert!(error.unwrap_err().to_string().contains("Failed to write Turtle output"));
+    }
+
     #[test]
     fn test_serialize_json() {
         let mut serializer = RdfSerializer::new();
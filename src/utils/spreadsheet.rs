@@ -0,0 +1,303 @@
+use rust_xlsxwriter::{Format, Workbook, Worksheet};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::error::{ErrorContext, Result};
+use crate::knowledge_graph::{Binding, KnowledgeGraphStats, SimpleSparqlResults};
+use crate::utils::CalibrationReport;
+
+/// Excel limits sheet names to 31 characters
+const MAX_SHEET_NAME: usize = 31;
+
+/// An XLSX workbook with one sheet per query result or report, each with a
+/// bold, frozen header row and numbers stored as numbers
+pub struct Spreadsheet {
+    workbook: Workbook,
+    header: Format,
+    integer: Format,
+    decimal: Format,
+    percent: Format,
+}
+
+impl Default for Spreadsheet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Spreadsheet {
+    pub fn new() -> Self {
+        Self {
+            workbook: Workbook::new(),
+            header: Format::new().set_bold(),
+            integer: Format::new().set_num_format("#,##0"),
+            decimal: Format::new().set_num_format("0.00"),
+            percent: Format::new().set_num_format("0.0%"),
+        }
+    }
+
+    /// Add a sheet with a column per result variable; numeric and boolean
+    /// literals become numbers and booleans, everything else text
+    pub fn add_query_results(&mut self, name: &str, results: &SimpleSparqlResults) -> Result<()> {
+        let (header, integer, decimal) = (self.header.clone(), self.integer.clone(), self.decimal.clone());
+        let sheet = self.add_sheet(name)?;
+        match results {
+            SimpleSparqlResults::Solutions { variables, rows } => {
+                let columns = SimpleSparqlResults::columns(variables, rows);
+                write_header(sheet, &header, &columns.iter().map(String::as_str).collect::<Vec<_>>())?;
+                for (i, row) in rows.iter().enumerate() {
+                    for (col, column) in columns.iter().enumerate() {
+                        if let Some(binding) = row.get(column) {
+                            write_binding(sheet, i as u32 + 1, col as u16, binding, &integer, &decimal)?;
+                        }
+                    }
+                }
+            }
+            SimpleSparqlResults::Boolean(result) => {
+                write_header(sheet, &header, &["result"])?;
+                sheet.write_boolean(1, 0, *result).storage_context(|| "Failed to write query result")?;
+            }
+        }
+        sheet.autofit();
+        Ok(())
+    }
+
+    /// Add a "Statistics" sheet: the totals, then triples per predicate,
+    /// entities per class and triples per source (most frequent first) and
+    /// the confidence distribution
+    pub fn add_statistics(&mut self, stats: &KnowledgeGraphStats) -> Result<()> {
+        let (header, integer, decimal) = (self.header.clone(), self.integer.clone(), self.decimal.clone());
+        let sheet = self.add_sheet("Statistics")?;
+
+        write_header(sheet, &header, &["Metric", "Value"])?;
+        let totals = [
+            ("Total triples", stats.total_triples),
+            ("Unique subjects", stats.unique_subjects),
+            ("Unique predicates", stats.unique_predicates),
+            ("Unique objects", stats.unique_objects),
+        ];
+        let mut row = 1;
+        for (metric, value) in totals {
+            write_pair(sheet, row, metric, value as f64, &integer)?;
+            row += 1;
+        }
+        let confidence = &stats.confidence;
+        for (metric, value) in [("Min confidence", confidence.min), ("Mean confidence", confidence.mean), ("Max confidence", confidence.max)] {
+            if let Some(value) = value {
+                write_pair(sheet, row, metric, value as f64, &decimal)?;
+                row += 1;
+            }
+        }
+
+        for (title, counts) in [(["Predicate", "Triples"], &stats.predicates), (["Class", "Entities"], &stats.classes), (["Source", "Triples"], &stats.sources)] {
+            if counts.is_empty() {
+                continue;
+            }
+            row += 1;
+            write_row_header(sheet, row, &header, &title)?;
+            for (key, count) in by_count(counts) {
+                row += 1;
+                write_pair(sheet, row, key, count as f64, &integer)?;
+            }
+            row += 1;
+        }
+
+        if confidence.bins.iter().any(|count| *count > 0) {
+            row += 1;
+            write_row_header(sheet, row, &header, &["Confidence", "Triples"])?;
+            for (i, count) in confidence.bins.iter().enumerate() {
+                row += 1;
+                let range = format!("{:.1}-{:.1}", i as f32 / 10.0, (i + 1) as f32 / 10.0);
+                write_pair(sheet, row, &range, *count as f64, &integer)?;
+            }
+        }
+
+        sheet.autofit();
+        Ok(())
+    }
+
+    /// Add a "Calibration" sheet: the summary, then accuracy per confidence bin
+    pub fn add_calibration(&mut self, report: &CalibrationReport) -> Result<()> {
+        let (header, integer, decimal, percent) =
+            (self.header.clone(), self.integer.clone(), self.decimal.clone(), self.percent.clone());
+        let sheet = self.add_sheet("Calibration")?;
+
+        write_header(sheet, &header, &["Metric", "Value"])?;
+        sheet.write_string(1, 0, "Labels").storage_context(|| "Failed to write calibration report")?;
+        sheet.write_string(1, 1, &report.label_source).storage_context(|| "Failed to write calibration report")?;
+        write_pair(sheet, 2, "Triples", report.total as f64, &integer)?;
+        write_pair(sheet, 3, "Correct", report.correct as f64, &integer)?;
+        write_pair(sheet, 4, "Expected calibration error", report.expected_calibration_error, &decimal)?;
+        let mut row = 5;
+        if let Some(min_confidence) = report.recommended_min_confidence {
+            write_pair(sheet, row, "Recommended min confidence", min_confidence as f64, &decimal)?;
+            row += 1;
+        }
+
+        row += 1;
+        write_row_header(sheet, row, &header, &["Lower", "Upper", "Triples", "Correct", "Mean confidence", "Accuracy"])?;
+        for bin in &report.bins {
+            row += 1;
+            let cells = [
+                (bin.lower as f64, &decimal),
+                (bin.upper as f64, &decimal),
+                (bin.count as f64, &integer),
+                (bin.correct as f64, &integer),
+                (bin.mean_confidence, &decimal),
+                (bin.accuracy, &percent),
+            ];
+            for (col, (value, format)) in cells.into_iter().enumerate() {
+                sheet.write_number_with_format(row, col as u16, value, format)
+                    .storage_context(|| "Failed to write calibration report")?;
+            }
+        }
+
+        sheet.autofit();
+        Ok(())
+    }
+
+    pub fn to_bytes(mut self) -> Result<Vec<u8>> {
+        self.workbook.save_to_buffer().storage_context(|| "Failed to build workbook")
+    }
+
+    pub fn save(mut self, path: &Path) -> Result<()> {
+        self.workbook.save(path)
+            .storage_context(|| format!("Failed to write workbook: {}", path.display()))
+    }
+
+    fn add_sheet(&mut self, name: &str) -> Result<&mut Worksheet> {
+        let name = self.unique_sheet_name(name);
+        let sheet = self.workbook.add_worksheet();
+        sheet.set_name(&name).storage_context(|| format!("Invalid sheet name: {}", name))?;
+        Ok(sheet)
+    }
+
+    /// `name` without the characters Excel rejects, shortened and numbered
+    /// so it does not clash with an existing sheet
+    fn unique_sheet_name(&mut self, name: &str) -> String {
+        let cleaned: String = name.chars()
+            .map(|c| if "[]:*?/\\".contains(c) { '_' } else { c })
+            .collect();
+        let cleaned = cleaned.trim_matches('\'').trim();
+        let base = if cleaned.is_empty() { "Sheet" } else { cleaned };
+
+        let existing: Vec<String> = self.workbook.worksheets().iter().map(|sheet| sheet.name()).collect();
+        let taken = |candidate: &str| existing.iter().any(|name| name.eq_ignore_ascii_case(candidate));
+        let mut candidate: String = base.chars().take(MAX_SHEET_NAME).collect();
+        let mut suffix = 2;
+        while taken(&candidate) {
+            let number = format!(" ({})", suffix);
+            candidate = base.chars().take(MAX_SHEET_NAME - number.len()).collect::<String>() + &number;
+            suffix += 1;
+        }
+        candidate
+    }
+}
+
+fn write_header(sheet: &mut Worksheet, format: &Format, titles: &[&str]) -> Result<()> {
+    write_row_header(sheet, 0, format, titles)?;
+    sheet.set_freeze_panes(1, 0).storage_context(|| "Failed to freeze header row")?;
+    Ok(())
+}
+
+fn write_row_header(sheet: &mut Worksheet, row: u32, format: &Format, titles: &[&str]) -> Result<()> {
+    for (col, title) in titles.iter().enumerate() {
+        sheet.write_string_with_format(row, col as u16, *title, format)
+            .storage_context(|| "Failed to write header")?;
+    }
+    Ok(())
+}
+
+fn write_pair(sheet: &mut Worksheet, row: u32, label: &str, value: f64, format: &Format) -> Result<()> {
+    sheet.write_string(row, 0, label).storage_context(|| "Failed to write cell")?;
+    sheet.write_number_with_format(row, 1, value, format).storage_context(|| "Failed to write cell")?;
+    Ok(())
+}
+
+fn write_binding(sheet: &mut Worksheet, row: u32, col: u16, binding: &Binding, integer: &Format, decimal: &Format) -> Result<()> {
+    let value = binding.value();
+    let written = match binding.xsd_type() {
+        Some("integer" | "int" | "long" | "short" | "byte" | "nonNegativeInteger" | "positiveInteger"
+            | "negativeInteger" | "nonPositiveInteger" | "unsignedInt" | "unsignedLong" | "unsignedShort" | "unsignedByte") => {
+            value.parse::<f64>().ok().map(|n| sheet.write_number_with_format(row, col, n, integer))
+        }
+        Some("decimal" | "double" | "float") => {
+            value.parse::<f64>().ok().filter(|n| n.is_finite()).map(|n| sheet.write_number_with_format(row, col, n, decimal))
+        }
+        Some("boolean") => match value {
+            "true" | "1" => Some(sheet.write_boolean(row, col, true)),
+            "false" | "0" => Some(sheet.write_boolean(row, col, false)),
+            _ => None,
+        },
+        _ => None,
+    };
+    match written {
+        Some(result) => result.map(|_| ()),
+        None => sheet.write_string(row, col, value).map(|_| ()),
+    }
+    .storage_context(|| "Failed to write query result")
+}
+
+fn by_count(counts: &BTreeMap<String, usize>) -> Vec<(&str, usize)> {
+    let mut sorted: Vec<(&str, usize)> = counts.iter().map(|(key, count)| (key.as_str(), *count)).collect();
+    sorted.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    sorted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use calamine::{Data, Reader, Xlsx};
+    use std::collections::HashMap;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_spreadsheet_sheets() {
+        let rows = vec![
+            HashMap::from([
+                ("name".to_string(), Binding::literal("Acme")),
+                ("employees".to_string(), Binding::typed_literal("1200", "http://www.w3.org/2001/XMLSchema#integer")),
+            ]),
+            HashMap::from([("name".to_string(), Binding::iri("http://ex.org/Globex"))]),
+        ];
+        let results = SimpleSparqlResults::Solutions { variables: vec!["name".into(), "employees".into()], rows };
+        let stats = KnowledgeGraphStats {
+            total_triples: 3,
+            unique_subjects: 1,
+            unique_predicates: 2,
+            unique_objects: 3,
+            predicates: BTreeMap::from([("http://ex.org/a".to_string(), 1), ("http://ex.org/b".to_string(), 2)]),
+            classes: BTreeMap::new(),
+            sources: BTreeMap::new(),
+            confidence: Default::default(),
+        };
+        let report = CalibrationReport::new(&[], &[], 2, "validation");
+
+        let mut spreadsheet = Spreadsheet::new();
+        spreadsheet.add_query_results("companies: all", &results).unwrap();
+        spreadsheet.add_query_results("companies: all", &SimpleSparqlResults::Boolean(true)).unwrap();
+        spreadsheet.add_statistics(&stats).unwrap();
+        spreadsheet.add_calibration(&report).unwrap();
+        let bytes = spreadsheet.to_bytes().unwrap();
+
+        let mut workbook = Xlsx::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(workbook.sheet_names(), ["companies_ all", "companies_ all (2)", "Statistics", "Calibration"]);
+
+        let sheet = workbook.worksheet_range("companies_ all").unwrap();
+        assert_eq!(sheet.get_value((0, 1)), Some(&Data::String("employees".into())));
+        assert_eq!(sheet.get_value((1, 1)), Some(&Data::Float(1200.0)));
+        assert_eq!(sheet.get_value((2, 0)), Some(&Data::String("http://ex.org/Globex".into())));
+        let sheet = workbook.worksheet_range("companies_ all (2)").unwrap();
+        assert_eq!(sheet.get_value((1, 0)), Some(&Data::Bool(true)));
+
+        let sheet = workbook.worksheet_range("Statistics").unwrap();
+        assert_eq!(sheet.get_value((1, 1)), Some(&Data::Float(3.0)));
+        // Predicates follow the totals, most frequent first
+        assert_eq!(sheet.get_value((6, 0)), Some(&Data::String("Predicate".into())));
+        assert_eq!(sheet.get_value((7, 0)), Some(&Data::String("http://ex.org/b".into())));
+
+        let sheet = workbook.worksheet_range("Calibration").unwrap();
+        assert_eq!(sheet.get_value((1, 1)), Some(&Data::String("validation".into())));
+        assert_eq!(sheet.get_value((6, 5)), Some(&Data::String("Accuracy".into())));
+    }
+}
@@ -0,0 +1,306 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::core::RdfTriple;
+use crate::utils::serialization::{is_resource, local_name, PropertyGraph, PropertyNode, RDF_TYPE};
+
+/// Literal values longer than this are cut in node labels
+const MAX_VALUE_CHARS: usize = 40;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VisualFormat {
+    /// Graphviz DOT
+    Dot,
+    /// Mermaid flowchart
+    Mermaid,
+    /// Self-contained HTML page with a force-directed layout
+    Html,
+}
+
+impl VisualFormat {
+    /// Format implied by a file extension: `.dot`/`.gv`, `.mmd`/`.mermaid`
+    /// or `.html`/`.htm`
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "dot" | "gv" => Some(Self::Dot),
+            "mmd" | "mermaid" => Some(Self::Mermaid),
+            "html" | "htm" => Some(Self::Html),
+            _ => None,
+        }
+    }
+}
+
+/// Triples linking the resources within `hops` links of `entity`, in either
+/// direction, plus the types and literal properties of those resources.
+/// `rdf:type` links are not followed, so a shared class does not pull in all
+/// of its instances.
+pub fn neighborhood(triples: &[RdfTriple], entity: &str, hops: usize) -> Vec<RdfTriple> {
+    let is_type = |t: &RdfTriple| t.predicate == RDF_TYPE || t.predicate == "rdf:type";
+    let mut reached: HashSet<&str> = HashSet::from([entity]);
+    let mut frontier = reached.clone();
+
+    for _ in 0..hops {
+        let mut next = HashSet::new();
+        for triple in triples.iter().filter(|t| !is_type(t) && is_resource(&t.object)) {
+            for (from, to) in [(&triple.subject, &triple.object), (&triple.object, &triple.subject)] {
+                if frontier.contains(from.as_str()) && reached.insert(to.as_str()) {
+                    next.insert(to.as_str());
+                }
+            }
+        }
+        frontier = next;
+    }
+
+    triples.iter()
+        .filter(|t| {
+            reached.contains(t.subject.as_str())
+                && (reached.contains(t.object.as_str()) || is_type(t) || !is_resource(&t.object))
+        })
+        .cloned()
+        .collect()
+}
+
+pub fn render(triples: &[RdfTriple], format: VisualFormat, title: &str) -> String {
+    let graph = PropertyGraph::from_triples(triples);
+    match format {
+        VisualFormat::Dot => to_dot(&graph, title),
+        VisualFormat::Mermaid => to_mermaid(&graph),
+        VisualFormat::Html => to_html(&graph, title),
+    }
+}
+
+/// Label, `«Type»` line and one `key: value` line per literal property
+fn node_lines(node: &PropertyNode) -> Vec<String> {
+    let mut lines = vec![node.label()];
+    if !node.labels.is_empty() {
+        lines.push(format!("«{}»", node.labels.join(", ")));
+    }
+    for (key, values) in &node.properties {
+        let value = values.join("; ");
+        let value = match value.char_indices().nth(MAX_VALUE_CHARS) {
+            Some((end, _)) => format!("{}…", &value[..end]),
+            None => value,
+        };
+        lines.push(format!("{}: {}", key, value));
+    }
+    lines
+}
+
+fn to_dot(graph: &PropertyGraph, title: &str) -> String {
+    let escape = |value: &str| value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', " ");
+    let quote = |value: &str| format!("\"{}\"", escape(value));
+
+    let mut output = format!("digraph {} {{\n", quote(title));
+    output.push_str("  graph [rankdir=LR];\n");
+    output.push_str("  node [shape=box, style=\"rounded,filled\", fillcolor=\"#eef3fb\", fontname=\"Helvetica\", fontsize=10];\n");
+    output.push_str("  edge [fontname=\"Helvetica\", fontsize=9, color=\"#777777\"];\n");
+
+    for (i, node) in graph.nodes.iter().enumerate() {
+        let label = node_lines(node).iter().map(|line| escape(line)).collect::<Vec<_>>().join("\\n");
+        output.push_str(&format!("  n{} [label=\"{}\", tooltip={}];\n", i, label, quote(&node.uri)));
+    }
+    for edge in &graph.edges {
+        output.push_str(&format!(
+            "  n{} -> n{} [label={}, tooltip={}];\n",
+            edge.source,
+            edge.target,
+            quote(&local_name(&edge.predicate)),
+            quote(&edge.predicate)
+        ));
+    }
+
+    output.push_str("}\n");
+    output
+}
+
+fn to_mermaid(graph: &PropertyGraph) -> String {
+    // Mermaid reads labels as markdown/HTML; entity codes keep them literal
+    let text = |value: &str| {
+        value.replace('&', "#amp;").replace('"', "#quot;").replace('<', "#lt;").replace('>', "#gt;")
+    };
+
+    let mut output = String::from("flowchart LR\n");
+    for (i, node) in graph.nodes.iter().enumerate() {
+        let lines: Vec<String> = node_lines(node).iter().map(|line| text(line)).collect();
+        output.push_str(&format!("  n{}[\"{}\"]\n", i, lines.join("<br/>")));
+    }
+    for edge in &graph.edges {
+        output.push_str(&format!("  n{} -->|\"{}\"| n{}\n", edge.source, text(&local_name(&edge.predicate)), edge.target));
+    }
+    output
+}
+
+fn to_html(graph: &PropertyGraph, title: &str) -> String {
+    let nodes: Vec<serde_json::Value> = graph.nodes.iter()
+        .map(|node| {
+            let mut details = node_lines(node);
+            details.insert(1, node.uri.clone());
+            serde_json::json!({ "label": node.label(), "details": details.join("\n") })
+        })
+        .collect();
+    let edges: Vec<serde_json::Value> = graph.edges.iter()
+        .map(|edge| serde_json::json!({
+            "source": edge.source,
+            "target": edge.target,
+            "label": local_name(&edge.predicate),
+            "predicate": edge.predicate,
+        }))
+        .collect();
+
+    // "</" would end the script element early
+    let data = serde_json::json!({ "nodes": nodes, "edges": edges }).to_string().replace("</", "<\\/");
+    let title = html_escape::encode_text(title);
+
+    HTML_TEMPLATE
+        .replace("{{title}}", &title)
+        .replace("{{summary}}", &format!("{} nodes, {} links", graph.nodes.len(), graph.edges.len()))
+        .replace("{{data}}", &data)
+}
+
+const HTML_TEMPLATE: &str = r##"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{{title}}</title>
+<style>
+  body { margin: 0; font-family: Helvetica, Arial, sans-serif; }
+  #info { position: fixed; top: 8px; left: 8px; padding: 6px 10px; background: #fff; border: 1px solid #ccc; font-size: 12px; }
+  svg { display: block; width: 100vw; height: 100vh; }
+  .node circle { fill: #6b9bd1; stroke: #fff; stroke-width: 1.5px; cursor: grab; }
+  .node text { font-size: 11px; pointer-events: none; }
+  .edge line { stroke: #aaa; marker-end: url(#arrow); }
+  .edge text { font-size: 9px; fill: #777; }
+</style>
+</head>
+<body>
+<div id="info"><b>{{title}}</b>: {{summary}}. Drag nodes to move them, hover for details.</div>
+<svg id="graph">
+  <defs><marker id="arrow" viewBox="0 -4 10 8" refX="18" markerWidth="6" markerHeight="6" orient="auto"><path d="M0,-4L10,0L0,4" fill="#aaa"/></marker></defs>
+</svg>
+<script>
+const data = {{data}};
+const svg = document.getElementById("graph");
+const element = (tag, parent, attributes = {}) => {
+  const e = document.createElementNS("http://www.w3.org/2000/svg", tag);
+  for (const [key, value] of Object.entries(attributes)) e.setAttribute(key, value);
+  parent.appendChild(e);
+  return e;
+};
+
+const width = () => svg.clientWidth, height = () => svg.clientHeight;
+const nodes = data.nodes.map((node, i) => ({
+  ...node,
+  x: width() / 2 + Math.cos(i) * (50 + i), y: height() / 2 + Math.sin(i) * (50 + i), vx: 0, vy: 0,
+}));
+const edges = data.edges.map(edge => {
+  const g = element("g", svg, { class: "edge" });
+  const line = element("line", g);
+  const text = element("text", g, { "text-anchor": "middle" });
+  text.textContent = edge.label;
+  element("title", g).textContent = edge.predicate;
+  return { ...edge, line, text };
+});
+for (const node of nodes) {
+  node.g = element("g", svg, { class: "node" });
+  element("circle", node.g, { r: 8 });
+  element("text", node.g, { x: 11, y: 4 }).textContent = node.label;
+  element("title", node.g).textContent = node.details;
+}
+
+let alpha = 1, dragged = null;
+function tick() {
+  for (let i = 0; i < nodes.length; i++) {
+    for (let j = i + 1; j < nodes.length; j++) {
+      const a = nodes[i], b = nodes[j];
+      const dx = b.x - a.x, dy = b.y - a.y, d2 = Math.max(dx * dx + dy * dy, 1);
+      const f = 3000 * alpha / d2;
+      a.vx -= dx * f; a.vy -= dy * f; b.vx += dx * f; b.vy += dy * f;
+    }
+  }
+  for (const edge of edges) {
+    const a = nodes[edge.source], b = nodes[edge.target];
+    const dx = b.x - a.x, dy = b.y - a.y, d = Math.max(Math.hypot(dx, dy), 1);
+    const f = (d - 120) * 0.02 * alpha / d;
+    a.vx += dx * f; a.vy += dy * f; b.vx -= dx * f; b.vy -= dy * f;
+  }
+  for (const node of nodes) {
+    node.vx += (width() / 2 - node.x) * 0.005 * alpha;
+    node.vy += (height() / 2 - node.y) * 0.005 * alpha;
+    if (node !== dragged) { node.x += node.vx; node.y += node.vy; }
+    node.vx *= 0.6; node.vy *= 0.6;
+  }
+}
+
+function draw() {
+  for (const edge of edges) {
+    const a = nodes[edge.source], b = nodes[edge.target];
+    edge.line.setAttribute("x1", a.x); edge.line.setAttribute("y1", a.y);
+    edge.line.setAttribute("x2", b.x); edge.line.setAttribute("y2", b.y);
+    edge.text.setAttribute("x", (a.x + b.x) / 2); edge.text.setAttribute("y", (a.y + b.y) / 2 - 3);
+  }
+  for (const node of nodes) node.g.setAttribute("transform", `translate(${node.x},${node.y})`);
+}
+
+function frame() {
+  tick();
+  draw();
+  alpha *= 0.99;
+  if (alpha > 0.01 || dragged) requestAnimationFrame(frame);
+}
+
+svg.addEventListener("mousedown", event => {
+  const node = nodes.find(n => n.g.contains(event.target));
+  if (!node) return;
+  dragged = node;
+  if (alpha <= 0.01) { alpha = 0.3; requestAnimationFrame(frame); } else { alpha = Math.max(alpha, 0.3); }
+});
+svg.addEventListener("mousemove", event => {
+  if (!dragged) return;
+  const box = svg.getBoundingClientRect();
+  dragged.x = event.clientX - box.left;
+  dragged.y = event.clientY - box.top;
+});
+window.addEventListener("mouseup", () => { dragged = null; });
+requestAnimationFrame(frame);
+</script>
+</body>
+</html>
+"##;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn triple(s: &str, p: &str, o: &str) -> RdfTriple {
+        RdfTriple::new(s.to_string(), p.to_string(), o.to_string())
+    }
+
+    #[test]
+    fn test_neighborhood_and_render() {
+        let triples = vec![
+            triple("http://ex.org/acme", "http://ex.org/hasName", "Acme \"Inc\""),
+            triple("http://ex.org/acme", RDF_TYPE, "http://ex.org/Company"),
+            triple("http://ex.org/acme", "http://ex.org/basedIn", "http://ex.org/paris"),
+            triple("http://ex.org/paris", "http://ex.org/hasName", "Paris"),
+            triple("http://ex.org/paris", "http://ex.org/locatedIn", "http://ex.org/france"),
+            triple("http://ex.org/globex", RDF_TYPE, "http://ex.org/Company"),
+        ];
+
+        let near = neighborhood(&triples, "http://ex.org/acme", 1);
+        assert_eq!(near.len(), 4);
+        assert!(near.iter().all(|t| t.subject != "http://ex.org/globex" && t.object != "http://ex.org/france"));
+        assert_eq!(neighborhood(&triples, "http://ex.org/acme", 2).len(), 5);
+
+        let dot = render(&near, VisualFormat::Dot, "acme");
+        assert!(dot.contains(r#"n0 [label="Acme \"Inc\"\n«Company»\nhasName: Acme \"Inc\"", tooltip="http://ex.org/acme"];"#));
+        assert!(dot.contains(r#"n0 -> n1 [label="basedIn""#));
+
+        let mermaid = render(&near, VisualFormat::Mermaid, "acme");
+        assert!(mermaid.contains(r#"n0["Acme #quot;Inc#quot;<br/>«Company»"#));
+        assert!(mermaid.contains(r#"n0 -->|"basedIn"| n1"#));
+
+        let html = render(&[triple("http://ex.org/a", "http://ex.org/note", "</script>")], VisualFormat::Html, "<kg>");
+        assert!(html.contains("<title>&lt;kg&gt;</title>") && !html.contains("note: </script>"));
+        assert_eq!(VisualFormat::from_path(Path::new("graph.GV")), Some(VisualFormat::Dot));
+    }
+}